@@ -0,0 +1,70 @@
+mikino_api::prelude!();
+
+use mikino_api::{check, rsmt2::SmtConf};
+
+/// Number of runs for each number of jobs.
+const RUNS: usize = 5;
+
+/// Checks the demo system with some number of jobs, yields the outcome and its JSON.
+fn check(jobs: usize) -> Res<(String, String)> {
+    let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO)?;
+    let config = check::Config::new(SmtConf::default_z3())
+        .bmc_max(10)
+        .jobs(jobs);
+    let mut outcome = check::run(&sys, &config)?;
+    // The provenance records the number of jobs.
+    outcome.provenance = None;
+    Ok((outcome.to_string(), outcome.to_json()))
+}
+
+fn run() -> Res<()> {
+    println!("checking demo system with 1 job...");
+    let reference = check(1)?;
+    println!("{}", reference.0);
+
+    for jobs in &[1, 4] {
+        println!(
+            "checking demo system {} times with {} job(s)...",
+            RUNS, jobs
+        );
+        for run in 0..RUNS {
+            let (outcome, json) = check(*jobs)?;
+            if outcome != reference.0 {
+                bail!(
+                    "run {} with {} job(s) yields outcome\n{}\nexpected\n{}",
+                    run,
+                    jobs,
+                    outcome,
+                    reference.0,
+                )
+            }
+            if json != reference.1 {
+                bail!(
+                    "run {} with {} job(s) yields JSON\n{}\nexpected\n{}",
+                    run,
+                    jobs,
+                    json,
+                    reference.1,
+                )
+            }
+        }
+    }
+
+    println!("success");
+    Ok(())
+}
+
+fn main() {
+    match run() {
+        Ok(()) => (),
+        Err(e) => {
+            println!("Error:\n{}", e.pretty(()));
+            std::process::exit(2);
+        }
+    }
+}
+
+#[test]
+fn test_determinism() {
+    main()
+}
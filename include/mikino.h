@@ -0,0 +1,67 @@
+/* C interface of mikino, see the documentation of module `ffi` of `mikino_api`.
+ *
+ * Build the library with `cargo rustc --release --features ffi --crate-type cdylib`.
+ */
+
+#ifndef MIKINO_H
+#define MIKINO_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque handle to a transition system. */
+typedef struct MikinoSystem MikinoSystem;
+/* Opaque cancellation handle. */
+typedef struct MikinoCancel MikinoCancel;
+
+/* Version of mikino, static, must not be freed. */
+const char *mikino_version(void);
+/* Error message of the last call on this thread, NULL if it did not fail. */
+const char *mikino_last_error(void);
+/* Releases a string returned by mikino. */
+void mikino_string_free(char *s);
+
+/* Parses a transition system, NULL on failure. */
+MikinoSystem *mikino_parse_system(const char *txt);
+/* Releases a system. */
+void mikino_system_free(MikinoSystem *sys);
+
+/* Creates a cancellation handle. */
+MikinoCancel *mikino_cancel_new(void);
+/* Cancels the checks and scripts running with a handle, thread-safe. */
+void mikino_cancel(const MikinoCancel *cancel);
+/* Releases a cancellation handle. */
+void mikino_cancel_free(MikinoCancel *cancel);
+
+/* Checks the candidates of a system, JSON outcome or NULL on failure.
+ *
+ * `backend` (`z3`, `cvc5` or `yices2`) can be NULL (`z3`), `solver` can be NULL (default command
+ * of the backend), negative `bmc_max` deactivates BMC, `cancel` can be NULL. */
+char *mikino_check(
+    const MikinoSystem *sys,
+    const char *backend,
+    const char *solver,
+    int64_t bmc_max,
+    size_t jobs,
+    const MikinoCancel *cancel
+);
+/* Runs an hsmt script, JSON steps and outcome or NULL on failure.
+ *
+ * `backend` can be NULL (`z3`), `solver` can be NULL (default command of the backend), `cancel`
+ * can be NULL. */
+char *mikino_run_script(
+    const char *txt,
+    const char *backend,
+    const char *solver,
+    const MikinoCancel *cancel
+);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MIKINO_H */
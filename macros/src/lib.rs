@@ -0,0 +1,208 @@
+//! Compile-time embedding of hsmt scripts and systems for [`mikino_api`].
+//!
+//! The [`hsmt_system!`] and [`hsmt_script!`] macros take a string literal containing hsmt code.
+//! The code is parsed (and built, for scripts) *at compile time*: syntax and type errors are
+//! compilation errors, reported on the literal. The macros expand to the parsing of the literal at
+//! runtime, which cannot fail since the exact same text was already accepted at compile time.
+//!
+//! The expansions refer to `::mikino_api`, crates using these macros must depend on `mikino_api`.
+//! It should be the same version as the one this crate depends on, otherwise code accepted at
+//! compile time might be rejected at runtime.
+//!
+//! Note that hsmt code must be given as a string literal (raw or not): some of hsmt's operators,
+//! like `¬` or `≥`, are not legal Rust tokens.
+
+use proc_macro::{Delimiter, Literal, Span, TokenStream, TokenTree};
+
+/// Embeds a transition system, expands to a `mikino_api::trans::Sys`.
+///
+/// # Examples
+///
+/// ```rust
+/// let sys: mikino_api::trans::Sys = mikino_macros::hsmt_system!(
+///     r#"
+/// svars { cnt: int }
+/// init { cnt = 0 }
+/// trans { 'cnt = cnt + 1 }
+/// candidates { "cnt is positive": cnt ≥ 0 }
+///     "#
+/// );
+/// assert_eq!(sys.po_s().len(), 1);
+/// ```
+///
+/// Errors are reported at compile time.
+///
+/// ```rust,compile_fail
+/// let sys = mikino_macros::hsmt_system!(
+///     r#"
+/// svars { cnt: int }
+/// init { cnt = 0 }
+/// trans { 'cnt = cnt + 1 }
+/// candidates { "cnt is positive": count ≥ 0 }
+///     "#
+/// );
+/// ```
+#[proc_macro]
+pub fn hsmt_system(input: TokenStream) -> TokenStream {
+    embed(input, "system", "::mikino_api::parse::trans", |txt| {
+        mikino_api::parse::trans(txt).map(|_| ())
+    })
+}
+
+/// Embeds a script, expands to a built script command.
+///
+/// The result has type `mikino_api::ast::script::Command<Expr, MExpr>`, see
+/// `mikino_api::script::build::of_str`. It can be run with `mikino_api::script::Script::new`.
+///
+/// # Examples
+///
+/// ```rust
+/// let script = mikino_macros::hsmt_script!(
+///     "
+/// vars { n m: int }
+/// assert { n > 0 ∧ m = n + 1 }
+/// check_sat!()
+///     "
+/// );
+/// # let _ = script;
+/// ```
+///
+/// Errors are reported at compile time.
+///
+/// ```rust,compile_fail
+/// let script = mikino_macros::hsmt_script!("vars { n: int } assert { m > 0 }");
+/// ```
+#[proc_macro]
+pub fn hsmt_script(input: TokenStream) -> TokenStream {
+    embed(
+        input,
+        "script",
+        "::mikino_api::script::build::of_str",
+        |txt| mikino_api::script::build::of_str(txt).map(|_| ()),
+    )
+}
+
+/// Checks the input literal with `check`, and expands to `runtime(literal)`.
+fn embed(
+    input: TokenStream,
+    desc: &str,
+    runtime: &str,
+    check: impl FnOnce(&str) -> mikino_api::prelude::Res<()>,
+) -> TokenStream {
+    let (lit, txt) = match literal(input) {
+        Ok(res) => res,
+        Err((span, msg)) => return error(span, &msg),
+    };
+    if let Err(e) = check(&txt) {
+        let msg: Vec<String> = e.iter().map(|e| e.to_string()).collect();
+        return error(
+            lit.span(),
+            &format!("illegal hsmt {}: {}", desc, msg.join(", ")),
+        );
+    }
+
+    let mut res: TokenStream = format!(
+        "{}(LIT).expect(\"[bug] hsmt {} accepted at compile time\")",
+        runtime, desc
+    )
+    .parse()
+    .expect("[bug] illegal expansion");
+    res = res.into_iter().map(|tt| replace_lit(tt, &lit)).collect();
+    Some(TokenTree::Group(proc_macro::Group::new(
+        Delimiter::Brace,
+        res,
+    )))
+    .into_iter()
+    .collect()
+}
+
+/// Replaces identifier `LIT` by a literal.
+fn replace_lit(tt: TokenTree, lit: &Literal) -> TokenTree {
+    match tt {
+        TokenTree::Ident(ident) if ident.to_string() == "LIT" => TokenTree::Literal(lit.clone()),
+        TokenTree::Group(group) => {
+            let stream = group
+                .stream()
+                .into_iter()
+                .map(|tt| replace_lit(tt, lit))
+                .collect();
+            TokenTree::Group(proc_macro::Group::new(group.delimiter(), stream))
+        }
+        tt => tt,
+    }
+}
+
+/// A `compile_error!` invocation.
+fn error(span: Span, msg: &str) -> TokenStream {
+    let res: TokenStream = format!("compile_error!({:?});", msg)
+        .parse()
+        .expect("[bug] illegal `compile_error` invocation");
+    res.into_iter()
+        .map(|mut tt| {
+            tt.set_span(span);
+            tt
+        })
+        .collect()
+}
+
+/// Extracts the string literal of a macro's input, and its value.
+fn literal(input: TokenStream) -> Result<(Literal, String), (Span, String)> {
+    let mut tokens = input.into_iter();
+    let (tt, rest) = (tokens.next(), tokens.next());
+    let lit = match (tt, rest) {
+        (Some(TokenTree::Literal(lit)), None) => lit,
+        // Input coming from `macro_rules` expansions can be wrapped in invisible groups.
+        (Some(TokenTree::Group(group)), None) if group.delimiter() == Delimiter::None => {
+            return literal(group.stream())
+        }
+        (Some(tt), _) => return Err((tt.span(), "expected a string literal".into())),
+        (None, _) => return Err((Span::call_site(), "expected a string literal".into())),
+    };
+    match unquote(&lit.to_string()) {
+        Some(txt) => Ok((lit, txt)),
+        None => Err((lit.span(), "expected a string literal".into())),
+    }
+}
+
+/// Value of a string literal, `None` if the input is not a string literal.
+fn unquote(lit: &str) -> Option<String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = raw[hashes..].strip_prefix('"')?;
+        let inner = inner.strip_suffix(&"#".repeat(hashes) as &str)?;
+        return inner.strip_suffix('"').map(String::from);
+    }
+
+    let inner = lit.strip_prefix('"')?.strip_suffix('"')?;
+    let mut res = String::with_capacity(inner.len());
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => res.push('\n'),
+            'r' => res.push('\r'),
+            't' => res.push('\t'),
+            '0' => res.push('\0'),
+            c @ ('\\' | '"' | '\'') => res.push(c),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                res.push(u8::from_str_radix(&hex, 16).ok()? as char)
+            }
+            'u' => {
+                let hex: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                res.push(std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?)
+            }
+            // Line continuation, skips the newline and the leading whitespaces of the next line.
+            '\n' => {
+                while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+                    let _ = chars.next();
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(res)
+}
@@ -169,10 +169,22 @@ get_values! {
 	cnt * (next_cnt + 7),
 }
 
-/// Let's force `reset` to be false.
+/// Check sats can assume boolean variables, possibly negated, without asserting them. This
+/// explores a scenario without changing the assertions: here, not resetting nor counting.
+if check_sat!(assuming: [¬reset, ¬counting]) {
+	panic!("unreachable")
+} else {
+	echo!("when stuttering, `next_cnt` is `cnt` which is strictly positive")
+}
+
+/// Snapshots record the declarations and assertions so far, we can come back here later.
+snapshot!(may_reset)
+
+/// Let's force `reset` to be false. Assertions can be named, with an identifier or a
+/// double-quoted label, see `unsat_core` below.
 echo!("if we forbid `reset`ting, `next_cnt` should always be strictly positive")
 assert {
-	¬reset
+	no_reset: ¬reset
 }
 
 /// This time `next_cnt` can only be strictly positive.
@@ -180,8 +192,59 @@ if check_sat!() {
 	panic!("unreachable")
 } else {
 	echo!("indeed it is")
+	/// `unsat_core` shows the named assertions the solver used to prove the check sat unsat.
+	/// Unnamed assertions never appear in it.
+	unsat_core!()
+	/// `get_mus` shows a minimal subset of the assertions that are unsatisfiable together, here all
+	/// of them are needed.
+	get_mus!()
+}
+
+/// Restoring a snapshot forgets everything asserted since, `reset` is allowed again.
+restore!(may_reset)
+if check_sat!() {
+	echo!("back to a state where `next_cnt` can be zero")
+} else {
+	panic!("unreachable")
+}
+
+/// Push and pop scope assertions as in SMT-LIB: `pop` retracts everything since the matching
+/// `push`, without resetting the solver. `push!(n)` and `pop!(n)` handle `n` levels at once.
+push!()
+assert { counting }
+if check_sat!() {
+	echo!("`next_cnt` can be zero while counting, as long as we `reset`")
+} else {
+	panic!("unreachable")
+}
+pop!()
+
+/// Bounded loops are unrolled: `repeat n { ... }` runs its body `n` times, and the body of
+/// `for i in lo..hi { ... }` (or `lo..=hi`) runs with `i` an integer constant in assertions and
+/// evaluations.
+///
+/// Besides check sat results, `let` can also define expressions. Until the end of the current
+/// block, a definition is replaced by its expression in assertions and evaluations.
+let scale = 10;
+let scaled = scale * cnt;
+for i in 1..=2 {
+	push!()
+	assert { cnt = scale * i }
+	if check_sat!() {
+		eval!(cnt + next_cnt, scaled)
+	} else {
+		panic!("unreachable")
+	}
+	pop!()
 }
 
+/// `minimize!(expr)` and `maximize!(expr)` show the optimal value of an integer or (unsigned)
+/// bitvector expression under the current assertions. Z3 optimizes natively, other solvers get
+/// there with check sats. Except with CVC5, `get_model!()` then yields an optimal model.
+push!()
+assert { cnt ≤ 100 }
+maximize!(cnt)
+pop!()
 
 echo!()
 echo!("all done here")
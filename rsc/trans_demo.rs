@@ -39,6 +39,9 @@
 //! do so by having a PO called, for instance, `"cnt is positive"` defined as `cnt >= 0` or `cnt ≥
 //! 0`.
 //!
+//! The documentation of a candidate labels it: its first paragraph is a title, the other
+//! paragraphs a description. Labels show up in check outcomes and counterexample headers.
+//!
 //! # This Example
 //!
 //! This system is a stopwatch. It has a (time) counter `cnt`, which would be the time a real
@@ -47,6 +50,28 @@
 //! while `stop` freezes the value of `cnt` as long as it remains true. Note that `reset` has
 //! priority over `stop`: if both are true then `cnt` will be forced to `0`.
 //!
+//! # Display Hints
+//!
+//! The documentation of a state variable can specify how its values should be displayed in
+//! counterexamples, with lines of the form `@display: <formatter>` and `@unit: <unit>`. Builtin
+//! formatters are `hex`, `oct` and `bin` for integers, and `decimal` for rationals. For instance,
+//! `cnt` below specifies its unit is `s`.
+//!
+//! # Variable Roles
+//!
+//! Instead of `svars`, variables can be declared with a role in `inputs { ... }`,
+//! `outputs { ... }` and `state { ... }` blocks. Inputs are chosen freely by the environment at
+//! each step: random testing picks their values at random, and the determinism check lets them
+//! differ. Counterexamples group the values of each step by role. Below, `stop` and `reset` could
+//! be declared as inputs and `cnt` as an output.
+//!
+//! # Timed Systems
+//!
+//! A system declaring a state variable `time: rat` is *timed*, `time` is its clock. In the
+//! transition relation of a timed system, `delta` stands for the duration of the step, `'time -
+//! time`. For instance, `delta > 0.0` states that time advances at each step. Counterexamples of
+//! timed systems show the value of the clock and the duration of each step.
+//!
 //! # Notes on Operators and Literals
 //!
 //! Several operators can take more than one UTF8 or ASCII form.
@@ -68,15 +93,17 @@ svars {
     /// Reset button (input).
     reset: bool,
     /// Time counter (output).
+    /// @unit: s
     cnt: int,
 }
 
 /// Initial predicate.
 /// 
-/// Comma-separated list of stateless expressions, with optional trailing comma.
+/// Comma-separated list of stateless expressions, with optional trailing comma. Expressions can
+/// be labeled, labels show up in error messages and assumption coverage reports.
 init {
     // `cnt` can be anything as long as it is positive.
-    cnt ≥ 0,
+    "cnt starts positive": cnt ≥ 0,
     // if `reset`, then `cnt` has to be `0`.
     (reset ⇒ cnt = 0),
 }
@@ -101,6 +128,7 @@ trans {
 
 /// Proof obligations.
 candidates {
+    /// The stopwatch never displays a negative time.
     "cnt is positive": cnt ≥ 0,
     "cnt is not -7": ¬(cnt = -7),
     "if reset then cnt is 0": reset ⇒ cnt = 0,
@@ -2,6 +2,8 @@
 
 crate::prelude!();
 
+use std::convert::TryFrom;
+
 pub mod script;
 
 /// AST for the term structure.
@@ -22,9 +24,29 @@ pub enum Expr<'txt> {
         op: Spn<expr::Op>,
         /// Arguments.
         args: Vec<Expr<'txt>>,
-        /// True if the operator application is closed, not used ATM.
+        /// True if the operator application is closed, *i.e.* parenthesized or written in prefix
+        /// notation; closed applications of non-associative operators are not merged in their
+        /// parent application.
         closed: bool,
     },
+    /// Uninterpreted function application, resolved from the declarations.
+    Fun {
+        /// Spanned function identifier.
+        ident: Spn<&'txt str>,
+        /// Arguments.
+        args: Vec<Expr<'txt>>,
+    },
+    /// Quantified expression, only legal in scripts.
+    Quant {
+        /// Span of the quantifier keyword.
+        span: Span,
+        /// True for `forall`, false for `exists`.
+        forall: bool,
+        /// Bound variables and their types.
+        vars: Vec<(Spn<&'txt str>, expr::Typ)>,
+        /// Body of the quantifier.
+        body: Box<Expr<'txt>>,
+    },
 }
 
 impl<'txt> Expr<'txt> {
@@ -47,8 +69,42 @@ impl<'txt> Expr<'txt> {
             Self::Var { ident, .. } => ident.span,
             Self::Cst(c) => c.span,
             Self::App { op, .. } => op.span,
+            Self::Fun { ident, .. } => ident.span,
+            Self::Quant { span, .. } => *span,
         }
     }
+    /// Span of the whole expression, from its first token to its last one.
+    ///
+    /// Unlike [`Self::span`], which only covers the operator of an application, this is the span
+    /// underlined by errors about the expression itself. Closing parentheses and braces are not
+    /// included.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse::{rules::hsmt_expr, Span};
+    /// let expr = hsmt_expr("x + 'y * 2").unwrap();
+    /// assert_eq!(expr.span(), Span::new(2, 3));
+    /// assert_eq!(expr.full_span(), Span::new(0, 10));
+    /// ```
+    pub fn full_span(&self) -> Span {
+        let mut span = self.span();
+        let mut todo = vec![self];
+        while let Some(expr) = todo.pop() {
+            span = span.union(expr.span());
+            match expr {
+                Self::Cst(_) => (),
+                Self::Var { pon, .. } => {
+                    if let Some(pon) = pon {
+                        span = span.union(*pon)
+                    }
+                }
+                Self::App { args, .. } | Self::Fun { args, .. } => todo.extend(args.iter()),
+                Self::Quant { body, .. } => todo.push(body),
+            }
+        }
+        span
+    }
 
     /// Binary operator application.
     pub fn binapp(op: Spn<expr::Op>, lft: Self, rgt: Self) -> Self {
@@ -77,6 +133,95 @@ impl<'txt> Expr<'txt> {
         }
     }
 
+    /// Conjunction, `true` if empty.
+    fn conj(span: Span, mut args: Vec<Self>) -> Self {
+        match args.len() {
+            0 => Self::cst(Spn::new(expr::Cst::bool(true), span)),
+            1 => args.pop().expect("[unreachable] pop on vec of len `1`"),
+            _ => Self::app(Spn::new(expr::Op::And, span), args),
+        }
+    }
+
+    /// Next version of an expression, fails if it mentions primed variables.
+    pub(crate) fn primed(&self) -> Result<Self, &'static str> {
+        match self {
+            Self::Cst(_) => Ok(self.clone()),
+            Self::Var { pon: Some(_), .. } => Err("unprimed arguments for this intrinsic"),
+            Self::Var { ident, pon: None } => Ok(Self::svar(*ident, Some(ident.span))),
+            Self::App { op, args, closed } => Ok(Self::App {
+                op: op.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| arg.primed())
+                    .collect::<Result<_, _>>()?,
+                closed: *closed,
+            }),
+            Self::Fun { ident, args } => Ok(Self::Fun {
+                ident: *ident,
+                args: args
+                    .iter()
+                    .map(|arg| arg.primed())
+                    .collect::<Result<_, _>>()?,
+            }),
+            Self::Quant { .. } => Err("unquantified arguments for this intrinsic"),
+        }
+    }
+
+    /// Intrinsic function application, see [`expr::patterns`].
+    ///
+    /// Fails on unknown intrinsics and arity errors, the error is what the parser expected.
+    pub fn intrinsic(name: Spn<&'txt str>, args: Vec<Self>) -> Result<Self, &'static str> {
+        use expr::Op;
+        let span = name.span;
+        let op = |op: Op| Spn::new(op, span);
+        let at_most_one = |args: &[Self]| {
+            let mut conjs = vec![];
+            for (idx, lft) in args.iter().enumerate() {
+                for rgt in args[idx + 1..].iter() {
+                    let both = Self::binapp(op(Op::And), lft.clone(), rgt.clone());
+                    conjs.push(Self::unapp(op(Op::Not), both))
+                }
+            }
+            Self::conj(span, conjs)
+        };
+        match *name {
+            "at_most_one" => Ok(at_most_one(&args)),
+            "exactly_one" => {
+                let at_least_one = match args.len() {
+                    0 => return Ok(Self::cst(Spn::new(expr::Cst::bool(false), span))),
+                    1 => args[0].clone(),
+                    _ => Self::app(op(Op::Or), args.clone()),
+                };
+                match args.len() {
+                    1 => Ok(at_least_one),
+                    _ => Ok(Self::conj(span, vec![at_least_one, at_most_one(&args)])),
+                }
+            }
+            "in_range" => match <[Self; 3]>::try_from(args) {
+                Ok([x, lo, hi]) => Ok(Self::conj(
+                    span,
+                    vec![
+                        Self::binapp(op(Op::Le), lo, x.clone()),
+                        Self::binapp(op(Op::Le), x, hi),
+                    ],
+                )),
+                Err(_) => Err("three arguments for `in_range`"),
+            },
+            "monotonic" => match <[Self; 1]>::try_from(args) {
+                Ok([x]) => Ok(Self::binapp(op(Op::Ge), x.primed()?, x)),
+                Err(_) => Err("one argument for `monotonic`"),
+            },
+            "unchanged" => {
+                let mut eqs = vec![];
+                for x in args {
+                    eqs.push(Self::binapp(op(Op::Eq), x.primed()?, x))
+                }
+                Ok(Self::conj(span, eqs))
+            }
+            _ => Err("intrinsic function"),
+        }
+    }
+
     /// True if `self` is an if-then-else application.
     pub fn is_ite(&self) -> bool {
         match self {
@@ -89,13 +234,233 @@ impl<'txt> Expr<'txt> {
     pub fn close(&mut self) {
         match self {
             Self::App { closed, .. } => *closed = true,
-            Self::Var { .. } | Self::Cst(_) => (),
+            Self::Var { .. } | Self::Cst(_) | Self::Fun { .. } | Self::Quant { .. } => (),
+        }
+    }
+
+    /// Checks that the enumeration variants appearing in the expression are declared.
+    fn check_variants(&self, decls: &trans::Decls) -> PRes<()> {
+        let mut todo = vec![self];
+        while let Some(expr) = todo.pop() {
+            match expr {
+                Self::Cst(cst) => {
+                    if let Ok((typ, variant)) = cst.as_variant() {
+                        if decls.enum_variants(typ).is_none() {
+                            return Err(PError::new(
+                                format!("unknown enumeration type `{}`", typ),
+                                cst.span,
+                            ));
+                        } else if !decls.has_variant(typ, variant) {
+                            return Err(PError::new(
+                                format!("unknown variant `{}`", cst.inner),
+                                cst.span,
+                            ));
+                        }
+                    }
+                }
+                Self::Var { .. } => (),
+                Self::App { args, .. } | Self::Fun { args, .. } => todo.extend(args.iter()),
+                Self::Quant { body, .. } => todo.push(body),
+            }
+        }
+        Ok(())
+    }
+
+    /// Span of the first quantifier of the expression, if any.
+    pub fn quantifier_span(&self) -> Option<Span> {
+        let mut todo = vec![self];
+        while let Some(expr) = todo.pop() {
+            match expr {
+                Self::Cst(_) | Self::Var { .. } => (),
+                Self::App { args, .. } | Self::Fun { args, .. } => todo.extend(args.iter().rev()),
+                Self::Quant { span, .. } => return Some(*span),
+            }
+        }
+        None
+    }
+
+    /// Replaces the free occurrences of the variables of `map` by their image.
+    ///
+    /// Replacement is simultaneous: images are not themselves rewritten. Variables with a *pre* or
+    /// *next* modifier are left untouched. Yields `true` if the expression changed.
+    pub fn replace_vars(&mut self, map: &Map<&str, Expr<'txt>>) -> bool {
+        match self {
+            Self::Var { ident, pon: None } => match map.get(ident.inner) {
+                Some(image) => {
+                    *self = image.clone();
+                    true
+                }
+                None => false,
+            },
+            Self::Cst(_) | Self::Var { .. } => false,
+            Self::App { args, .. } | Self::Fun { args, .. } => {
+                let mut changed = false;
+                for arg in args.iter_mut() {
+                    changed = arg.replace_vars(map) || changed
+                }
+                changed
+            }
+            Self::Quant { vars, body, .. } => {
+                if vars.iter().any(|(var, _)| map.contains_key(var.inner)) {
+                    let mut map = map.clone();
+                    for (var, _) in vars.iter() {
+                        let _ = map.remove(var.inner);
+                    }
+                    body.replace_vars(&map)
+                } else {
+                    body.replace_vars(map)
+                }
+            }
+        }
+    }
+
+    /// Checks the bound variables of the expression, yields their types.
+    ///
+    /// Bound variables cannot shadow declarations or other bound variables, cannot be bound with
+    /// different types, and can only appear in the body of their quantifier.
+    fn bound_vars(&self, decls: &trans::Decls) -> PRes<Map<&'txt str, expr::Typ>> {
+        fn go<'txt>(
+            expr: &Expr<'txt>,
+            decls: &trans::Decls,
+            scope: &mut Vec<&'txt str>,
+            bound: &mut Map<&'txt str, expr::Typ>,
+            free: &mut Vec<Spn<&'txt str>>,
+        ) -> PRes<()> {
+            match expr {
+                Expr::Cst(_) => (),
+                Expr::Var { ident, .. } => {
+                    if !scope.contains(&ident.inner) && !decls.contains(ident.inner) {
+                        free.push(*ident)
+                    }
+                }
+                Expr::App { args, .. } | Expr::Fun { args, .. } => {
+                    for arg in args {
+                        go(arg, decls, scope, bound, free)?
+                    }
+                }
+                Expr::Quant { vars, body, .. } => {
+                    for (var, typ) in vars.iter() {
+                        if decls.contains(var.inner)
+                            || decls.get_fun(var.inner).is_some()
+                            || scope.contains(&var.inner)
+                        {
+                            return Err(PError::new(
+                                format!("bound variable `{}` shadows a declaration", var.inner),
+                                var.span,
+                            ));
+                        }
+                        decls
+                            .check_typ(typ)
+                            .map_err(|e| PError::new(e.to_string(), var.span))?;
+                        match bound.get(var.inner) {
+                            Some(other) if other != typ => {
+                                return Err(PError::new(
+                                    format!(
+                                        "variable `{}` is bound with types `{}` and `{}`",
+                                        var.inner, other, typ
+                                    ),
+                                    var.span,
+                                ))
+                            }
+                            Some(_) => (),
+                            None => {
+                                let _ = bound.insert(var.inner, typ.clone());
+                            }
+                        }
+                        scope.push(var.inner)
+                    }
+                    go(body, decls, scope, bound, free)?;
+                    scope.truncate(scope.len() - vars.len())
+                }
+            }
+            Ok(())
+        }
+        let (mut scope, mut bound, mut free) = (vec![], Map::new(), vec![]);
+        go(self, decls, &mut scope, &mut bound, &mut free)?;
+        if let Some(var) = free.into_iter().find(|var| bound.contains_key(var.inner)) {
+            return Err(PError::new(
+                format!(
+                    "bound variable `{}` is used outside of its quantifier",
+                    var.inner
+                ),
+                var.span,
+            ));
+        }
+        Ok(bound)
+    }
+
+    /// Resolves uninterpreted function applications and quantifiers from the declarations.
+    fn resolve_funs(self, decls: &trans::Decls) -> PRes<Self> {
+        match self {
+            Self::Cst(_) | Self::Var { .. } => Ok(self),
+            Self::App { op, args, closed } => Ok(Self::App {
+                op,
+                args: args
+                    .into_iter()
+                    .map(|arg| arg.resolve_funs(decls))
+                    .collect::<PRes<_>>()?,
+                closed,
+            }),
+            Self::Quant {
+                span,
+                forall,
+                vars,
+                body,
+            } => {
+                let binders = expr::Binders::new(
+                    vars.into_iter()
+                        .map(|(var, typ)| expr::Var::new(var.inner, typ))
+                        .collect(),
+                );
+                let op = if forall {
+                    expr::Op::Forall(binders)
+                } else {
+                    expr::Op::Exists(binders)
+                };
+                Ok(Self::App {
+                    op: Spn::new(op, span),
+                    args: vec![body.resolve_funs(decls)?],
+                    closed: true,
+                })
+            }
+            Self::Fun { ident, args } => {
+                let fun = decls.get_fun(ident.inner).ok_or_else(|| {
+                    PError::new(format!("unknown function `{}`", ident.inner), ident.span)
+                })?;
+                Ok(Self::App {
+                    op: Spn::new(expr::Op::Fun(fun), ident.span),
+                    args: args
+                        .into_iter()
+                        .map(|arg| arg.resolve_funs(decls))
+                        .collect::<PRes<_>>()?,
+                    closed: true,
+                })
+            }
         }
     }
 
     /// Turns itself into an expression from some declarations.
     pub fn to_sexpr(self, decls: &trans::Decls) -> PRes<expr::SExpr> {
-        self.inner_to_expr(|var, next_opt| {
+        if let Some(span) = self.quantifier_span() {
+            return Err(PError::new(
+                "quantifiers are only supported in scripts",
+                span,
+            ));
+        }
+        self.check_variants(decls)?;
+        self.resolve_funs(decls)?.inner_to_expr(|var, next_opt| {
+            if var.inner == trans::time::DELTA && !decls.contains(var.inner) {
+                if let Some(span) = next_opt {
+                    return Err(PError::new("illegal *next* modifier on `delta`", span));
+                }
+                let delta = trans::time::delta(decls).ok_or_else(|| {
+                    PError::new(
+                        "`delta` requires a state variable `time: rat`, the clock",
+                        var.span,
+                    )
+                })?;
+                return Ok(Spn::new(delta, var.span));
+            }
             let svar = if next_opt.is_some() {
                 decls.get_next_var(var.inner)
             } else {
@@ -104,23 +469,38 @@ impl<'txt> Expr<'txt> {
             .ok_or_else(|| PError::new(format!("unknown variable `{}`", var.inner), var.span))?;
             Ok(Spn::new(
                 expr::SExpr::new_var(svar),
-                var.span.merge(next_opt.unwrap_or(var.span)),
+                var.span.union(next_opt.unwrap_or(var.span)),
             ))
         })
     }
 
     /// Turns itself into a stateless expression from some declarations.
     pub fn to_expr(self, decls: &trans::Decls) -> PRes<expr::Expr> {
-        self.inner_to_expr(|var, next_opt| {
+        let bound = self.bound_vars(decls)?;
+        self.check_variants(decls)?;
+        self.resolve_funs(decls)?.inner_to_expr(|var, next_opt| {
             if let Some(span) = next_opt {
                 return Err(PError::new("illegal *next* modifier", span));
             }
-            let svar = decls.get_var(var.inner).ok_or_else(|| {
-                PError::new(format!("unknown variable `{}`", var.inner), var.span)
-            })?;
+            if var.inner == trans::time::DELTA && !decls.contains(var.inner) {
+                return Err(PError::new(
+                    "`delta` is only legal in the transition relation",
+                    var.span,
+                ));
+            }
+            let svar = decls
+                .get_var(var.inner)
+                .or_else(|| {
+                    bound
+                        .get(var.inner)
+                        .map(|typ| expr::Var::new(var.inner, typ.clone()))
+                })
+                .ok_or_else(|| {
+                    PError::new(format!("unknown variable `{}`", var.inner), var.span)
+                })?;
             Ok(Spn::new(
                 expr::Expr::new_var(svar),
-                var.span.merge(next_opt.unwrap_or(var.span)),
+                var.span.union(next_opt.unwrap_or(var.span)),
             ))
         })
     }
@@ -128,17 +508,44 @@ impl<'txt> Expr<'txt> {
     /// Turns itself into an expression.
     ///
     /// - `handle_var` turns variables into actual expression variables.
+    ///
+    /// Type errors are reported on the operator of the ill-typed application, with a note
+    /// spanning the whole application, see [`Self::full_span`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{parse, trans::Decls, expr::Typ};
+    /// let mut decls = Decls::new();
+    /// let _ = decls.register("x", Typ::Int);
+    /// let _ = decls.register("b", Typ::Bool);
+    /// let txt = "x ≥ 0 ⋀ (b ⋁ x + b > 7)";
+    /// let err = parse::rules::hsmt_expr(txt).unwrap().to_expr(&decls).unwrap_err();
+    /// assert_eq!(&txt[err.span.start..err.span.end], "+");
+    /// let (note, msg) = &err.notes[0];
+    /// assert_eq!((&txt[note.start..note.end], msg.as_str()), ("x + b", "in this application"));
+    /// ```
     pub fn inner_to_expr<V: HasTyp>(
         self,
         mut handle_var: impl FnMut(Spn<&'txt str>, Option<Span>) -> PRes<Spn<expr::PExpr<V>>>,
     ) -> PRes<expr::PExpr<V>> {
-        let mut stack: Vec<(Spn<expr::Op>, Vec<expr::PExpr<V>>, _, bool)> = Vec::with_capacity(17);
+        // Operator, arguments so far, remaining arguments, closed flag, span of the application so
+        // far.
+        let mut stack: Vec<(Spn<expr::Op>, Vec<expr::PExpr<V>>, _, bool, Span)> =
+            Vec::with_capacity(17);
         let mut current = self;
 
         'go_down: loop {
             let mut res: Spn<expr::PExpr<V>> = match current {
                 Expr::Cst(cst) => cst.map(expr::PExpr::new_cst),
                 Expr::Var { ident, pon } => handle_var(ident, pon)?,
+                Expr::Fun { ident, .. } => {
+                    return Err(PError::new(
+                        format!("unresolved function `{}`", ident.inner),
+                        ident.span,
+                    ))
+                }
+                Expr::Quant { span, .. } => return Err(PError::new("unresolved quantifier", span)),
                 Expr::App { op, args, closed } => {
                     let mut args = args.into_iter();
                     if let Some(next) = args.next() {
@@ -146,29 +553,42 @@ impl<'txt> Expr<'txt> {
                     } else {
                         return Err(PError::new("illegal unary operator application", op.span));
                     }
-                    stack.push((op, Vec::with_capacity(args.len()), args, closed));
+                    let span = op.span;
+                    stack.push((op, Vec::with_capacity(args.len()), args, closed, span));
                     continue 'go_down;
                 }
             };
 
-            'go_up: while let Some((op, mut args, mut todo, closed)) = stack.pop() {
+            'go_up: while let Some((op, mut args, mut todo, closed, span)) = stack.pop() {
+                let span = span.union(res.span);
                 if let Some(next) = todo.next() {
                     args.push(res.inner);
                     current = next;
-                    stack.push((op, args, todo, closed));
+                    stack.push((op, args, todo, closed, span));
                     continue 'go_down;
                 } else {
-                    if let Some((up_op, up_args, _, _)) = stack.last_mut() {
-                        if up_op.inner == op.inner && op.inner.is_left_associative() {
+                    if let Some((up_op, up_args, _, _, up_span)) = stack.last_mut() {
+                        // Closed applications of non-associative operators are kept as is, as
+                        // in `a - (b - c)`.
+                        let merge =
+                            op.inner.is_associative() || !closed && op.inner.is_left_associative();
+                        if up_op.inner == op.inner && merge {
                             up_op.span = op.span;
                             up_args.extend(args);
+                            *up_span = up_span.union(span);
                             continue 'go_up;
                         }
                     }
                     args.push(res.inner);
-                    let expr =
-                        expr::PExpr::new_op(op.inner, args).map_err(|e| PError::new(e, op.span))?;
-                    res = Spn::new(expr, op.span);
+                    let expr = expr::PExpr::new_op(op.inner.clone(), args).map_err(|e| {
+                        let err = PError::new(e, op.span);
+                        if span == op.span {
+                            err
+                        } else {
+                            err.with_note(span, "in this application")
+                        }
+                    })?;
+                    res = Spn::new(expr, span);
                     continue 'go_up;
                 }
             }
@@ -189,11 +609,7 @@ impl<'txt> fmt::Display for Expr<'txt> {
                 ident.fmt(fmt)?;
                 Ok(())
             }
-            &Self::App {
-                op,
-                ref args,
-                closed: _,
-            } if *op == expr::Op::Ite => {
+            Self::App { op, args, .. } if **op == expr::Op::Ite => {
                 assert_eq!(args.len(), 3);
 
                 write!(fmt, "if {} {{ {} }} else ", args[0], args[1])?;
@@ -204,12 +620,42 @@ impl<'txt> fmt::Display for Expr<'txt> {
                     write!(fmt, "{{ {} }}", args[2])
                 }
             }
-            &Self::App {
-                op,
-                ref args,
-                closed,
+            Self::App { op, args, .. }
+                if matches!(**op, expr::Op::Select | expr::Op::Store | expr::Op::Fun(_)) =>
+            {
+                write!(fmt, "{}(", op.hsmt_str()[0])?;
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx > 0 {
+                        ", ".fmt(fmt)?
+                    }
+                    arg.fmt(fmt)?
+                }
+                ")".fmt(fmt)
+            }
+            Self::Quant {
+                forall, vars, body, ..
             } => {
-                let closed = closed || true;
+                write!(fmt, "{} (", if *forall { "forall" } else { "exists" })?;
+                for (idx, (var, typ)) in vars.iter().enumerate() {
+                    if idx > 0 {
+                        ", ".fmt(fmt)?
+                    }
+                    write!(fmt, "{}: {}", var.inner, typ)?
+                }
+                write!(fmt, ") {{ {} }}", body)
+            }
+            Self::Fun { ident, args } => {
+                write!(fmt, "{}(", ident.inner)?;
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx > 0 {
+                        ", ".fmt(fmt)?
+                    }
+                    arg.fmt(fmt)?
+                }
+                ")".fmt(fmt)
+            }
+            Self::App { op, args, closed } => {
+                let closed = *closed || true;
                 if closed {
                     "(".fmt(fmt)?
                 }
@@ -238,3 +684,68 @@ impl<'txt> fmt::Display for Expr<'txt> {
         }
     }
 }
+
+/// Frame condition in a transition relation, `unchanged { ... }` or `frame except { ... }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame<'txt> {
+    /// Span of the keyword(s).
+    pub span: Span,
+    /// True for `frame except { ... }`, where the variables listed are the ones that can change.
+    pub except: bool,
+    /// Variables listed.
+    pub vars: Vec<Spn<&'txt str>>,
+}
+
+impl<'txt> Frame<'txt> {
+    /// Variables that do not change.
+    pub fn framed(&self, decls: &trans::Decls) -> PRes<Vec<expr::Var>> {
+        for var in self.vars.iter() {
+            if !decls.contains(var.inner) {
+                return Err(PError::new(
+                    format!("unknown variable `{}`", var.inner),
+                    var.span,
+                ));
+            }
+        }
+        if self.except {
+            Ok(decls
+                .all()
+                .filter(|var| self.vars.iter().all(|v| v.inner != var.id()))
+                .collect())
+        } else {
+            Ok(self
+                .vars
+                .iter()
+                .filter_map(|var| decls.get_var(var.inner))
+                .collect())
+        }
+    }
+
+    /// Turns itself into a stateful expression, `'x = x` for each variable `x` that does not
+    /// change.
+    pub fn to_sexpr(&self, decls: &trans::Decls) -> PRes<expr::SExpr> {
+        let framed: Vec<expr::Expr> = self
+            .framed(decls)?
+            .into_iter()
+            .map(expr::PExpr::new_var)
+            .collect();
+        expr::patterns::unchanged(decls, &framed).map_err(|e| PError::new(e.to_string(), self.span))
+    }
+}
+
+impl<'txt> fmt::Display for Frame<'txt> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.except {
+            "frame except { ".fmt(fmt)?
+        } else {
+            "unchanged { ".fmt(fmt)?
+        }
+        for (idx, var) in self.vars.iter().enumerate() {
+            if idx > 0 {
+                ", ".fmt(fmt)?
+            }
+            var.fmt(fmt)?
+        }
+        " }".fmt(fmt)
+    }
+}
@@ -4,6 +4,8 @@
 
 crate::prelude!();
 
+use crate::meta::Label;
+
 /// Command trait.
 pub trait CommandExt {
     /// True if the command is a query, *i.e.* produces a result.
@@ -120,6 +122,8 @@ pub struct Assert<E> {
     pub span: Span,
     /// Expressions to assert.
     pub exprs: Vec<E>,
+    /// Names of the expressions, reported by [`UnsatCore`]; same length as `exprs`.
+    pub names: Vec<Option<Spn<String>>>,
 }
 impl<E> CommandExt for Assert<E> {
     fn is_query(&self) -> bool {
@@ -134,13 +138,29 @@ impl<E> CommandExt for Assert<E> {
 }
 
 impl<E> Assert<E> {
-    /// Constructor.
+    /// Constructor, for unnamed expressions.
     pub fn new(span: impl Into<Span>, exprs: Vec<E>) -> Self {
+        let names = exprs.iter().map(|_| None).collect();
+        Self {
+            span: span.into(),
+            exprs,
+            names,
+        }
+    }
+    /// Constructor, for optionally named expressions.
+    pub fn new_named(span: impl Into<Span>, exprs: Vec<(Option<Spn<String>>, E)>) -> Self {
+        let (names, exprs) = exprs.into_iter().unzip();
         Self {
             span: span.into(),
             exprs,
+            names,
         }
     }
+
+    /// Iterator over the expressions and their names.
+    pub fn named_exprs(&self) -> impl Iterator<Item = (Option<&Spn<String>>, &E)> {
+        self.names.iter().map(Option::as_ref).zip(self.exprs.iter())
+    }
 }
 
 /// Echoes something.
@@ -205,6 +225,133 @@ impl Reset {
     }
 }
 
+/// Takes a named snapshot of the solver's assertion state.
+///
+/// The snapshot is the sequence of declarations, options and assertions since the last reset (or
+/// restore). [`Restore`] replays it, so snapshots can be restored in any order, unlike push/pop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Span.
+    pub span: Span,
+    /// Name of the snapshot.
+    pub name: Spn<String>,
+}
+impl CommandExt for Snapshot {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        format!("snapshot!({})", self.name.inner)
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl Snapshot {
+    /// Constructor.
+    pub fn new(span: impl Into<Span>, name: impl Into<Spn<String>>) -> Self {
+        Self {
+            span: span.into(),
+            name: name.into(),
+        }
+    }
+}
+
+/// Restores a named [`Snapshot`].
+///
+/// Resets the solver and replays the snapshot. Does not affect meta-variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restore {
+    /// Span.
+    pub span: Span,
+    /// Name of the snapshot.
+    pub name: Spn<String>,
+}
+impl CommandExt for Restore {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        format!("restore!({})", self.name.inner)
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl Restore {
+    /// Constructor.
+    pub fn new(span: impl Into<Span>, name: impl Into<Spn<String>>) -> Self {
+        Self {
+            span: span.into(),
+            name: name.into(),
+        }
+    }
+}
+
+/// Pushes some levels on the solver's assertion stack.
+///
+/// Everything declared or asserted after the push is retracted by the matching [`Pop`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Push {
+    /// Span.
+    pub span: Span,
+    /// Number of levels pushed.
+    pub count: usize,
+}
+impl CommandExt for Push {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        format!("push!({})", self.count)
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl Push {
+    /// Constructor.
+    pub fn new(span: impl Into<Span>, count: usize) -> Self {
+        Self {
+            span: span.into(),
+            count,
+        }
+    }
+}
+
+/// Pops some levels from the solver's assertion stack, see [`Push`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pop {
+    /// Span.
+    pub span: Span,
+    /// Number of levels popped.
+    pub count: usize,
+}
+impl CommandExt for Pop {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        format!("pop!({})", self.count)
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl Pop {
+    /// Constructor.
+    pub fn new(span: impl Into<Span>, count: usize) -> Self {
+        Self {
+            span: span.into(),
+            count,
+        }
+    }
+}
+
 /// Exits with an exit code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Exit {
@@ -329,10 +476,13 @@ impl MLet {
 pub struct CheckSat {
     /// Keyword span.
     pub span: Span,
-    /// Optional list of `declare-const`ed `Bool` identifiers to assume true in the check sat.
-    pub assuming: Vec<Spn<String>>,
+    /// Optional list of `declare-const`ed `Bool` identifiers to assume in the check sat, with
+    /// their polarity: `false` to assume the identifier is false.
+    pub assuming: Vec<(Spn<String>, bool)>,
     /// Optional timeout.
     pub timeout: Option<time::Duration>,
+    /// Label, from the doc comment of the command, see [`Command::set_label`].
+    pub label: Option<Label>,
 }
 impl CommandExt for CheckSat {
     fn is_query(&self) -> bool {
@@ -350,13 +500,14 @@ impl CheckSat {
     /// Constructor.
     pub fn new(
         span: impl Into<Span>,
-        assuming: Option<Vec<Spn<String>>>,
+        assuming: Option<Vec<(Spn<String>, bool)>>,
         timeout: Option<time::Duration>,
     ) -> Self {
         Self {
             span: span.into(),
             assuming: assuming.unwrap_or_else(Vec::new),
             timeout,
+            label: None,
         }
     }
 }
@@ -391,6 +542,68 @@ impl GetModel {
     }
 }
 
+/// A get MUS, extracts a minimal unsatisfiable subset of the assertions since the last reset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetMus {
+    /// Keyword span.
+    pub span: Span,
+    /// Token used to invoke the command.
+    pub token: String,
+}
+impl CommandExt for GetMus {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        "get-mus".into()
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl GetMus {
+    /// Constructor.
+    pub fn new(span: impl Into<Span>, token: impl Into<String>) -> Self {
+        Self {
+            span: span.into(),
+            token: token.into(),
+        }
+    }
+}
+
+/// An unsat core request, lists the named assertions used to prove the last check sat unsat.
+///
+/// Expressions are named in [`Assert`]s as `assert { <name>: <expr>, ... }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsatCore {
+    /// Keyword span.
+    pub span: Span,
+    /// Token used to invoke the command.
+    pub token: String,
+}
+impl CommandExt for UnsatCore {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        "get-unsat-core".into()
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl UnsatCore {
+    /// Constructor.
+    pub fn new(span: impl Into<Span>, token: impl Into<String>) -> Self {
+        Self {
+            span: span.into(),
+            token: token.into(),
+        }
+    }
+}
+
 /// Some evaluation requests.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GetValues<E> {
@@ -424,6 +637,54 @@ impl<E> GetValues<E> {
     }
 }
 
+/// An optimization request, yields the minimum or maximum of an integer or bitvector expression.
+///
+/// Bitvectors are seen as unsigned. Does not modify the assertions, see
+/// [`crate::script::Script::optimize`] for the state of the solver afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Optimize<E> {
+    /// Keyword span.
+    pub span: Span,
+    /// Token provided by user.
+    pub token: String,
+    /// Objective and its string user-representation.
+    pub objective: (E, String),
+    /// True for `maximize`, false for `minimize`.
+    pub maximize: bool,
+}
+impl<E> CommandExt for Optimize<E> {
+    fn is_query(&self) -> bool {
+        false
+    }
+    fn desc(&self) -> String {
+        if self.maximize {
+            "maximize".into()
+        } else {
+            "minimize".into()
+        }
+    }
+    fn exits(&self) -> bool {
+        false
+    }
+}
+
+impl<E> Optimize<E> {
+    /// Constructor.
+    pub fn new(
+        span: impl Into<Span>,
+        token: impl Into<String>,
+        objective: (E, String),
+        maximize: bool,
+    ) -> Self {
+        Self {
+            span: span.into(),
+            token: token.into(),
+            objective,
+            maximize,
+        }
+    }
+}
+
 /// An if-then-else on a meta, boolean variable ([`Query`]).
 ///
 /// `Ite` is a [`Query`] because it **can** produce a result. Namely, if all of its branches end
@@ -587,10 +848,24 @@ pub enum Command<E, ME> {
     GetModel(GetModel),
     /// Evaluation request.
     GetValues(GetValues<E>),
+    /// Optimization request.
+    Optimize(Optimize<E>),
+    /// Minimal unsatisfiable subset request.
+    GetMus(GetMus),
+    /// Unsat core request.
+    UnsatCore(UnsatCore),
     /// Commands that can produce boolean results.
     Query(Query<E, ME>),
     /// Reset.
     Reset(Reset),
+    /// Snapshot.
+    Snapshot(Snapshot),
+    /// Restore.
+    Restore(Restore),
+    /// Push.
+    Push(Push),
+    /// Pop.
+    Pop(Pop),
 }
 impl<E, ME> CommandExt for Command<E, ME> {
     fn is_query(&self) -> bool {
@@ -602,7 +877,14 @@ impl<E, ME> CommandExt for Command<E, ME> {
             Self::Echo(c) => c.is_query(),
             Self::GetModel(c) => c.is_query(),
             Self::GetValues(c) => c.is_query(),
+            Self::Optimize(c) => c.is_query(),
+            Self::GetMus(c) => c.is_query(),
+            Self::UnsatCore(c) => c.is_query(),
             Self::Reset(q) => q.is_query(),
+            Self::Snapshot(c) => c.is_query(),
+            Self::Restore(c) => c.is_query(),
+            Self::Push(c) => c.is_query(),
+            Self::Pop(c) => c.is_query(),
             Self::Query(q) => q.is_query(),
         }
     }
@@ -615,7 +897,14 @@ impl<E, ME> CommandExt for Command<E, ME> {
             Self::Echo(c) => c.desc(),
             Self::GetModel(c) => c.desc(),
             Self::GetValues(c) => c.desc(),
+            Self::Optimize(c) => c.desc(),
+            Self::GetMus(c) => c.desc(),
+            Self::UnsatCore(c) => c.desc(),
             Self::Reset(q) => q.desc(),
+            Self::Snapshot(c) => c.desc(),
+            Self::Restore(c) => c.desc(),
+            Self::Push(c) => c.desc(),
+            Self::Pop(c) => c.desc(),
             Self::Query(q) => q.desc(),
         }
     }
@@ -628,12 +917,150 @@ impl<E, ME> CommandExt for Command<E, ME> {
             Self::Echo(c) => c.exits(),
             Self::GetModel(c) => c.exits(),
             Self::GetValues(c) => c.exits(),
+            Self::Optimize(c) => c.exits(),
+            Self::GetMus(c) => c.exits(),
+            Self::UnsatCore(c) => c.exits(),
             Self::Reset(c) => c.exits(),
+            Self::Snapshot(c) => c.exits(),
+            Self::Restore(c) => c.exits(),
+            Self::Push(c) => c.exits(),
+            Self::Pop(c) => c.exits(),
             Self::Query(q) => q.exits(),
         }
     }
 }
 
+impl<E, ME> Command<E, ME> {
+    /// Sets the label of the check-sat of the command, if any.
+    ///
+    /// Only check-sat commands, meta-let-s and if-then-else-s on a check-sat have a check-sat,
+    /// the label is ignored for other commands.
+    pub fn set_label(&mut self, label: Label) {
+        match self {
+            Self::MLet(MLet { rhs: check, .. })
+            | Self::Query(Query::CheckSat(check))
+            | Self::Query(Query::Ite(Ite {
+                cnd: Either::Right(check),
+                ..
+            })) => check.label = Some(label),
+            Self::SetOptions(_)
+            | Self::Vars(_)
+            | Self::Assert(_)
+            | Self::Echo(_)
+            | Self::GetModel(_)
+            | Self::GetValues(_)
+            | Self::Optimize(_)
+            | Self::GetMus(_)
+            | Self::UnsatCore(_)
+            | Self::Reset(_)
+            | Self::Snapshot(_)
+            | Self::Restore(_)
+            | Self::Push(_)
+            | Self::Pop(_)
+            | Self::Query(_) => (),
+        }
+    }
+
+    /// True if the command names assertions or requests unsat cores, which the solver must then
+    /// be told to produce.
+    pub fn uses_unsat_cores(&self) -> bool {
+        match self {
+            Self::Assert(a) => a.names.iter().any(Option::is_some),
+            Self::UnsatCore(_) => true,
+            Self::Query(Query::Block(b)) => b.content.iter().any(Self::uses_unsat_cores),
+            Self::Query(Query::Ite(ite)) => {
+                ite.thn.content.iter().any(Self::uses_unsat_cores)
+                    || ite.els.content.iter().any(Self::uses_unsat_cores)
+                    || ite
+                        .otw
+                        .as_ref()
+                        .map(|otw| otw.content.iter().any(Self::uses_unsat_cores))
+                        .unwrap_or(false)
+            }
+            // No expressions in there, `MLet`s and check sats only mention identifiers.
+            Self::SetOptions(_)
+            | Self::Vars(_)
+            | Self::MLet(_)
+            | Self::Echo(_)
+            | Self::GetModel(_)
+            | Self::GetValues(_)
+            | Self::Optimize(_)
+            | Self::GetMus(_)
+            | Self::Reset(_)
+            | Self::Snapshot(_)
+            | Self::Restore(_)
+            | Self::Push(_)
+            | Self::Pop(_)
+            | Self::Query(_) => false,
+        }
+    }
+}
+
+impl<'txt> Command<ast::Expr<'txt>, ast::Expr<'txt>> {
+    /// Replaces the free occurrences of the variables of `map` by their image in the expressions of
+    /// the command, see [`ast::Expr::replace_vars`].
+    ///
+    /// The user-representation of the expressions of [`GetValues`] and [`Optimize`] is updated
+    /// when they change.
+    ///
+    /// Only expressions are affected: the assumption literals of check sats, including the ones of
+    /// [`MLet`]s and of the conditions of [`Ite`]s, and meta-variables are identifiers that are
+    /// left as is.
+    pub fn replace_vars(&mut self, map: &Map<&str, ast::Expr<'txt>>) {
+        match self {
+            Self::Assert(a) => {
+                for expr in a.exprs.iter_mut() {
+                    let _ = expr.replace_vars(map);
+                }
+            }
+            Self::GetValues(gv) => {
+                for (expr, repr) in gv.exprs.iter_mut() {
+                    if expr.replace_vars(map) {
+                        *repr = expr.to_string()
+                    }
+                }
+            }
+            Self::Optimize(opt) => {
+                let (expr, repr) = &mut opt.objective;
+                if expr.replace_vars(map) {
+                    *repr = expr.to_string()
+                }
+            }
+            Self::Query(Query::Block(b)) => {
+                for cmd in b.content.iter_mut() {
+                    cmd.replace_vars(map)
+                }
+            }
+            // The condition is a meta-variable or a check sat, neither has expressions.
+            Self::Query(Query::Ite(ite)) => {
+                let otw = ite.otw.iter_mut().flat_map(|otw| otw.content.iter_mut());
+                for cmd in ite
+                    .thn
+                    .content
+                    .iter_mut()
+                    .chain(&mut ite.els.content)
+                    .chain(otw)
+                {
+                    cmd.replace_vars(map)
+                }
+            }
+            Self::SetOptions(_)
+            | Self::Vars(_)
+            | Self::MLet(_)
+            | Self::Echo(_)
+            | Self::GetModel(_)
+            | Self::GetMus(_)
+            | Self::UnsatCore(_)
+            | Self::Reset(_)
+            | Self::Snapshot(_)
+            | Self::Restore(_)
+            | Self::Push(_)
+            | Self::Pop(_)
+            | Self::Query(_) => (),
+        }
+    }
+}
+
 impl<E, ME> From<SetOptions> for Command<E, ME> {
     fn from(l: SetOptions) -> Self {
         Self::SetOptions(l)
@@ -664,16 +1091,51 @@ impl<E, ME> From<GetModel> for Command<E, ME> {
         Self::GetModel(gm)
     }
 }
+impl<E, ME> From<Optimize<E>> for Command<E, ME> {
+    fn from(opt: Optimize<E>) -> Self {
+        Self::Optimize(opt)
+    }
+}
 impl<E, ME> From<GetValues<E>> for Command<E, ME> {
     fn from(gm: GetValues<E>) -> Self {
         Self::GetValues(gm)
     }
 }
+impl<E, ME> From<GetMus> for Command<E, ME> {
+    fn from(gm: GetMus) -> Self {
+        Self::GetMus(gm)
+    }
+}
+impl<E, ME> From<UnsatCore> for Command<E, ME> {
+    fn from(uc: UnsatCore) -> Self {
+        Self::UnsatCore(uc)
+    }
+}
 impl<E, ME> From<Reset> for Command<E, ME> {
     fn from(r: Reset) -> Self {
         Self::Reset(r)
     }
 }
+impl<E, ME> From<Snapshot> for Command<E, ME> {
+    fn from(s: Snapshot) -> Self {
+        Self::Snapshot(s)
+    }
+}
+impl<E, ME> From<Restore> for Command<E, ME> {
+    fn from(r: Restore) -> Self {
+        Self::Restore(r)
+    }
+}
+impl<E, ME> From<Push> for Command<E, ME> {
+    fn from(p: Push) -> Self {
+        Self::Push(p)
+    }
+}
+impl<E, ME> From<Pop> for Command<E, ME> {
+    fn from(p: Pop) -> Self {
+        Self::Pop(p)
+    }
+}
 impl<E, ME> From<Exit> for Command<E, ME> {
     fn from(e: Exit) -> Self {
         Self::Query(e.into())
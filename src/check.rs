@@ -2,14 +2,53 @@
 
 crate::prelude!();
 
-use rsmt2::print::Expr2Smt;
+use rsmt2::{
+    actlit::Actlit,
+    print::{Expr2Smt, Sym2Smt},
+};
 
+use cexs::Cex;
 use expr::{Expr, Var};
 use trans::Sys;
 
+pub mod batch;
+pub mod bounds;
+pub mod cache;
+pub mod certificate;
 pub mod cexs;
-
+pub mod coverage;
+pub mod determinism;
+pub mod driver;
+pub mod engine;
+pub mod explain;
+pub mod explicit;
+pub mod filter;
+pub mod generate;
+pub mod init;
+pub mod interpolation;
+pub mod liveness;
+pub mod ltl;
+pub mod minimize;
+pub mod narrow;
+pub mod pdr;
+pub mod preprocess;
+pub mod random;
+pub mod reach;
+pub mod record;
+pub mod replay;
+pub mod stats;
+pub mod suggest;
+pub mod trace;
+pub mod vacuity;
+pub mod watch;
+
+pub use batch::batch;
 pub use cexs::Cexs;
+pub use driver::{run, Config, Outcome, OutcomeDiff, Verdict};
+pub use engine::Engine;
+pub use pdr::pdr;
+pub use suggest::suggest_invariants;
+pub use watch::watch;
 
 /// Aggregrates properties that are considered "ok" and properties that have been falsified.
 ///
@@ -60,6 +99,18 @@ impl<'s> CheckRes<'s> {
     /// - when `po` does not belong to `self.okay`;
     /// - when a falsification for `po` has already been registered.
     pub fn register_falsification(&mut self, po: &'s String, solver: &mut SFSolver) -> Res<()> {
+        let mut cex = Cex::new();
+        cex.populate(solver)?;
+        self.register_cex(po, cex)
+    }
+
+    /// Registers a falsification with its counterexample.
+    ///
+    /// # Errors
+    ///
+    /// - when `po` does not belong to `self.okay`;
+    /// - when a falsification for `po` has already been registered.
+    pub fn register_cex(&mut self, po: &'s String, cex: Cex) -> Res<()> {
         if self.cexs.contains_key(po) {
             bail!("trying to register PO `{}` as falsified twice")
         }
@@ -67,7 +118,7 @@ impl<'s> CheckRes<'s> {
             bail!("trying to register unknown PO `{}` as falsified")
         }
 
-        self.cexs.insert_falsification(po, solver)?;
+        self.cexs.insert_cex(po, cex)?;
         let was_there = self.okay.remove(po);
         if !was_there {
             bail!("trying to to register unknown PO `{}` as falsified", po)
@@ -198,6 +249,26 @@ where
     }
 }
 
+/// Internal mini structure to represent the equality of a variable at two steps.
+struct VarEq<'v> {
+    /// Variable.
+    var: &'v Var,
+    /// Left step.
+    lft: Unroll,
+    /// Right step.
+    rgt: Unroll,
+}
+impl<'v> Expr2Smt<()> for VarEq<'v> {
+    fn expr_to_smt2<W: Write>(&self, w: &mut W, _: ()) -> SmtRes<()> {
+        write!(w, "(= ")?;
+        self.var.sym_to_smt2(w, self.lft)?;
+        write!(w, " ")?;
+        self.var.sym_to_smt2(w, self.rgt)?;
+        write!(w, ")")?;
+        Ok(())
+    }
+}
+
 /// Internal version of a checker.
 ///
 /// Provides low-level features for the actual checker. These features are easy to use wrong, so
@@ -209,13 +280,26 @@ pub struct InternalChecker<'sys> {
     sys: &'sys Sys,
     /// List of all variables of the system.
     vars: Vec<Var>,
+    /// Steps for which the variables are declared.
+    declared: Set<Unroll>,
+    /// True if the solver supports activation literals, *i.e.* `check-sat-assuming`.
+    actlits: bool,
 }
 impl<'sys> InternalChecker<'sys> {
     /// Constructor.
     pub fn new(sys: &'sys Sys, conf: SmtConf, tee: Option<PathBuf>) -> Res<Self> {
-        let solver = SFSolver::new(conf, tee)?;
+        let actlits = conf.get_check_sat_assuming().is_some();
+        let mut solver = SFSolver::new(conf, tee)?;
+        solver.declare_enums(sys.decls().enums())?;
+        solver.declare_funs(sys.decls().funs())?;
         let vars = sys.decls().all().collect();
-        Ok(Self { solver, sys, vars })
+        Ok(Self {
+            solver,
+            sys,
+            vars,
+            declared: Set::new(),
+            actlits,
+        })
     }
 
     /// Declares all variables for some step.
@@ -225,6 +309,7 @@ impl<'sys> InternalChecker<'sys> {
                 .declare_const_with(var, &var.typ(), step)
                 .chain_err(|| format!("while declaring variable `{}@{}`", var, step))?
         }
+        let _ = self.declared.insert(step);
         Ok(())
     }
 
@@ -244,6 +329,24 @@ impl<'sys> InternalChecker<'sys> {
         Ok(())
     }
 
+    /// Asserts that the states at two steps are equal, guarded by an activation literal if any.
+    pub fn assert_frames_eq(
+        &mut self,
+        guard: Option<&Actlit>,
+        lft: Unroll,
+        rgt: Unroll,
+    ) -> Res<()> {
+        for var in &self.vars {
+            let eq = VarEq { var, lft, rgt };
+            match guard {
+                Some(actlit) => self.solver.assert_act(actlit, &eq),
+                None => self.solver.assert(&eq),
+            }
+            .chain_err(|| format!("while asserting `{}@{} = {}@{}`", var, lft, var, rgt))?
+        }
+        Ok(())
+    }
+
     /// Asserts a stateless expression at some step.
     #[allow(dead_code)]
     pub fn assert_expr(&mut self, expr: &Expr, step: Unroll) -> Res<()> {
@@ -313,13 +416,77 @@ impl<'sys> InternalChecker<'sys> {
             })?;
             if self.solver.check_sat()? {
                 changed = true;
-                res.register_falsification(name, &mut self.solver)?
+                let mut cex = Cex::new();
+                cex.populate_or_salvage(
+                    &mut self.solver,
+                    &self.vars,
+                    self.declared.iter().cloned(),
+                );
+                res.register_cex(name, cex)?
             }
             self.solver.pop(1)?
         }
         Ok(changed)
     }
 
+    /// Finds falsifications of the proof objectives at some step, incrementally.
+    ///
+    /// Same as [`Self::find_po_falsifications`], but the negation of each candidate is guarded by
+    /// an activation literal instead of a push/pop pair, so that the solver keeps what it learned
+    /// from one check to the next. All checks also assume `guard`, if any.
+    ///
+    /// Same as [`Self::find_po_falsifications`] if the solver does not support activation
+    /// literals, in which case `guard` must be `None`.
+    pub fn find_po_falsifications_act(
+        &mut self,
+        step: Unroll,
+        res: &mut CheckRes<'sys>,
+        guard: Option<&Actlit>,
+    ) -> Res<bool> {
+        if !self.actlits {
+            return self.find_po_falsifications(step, res);
+        }
+        let mut changed = false;
+        let to_check: Vec<_> = res
+            .okay
+            .iter()
+            .map(|po| {
+                self.sys
+                    .po_s()
+                    .get_key_value(po as &str)
+                    .ok_or_else(|| format!("unknown candidate `{}`", po))
+            })
+            .collect();
+        for to_check in to_check {
+            let (name, po) = to_check?;
+            let not_po = po.negated();
+            let actlit = self.solver.get_actlit()?;
+            self.solver
+                .assert_act_with(&actlit, &not_po, step)
+                .chain_err(|| {
+                    format!(
+                        "while asserting negation of candidate `{}` at step {}",
+                        name, step
+                    )
+                })?;
+            if self
+                .solver
+                .check_sat_act(guard.into_iter().chain(Some(&actlit)))?
+            {
+                changed = true;
+                let mut cex = Cex::new();
+                cex.populate_or_salvage(
+                    &mut self.solver,
+                    &self.vars,
+                    self.declared.iter().cloned(),
+                );
+                res.register_cex(name, cex)?
+            }
+            self.solver.de_actlit(actlit)?
+        }
+        Ok(changed)
+    }
+
     /// Checks that the current state of the solver is satisfiable.
     #[allow(dead_code)]
     pub fn check_sat(&mut self) -> Res<bool> {
@@ -350,6 +517,12 @@ impl<'sys> Base<'sys> {
         })
     }
 
+    /// Kills the solver when `cancel` is cancelled, see [`engine::Cancel`].
+    pub fn cancellable(mut self, cancel: &engine::Cancel) -> Self {
+        self.checker.solver.watch(cancel);
+        self
+    }
+
     /// Checks whether some properties are falsified in the initial states.
     pub fn check(&mut self) -> Res<BaseRes<'sys>> {
         self.checker.declare_vars(0)?;
@@ -377,6 +550,12 @@ impl<'sys> Step<'sys> {
         })
     }
 
+    /// Kills the solver when `cancel` is cancelled, see [`engine::Cancel`].
+    pub fn cancellable(mut self, cancel: &engine::Cancel) -> Self {
+        self.checker.solver.watch(cancel);
+        self
+    }
+
     /// Checks whether some properties are falsified in the initial states.
     pub fn check(&mut self) -> Res<StepRes<'sys>> {
         self.checker.declare_vars(0)?;
@@ -422,13 +601,64 @@ impl<'sys> Step<'sys> {
     }
 }
 
+/// Unrolling strategy of a [`Bmc`].
+///
+/// All strategies explore the same traces, and find the same falsifications at the same depths.
+/// They only differ in how the solver builds them, which can make a big difference in practice.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::{self, Unrolling}, rsmt2::SmtConf};
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(10);
+/// let forward = check::run(&sys, &config).unwrap();
+/// let backward = check::run(&sys, &config.clone().unrolling(Unrolling::Backward)).unwrap();
+/// let falsified = |outcome: &check::Outcome| {
+///     outcome
+///         .falsified()
+///         .map(|(name, depth, _)| (name.clone(), depth))
+///         .collect::<Vec<_>>()
+/// };
+/// assert_eq!(falsified(&forward), falsified(&backward));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unrolling {
+    /// From the initial states towards the falsifying states.
+    #[default]
+    Forward,
+    /// From the falsifying states back towards the initial states.
+    Backward,
+    /// From both ends, the two halves meeting in the middle.
+    Bidirectional,
+}
+impl fmt::Display for Unrolling {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Forward => write!(fmt, "forward"),
+            Self::Backward => write!(fmt, "backward"),
+            Self::Bidirectional => write!(fmt, "bidirectional"),
+        }
+    }
+}
+
 /// Bounded Model Checker.
+///
+/// Unrolls forward by default, see [`Unrolling`] and [`Self::with_unrolling`] for other
+/// strategies.
+///
+/// Solving is incremental: each depth only declares the variables of one new step and asserts one
+/// more transition, everything else stays in the solver. Candidate checks and the depth-specific
+/// assertions of backward and bidirectional unrolling are guarded by activation literals rather
+/// than push/pop, which would discard what the solver learned. Solvers without
+/// `check-sat-assuming` fall back to push/pop.
 pub struct Bmc<'sys> {
     /// Underlying checker.
     checker: InternalChecker<'sys>,
     /// Next step at which the system should be unrolled.
     ///
-    /// When `self.next_step` is `s`, it means the system is currently unrolled to `s - 1`.
+    /// When `self.next_step` is `s`, it means the system is currently unrolled to `s - 1`. In
+    /// backward and bidirectional mode, only counts forward steps.
     ///
     /// # Invariants
     ///
@@ -436,15 +666,26 @@ pub struct Bmc<'sys> {
     next_step: Unroll,
     /// Step at which the system was last check.
     ///
+    /// In backward and bidirectional mode, depth of the next check.
+    ///
     /// # Invariants
     ///
-    /// - `self.check_step == self.next_step || self.check_step + 1 == self.next_step`
+    /// - `self.check_step == self.next_step || self.check_step + 1 == self.next_step` (forward)
     check_step: Unroll,
     /// Bmc result.
     res: BmcRes<'sys>,
+    /// Unrolling strategy.
+    unrolling: Unrolling,
+    /// Step of the falsifying state in backward and bidirectional mode.
+    ///
+    /// Backward steps are numbered downwards from the anchor, so that the transition relation is
+    /// unrolled as usual.
+    anchor: Unroll,
+    /// Number of backward steps unrolled.
+    back: Unroll,
 }
 impl<'sys> Bmc<'sys> {
-    /// Constructor.
+    /// Constructor, forward unrolling.
     pub fn new(
         sys: &'sys Sys,
         conf: SmtConf,
@@ -464,9 +705,61 @@ impl<'sys> Bmc<'sys> {
             next_step: 1,
             check_step: 0,
             res,
+            unrolling: Unrolling::Forward,
+            anchor: 0,
+            back: 0,
+        })
+    }
+
+    /// Constructor for some unrolling strategy, up to depth `max`.
+    ///
+    /// Backward and bidirectional unrolling fail when checking past depth `max`, forward unrolling
+    /// ignores `max`.
+    pub fn with_unrolling(
+        sys: &'sys Sys,
+        conf: SmtConf,
+        tee: Option<PathBuf>,
+        res: BmcRes<'sys>,
+        unrolling: Unrolling,
+        max: Unroll,
+    ) -> Res<Self> {
+        if unrolling == Unrolling::Forward {
+            return Self::new(sys, conf, tee, res);
+        }
+        let tee = tee.map(|mut path| {
+            path.push("bmc.smt2");
+            path
+        });
+
+        let mut checker = InternalChecker::new(sys, conf, tee)?;
+        let anchor = max + 1;
+        if unrolling == Unrolling::Bidirectional {
+            checker.declare_vars(0)?;
+            checker.assert_init()?;
+        }
+        checker.declare_vars(anchor)?;
+        Ok(Self {
+            checker,
+            next_step: 1,
+            check_step: 0,
+            res,
+            unrolling,
+            anchor,
+            back: 0,
         })
     }
 
+    /// Unrolling strategy.
+    pub fn unrolling(&self) -> Unrolling {
+        self.unrolling
+    }
+
+    /// Kills the solver when `cancel` is cancelled, see [`engine::Cancel`].
+    pub fn cancellable(mut self, cancel: &engine::Cancel) -> Self {
+        self.checker.solver.watch(cancel);
+        self
+    }
+
     /// Accessor to the BMC result.
     pub fn res(&self) -> &BmcRes<'sys> {
         &self.res
@@ -493,6 +786,9 @@ impl<'sys> Bmc<'sys> {
 
     /// Unrolls the system and performs the next check.
     pub fn next_check(&mut self) -> Res<bool> {
+        if self.unrolling != Unrolling::Forward {
+            return self.next_check_from_anchor();
+        }
         let res = if self.check_step == 0 {
             self.check()
                 .chain_err(|| "while checking for a falsification in the initial state(s)")?
@@ -510,6 +806,98 @@ impl<'sys> Bmc<'sys> {
         Ok(res)
     }
 
+    /// Unrolls the system and performs the next check, backward and bidirectional mode.
+    ///
+    /// Bidirectional mode unrolls the shortest half, backward first when both have the same
+    /// length.
+    fn next_check_from_anchor(&mut self) -> Res<bool> {
+        let depth = self.check_step;
+        if depth >= self.anchor {
+            bail!(
+                "cannot check past depth {} with {} unrolling",
+                self.anchor - 1,
+                self.unrolling
+            )
+        }
+        if depth > 0 {
+            if self.unrolling == Unrolling::Bidirectional && self.next_step - 1 < self.back {
+                let step = self.next_step;
+                self.checker.declare_vars(step)?;
+                self.checker
+                    .assert_trans(step - 1)
+                    .chain_err(|| format!("while unrolling the system to step {}", step))?;
+                self.next_step += 1;
+            } else {
+                let step = self.anchor - self.back - 1;
+                self.checker.declare_vars(step)?;
+                self.checker.assert_trans(step).chain_err(|| {
+                    format!("while unrolling the system backward to depth {}", depth)
+                })?;
+                self.back += 1;
+            }
+        }
+
+        // Forward steps are `0..=fwd`, backward steps `first_back..=anchor`.
+        let bidir = self.unrolling == Unrolling::Bidirectional;
+        let (fwd, first_back, anchor) = if bidir {
+            (self.next_step - 1, self.anchor - self.back, self.anchor)
+        } else {
+            (0, self.anchor - self.back, self.anchor)
+        };
+        let known: Set<String> = self.res.cexs.keys().map(|name| name.to_string()).collect();
+
+        // The link between the two ends only holds at this depth, guard it, or push if the solver
+        // does not support activation literals.
+        let guard = if self.checker.actlits {
+            Some(self.checker.solver.get_actlit()?)
+        } else {
+            self.checker.solver.push(1)?;
+            None
+        };
+        let res = if bidir {
+            self.checker
+                .assert_frames_eq(guard.as_ref(), fwd, first_back)
+        } else {
+            let sys = self.checker.sys;
+            match guard.as_ref() {
+                Some(actlit) => self
+                    .checker
+                    .solver
+                    .assert_act_with(actlit, sys.init(), first_back),
+                None => self.checker.solver.assert_with(sys.init(), first_back),
+            }
+            .chain_err(|| format!("while asserting init predicate at {}", first_back))
+        }
+        .and_then(|()| {
+            self.checker
+                .find_po_falsifications_act(anchor, &mut self.res, guard.as_ref())
+        });
+        match guard {
+            Some(actlit) => self.checker.solver.de_actlit(actlit)?,
+            None => self.checker.solver.pop(1)?,
+        }
+        let res =
+            res.chain_err(|| format!("while checking for a falsification at depth {}", depth))?;
+
+        // Renumber the steps of the new counterexamples to `0..=depth`.
+        for (name, cex) in self.res.cexs.iter_mut() {
+            if known.contains(name as &str) {
+                continue;
+            }
+            cex.trace = cex.trace.renumber(|step| {
+                if bidir && step <= fwd {
+                    Some(step)
+                } else if step >= first_back {
+                    Some(fwd + step - first_back)
+                } else {
+                    None
+                }
+            });
+        }
+        self.check_step += 1;
+        Ok(res)
+    }
+
     /// Checks whether some properties can be falsified at the current step.
     ///
     /// Returns `true` if some new falsifications were discovered.
@@ -523,9 +911,9 @@ impl<'sys> Bmc<'sys> {
         }
         let res = self
             .checker
-            .find_po_falsifications(self.check_step, &mut self.res);
+            .find_po_falsifications_act(self.check_step, &mut self.res, None)?;
         self.check_step += 1;
-        res
+        Ok(res)
     }
 
     /// Unrolls the system one step further.
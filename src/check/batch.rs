@@ -0,0 +1,384 @@
+//! Batch verification of many system files.
+//!
+//! [`batch`] checks the systems of many files and produces a [`Report`] with the [`Outcome`] of
+//! each file. Directories are searched recursively for files with extension [`EXTENSION`].
+//!
+//! Files are checked by a pool of [`Config::jobs`] workers sharing the list of files to check,
+//! each file is checked by one worker with its own solvers. This bounds the number of solvers
+//! running at the same time for the whole batch. When [`Config::budget`] is set, files not started
+//! before the budget expires are [skipped](Status::Skipped), and so are files not started before
+//! the batch is [cancelled](Config::cancel).
+//!
+//! Failing to read, parse or check a file does not stop the batch, the error is reported for this
+//! file.
+//!
+//! The [`Provenance`] of the report has the hashes of the files checked, and the outcome of each
+//! file has the hash of this file.
+//!
+//! The plain text rendering of a [`Report`] lists the falsified candidates of each file, with the
+//! title of their [label](crate::meta::Label) if any.
+
+crate::prelude!();
+
+use std::{sync::Mutex, time::Instant};
+
+use super::{
+    driver::{run, Config, Outcome},
+    trace::json_str,
+};
+use crate::meta::Provenance;
+
+/// Extension of the files searched for in directories.
+pub const EXTENSION: &str = "mnk";
+
+/// Status of a file after a batch.
+#[derive(Debug)]
+pub enum Status {
+    /// The file was checked.
+    Checked(Outcome),
+    /// Reading, parsing or checking the file failed.
+    Error(ErrorChain),
+    /// The budget expired, or the batch was cancelled, before the file was checked.
+    Skipped,
+}
+
+/// Report for one file of a batch.
+#[derive(Debug)]
+pub struct FileReport {
+    /// File.
+    pub path: PathBuf,
+    /// Status of the file.
+    pub status: Status,
+    /// Time spent on the file.
+    pub time: time::Duration,
+}
+impl FileReport {
+    /// Outcome of the file, if it was checked.
+    pub fn outcome(&self) -> Option<&Outcome> {
+        match &self.status {
+            Status::Checked(outcome) => Some(outcome),
+            Status::Error(_) | Status::Skipped => None,
+        }
+    }
+}
+
+/// Aggregate counts of a [`Report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Totals {
+    /// Files checked.
+    pub checked: usize,
+    /// Files that could not be checked.
+    pub errors: usize,
+    /// Files skipped.
+    pub skipped: usize,
+    /// Proved candidates.
+    pub proved: usize,
+    /// Falsified candidates with severity error.
+    pub failures: usize,
+    /// Falsified candidates with severity warning.
+    pub warned: usize,
+    /// Candidates that are neither proved nor falsified.
+    pub unknown: usize,
+}
+
+/// Result of a batch, see the [module-level documentation](self).
+#[derive(Debug)]
+pub struct Report {
+    /// Reports for all the files, in order.
+    pub files: Vec<FileReport>,
+    /// Total time of the batch.
+    pub time: time::Duration,
+    /// Provenance of the batch.
+    pub provenance: Provenance,
+}
+impl Report {
+    /// Aggregate counts over all files.
+    pub fn totals(&self) -> Totals {
+        let mut totals = Totals::default();
+        for file in self.files.iter() {
+            match &file.status {
+                Status::Checked(outcome) => {
+                    totals.checked += 1;
+                    totals.proved += outcome.proved().count();
+                    totals.failures += outcome.failures().count();
+                    totals.warned += outcome.warned().count();
+                    totals.unknown += outcome.unknown().count();
+                }
+                Status::Error(_) => totals.errors += 1,
+                Status::Skipped => totals.skipped += 1,
+            }
+        }
+        totals
+    }
+
+    /// True if no file failed to be checked and all checked files are a
+    /// [success](Outcome::is_success).
+    ///
+    /// Skipped files do not count as failures.
+    pub fn is_success(&self) -> bool {
+        self.files.iter().all(|file| match &file.status {
+            Status::Checked(outcome) => outcome.is_success(),
+            Status::Error(_) => false,
+            Status::Skipped => true,
+        })
+    }
+
+    /// JSON representation.
+    ///
+    /// Times are in milliseconds.
+    pub fn to_json(&self) -> String {
+        let totals = self.totals();
+        let mut s = format!("{{\"provenance\":{},\"files\":[", self.provenance.to_json());
+        for (idx, file) in self.files.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!(
+                "{{\"path\":{},\"time_ms\":{},",
+                json_str(&file.path.display().to_string()),
+                file.time.as_millis()
+            ));
+            match &file.status {
+                Status::Checked(outcome) => s.push_str(&format!(
+                    "\"status\":\"checked\",\"outcome\":{}}}",
+                    outcome.to_json()
+                )),
+                Status::Error(e) => s.push_str(&format!(
+                    "\"status\":\"error\",\"error\":{}}}",
+                    json_str(&e.to_string())
+                )),
+                Status::Skipped => s.push_str("\"status\":\"skipped\"}"),
+            }
+        }
+        s.push_str(&format!(
+            "],\"totals\":{{\"checked\":{},\"errors\":{},\"skipped\":{},\"proved\":{},\
+            \"failures\":{},\"warned\":{},\"unknown\":{}}},\"time_ms\":{}}}",
+            totals.checked,
+            totals.errors,
+            totals.skipped,
+            totals.proved,
+            totals.failures,
+            totals.warned,
+            totals.unknown,
+            self.time.as_millis()
+        ));
+        s
+    }
+}
+impl fmt::Display for Report {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for file in self.files.iter() {
+            write!(fmt, "{}: ", file.path.display())?;
+            match &file.status {
+                Status::Checked(outcome) => {
+                    writeln!(
+                        fmt,
+                        "{} proved, {} falsified, {} unknown",
+                        outcome.proved().count(),
+                        outcome.falsified().count(),
+                        outcome.unknown().count()
+                    )?;
+                    for (name, depth, _) in outcome.falsified() {
+                        write!(fmt, "    falsified at depth {}: `{}`", depth, name)?;
+                        match outcome.label(name) {
+                            Some(label) => writeln!(fmt, ", {}", label)?,
+                            None => writeln!(fmt)?,
+                        }
+                    }
+                }
+                Status::Error(e) => writeln!(fmt, "error, {}", e)?,
+                Status::Skipped => writeln!(fmt, "skipped")?,
+            }
+        }
+        let totals = self.totals();
+        writeln!(
+            fmt,
+            "{} file(s) checked, {} error(s), {} skipped",
+            totals.checked, totals.errors, totals.skipped
+        )?;
+        writeln!(
+            fmt,
+            "{} proved, {} failure(s), {} warning(s), {} unknown",
+            totals.proved, totals.failures, totals.warned, totals.unknown
+        )
+    }
+}
+
+/// Collects the files of a batch, see the [module-level documentation](self).
+///
+/// Files in `paths` are kept regardless of their extension. Directories are searched recursively
+/// for files with extension [`EXTENSION`], which are sorted. Files appear only once.
+pub fn files<P: Into<PathBuf>>(paths: impl IntoIterator<Item = P>) -> Res<Vec<PathBuf>> {
+    fn search(dir: &std::path::Path, res: &mut Vec<PathBuf>) -> Res<()> {
+        let entries = std::fs::read_dir(dir)
+            .chain_err(|| format!("while reading directory `{}`", dir.display()))?;
+        let mut paths = vec![];
+        for entry in entries {
+            let entry =
+                entry.chain_err(|| format!("while reading directory `{}`", dir.display()))?;
+            paths.push(entry.path())
+        }
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                search(&path, res)?
+            } else if path
+                .extension()
+                .map(|ext| ext == EXTENSION)
+                .unwrap_or(false)
+            {
+                res.push(path)
+            }
+        }
+        Ok(())
+    }
+
+    let mut res = vec![];
+    for path in paths {
+        let path = path.into();
+        if path.is_dir() {
+            search(&path, &mut res)?
+        } else {
+            res.push(path)
+        }
+    }
+    let mut known = Set::new();
+    res.retain(|path| known.insert(path.clone()));
+    Ok(res)
+}
+
+/// Reads, parses and checks a file.
+fn check_file(path: &std::path::Path, config: &Config) -> Res<Outcome> {
+    let content = std::fs::read_to_string(path)
+        .chain_err(|| format!("while reading `{}`", path.display()))?;
+    let sys = parse::trans(&content).chain_err(|| format!("while parsing `{}`", path.display()))?;
+    let mut outcome =
+        run(&sys, config).chain_err(|| format!("while checking `{}`", path.display()))?;
+    outcome.provenance = outcome
+        .provenance
+        .map(|provenance| provenance.input(path.display().to_string(), &content));
+    Ok(outcome)
+}
+
+/// Checks the systems of some files and directories, see the [module-level documentation](self).
+///
+/// When [`Config::tee`] is set, the solver interactions for the `n`-th file are teed in
+/// sub-directory `file_<n>`.
+///
+/// # Errors
+///
+/// - when a directory cannot be read.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::{self, batch::Status, filter::Filter}, rsmt2::SmtConf};
+/// # let dir = std::env::temp_dir().join(format!("mikino_batch_{}", std::process::id()));
+/// std::fs::create_dir_all(dir.join("sub")).unwrap();
+/// let model = "svars { x: int } init { x = 0 } trans { 'x = x + 1 } candidates { \"x\": x ≥ 0 }";
+/// std::fs::write(dir.join("a.mnk"), model).unwrap();
+/// std::fs::write(dir.join("sub").join("b.mnk"), "svars { x: int }").unwrap();
+/// std::fs::write(dir.join("notes.txt"), "not a model").unwrap();
+///
+/// // Let's not actually check anything in this example.
+/// let config = check::Config::new(SmtConf::z3("z3")).filter(Filter::new().exclude_name("*"));
+/// let report = check::batch(Some(&dir), &config).unwrap();
+/// let names: Vec<_> = report
+///     .files
+///     .iter()
+///     .map(|file| file.path.strip_prefix(&dir).unwrap().display().to_string())
+///     .collect();
+/// assert_eq!(names, vec!["a.mnk", "sub/b.mnk"]);
+/// assert!(matches!(report.files[0].status, Status::Checked(_)));
+/// assert!(matches!(report.files[1].status, Status::Error(_)));
+/// assert!(!report.is_success());
+/// // The report records the hash of the files checked.
+/// assert_eq!(report.provenance.inputs.len(), 1);
+///
+/// // Nothing is checked with an empty budget.
+/// let config = config.budget(std::time::Duration::from_secs(0));
+/// let report = check::batch(Some(&dir), &config).unwrap();
+/// assert_eq!(report.totals().skipped, 2);
+/// assert!(report.is_success());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn batch<P: Into<PathBuf>>(paths: impl IntoIterator<Item = P>, config: &Config) -> Res<Report> {
+    let start = Instant::now();
+    let files = files(paths)?;
+    let deadline = config.budget.map(|budget| start + budget);
+    let file_config = config.clone().jobs(1);
+
+    // Files left to check, in reverse order so that `pop` yields them in order.
+    let todo: Vec<(usize, &PathBuf)> = files.iter().enumerate().rev().collect();
+    let todo = Mutex::new(todo);
+    let results: Mutex<Map<usize, FileReport>> = Mutex::new(Map::new());
+
+    let work = || loop {
+        let next = todo.lock().expect("file queue lock poisoned").pop();
+        let (idx, path) = match next {
+            Some(next) => next,
+            None => break,
+        };
+
+        let file_start = Instant::now();
+        let status =
+            if deadline.map(|d| file_start >= d).unwrap_or(false) || config.cancel.is_cancelled() {
+                Status::Skipped
+            } else {
+                let res = (|| {
+                    let mut config = file_config.clone();
+                    // The file only gets what is left of the budget.
+                    config.budget = deadline.map(|d| d.saturating_duration_since(file_start));
+                    if let Some(tee) = config.tee.as_ref() {
+                        let tee = tee.join(format!("file_{}", idx));
+                        std::fs::create_dir_all(&tee).chain_err(|| {
+                            format!("while creating tee directory `{}`", tee.display())
+                        })?;
+                        config.tee = Some(tee);
+                    }
+                    check_file(path, &config)
+                })();
+                match res {
+                    Ok(outcome) => Status::Checked(outcome),
+                    Err(e) => Status::Error(e),
+                }
+            };
+
+        let _ = results.lock().expect("results lock poisoned").insert(
+            idx,
+            FileReport {
+                path: path.clone(),
+                status,
+                time: file_start.elapsed(),
+            },
+        );
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..std::cmp::min(std::cmp::max(config.jobs, 1), files.len()) {
+            let _ = scope.spawn(work);
+        }
+    });
+
+    let files: Vec<FileReport> = results
+        .into_inner()
+        .expect("results lock poisoned")
+        .into_values()
+        .collect();
+    let mut provenance = Provenance::of_config(config);
+    for file in files.iter() {
+        let name = file.path.display().to_string();
+        let hash = file
+            .outcome()
+            .and_then(|outcome| outcome.provenance.as_ref())
+            .and_then(|provenance| provenance.inputs.get(&name));
+        if let Some(hash) = hash {
+            let _ = provenance.inputs.insert(name, *hash);
+        }
+    }
+    Ok(Report {
+        files,
+        time: start.elapsed(),
+        provenance,
+    })
+}
@@ -0,0 +1,610 @@
+//! Interval analysis, invariant bounds for the arithmetic variables of a system.
+//!
+//! The analysis computes an over-approximation of the reachable values of each arithmetic
+//! variable as an [`Interval`], by abstract interpretation of the initial predicate and the
+//! transition relation. The fixpoint computation uses widening, which makes it fast and
+//! terminating, followed by a few narrowing steps to recover some precision.
+//!
+//! The analysis is cheap and incomplete: it only understands comparisons between a variable and an
+//! expression (possibly under negations, conjunctions and disjunctions), and linear arithmetic.
+//! Other constraints are ignored, which is sound.
+//!
+//! Bounds are invariants, so [`lemmas`] are always valid strengthening lemmas. They are offered by
+//! [`suggest::strengthenings`](super::suggest::strengthenings), and the [`Strengthen`]
+//! pre-processing pass conjoins them to the transition relation.
+
+crate::prelude!();
+
+use super::preprocess::Pass;
+use expr::{Cst, Expr, HasTyp, Op, PExpr, SExpr, SVar, Var};
+use trans::Sys;
+
+/// Number of plain iterations before widening kicks in.
+const WIDEN_AFTER: usize = 3;
+/// Number of narrowing steps after the widening fixpoint.
+const NARROWING_STEPS: usize = 2;
+
+/// An interval, `None` bounds are infinite.
+///
+/// An interval with `lo > hi` is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interval {
+    /// Lower bound, inclusive.
+    pub lo: Option<Rat>,
+    /// Upper bound, inclusive.
+    pub hi: Option<Rat>,
+}
+impl fmt::Display for Interval {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match &self.lo {
+            Some(lo) => write!(fmt, "[{}, ", lo)?,
+            None => write!(fmt, "(-∞, ")?,
+        }
+        match &self.hi {
+            Some(hi) => write!(fmt, "{}]", hi),
+            None => write!(fmt, "+∞)"),
+        }
+    }
+}
+impl Interval {
+    /// Unbounded interval.
+    pub fn top() -> Self {
+        Self { lo: None, hi: None }
+    }
+    /// Singleton interval.
+    pub fn cst(val: Rat) -> Self {
+        Self {
+            lo: Some(val.clone()),
+            hi: Some(val),
+        }
+    }
+
+    /// True if the interval is empty.
+    pub fn is_empty(&self) -> bool {
+        matches!((&self.lo, &self.hi), (Some(lo), Some(hi)) if lo > hi)
+    }
+    /// True if the interval is unbounded.
+    pub fn is_top(&self) -> bool {
+        self.lo.is_none() && self.hi.is_none()
+    }
+    /// Value of the interval, if it is a singleton.
+    pub fn as_cst(&self) -> Option<&Rat> {
+        match (&self.lo, &self.hi) {
+            (Some(lo), Some(hi)) if lo == hi => Some(lo),
+            _ => None,
+        }
+    }
+
+    /// Smallest interval containing both intervals.
+    pub fn join(&self, that: &Self) -> Self {
+        if self.is_empty() {
+            return that.clone();
+        } else if that.is_empty() {
+            return self.clone();
+        }
+        let lo = match (&self.lo, &that.lo) {
+            (Some(l1), Some(l2)) => Some(std::cmp::min(l1, l2).clone()),
+            _ => None,
+        };
+        let hi = match (&self.hi, &that.hi) {
+            (Some(h1), Some(h2)) => Some(std::cmp::max(h1, h2).clone()),
+            _ => None,
+        };
+        Self { lo, hi }
+    }
+    /// Intersection of two intervals.
+    pub fn meet(&self, that: &Self) -> Self {
+        let lo = match (&self.lo, &that.lo) {
+            (Some(l1), Some(l2)) => Some(std::cmp::max(l1, l2).clone()),
+            (lo, None) | (None, lo) => lo.clone(),
+        };
+        let hi = match (&self.hi, &that.hi) {
+            (Some(h1), Some(h2)) => Some(std::cmp::min(h1, h2).clone()),
+            (hi, None) | (None, hi) => hi.clone(),
+        };
+        Self { lo, hi }
+    }
+    /// Widening, bounds that grow from `self` to `next` become infinite.
+    fn widen(&self, next: &Self) -> Self {
+        if self.is_empty() {
+            return next.clone();
+        }
+        let lo = match (&self.lo, &next.lo) {
+            (Some(l1), Some(l2)) if l2 >= l1 => Some(l1.clone()),
+            _ => None,
+        };
+        let hi = match (&self.hi, &next.hi) {
+            (Some(h1), Some(h2)) if h2 <= h1 => Some(h1.clone()),
+            _ => None,
+        };
+        Self { lo, hi }
+    }
+    /// Rounds the bounds inwards to integers.
+    fn to_int(&self) -> Self {
+        Self {
+            lo: self.lo.as_ref().map(|lo| lo.ceil()),
+            hi: self.hi.as_ref().map(|hi| hi.floor()),
+        }
+    }
+
+    /// Sum of two intervals.
+    fn add(&self, that: &Self) -> Self {
+        let add = |b1: &Option<Rat>, b2: &Option<Rat>| match (b1, b2) {
+            (Some(b1), Some(b2)) => Some(b1 + b2),
+            _ => None,
+        };
+        Self {
+            lo: add(&self.lo, &that.lo),
+            hi: add(&self.hi, &that.hi),
+        }
+    }
+    /// Opposite of an interval.
+    fn neg(&self) -> Self {
+        Self {
+            lo: self.hi.as_ref().map(|hi| -hi),
+            hi: self.lo.as_ref().map(|lo| -lo),
+        }
+    }
+    /// Product of two intervals.
+    fn mul(&self, that: &Self) -> Self {
+        let zero = Rat::zero();
+        // Product of two bounds, `None` for infinity of unknown sign.
+        let mul = |b1: &Option<Rat>, b2: &Option<Rat>| match (b1, b2) {
+            (Some(b1), Some(b2)) => Some(b1 * b2),
+            (Some(b), None) | (None, Some(b)) if *b == zero => Some(zero.clone()),
+            _ => None,
+        };
+        let bounded = |i: &Self| i.lo.is_some() && i.hi.is_some();
+        if !bounded(self) || !bounded(that) {
+            // Only handle constant factors for unbounded intervals.
+            return match (self.as_cst(), that.as_cst()) {
+                (Some(k), _) => that.scale(k),
+                (_, Some(k)) => self.scale(k),
+                _ => Self::top(),
+            };
+        }
+        let prods: Vec<Rat> = [(&self.lo, &that.lo), (&self.lo, &that.hi)]
+            .iter()
+            .chain([(&self.hi, &that.lo), (&self.hi, &that.hi)].iter())
+            .filter_map(|(b1, b2)| mul(b1, b2))
+            .collect();
+        Self {
+            lo: prods.iter().min().cloned(),
+            hi: prods.iter().max().cloned(),
+        }
+    }
+    /// Product by a constant.
+    fn scale(&self, k: &Rat) -> Self {
+        if k.is_zero() {
+            return Self::cst(Rat::zero());
+        }
+        let scaled = Self {
+            lo: self.lo.as_ref().map(|lo| lo * k),
+            hi: self.hi.as_ref().map(|hi| hi * k),
+        };
+        if *k < Rat::zero() {
+            Self {
+                lo: scaled.hi,
+                hi: scaled.lo,
+            }
+        } else {
+            scaled
+        }
+    }
+}
+
+/// Variables the analysis works on, identified by identifier and step (`0` or `1`).
+trait Slot: HasTyp {
+    /// Identifier and step.
+    fn slot(&self) -> (String, Unroll);
+}
+impl Slot for Var {
+    fn slot(&self) -> (String, Unroll) {
+        (self.id().to_string(), 0)
+    }
+}
+impl Slot for SVar {
+    fn slot(&self) -> (String, Unroll) {
+        (self.id().to_string(), if self.is_next() { 1 } else { 0 })
+    }
+}
+
+/// Abstract state, arithmetic variables missing from the map are unbounded.
+type AState = Map<(String, Unroll), Interval>;
+
+/// Abstract evaluation of an arithmetic expression.
+fn eval<V: Slot>(expr: &PExpr<V>, state: &AState) -> Interval {
+    match expr {
+        PExpr::Cst(Cst::I(i)) => Interval::cst(Rat::from_integer(i.clone())),
+        PExpr::Cst(Cst::R(r)) => Interval::cst(r.clone()),
+        PExpr::Cst(Cst::B(_) | Cst::BV(_, _) | Cst::Array { .. } | Cst::Enum { .. }) => {
+            Interval::top()
+        }
+        PExpr::Var(var) => state
+            .get(&var.slot())
+            .cloned()
+            .unwrap_or_else(Interval::top),
+        PExpr::App { op, args, .. } => {
+            let mut vals = args.iter().map(|arg| eval(arg, state));
+            match op {
+                Op::Add => vals.fold(Interval::cst(Rat::zero()), |acc, val| acc.add(&val)),
+                Op::Mul => vals.fold(Interval::cst(Rat::one()), |acc, val| acc.mul(&val)),
+                Op::Sub if args.len() == 1 => vals
+                    .next()
+                    .map(|val| val.neg())
+                    .unwrap_or_else(Interval::top),
+                Op::Sub => {
+                    let head = vals.next().unwrap_or_else(Interval::top);
+                    vals.fold(head, |acc, val| acc.add(&val.neg()))
+                }
+                Op::Div if args.len() == 2 => {
+                    let (num, den) = (eval(&args[0], state), eval(&args[1], state));
+                    match den.as_cst() {
+                        Some(k) if !k.is_zero() => num.scale(&k.recip()),
+                        _ => Interval::top(),
+                    }
+                }
+                Op::Mod if args.len() == 2 => match eval(&args[1], state).as_cst() {
+                    Some(k) if !k.is_zero() => Interval {
+                        lo: Some(Rat::zero()),
+                        hi: Some(num::Signed::abs(k) - Rat::one()),
+                    },
+                    _ => Interval::top(),
+                },
+                Op::Ite if args.len() == 3 => {
+                    let thn = refine(&args[0], true, state.clone()).map(|s| eval(&args[1], &s));
+                    let els = refine(&args[0], false, state.clone()).map(|s| eval(&args[2], &s));
+                    match (thn, els) {
+                        (Some(thn), Some(els)) => thn.join(&els),
+                        (Some(val), None) | (None, Some(val)) => val,
+                        (None, None) => Interval {
+                            lo: Some(Rat::one()),
+                            hi: Some(Rat::zero()),
+                        },
+                    }
+                }
+                _ => Interval::top(),
+            }
+        }
+    }
+}
+
+/// Constrains a variable of an abstract state, `None` if the result is empty.
+fn constrain<V: Slot>(var: &V, constraint: Interval, mut state: AState) -> Option<AState> {
+    let typ = var.typ();
+    if !typ.is_arith() {
+        return Some(state);
+    }
+    let entry = state.entry(var.slot()).or_insert_with(Interval::top);
+    *entry = entry.meet(&constraint);
+    if typ == Typ::Int {
+        *entry = entry.to_int()
+    }
+    if entry.is_empty() {
+        None
+    } else {
+        Some(state)
+    }
+}
+
+/// Refines an abstract state with a boolean expression (negated if `!pos`).
+///
+/// Returns `None` if the refined state is empty.
+fn refine<V: Slot>(expr: &PExpr<V>, pos: bool, state: AState) -> Option<AState> {
+    let (op, args) = match expr {
+        PExpr::Cst(Cst::B(b)) => return if *b == pos { Some(state) } else { None },
+        PExpr::App { op, args, .. } => (op, args),
+        _ => return Some(state),
+    };
+    match (op, pos) {
+        (Op::Not, _) => refine(&args[0], !pos, state),
+        (Op::And, true) | (Op::Or, false) => args
+            .iter()
+            .try_fold(state, |state, arg| refine(arg, pos, state)),
+        (Op::Or, true) | (Op::And, false) => {
+            let mut res: Option<AState> = None;
+            for arg in args {
+                if let Some(refined) = refine(arg, pos, state.clone()) {
+                    res = Some(match res {
+                        None => refined,
+                        Some(res) => join(&res, &refined),
+                    })
+                }
+            }
+            res
+        }
+        (Op::Eq | Op::Ge | Op::Le | Op::Gt | Op::Lt, _) if args.len() == 2 => {
+            // Normalize to `lft op rgt` with `op` one of `=, ≥, ≤` (strict bounds are relaxed,
+            // except for integers).
+            let op = match (op, pos) {
+                (_, true) => op.clone(),
+                (Op::Ge, false) => Op::Lt,
+                (Op::Le, false) => Op::Gt,
+                (Op::Gt, false) => Op::Le,
+                (Op::Lt, false) => Op::Ge,
+                // Disequalities are not supported.
+                _ => return Some(state),
+            };
+            let mut state = Some(state);
+            for (lft, rgt, op) in [
+                (&args[0], &args[1], op.clone()),
+                (&args[1], &args[0], flip(op)),
+            ] {
+                if let (PExpr::Var(var), Some(current)) = (lft, state.as_ref()) {
+                    let val = eval(rgt, current);
+                    let one = if var.typ() == Typ::Int {
+                        Rat::one()
+                    } else {
+                        Rat::zero()
+                    };
+                    let constraint = match op {
+                        Op::Eq => val,
+                        Op::Ge => Interval {
+                            lo: val.lo,
+                            hi: None,
+                        },
+                        Op::Gt => Interval {
+                            lo: val.lo.map(|lo| lo + &one),
+                            hi: None,
+                        },
+                        Op::Le => Interval {
+                            lo: None,
+                            hi: val.hi,
+                        },
+                        Op::Lt => Interval {
+                            lo: None,
+                            hi: val.hi.map(|hi| hi - &one),
+                        },
+                        _ => unreachable!("comparison operators only"),
+                    };
+                    state = state.and_then(|state| constrain(var, constraint, state))
+                }
+            }
+            state
+        }
+        _ => Some(state),
+    }
+}
+
+/// Operator such that `a op b` iff `b (flip op) a`.
+fn flip(op: Op) -> Op {
+    match op {
+        Op::Ge => Op::Le,
+        Op::Le => Op::Ge,
+        Op::Gt => Op::Lt,
+        Op::Lt => Op::Gt,
+        op => op,
+    }
+}
+
+/// Join of two abstract states.
+fn join(s1: &AState, s2: &AState) -> AState {
+    s1.iter()
+        .filter_map(|(slot, i1)| s2.get(slot).map(|i2| (slot.clone(), i1.join(i2))))
+        .filter(|(_, i)| !i.is_top())
+        .collect()
+}
+
+/// Abstract initial states, over step `0`.
+fn init(sys: &Sys) -> Option<AState> {
+    let state = refine(sys.init(), true, AState::new())?;
+    // Second pass, for constraints that depend on variables constrained later in the first pass.
+    refine(sys.init(), true, state)
+}
+
+/// Abstract successors of an abstract state over step `0`, also over step `0`.
+fn post(sys: &Sys, state: &AState) -> Option<AState> {
+    let state = refine(sys.trans(), true, state.clone())?;
+    let state = refine(sys.trans(), true, state)?;
+    Some(
+        state
+            .into_iter()
+            .filter(|((_, step), _)| *step == 1)
+            .map(|((id, _), i)| ((id, 0), i))
+            .collect(),
+    )
+}
+
+/// Computes invariant bounds for the arithmetic variables of a system.
+///
+/// Variables without any bound do not appear in the result. If the analysis finds that the
+/// system has no initial state, all variables get an empty interval.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::bounds, parse};
+/// let sys = parse::trans(r#"
+/// svars { cnt lim: int, r: rat }
+/// init { cnt = 0, lim ≥ 3, r = 0.0 }
+/// trans {
+///     'lim = lim,
+///     'cnt = (if cnt < 10 { cnt + 1 } else { 0 }),
+///     'r = r - 1.0,
+/// }
+/// candidates { "cnt is not 11": ¬(cnt = 11) }
+/// "#).unwrap();
+/// let bounds: Vec<_> = bounds::analyze(&sys)
+///     .into_iter()
+///     .map(|(var, i)| format!("{}: {}", var, i))
+///     .collect();
+/// assert_eq!(bounds, vec!["cnt: [0, 10]", "lim: [3, +∞)", "r: (-∞, 0]"]);
+/// ```
+pub fn analyze(sys: &Sys) -> Map<Var, Interval> {
+    let start = match init(sys) {
+        Some(start) => start,
+        None => {
+            let empty = Interval {
+                lo: Some(Rat::one()),
+                hi: Some(Rat::zero()),
+            };
+            return sys
+                .decls()
+                .all()
+                .filter(|var| var.typ().is_arith())
+                .map(|var| (var, empty.clone()))
+                .collect();
+        }
+    };
+
+    let mut state = start.clone();
+    let mut iter = 0;
+    loop {
+        let next = match post(sys, &state) {
+            Some(next) => join(&state, &next),
+            None => state.clone(),
+        };
+        let next = if iter >= WIDEN_AFTER {
+            next.iter()
+                .filter_map(|(slot, i)| state.get(slot).map(|prev| (slot.clone(), prev.widen(i))))
+                .filter(|(_, i)| !i.is_top())
+                .collect()
+        } else {
+            next
+        };
+        if next == state {
+            break;
+        }
+        state = next;
+        iter += 1;
+    }
+
+    for _ in 0..NARROWING_STEPS {
+        let next = match post(sys, &state) {
+            Some(next) => join(&start, &next),
+            None => start.clone(),
+        };
+        state = state
+            .iter()
+            .map(|(slot, i)| {
+                let narrowed = match next.get(slot) {
+                    Some(n) => i.meet(n),
+                    None => i.clone(),
+                };
+                (slot.clone(), narrowed)
+            })
+            .chain(
+                next.iter()
+                    .filter(|(slot, _)| !state.contains_key(*slot))
+                    .map(|(slot, i)| (slot.clone(), i.clone())),
+            )
+            .collect();
+    }
+
+    sys.decls()
+        .all()
+        .filter_map(|var| {
+            let i = state.get(&(var.id().to_string(), 0))?.clone();
+            Some((var, i))
+        })
+        .collect()
+}
+
+/// Turns a bound into a constant of some type.
+fn bound_cst(typ: Typ, bound: &Rat) -> Cst {
+    if typ == Typ::Int {
+        Cst::int(bound.to_integer())
+    } else {
+        Cst::rat(bound.clone())
+    }
+}
+
+/// Bound constraints over some variables.
+fn constraints<V: HasTyp + Clone>(
+    bounds: &Map<Var, Interval>,
+    mut var: impl FnMut(&Var) -> Option<V>,
+) -> Res<Vec<PExpr<V>>> {
+    let mut res = vec![];
+    for (v, i) in bounds {
+        let pv = match var(v) {
+            Some(pv) => PExpr::new_var(pv),
+            None => continue,
+        };
+        if let Some(val) = i.as_cst() {
+            res.push(PExpr::new_op(
+                Op::Eq,
+                vec![pv, bound_cst(v.typ(), val).into()],
+            )?);
+            continue;
+        }
+        if let Some(lo) = &i.lo {
+            res.push(PExpr::new_op(
+                Op::Ge,
+                vec![pv.clone(), bound_cst(v.typ(), lo).into()],
+            )?)
+        }
+        if let Some(hi) = &i.hi {
+            res.push(PExpr::new_op(
+                Op::Le,
+                vec![pv, bound_cst(v.typ(), hi).into()],
+            )?)
+        }
+    }
+    Ok(res)
+}
+
+/// Invariant bounds of a system as lemmas, see [`analyze`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::bounds, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let lemmas: Vec<_> = bounds::lemmas(&sys)
+///     .unwrap()
+///     .into_iter()
+///     .map(|lemma| lemma.to_string())
+///     .collect();
+/// assert_eq!(lemmas, vec!["(>= cnt 0)"]);
+/// ```
+pub fn lemmas(sys: &Sys) -> Res<Vec<Expr>> {
+    constraints(&analyze(sys), |var| Some(var.clone()))
+}
+
+/// Conjoins the invariant bounds of a system to its transition relation, on both states.
+///
+/// The bounds are invariants so this does not change the reachable states, but it strengthens
+/// the step check.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::{bounds::Strengthen, preprocess::Pipeline}, parse};
+/// let sys = parse::trans(
+///     "svars { cnt: int } init { cnt = 0 } trans { 'cnt = cnt + 1 } \
+///     candidates { \"cnt is not -1\": ¬(cnt = -1) }",
+/// )
+/// .unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(Strengthen);
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(
+///     sys.trans().to_string(),
+///     "(and (= cnt@1 (+ cnt@0 1)) (>= cnt@0 0) (>= cnt@1 0))",
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strengthen;
+impl Pass for Strengthen {
+    fn name(&self) -> &str {
+        "bounds"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        let bounds = analyze(sys);
+        let decls = sys.decls();
+        let mut conjs = match sys.trans() {
+            PExpr::App {
+                op: Op::And, args, ..
+            } => args.clone(),
+            trans => vec![trans.clone()],
+        };
+        conjs.extend(constraints(&bounds, |var| decls.get_curr_var(var.id()))?);
+        conjs.extend(constraints(&bounds, |var| decls.get_next_var(var.id()))?);
+        Ok(Sys::new(
+            decls.clone(),
+            sys.init().clone(),
+            SExpr::new_op(Op::And, conjs)?,
+            sys.po_s().clone(),
+        ))
+    }
+}
@@ -0,0 +1,173 @@
+//! Cache of proved candidates.
+//!
+//! A [`Cache`] stores *certificates* for proved candidates in a directory. A certificate is keyed
+//! by a hash of the *slice* of the system the proof depends on: the declarations, the initial and
+//! transition predicates, and the candidate itself. It also lists the *lemmas* of the proof, the
+//! other candidates that were assumed in the step check.
+//!
+//! A candidate is considered proved by the cache if it has a certificate for its slice and all the
+//! lemmas of this certificate are candidates of the current system with the exact same definition.
+//! This is sound: the candidate and its lemmas all hold in the initial states and their
+//! conjunction is inductive, regardless of the other candidates.
+//!
+//! Hashes are computed on the textual representation of the system with FNV-1a, so that they are
+//! stable across runs, platforms, and compiler versions.
+//!
+//! Certificates also record the [`Provenance`] of the cache, if any, as comments.
+
+crate::prelude!();
+
+use crate::meta::Provenance;
+use trans::Sys;
+
+/// Header of certificate files.
+const HEADER: &str = "mikino certificate v1";
+
+/// FNV-1a hash of some strings.
+pub(crate) fn fnv<'a>(strs: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for s in strs {
+        // Separator, avoids collisions between `("ab", "c")` and `("a", "bc")`.
+        for byte in s.bytes().chain(Some(0xff)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Stores certificates for proved candidates, see the [module-level documentation](self).
+#[derive(Debug, Clone)]
+pub struct Cache {
+    /// Cache directory.
+    dir: PathBuf,
+    /// Provenance recorded in the certificates.
+    provenance: Option<Provenance>,
+}
+impl Cache {
+    /// Constructor, creates the cache directory if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Res<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .chain_err(|| format!("while creating cache directory `{}`", dir.display()))?;
+        Ok(Self {
+            dir,
+            provenance: None,
+        })
+    }
+    /// Sets the provenance recorded in the certificates.
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Cache directory.
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    /// Hash of a candidate, name and definition.
+    fn candidate_hash(sys: &Sys, candidate: &str) -> Res<u64> {
+        let po = match sys.po_s().get(candidate) {
+            Some(po) => po,
+            None => bail!("unknown candidate `{}`", candidate),
+        };
+        Ok(fnv([candidate, &po.to_string()]))
+    }
+
+    /// Path to the certificate of a candidate.
+    fn cert_path(&self, sys: &Sys, candidate: &str) -> Res<PathBuf> {
+        let slice = fnv([
+            &sys.decls().to_string() as &str,
+            &sys.init().to_string(),
+            &sys.trans().to_string(),
+        ]);
+        let candidate = Self::candidate_hash(sys, candidate)?;
+        let key = fnv([
+            &format!("{:016x}", slice) as &str,
+            &format!("{:016x}", candidate),
+        ]);
+        Ok(self.dir.join(format!("{:016x}.cert", key)))
+    }
+
+    /// True if the cache has a valid certificate for a candidate.
+    ///
+    /// Unreadable or ill-formed certificates are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::cache::Cache, parse};
+    /// let dir = std::env::temp_dir().join(format!("mikino_cache_{}", std::process::id()));
+    /// let cache = Cache::new(&dir).unwrap();
+    ///
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let lemmas = vec!["if reset then cnt is 0".to_string()];
+    /// assert!(!cache.is_proved(&sys, "cnt is positive").unwrap());
+    /// cache.store_proved(&sys, "cnt is positive", &lemmas).unwrap();
+    /// assert!(cache.is_proved(&sys, "cnt is positive").unwrap());
+    ///
+    /// // Changing a lemma invalidates the certificate.
+    /// let txt = mikino_api::TRANS_DEMO.replace("reset ⇒ cnt = 0", "reset ⇒ cnt ≥ 0");
+    /// let changed = parse::trans(&txt).unwrap();
+    /// assert!(!cache.is_proved(&changed, "cnt is positive").unwrap());
+    ///
+    /// // So does changing the transition relation.
+    /// let txt = mikino_api::TRANS_DEMO.replace("cnt + 1", "cnt + 2");
+    /// let changed = parse::trans(&txt).unwrap();
+    /// assert!(!cache.is_proved(&changed, "cnt is positive").unwrap());
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn is_proved(&self, sys: &Sys, candidate: &str) -> Res<bool> {
+        let path = self.cert_path(sys, candidate)?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return Ok(false),
+        };
+        let mut lines = content.lines();
+        if lines.next() != Some(HEADER) {
+            return Ok(false);
+        }
+
+        let mut known = Set::new();
+        for name in sys.po_s().keys() {
+            let _ = known.insert(format!("{:016x}", Self::candidate_hash(sys, name)?));
+        }
+        for line in lines {
+            match line.strip_prefix("lemma ") {
+                Some(lemma) if known.contains(lemma.trim()) => (),
+                Some(_) => return Ok(false),
+                // Comment or unknown entry.
+                None => (),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Stores a certificate for a proved candidate, with the lemmas assumed in its proof.
+    pub fn store_proved<'a>(
+        &self,
+        sys: &Sys,
+        candidate: &str,
+        lemmas: impl IntoIterator<Item = &'a String>,
+    ) -> Res<()> {
+        let path = self.cert_path(sys, candidate)?;
+        let mut content = format!("{}\n# candidate `{}`\n", HEADER, candidate);
+        if let Some(provenance) = self.provenance.as_ref() {
+            for line in provenance.to_string().lines() {
+                content.push_str(&format!("# {}\n", line));
+            }
+        }
+        for lemma in lemmas {
+            if lemma != candidate {
+                content.push_str(&format!(
+                    "lemma {:016x}\n",
+                    Self::candidate_hash(sys, lemma)?
+                ));
+            }
+        }
+        std::fs::write(&path, content)
+            .chain_err(|| format!("while writing certificate `{}`", path.display()))?;
+        Ok(())
+    }
+}
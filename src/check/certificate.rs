@@ -0,0 +1,374 @@
+//! Machine-checkable certificates for candidates proved by induction.
+//!
+//! A [`Certificate`] records an *inductive invariant*, the conjunction of some candidates, along
+//! with two SMT-LIB 2 queries:
+//!
+//! - the *base* query, unsatisfiable if the invariant holds in all initial states, and
+//! - the *step* query, unsatisfiable if the invariant is preserved by all transitions.
+//!
+//! Together, their unsatisfiability witnesses that all the candidates of the invariant hold in all
+//! reachable states. The certificate file is a standalone SMT-LIB 2 script that any solver can
+//! check independently of mikino: it should answer `unsat` twice. [`Certificate::validate`]
+//! re-validates a certificate against a system later on, for instance for audit trails.
+//!
+//! Unlike the certificates of the [cache](super::cache), these certificates are self-contained:
+//! they hold the actual proof obligations, not hashes of the system.
+//!
+//! When [`Config::certificate`] is set, [`check::run`](super::run()) writes a certificate for the
+//! proved candidates, see [`generate`].
+//!
+//! [`Config::certificate`]: super::Config::certificate
+
+crate::prelude!();
+
+use super::{Base, InternalChecker, Step};
+use crate::{meta::Provenance, solver::smt2};
+use expr::{Expr, Op};
+use trans::Sys;
+
+/// Header of certificate files.
+const HEADER: &str = "; mikino proof certificate v1";
+/// Comment introducing the base query.
+const BASE: &str = "; base case, unsat if the invariant holds in all initial states";
+/// Comment introducing the step query.
+const STEP: &str = "; step case, unsat if all transitions preserve the invariant";
+/// Prefix of the comments listing the candidates of the invariant.
+const CANDIDATE: &str = "; candidate `";
+
+/// Certificate for some candidates of a system, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// Candidates the invariant is the conjunction of.
+    candidates: Set<String>,
+    /// Declarations shared by the queries.
+    decls: String,
+    /// Base query.
+    base: String,
+    /// Step query.
+    step: String,
+    /// Provenance, written as comments.
+    provenance: Option<Provenance>,
+}
+impl Certificate {
+    /// Certificate for some candidates, does not check anything.
+    ///
+    /// See [`generate`] to produce a certificate for candidates that are not inductive on their
+    /// own.
+    ///
+    /// # Errors
+    ///
+    /// - when some candidate does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::certificate::Certificate, parse};
+    /// let sys = parse::trans(r#"
+    /// svars { cnt: int }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = cnt + 1 }
+    /// candidates { "positive": cnt ≥ 0 }
+    /// "#).unwrap();
+    /// let cert = Certificate::new(&sys, Some("positive")).unwrap();
+    /// assert_eq!(
+    ///     cert.to_string(),
+    ///     "\
+    /// ; mikino proof certificate v1
+    /// ; candidate `positive`
+    /// (declare-const cnt@0 Int)
+    /// (declare-const cnt@1 Int)
+    /// ; base case, unsat if the invariant holds in all initial states
+    /// (push 1)
+    /// (assert (= cnt@0 0))
+    /// (assert (not (>= cnt@0 0)))
+    /// (check-sat)
+    /// (pop 1)
+    /// ; step case, unsat if all transitions preserve the invariant
+    /// (push 1)
+    /// (assert (>= cnt@0 0))
+    /// (assert (= cnt@1 (+ cnt@0 1)))
+    /// (assert (not (>= cnt@1 0)))
+    /// (check-sat)
+    /// (pop 1)
+    /// ",
+    /// );
+    /// ```
+    pub fn new<Str: AsRef<str>>(sys: &Sys, candidates: impl IntoIterator<Item = Str>) -> Res<Self> {
+        let candidates: Set<String> = candidates
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect();
+        let inv = Self::invariant_of(sys, &candidates)?;
+        let not_inv = Expr::new_op(Op::Not, vec![inv.clone()])?;
+
+        let mut base = smt2::assert(sys.init(), 0)?;
+        base.push_str(&smt2::assert(&not_inv, 0)?);
+        let mut step = smt2::assert(&inv, 0)?;
+        step.push_str(&smt2::assert(sys.trans(), 0)?);
+        step.push_str(&smt2::assert(&not_inv, 1)?);
+
+        Ok(Self {
+            candidates,
+            decls: smt2::declare(sys.decls(), Some(&[0, 1]))?,
+            base,
+            step,
+            provenance: None,
+        })
+    }
+
+    /// Sets the provenance written in the certificate.
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Conjunction of some candidates of a system.
+    fn invariant_of(sys: &Sys, candidates: &Set<String>) -> Res<Expr> {
+        let mut conj = Vec::with_capacity(candidates.len());
+        for name in candidates {
+            match sys.po_s().get(name) {
+                Some(po) => conj.push(po.clone()),
+                None => bail!("unknown candidate `{}`", name),
+            }
+        }
+        match conj.len() {
+            0 => Ok(Expr::new_cst(true.into())),
+            1 => Ok(conj.pop().expect("[unreachable] one candidate")),
+            _ => Expr::new_op(Op::And, conj),
+        }
+    }
+
+    /// Candidates certified.
+    pub fn candidates(&self) -> &Set<String> {
+        &self.candidates
+    }
+    /// Inductive invariant, the conjunction of the candidates.
+    ///
+    /// # Errors
+    ///
+    /// - when some candidate does not exist in `sys`.
+    pub fn invariant(&self, sys: &Sys) -> Res<Expr> {
+        Self::invariant_of(sys, &self.candidates)
+    }
+
+    /// Parses a certificate.
+    ///
+    /// Comments other than the ones listing the candidates are ignored, the provenance is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::certificate::Certificate, parse};
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let cert = Certificate::new(&sys, ["cnt is positive", "if reset then cnt is 0"]).unwrap();
+    /// assert_eq!(Certificate::of_str(&cert.to_string()).unwrap(), cert);
+    ///
+    /// let err = Certificate::of_str("(check-sat)").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "illegal certificate, expected header `; mikino proof certificate v1`",
+    /// );
+    /// ```
+    pub fn of_str(txt: &str) -> Res<Self> {
+        let mut lines = txt.lines();
+        if lines.next() != Some(HEADER) {
+            bail!("illegal certificate, expected header `{}`", HEADER)
+        }
+        let mut candidates = Set::new();
+        let (mut decls, mut base, mut step) = (String::new(), String::new(), String::new());
+        // Part being read, declarations until the base query.
+        let mut current = &mut decls;
+        for line in lines {
+            if line == BASE {
+                current = &mut base
+            } else if line == STEP {
+                current = &mut step
+            } else if let Some(name) = line.strip_prefix(CANDIDATE) {
+                match name.strip_suffix('`') {
+                    Some(name) => {
+                        let _ = candidates.insert(name.to_string());
+                    }
+                    None => bail!("illegal certificate, ill-formed candidate line `{}`", line),
+                }
+            } else if !line.starts_with(';') {
+                current.push_str(line);
+                current.push('\n');
+            }
+        }
+        if base.is_empty() || step.is_empty() {
+            bail!("illegal certificate, expected a base query and a step query")
+        }
+        for query in [&mut base, &mut step] {
+            let stripped = query
+                .strip_prefix("(push 1)\n")
+                .and_then(|query| query.strip_suffix("(check-sat)\n(pop 1)\n"))
+                .map(String::from);
+            match stripped {
+                Some(stripped) => *query = stripped,
+                None => bail!("illegal certificate, ill-formed query"),
+            }
+        }
+        Ok(Self {
+            candidates,
+            decls,
+            base,
+            step,
+            provenance: None,
+        })
+    }
+
+    /// Loads a certificate from a file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Res<Self> {
+        let path = path.as_ref();
+        let txt = std::fs::read_to_string(path)
+            .chain_err(|| format!("while reading certificate `{}`", path.display()))?;
+        Self::of_str(&txt).chain_err(|| format!("while loading certificate `{}`", path.display()))
+    }
+    /// Writes a certificate to a file.
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> Res<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string())
+            .chain_err(|| format!("while writing certificate `{}`", path.display()))?;
+        Ok(())
+    }
+
+    /// Re-validates a certificate against a system.
+    ///
+    /// Checks that the queries of the certificate are the ones of `sys`, and that the solver
+    /// answers `unsat` to both of them.
+    ///
+    /// # Errors
+    ///
+    /// - when some candidate of the certificate does not exist in `sys`,
+    /// - when the queries do not match `sys`, *e.g.* because its transition predicate changed,
+    /// - when some query is satisfiable, or
+    /// - when the solver fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mikino_api::{check::certificate::Certificate, parse, rsmt2::SmtConf};
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let cert = Certificate::new(&sys, ["cnt is positive", "if reset then cnt is 0"]).unwrap();
+    /// cert.validate(&sys, SmtConf::z3("z3")).unwrap();
+    ///
+    /// let txt = mikino_api::TRANS_DEMO.replace("cnt + 1", "cnt - 1");
+    /// let changed = parse::trans(&txt).unwrap();
+    /// let err = cert.validate(&changed, SmtConf::z3("z3")).unwrap_err();
+    /// assert_eq!(err.to_string(), "the step query of the certificate does not match the system");
+    ///
+    /// let cert = Certificate::new(&sys, Some("cnt is positive")).unwrap();
+    /// let err = cert.validate(&sys, SmtConf::z3("z3")).unwrap_err();
+    /// assert_eq!(err.to_string(), "the step query of the certificate is satisfiable");
+    /// ```
+    pub fn validate(&self, sys: &Sys, conf: SmtConf) -> Res<()> {
+        let expected = Self::new(sys, &self.candidates)?;
+        if expected.decls != self.decls {
+            bail!("the declarations of the certificate do not match the system")
+        }
+        if expected.base != self.base {
+            bail!("the base query of the certificate does not match the system")
+        }
+        if expected.step != self.step {
+            bail!("the step query of the certificate does not match the system")
+        }
+
+        let inv = self.invariant(sys)?;
+        let not_inv = Expr::new_op(Op::Not, vec![inv.clone()])?;
+        let mut checker = InternalChecker::new(sys, conf, None)?;
+        checker.declare_vars(0)?;
+        checker.declare_vars(1)?;
+
+        checker.solver().push(1)?;
+        checker.assert_init()?;
+        checker.assert_expr(&not_inv, 0)?;
+        if checker.check_sat().chain_err(|| "during base query")? {
+            bail!("the base query of the certificate is satisfiable")
+        }
+        checker.solver().pop(1)?;
+
+        checker.solver().push(1)?;
+        checker.assert_expr(&inv, 0)?;
+        checker.assert_trans(0)?;
+        checker.assert_expr(&not_inv, 1)?;
+        if checker.check_sat().chain_err(|| "during step query")? {
+            bail!("the step query of the certificate is satisfiable")
+        }
+        checker.solver().pop(1)?;
+
+        checker.solver().kill()?;
+        Ok(())
+    }
+}
+impl fmt::Display for Certificate {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "{}", HEADER)?;
+        if let Some(provenance) = self.provenance.as_ref() {
+            for line in provenance.to_string().lines() {
+                writeln!(fmt, "; {}", line)?
+            }
+        }
+        for name in self.candidates.iter() {
+            writeln!(fmt, "{}{}`", CANDIDATE, name)?
+        }
+        write!(fmt, "{}", self.decls)?;
+        for (comment, query) in [(BASE, &self.base), (STEP, &self.step)] {
+            writeln!(fmt, "{}\n(push 1)", comment)?;
+            write!(fmt, "{}", query)?;
+            writeln!(fmt, "(check-sat)\n(pop 1)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Certificate for the largest subset of some candidates that is inductive on its own.
+///
+/// Drops the candidates that do not hold in the initial states, and then the ones that are not
+/// preserved by the transitions assuming the others, until the remaining ones are inductive. The
+/// candidates dropped are not in [`Certificate::candidates`]: typically, candidates proved by
+/// engines that strengthen them such as [PDR](super::pdr::Pdr).
+///
+/// # Errors
+///
+/// - when some candidate does not exist, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::certificate, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let names: Vec<_> = sys.po_s().keys().cloned().collect();
+/// let cert = certificate::generate(&sys, &names, SmtConf::z3("z3")).unwrap();
+/// cert.validate(&sys, SmtConf::z3("z3")).unwrap();
+/// cert.write("demo.cert").unwrap();
+/// ```
+pub fn generate<'a>(
+    sys: &Sys,
+    candidates: impl IntoIterator<Item = &'a String>,
+    conf: SmtConf,
+) -> Res<Certificate> {
+    let candidates: Set<&String> = candidates.into_iter().collect();
+    for name in candidates.iter() {
+        if !sys.po_s().contains_key(*name) {
+            bail!("unknown candidate `{}`", name)
+        }
+    }
+    let sys = sys.restrict(|name| candidates.contains(name));
+    let base = Base::new(&sys, conf.clone(), None)?
+        .check()
+        .chain_err(|| "during base check")?;
+    let step_sys = sys.restrict(|name| base.okay.contains(name));
+    let okay: Vec<String> = if step_sys.po_s().is_empty() {
+        vec![]
+    } else {
+        Step::new(&step_sys, conf, None)?
+            .check()
+            .chain_err(|| "during step check")?
+            .okay
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    };
+    Certificate::new(&sys, okay)
+}
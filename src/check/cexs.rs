@@ -2,26 +2,149 @@
 
 crate::prelude!();
 
-use expr::{Cst, Typ, Var};
+use super::trace::{json_str, Trace, TraceFilter, ValDiff};
+use expr::{display::Formatters, Cst, Typ, Var};
 
 /// A counterexample.
 #[derive(Debug, Clone)]
 pub struct Cex {
     /// Trace of values for each variable, organized by steps.
-    pub trace: Map<Unroll, Map<Var, Cst>>,
+    pub trace: Trace,
     /// Unexpected variables produced by Z3.
     ///
     /// Z3 can produce additional variables when asked for a model. This can happen when there is a
     /// potential division by zero for instance.
     pub unexpected: Map<String, String>,
+    /// Reason why the counterexample is partial, `None` if it is complete.
+    ///
+    /// See [`Self::populate_or_salvage`].
+    pub incomplete: Option<String>,
 }
 impl Cex {
     /// Constructor.
     pub fn new() -> Self {
         Self {
-            trace: Map::new(),
+            trace: Trace::new(),
             unexpected: Map::new(),
+            incomplete: None,
+        }
+    }
+
+    /// True if the counterexample is complete, see [`Self::incomplete`].
+    pub fn is_complete(&self) -> bool {
+        self.incomplete.is_none()
+    }
+
+    /// Number of steps of the counterexample.
+    pub fn len(&self) -> usize {
+        self.trace.len()
+    }
+    /// True if the counterexample has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.trace.is_empty()
+    }
+    /// Last step of the counterexample, `None` if it has no steps.
+    pub fn last_step(&self) -> Option<Unroll> {
+        self.trace.keys().next_back().cloned()
+    }
+
+    /// Steps of the counterexample, in order, with the values of the variables.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::cexs::Cex, expr::{Cst, Typ, Var}};
+    /// let (cnt, reset) = (Var::new("cnt", Typ::Int), Var::new("reset", Typ::Bool));
+    /// let mut cex = Cex::new();
+    /// for step in 0..3 {
+    ///     cex.insert(step, cnt.clone(), Cst::int(step)).unwrap();
+    ///     cex.insert(step, reset.clone(), Cst::bool(step == 2)).unwrap();
+    /// }
+    /// assert_eq!(cex.len(), 3);
+    /// assert_eq!(cex.last_step(), Some(2));
+    ///
+    /// let resets: Vec<_> = cex
+    ///     .steps()
+    ///     .filter(|(_, state)| state[&reset] == Cst::bool(true))
+    ///     .map(|(step, _)| step)
+    ///     .collect();
+    /// assert_eq!(resets, vec![2]);
+    ///
+    /// assert_eq!(cex.state(1).map(|state| state.len()), Some(2));
+    /// assert_eq!(cex.get(1, "cnt"), Some(&Cst::int(1)));
+    /// assert_eq!(cex.get(7, "cnt"), None);
+    ///
+    /// let cnts: Vec<_> = cex.values_of("cnt").map(|(_, val)| val.to_string()).collect();
+    /// assert_eq!(cnts, vec!["0", "1", "2"]);
+    /// assert_eq!(cex.vars().collect::<Vec<_>>(), vec![&cnt, &reset]);
+    ///
+    /// // `&Cex` iterates over the steps.
+    /// for (step, state) in &cex {
+    ///     assert_eq!(state[&cnt], Cst::int(step));
+    /// }
+    /// ```
+    pub fn steps(&self) -> impl Iterator<Item = (Unroll, &Map<Var, Cst>)> + '_ {
+        self.trace.iter().map(|(step, state)| (*step, state))
+    }
+    /// Values of the variables at some step.
+    pub fn state(&self, step: Unroll) -> Option<&Map<Var, Cst>> {
+        self.trace.get(&step)
+    }
+    /// Value of a variable at some step, see [`Trace::get_val`].
+    pub fn get(&self, step: Unroll, var: impl AsRef<str>) -> Option<&Cst> {
+        self.trace.get_val(step, var)
+    }
+    /// Values of a variable, in order, for the steps that have one.
+    pub fn values_of<'a>(
+        &'a self,
+        var: impl AsRef<str> + 'a,
+    ) -> impl Iterator<Item = (Unroll, &'a Cst)> + 'a {
+        self.trace
+            .keys()
+            .filter_map(move |step| Some((*step, self.trace.get_val(*step, var.as_ref())?)))
+    }
+    /// Variables appearing in the counterexample, in order, without duplicates.
+    pub fn vars(&self) -> impl Iterator<Item = &Var> + '_ {
+        let vars: Set<&Var> = self.trace.values().flat_map(|state| state.keys()).collect();
+        vars.into_iter()
+    }
+
+    /// JSON representation.
+    ///
+    /// Has the [trace](Trace::to_json), the values of the unexpected variables, and the reason why
+    /// the counterexample is incomplete (`null` if it is complete).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::cexs::Cex, expr::{Cst, Typ, Var}};
+    /// let mut cex = Cex::new();
+    /// cex.insert(0, Var::new("cnt", Typ::Int), Cst::int(3)).unwrap();
+    /// cex.insert_unexpected("div0", "7").unwrap();
+    /// assert_eq!(
+    ///     cex.to_json(),
+    ///     r#"{"trace":{"steps":[{"step":0,"vars":[{"var":"cnt","typ":"int","value":"3"}]}]},"unexpected":[{"var":"div0","value":"7"}],"incomplete":null}"#,
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut s = format!("{{\"trace\":{},\"unexpected\":[", self.trace.to_json());
+        for (idx, (var, val)) in self.unexpected.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!(
+                "{{\"var\":{},\"value\":{}}}",
+                json_str(var),
+                json_str(val)
+            ));
         }
+        s.push_str("],\"incomplete\":");
+        match self.incomplete.as_ref() {
+            Some(reason) => s.push_str(&json_str(reason)),
+            None => s.push_str("null"),
+        }
+        s.push('}');
+        s
     }
 
     /// Inserts a value for a variable at some step.
@@ -54,6 +177,238 @@ impl Cex {
         }
     }
 
+    /// Renders the trace, honoring the display hints of the declarations.
+    ///
+    /// For [timed systems](trans::time), step headers also show the value of the clock and the
+    /// duration of the step. When some variables have a [role](trans::Role), the values of a step
+    /// are grouped by role: inputs, state and outputs, then the variables without a role.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::cexs::Cex, expr::{display::Formatters, Cst}, parse};
+    /// let sys = parse::trans(r#"
+    /// svars {
+    ///     /// @display: hex
+    ///     /// @unit: ms
+    ///     cnt: int,
+    ///     reset: bool,
+    /// }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = cnt + 1 }
+    /// candidates { "cnt is not 31": ¬(cnt = 31) }
+    /// "#).unwrap();
+    /// let decls = sys.decls();
+    /// let mut cex = Cex::new();
+    /// for var in decls.all() {
+    ///     let val = if var.id() == "cnt" { Cst::int(31) } else { Cst::bool(false) };
+    ///     cex.insert(0, var, val).unwrap();
+    /// }
+    /// assert_eq!(
+    ///     cex.render(decls, &Formatters::new()),
+    ///     "\
+    /// step 0
+    ///       cnt = 0x1f ms
+    ///     reset = false
+    /// ",
+    /// );
+    /// ```
+    pub fn render(&self, decls: &trans::Decls, fmts: &Formatters) -> String {
+        self.render_trace(&self.trace, decls, fmts)
+    }
+
+    /// Renders the trace as a counterexample for a candidate of a system, see [`Self::render`].
+    ///
+    /// The header names the candidate and gives the title of its [label](crate::meta::Label), if
+    /// any. The description of the label follows as `//` comments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::cexs::Cex, expr::{display::Formatters, Cst}, parse};
+    /// let sys = parse::trans(r#"
+    /// svars { cnt: int }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = cnt + 1 }
+    /// candidates {
+    ///     /// The counter stays small.
+    ///     ///
+    ///     /// It never reaches `1`.
+    ///     "cnt is not 1": ¬(cnt = 1),
+    ///     "cnt is not 2": ¬(cnt = 2),
+    /// }
+    /// "#).unwrap();
+    /// let mut cex = Cex::new();
+    /// cex.insert(0, sys.decls().get_var("cnt").unwrap(), Cst::int(1)).unwrap();
+    /// assert_eq!(
+    ///     cex.render_titled("cnt is not 1", &sys, &Formatters::new()),
+    ///     "\
+    /// counterexample for `cnt is not 1`: The counter stays small.
+    /// // It never reaches `1`.
+    /// step 0
+    ///     cnt = 1
+    /// ",
+    /// );
+    /// assert!(cex
+    ///     .render_titled("cnt is not 2", &sys, &Formatters::new())
+    ///     .starts_with("counterexample for `cnt is not 2`\nstep 0\n"));
+    /// ```
+    pub fn render_titled(&self, candidate: &str, sys: &trans::Sys, fmts: &Formatters) -> String {
+        let mut s = format!("counterexample for `{}`", candidate);
+        match sys.label_of(candidate) {
+            Some(label) => {
+                s.push_str(&format!(": {}\n", label.title));
+                for line in label.description.iter().flat_map(|desc| desc.lines()) {
+                    if line.is_empty() {
+                        s.push_str("//\n")
+                    } else {
+                        s.push_str(&format!("// {}\n", line))
+                    }
+                }
+            }
+            None => s.push('\n'),
+        }
+        s.push_str(&self.render(sys.decls(), fmts));
+        s
+    }
+
+    /// Renders the part of the trace selected by a filter, see [`Self::render`].
+    ///
+    /// Fails if the filter cannot be applied, see [`Trace::filter`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{cexs::Cex, trace::TraceFilter}, expr::{display::Formatters, Cst}, parse};
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let mut cex = Cex::new();
+    /// for step in 0..4 {
+    ///     for var in sys.decls().all() {
+    ///         let val = if var.id() == "cnt" { Cst::int(step) } else { Cst::bool(false) };
+    ///         cex.insert(step, var, val).unwrap();
+    ///     }
+    /// }
+    /// let filter = TraceFilter::new().window(1, 3).select(vec!["cnt"]);
+    /// assert_eq!(
+    ///     cex.render_filtered(sys.decls(), &Formatters::new(), &filter).unwrap(),
+    ///     "\
+    /// step 1
+    ///       cnt = 1 s
+    /// step 2
+    ///       cnt = 2 s
+    /// ",
+    /// );
+    ///
+    /// // Only the variables that changed since the previous step.
+    /// let filter = TraceFilter::new().window(0, 2).changed();
+    /// assert_eq!(
+    ///     cex.render_filtered(sys.decls(), &Formatters::new(), &filter).unwrap(),
+    ///     "\
+    /// step 0
+    ///       cnt = 0 s
+    ///     reset = false
+    ///      stop = false
+    /// step 1
+    ///       cnt = 1 s
+    /// ",
+    /// );
+    /// ```
+    pub fn render_filtered(
+        &self,
+        decls: &trans::Decls,
+        fmts: &Formatters,
+        filter: &TraceFilter,
+    ) -> Res<String> {
+        let trace = self.trace.filter(filter)?;
+        Ok(self.render_trace(&trace, decls, fmts))
+    }
+
+    /// VCD (value change dump) representation of the trace, see [`Trace::to_vcd`].
+    ///
+    /// The values of the unexpected variables are not dumped.
+    pub fn to_vcd(&self) -> Res<String> {
+        self.trace.to_vcd()
+    }
+
+    /// Compares two steps of the counterexample, see [`Trace::diff_steps`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::cexs::Cex, expr::Cst, parse};
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let mut cex = Cex::new();
+    /// for step in 0..3 {
+    ///     for var in sys.decls().all() {
+    ///         let val = if var.id() == "cnt" { Cst::int(step) } else { Cst::bool(false) };
+    ///         cex.insert(step, var, val).unwrap();
+    ///     }
+    /// }
+    /// let diff = cex.diff_steps(0, 2);
+    /// assert_eq!(diff.keys().collect::<Vec<_>>(), vec!["cnt"]);
+    /// assert_eq!(diff["cnt"], (Some(Cst::int(0)), Some(Cst::int(2))));
+    /// ```
+    pub fn diff_steps(&self, lft: Unroll, rgt: Unroll) -> Map<String, ValDiff> {
+        self.trace.diff_steps(lft, rgt)
+    }
+
+    /// Renders a part of the trace, the clock is taken from the full trace.
+    fn render_trace(&self, trace: &Trace, decls: &trans::Decls, fmts: &Formatters) -> String {
+        let max_id_len = decls.max_id_len();
+        let mut s = String::new();
+        let clock = trans::time::clock(decls);
+        for (step, vals) in trace.iter() {
+            s.push_str(&format!("step {}", step));
+            let time = clock
+                .as_ref()
+                .and_then(|clock| self.trace.get(step)?.get(clock));
+            if let Some(time) = time {
+                s.push_str(&format!(
+                    " (time = {}",
+                    fmts.render(decls.hint(trans::time::CLOCK), time)
+                ));
+                if let Some(delta) = trans::time::elapsed(&self.trace, *step) {
+                    s.push_str(&format!(
+                        ", +{}",
+                        fmts.render(decls.hint(trans::time::CLOCK), &Cst::rat(delta))
+                    ));
+                }
+                s.push(')');
+            }
+            s.push('\n');
+            let mut vals: Vec<_> = vals.iter().collect();
+            // With roles, values are grouped under a comment giving the block declaring them.
+            let mut group = None;
+            if decls.has_roles() {
+                let rank = |var: &Var| {
+                    decls
+                        .role(var.id())
+                        .and_then(|role| trans::Role::ALL.iter().position(|r| *r == role))
+                        .unwrap_or(trans::Role::ALL.len())
+                };
+                vals.sort_by_key(|(var, _)| rank(var));
+            }
+            for (var, cst) in vals {
+                let role = decls.role(var.id());
+                if decls.has_roles() && group != Some(role) {
+                    group = Some(role);
+                    let keyword = role.map(trans::Role::keyword).unwrap_or("svars");
+                    s.push_str(&format!("    // {}\n", keyword))
+                }
+                s.push_str(&format!(
+                    "    {:>width$} = {}\n",
+                    var.id(),
+                    fmts.render(decls.hint(var.id()), cst),
+                    width = max_id_len,
+                ));
+            }
+        }
+        if let Some(reason) = self.incomplete.as_ref() {
+            s.push_str(&format!("incomplete counterexample: {}\n", reason));
+        }
+        s
+    }
+
     /// Populates itself given a solver.
     ///
     /// Uses `get_model` to retrieve the counterexample. The solver must have answered `sat` to a PO
@@ -95,6 +450,66 @@ impl Cex {
         }
         Ok(())
     }
+
+    /// Populates itself given a solver, salvages what it can if model retrieval fails.
+    ///
+    /// Same as [`Self::populate`] when `get_model` succeeds. Otherwise, for instance when the
+    /// solver times out or dies midway, the values retrieved so far are kept and the values of
+    /// `vars` are then requested step by step with `get_value`, until the solver fails again. The
+    /// counterexample is then [incomplete](Self::incomplete), but has a (possibly empty) entry for
+    /// each of the `steps` so that its length is right.
+    ///
+    /// Never fails: failures only make the counterexample incomplete.
+    pub fn populate_or_salvage(
+        &mut self,
+        solver: &mut SFSolver,
+        vars: &[Var],
+        steps: impl IntoIterator<Item = Unroll>,
+    ) {
+        let err = match self.populate(solver) {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        let mut reason = err.to_string();
+        let steps: Vec<Unroll> = steps.into_iter().collect();
+        for step in steps.iter() {
+            let _ = self.trace.entry(*step).or_default();
+        }
+        'steps: for step in steps {
+            let missing: Vec<expr::Expr> = vars
+                .iter()
+                .filter(|var| self.trace.get_val(step, var.id()).is_none())
+                .map(|var| expr::PExpr::new_var(var.clone()))
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+            let vals: Vec<((), Either<Cst, String>)> =
+                match solver.get_values_with(missing.iter(), step) {
+                    Ok(vals) if vals.len() == missing.len() => vals,
+                    Ok(vals) => {
+                        reason = format!(
+                            "{}, then solver produced {} value(s) for step {}, expected {}",
+                            reason,
+                            vals.len(),
+                            step,
+                            missing.len()
+                        );
+                        break 'steps;
+                    }
+                    Err(e) => {
+                        reason = format!("{}, then failed at step {}: {}", reason, step, e);
+                        break 'steps;
+                    }
+                };
+            for (var, (_, val)) in missing.into_iter().zip(vals) {
+                if let (expr::PExpr::Var(var), Either::Left(cst)) = (var, val) {
+                    let _ = self.trace.entry(step).or_default().insert(var, cst);
+                }
+            }
+        }
+        self.incomplete = Some(reason)
+    }
 }
 
 /// Counterexamples for some POs.
@@ -103,6 +518,17 @@ pub struct Cexs<'sys> {
     /// Map from POs to a counterexample for this PO.
     pub falsifications: Map<&'sys String, Cex>,
 }
+impl<'a> IntoIterator for &'a Cex {
+    type Item = (Unroll, &'a Map<Var, Cst>);
+    type IntoIter = std::iter::Map<
+        std::collections::btree_map::Iter<'a, Unroll, Map<Var, Cst>>,
+        fn((&'a Unroll, &'a Map<Var, Cst>)) -> (Unroll, &'a Map<Var, Cst>),
+    >;
+    fn into_iter(self) -> Self::IntoIter {
+        self.trace.iter().map(|(step, state)| (*step, state))
+    }
+}
+
 impl<'sys> Deref for Cexs<'sys> {
     type Target = Map<&'sys String, Cex>;
     fn deref(&self) -> &Map<&'sys String, Cex> {
@@ -129,6 +555,14 @@ impl<'sys> Cexs<'sys> {
     pub fn insert_falsification(&mut self, po: &'sys String, solver: &mut SFSolver) -> Res<()> {
         let mut cex = Cex::new();
         cex.populate(solver)?;
+        self.insert_cex(po, cex)
+    }
+    /// Inserts a counterexample for a PO.
+    ///
+    /// # Errors
+    ///
+    /// - when a counterexample for `po` is already registered.
+    pub fn insert_cex(&mut self, po: &'sys String, cex: Cex) -> Res<()> {
         let prev = self.insert(po, cex);
         if prev.is_some() {
             bail!(
@@ -192,11 +626,9 @@ impl<'a> rsmt2::parse::IdentParser<(String, Option<Unroll>), Typ, &'a str> for S
         Ok((name.into(), step))
     }
     fn parse_type(self, input: &'a str) -> SmtRes<Typ> {
-        match input {
-            "Bool" => Ok(Typ::Bool),
-            "Int" => Ok(Typ::Int),
-            "Real" => Ok(Typ::Rat),
-            _ => bail!("unexpected type string `{}`", input),
+        match Typ::of_smt_str(input) {
+            Some(typ) => Ok(typ),
+            None => bail!("unexpected type string `{}`", input),
         }
     }
 }
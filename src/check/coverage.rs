@@ -0,0 +1,248 @@
+//! Assumption coverage of proofs.
+//!
+//! The *assumptions* of a system are the top-level conjuncts of its initial predicate and of its
+//! transition relation. Once some candidates are proved, [`coverage`] replays the final induction
+//! queries with named assumptions and retrieves unsat cores, which tell which assumptions the
+//! proof actually needs.
+//!
+//! Assumptions are reported by label when they have one, see
+//! [`Sys::with_conjunct_labels`](trans::Sys::with_conjunct_labels).
+//!
+//! Top-level conjuncts of the transition relation that are disjunctions are also split into
+//! *transition disjuncts*. A disjunct is used if it can be taken from a state verifying the proved
+//! candidates, *i.e.* it is compatible with the rest of the transition relation and the invariant.
+//!
+//! Assumptions that are never used often indicate modeling mistakes (a typo in a constraint that
+//! makes it irrelevant), or an over-constrained environment (the properties hold for reasons that
+//! have nothing to do with the constraint). Unused disjuncts are dead transitions.
+//!
+//! The solver configuration must support unsat cores, which [`coverage`] activates.
+
+crate::prelude!();
+
+use super::{preprocess::conjuncts, InternalChecker};
+use expr::{Expr, Op, PExpr, SExpr};
+use trans::Sys;
+
+/// An assumption and whether the proof uses it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage<E> {
+    /// Assumption.
+    pub expr: E,
+    /// Label of the assumption, if any, see [`Sys::with_conjunct_labels`].
+    pub label: Option<String>,
+    /// True if the proof needs the assumption.
+    pub used: bool,
+}
+impl<E: fmt::Display> fmt::Display for Usage<E> {
+    /// Displays the label of the assumption between double quotes if any, its expression
+    /// otherwise.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(fmt, "\"{}\"", label),
+            None => self.expr.fmt(fmt),
+        }
+    }
+}
+
+/// Assumption coverage of a proof, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// Conjuncts of the initial predicate.
+    pub init: Vec<Usage<Expr>>,
+    /// Conjuncts of the transition relation.
+    pub trans: Vec<Usage<SExpr>>,
+    /// Disjuncts of the disjunctions of the transition relation.
+    pub disjuncts: Vec<Usage<SExpr>>,
+}
+impl Coverage {
+    /// True if all assumptions and disjuncts are used.
+    pub fn is_full(&self) -> bool {
+        self.init.iter().all(|u| u.used)
+            && self.trans.iter().all(|u| u.used)
+            && self.disjuncts.iter().all(|u| u.used)
+    }
+
+    /// Unused assumptions and disjuncts, as strings, see [`Usage`]'s display.
+    pub fn unused(&self) -> Vec<String> {
+        let init = self.init.iter().filter(|u| !u.used).map(|u| u.to_string());
+        let trans = self.trans.iter().filter(|u| !u.used).map(|u| u.to_string());
+        let disjuncts = self
+            .disjuncts
+            .iter()
+            .filter(|u| !u.used)
+            .map(|u| u.to_string());
+        init.chain(trans).chain(disjuncts).collect()
+    }
+}
+impl fmt::Display for Coverage {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fn section<E: fmt::Display>(
+            fmt: &mut fmt::Formatter,
+            title: &str,
+            usages: &[Usage<E>],
+        ) -> fmt::Result {
+            if usages.is_empty() {
+                return Ok(());
+            }
+            writeln!(fmt, "{}", title)?;
+            for usage in usages {
+                let mark = if usage.used { "used" } else { "UNUSED" };
+                writeln!(fmt, "    {:>6}  {}", mark, usage)?;
+            }
+            Ok(())
+        }
+        section(fmt, "init", &self.init)?;
+        section(fmt, "trans", &self.trans)?;
+        section(fmt, "transition disjuncts", &self.disjuncts)
+    }
+}
+
+/// Top-level disjuncts of an expression.
+fn disjuncts(expr: &SExpr) -> Option<&[SExpr]> {
+    match expr {
+        PExpr::App {
+            op: Op::Or, args, ..
+        } => Some(args),
+        _ => None,
+    }
+}
+
+/// Computes the assumption coverage of the proof of some candidates.
+///
+/// The candidates must hold in the initial states and be inductive *together*, which is the case
+/// for the candidates [proved](super::Outcome::proved) by [`run`](super::run) on `sys`.
+///
+/// # Errors
+///
+/// - when some candidate does not exist,
+/// - when the candidates are not provable by 1-induction, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int, inc: bool }
+/// init { cnt = 0, "increments initially": inc }
+/// trans {
+///     'cnt = (if inc { cnt + 1 } else { cnt }),
+///     'inc ∨ cnt < 0,
+/// }
+/// candidates { "cnt is positive": cnt ≥ 0 }
+/// "#).unwrap();
+/// let conf = SmtConf::z3("z3");
+/// let outcome = check::run(&sys, &check::Config::new(conf.clone())).unwrap();
+/// let coverage = check::coverage::coverage(&sys, outcome.proved(), &conf).unwrap();
+/// // The proof needs neither `inc` initially nor the last constraint, whose second disjunct can
+/// // never be taken.
+/// assert_eq!(
+///     coverage.unused(),
+///     vec!["\"increments initially\"", "(or inc@1 (< cnt@0 0))", "(< cnt@0 0)"],
+/// );
+/// ```
+pub fn coverage<S: AsRef<str>>(
+    sys: &Sys,
+    candidates: impl IntoIterator<Item = S>,
+    conf: &SmtConf,
+) -> Res<Coverage> {
+    let mut po_s = vec![];
+    for name in candidates {
+        match sys.po_s().get(name.as_ref()) {
+            Some(po) => po_s.push(po.clone()),
+            None => bail!("unknown candidate `{}`", name.as_ref()),
+        }
+    }
+    let invariant: Expr = match po_s.len() {
+        0 => bail!("cannot compute the coverage of an empty proof"),
+        1 => po_s.pop().expect("[unreachable] pop on vec of len `1`"),
+        _ => PExpr::new_op(Op::And, po_s)?,
+    };
+    let mut conf = conf.clone();
+    conf.unsat_cores();
+
+    // Base case.
+    let init = conjuncts(sys.init());
+    let mut checker = InternalChecker::new(sys, conf.clone(), None)?;
+    checker.declare_vars(0)?;
+    for (idx, conj) in init.iter().enumerate() {
+        checker
+            .solver()
+            .named_assert_with(format!("init_{}", idx), conj, 0)
+            .chain_err(|| "while asserting the initial predicate")?;
+    }
+    checker
+        .solver()
+        .assert_with(invariant.negated(), 0)
+        .chain_err(|| "while asserting the negation of the candidates")?;
+    if checker.check_sat()? {
+        bail!("the candidates do not hold in the initial states")
+    }
+    let core: Set<String> = checker.solver().get_unsat_core()?.into_iter().collect();
+    checker.solver().kill()?;
+    let init = init
+        .into_iter()
+        .enumerate()
+        .map(|(idx, expr)| Usage {
+            label: sys.init_label_of(&expr).map(String::from),
+            expr,
+            used: core.contains(&format!("init_{}", idx)),
+        })
+        .collect();
+
+    // Step case.
+    let trans = conjuncts(sys.trans());
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    checker.declare_vars(0)?;
+    checker.declare_vars(1)?;
+    for (idx, conj) in trans.iter().enumerate() {
+        checker
+            .solver()
+            .named_assert_with(format!("trans_{}", idx), conj, 0)
+            .chain_err(|| "while asserting the transition relation")?;
+    }
+    checker.assert_expr(&invariant, 0)?;
+    checker.solver().push(1)?;
+    checker
+        .solver()
+        .assert_with(invariant.negated(), 1)
+        .chain_err(|| "while asserting the negation of the candidates")?;
+    if checker.check_sat()? {
+        bail!("the candidates are not inductive")
+    }
+    let core: Set<String> = checker.solver().get_unsat_core()?.into_iter().collect();
+    checker.solver().pop(1)?;
+
+    let mut disjunct_usages = vec![];
+    for disjunct in trans.iter().filter_map(disjuncts).flatten() {
+        checker.solver().push(1)?;
+        checker
+            .solver()
+            .assert_with(disjunct, 0)
+            .chain_err(|| format!("while asserting transition disjunct `{}`", disjunct))?;
+        let used = checker.check_sat()?;
+        checker.solver().pop(1)?;
+        disjunct_usages.push(Usage {
+            expr: disjunct.clone(),
+            label: None,
+            used,
+        })
+    }
+    checker.solver().kill()?;
+    let trans = trans
+        .into_iter()
+        .enumerate()
+        .map(|(idx, expr)| Usage {
+            label: sys.trans_label_of(&expr).map(String::from),
+            expr,
+            used: core.contains(&format!("trans_{}", idx)),
+        })
+        .collect();
+
+    Ok(Coverage {
+        init,
+        trans,
+        disjuncts: disjunct_usages,
+    })
+}
@@ -0,0 +1,187 @@
+//! Determinism analysis of transition relations.
+//!
+//! A transition relation is *deterministic* if, for each state and each value of the inputs, it
+//! allows at most one next state. The *inputs* are the state variables whose next value is chosen
+//! freely at each step: the variables declared in an `inputs` block if any, and otherwise the ones
+//! the transition relation only reads, see [`inputs`].
+//!
+//! [`check()`] copies the transition relation and asks the solver for two transitions from the
+//! same state, with the same next inputs, that disagree on the next value of some other variable.
+//! The check considers all states, reachable or not, and ignores the initial predicate.
+
+crate::prelude!();
+
+use rsmt2::print::Expr2Smt;
+
+use super::{
+    cexs::Cex,
+    preprocess::{as_def, conjuncts},
+    trace::TraceDiff,
+    InternalChecker, VarEq,
+};
+use expr::SVar;
+use trans::Sys;
+
+/// Two transitions from the same state, with the same inputs, to different next states.
+#[derive(Debug, Clone)]
+pub struct NonDeterminism {
+    /// First transition, a counterexample with two steps.
+    pub lft: Cex,
+    /// Second transition, same first step as `lft`.
+    pub rgt: Cex,
+}
+impl NonDeterminism {
+    /// Differences between the two transitions, only on their second step.
+    pub fn diff(&self) -> TraceDiff {
+        self.lft.trace.diff(&self.rgt.trace)
+    }
+}
+
+/// Input variables of a system.
+///
+/// If some variables have a [role](trans::Role), the inputs are the variables declared in an
+/// `inputs` block. Otherwise, the inputs are the state variables whose next value is only read by
+/// the transition relation: a variable is an input if its next value only appears in the
+/// right-hand sides of the top-level definitions `'w = e` of other variables, if at all.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::determinism, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let inputs: Vec<_> = determinism::inputs(&sys).into_iter().collect();
+/// assert_eq!(inputs, vec!["reset", "stop"]);
+///
+/// let sys = parse::trans(
+///     "inputs { inc: bool } state { cnt: int } init { cnt = 0 } \
+///     trans { 'cnt ≥ cnt, 'cnt ≤ cnt + 1 } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let inputs: Vec<_> = determinism::inputs(&sys).into_iter().collect();
+/// assert_eq!(inputs, vec!["inc"]);
+/// ```
+pub fn inputs(sys: &Sys) -> Set<String> {
+    if sys.decls().has_roles() {
+        return sys
+            .decls()
+            .with_role(trans::Role::Input)
+            .map(|var| var.id().to_string())
+            .collect();
+    }
+    let mut constrained = Set::new();
+    for conj in conjuncts(sys.trans()) {
+        match as_def(&conj, |svar: &SVar| svar.is_next()) {
+            Some((svar, _)) => {
+                let _ = constrained.insert(svar.id().to_string());
+            }
+            None => {
+                constrained.extend(conj.next_vars().into_iter().map(|var| var.id().to_string()))
+            }
+        }
+    }
+    sys.decls()
+        .all()
+        .map(|var| var.id().to_string())
+        .filter(|id| !constrained.contains(id))
+        .collect()
+}
+
+/// Checks that the transition relation of a system is deterministic, given its inputs.
+///
+/// Yields `None` if it is, and two transitions disagreeing on a next state otherwise.
+///
+/// # Errors
+///
+/// - when some input is not a variable of the system, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::determinism, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let inputs = determinism::inputs(&sys);
+/// assert!(determinism::check(&sys, &inputs, SmtConf::z3("z3")).unwrap().is_none());
+///
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt ≥ cnt, 'cnt ≤ cnt + 1 } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let inputs = determinism::inputs(&sys);
+/// let pair = determinism::check(&sys, &inputs, SmtConf::z3("z3")).unwrap().unwrap();
+/// let diff = pair.diff();
+/// assert_eq!(diff.first_divergence(), Some(1));
+/// assert!(diff.steps[&1].contains_key("cnt"));
+/// ```
+pub fn check(sys: &Sys, inputs: &Set<String>, conf: SmtConf) -> Res<Option<NonDeterminism>> {
+    for input in inputs {
+        if sys.decls().get_var(input).is_none() {
+            bail!("unknown input variable `{}`", input)
+        }
+    }
+    let vars: Vec<_> = sys.decls().all().collect();
+    let outputs: Vec<_> = vars
+        .iter()
+        .filter(|var| !inputs.contains(var.id()))
+        .collect();
+    if outputs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    // Transitions from step `0` to step `1`, and from step `2` to step `3`.
+    for step in 0..4 {
+        checker.declare_vars(step)?;
+    }
+    checker.assert_trans(0)?;
+    checker.assert_trans(2)?;
+    checker.assert_frames_eq(None, 0, 2)?;
+    for var in vars.iter().filter(|var| inputs.contains(var.id())) {
+        checker
+            .solver()
+            .assert(&VarEq {
+                var,
+                lft: 1,
+                rgt: 3,
+            })
+            .chain_err(|| format!("while asserting equality of input `{}`", var))?;
+    }
+    let mut differ: Vec<u8> = b"(or".to_vec();
+    for var in outputs {
+        differ.extend_from_slice(b" (not ");
+        VarEq {
+            var,
+            lft: 1,
+            rgt: 3,
+        }
+        .expr_to_smt2(&mut differ, ())?;
+        differ.push(b')');
+    }
+    differ.push(b')');
+    checker
+        .solver()
+        .assert(&*String::from_utf8_lossy(&differ))
+        .chain_err(|| "while asserting that the next states differ")?;
+
+    let res = if checker.check_sat()? {
+        let mut cex = Cex::new();
+        cex.populate_or_salvage(checker.solver(), &vars, 0..4);
+        let mut rgt = cex.clone();
+        cex.trace = cex.trace.window(0, 2);
+        rgt.trace = rgt.trace.renumber(|step| step.checked_sub(2));
+        Some(NonDeterminism { lft: cex, rgt })
+    } else {
+        None
+    };
+    checker.solver().kill()?;
+
+    Ok(res)
+}
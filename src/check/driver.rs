@@ -0,0 +1,1388 @@
+//! Top-level check driver: a portfolio of engines, optionally in parallel over candidates.
+//!
+//! The entry point is [`run`], which takes a system and a [`Config`] and produces an [`Outcome`]
+//! mapping each candidate to a [`Verdict`]: proved, falsified at some step, unknown, or timeout
+//! when a solver query reached the [query limits](Config::query_timeout). Displaying
+//! an [`Outcome`] yields one line per candidate with its verdict.
+//!
+//! Candidates go through the [engines](super::engine) of [`Config::engines`] in order, induction
+//! followed by BMC by default. Each engine only gets the candidates the previous ones did not decide.
+//!
+//! In [portfolio](Config::portfolio) mode, the engines run at the same time on separate threads,
+//! each with all the candidates, possibly with several [solvers](Config::alt_confs). The first
+//! conclusive verdict for a candidate wins, and the engines still running are
+//! [cancelled](Cancel) once all the candidates are decided.
+//!
+//! When [`Config::jobs`] is `1`, all candidates are checked together by the same solvers. Otherwise
+//! each candidate is checked independently by its own solvers, and up to `jobs` candidates are
+//! checked at the same time on separate threads. A candidate checked alone cannot use the other
+//! candidates as lemmas, so the candidates left undecided are then checked again together, with
+//! the proved candidates as lemmas.
+//!
+//! When [`Config::cache`] is set, candidates with a valid certificate in the cache are reported as
+//! proved without running any solver, and newly proved candidates get a certificate.
+//!
+//! Candidates marked `#[warn]` have [severity](Severity) warning: the [`Outcome`] reports their
+//! falsification but is still a [success](Outcome::is_success). The [labels](Label) of the
+//! candidates are also carried to the [`Outcome`].
+//!
+//! Only the candidates selected by [`Config::filter`] are checked, the others do not appear in the
+//! [`Outcome`].
+//!
+//! Systems go through the [`Config::preprocess`] pipeline right before being checked. In parallel
+//! mode, each single-candidate system is pre-processed separately.
+//!
+//! Before checking anything, [`run`] runs the [`Config::lints`] over the (filtered) system. Denied
+//! lints with findings make it fail, warnings go to [`Outcome::lints`]. When
+//! [`Config::check_init`] is set, it then fails if the initial predicate is unsatisfiable, see
+//! [`super::init`].
+//!
+//! Once the candidates are checked, [`run`] looks for proved candidates that might hold vacuously
+//! if lint [`VacuousProof`](lint::Lint::VacuousProof) is not allowed, see [`super::vacuity`]. The
+//! reachability queries go up to [`Config::bmc_max`], or [`super::vacuity::DEFAULT_DEPTH`] if BMC
+//! is deactivated.
+//!
+//! When [`Config::certificate`] is set, [`run`] then writes a [certificate](super::certificate)
+//! for the proved candidates whose conjunction is inductive.
+//!
+//! The [liveness](Sys::liveness) and [LTL](Sys::ltl) properties of the system selected by the
+//! filter are finally checked by the same engines, each of them through its
+//! [reduction](super::ltl::Safety) to a safety candidate. They get a verdict in the [`Outcome`]
+//! like candidates, a falsification is a lasso whose loop start is in [`Outcome::loops`].
+
+crate::prelude!();
+
+use std::{sync::Mutex, time::Instant};
+
+use super::{
+    cache::Cache,
+    cexs::Cex,
+    engine::{Cancel, Engines, Event, Events},
+    filter::Filter,
+    ltl::{Ltl, Safety},
+    preprocess::Pipeline,
+    trace::json_str,
+    Unrolling,
+};
+use crate::{
+    lint::{self, Lints},
+    meta::{Label, Provenance},
+    solver,
+};
+use trans::{Severity, Sys};
+
+/// Check configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Solver configuration, shared by all solvers.
+    pub conf: SmtConf,
+    /// Directory where to tee the solver interactions, if any.
+    ///
+    /// When checking candidates in parallel, the interactions for the `n`-th candidate (in
+    /// alphabetical order) are teed in sub-directory `candidate_<n>`, and the ones for the
+    /// undecided candidates checked again together in sub-directory `undecided`.
+    ///
+    /// Each solver gets its own file, which also logs the answers to the check-sat commands and
+    /// can be [replayed](crate::solver::dialogue) later on.
+    pub tee: Option<PathBuf>,
+    /// Maximum BMC depth, `None` deactivates BMC.
+    pub bmc_max: Option<Unroll>,
+    /// BMC unrolling strategy, forward by default.
+    pub unrolling: Unrolling,
+    /// Maximum number of candidates checked at the same time, `0` is treated as `1`.
+    ///
+    /// With more than one job, each candidate is first checked alone. Candidates that are only
+    /// inductive together with other candidates are then undecided: they are checked again in a
+    /// single job, with the candidates proved alone as lemmas. The verdicts thus do not depend on
+    /// the number of jobs, but candidates that depend on each other are checked twice.
+    pub jobs: usize,
+    /// Cache directory for proved candidates, if any, see [`Cache`].
+    pub cache: Option<PathBuf>,
+    /// Pre-processing pipeline, empty by default.
+    pub preprocess: Pipeline,
+    /// Candidate filter, selects all candidates by default.
+    pub filter: Filter,
+    /// Global time budget, `None` for no budget.
+    ///
+    /// [`run`] gives engines what is left of the budget, and skips the engines not started before
+    /// it expires. A [`batch`](super::batch()) skips the files not started before it expires.
+    pub budget: Option<time::Duration>,
+    /// Engine portfolio, [standard](Engines::standard) by default.
+    pub engines: Engines,
+    /// Event handler, ignores events by default.
+    pub events: Events,
+    /// Cancellation handle, see [`Cancel`].
+    ///
+    /// Once cancelled, [`run`] skips the engines not started yet and a [`batch`](super::batch())
+    /// skips the files not started yet.
+    pub cancel: Cancel,
+    /// Lint levels, default levels by default.
+    pub lints: Lints,
+    /// True if the engines run at the same time instead of in order, false by default.
+    pub portfolio: bool,
+    /// Alternative solver configurations, none by default.
+    ///
+    /// In portfolio mode, each engine also runs with each of these configurations. The tee
+    /// directory for the `n`-th one is sub-directory `solver_<n>`. Ignored otherwise.
+    pub alt_confs: Vec<SmtConf>,
+    /// Time limit for each solver query, `None` for no limit.
+    ///
+    /// Passed to the solvers of the built-in engines, see [`solver::Backend::limit`]. Solvers do
+    /// not always honor their own limits, so the built-in engines also kill the solver of a
+    /// check-sat query running longer than that, the same way as on [cancellation](Cancel). A
+    /// query reaching it stops the engine, the candidates it did not decide get a
+    /// [`Verdict::Timeout`].
+    pub query_timeout: Option<time::Duration>,
+    /// Memory limit of the solvers in megabytes, `None` for no limit.
+    ///
+    /// Same as [`Self::query_timeout`], but only enforced by the solvers. Not all backends support
+    /// it, [`run`] fails if the backend of [`Self::conf`] does not.
+    pub memory_limit: Option<u64>,
+    /// True if the counterexamples are [minimized](super::minimize), false by default.
+    ///
+    /// [`run`] minimizes the counterexample of each falsified candidate once the engines are done,
+    /// with the query limits of the built-in engines.
+    pub minimize_cexs: bool,
+    /// True if [`run`] fails on systems with an unsatisfiable initial predicate, false by default.
+    ///
+    /// Such systems have no run, their candidates would all be proved vacuously.
+    pub check_init: bool,
+    /// True if the built-in engines report each query they check, false by default.
+    ///
+    /// Queries are reported in hsmt syntax with a plain-language description, as
+    /// [`Event::Explain`]s to the [`Self::events`] handler.
+    pub explain: bool,
+    /// File where to write a [certificate](super::certificate) for the proved candidates, if any.
+    ///
+    /// Only the proved candidates whose conjunction is inductive are certified, see
+    /// [`super::certificate::generate`]. No file is written if no candidate is proved.
+    pub certificate: Option<PathBuf>,
+}
+impl Config {
+    /// Constructor, no tee, no BMC (forward unrolling), one job, no cache, no pre-processing, no
+    /// filtering, no budget, standard engines, no event handler, not cancelled, default lints, no
+    /// portfolio, no query limits, no counterexample minimization, no initial predicate check, no
+    /// explanations, no certificate.
+    pub fn new(conf: SmtConf) -> Self {
+        Self {
+            conf,
+            tee: None,
+            bmc_max: None,
+            unrolling: Unrolling::Forward,
+            jobs: 1,
+            cache: None,
+            preprocess: Pipeline::new(),
+            filter: Filter::new(),
+            budget: None,
+            engines: Engines::standard(),
+            events: Events::new(),
+            cancel: Cancel::new(),
+            lints: Lints::new(),
+            portfolio: false,
+            alt_confs: vec![],
+            query_timeout: None,
+            memory_limit: None,
+            minimize_cexs: false,
+            check_init: false,
+            explain: false,
+            certificate: None,
+        }
+    }
+    /// Sets the tee directory.
+    pub fn tee(mut self, tee: impl Into<PathBuf>) -> Self {
+        self.tee = Some(tee.into());
+        self
+    }
+    /// Sets the maximum BMC depth.
+    pub fn bmc_max(mut self, bmc_max: Unroll) -> Self {
+        self.bmc_max = Some(bmc_max);
+        self
+    }
+    /// Sets the BMC unrolling strategy.
+    pub fn unrolling(mut self, unrolling: Unrolling) -> Self {
+        self.unrolling = unrolling;
+        self
+    }
+    /// Sets the maximum number of parallel jobs.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+    /// Sets the cache directory.
+    pub fn cache(mut self, cache: impl Into<PathBuf>) -> Self {
+        self.cache = Some(cache.into());
+        self
+    }
+    /// Sets the pre-processing pipeline.
+    pub fn preprocess(mut self, preprocess: Pipeline) -> Self {
+        self.preprocess = preprocess;
+        self
+    }
+    /// Sets the candidate filter.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+    /// Sets the global time budget.
+    pub fn budget(mut self, budget: time::Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+    /// Sets the engine portfolio.
+    pub fn engines(mut self, engines: Engines) -> Self {
+        self.engines = engines;
+        self
+    }
+    /// Sets the event handler.
+    pub fn events(mut self, events: Events) -> Self {
+        self.events = events;
+        self
+    }
+    /// Sets the cancellation handle.
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+    /// Sets the lint levels.
+    pub fn lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+    /// Sets portfolio mode.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{
+    /// #     check::{self, cexs::Cex, engine::{Cancel, Engine, Engines, Events}, Outcome, Verdict},
+    /// #     prelude::Res, rsmt2::SmtConf, trans::Sys,
+    /// # };
+    /// # use std::collections::BTreeSet as Set;
+    /// /// Proves everything, after some time.
+    /// struct Slow(Cancel);
+    /// impl Engine for Slow {
+    ///     fn name(&self) -> &str {
+    ///         "slow"
+    ///     }
+    ///     fn prepare(&mut self, _sys: &Sys, config: &check::Config) -> Res<()> {
+    ///         self.0 = config.cancel.clone();
+    ///         Ok(())
+    ///     }
+    ///     fn run(
+    ///         &mut self,
+    ///         candidates: &Set<String>,
+    ///         _budget: Option<std::time::Duration>,
+    ///         _events: &Events,
+    ///     ) -> Res<Outcome> {
+    ///         let mut outcome = Outcome::new();
+    ///         for _ in 0..1000 {
+    ///             if self.0.is_cancelled() {
+    ///                 return Ok(outcome);
+    ///             }
+    ///             std::thread::sleep(std::time::Duration::from_millis(10));
+    ///         }
+    ///         for name in candidates {
+    ///             let _ = outcome.verdicts.insert(name.clone(), Verdict::Proved);
+    ///         }
+    ///         Ok(outcome)
+    ///     }
+    /// }
+    /// /// Falsifies everything at step 0, right away.
+    /// struct Fast;
+    /// impl Engine for Fast {
+    ///     fn name(&self) -> &str {
+    ///         "fast"
+    ///     }
+    ///     fn prepare(&mut self, _sys: &Sys, _config: &check::Config) -> Res<()> {
+    ///         Ok(())
+    ///     }
+    ///     fn run(
+    ///         &mut self,
+    ///         candidates: &Set<String>,
+    ///         _budget: Option<std::time::Duration>,
+    ///         _events: &Events,
+    ///     ) -> Res<Outcome> {
+    ///         let mut outcome = Outcome::new();
+    ///         for name in candidates {
+    ///             let verdict = Verdict::Falsified { depth: 0, cex: Cex::new() };
+    ///             let _ = outcome.verdicts.insert(name.clone(), verdict);
+    ///         }
+    ///         Ok(outcome)
+    ///     }
+    /// }
+    ///
+    /// let sys = mikino_api::parse::trans(r#"
+    /// svars { x: int }
+    /// init { x = 0 }
+    /// trans { 'x = x + 1 }
+    /// candidates { "positive": x ≥ 0 }
+    /// "#).unwrap();
+    /// let mut engines = Engines::new();
+    /// engines.push(|| Slow(Cancel::new())).push(|| Fast);
+    /// let config = check::Config::new(SmtConf::z3("z3"))
+    ///     .engines(engines)
+    ///     .portfolio(true);
+    /// let start = std::time::Instant::now();
+    /// let outcome = check::run(&sys, &config).unwrap();
+    /// // `fast` answers first, `slow` is cancelled.
+    /// assert!(outcome["positive"].is_falsified());
+    /// assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    /// ```
+    pub fn portfolio(mut self, portfolio: bool) -> Self {
+        self.portfolio = portfolio;
+        self
+    }
+    /// Adds an alternative solver configuration, and activates portfolio mode.
+    pub fn alt_conf(mut self, conf: SmtConf) -> Self {
+        self.alt_confs.push(conf);
+        self.portfolio = true;
+        self
+    }
+    /// Sets the time limit of each solver query.
+    ///
+    /// # Examples
+    ///
+    /// Solvers stuck in a query are killed, here a fake solver that never answers.
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{self, engine::{Bmc, Engines}}, rsmt2::SmtConf};
+    /// # #[cfg(target_os = "linux")] {
+    /// # let dir = std::env::temp_dir().join(format!("mikino_timeout_{}", std::process::id()));
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// # let stuck = dir.join("stuck.sh");
+    /// # std::fs::write(&stuck, r#"#!/bin/sh
+    /// # depth=0
+    /// # while read -r line; do
+    /// #   case "$line" in *check-sat*) exec sleep 60 ;; esac
+    /// #   open=$(printf '%s' "$line" | tr -cd '(' | wc -c)
+    /// #   close=$(printf '%s' "$line" | tr -cd ')' | wc -c)
+    /// #   depth=$((depth + open - close))
+    /// #   if [ "$depth" -eq 0 ]; then echo success; fi
+    /// # done
+    /// # "#,
+    /// # ).unwrap();
+    /// # use std::os::unix::fs::PermissionsExt;
+    /// # std::fs::set_permissions(&stuck, std::fs::Permissions::from_mode(0o755)).unwrap();
+    /// # let stuck = stuck.display().to_string();
+    /// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let mut engines = Engines::new();
+    /// engines.push(Bmc::new);
+    /// let config = check::Config::new(SmtConf::z3(stuck))
+    ///     .bmc_max(10)
+    ///     .engines(engines)
+    ///     .query_timeout(std::time::Duration::from_millis(200));
+    ///
+    /// let start = std::time::Instant::now();
+    /// let outcome = check::run(&sys, &config).unwrap();
+    /// assert!(start.elapsed() < std::time::Duration::from_secs(30));
+    /// assert!(outcome.values().all(|verdict| verdict.is_timeout()));
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// # }
+    /// ```
+    pub fn query_timeout(mut self, timeout: time::Duration) -> Self {
+        self.query_timeout = Some(timeout);
+        self
+    }
+    /// Sets the memory limit of the solvers, in megabytes.
+    pub fn memory_limit(mut self, mb: u64) -> Self {
+        self.memory_limit = Some(mb);
+        self
+    }
+    /// Sets whether counterexamples are minimized.
+    pub fn minimize_cexs(mut self, minimize: bool) -> Self {
+        self.minimize_cexs = minimize;
+        self
+    }
+    /// Sets whether the initial predicate is checked for satisfiability.
+    pub fn check_init(mut self, check_init: bool) -> Self {
+        self.check_init = check_init;
+        self
+    }
+    /// Sets whether the built-in engines explain the queries they check.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mikino_api::{check::{self, engine::{Event, Events}}, rsmt2::SmtConf};
+    /// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let config = check::Config::new(SmtConf::z3("z3"))
+    ///     .bmc_max(3)
+    ///     .explain(true)
+    ///     .events(Events::handler(|event| {
+    ///         if let Event::Explain { .. } = event {
+    ///             println!("{}", event)
+    ///         }
+    ///     }));
+    /// let _ = check::run(&sys, &config).unwrap();
+    /// ```
+    pub fn explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+    /// Sets the file where to write the certificate of the proved candidates.
+    pub fn certificate(mut self, certificate: impl Into<PathBuf>) -> Self {
+        self.certificate = Some(certificate.into());
+        self
+    }
+
+    /// Solver configuration of the built-in engines: [`Self::conf`] with the query limits.
+    ///
+    /// Fails if the backend of [`Self::conf`] cannot enforce the limits.
+    pub fn limited_conf(&self) -> Res<SmtConf> {
+        let mut conf = self.conf.clone();
+        solver::backend_of(&conf).limit(&mut conf, self.query_timeout, self.memory_limit)?;
+        Ok(conf)
+    }
+}
+
+/// Final result for a candidate.
+#[derive(Debug, Clone)]
+pub enum Verdict {
+    /// The candidate holds in the initial states and is inductive.
+    Proved,
+    /// The candidate is falsified by a trace of length `depth + 1`.
+    Falsified {
+        /// Step at which the candidate is falsified.
+        depth: Unroll,
+        /// Counterexample.
+        cex: Cex,
+    },
+    /// The candidate is not inductive and BMC did not find a falsification.
+    Unknown {
+        /// Maximum depth explored by BMC, `None` if BMC did not run.
+        bmc_depth: Option<Unroll>,
+    },
+    /// A solver query on the candidate reached the [query limits](Config::query_timeout).
+    Timeout {
+        /// Maximum depth explored by BMC, `None` if BMC did not run.
+        bmc_depth: Option<Unroll>,
+    },
+}
+impl Verdict {
+    /// True if the verdict is [`Self::Proved`].
+    pub fn is_proved(&self) -> bool {
+        matches!(self, Self::Proved)
+    }
+    /// True if the verdict is [`Self::Falsified`].
+    pub fn is_falsified(&self) -> bool {
+        matches!(self, Self::Falsified { .. })
+    }
+    /// True if the verdict is [`Self::Unknown`].
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Self::Unknown { .. })
+    }
+    /// True if the verdict is [`Self::Timeout`].
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout { .. })
+    }
+}
+impl fmt::Display for Verdict {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Proved => "proved".fmt(fmt),
+            Self::Falsified { depth, .. } => write!(fmt, "falsified at step {}", depth),
+            Self::Unknown {
+                bmc_depth: Some(depth),
+            } => write!(fmt, "unknown, no falsification up to step {}", depth),
+            Self::Unknown { bmc_depth: None } => "unknown".fmt(fmt),
+            Self::Timeout {
+                bmc_depth: Some(depth),
+            } => write!(fmt, "timeout, no falsification up to step {}", depth),
+            Self::Timeout { bmc_depth: None } => "timeout".fmt(fmt),
+        }
+    }
+}
+
+/// Verdicts for all the candidates of a system.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    /// Maps candidates to their verdict.
+    pub verdicts: Map<String, Verdict>,
+    /// Candidates with severity [`Severity::Warning`].
+    pub warnings: Set<String>,
+    /// Labels of the candidates that have one.
+    pub labels: Map<String, Label>,
+    /// Provenance of the outcome, set by [`run`].
+    pub provenance: Option<Provenance>,
+    /// Lint warnings on the system, see [`Config::lints`].
+    pub lints: Vec<lint::Warning>,
+    /// Maps falsified liveness and LTL properties to the step their counterexample loops back to.
+    ///
+    /// The successor of the last state of the counterexample is the state at that step.
+    pub loops: Map<String, Unroll>,
+}
+/// One line per candidate, with its verdict.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::check::{cexs::Cex, Outcome, Verdict};
+/// let mut outcome = Outcome::new();
+/// let _ = outcome.verdicts.insert("cnt positive".into(), Verdict::Proved);
+/// let falsified = Verdict::Falsified { depth: 3, cex: Cex::new() };
+/// let _ = outcome.verdicts.insert("cnt below 3".into(), falsified);
+/// let _ = outcome.warnings.insert("cnt below 3".into());
+/// let unknown = Verdict::Unknown { bmc_depth: Some(10) };
+/// let _ = outcome.verdicts.insert("cnt even".into(), unknown);
+/// let timeout = Verdict::Timeout { bmc_depth: None };
+/// let _ = outcome.verdicts.insert("cnt odd".into(), timeout);
+/// assert_eq!(
+///     outcome.to_string(),
+///     "\
+/// `cnt below 3`: falsified at step 3 (warning)
+/// `cnt even`: unknown, no falsification up to step 10
+/// `cnt odd`: timeout
+/// `cnt positive`: proved
+/// ",
+/// );
+/// assert_eq!(outcome.unknown().count(), 2);
+/// assert_eq!(outcome.timed_out().collect::<Vec<_>>(), vec!["cnt odd"]);
+/// ```
+impl fmt::Display for Outcome {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (name, verdict) in self.verdicts.iter() {
+            write!(fmt, "`{}`: {}", name, verdict)?;
+            if verdict.is_falsified() && self.warnings.contains(name) {
+                write!(fmt, " (warning)")?;
+            }
+            writeln!(fmt)?;
+        }
+        Ok(())
+    }
+}
+impl Deref for Outcome {
+    type Target = Map<String, Verdict>;
+    fn deref(&self) -> &Map<String, Verdict> {
+        &self.verdicts
+    }
+}
+impl Default for Outcome {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Outcome {
+    /// Empty outcome.
+    pub fn new() -> Self {
+        Self {
+            verdicts: Map::new(),
+            warnings: Set::new(),
+            labels: Map::new(),
+            provenance: None,
+            lints: vec![],
+            loops: Map::new(),
+        }
+    }
+
+    /// Merges another outcome into this one.
+    ///
+    /// Keeps the provenance of `self` if any, the one of `other` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// - when both outcomes have a verdict for the same candidate.
+    pub fn merge(&mut self, other: Self) -> Res<()> {
+        for (name, verdict) in other.verdicts {
+            if self.verdicts.contains_key(&name) {
+                bail!("trying to merge two verdicts for candidate `{}`", name)
+            }
+            let _ = self.verdicts.insert(name, verdict);
+        }
+        self.warnings.extend(other.warnings);
+        self.labels.extend(other.labels);
+        self.lints.extend(other.lints);
+        self.loops.extend(other.loops);
+        if self.provenance.is_none() {
+            self.provenance = other.provenance
+        }
+        Ok(())
+    }
+
+    /// Severity of a candidate.
+    pub fn severity(&self, candidate: &str) -> Severity {
+        if self.warnings.contains(candidate) {
+            Severity::Warning
+        } else {
+            Severity::Error
+        }
+    }
+    /// Label of a candidate, if any.
+    pub fn label(&self, candidate: &str) -> Option<&Label> {
+        self.labels.get(candidate)
+    }
+    /// Title of a candidate, the title of its [label](Self::label) if any, its name otherwise.
+    pub fn title<'a>(&'a self, candidate: &'a str) -> &'a str {
+        self.label(candidate)
+            .map(|label| label.title.as_str())
+            .unwrap_or(candidate)
+    }
+    /// True if no candidate with severity [`Severity::Error`] is falsified.
+    ///
+    /// Unknown candidates do not count as failures.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mikino_api::{check, parse, rsmt2::SmtConf};
+    /// let sys = parse::trans(r#"
+    /// svars { cnt: int }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = cnt + 1 }
+    /// candidates {
+    ///     "cnt is positive": cnt ≥ 0,
+    ///     #[warn]
+    ///     "cnt is not 3": ¬(cnt = 3),
+    /// }
+    /// "#).unwrap();
+    /// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(5);
+    /// let outcome = check::run(&sys, &config).unwrap();
+    /// assert_eq!(outcome.warned().count(), 1);
+    /// assert_eq!(outcome.failures().count(), 0);
+    /// assert!(outcome.is_success());
+    /// ```
+    pub fn is_success(&self) -> bool {
+        self.failures().next().is_none()
+    }
+    /// Falsified candidates with severity [`Severity::Error`].
+    pub fn failures(&self) -> impl Iterator<Item = (&String, Unroll, &Cex)> + '_ {
+        self.falsified()
+            .filter(move |(name, _, _)| !self.warnings.contains(*name))
+    }
+    /// Falsified candidates with severity [`Severity::Warning`].
+    pub fn warned(&self) -> impl Iterator<Item = (&String, Unroll, &Cex)> + '_ {
+        self.falsified()
+            .filter(move |(name, _, _)| self.warnings.contains(*name))
+    }
+
+    /// Proved candidates.
+    pub fn proved(&self) -> impl Iterator<Item = &String> + '_ {
+        self.verdicts
+            .iter()
+            .filter(|(_, v)| v.is_proved())
+            .map(|(name, _)| name)
+    }
+    /// Falsified candidates, with their falsification depth and counterexample.
+    pub fn falsified(&self) -> impl Iterator<Item = (&String, Unroll, &Cex)> + '_ {
+        self.verdicts.iter().filter_map(|(name, v)| match v {
+            Verdict::Falsified { depth, cex } => Some((name, *depth, cex)),
+            _ => None,
+        })
+    }
+    /// Candidates that are neither proved nor falsified, including the ones that
+    /// [timed out](Self::timed_out).
+    pub fn unknown(&self) -> impl Iterator<Item = &String> + '_ {
+        self.verdicts
+            .iter()
+            .filter(|(_, v)| v.is_unknown() || v.is_timeout())
+            .map(|(name, _)| name)
+    }
+    /// Candidates on which a solver query reached the [query limits](Config::query_timeout).
+    pub fn timed_out(&self) -> impl Iterator<Item = &String> + '_ {
+        self.verdicts
+            .iter()
+            .filter(|(_, v)| v.is_timeout())
+            .map(|(name, _)| name)
+    }
+
+    /// JSON representation, with the [provenance](Provenance::to_json) if any.
+    ///
+    /// Falsified candidates have their depth and [counterexample](Cex::to_json), and their
+    /// [loop start](Self::loops) if any. Labels are [`Label::to_json`], `null` for candidates
+    /// without a label.
+    pub fn to_json(&self) -> String {
+        let mut s = String::from("{\"provenance\":");
+        match self.provenance.as_ref() {
+            Some(provenance) => s.push_str(&provenance.to_json()),
+            None => s.push_str("null"),
+        }
+        s.push_str(",\"verdicts\":[");
+        for (idx, (name, verdict)) in self.verdicts.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!(
+                "{{\"candidate\":{},\"severity\":\"{}\",\"label\":{},",
+                json_str(name),
+                self.severity(name),
+                self.label(name)
+                    .map(Label::to_json)
+                    .unwrap_or_else(|| "null".into())
+            ));
+            match verdict {
+                Verdict::Proved => s.push_str("\"verdict\":\"proved\"}"),
+                Verdict::Falsified { depth, cex } => s.push_str(&format!(
+                    "\"verdict\":\"falsified\",\"depth\":{},\"cex\":{}{}}}",
+                    depth,
+                    cex.to_json(),
+                    self.loops
+                        .get(name)
+                        .map(|step| format!(",\"loop_start\":{}", step))
+                        .unwrap_or_default(),
+                )),
+                Verdict::Unknown { bmc_depth } => s.push_str(&format!(
+                    "\"verdict\":\"unknown\",\"bmc_depth\":{}}}",
+                    bmc_depth
+                        .map(|depth| depth.to_string())
+                        .unwrap_or_else(|| "null".into())
+                )),
+                Verdict::Timeout { bmc_depth } => s.push_str(&format!(
+                    "\"verdict\":\"timeout\",\"bmc_depth\":{}}}",
+                    bmc_depth
+                        .map(|depth| depth.to_string())
+                        .unwrap_or_else(|| "null".into())
+                )),
+            }
+        }
+        s.push_str("]}");
+        s
+    }
+
+    /// Changes of the verdicts from this outcome to a `new` one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::check::{cexs::Cex, Outcome, Verdict};
+    /// let mut old = Outcome::new();
+    /// let _ = old.verdicts.insert("x".into(), Verdict::Proved);
+    /// let _ = old.verdicts.insert("y".into(), Verdict::Proved);
+    /// let _ = old.verdicts.insert("z".into(), Verdict::Unknown { bmc_depth: Some(5) });
+    /// let _ = old.verdicts.insert("gone".into(), Verdict::Proved);
+    ///
+    /// let mut new = Outcome::new();
+    /// let _ = new.verdicts.insert("x".into(), Verdict::Proved);
+    /// let falsified = Verdict::Falsified { depth: 2, cex: Cex::new() };
+    /// let _ = new.verdicts.insert("y".into(), falsified);
+    /// let _ = new.verdicts.insert("z".into(), Verdict::Proved);
+    /// let _ = new.verdicts.insert("fresh".into(), Verdict::Unknown { bmc_depth: None });
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.newly_proved().collect::<Vec<_>>(), vec!["z"]);
+    /// assert_eq!(diff.newly_falsified().collect::<Vec<_>>(), vec!["y"]);
+    /// assert_eq!(
+    ///     diff.to_string(),
+    ///     "\
+    /// `fresh`: unknown (new)
+    /// `gone`: removed (was proved)
+    /// `y`: falsified at step 2 (was proved)
+    /// `z`: proved (was unknown, no falsification up to step 5)
+    /// ",
+    /// );
+    /// assert!(new.diff(&new).is_empty());
+    /// ```
+    pub fn diff(&self, new: &Self) -> OutcomeDiff {
+        let names: Set<&String> = self.verdicts.keys().chain(new.verdicts.keys()).collect();
+        let mut changes = Map::new();
+        for name in names {
+            let (old, new) = (self.verdicts.get(name), new.verdicts.get(name));
+            if old.map(Verdict::to_string) != new.map(Verdict::to_string) {
+                let _ = changes.insert(name.clone(), (old.cloned(), new.cloned()));
+            }
+        }
+        OutcomeDiff { changes }
+    }
+}
+
+/// Old and new verdicts of a candidate that changed between two outcomes, `None` if missing.
+pub type VerdictDiff = (Option<Verdict>, Option<Verdict>);
+
+/// Changes of the verdicts between two outcomes, see [`Outcome::diff`].
+///
+/// The display implementation lists the changes, one candidate per line.
+#[derive(Debug, Clone)]
+pub struct OutcomeDiff {
+    /// Maps candidates to their old and new verdicts.
+    ///
+    /// Candidates whose verdict did not change do not appear. Verdicts are compared by their
+    /// [display](fmt::Display), *i.e.* two falsifications at the same depth are the same verdict
+    /// even if their counterexamples differ.
+    pub changes: Map<String, VerdictDiff>,
+}
+impl OutcomeDiff {
+    /// True if no verdict changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Candidates proved in the new outcome that were not proved in the old one.
+    pub fn newly_proved(&self) -> impl Iterator<Item = &String> + '_ {
+        self.changes
+            .iter()
+            .filter(|(_, (old, new))| {
+                new.as_ref().is_some_and(Verdict::is_proved)
+                    && !old.as_ref().is_some_and(Verdict::is_proved)
+            })
+            .map(|(name, _)| name)
+    }
+    /// Candidates falsified in the new outcome that were not falsified in the old one.
+    pub fn newly_falsified(&self) -> impl Iterator<Item = &String> + '_ {
+        self.changes
+            .iter()
+            .filter(|(_, (old, new))| {
+                new.as_ref().is_some_and(Verdict::is_falsified)
+                    && !old.as_ref().is_some_and(Verdict::is_falsified)
+            })
+            .map(|(name, _)| name)
+    }
+}
+impl fmt::Display for OutcomeDiff {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (name, (old, new)) in self.changes.iter() {
+            write!(fmt, "`{}`: ", name)?;
+            match new {
+                Some(new) => write!(fmt, "{}", new)?,
+                None => write!(fmt, "removed")?,
+            }
+            match old {
+                Some(old) => writeln!(fmt, " (was {})", old)?,
+                None => writeln!(fmt, " (new)")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Depth of a counterexample, *i.e.* its last step.
+pub(crate) fn cex_depth(cex: &Cex) -> Unroll {
+    cex.trace.keys().next_back().cloned().unwrap_or(0)
+}
+
+/// Remaining budget before `deadline`, `Err(())` if it expired.
+fn budget_before(deadline: Option<Instant>) -> Result<Option<time::Duration>, ()> {
+    match deadline {
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+            Some(budget) if budget > time::Duration::ZERO => Ok(Some(budget)),
+            _ => Err(()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Verdicts of the engines, as they arrive.
+struct Verdicts<'a> {
+    /// Check configuration.
+    config: &'a Config,
+    /// Candidates not decided yet.
+    pending: Set<String>,
+    /// Best unknown verdicts so far, by candidate.
+    unknown: Map<String, Option<Unroll>>,
+    /// Candidates on which an engine timed out.
+    timeouts: Set<String>,
+    /// Conclusive verdicts.
+    outcome: Outcome,
+}
+impl<'a> Verdicts<'a> {
+    /// Constructor, all the candidates of `sys` are pending.
+    fn new(sys: &Sys, config: &'a Config) -> Self {
+        Self {
+            config,
+            pending: sys.po_s().keys().cloned().collect(),
+            unknown: Map::new(),
+            timeouts: Set::new(),
+            outcome: Outcome::new(),
+        }
+    }
+
+    /// Records the outcome of an engine ran on `candidates`, ignores the candidates that are
+    /// already decided.
+    fn record(&mut self, engine: &str, candidates: &Set<String>, res: Outcome) -> Res<()> {
+        for (candidate, verdict) in res.verdicts {
+            if !candidates.contains(&candidate) {
+                bail!(
+                    "engine `{}` produced a verdict for unexpected candidate `{}`",
+                    engine,
+                    candidate
+                )
+            }
+            if !self.pending.contains(&candidate) {
+                continue;
+            }
+            match verdict {
+                Verdict::Unknown { bmc_depth } => {
+                    let best = self.unknown.entry(candidate).or_insert(None);
+                    *best = std::cmp::max(*best, bmc_depth);
+                }
+                Verdict::Timeout { bmc_depth } => {
+                    let best = self.unknown.entry(candidate.clone()).or_insert(None);
+                    *best = std::cmp::max(*best, bmc_depth);
+                    let _ = self.timeouts.insert(candidate);
+                }
+                verdict => {
+                    let _ = self.pending.remove(&candidate);
+                    self.config.events.emit(Event::Verdict {
+                        engine: engine.to_string(),
+                        candidate: candidate.clone(),
+                        verdict: verdict.clone(),
+                    });
+                    let _ = self.outcome.verdicts.insert(candidate, verdict);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Final outcome, candidates still pending are unknown, or timeouts if an engine timed out on
+    /// them.
+    fn finish(mut self) -> Outcome {
+        for candidate in self.pending {
+            let bmc_depth = self.unknown.get(&candidate).cloned().unwrap_or(None);
+            let verdict = if self.timeouts.contains(&candidate) {
+                Verdict::Timeout { bmc_depth }
+            } else {
+                Verdict::Unknown { bmc_depth }
+            };
+            let _ = self.outcome.verdicts.insert(candidate, verdict);
+        }
+        self.outcome
+    }
+}
+
+/// Runs the engines in order, each engine only gets the candidates the previous engines left
+/// undecided. Engines not started before `deadline`, or after cancellation, are skipped.
+fn run_engines(sys: &Sys, config: &Config, deadline: Option<Instant>) -> Res<Outcome> {
+    let mut verdicts = Verdicts::new(sys, config);
+    for mut engine in config.engines.instantiate() {
+        if verdicts.pending.is_empty() || config.cancel.is_cancelled() {
+            break;
+        }
+        let budget = match budget_before(deadline) {
+            Ok(budget) => budget,
+            Err(()) => break,
+        };
+        let name = engine.name().to_string();
+        config.events.emit(Event::Start {
+            engine: name.clone(),
+            candidates: verdicts.pending.len(),
+        });
+        engine
+            .prepare(sys, config)
+            .chain_err(|| format!("while preparing engine `{}`", name))?;
+        let candidates = verdicts.pending.clone();
+        let res = engine
+            .run(&candidates, budget, &config.events)
+            .chain_err(|| format!("during engine `{}`", name))?;
+        verdicts.record(&name, &candidates, res)?;
+    }
+    Ok(verdicts.finish())
+}
+
+/// Runs the engines at the same time, see [`Config::portfolio`].
+///
+/// Engine failures are only reported if some candidates are left undecided.
+fn run_portfolio(sys: &Sys, config: &Config, deadline: Option<Instant>) -> Res<Outcome> {
+    let mut verdicts = Verdicts::new(sys, config);
+    let budget = match budget_before(deadline) {
+        Ok(budget) => budget,
+        Err(()) => return Ok(verdicts.finish()),
+    };
+    if config.cancel.is_cancelled() {
+        return Ok(verdicts.finish());
+    }
+    // Cancels the engines still running once all the candidates are decided.
+    let cancel = config.cancel.child();
+    let mut configs = vec![Config {
+        cancel: cancel.clone(),
+        ..config.clone()
+    }];
+    for (idx, conf) in config.alt_confs.iter().enumerate() {
+        let tee = match config.tee.as_ref() {
+            Some(tee) => {
+                let tee = tee.join(format!("solver_{}", idx));
+                std::fs::create_dir_all(&tee)
+                    .chain_err(|| format!("while creating tee directory `{}`", tee.display()))?;
+                Some(tee)
+            }
+            None => None,
+        };
+        configs.push(Config {
+            conf: conf.clone(),
+            tee,
+            cancel: cancel.clone(),
+            ..config.clone()
+        });
+    }
+    let candidates = verdicts.pending.clone();
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let error = std::thread::scope(|scope| {
+        for config in configs.iter() {
+            for mut engine in config.engines.instantiate() {
+                let (sender, candidates) = (sender.clone(), &candidates);
+                let _ = scope.spawn(move || {
+                    let name = engine.name().to_string();
+                    config.events.emit(Event::Start {
+                        engine: name.clone(),
+                        candidates: candidates.len(),
+                    });
+                    let res = engine
+                        .prepare(sys, config)
+                        .chain_err(|| format!("while preparing engine `{}`", name))
+                        .and_then(|()| {
+                            engine
+                                .run(candidates, budget, &config.events)
+                                .chain_err(|| format!("during engine `{}`", name))
+                        });
+                    // Only fails if the receiver is gone, in which case nobody cares.
+                    let _ = sender.send((name, res));
+                });
+            }
+        }
+        drop(sender);
+
+        let mut error = None;
+        for (name, res) in receiver {
+            if let Err(e) = res.and_then(|res| verdicts.record(&name, &candidates, res)) {
+                error.get_or_insert(e);
+            }
+            if verdicts.pending.is_empty() {
+                cancel.cancel()
+            }
+        }
+        error
+    });
+
+    match error {
+        Some(e) if !verdicts.pending.is_empty() => Err(e),
+        _ => Ok(verdicts.finish()),
+    }
+}
+
+/// Checks all the candidates of a system with the same engines.
+///
+/// Engines run in order, or at the same time in [portfolio](Config::portfolio) mode.
+fn run_seq(
+    original: &Sys,
+    config: &Config,
+    tee: Option<PathBuf>,
+    cache: Option<&Cache>,
+    deadline: Option<Instant>,
+) -> Res<Outcome> {
+    if original.po_s().is_empty() {
+        return Ok(Outcome::new());
+    }
+    let sys = &config.preprocess.run(original)?;
+    let engine_config = Config {
+        tee,
+        ..config.clone()
+    };
+
+    let outcome = if config.portfolio {
+        run_portfolio(sys, &engine_config, deadline)?
+    } else {
+        run_engines(sys, &engine_config, deadline)?
+    };
+    if let Some(cache) = cache {
+        let proved: Set<String> = outcome.proved().cloned().collect();
+        for name in proved.iter() {
+            cache.store_proved(original, name, &proved)?
+        }
+    }
+    Ok(outcome)
+}
+
+/// Checks the candidates of a system, see the [module-level documentation](self).
+///
+/// # Errors
+///
+/// - when a solver cannot be spawned or fails,
+/// - when the backend cannot enforce the [memory limit](Config::memory_limit), or
+/// - when the tee directories cannot be created.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, rsmt2::SmtConf};
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(10).jobs(4);
+/// let outcome = check::run(&sys, &config).unwrap();
+/// for name in outcome.proved() {
+///     println!("proved `{}`", name)
+/// }
+/// ```
+///
+/// ```rust
+/// # use mikino_api::{check, rsmt2::SmtConf};
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let config = check::Config::new(SmtConf::cvc4("cvc5")).memory_limit(512);
+/// let err = check::run(&sys, &config).unwrap_err();
+/// assert_eq!(err.to_string(), "the cvc5 backend does not support memory limits");
+/// ```
+pub fn run(sys: &Sys, config: &Config) -> Res<Outcome> {
+    // Fail early on limits the backends cannot enforce.
+    let _ = config.limited_conf()?;
+    if config.portfolio {
+        for conf in config.alt_confs.iter() {
+            solver::backend_of(conf).limit(&mut conf.clone(), None, config.memory_limit)?
+        }
+    }
+    let original = sys;
+    let deadline = config.budget.map(|budget| Instant::now() + budget);
+    let mut outcome = Outcome::new();
+    let provenance = Provenance::of_config(config).system(sys);
+    outcome.provenance = Some(provenance.clone());
+
+    let filtered;
+    let sys = if config.filter.is_empty() {
+        sys
+    } else {
+        filtered = config.filter.apply(sys);
+        &filtered
+    };
+    outcome.warnings = sys.warnings().clone();
+    outcome.labels = sys.labels().clone();
+    outcome.lints = lint::system(sys, &config.lints)?;
+    if config.check_init && !sys.po_s().is_empty() {
+        let _ = super::init::validate(sys, config.limited_conf()?)?;
+    }
+
+    let selected = sys;
+
+    let cache = match config.cache.as_ref() {
+        Some(dir) => Some(Cache::new(dir)?.provenance(provenance.clone())),
+        None => None,
+    };
+    let uncached;
+    let sys = if let Some(cache) = cache.as_ref() {
+        for name in sys.po_s().keys() {
+            if cache.is_proved(sys, name)? {
+                let _ = outcome.verdicts.insert(name.clone(), Verdict::Proved);
+            }
+        }
+        uncached = sys.restrict(|name| !outcome.verdicts.contains_key(name));
+        &uncached
+    } else {
+        sys
+    };
+
+    let jobs = std::cmp::max(config.jobs, 1);
+    if jobs == 1 || sys.po_s().len() <= 1 {
+        let res = run_seq(sys, config, config.tee.clone(), cache.as_ref(), deadline)?;
+        outcome.merge(res)?;
+        minimize_cexs(sys, config, &mut outcome)?;
+        check_vacuity(selected, config, &mut outcome)?;
+        write_certificate(selected, config, &outcome, provenance)?;
+        check_temporal(original, config, deadline, &mut outcome)?;
+        return Ok(outcome);
+    }
+
+    // Candidates left to check, in reverse order so that `pop` yields them in order.
+    let todo: Vec<(usize, &String)> = sys.po_s().keys().enumerate().rev().collect();
+    let todo = Mutex::new(todo);
+    // Results are indexed by candidate so that merging, and error reporting, do not depend on the
+    // order in which threads finish.
+    let results: Mutex<Map<usize, Res<Outcome>>> = Mutex::new(Map::new());
+
+    let work = || loop {
+        let next = todo.lock().expect("candidate queue lock poisoned").pop();
+        let (idx, name) = match next {
+            Some(next) => next,
+            None => break,
+        };
+
+        let res = (|| {
+            let tee = match config.tee.as_ref() {
+                Some(tee) => {
+                    let tee = tee.join(format!("candidate_{}", idx));
+                    std::fs::create_dir_all(&tee).chain_err(|| {
+                        format!("while creating tee directory `{}`", tee.display())
+                    })?;
+                    Some(tee)
+                }
+                None => None,
+            };
+            let sub_sys = sys.restrict(|po| po == name);
+            run_seq(&sub_sys, config, tee, cache.as_ref(), deadline)
+                .chain_err(|| format!("while checking candidate `{}`", name))
+        })();
+
+        if res.is_err() {
+            // Abort, other threads will stop after their current candidate.
+            todo.lock().expect("candidate queue lock poisoned").clear();
+        }
+        let _ = results
+            .lock()
+            .expect("results lock poisoned")
+            .insert(idx, res);
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..std::cmp::min(jobs, sys.po_s().len()) {
+            let _ = scope.spawn(work);
+        }
+    });
+
+    for (_, res) in results.into_inner().expect("results lock poisoned") {
+        outcome.merge(res?)?
+    }
+    recheck_undecided(sys, config, cache.as_ref(), deadline, &mut outcome)?;
+    minimize_cexs(sys, config, &mut outcome)?;
+    check_vacuity(selected, config, &mut outcome)?;
+    write_certificate(selected, config, &outcome, provenance)?;
+    check_temporal(original, config, deadline, &mut outcome)?;
+    Ok(outcome)
+}
+
+/// Checks the candidates left undecided by parallel jobs again, together, see [`Config::jobs`].
+///
+/// When teeing, the interactions are teed in sub-directory `undecided`.
+fn recheck_undecided(
+    sys: &Sys,
+    config: &Config,
+    cache: Option<&Cache>,
+    deadline: Option<Instant>,
+    outcome: &mut Outcome,
+) -> Res<()> {
+    let (mut undecided, mut proved) = (Set::new(), Set::new());
+    for name in sys.po_s().keys() {
+        match outcome.verdicts.get(name) {
+            Some(Verdict::Proved) => {
+                let _ = proved.insert(name.clone());
+            }
+            Some(Verdict::Falsified { .. }) | None => (),
+            Some(Verdict::Unknown { .. }) | Some(Verdict::Timeout { .. }) => {
+                let _ = undecided.insert(name.clone());
+            }
+        }
+    }
+    // Nothing to gain if there are no other candidates to use as lemmas.
+    if undecided.is_empty() || undecided.len() + proved.len() < 2 {
+        return Ok(());
+    }
+    let tee = match config.tee.as_ref() {
+        Some(tee) => {
+            let tee = tee.join("undecided");
+            std::fs::create_dir_all(&tee)
+                .chain_err(|| format!("while creating tee directory `{}`", tee.display()))?;
+            Some(tee)
+        }
+        None => None,
+    };
+    let together = sys.restrict(|name| undecided.contains(name) || proved.contains(name));
+    let res = run_seq(&together, config, tee, cache, deadline)
+        .chain_err(|| "while checking the undecided candidates together")?;
+    for (name, verdict) in res.verdicts {
+        if undecided.contains(&name) {
+            let _ = outcome.verdicts.insert(name, verdict);
+        }
+    }
+    Ok(())
+}
+
+/// Checks the liveness and LTL properties of a system selected by the filter, see [`run`].
+///
+/// When teeing, the interactions for the `n`-th property (liveness properties first, in
+/// alphabetical order) are teed in sub-directory `property_<n>`.
+fn check_temporal(
+    sys: &Sys,
+    config: &Config,
+    deadline: Option<Instant>,
+    outcome: &mut Outcome,
+) -> Res<()> {
+    let liveness = sys
+        .liveness()
+        .iter()
+        .map(|(name, prop)| (name, Ltl::from(prop)));
+    let ltl = sys.ltl().iter().map(|(name, ltl)| (name, ltl.clone()));
+    for (idx, (name, ltl)) in liveness.chain(ltl).enumerate() {
+        if !config.filter.selects(sys, name) {
+            continue;
+        }
+        let tee = match config.tee.as_ref() {
+            Some(tee) => {
+                let tee = tee.join(format!("property_{}", idx));
+                std::fs::create_dir_all(&tee)
+                    .chain_err(|| format!("while creating tee directory `{}`", tee.display()))?;
+                Some(tee)
+            }
+            None => None,
+        };
+        let safety = Safety::new(sys, &ltl, name.clone())
+            .chain_err(|| format!("while reducing property `{}` to safety", name))?;
+        let res = run_seq(&safety.sys, config, tee, None, deadline)
+            .chain_err(|| format!("while checking property `{}`", name))?;
+        for (_, verdict) in res.verdicts {
+            let verdict = match verdict {
+                Verdict::Falsified { cex, .. } => match safety.lasso(sys, &cex) {
+                    Some(lasso) => {
+                        let _ = outcome.loops.insert(name.clone(), lasso.loop_start);
+                        Verdict::Falsified {
+                            depth: lasso.cex.last_step().unwrap_or(0),
+                            cex: lasso.cex,
+                        }
+                    }
+                    None => bail!(
+                        "[bug] counterexample for property `{}` is not a lasso",
+                        name
+                    ),
+                },
+                verdict => verdict,
+            };
+            let _ = outcome.verdicts.insert(name.clone(), verdict);
+        }
+    }
+    Ok(())
+}
+
+/// Writes the certificate of the proved candidates, see [`Config::certificate`].
+fn write_certificate(
+    sys: &Sys,
+    config: &Config,
+    outcome: &Outcome,
+    provenance: Provenance,
+) -> Res<()> {
+    let path = match config.certificate.as_ref() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if outcome.proved().next().is_none() {
+        return Ok(());
+    }
+    super::certificate::generate(sys, outcome.proved(), config.limited_conf()?)
+        .chain_err(|| "while generating the certificate of the proved candidates")?
+        .provenance(provenance)
+        .write(path)
+}
+
+/// Adds the vacuity findings of the proved candidates to the lints of an outcome, see [`run`].
+///
+/// Fails if the lint is denied and some proved candidate might hold vacuously.
+fn check_vacuity(sys: &Sys, config: &Config, outcome: &mut Outcome) -> Res<()> {
+    let lint = lint::Lint::VacuousProof;
+    let level = config.lints.level(lint);
+    if level == lint::Level::Allow {
+        return Ok(());
+    }
+    let depth = config.bmc_max.unwrap_or(super::vacuity::DEFAULT_DEPTH);
+    let conf = config.limited_conf()?;
+    let mut findings = vec![];
+    for name in outcome.proved() {
+        for vacuity in super::vacuity::check(sys, name, depth, conf.clone())? {
+            findings.push(lint::Warning {
+                lint,
+                level,
+                msg: vacuity.to_string(),
+            })
+        }
+    }
+    if level == lint::Level::Deny && !findings.is_empty() {
+        let denied: Vec<String> = findings.iter().map(|w| w.to_string()).collect();
+        bail!("{}", denied.join("\n"))
+    }
+    outcome.lints.extend(findings);
+    Ok(())
+}
+
+/// Minimizes the counterexamples of the falsified candidates, see [`Config::minimize_cexs`].
+fn minimize_cexs(sys: &Sys, config: &Config, outcome: &mut Outcome) -> Res<()> {
+    if !config.minimize_cexs {
+        return Ok(());
+    }
+    let conf = config.limited_conf()?;
+    for (name, verdict) in outcome.verdicts.iter_mut() {
+        if let Verdict::Falsified { cex, .. } = verdict {
+            *cex = super::minimize::minimize(sys, name, cex, &conf)
+                .chain_err(|| format!("while minimizing the counterexample of `{}`", name))?
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,984 @@
+//! Verification engines, the building blocks of the check portfolio.
+//!
+//! An [`Engine`] decides some candidates of a system. [`run`](super::run) goes through the
+//! [`Engines`] of [`Config::engines`] in order, each engine only gets the candidates the previous
+//! engines did not prove or falsify. The [standard portfolio](Engines::standard) is
+//!
+//! - [`Induction`], which falsifies candidates in the initial states and proves inductive ones;
+//! - [`Bmc`], which looks for falsifications up to [`Config::bmc_max`].
+//!
+//! The [`Pdr`](super::pdr::Pdr) and [`Interpolation`](super::interpolation::Interpolation) engines
+//! prove candidates that need strengthening to be inductive.
+//!
+//! Users can plug their own engines, *e.g.* backed by an external tool, by implementing [`Engine`].
+//!
+//! Engines report their progress as [`Event`]s to the [`Events`] handler of [`Config::events`]:
+//! the depths BMC checks, the end of the base and step checks of induction, the verdicts... so
+//! that front-ends can display progress bars or live logs. When [`Config::explain`] is set, the
+//! built-in engines also report each query they check along with what it means, see
+//! [`super::explain`].
+//!
+//! Checks can be stopped from another thread with the [`Cancel`] handle of [`Config::cancel`]:
+//! engines not started yet are skipped, and long-running engines should stop as soon as possible.
+//!
+//! In [portfolio](Config::portfolio) mode, the engines run at the same time instead of in order.
+
+crate::prelude!();
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc, Mutex,
+};
+
+use super::{
+    driver::{cex_depth, Config, Outcome, Verdict},
+    Base, Bmc as BmcChecker, BmcRes, CheckRes, Step, Unrolling,
+};
+use trans::Sys;
+
+/// Something that happened while checking a system.
+///
+/// Displaying an event yields a one-line log message, except for [`Event::Explain`] which also
+/// displays its (indented) query on the following lines.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::check::engine::Event;
+/// let events = [
+///     Event::BaseDone { engine: "induction".into(), holding: 3 },
+///     Event::StepDone { engine: "induction".into(), proved: 1 },
+///     Event::DepthStart { engine: "bmc".into(), depth: 0 },
+///     Event::DepthDone { engine: "bmc".into(), depth: 0, falsified: 0 },
+///     Event::DepthStart { engine: "bmc".into(), depth: 1 },
+///     Event::DepthDone { engine: "bmc".into(), depth: 1, falsified: 2 },
+///     Event::Explain {
+///         engine: "bmc".into(),
+///         description: "depth 1: is there a run falsifying some candidate?".into(),
+///         query: "// state 0 is initial\nx = 0\n// ...".into(),
+///     },
+/// ];
+/// let log: Vec<_> = events.iter().map(|event| event.to_string()).collect();
+/// assert_eq!(
+///     log,
+///     vec![
+///         "[induction] 3 candidate(s) hold initially",
+///         "[induction] 1 candidate(s) inductive",
+///         "[bmc] checking depth 0",
+///         "[bmc] checked depth 0",
+///         "[bmc] checking depth 1",
+///         "[bmc] checked depth 1, falsified 2 candidate(s)",
+///         "[bmc] depth 1: is there a run falsifying some candidate?\n    \
+///         // state 0 is initial\n    x = 0\n    // ...",
+///     ],
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// An engine starts working on some candidates.
+    Start {
+        /// Engine.
+        engine: String,
+        /// Number of candidates.
+        candidates: usize,
+    },
+    /// Induction checked which candidates hold in the initial states.
+    BaseDone {
+        /// Engine.
+        engine: String,
+        /// Number of candidates that hold in the initial states.
+        holding: usize,
+    },
+    /// Induction checked which candidates are inductive.
+    StepDone {
+        /// Engine.
+        engine: String,
+        /// Number of inductive candidates.
+        proved: usize,
+    },
+    /// BMC starts looking for falsifications at some depth.
+    DepthStart {
+        /// Engine.
+        engine: String,
+        /// Depth.
+        depth: Unroll,
+    },
+    /// BMC is done looking for falsifications at some depth.
+    DepthDone {
+        /// Engine.
+        engine: String,
+        /// Depth.
+        depth: Unroll,
+        /// Number of candidates falsified at this depth.
+        falsified: usize,
+    },
+    /// An engine reports its progress.
+    Progress {
+        /// Engine.
+        engine: String,
+        /// Progress message.
+        message: String,
+    },
+    /// An engine explains the query it is about to check, see [`Config::explain`].
+    Explain {
+        /// Engine.
+        engine: String,
+        /// What the query checks.
+        description: String,
+        /// Query, in hsmt syntax.
+        query: String,
+    },
+    /// An engine proved or falsified a candidate.
+    Verdict {
+        /// Engine.
+        engine: String,
+        /// Candidate.
+        candidate: String,
+        /// Verdict for the candidate, never [`Verdict::Unknown`] nor [`Verdict::Timeout`].
+        verdict: Verdict,
+    },
+}
+impl fmt::Display for Event {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Start { engine, candidates } => {
+                write!(fmt, "[{}] starting on {} candidate(s)", engine, candidates)
+            }
+            Self::BaseDone { engine, holding } => {
+                write!(fmt, "[{}] {} candidate(s) hold initially", engine, holding)
+            }
+            Self::StepDone { engine, proved } => {
+                write!(fmt, "[{}] {} candidate(s) inductive", engine, proved)
+            }
+            Self::DepthStart { engine, depth } => {
+                write!(fmt, "[{}] checking depth {}", engine, depth)
+            }
+            Self::DepthDone {
+                engine,
+                depth,
+                falsified: 0,
+            } => write!(fmt, "[{}] checked depth {}", engine, depth),
+            Self::DepthDone {
+                engine,
+                depth,
+                falsified,
+            } => write!(
+                fmt,
+                "[{}] checked depth {}, falsified {} candidate(s)",
+                engine, depth, falsified
+            ),
+            Self::Progress { engine, message } => write!(fmt, "[{}] {}", engine, message),
+            Self::Explain {
+                engine,
+                description,
+                query,
+            } => {
+                write!(fmt, "[{}] {}", engine, description)?;
+                for line in query.lines() {
+                    write!(fmt, "\n    {}", line)?
+                }
+                Ok(())
+            }
+            Self::Verdict {
+                engine,
+                candidate,
+                verdict,
+            } => match verdict {
+                Verdict::Proved => write!(fmt, "[{}] proved `{}`", engine, candidate),
+                Verdict::Falsified { depth, .. } => write!(
+                    fmt,
+                    "[{}] falsified `{}` at depth {}",
+                    engine, candidate, depth
+                ),
+                Verdict::Unknown { .. } => write!(fmt, "[{}] unknown `{}`", engine, candidate),
+                Verdict::Timeout { .. } => write!(fmt, "[{}] timeout on `{}`", engine, candidate),
+            },
+        }
+    }
+}
+
+/// Event handler.
+type Handler = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// Handler for [`Event`]s, ignores them by default.
+#[derive(Clone, Default)]
+pub struct Events {
+    /// Handler, if any.
+    handler: Option<Handler>,
+}
+impl fmt::Debug for Events {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.handler.is_some() {
+            write!(fmt, "Events(handler)")
+        } else {
+            write!(fmt, "Events(none)")
+        }
+    }
+}
+impl Events {
+    /// Ignores all events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Calls `handler` on all events.
+    ///
+    /// When checking candidates in parallel, the handler is called from several threads.
+    pub fn handler(handler: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        Self {
+            handler: Some(Arc::new(handler)),
+        }
+    }
+    /// Reports an event.
+    pub fn emit(&self, event: Event) {
+        if let Some(handler) = self.handler.as_ref() {
+            handler(&event)
+        }
+    }
+    /// Reports the progress of an engine.
+    pub fn progress(&self, engine: &str, message: impl Into<String>) {
+        if self.handler.is_some() {
+            self.emit(Event::Progress {
+                engine: engine.into(),
+                message: message.into(),
+            })
+        }
+    }
+}
+
+/// Cancellation handle, shared by all its clones.
+///
+/// Cancelling also kills the solvers [watching](crate::solver::Solver::watch) the handle, so that
+/// the built-in engines stop right away instead of after their current query. They then report
+/// what they found so far, for instance the depth BMC reached. Solvers are only killed on Linux,
+/// on other platforms the engines stop after their current query.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::check::engine::Cancel;
+/// let cancel = Cancel::new();
+/// let handle = cancel.clone();
+/// assert!(!cancel.is_cancelled());
+/// std::thread::spawn(move || handle.cancel()).join().unwrap();
+/// assert!(cancel.is_cancelled());
+/// ```
+///
+/// Interrupting a check stuck in a solver query, here with a fake solver that never answers.
+///
+/// ```rust
+/// # use mikino_api::{check::{self, engine::{Bmc, Cancel, Engines}}, rsmt2::SmtConf};
+/// # #[cfg(target_os = "linux")] {
+/// # let dir = std::env::temp_dir().join(format!("mikino_cancel_{}", std::process::id()));
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # let stuck = dir.join("stuck.sh");
+/// # std::fs::write(&stuck, r#"#!/bin/sh
+/// # depth=0
+/// # while read -r line; do
+/// #   case "$line" in *check-sat*) exec sleep 60 ;; esac
+/// #   open=$(printf '%s' "$line" | tr -cd '(' | wc -c)
+/// #   close=$(printf '%s' "$line" | tr -cd ')' | wc -c)
+/// #   depth=$((depth + open - close))
+/// #   if [ "$depth" -eq 0 ]; then echo success; fi
+/// # done
+/// # "#,
+/// # ).unwrap();
+/// # use std::os::unix::fs::PermissionsExt;
+/// # std::fs::set_permissions(&stuck, std::fs::Permissions::from_mode(0o755)).unwrap();
+/// # let stuck = stuck.display().to_string();
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let mut engines = Engines::new();
+/// engines.push(Bmc::new);
+/// let cancel = Cancel::new();
+/// let config = check::Config::new(SmtConf::z3(stuck))
+///     .bmc_max(10)
+///     .engines(engines)
+///     .cancel(cancel.clone());
+///
+/// let start = std::time::Instant::now();
+/// let handle = std::thread::spawn(move || check::run(&sys, &config));
+/// std::thread::sleep(std::time::Duration::from_millis(200));
+/// cancel.cancel();
+/// let outcome = handle.join().unwrap().unwrap();
+/// assert!(start.elapsed() < std::time::Duration::from_secs(30));
+/// // Nothing was checked.
+/// assert!(outcome.values().all(|verdict| verdict.is_unknown()));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Cancel {
+    /// True once cancelled.
+    flag: Arc<AtomicBool>,
+    /// Process IDs of the solvers to kill on cancellation.
+    pids: Arc<Mutex<Set<u32>>>,
+    /// Handle this one is a [child](Self::child) of, if any.
+    parent: Option<Box<Cancel>>,
+    /// Time after which the check-sat queries of the solvers watching this handle are killed.
+    query_timeout: Option<time::Duration>,
+}
+impl Cancel {
+    /// Constructor, not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// A new handle, cancelled when this one is, that can be cancelled without cancelling this one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::check::engine::Cancel;
+    /// let cancel = Cancel::new();
+    /// let child = cancel.child();
+    /// child.cancel();
+    /// assert!(child.is_cancelled());
+    /// assert!(!cancel.is_cancelled());
+    ///
+    /// let child = cancel.child();
+    /// cancel.cancel();
+    /// assert!(child.is_cancelled());
+    /// ```
+    pub fn child(&self) -> Self {
+        Self {
+            flag: Arc::new(AtomicBool::new(false)),
+            pids: Arc::new(Mutex::new(Set::new())),
+            parent: Some(Box::new(self.clone())),
+            query_timeout: self.query_timeout,
+        }
+    }
+    /// A clone of this handle that also kills the solvers watching it when one of their
+    /// check-sat queries runs longer than `timeout`, see [`Config::query_timeout`].
+    ///
+    /// The query then fails with an [`rsmt2`] timeout error.
+    pub(crate) fn with_query_timeout(&self, timeout: Option<time::Duration>) -> Self {
+        Self {
+            query_timeout: timeout,
+            ..self.clone()
+        }
+    }
+    /// Cancels, for this handle and all its clones, and kills the solvers watching it.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+        let pids = self.pids.lock().expect("cancel lock poisoned");
+        for pid in pids.iter() {
+            kill(*pid)
+        }
+    }
+    /// True if cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_cancelled())
+    }
+
+    /// Kills process `pid` on cancellation of this handle or of its parents, until the result is
+    /// dropped. Kills it right away if already cancelled.
+    pub(crate) fn watch(&self, pid: u32) -> Watch {
+        let mut watch = Watch {
+            pid,
+            pids: vec![],
+            query_timeout: self.query_timeout,
+        };
+        let mut current = Some(self);
+        while let Some(cancel) = current {
+            let _ = cancel
+                .pids
+                .lock()
+                .expect("cancel lock poisoned")
+                .insert(pid);
+            watch.pids.push(cancel.pids.clone());
+            current = cancel.parent.as_deref();
+        }
+        if self.is_cancelled() {
+            kill(pid)
+        }
+        watch
+    }
+}
+
+/// Kills a process, ignores failures.
+///
+/// Only [watched](Cancel::watch) processes are killed, and process IDs are only known on Linux,
+/// see [`Solver::pid`](crate::solver::Solver::pid).
+#[cfg(target_os = "linux")]
+fn kill(pid: u32) {
+    use std::convert::TryFrom;
+    if let Ok(pid) = libc::pid_t::try_from(pid) {
+        // Safety: sending a signal has no memory safety requirement.
+        let _ = unsafe { libc::kill(pid, libc::SIGKILL) };
+    }
+}
+/// Kills a process, does nothing since process IDs are unknown outside of Linux.
+#[cfg(not(target_os = "linux"))]
+fn kill(_pid: u32) {}
+
+/// A process [watched](Cancel::watch) by some cancellation handles, stops watching it when dropped.
+pub(crate) struct Watch {
+    /// Process ID.
+    pid: u32,
+    /// Processes to kill of the handles watching the process.
+    pids: Vec<Arc<Mutex<Set<u32>>>>,
+    /// Query timeout of the handle watching the process, see [`Self::watchdog`].
+    query_timeout: Option<time::Duration>,
+}
+impl Watch {
+    /// Starts a watchdog killing the process once the query timeout of the handle watching it
+    /// expires, `None` if there is no query timeout.
+    ///
+    /// The watchdog kills the process the same way a [`Cancel`] does.
+    pub(crate) fn watchdog(&self) -> Option<Watchdog> {
+        let timeout = self.query_timeout?;
+        let cancel = Cancel::new();
+        let watch = cancel.watch(self.pid);
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(timeout) {
+                    cancel.cancel()
+                }
+            })
+        };
+        Some(Watchdog {
+            cancel,
+            _watch: watch,
+            stop,
+            thread,
+        })
+    }
+}
+
+/// Kills a process when a query runs for too long, see [`Watch::watchdog`].
+pub(crate) struct Watchdog {
+    /// Cancelled by the watchdog thread when the query times out.
+    cancel: Cancel,
+    /// Watches the process with [`Self::cancel`].
+    _watch: Watch,
+    /// Stops the watchdog thread.
+    stop: mpsc::Sender<()>,
+    /// Watchdog thread.
+    thread: std::thread::JoinHandle<()>,
+}
+impl Watchdog {
+    /// Stops the watchdog, true if it killed the process.
+    pub(crate) fn stop(self) -> bool {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+        self.cancel.is_cancelled()
+    }
+}
+impl Drop for Watch {
+    fn drop(&mut self) {
+        for pids in self.pids.iter() {
+            let _ = pids.lock().expect("cancel lock poisoned").remove(&self.pid);
+        }
+    }
+}
+
+/// A verification engine.
+///
+/// An engine is first [prepared](Self::prepare) with the pre-processed system, and then
+/// [ran](Self::run) once on the candidates left undecided by the previous engines.
+///
+/// Engines must be sound: a candidate can only be reported proved if it is an invariant of the
+/// system, and falsified with a counterexample that is a trace of the system.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{
+/// #     check::{self, engine::{Engine, Engines, Events}, preprocess::Pipeline, Outcome, Verdict},
+/// #     prelude::Res, rsmt2::SmtConf, trans::Sys,
+/// # };
+/// # use std::collections::BTreeSet as Set;
+/// /// Proves candidates that are `true` after pre-processing.
+/// #[derive(Default)]
+/// struct Trivial {
+///     sys: Option<Sys>,
+/// }
+/// impl Engine for Trivial {
+///     fn name(&self) -> &str {
+///         "trivial"
+///     }
+///     fn prepare(&mut self, sys: &Sys, _config: &check::Config) -> Res<()> {
+///         self.sys = Some(sys.restrict(|_| true));
+///         Ok(())
+///     }
+///     fn run(
+///         &mut self,
+///         candidates: &Set<String>,
+///         _budget: Option<std::time::Duration>,
+///         _events: &Events,
+///     ) -> Res<Outcome> {
+///         let mut outcome = Outcome::new();
+///         let sys = self.sys.as_ref().expect("engine was not prepared");
+///         for name in candidates {
+///             if sys.po_s()[name].to_string() == "true" {
+///                 let _ = outcome.verdicts.insert(name.clone(), Verdict::Proved);
+///             }
+///         }
+///         Ok(outcome)
+///     }
+/// }
+///
+/// let sys = mikino_api::parse::trans(r#"
+/// svars { x: int }
+/// init { x = 0 }
+/// trans { 'x = x + 1 }
+/// candidates { "trivial": x = x, "positive": x ≥ 0 }
+/// "#).unwrap();
+/// let mut engines = Engines::new();
+/// engines.push(Trivial::default);
+/// let config = check::Config::new(SmtConf::z3("z3"))
+///     .preprocess(Pipeline::standard())
+///     .engines(engines);
+/// let outcome = check::run(&sys, &config).unwrap();
+/// assert!(outcome["trivial"].is_proved());
+/// assert!(outcome["positive"].is_unknown());
+/// ```
+pub trait Engine: Send {
+    /// Name of the engine, used in events and error messages.
+    fn name(&self) -> &str;
+    /// Prepares the engine for a system, which went through the pre-processing pipeline.
+    fn prepare(&mut self, sys: &Sys, config: &Config) -> Res<()>;
+    /// Checks some candidates of the system.
+    ///
+    /// The outcome must only mention `candidates`, candidates it does not mention are unknown.
+    /// The engine should stop after `budget`, if any, and report the candidates it did not decide
+    /// as unknown.
+    fn run(
+        &mut self,
+        candidates: &Set<String>,
+        budget: Option<time::Duration>,
+        events: &Events,
+    ) -> Res<Outcome>;
+}
+
+/// Creates engines.
+type Factory = Arc<dyn Fn() -> Box<dyn Engine> + Send + Sync>;
+
+/// A sequence of engines, see the [module-level documentation](self).
+///
+/// Stores engine constructors, so that each check, and each thread when checking in parallel, uses
+/// its own engines.
+#[derive(Clone, Default)]
+pub struct Engines {
+    /// Engine names and constructors, in order.
+    engines: Vec<(String, Factory)>,
+}
+impl fmt::Debug for Engines {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.names()).finish()
+    }
+}
+impl Engines {
+    /// Empty portfolio, decides nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Standard portfolio, induction followed by BMC.
+    pub fn standard() -> Self {
+        let mut slf = Self::new();
+        slf.push(Induction::new).push(Bmc::new);
+        slf
+    }
+
+    /// True if there are no engines.
+    pub fn is_empty(&self) -> bool {
+        self.engines.is_empty()
+    }
+    /// Number of engines.
+    pub fn len(&self) -> usize {
+        self.engines.len()
+    }
+    /// Names of the engines, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.engines.iter().map(|(name, _)| name as &str)
+    }
+    /// True if this is the [standard portfolio](Self::standard).
+    pub fn is_standard(&self) -> bool {
+        self.names().eq(Self::standard().names())
+    }
+
+    /// Appends an engine, given its constructor.
+    pub fn push<E: Engine + 'static>(
+        &mut self,
+        factory: impl Fn() -> E + Send + Sync + 'static,
+    ) -> &mut Self {
+        let len = self.len();
+        self.insert(len, factory)
+    }
+    /// Inserts an engine at some position, panics if `idx > self.len()`.
+    pub fn insert<E: Engine + 'static>(
+        &mut self,
+        idx: usize,
+        factory: impl Fn() -> E + Send + Sync + 'static,
+    ) -> &mut Self {
+        let name = factory().name().to_string();
+        let factory: Factory = Arc::new(move || Box::new(factory()));
+        self.engines.insert(idx, (name, factory));
+        self
+    }
+
+    /// Creates the engines, in order.
+    pub fn instantiate(&self) -> impl Iterator<Item = Box<dyn Engine>> + '_ {
+        self.engines.iter().map(|(_, factory)| factory())
+    }
+}
+
+/// Solver configuration of the built-in engines.
+pub(super) struct Setup {
+    /// System.
+    pub sys: Sys,
+    /// Solver configuration.
+    pub conf: SmtConf,
+    /// Tee directory.
+    pub tee: Option<PathBuf>,
+    /// Cancellation handle.
+    pub cancel: Cancel,
+    /// True if the solver queries are limited, see [`Config::query_timeout`].
+    pub limited: bool,
+    /// True if the queries are explained, see [`Config::explain`].
+    pub explain: bool,
+}
+impl Setup {
+    /// Constructor.
+    ///
+    /// The cancellation handle also kills the solvers of queries reaching the query timeout.
+    pub fn new(sys: &Sys, config: &Config) -> Res<Self> {
+        Ok(Self {
+            sys: sys.restrict(|_| true),
+            conf: config.limited_conf()?,
+            tee: config.tee.clone(),
+            cancel: config.cancel.with_query_timeout(config.query_timeout),
+            limited: config.query_timeout.is_some() || config.memory_limit.is_some(),
+            explain: config.explain,
+        })
+    }
+
+    /// Reports an [`Event::Explain`] if the queries are explained.
+    ///
+    /// Queries without an hsmt syntax are only reported as progress.
+    pub fn explain(
+        &self,
+        events: &Events,
+        engine: &str,
+        description: impl Into<String>,
+        query: impl FnOnce() -> Res<String>,
+    ) {
+        if !self.explain {
+            return;
+        }
+        let description = description.into();
+        match query() {
+            Ok(query) => events.emit(Event::Explain {
+                engine: engine.into(),
+                description,
+                query,
+            }),
+            Err(e) => events.progress(
+                engine,
+                format!("{} (cannot explain the query: {})", description, e),
+            ),
+        }
+    }
+
+    /// True if an error is a solver query reaching the query limits.
+    ///
+    /// Solvers answer `unknown` when they reach a limit, which is only a timeout if there are
+    /// limits.
+    pub fn is_timeout(&self, err: &ErrorChain) -> bool {
+        use rsmt2::errors::ErrorKind as EK;
+        err.smt_kinds().any(|kind| match kind {
+            EK::Timeout => true,
+            EK::Unknown => self.limited,
+            _ => false,
+        })
+    }
+}
+
+/// Gives a [`Verdict::Timeout`] to the candidates without a verdict.
+pub(super) fn time_out(outcome: &mut Outcome, candidates: &Set<String>, bmc_depth: Option<Unroll>) {
+    for name in candidates {
+        let _ = outcome
+            .verdicts
+            .entry(name.clone())
+            .or_insert(Verdict::Timeout { bmc_depth });
+    }
+}
+
+/// Extracts the setup of an engine, fails if it was not prepared.
+pub(super) fn setup<'a>(setup: &'a Option<Setup>, engine: &str) -> Res<&'a Setup> {
+    match setup.as_ref() {
+        Some(setup) => Ok(setup),
+        None => bail!("engine `{}` was not prepared", engine),
+    }
+}
+
+/// Induction engine.
+///
+/// Falsifies the candidates that do not hold in the initial states, and proves the candidates
+/// that are inductive assuming the other candidates that hold in the initial states.
+#[derive(Default)]
+pub struct Induction {
+    /// Setup, `None` until prepared.
+    setup: Option<Setup>,
+}
+impl Induction {
+    /// Name of the engine.
+    pub const NAME: &'static str = "induction";
+    /// Constructor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl Engine for Induction {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+    fn prepare(&mut self, sys: &Sys, config: &Config) -> Res<()> {
+        self.setup = Some(Setup::new(sys, config)?);
+        Ok(())
+    }
+    fn run(
+        &mut self,
+        candidates: &Set<String>,
+        _budget: Option<time::Duration>,
+        events: &Events,
+    ) -> Res<Outcome> {
+        let setup = setup(&self.setup, Self::NAME)?;
+        let Setup {
+            sys,
+            conf,
+            tee,
+            cancel,
+            ..
+        } = setup;
+        let mut outcome = Outcome::new();
+        let sys = &sys.restrict(|name| candidates.contains(name));
+        if sys.po_s().is_empty() {
+            return Ok(outcome);
+        }
+
+        setup.explain(
+            events,
+            Self::NAME,
+            "base case: is there an initial state falsifying some candidate?",
+            || super::explain::base_query(sys),
+        );
+        let mut base = Base::new(sys, conf.clone(), tee.clone())?.cancellable(cancel);
+        let base_res = match base.check() {
+            Ok(res) => res,
+            Err(_) if cancel.is_cancelled() => {
+                events.progress(Self::NAME, "cancelled");
+                return Ok(outcome);
+            }
+            Err(e) if setup.is_timeout(&e) => {
+                events.progress(Self::NAME, "base check timed out");
+                time_out(&mut outcome, candidates, None);
+                return Ok(outcome);
+            }
+            Err(e) => return Err(e.chain_err(|| "during base check")),
+        };
+        for (name, cex) in base_res.cexs.iter() {
+            let _ = outcome.verdicts.insert(
+                name.to_string(),
+                Verdict::Falsified {
+                    depth: 0,
+                    cex: cex.clone(),
+                },
+            );
+        }
+        events.emit(Event::BaseDone {
+            engine: Self::NAME.into(),
+            holding: base_res.okay.len(),
+        });
+
+        let step_sys = sys.restrict(|name| base_res.okay.contains(name));
+        if !step_sys.po_s().is_empty() {
+            setup.explain(
+                events,
+                Self::NAME,
+                "step case: is there a state verifying all the candidates holding initially, with \
+                a successor (primed variables) falsifying one of them?",
+                || super::explain::step_query(&step_sys),
+            );
+            let mut step = Step::new(&step_sys, conf.clone(), tee.clone())?.cancellable(cancel);
+            match step.check() {
+                Ok(step_res) => {
+                    events.emit(Event::StepDone {
+                        engine: Self::NAME.into(),
+                        proved: step_res.okay.len(),
+                    });
+                    for name in step_res.okay.iter() {
+                        let _ = outcome.verdicts.insert(name.to_string(), Verdict::Proved);
+                    }
+                }
+                Err(_) if cancel.is_cancelled() => events.progress(Self::NAME, "cancelled"),
+                Err(e) if setup.is_timeout(&e) => {
+                    events.progress(Self::NAME, "step check timed out");
+                    time_out(&mut outcome, candidates, None);
+                }
+                Err(e) => return Err(e.chain_err(|| "during step check")),
+            }
+        }
+        for name in base_res.okay.iter() {
+            let _ = outcome
+                .verdicts
+                .entry(name.to_string())
+                .or_insert(Verdict::Unknown { bmc_depth: None });
+        }
+        Ok(outcome)
+    }
+}
+
+/// Bounded model checking engine.
+///
+/// Looks for falsifications up to [`Config::bmc_max`] with the [`Config::unrolling`] strategy,
+/// does nothing if it is `None`. Stops early when its budget expires, when the check is
+/// [cancelled](Cancel), or when a query reaches the [query limits](Config::query_timeout).
+#[derive(Default)]
+pub struct Bmc {
+    /// Setup, `None` until prepared.
+    setup: Option<Setup>,
+    /// Maximum depth.
+    max: Option<Unroll>,
+    /// Unrolling strategy.
+    unrolling: Unrolling,
+}
+impl Bmc {
+    /// Name of the engine.
+    pub const NAME: &'static str = "bmc";
+    /// Constructor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl Engine for Bmc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+    fn prepare(&mut self, sys: &Sys, config: &Config) -> Res<()> {
+        self.setup = Some(Setup::new(sys, config)?);
+        self.max = config.bmc_max;
+        self.unrolling = config.unrolling;
+        Ok(())
+    }
+    fn run(
+        &mut self,
+        candidates: &Set<String>,
+        budget: Option<time::Duration>,
+        events: &Events,
+    ) -> Res<Outcome> {
+        let setup = setup(&self.setup, Self::NAME)?;
+        let Setup {
+            sys,
+            conf,
+            tee,
+            cancel,
+            ..
+        } = setup;
+        let mut outcome = Outcome::new();
+        let max = match self.max {
+            Some(max) => max,
+            None => {
+                for name in candidates {
+                    let _ = outcome
+                        .verdicts
+                        .insert(name.clone(), Verdict::Unknown { bmc_depth: None });
+                }
+                return Ok(outcome);
+            }
+        };
+        let sys = &sys.restrict(|name| candidates.contains(name));
+        if sys.po_s().is_empty() {
+            return Ok(outcome);
+        }
+        let deadline = budget.map(|budget| std::time::Instant::now() + budget);
+
+        let bmc_res: BmcRes = CheckRes::new(sys).into();
+        let mut bmc = BmcChecker::with_unrolling(
+            sys,
+            conf.clone(),
+            tee.clone(),
+            bmc_res,
+            self.unrolling,
+            max,
+        )?
+        .cancellable(cancel);
+        let mut timed_out = false;
+        while !bmc.is_done() && bmc.next_check_step() <= max {
+            if deadline
+                .map(|deadline| std::time::Instant::now() >= deadline)
+                .unwrap_or(false)
+            {
+                events.progress(Self::NAME, "budget expired");
+                break;
+            }
+            if cancel.is_cancelled() {
+                events.progress(Self::NAME, "cancelled");
+                break;
+            }
+            let depth = bmc.next_check_step();
+            let falsified = bmc.res().cexs.len();
+            events.emit(Event::DepthStart {
+                engine: Self::NAME.into(),
+                depth,
+            });
+            setup.explain(
+                events,
+                Self::NAME,
+                format!(
+                    "depth {}: is there a run of {} transition(s) from an initial state to a state \
+                    falsifying some candidate?",
+                    depth, depth
+                ),
+                || {
+                    let okay = &bmc.res().okay;
+                    let sys = sys.restrict(|name| okay.contains(&name));
+                    super::explain::bmc_query(&sys, depth)
+                },
+            );
+            match bmc.next_check() {
+                Ok(_) => events.emit(Event::DepthDone {
+                    engine: Self::NAME.into(),
+                    depth,
+                    falsified: bmc.res().cexs.len() - falsified,
+                }),
+                Err(_) if cancel.is_cancelled() => {
+                    events.progress(Self::NAME, "cancelled");
+                    break;
+                }
+                Err(e) if setup.is_timeout(&e) => {
+                    events.progress(Self::NAME, "query timed out");
+                    timed_out = true;
+                    break;
+                }
+                Err(e) => return Err(e.chain_err(|| "during BMC")),
+            }
+        }
+        // Last depth checked, `None` if nothing was checked.
+        let depth = bmc.next_check_step().checked_sub(1);
+        let bmc_res = bmc.destroy()?;
+        for (name, cex) in bmc_res.cexs.iter() {
+            let _ = outcome.verdicts.insert(
+                name.to_string(),
+                Verdict::Falsified {
+                    depth: cex_depth(cex),
+                    cex: cex.clone(),
+                },
+            );
+        }
+        for name in bmc_res.okay.iter() {
+            let verdict = if timed_out {
+                Verdict::Timeout { bmc_depth: depth }
+            } else {
+                Verdict::Unknown { bmc_depth: depth }
+            };
+            let _ = outcome.verdicts.insert(name.to_string(), verdict);
+        }
+        Ok(outcome)
+    }
+}
@@ -0,0 +1,341 @@
+//! Narrated (pedagogical) versions of the checks.
+//!
+//! The functions in this module run the same checks as the rest of [`check`](super), but produce
+//! a markdown document explaining what is happening: the actual SMT-LIB 2 queries, the solver's
+//! answers, and what they mean.
+//!
+//! The [base](base_query), [step](step_query) and [BMC](bmc_query) queries can also be rendered in
+//! hsmt syntax. When [`Config::explain`](super::Config::explain) is set, the built-in engines
+//! report each query they check this way as an [`Event::Explain`](super::engine::Event::Explain).
+
+crate::prelude!();
+
+use rsmt2::print::Expr2Smt;
+
+use super::{cexs::Cex, suggest, Base, Step};
+use expr::{display::Formatters, Expr, Op, SExpr};
+use trans::Sys;
+
+/// Unrolling depth used to sample reachable states for strengthening suggestions.
+const SUGGEST_DEPTH: Unroll = 5;
+
+/// SMT-LIB 2 representation of an expression at some step.
+fn smt2(expr: &impl Expr2Smt<Unroll>, step: Unroll) -> Res<String> {
+    let mut buf: Vec<u8> = vec![];
+    expr.expr_to_smt2(&mut buf, step)?;
+    match String::from_utf8(buf) {
+        Ok(s) => Ok(s),
+        Err(e) => bail!("illegal UTF-8 in SMT-LIB 2 expression: {}", e),
+    }
+}
+
+/// Conjunction of the candidates of a system.
+fn candidates(sys: &Sys) -> Res<Expr> {
+    let mut po_s: Vec<Expr> = sys.po_s().values().cloned().collect();
+    match po_s.len() {
+        0 => Ok(Expr::new_cst(true.into())),
+        1 => Ok(po_s.pop().expect("[unreachable] one candidate")),
+        _ => Expr::new_op(Op::And, po_s),
+    }
+}
+
+/// Base case query of induction over the candidates of a system, in hsmt syntax.
+///
+/// The query is satisfiable if some initial state falsifies some candidate.
+///
+/// # Errors
+///
+/// - when some expression has no hsmt syntax, see [`expr::hsmt`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::explain, parse};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"positive\": cnt ≥ 0, \"small\": cnt < 3 }",
+/// )
+/// .unwrap();
+/// assert_eq!(explain::base_query(&sys).unwrap(), "cnt = 0 ⋀ ¬(cnt ≥ 0 ⋀ cnt < 3)");
+/// ```
+pub fn base_query(sys: &Sys) -> Res<String> {
+    let not_po_s = Expr::new_op(Op::Not, vec![candidates(sys)?])?;
+    Expr::new_op(Op::And, vec![sys.init().clone(), not_po_s])?.to_hsmt()
+}
+
+/// Step case query of induction over the candidates of a system, in hsmt syntax.
+///
+/// The query is satisfiable if some state verifying all the candidates has a successor falsifying
+/// some candidate. As in transition predicates, primed variables are the ones of the successor.
+///
+/// # Errors
+///
+/// - when some expression has no hsmt syntax, see [`expr::hsmt`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::explain, parse};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     explain::step_query(&sys).unwrap(),
+///     "cnt ≥ 0 ⋀ 'cnt = if inc { cnt + 1 } else { cnt } ⋀ ¬('cnt ≥ 0)",
+/// );
+/// ```
+pub fn step_query(sys: &Sys) -> Res<String> {
+    let po_s = candidates(sys)?;
+    let not_po_s = SExpr::new_op(Op::Not, vec![po_s.clone().into_sexpr(true)])?;
+    SExpr::new_op(
+        Op::And,
+        vec![po_s.into_sexpr(false), sys.trans().clone(), not_po_s],
+    )?
+    .to_hsmt()
+}
+
+/// BMC query at some depth over the candidates of a system, in hsmt syntax.
+///
+/// The query is satisfiable if some run of `depth` transitions from an initial state reaches a
+/// state falsifying some candidate. It spans several lines, one for each of the initial predicate,
+/// the transition predicate and the candidates, each preceded by a comment saying which state(s)
+/// it constrains.
+///
+/// # Errors
+///
+/// - when some expression has no hsmt syntax, see [`expr::hsmt`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::explain, parse};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"small\": cnt < 3 }",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     explain::bmc_query(&sys, 3).unwrap(),
+///     "\
+/// // state 0 is initial
+/// cnt = 0
+/// // each state `k` with 0 ≤ k < 3 leads to state `k + 1`
+/// 'cnt = if inc { cnt + 1 } else { cnt }
+/// // state 3 falsifies some candidate
+/// ¬(cnt < 3)",
+/// );
+/// assert_eq!(
+///     explain::bmc_query(&sys, 0).unwrap(),
+///     "// state 0 is initial\ncnt = 0\n// state 0 falsifies some candidate\n¬(cnt < 3)",
+/// );
+/// ```
+pub fn bmc_query(sys: &Sys, depth: Unroll) -> Res<String> {
+    let mut lines = vec!["// state 0 is initial".to_string(), sys.init().to_hsmt()?];
+    if depth > 0 {
+        lines.push(format!(
+            "// each state `k` with 0 ≤ k < {} leads to state `k + 1`",
+            depth
+        ));
+        lines.push(sys.trans().to_hsmt()?);
+    }
+    lines.push(format!("// state {} falsifies some candidate", depth));
+    lines.push(Expr::new_op(Op::Not, vec![candidates(sys)?])?.to_hsmt()?);
+    Ok(lines.join("\n"))
+}
+
+/// Pushes a code block.
+fn code_block(s: &mut String, lang: &str, lines: impl IntoIterator<Item = String>) {
+    s.push_str("```");
+    s.push_str(lang);
+    s.push('\n');
+    for line in lines {
+        s.push_str(&line);
+        s.push('\n');
+    }
+    s.push_str("```\n\n");
+}
+
+/// Pushes the values of a counterexample/CTI.
+fn push_trace(s: &mut String, sys: &Sys, cex: &Cex) {
+    code_block(
+        s,
+        "",
+        cex.render(sys.decls(), &Formatters::new())
+            .lines()
+            .map(String::from),
+    )
+}
+
+/// Narrated 1-induction over a single candidate.
+///
+/// Produces a markdown document describing
+///
+/// - the base case query, *is there an initial state falsifying the candidate?*
+/// - the step case query, *is there a state verifying the candidate with a successor falsifying
+///   it?*
+/// - the solver's verdicts, and
+/// - when the step case fails, the *Counterexample To Induction* (CTI), what a strengthening
+///   invariant must exclude, and [suggestions](suggest::suggestions) of such invariants.
+///
+/// # Errors
+///
+/// - when `candidate` is not a candidate of `sys`, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, rsmt2::SmtConf};
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let doc = check::explain::induction(&sys, "cnt is positive", SmtConf::z3("z3")).unwrap();
+/// println!("{}", doc);
+/// ```
+pub fn induction(sys: &Sys, candidate: &str, conf: SmtConf) -> Res<String> {
+    let po = match sys.po_s().get(candidate) {
+        Some(po) => po,
+        None => bail!("unknown candidate `{}`", candidate),
+    };
+    let sys = sys.restrict(|name| name == candidate);
+
+    let mut s = format!("# Induction over candidate `{}`\n\n", candidate);
+    s.push_str(&format!(
+        "We want to prove that `{}` is an invariant: that it holds in all reachable states of the \
+        system. Induction splits this proof in two parts, the *base case* and the *step case*.\n\n",
+        po,
+    ));
+
+    s.push_str(
+        "We write `v@k` for the value of state variable `v` at step `k`. The solver declares one \
+        such constant for each state variable and each step it needs:\n\n",
+    );
+    let decls: Vec<String> = (0..2)
+        .flat_map(|step| {
+            sys.decls()
+                .all()
+                .map(move |var| format!("(declare-const {}@{} {})", var.id(), step, var.typ()))
+        })
+        .collect();
+    code_block(&mut s, "smt", decls);
+
+    // Base case.
+    s.push_str("## Base case\n\n");
+    s.push_str(
+        "The base case checks that the candidate holds in all initial states. We ask the solver \
+        whether there is a state verifying `init` but **not** the candidate:\n\n",
+    );
+    code_block(
+        &mut s,
+        "smt",
+        vec![
+            format!("(assert {})", smt2(sys.init(), 0)?),
+            format!("(assert {})", smt2(&po.negated(), 0)?),
+            "(check-sat)".into(),
+        ],
+    );
+    let base = Base::new(&sys, conf.clone(), None)?
+        .check()
+        .chain_err(|| "during base check")?;
+    if let Some(cex) = base.cexs.values().next() {
+        s.push_str(
+            "The solver answers `sat`: the candidate is **falsified** by the following initial \
+            state.\n\n",
+        );
+        push_trace(&mut s, &sys, cex);
+        s.push_str("The candidate does not hold, there is no need to go further.\n");
+        return Ok(s);
+    }
+    s.push_str(
+        "The solver answers `unsat`: there is no initial state falsifying the candidate, \
+        **the base case holds**.\n\n",
+    );
+
+    // Step case.
+    s.push_str("## Step case\n\n");
+    s.push_str(
+        "The step case checks that the candidate is *preserved* by the transition relation: from \
+        any state verifying the candidate, all successors verify it too. We ask the solver whether \
+        there is a state `@0` verifying the candidate with a successor `@1` that does **not** \
+        verify it:\n\n",
+    );
+    code_block(
+        &mut s,
+        "smt",
+        vec![
+            format!("(assert {})", smt2(po, 0)?),
+            format!("(assert {})", smt2(sys.trans(), 0)?),
+            format!("(assert {})", smt2(&po.negated(), 1)?),
+            "(check-sat)".into(),
+        ],
+    );
+    let step = Step::new(&sys, conf.clone(), None)?
+        .check()
+        .chain_err(|| "during step check")?;
+
+    if let Some(cti) = step.cexs.values().next() {
+        s.push_str(
+            "The solver answers `sat`: the candidate is **not inductive**. The model is a \
+            *Counterexample To Induction* (CTI), a state verifying the candidate whose successor \
+            does not:\n\n",
+        );
+        push_trace(&mut s, &sys, cti);
+        s.push_str("## What now?\n\n");
+        s.push_str(
+            "A CTI does not mean the candidate is false: state `@0` may be unreachable. To prove \
+            the candidate, find an invariant `I` such that `candidate ∧ I` is inductive. Such an \
+            `I` must exclude the CTI, *i.e.* be false on state `@0`. The weakest such strengthening \
+            is the negation of the state itself:\n\n",
+        );
+        let pre_state: Vec<String> = cti
+            .trace
+            .get(&0)
+            .into_iter()
+            .flat_map(|vals| vals.iter())
+            .map(|(var, val)| format!("{} = {}", var.id(), val))
+            .collect();
+        code_block(&mut s, "", Some(format!("¬({})", pre_state.join(" ∧ "))));
+        s.push_str(
+            "It is rarely a good one though: a useful strengthening excludes *all* the unreachable \
+            states looking like this one. Look at the CTI and ask yourself *why* state `@0` cannot \
+            be reached, then add that reason as a new candidate. If instead state `@0` is \
+            reachable, then the candidate is false and BMC will eventually find a counterexample.\n",
+        );
+
+        let samples = suggest::reachable_samples(&sys, conf, SUGGEST_DEPTH, 3)
+            .chain_err(|| "while sampling reachable states")?;
+        let suggestions = cti
+            .trace
+            .get(&0)
+            .map(|cti| suggest::suggestions(sys.decls(), &samples, cti))
+            .unwrap_or_default();
+        if !suggestions.is_empty() {
+            s.push_str(&format!(
+                "\nThe following predicates hold on all {} reachable states found by unrolling the \
+                system up to depth {}, but not on state `@0`. They are not necessarily invariants, \
+                but they are good candidates for a strengthening:\n\n",
+                samples.len(),
+                SUGGEST_DEPTH,
+            ));
+            for expr in suggestions {
+                s.push_str(&format!("- `{}`\n", expr));
+            }
+        }
+    } else {
+        s.push_str(
+            "The solver answers `unsat`: the candidate is preserved by the transition relation, \
+            **the step case holds**.\n\n",
+        );
+        s.push_str("## Conclusion\n\n");
+        s.push_str(
+            "Both the base case and the step case hold: the candidate holds in all initial states \
+            and is preserved by all transitions. By induction, it holds in all reachable states, \
+            **the candidate is proved**.\n",
+        );
+    }
+
+    Ok(s)
+}
@@ -0,0 +1,467 @@
+//! Explicit-state exploration of finite systems, no solver involved.
+//!
+//! An [`Explorer`] enumerates the whole state space of a system, and explores the states reachable
+//! from the initial states breadth-first. The answers are complete: a candidate is either proved or
+//! falsified, with a shortest counterexample, and the exploration yields the exact
+//! [diameter](Exploration::diameter) of the system.
+//!
+//! Boolean variables are finite. Integer variables must be given bounds with [`Explorer::bound`],
+//! and rational variables are not supported. Bounds are *assumptions*: the explored behaviors are
+//! the ones where all variables stay within their bounds. So, results on systems with bounded
+//! integers only hold for the bounded system.
+//!
+//! The cost of the exploration is quadratic in the size of the state space, which is why this
+//! engine is meant for small systems; its size is limited by [`Explorer::max_states`]. The
+//! exploration can also stop at some [depth](Explorer::max_depth), candidates that hold on all the
+//! states explored are then unknown.
+//!
+//! [`Explorer::graph`] yields the [`StateGraph`] of the reachable states and their transitions,
+//! which can be exported to the DOT format of Graphviz with the states falsifying some candidates
+//! highlighted.
+
+crate::prelude!();
+
+use super::{cexs::Cex, driver::Verdict, replay, trace::Trace};
+use expr::{Cst, Var};
+use trans::Sys;
+
+/// Default maximal size of the state space, see [`Explorer::max_states`].
+pub const DEFAULT_MAX_STATES: usize = 1 << 12;
+
+/// Result of an explicit-state exploration.
+#[derive(Debug, Clone)]
+pub struct Exploration {
+    /// Size of the state space.
+    pub states: usize,
+    /// Number of reachable states.
+    pub reachable: usize,
+    /// Largest distance, in steps, between the initial states and a reachable state.
+    pub diameter: Unroll,
+    /// Verdict for each candidate, either proved or falsified.
+    pub verdicts: Map<String, Verdict>,
+}
+
+/// Graph of the reachable states of a system, see [`Explorer::graph`].
+///
+/// States are identified by their index in [`Self::states`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateGraph {
+    /// State variables, in the order of the values of the states.
+    pub vars: Vec<Var>,
+    /// Reachable states, in breadth-first order from the initial states.
+    pub states: Vec<Vec<Cst>>,
+    /// Initial states.
+    pub init: Set<usize>,
+    /// Transitions between states.
+    pub edges: Set<(usize, usize)>,
+    /// States whose successors were not explored because of [`Explorer::max_depth`].
+    pub frontier: Set<usize>,
+    /// Candidates falsified by each state, states that falsify no candidate do not appear.
+    pub violations: Map<usize, Set<String>>,
+}
+impl StateGraph {
+    /// True if the graph has all the reachable states and their transitions.
+    pub fn is_complete(&self) -> bool {
+        self.frontier.is_empty()
+    }
+
+    /// DOT representation, for Graphviz.
+    ///
+    /// An edge from a point node leads to each initial state. States falsifying some candidate are
+    /// red and list the candidates they falsify, the states of the frontier are dashed.
+    pub fn to_dot(&self) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut s = String::from("digraph states {\n    node [shape = box];\n");
+        s.push_str("    init [shape = point];\n");
+        for (idx, state) in self.states.iter().enumerate() {
+            let mut label: Vec<String> = self
+                .vars
+                .iter()
+                .zip(state.iter())
+                .map(|(var, val)| escape(&format!("{} = {}", var.id(), val)))
+                .collect();
+            let mut attrs = vec![];
+            if let Some(falsified) = self.violations.get(&idx) {
+                for name in falsified {
+                    label.push(escape(&format!("falsifies `{}`", name)))
+                }
+                attrs.push("color = red, fontcolor = red");
+            }
+            if self.frontier.contains(&idx) {
+                attrs.push("style = dashed");
+            }
+            s.push_str(&format!("    s{} [label = \"{}\"", idx, label.join("\\n")));
+            for attr in attrs {
+                s.push_str(", ");
+                s.push_str(attr);
+            }
+            s.push_str("];\n");
+        }
+        for idx in self.init.iter() {
+            s.push_str(&format!("    init -> s{};\n", idx));
+        }
+        for (src, tgt) in self.edges.iter() {
+            s.push_str(&format!("    s{} -> s{};\n", src, tgt));
+        }
+        s.push_str("}\n");
+        s
+    }
+}
+
+/// Explicit-state explorer, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::{explicit::Explorer, Verdict}, parse};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int, up: bool }
+/// init { cnt = 0, up }
+/// trans {
+///     'up = (if up { cnt < 2 } else { cnt ≤ 0 }),
+///     'cnt = (if 'up { cnt + 1 } else { cnt - 1 }),
+/// }
+/// candidates {
+///     "cnt is positive": cnt ≥ 0,
+///     "cnt is not 2": ¬(cnt = 2),
+/// }
+/// "#).unwrap();
+/// let exploration = Explorer::new(&sys).bound("cnt", -5, 5).run().unwrap();
+/// assert_eq!(exploration.states, 22);
+/// assert_eq!(exploration.reachable, 5);
+/// assert_eq!(exploration.diameter, 4);
+/// assert!(exploration.verdicts["cnt is positive"].is_proved());
+/// // Shortest counterexample.
+/// match &exploration.verdicts["cnt is not 2"] {
+///     Verdict::Falsified { depth, .. } => assert_eq!(*depth, 2),
+///     verdict => panic!("unexpected verdict {:?}", verdict),
+/// }
+///
+/// // Integer variables need bounds.
+/// let err = Explorer::new(&sys).run().unwrap_err();
+/// assert_eq!(err.to_string(), "integer variable `cnt` has no bounds");
+/// ```
+#[derive(Clone)]
+pub struct Explorer<'sys> {
+    /// System to explore.
+    sys: &'sys Sys,
+    /// Bounds for integer variables, inclusive.
+    bounds: Map<String, (Int, Int)>,
+    /// Maximal size of the state space.
+    max_states: usize,
+    /// Maximal distance to the initial states of the explored states, if any.
+    max_depth: Option<Unroll>,
+}
+impl<'sys> Explorer<'sys> {
+    /// Constructor, no bounds, [`DEFAULT_MAX_STATES`] maximal states, no maximal depth.
+    pub fn new(sys: &'sys Sys) -> Self {
+        Self {
+            sys,
+            bounds: Map::new(),
+            max_states: DEFAULT_MAX_STATES,
+            max_depth: None,
+        }
+    }
+
+    /// Bounds an integer variable, both bounds are inclusive.
+    pub fn bound(mut self, var: impl Into<String>, lo: impl Into<Int>, hi: impl Into<Int>) -> Self {
+        let _ = self.bounds.insert(var.into(), (lo.into(), hi.into()));
+        self
+    }
+    /// Sets the maximal size of the state space, exploration fails on bigger state spaces.
+    pub fn max_states(mut self, max_states: usize) -> Self {
+        self.max_states = max_states;
+        self
+    }
+    /// Sets the maximal depth: the successors of the states at this distance from the initial
+    /// states are not explored.
+    pub fn max_depth(mut self, max_depth: Unroll) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+    /// True if the successors of a state at some distance from the initial states are explored.
+    fn expands(&self, depth: Unroll) -> bool {
+        self.max_depth.is_none_or(|max| depth < max)
+    }
+
+    /// Values of each variable of the system.
+    fn domains(&self) -> Res<Vec<(Var, Vec<Cst>)>> {
+        for var in self.bounds.keys() {
+            match self.sys.decls().get_var(var) {
+                Some(v) if v.typ() == Typ::Int => (),
+                Some(v) => bail!("cannot bound variable `{}` of type `{}`", var, v.typ()),
+                None => bail!("cannot bound unknown variable `{}`", var),
+            }
+        }
+        let mut res = vec![];
+        for var in self.sys.decls().all() {
+            let domain = match var.typ() {
+                Typ::Bool => vec![Cst::bool(false), Cst::bool(true)],
+                Typ::Int => match self.bounds.get(var.id()) {
+                    Some((lo, hi)) => {
+                        let mut domain = vec![];
+                        let mut val = lo.clone();
+                        while &val <= hi {
+                            if domain.len() > self.max_states {
+                                bail!("state space has more than {} states", self.max_states)
+                            }
+                            domain.push(Cst::int(val.clone()));
+                            val += 1;
+                        }
+                        domain
+                    }
+                    None => bail!("integer variable `{}` has no bounds", var.id()),
+                },
+                Typ::BitVec(width) => {
+                    if width >= usize::BITS || 1 << width > self.max_states {
+                        bail!("state space has more than {} states", self.max_states)
+                    }
+                    (0..1usize << width)
+                        .map(|val| Cst::bv(width, val))
+                        .collect()
+                }
+                Typ::Enum(name) => match self.sys.decls().enum_variants(&name) {
+                    Some(variants) => variants
+                        .iter()
+                        .map(|variant| Cst::variant(name.clone(), variant.clone()))
+                        .collect(),
+                    None => bail!("unknown enumeration type `{}`", name),
+                },
+                Typ::Array(_, _) => {
+                    bail!("cannot explore array variable `{}` explicitly", var.id())
+                }
+                Typ::Rat => bail!("cannot explore rational variable `{}` explicitly", var.id()),
+            };
+            res.push((var, domain))
+        }
+        Ok(res)
+    }
+
+    /// Enumerates the state space.
+    fn states(&self) -> Res<Vec<Vec<Cst>>> {
+        let domains = self.domains()?;
+        let mut size: usize = 1;
+        for (_, domain) in domains.iter() {
+            size = size
+                .checked_mul(domain.len())
+                .filter(|size| *size <= self.max_states)
+                .ok_or_else(|| format!("state space has more than {} states", self.max_states))?;
+        }
+        let mut states = vec![vec![]];
+        for (_, domain) in domains {
+            states = states
+                .into_iter()
+                .flat_map(|state| {
+                    domain.iter().map(move |val| {
+                        let mut state = state.clone();
+                        state.push(val.clone());
+                        state
+                    })
+                })
+                .collect();
+        }
+        Ok(states)
+    }
+
+    /// Adds a state to a trace at some step.
+    fn add_state(&self, trace: &mut Trace, step: Unroll, state: &[Cst]) {
+        let map = trace.entry(step).or_default();
+        for (var, val) in self.sys.decls().all().zip(state.iter()) {
+            let _ = map.insert(var, val.clone());
+        }
+    }
+
+    /// Runs the exploration.
+    ///
+    /// # Errors
+    ///
+    /// - when the system has rational or unbounded integer variables,
+    /// - when the state space has more than [`Self::max_states`] states.
+    pub fn run(&self) -> Res<Exploration> {
+        let states = self.states()?;
+        let (sys, decls) = (self.sys, self.sys.decls());
+
+        // For each explored state, its predecessor (if any) and its distance to the initial states.
+        let mut explored: Map<usize, (Option<usize>, Unroll)> = Map::new();
+        let mut queue = std::collections::VecDeque::new();
+        for (idx, state) in states.iter().enumerate() {
+            let mut trace = Trace::new();
+            self.add_state(&mut trace, 0, state);
+            if replay::eval_at(sys.init(), &trace, 0)?.as_bool()? {
+                let _ = explored.insert(idx, (None, 0));
+                queue.push_back(idx);
+            }
+        }
+
+        let mut verdicts = Map::new();
+        let mut diameter = 0;
+        while let Some(idx) = queue.pop_front() {
+            let depth = explored[&idx].1;
+            diameter = std::cmp::max(diameter, depth);
+
+            let mut trace = Trace::new();
+            self.add_state(&mut trace, 0, &states[idx]);
+            for (name, po) in sys.po_s() {
+                if verdicts.contains_key(name) || replay::eval_at(po, &trace, 0)?.as_bool()? {
+                    continue;
+                }
+                // Breadth-first, this is a shortest counterexample.
+                let mut path = vec![idx];
+                while let Some(pred) = explored[path.last().expect("non-empty path")].0 {
+                    path.push(pred)
+                }
+                let mut cex = Cex::new();
+                for (step, idx) in path.into_iter().rev().enumerate() {
+                    for (var, val) in decls.all().zip(states[idx].iter()) {
+                        cex.insert(step, var, val.clone())?
+                    }
+                }
+                let _ = verdicts.insert(name.clone(), Verdict::Falsified { depth, cex });
+            }
+
+            if !self.expands(depth) {
+                continue;
+            }
+            for (next, state) in states.iter().enumerate() {
+                if explored.contains_key(&next) {
+                    continue;
+                }
+                self.add_state(&mut trace, 1, state);
+                if replay::eval_between(sys.trans(), &trace, 0)?.as_bool()? {
+                    let _ = explored.insert(next, (Some(idx), depth + 1));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        for name in sys.po_s().keys() {
+            if !verdicts.contains_key(name) {
+                let verdict = match self.max_depth {
+                    Some(max) if diameter >= max => Verdict::Unknown {
+                        bmc_depth: Some(max),
+                    },
+                    _ => Verdict::Proved,
+                };
+                let _ = verdicts.insert(name.clone(), verdict);
+            }
+        }
+        Ok(Exploration {
+            states: states.len(),
+            reachable: explored.len(),
+            diameter,
+            verdicts,
+        })
+    }
+
+    /// Explores the reachable states, yields the state graph.
+    ///
+    /// # Errors
+    ///
+    /// - when the system has rational or unbounded integer variables,
+    /// - when the state space has more than [`Self::max_states`] states.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::explicit::Explorer, parse};
+    /// let sys = parse::trans(r#"
+    /// svars { cnt: int }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = (if cnt < 2 { cnt + 1 } else { 0 }) }
+    /// candidates { "cnt is not 2": ¬(cnt = 2) }
+    /// "#).unwrap();
+    /// let graph = Explorer::new(&sys).bound("cnt", 0, 5).graph().unwrap();
+    /// assert!(graph.is_complete());
+    /// assert_eq!(
+    ///     graph.to_dot(),
+    ///     r#"digraph states {
+    ///     node [shape = box];
+    ///     init [shape = point];
+    ///     s0 [label = "cnt = 0"];
+    ///     s1 [label = "cnt = 1"];
+    ///     s2 [label = "cnt = 2\nfalsifies `cnt is not 2`", color = red, fontcolor = red];
+    ///     init -> s0;
+    ///     s0 -> s1;
+    ///     s1 -> s2;
+    ///     s2 -> s0;
+    /// }
+    /// "#,
+    /// );
+    ///
+    /// let graph = Explorer::new(&sys).bound("cnt", 0, 5).max_depth(1).graph().unwrap();
+    /// assert_eq!(graph.states.len(), 2);
+    /// assert_eq!(graph.frontier.iter().collect::<Vec<_>>(), vec![&1]);
+    /// ```
+    pub fn graph(&self) -> Res<StateGraph> {
+        let states = self.states()?;
+        let sys = self.sys;
+
+        let mut graph = StateGraph {
+            vars: sys.decls().all().collect(),
+            states: vec![],
+            init: Set::new(),
+            edges: Set::new(),
+            frontier: Set::new(),
+            violations: Map::new(),
+        };
+        // Maps the explored states to their node and their distance to the initial states.
+        let mut explored: Map<usize, (usize, Unroll)> = Map::new();
+        let mut queue = std::collections::VecDeque::new();
+        // Adds a state to the graph, yields its node.
+        let discover = |graph: &mut StateGraph, idx: usize| -> Res<usize> {
+            let node = graph.states.len();
+            graph.states.push(states[idx].clone());
+            let mut trace = Trace::new();
+            self.add_state(&mut trace, 0, &states[idx]);
+            for (name, po) in sys.po_s() {
+                if !replay::eval_at(po, &trace, 0)?.as_bool()? {
+                    let _ = graph
+                        .violations
+                        .entry(node)
+                        .or_default()
+                        .insert(name.clone());
+                }
+            }
+            Ok(node)
+        };
+
+        for (idx, state) in states.iter().enumerate() {
+            let mut trace = Trace::new();
+            self.add_state(&mut trace, 0, state);
+            if replay::eval_at(sys.init(), &trace, 0)?.as_bool()? {
+                let node = discover(&mut graph, idx)?;
+                let _ = graph.init.insert(node);
+                let _ = explored.insert(idx, (node, 0));
+                queue.push_back(idx);
+            }
+        }
+
+        while let Some(idx) = queue.pop_front() {
+            let (node, depth) = explored[&idx];
+            if !self.expands(depth) {
+                let _ = graph.frontier.insert(node);
+                continue;
+            }
+            let mut trace = Trace::new();
+            self.add_state(&mut trace, 0, &states[idx]);
+            for (next, state) in states.iter().enumerate() {
+                self.add_state(&mut trace, 1, state);
+                if !replay::eval_between(sys.trans(), &trace, 0)?.as_bool()? {
+                    continue;
+                }
+                let next_node = match explored.get(&next) {
+                    Some((next_node, _)) => *next_node,
+                    None => {
+                        let next_node = discover(&mut graph, next)?;
+                        let _ = explored.insert(next, (next_node, depth + 1));
+                        queue.push_back(next);
+                        next_node
+                    }
+                };
+                let _ = graph.edges.insert((node, next_node));
+            }
+        }
+
+        Ok(graph)
+    }
+}
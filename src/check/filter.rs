@@ -0,0 +1,157 @@
+//! Candidate filtering, by tag or by name.
+//!
+//! Candidates can carry tags, see [`Sys::tags`]. A [`Filter`] selects the candidates to check based
+//! on their tags and on name globs. Globs are matched against the whole name of the candidate, `*`
+//! matches any sequence of characters and `?` matches exactly one character.
+
+crate::prelude!();
+
+use trans::Sys;
+
+/// Candidate filter.
+///
+/// A candidate is selected if
+/// - there are no inclusion criteria, or it matches at least one of them, and
+/// - it matches none of the exclusion criteria.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::filter::Filter, parse};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int }
+/// init { cnt = 0 }
+/// trans { 'cnt = cnt + 1 }
+/// candidates {
+///     #[tag(critical)]
+///     "cnt is positive": cnt ≥ 0,
+///     #[tag(critical, slow)]
+///     "cnt is not 7": ¬(cnt = 7),
+///     "cnt is not -1": ¬(cnt = -1),
+/// }
+/// "#).unwrap();
+/// let selected = |filter: Filter| -> Vec<String> {
+///     filter.apply(&sys).po_s().keys().cloned().collect()
+/// };
+///
+/// assert_eq!(selected(Filter::new()).len(), 3);
+/// assert_eq!(
+///     selected(Filter::new().include_tag("critical").exclude_tag("slow")),
+///     vec!["cnt is positive"],
+/// );
+/// assert_eq!(
+///     selected(Filter::new().include_name("cnt is not *")),
+///     vec!["cnt is not -1", "cnt is not 7"],
+/// );
+/// assert_eq!(
+///     selected(Filter::new().include_tag("slow").include_name("*-?")),
+///     vec!["cnt is not -1", "cnt is not 7"],
+/// );
+/// assert_eq!(
+///     selected(Filter::new().exclude_name("*positive")),
+///     vec!["cnt is not -1", "cnt is not 7"],
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Tags selecting candidates.
+    pub include_tags: Set<String>,
+    /// Tags excluding candidates.
+    pub exclude_tags: Set<String>,
+    /// Name globs selecting candidates.
+    pub include_names: Vec<String>,
+    /// Name globs excluding candidates.
+    pub exclude_names: Vec<String>,
+}
+impl Filter {
+    /// Constructor, selects all candidates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tag selecting candidates.
+    pub fn include_tag(mut self, tag: impl Into<String>) -> Self {
+        let _ = self.include_tags.insert(tag.into());
+        self
+    }
+    /// Adds a tag excluding candidates.
+    pub fn exclude_tag(mut self, tag: impl Into<String>) -> Self {
+        let _ = self.exclude_tags.insert(tag.into());
+        self
+    }
+    /// Adds a name glob selecting candidates.
+    pub fn include_name(mut self, glob: impl Into<String>) -> Self {
+        self.include_names.push(glob.into());
+        self
+    }
+    /// Adds a name glob excluding candidates.
+    pub fn exclude_name(mut self, glob: impl Into<String>) -> Self {
+        self.exclude_names.push(glob.into());
+        self
+    }
+
+    /// True if the filter selects all candidates.
+    pub fn is_empty(&self) -> bool {
+        self.include_tags.is_empty()
+            && self.exclude_tags.is_empty()
+            && self.include_names.is_empty()
+            && self.exclude_names.is_empty()
+    }
+
+    /// True if the filter selects a candidate of a system.
+    pub fn selects(&self, sys: &Sys, candidate: &str) -> bool {
+        let matches = |tags: &Set<String>, globs: &[String]| {
+            sys.tags_of(candidate).any(|tag| tags.contains(tag))
+                || globs.iter().any(|glob| glob_match(glob, candidate))
+        };
+        let included = (self.include_tags.is_empty() && self.include_names.is_empty())
+            || matches(&self.include_tags, &self.include_names);
+        included && !matches(&self.exclude_tags, &self.exclude_names)
+    }
+
+    /// Copy of a system where only the selected candidates are preserved.
+    pub fn apply(&self, sys: &Sys) -> Sys {
+        sys.restrict(|name| self.selects(sys, name))
+    }
+}
+
+/// True if a glob matches a whole string.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::check::filter::glob_match;
+/// assert!(glob_match("cnt*", "cnt is positive"));
+/// assert!(glob_match("*is*", "cnt is positive"));
+/// assert!(glob_match("cnt is ?ositive", "cnt is positive"));
+/// assert!(!glob_match("cnt", "cnt is positive"));
+/// assert!(!glob_match("?cnt*", "cnt is positive"));
+/// assert!(glob_match("*", ""));
+/// ```
+pub fn glob_match(glob: &str, s: &str) -> bool {
+    let (glob, s): (Vec<char>, Vec<char>) = (glob.chars().collect(), s.chars().collect());
+    let (mut g, mut i) = (0, 0);
+    // Position of the last `*` in the glob, and position in `s` it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while i < s.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, i));
+                g += 1;
+            }
+            Some(c) if *c == '?' || *c == s[i] => {
+                g += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    g = star + 1;
+                    i = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|c| *c == '*')
+}
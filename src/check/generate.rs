@@ -0,0 +1,438 @@
+//! Candidate generation from the structure of a system.
+//!
+//! [`generate`] proposes candidate invariants by looking at the syntax of the initial predicate and
+//! of the transition relation, following three [`Template`]s:
+//!
+//! - *preserved equalities*: equalities of the initial predicate between variables the transition
+//!   relation leaves unchanged or shifts by the same amount, and top-level constraints of the
+//!   transition relation that only mention next state variables, which every step re-establishes;
+//! - *guard implications*: when the transition relation defines `'v` as `if 'g { e } else { ... }`
+//!   with `'g` and `e` only mentioning next state variables, `g ⇒ v = e` holds after each step;
+//!   same for implications of the transition relation only mentioning next state variables;
+//! - *bounds*: `v ≥ c` and `v ≤ c` for each arithmetic variable `v` and each constant `c` appearing
+//!   in the initial predicate or the transition relation, unless the initial predicate fixes a
+//!   value for `v` that violates the bound.
+//!
+//! Generation is purely syntactic and does not prove anything. [`with_generated`] adds generated
+//! candidates to a system so that [`run`](super::run) can try to prove them, and [`invariants`]
+//! does both and only keeps the proved ones.
+
+crate::prelude!();
+
+use super::{preprocess::conjuncts, Config};
+use expr::{Cst, Expr, Op, PExpr, SExpr, SVar, Var};
+use trans::Sys;
+
+/// Tag of the candidates added by [`with_generated`].
+pub const TAG: &str = "generated";
+
+/// Template a generated candidate instantiates, see the [module-level documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Template {
+    /// Equality preserved or re-established by the transition relation.
+    Preserved,
+    /// Implication guarded by a condition of the transition relation.
+    Guard,
+    /// Bound on an arithmetic variable.
+    Bound,
+}
+impl fmt::Display for Template {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Preserved => "preserved".fmt(fmt),
+            Self::Guard => "guard".fmt(fmt),
+            Self::Bound => "bound".fmt(fmt),
+        }
+    }
+}
+
+/// A generated candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    /// Template the candidate instantiates.
+    pub template: Template,
+    /// Candidate.
+    pub expr: Expr,
+}
+impl Generated {
+    /// Candidate name, the template followed by the candidate.
+    pub fn name(&self) -> String {
+        format!("{} {}", self.template, self.expr)
+    }
+}
+impl fmt::Display for Generated {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}: {}", self.template, self.expr)
+    }
+}
+
+/// Applies an operator, panics on ill-typed applications.
+fn app(op: Op, args: Vec<Expr>) -> Expr {
+    Expr::new_op(op, args).expect("[bug] ill-typed generation template")
+}
+
+/// Stateless version of a stateful expression that only mentions next state variables.
+fn next_only(expr: &SExpr) -> Option<Expr> {
+    expr.fold(
+        |svar| {
+            if svar.is_next() {
+                Some(PExpr::new_var((**svar).clone()))
+            } else {
+                None
+            }
+        },
+        |cst| Some(cst.clone().into()),
+        |op, args| Expr::new_op(op, args.into_iter().collect::<Option<_>>()?).ok(),
+    )
+}
+
+/// Splits a definition `'v = rhs` (or `rhs = 'v`) of a next state variable.
+fn as_next_def(conj: &SExpr) -> Option<(&SVar, &SExpr)> {
+    match conj {
+        PExpr::App {
+            op: Op::Eq, args, ..
+        } if args.len() == 2 => match (&args[0], &args[1]) {
+            (PExpr::Var(svar), rhs) | (rhs, PExpr::Var(svar)) if svar.is_next() => {
+                Some((svar, rhs))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Amount by which a definition `'v = rhs` shifts `v`, if `rhs` is `v`, `v + k` or `v - k`.
+fn shift(var: &Var, rhs: &SExpr) -> Option<Rat> {
+    let is_curr =
+        |expr: &SExpr| matches!(expr, PExpr::Var(svar) if !svar.is_next() && **svar == *var);
+    let as_rat = |expr: &SExpr| match expr {
+        PExpr::Cst(Cst::I(i)) => Some(Rat::from_integer(i.clone())),
+        PExpr::Cst(Cst::R(r)) => Some(r.clone()),
+        _ => None,
+    };
+    match rhs {
+        _ if is_curr(rhs) => Some(Rat::zero()),
+        PExpr::App {
+            op: Op::Add, args, ..
+        } if args.len() == 2 => match (&args[0], &args[1]) {
+            (v, k) | (k, v) if is_curr(v) && as_rat(k).is_some() => as_rat(k),
+            _ => None,
+        },
+        PExpr::App {
+            op: Op::Sub, args, ..
+        } if args.len() == 2 && is_curr(&args[0]) => as_rat(&args[1]).map(|k| -k),
+        _ => None,
+    }
+}
+
+/// Variables of a stateless expression.
+fn vars_of(expr: &Expr) -> Set<Var> {
+    let mut vars = Set::new();
+    expr.fold(
+        |var| {
+            let _ = vars.insert(var.clone());
+        },
+        |_| (),
+        |_, _| (),
+    );
+    vars
+}
+
+/// Preserved equalities.
+fn preserved(sys: &Sys, res: &mut Vec<Generated>) {
+    let trans = conjuncts(sys.trans());
+    let mut defs: Map<&str, &SExpr> = Map::new();
+    for conj in trans.iter() {
+        if let Some((svar, rhs)) = as_next_def(conj) {
+            let _ = defs.entry(svar.id()).or_insert(rhs);
+        }
+    }
+
+    for conj in conjuncts(sys.init()) {
+        let args = match &conj {
+            PExpr::App {
+                op: Op::Eq, args, ..
+            } if args.len() == 2 => args,
+            _ => continue,
+        };
+        let vars = vars_of(&conj);
+        let shifts: Option<Vec<Rat>> = vars
+            .iter()
+            .map(|var| defs.get(var.id()).and_then(|rhs| shift(var, rhs)))
+            .collect();
+        let shifts = match shifts {
+            Some(shifts) if !shifts.is_empty() => shifts,
+            _ => continue,
+        };
+        let unchanged = shifts.iter().all(|shift| shift.is_zero());
+        let lockstep = args.iter().all(PExpr::is_var) && shifts.windows(2).all(|w| w[0] == w[1]);
+        if unchanged || lockstep {
+            res.push(Generated {
+                template: Template::Preserved,
+                expr: conj,
+            })
+        }
+    }
+
+    for conj in trans.iter() {
+        let is_implication = matches!(
+            conj,
+            PExpr::App {
+                op: Op::Implies,
+                ..
+            }
+        );
+        if is_implication {
+            continue;
+        }
+        if let Some(expr) = next_only(conj) {
+            res.push(Generated {
+                template: Template::Preserved,
+                expr,
+            })
+        }
+    }
+}
+
+/// Guard implications.
+fn guards(sys: &Sys, res: &mut Vec<Generated>) {
+    for conj in conjuncts(sys.trans()) {
+        if let PExpr::App {
+            op: Op::Implies, ..
+        } = conj
+        {
+            if let Some(expr) = next_only(&conj) {
+                res.push(Generated {
+                    template: Template::Guard,
+                    expr,
+                })
+            }
+            continue;
+        }
+
+        let (svar, rhs) = match as_next_def(&conj) {
+            Some(def) => def,
+            None => continue,
+        };
+        let var: Var = (**svar).clone();
+        let mut branch = |path: &[SExpr], value: &SExpr| {
+            let value = match next_only(value) {
+                Some(value) if value != PExpr::new_var(var.clone()) => value,
+                _ => return,
+            };
+            let guard: Option<Vec<Expr>> = path.iter().map(next_only).collect();
+            let guard = match guard {
+                Some(mut guard) if guard.len() == 1 => {
+                    guard.pop().expect("[unreachable] len is `1`")
+                }
+                Some(guard) if !guard.is_empty() => app(Op::And, guard),
+                _ => return,
+            };
+            let eq = app(Op::Eq, vec![PExpr::new_var(var.clone()), value]);
+            res.push(Generated {
+                template: Template::Guard,
+                expr: app(Op::Implies, vec![guard, eq]),
+            })
+        };
+
+        let mut path = vec![];
+        let mut current = rhs;
+        while let PExpr::App {
+            op: Op::Ite, args, ..
+        } = current
+        {
+            path.push(args[0].clone());
+            branch(&path, &args[1]);
+            let cnd = path.pop().expect("[unreachable] pop on non-empty vec");
+            path.push(SExpr::new_op(Op::Not, vec![cnd]).expect("[bug] ill-typed ite condition"));
+            current = &args[2];
+        }
+        if !path.is_empty() {
+            branch(&path, current)
+        }
+    }
+}
+
+/// Bounds.
+fn bounds(sys: &Sys, res: &mut Vec<Generated>) {
+    let mut csts: Set<Rat> = Set::new();
+    let mut collect = |cst: &Cst| match cst {
+        Cst::I(i) => {
+            let _ = csts.insert(Rat::from_integer(i.clone()));
+        }
+        Cst::R(r) => {
+            let _ = csts.insert(r.clone());
+        }
+        Cst::B(_) | Cst::BV(_, _) | Cst::Array { .. } | Cst::Enum { .. } => (),
+    };
+    sys.init().fold(|_| (), &mut collect, |_, _| ());
+    sys.trans().fold(|_| (), &mut collect, |_, _| ());
+
+    let mut init_vals: Map<Var, Rat> = Map::new();
+    for conj in conjuncts(sys.init()) {
+        if let PExpr::App {
+            op: Op::Eq, args, ..
+        } = &conj
+        {
+            if let [PExpr::Var(var), PExpr::Cst(cst)] | [PExpr::Cst(cst), PExpr::Var(var)] =
+                args.as_slice()
+            {
+                let val = match cst {
+                    Cst::I(i) => Rat::from_integer(i.clone()),
+                    Cst::R(r) => r.clone(),
+                    Cst::B(_) | Cst::BV(_, _) | Cst::Array { .. } | Cst::Enum { .. } => continue,
+                };
+                let _ = init_vals.entry(var.clone()).or_insert(val);
+            }
+        }
+    }
+
+    for var in sys.decls().all().filter(|var| var.typ().is_arith()) {
+        for val in csts.iter() {
+            let cst = match var.typ() {
+                Typ::Int if val.is_integer() => Cst::int(val.to_integer()),
+                Typ::Int => continue,
+                _ => Cst::rat(val.clone()),
+            };
+            let init = init_vals.get(&var);
+            let v = PExpr::new_var(var.clone());
+            if init.map(|init| init >= val).unwrap_or(true) {
+                res.push(Generated {
+                    template: Template::Bound,
+                    expr: app(Op::Ge, vec![v.clone(), cst.clone().into()]),
+                })
+            }
+            if init.map(|init| init <= val).unwrap_or(true) {
+                res.push(Generated {
+                    template: Template::Bound,
+                    expr: app(Op::Le, vec![v, cst.into()]),
+                })
+            }
+        }
+    }
+}
+
+/// Generates candidates from the structure of a system, see the
+/// [module-level documentation](self).
+///
+/// Candidates are ordered by template (preserved equalities, guard implications, bounds), then by
+/// order of appearance in the system. Bounds are ordered by variable, then by constant. Candidates
+/// are unique and different from the candidates of `sys`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::generate, parse};
+/// let sys = parse::trans(r#"
+/// svars { cnt max x y: int, reset stop: bool }
+/// init { cnt = 0, max = 3, x = y }
+/// trans {
+///     'max = max,
+///     'x = x + 2,
+///     'y = y + 2,
+///     'cnt = if 'reset { 0 } else if 'stop { cnt } else { cnt + 1 },
+///     'stop ⇒ ¬'reset,
+/// }
+/// candidates { "cnt is positive": cnt ≥ 0 }
+/// "#).unwrap();
+/// let generated: Vec<_> = generate::generate(&sys)
+///     .into_iter()
+///     .map(|gen| gen.to_string())
+///     .collect();
+/// assert_eq!(
+///     &generated[0..4],
+///     &[
+///         "preserved: (= max 3)",
+///         "preserved: (= x y)",
+///         "guard: (=> reset (= cnt 0))",
+///         "guard: (=> stop (not reset))",
+///     ],
+/// );
+/// // `cnt ≥ 0` is already a candidate, `cnt ≥ 1` is false initially.
+/// assert_eq!(
+///     &generated[4..10],
+///     &[
+///         "bound: (<= cnt 0)",
+///         "bound: (<= cnt 1)",
+///         "bound: (<= cnt 2)",
+///         "bound: (<= cnt 3)",
+///         "bound: (>= max 0)",
+///         "bound: (>= max 1)",
+///     ],
+/// );
+/// ```
+pub fn generate(sys: &Sys) -> Vec<Generated> {
+    let mut candidates = vec![];
+    preserved(sys, &mut candidates);
+    guards(sys, &mut candidates);
+    bounds(sys, &mut candidates);
+
+    let mut res: Vec<Generated> = vec![];
+    for candidate in candidates {
+        let known = sys.po_s().values().any(|po| *po == candidate.expr)
+            || res.iter().any(|gen| gen.expr == candidate.expr);
+        if !known {
+            res.push(candidate)
+        }
+    }
+    res
+}
+
+/// Copy of a system with some generated candidates.
+///
+/// Generated candidates are named by [`Generated::name`], tagged [`TAG`] and have severity
+/// [warning](trans::Severity::Warning) so that their falsification is not a failure. Generated
+/// candidates with the same name as an existing candidate are ignored.
+pub fn with_generated(sys: &Sys, generated: &[Generated]) -> Sys {
+    let mut po_s = sys.po_s().clone();
+    let mut tags = sys.tags().clone();
+    let mut warnings = sys.warnings().clone();
+    for gen in generated {
+        let name = gen.name();
+        if po_s.contains_key(&name) {
+            continue;
+        }
+        let _ = po_s.insert(name.clone(), gen.expr.clone());
+        let _ = tags.entry(name.clone()).or_default().insert(TAG.into());
+        let _ = warnings.insert(name);
+    }
+    Sys::new(
+        sys.decls().clone(),
+        sys.init().clone(),
+        sys.trans().clone(),
+        po_s,
+    )
+    .with_tags(tags)
+    .with_warnings(warnings)
+    .with_labels(sys.labels().clone())
+}
+
+/// Generated candidates that are invariants of a system.
+///
+/// Generates candidates with [`generate`] and checks them, without the candidates of `sys`.
+///
+/// # Errors
+///
+/// - when the check fails, see [`run`](super::run).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, rsmt2::SmtConf};
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(5);
+/// for gen in check::generate::invariants(&sys, &config).unwrap() {
+///     println!("{}", gen)
+/// }
+/// ```
+pub fn invariants(sys: &Sys, config: &Config) -> Res<Vec<Generated>> {
+    let generated = generate(sys);
+    let names: Set<String> = generated.iter().map(Generated::name).collect();
+    let sys = with_generated(sys, &generated).restrict(|name| names.contains(name));
+    let outcome = super::run(&sys, config).chain_err(|| "while checking generated candidates")?;
+    let proved: Set<&String> = outcome.proved().collect();
+    Ok(generated
+        .into_iter()
+        .filter(|gen| proved.contains(&gen.name()))
+        .collect())
+}
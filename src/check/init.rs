@@ -0,0 +1,84 @@
+//! Validation of the initial predicate.
+//!
+//! A system whose initial predicate is unsatisfiable has no run at all: all its candidates hold
+//! vacuously, and induction and BMC happily prove them. [`witness`] asks the solver for a sample
+//! initial state, and [`validate`] fails with a clear message when there is none. The driver
+//! validates the initial predicate before running any engine when [`Config::check_init`] is set.
+//!
+//! [`Config::check_init`]: super::Config::check_init
+
+crate::prelude!();
+
+use super::{cexs::Cex, InternalChecker};
+use trans::Sys;
+
+/// Looks for an initial state of a system.
+///
+/// Yields a one-step counterexample holding a sample initial state, or `None` if the initial
+/// predicate is unsatisfiable.
+///
+/// # Errors
+///
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::init, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let state = init::witness(&sys, SmtConf::z3("z3")).unwrap().unwrap();
+/// assert_eq!(state.len(), 1);
+///
+/// let sys = parse::trans(
+///     "svars { cnt: int } init { cnt > 0, cnt < 0 } trans { 'cnt = cnt + 1 } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// assert!(init::witness(&sys, SmtConf::z3("z3")).unwrap().is_none());
+/// ```
+pub fn witness(sys: &Sys, conf: SmtConf) -> Res<Option<Cex>> {
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    let res = if checker.check_sat()? {
+        let mut cex = Cex::new();
+        let vars: Vec<_> = sys.decls().all().collect();
+        cex.populate_or_salvage(checker.solver(), &vars, 0..1);
+        Some(cex)
+    } else {
+        None
+    };
+    checker.solver().kill()?;
+    Ok(res)
+}
+
+/// Checks that the initial predicate of a system is satisfiable, yields a sample initial state.
+///
+/// # Errors
+///
+/// - when the initial predicate is unsatisfiable, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::init, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { cnt: int } init { cnt > 0, cnt < 0 } trans { 'cnt = cnt + 1 } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let err = init::validate(&sys, SmtConf::z3("z3")).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "the initial predicate is unsatisfiable, all candidates would hold vacuously",
+/// );
+/// ```
+pub fn validate(sys: &Sys, conf: SmtConf) -> Res<Cex> {
+    match witness(sys, conf).chain_err(|| "while checking the initial predicate")? {
+        Some(cex) => Ok(cex),
+        None => {
+            bail!("the initial predicate is unsatisfiable, all candidates would hold vacuously")
+        }
+    }
+}
@@ -0,0 +1,406 @@
+//! Interpolation, proves candidates that are not inductive by generating strengthening invariants.
+//!
+//! Given a candidate `P` and a depth `k`, the unrolling `R(s_0) ∧ T(s_0, s_1)` of a set of states
+//! `R` is split from the rest of a BMC query, `T(s_1, s_2) ∧ ... ∧ T(s_{k-1}, s_k)` with `¬P` at
+//! some step between `1` and `k`. When the query is unsat, that is when no state of `R` falsifies
+//! `P` in `k` steps or less, the solver produces a *Craig interpolant* `I(s_1)` of the two parts:
+//!
+//! - `I` over-approximates the successors of `R`, and
+//! - no state of `I` falsifies `P` in `k - 1` steps or less.
+//!
+//! Starting from the initial states, the engine adds interpolants to `R`, and asks
+//! [induction](super::Step) whether `P` is inductive when strengthened by `R` after each of them.
+//! A satisfiable query restarts the search at depth `k + 1`, unless `R` is still the initial
+//! states: `P` is then falsified in `k` steps or less, which is left to [BMC](super::engine::Bmc).
+//!
+//! Interpolation is available as the [`Interpolation`] engine, named `"interpolation"` in
+//! [configurations](crate::config). It needs a solver supporting `get-interpolant`, see
+//! [`rsmt2::Solver::get_interpolant`], and does not support uninterpreted functions.
+
+crate::prelude!();
+
+use std::time::Instant;
+
+use super::{
+    cexs::Cex,
+    driver::{Config, Outcome, Verdict},
+    engine::{setup, Cancel, Engine, Events, Setup},
+    Base, InternalChecker, Step,
+};
+use crate::solver::{self, smt2};
+use expr::{Expr, Op, Var};
+use parse::Parser;
+use trans::Sys;
+
+/// Default maximal depth, see [`Interpolation::max_depth`].
+pub const DEFAULT_MAX_DEPTH: Unroll = 8;
+
+/// Interpolation engine, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// Uninterpreted functions and solvers that do not produce interpolants are reported before
+/// spawning any solver.
+///
+/// ```rust
+/// # use mikino_api::{
+/// #     check::{self, engine::{Engine, Events}, interpolation::Interpolation},
+/// #     parse, solver::{Backend, Cvc5},
+/// # };
+/// # use std::collections::BTreeSet as Set;
+/// let sys = parse::trans(r#"
+/// svars { x: int }
+/// init { x = 0 }
+/// trans { 'x = x + 1 }
+/// candidates { "x is positive": x ≥ 0 }
+/// "#).unwrap();
+/// let candidates: Set<String> = sys.po_s().keys().cloned().collect();
+/// let mut engine = Interpolation::new();
+/// engine.prepare(&sys, &check::Config::new(Cvc5.conf("cvc5"))).unwrap();
+/// let err = engine.run(&candidates, None, &Events::new()).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "engine `interpolation` needs a solver producing interpolants, `cvc5` does not",
+/// );
+/// ```
+///
+/// `x ≥ 0` is not inductive since `y` could be negative, the interpolants show that `y` stays
+/// positive.
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::{self, engine::Engines, interpolation::Interpolation}, parse};
+/// # use mikino_api::rsmt2::SmtConf;
+/// let sys = parse::trans(r#"
+/// svars { x: int, y: int }
+/// init { x = 0 ∧ y = 1 }
+/// trans { 'x = x + y ∧ 'y = y }
+/// candidates { "x is positive": x ≥ 0 }
+/// "#).unwrap();
+/// let mut engines = Engines::new();
+/// engines.push(Interpolation::new);
+/// let config = check::Config::new(SmtConf::z3("z3")).engines(engines);
+/// let outcome = check::run(&sys, &config).unwrap();
+/// assert!(outcome["x is positive"].is_proved());
+/// ```
+pub struct Interpolation {
+    /// Setup, `None` until prepared.
+    setup: Option<Setup>,
+    /// Maximal depth, and maximal number of interpolants at each depth.
+    max_depth: Unroll,
+}
+impl Default for Interpolation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Interpolation {
+    /// Name of the engine.
+    pub const NAME: &'static str = "interpolation";
+    /// Constructor.
+    pub fn new() -> Self {
+        Self {
+            setup: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+    /// Sets the maximal depth, [`DEFAULT_MAX_DEPTH`] by default.
+    ///
+    /// Also bounds the number of interpolants computed at each depth.
+    pub fn max_depth(mut self, max_depth: Unroll) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+impl Engine for Interpolation {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+    fn prepare(&mut self, sys: &Sys, config: &Config) -> Res<()> {
+        self.setup = Some(Setup::new(sys, config)?);
+        Ok(())
+    }
+    fn run(
+        &mut self,
+        candidates: &Set<String>,
+        budget: Option<time::Duration>,
+        events: &Events,
+    ) -> Res<Outcome> {
+        let setup = setup(&self.setup, Self::NAME)?;
+        let Setup {
+            sys,
+            conf,
+            tee,
+            cancel,
+            ..
+        } = setup;
+        if !sys.decls().funs().is_empty() {
+            bail!(
+                "engine `{}` does not support uninterpreted functions",
+                Self::NAME
+            )
+        }
+        if !conf.get_interpolants() {
+            bail!(
+                "engine `{}` needs a solver producing interpolants, `{}` does not",
+                Self::NAME,
+                solver::backend_of(conf).name()
+            )
+        }
+        let deadline = budget.map(|budget| Instant::now() + budget);
+        let mut outcome = Outcome::new();
+
+        for (idx, (name, po)) in sys
+            .po_s()
+            .iter()
+            .filter(|(name, _)| candidates.contains(*name))
+            .enumerate()
+        {
+            let search = Search {
+                sys,
+                conf,
+                tee: tee.as_ref().map(|tee| (tee, idx)),
+                cancel,
+                name,
+                po,
+            };
+            let verdict = search.check(self.max_depth, &|| {
+                cancel.is_cancelled()
+                    || deadline
+                        .map(|deadline| Instant::now() >= deadline)
+                        .unwrap_or(false)
+            });
+            let verdict = match verdict {
+                Ok(verdict) => verdict,
+                Err(_) if cancel.is_cancelled() => Check::Stopped("cancelled"),
+                Err(e) if setup.is_timeout(&e) => {
+                    events.progress(Self::NAME, format!("`{}`: query timed out", name));
+                    let _ = outcome
+                        .verdicts
+                        .insert(name.clone(), Verdict::Timeout { bmc_depth: None });
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e.chain_err(|| {
+                        format!("while running interpolation on candidate `{}`", name)
+                    }))
+                }
+            };
+
+            let verdict = match verdict {
+                Check::Proved {
+                    depth,
+                    interpolants,
+                } => {
+                    events.progress(
+                        Self::NAME,
+                        format!(
+                            "`{}`: proved at depth {} with {} interpolant(s)",
+                            name, depth, interpolants
+                        ),
+                    );
+                    Verdict::Proved
+                }
+                Check::Initial(cex) => Verdict::Falsified { depth: 0, cex },
+                Check::Reached(depth) => {
+                    events.progress(
+                        Self::NAME,
+                        format!("`{}`: falsifiable in {} step(s) or less", name, depth),
+                    );
+                    Verdict::Unknown { bmc_depth: None }
+                }
+                Check::Stopped(why) => {
+                    events.progress(Self::NAME, format!("`{}`: {}", name, why));
+                    Verdict::Unknown { bmc_depth: None }
+                }
+            };
+            let _ = outcome.verdicts.insert(name.clone(), verdict);
+        }
+        Ok(outcome)
+    }
+}
+
+/// Result of an interpolation check for a single candidate.
+enum Check {
+    /// Proved, with the depth and the number of interpolants at that depth.
+    Proved {
+        /// Depth of the interpolation queries.
+        depth: Unroll,
+        /// Number of interpolants strengthening the candidate.
+        interpolants: usize,
+    },
+    /// Falsified in the initial states.
+    Initial(Cex),
+    /// Falsifiable in some number of steps or less.
+    Reached(Unroll),
+    /// Stopped before deciding the candidate, with the reason.
+    Stopped(&'static str),
+}
+
+/// Interpolation search for a single candidate.
+struct Search<'a> {
+    /// System.
+    sys: &'a Sys,
+    /// Solver configuration.
+    conf: &'a SmtConf,
+    /// Tee directory and index of the candidate, if any.
+    tee: Option<(&'a PathBuf, usize)>,
+    /// Cancellation handle.
+    cancel: &'a Cancel,
+    /// Name of the candidate.
+    name: &'a String,
+    /// Candidate.
+    po: &'a Expr,
+}
+impl<'a> Search<'a> {
+    /// Tee directory, if any.
+    ///
+    /// Base and step checks go to `base.smt2` and `step.smt2` in this directory, as for the
+    /// [induction engine](super::engine::Induction).
+    fn tee_dir(&self) -> Option<PathBuf> {
+        self.tee.map(|(tee, _)| tee.clone())
+    }
+    /// Tee file for some interpolation query, if any.
+    fn tee_file(&self, query: impl fmt::Display) -> Option<PathBuf> {
+        self.tee.map(|(tee, idx)| {
+            let mut path = tee.clone();
+            path.push(format!("interpolation_{}_{}.smt2", idx, query));
+            path
+        })
+    }
+
+    /// Decides the candidate, stops after `max_depth` or when `stop` is true.
+    fn check(&self, max_depth: Unroll, stop: &dyn Fn() -> bool) -> Res<Check> {
+        let sys = self.sys.restrict(|name| name == self.name);
+        let mut base = Base::new(&sys, self.conf.clone(), self.tee_dir())?.cancellable(self.cancel);
+        if let Some((_, cex)) = base.check()?.cexs.iter().next() {
+            return Ok(Check::Initial(cex.clone()));
+        }
+
+        for depth in 1..=max_depth {
+            let mut reach = self.sys.init().clone();
+            for count in 0..max_depth {
+                if stop() {
+                    return Ok(Check::Stopped("stopped"));
+                }
+                let itp = match self.interpolant(&reach, depth, count)? {
+                    Some(itp) => itp,
+                    None if count == 0 => return Ok(Check::Reached(depth)),
+                    None => break,
+                };
+                reach = Expr::new_op(Op::Or, vec![reach, itp])?;
+                if self.strengthened(&reach)? {
+                    return Ok(Check::Proved {
+                        depth,
+                        interpolants: count + 1,
+                    });
+                }
+            }
+        }
+        Ok(Check::Stopped("reached the maximum depth"))
+    }
+
+    /// Interpolant of the unrolling of `reach` for one step and the rest of a BMC query at
+    /// `depth`, `None` if the query is satisfiable.
+    ///
+    /// The interpolant is over the state variables, at step `1` in the query.
+    fn interpolant(&self, reach: &Expr, depth: Unroll, count: usize) -> Res<Option<Expr>> {
+        let tee = self.tee_file(format_args!("{}_{}", depth, count));
+        let mut checker = InternalChecker::new(self.sys, self.conf.clone(), tee)?;
+        checker.solver().watch(self.cancel);
+        for step in 0..=depth {
+            checker.declare_vars(step)?;
+        }
+
+        let pre = format!(
+            "(and {} {})",
+            smt2::expr(reach, 0)?,
+            smt2::expr(self.sys.trans(), 0)?
+        );
+        let mut post = "(and".to_string();
+        for step in 1..depth {
+            post.push(' ');
+            post.push_str(&smt2::expr(self.sys.trans(), step)?);
+        }
+        post.push_str(" (or");
+        for step in 1..=depth {
+            post.push(' ');
+            post.push_str(&smt2::expr(&self.po.negated(), step)?);
+        }
+        post.push_str("))");
+        checker
+            .solver()
+            .named_assert("pre", &pre)
+            .chain_err(|| "while asserting the reachable states")?;
+        checker
+            .solver()
+            .named_assert("post", &post)
+            .chain_err(|| format!("while asserting the BMC query at depth {}", depth))?;
+
+        let res = if checker.check_sat()? {
+            None
+        } else {
+            let itp: String = checker
+                .solver()
+                .get_interpolant("pre", "post")
+                .chain_err(|| "while retrieving an interpolant")?;
+            let mut parser = Parser::new(&itp);
+            let itp = parser
+                .pexpr(|parser| unrolled_var(parser, self.sys, 1))
+                .chain_err(|| format!("while parsing interpolant `{}`", itp))?;
+            Some(itp)
+        };
+        checker.solver().kill()?;
+        Ok(res)
+    }
+
+    /// True if the candidate strengthened by `reach` is inductive.
+    ///
+    /// `reach` must hold in the initial states.
+    fn strengthened(&self, reach: &Expr) -> Res<bool> {
+        // Priming the candidate's name cannot clash with it.
+        let reach_name = format!("{}'", self.name);
+        let mut po_s = Map::new();
+        let _ = po_s.insert(self.name.clone(), self.po.clone());
+        let _ = po_s.insert(reach_name, reach.clone());
+        let sys = Sys::new(
+            self.sys.decls().clone(),
+            self.sys.init().clone(),
+            self.sys.trans().clone(),
+            po_s,
+        );
+        let mut step = Step::new(&sys, self.conf.clone(), self.tee_dir())?.cancellable(self.cancel);
+        let res = step.check()?;
+        Ok(res.okay.contains(self.name))
+    }
+}
+
+/// Parses an unrolled state variable at some step, fails on any other symbol.
+///
+/// Yields `None` on applications, so that [`Parser::pexpr`] can parse them.
+fn unrolled_var(parser: &mut Parser, sys: &Sys, step: Unroll) -> Res<Option<Var>> {
+    let rest = parser.rest();
+    let sym = if rest.is_empty() || rest.starts_with('(') {
+        return Ok(None);
+    } else if let Some(quoted) = rest.strip_prefix('|') {
+        match quoted.find('|') {
+            Some(end) => &rest[..end + 2],
+            None => bail!(parser.fail("unclosed quoted symbol")),
+        }
+    } else {
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .unwrap_or(rest.len());
+        &rest[..end]
+    };
+    let var = match decls::demangle(sym)? {
+        (id, Some(s)) if s == step => sys.decls().get_var(&id),
+        _ => None,
+    };
+    match var {
+        Some(var) => {
+            let _ = parser.try_tag(sym);
+            Ok(Some(var))
+        }
+        None => bail!(parser.fail(format!("unexpected symbol `{}`", sym))),
+    }
+}
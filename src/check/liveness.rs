@@ -0,0 +1,265 @@
+//! Liveness properties, checked by lasso-shaped BMC.
+//!
+//! A liveness [`Property`] states that something good eventually happens. It is falsified by an
+//! infinite run, which bounded model checking can only find in the shape of a *lasso*: a finite
+//! run whose last state loops back to one of its states. [`bmc`] looks for a shortest [`Lasso`]
+//! violating the property, which is then a genuine infinite counterexample.
+//!
+//! Systems declare their liveness properties in a `liveness { ... }` block, see
+//! [`Sys::liveness`], they can also be parsed on their own by [`Property::parse`]. The
+//! [driver](super::driver) checks the ones of a system with its engines, as the LTL formulas they
+//! correspond to, see [`super::ltl`].
+//!
+//! Like BMC for candidates, lasso-shaped BMC only falsifies: not finding a lasso up to some depth
+//! does not mean the property holds.
+
+crate::prelude!();
+
+use rsmt2::print::Expr2Smt;
+
+use super::{cexs::Cex, InternalChecker};
+use expr::{display::Formatters, Expr};
+use trans::{Decls, Sys};
+
+/// A liveness property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Property {
+    /// The expression holds at some step of every run.
+    Eventually(Expr),
+    /// The expression holds infinitely often on every infinite run.
+    InfinitelyOften(Expr),
+}
+impl Property {
+    /// Parses a property, `eventually <expr>` or `infinitely_often <expr>`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::liveness::Property, parse};
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let prop = Property::parse("eventually cnt > 3", sys.decls()).unwrap();
+    /// assert_eq!(prop.to_string(), "eventually (> cnt 3)");
+    /// let prop = Property::parse("infinitely_often reset", sys.decls()).unwrap();
+    /// assert_eq!(prop.to_string(), "infinitely_often reset");
+    ///
+    /// let err = Property::parse("always cnt > 3", sys.decls()).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "expected `eventually` or `infinitely_often` followed by an expression",
+    /// );
+    /// let err = Property::parse("eventually cnt", sys.decls()).unwrap_err();
+    /// assert_eq!(err.to_string(), "liveness property has type `int`, expected `bool`");
+    /// ```
+    ///
+    /// See also the `liveness { ... }` block of [`parse::trans`](crate::parse::trans()).
+    pub fn parse(txt: &str, decls: &Decls) -> Res<Self> {
+        let txt = txt.trim();
+        let (keyword, ast) = crate::parse::rules::liveness_property(txt).map_err(|_| {
+            Error::from("expected `eventually` or `infinitely_often` followed by an expression")
+        })?;
+        let expr = ast
+            .to_expr(decls)
+            .map_err(|e| e.into_error(txt))
+            .chain_err(|| "in liveness property")?;
+        if expr.typ() != Typ::Bool {
+            bail!(
+                "liveness property has type `{}`, expected `bool`",
+                expr.typ()
+            )
+        }
+        Self::of_keyword(keyword.inner, expr)
+            .ok_or_else(|| format!("unknown liveness keyword `{}`", keyword.inner).into())
+    }
+    /// Property of a keyword, `eventually` or `infinitely_often`, and an expression.
+    pub(crate) fn of_keyword(keyword: &str, expr: Expr) -> Option<Self> {
+        match keyword {
+            "eventually" => Some(Self::Eventually(expr)),
+            "infinitely_often" => Some(Self::InfinitelyOften(expr)),
+            _ => None,
+        }
+    }
+
+    /// Expression of the property.
+    pub fn expr(&self) -> &Expr {
+        match self {
+            Self::Eventually(expr) | Self::InfinitelyOften(expr) => expr,
+        }
+    }
+}
+impl fmt::Display for Property {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Eventually(expr) => write!(fmt, "eventually {}", expr),
+            Self::InfinitelyOften(expr) => write!(fmt, "infinitely_often {}", expr),
+        }
+    }
+}
+
+/// A lasso-shaped counterexample: the successor of its last state is the state at `loop_start`.
+#[derive(Debug, Clone)]
+pub struct Lasso {
+    /// Finite run, the stem followed by the loop.
+    pub cex: Cex,
+    /// Step the last state loops back to.
+    pub loop_start: Unroll,
+}
+impl Lasso {
+    /// Renders the lasso, see [`Cex::render`], with the loop highlighted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{cexs::Cex, liveness::Lasso}, expr::{display::Formatters, Cst}, parse};
+    /// let sys = parse::trans(
+    ///     "svars { cnt: int } init { cnt = 0 } trans { 'cnt = 1 - cnt } \
+    ///     candidates { \"cnt\": cnt ≥ 0 }",
+    /// )
+    /// .unwrap();
+    /// let cnt = sys.decls().get_var("cnt").unwrap();
+    /// let mut cex = Cex::new();
+    /// for step in 0..3 {
+    ///     cex.insert(step, cnt.clone(), Cst::int(step % 2)).unwrap();
+    /// }
+    /// let lasso = Lasso { cex, loop_start: 1 };
+    /// assert_eq!(
+    ///     lasso.render(sys.decls(), &Formatters::new()),
+    ///     "\
+    /// step 0
+    ///     cnt = 0
+    /// step 1 (loop start)
+    ///     cnt = 1
+    /// step 2
+    ///     cnt = 0
+    /// loops back to step 1
+    /// ",
+    /// );
+    /// ```
+    pub fn render(&self, decls: &Decls, fmts: &Formatters) -> String {
+        let header = format!("step {}", self.loop_start);
+        let mut s = String::new();
+        for line in self.cex.render(decls, fmts).lines() {
+            s.push_str(line);
+            let rest = line.strip_prefix(&header);
+            if rest.is_some_and(|rest| rest.is_empty() || rest.starts_with(" (")) {
+                s.push_str(" (loop start)")
+            }
+            s.push('\n')
+        }
+        s.push_str(&format!("loops back to step {}\n", self.loop_start));
+        s
+    }
+}
+
+/// Looks for a shortest lasso of at most `depth + 1` states violating a liveness property.
+///
+/// For [`Property::Eventually`], the expression is false on all the states of the lasso. For
+/// [`Property::InfinitelyOften`], it is false on all the states of the loop. Yields `None` if no
+/// such lasso exists.
+///
+/// # Errors
+///
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::liveness::{self, Property}, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// // `cnt` stays at `0` if `inc` is always false.
+/// let prop = Property::parse("eventually cnt > 0", sys.decls()).unwrap();
+/// let lasso = liveness::bmc(&sys, &prop, 5, SmtConf::z3("z3")).unwrap().unwrap();
+/// assert_eq!((lasso.cex.len(), lasso.loop_start), (1, 0));
+///
+/// // `cnt` can increase once and then stay at `1` forever.
+/// let prop = Property::parse("infinitely_often cnt = 0", sys.decls()).unwrap();
+/// let lasso = liveness::bmc(&sys, &prop, 5, SmtConf::z3("z3")).unwrap().unwrap();
+/// assert_eq!((lasso.cex.len(), lasso.loop_start), (2, 1));
+/// ```
+pub fn bmc(sys: &Sys, prop: &Property, depth: Unroll, conf: SmtConf) -> Res<Option<Lasso>> {
+    let neg = Expr::new_op(expr::Op::Not, vec![prop.expr().clone()])?;
+    let constraints = match prop {
+        Property::Eventually(_) => Constraints {
+            always: Some(&neg),
+            ..Constraints::default()
+        },
+        Property::InfinitelyOften(_) => Constraints {
+            in_loop: Some(&neg),
+            ..Constraints::default()
+        },
+    };
+    lasso(sys, &constraints, depth, conf)
+}
+
+/// Constraints on the lassos [`lasso`] looks for.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Constraints<'a> {
+    /// Holds on all the states of the lasso.
+    pub always: Option<&'a Expr>,
+    /// Holds on all the states of the loop.
+    pub in_loop: Option<&'a Expr>,
+    /// Each of them holds on at least one state of the loop.
+    pub fair: &'a [Expr],
+}
+
+/// Looks for a shortest lasso of at most `depth + 1` states verifying some constraints.
+pub(crate) fn lasso(
+    sys: &Sys,
+    constraints: &Constraints,
+    depth: Unroll,
+    conf: SmtConf,
+) -> Res<Option<Lasso>> {
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    let mut res = None;
+
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    'depth: for last in 0..=depth {
+        if let Some(always) = constraints.always {
+            checker.assert_expr(always, last)?;
+        }
+        checker.declare_vars(last + 1)?;
+        checker.assert_trans(last)?;
+        for loop_start in 0..=last {
+            checker.solver().push(1)?;
+            checker.assert_frames_eq(None, last + 1, loop_start)?;
+            if let Some(in_loop) = constraints.in_loop {
+                for step in loop_start..=last {
+                    checker.assert_expr(in_loop, step)?;
+                }
+            }
+            for fair in constraints.fair {
+                // Disjunction over the steps of the loop, not expressible as an `Expr`.
+                let mut disj: Vec<u8> = b"(or".to_vec();
+                for step in loop_start..=last {
+                    disj.push(b' ');
+                    fair.expr_to_smt2(&mut disj, step)?;
+                }
+                disj.push(b')');
+                let disj = String::from_utf8_lossy(&disj);
+                checker
+                    .solver()
+                    .assert(&*disj)
+                    .chain_err(|| format!("while asserting fairness constraint `{}`", fair))?;
+            }
+            if checker.check_sat()? {
+                let mut cex = Cex::new();
+                let vars: Vec<_> = sys.decls().all().collect();
+                cex.populate_or_salvage(checker.solver(), &vars, 0..=last);
+                cex.trace.truncate(last + 1);
+                res = Some(Lasso { cex, loop_start });
+            }
+            checker.solver().pop(1)?;
+            if res.is_some() {
+                break 'depth;
+            }
+        }
+    }
+    checker.solver().kill()?;
+
+    Ok(res)
+}
@@ -0,0 +1,676 @@
+//! Linear Temporal Logic (LTL) properties, compiled to monitors.
+//!
+//! [`Ltl`] formulas combine boolean hsmt expressions, the *atoms*, with the temporal operators `X`
+//! (next), `G` (globally), `F` (finally) and `U` (until), and the boolean connectives. A
+//! [`Monitor`] is the tableau of the negation of a formula: fresh boolean state variables that
+//! guess, at each step, whether the `X` and `U` subformulas hold, constrained by the transition
+//! relation and by fairness constraints. Composed with a system, the fair runs of the
+//! [product](Monitor::product) are exactly the runs of the system violating the formula.
+//!
+//! [`bmc`] looks for such a run with lasso-shaped BMC, see [`super::liveness`]: a violation is
+//! reported as a [`Lasso`] over the variables of the system.
+//!
+//! Systems declare their LTL properties in an `ltl { ... }` block, see [`Sys::ltl`]. The
+//! [driver](super::driver) checks them with its engines, like candidates, through the
+//! [reduction](Safety) of the fair runs of the product to a safety candidate. Unlike [`bmc`], the
+//! engines can then also prove formulas.
+//!
+//! ```rust,no_run
+//! # use mikino_api::{check, parse, rsmt2::SmtConf};
+//! let sys = parse::trans(r#"
+//! svars { req ack: bool }
+//! init { ¬ack }
+//! trans { 'ack = req }
+//! candidates { "ack": ack ⇒ ack }
+//! ltl {
+//!     "response": G ({req} ⇒ X {ack}),
+//!     "fairness": G F {req},
+//! }
+//! "#).unwrap();
+//! let config = check::Config::new(SmtConf::z3("z3")).bmc_max(5);
+//! let outcome = check::run(&sys, &config).unwrap();
+//! assert!(outcome["response"].is_proved());
+//! assert!(outcome["fairness"].is_falsified());
+//! assert_eq!(outcome.loops["fairness"], 0);
+//! ```
+//!
+//! # Syntax
+//!
+//! Atoms are hsmt expressions between braces. From lowest to highest precedence:
+//!
+//! - implication `⇒` (also `=>`, `→`), right-associative;
+//! - disjunction `⋁` (also `∨`, `||`, `or`);
+//! - conjunction `⋀` (also `∧`, `&&`, `and`);
+//! - until `U`, right-associative;
+//! - unary operators `¬` (also `!`, `not`), `X`, `G` and `F`, and parentheses.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use mikino_api::{check::ltl::Ltl, parse};
+//! let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+//! let ltl = Ltl::parse("G ({reset} ⇒ X F {cnt = 0})", sys.decls()).unwrap();
+//! assert_eq!(ltl.to_string(), "G ({ reset } ⇒ X F { cnt = 0 })");
+//! let ltl = Ltl::parse("{cnt ≥ 0} U {stop} ∧ ¬{reset}", sys.decls()).unwrap();
+//! assert_eq!(ltl.to_string(), "{ cnt ≥ 0 } U { stop } ⋀ ¬{ reset }");
+//!
+//! let err = Ltl::parse("G {cnt}", sys.decls()).unwrap_err();
+//! assert_eq!(err.to_string(), "LTL atom has type `int`, expected `bool`");
+//! ```
+
+crate::prelude!();
+
+use super::{
+    cexs::Cex,
+    liveness::{self, Constraints, Lasso},
+};
+use crate::parse::Span;
+use expr::{Cst, Expr, Op, PExpr, SExpr, Var};
+use trans::{Decls, Sys};
+
+/// An LTL formula, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ltl {
+    /// A boolean expression over the variables of the system.
+    Atom(Expr),
+    /// Negation.
+    Not(Box<Ltl>),
+    /// Conjunction.
+    And(Box<Ltl>, Box<Ltl>),
+    /// Disjunction.
+    Or(Box<Ltl>, Box<Ltl>),
+    /// Implication.
+    Implies(Box<Ltl>, Box<Ltl>),
+    /// Holds at the next step.
+    Next(Box<Ltl>),
+    /// Holds at all the steps from now on.
+    Globally(Box<Ltl>),
+    /// Holds at some step from now on.
+    Finally(Box<Ltl>),
+    /// The second formula holds at some step from now on, the first one holds until then.
+    Until(Box<Ltl>, Box<Ltl>),
+}
+impl Ltl {
+    /// Parses a formula, see the [module-level documentation](self).
+    ///
+    /// See also the `ltl { ... }` block of [`parse::trans`](crate::parse::trans()).
+    pub fn parse(txt: &str, decls: &Decls) -> Res<Self> {
+        let mut parser = Parser { txt, pos: 0, decls };
+        let res = parser.implies()?;
+        parser.ws();
+        if parser.pos < txt.len() {
+            return Err(parser.error("expected the end of the LTL formula").into());
+        }
+        Ok(res)
+    }
+
+    /// Precedence of the formula, higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Implies(_, _) => 0,
+            Self::Or(_, _) => 1,
+            Self::And(_, _) => 2,
+            Self::Until(_, _) => 3,
+            _ => 4,
+        }
+    }
+
+    /// Equivalent formula without `G`, `F`, `∨` and `⇒`.
+    fn desugar(&self) -> Self {
+        let tru = || Box::new(Self::Atom(Cst::bool(true).into()));
+        let not = |f: Self| Box::new(Self::Not(Box::new(f)));
+        match self {
+            Self::Atom(expr) => Self::Atom(expr.clone()),
+            Self::Not(f) => Self::Not(Box::new(f.desugar())),
+            Self::And(lft, rgt) => Self::And(Box::new(lft.desugar()), Box::new(rgt.desugar())),
+            Self::Or(lft, rgt) => {
+                Self::Not(Box::new(Self::And(not(lft.desugar()), not(rgt.desugar()))))
+            }
+            Self::Implies(lft, rgt) => Self::Not(Box::new(Self::And(
+                Box::new(lft.desugar()),
+                not(rgt.desugar()),
+            ))),
+            Self::Next(f) => Self::Next(Box::new(f.desugar())),
+            Self::Globally(f) => Self::Not(Box::new(Self::Until(tru(), not(f.desugar())))),
+            Self::Finally(f) => Self::Until(tru(), Box::new(f.desugar())),
+            Self::Until(lft, rgt) => Self::Until(Box::new(lft.desugar()), Box::new(rgt.desugar())),
+        }
+    }
+}
+/// Formula of a liveness property: `F {p}` for `eventually p`, `G F {p}` for
+/// `infinitely_often p`.
+impl From<&liveness::Property> for Ltl {
+    fn from(prop: &liveness::Property) -> Self {
+        let atom = Box::new(Self::Atom(prop.expr().clone()));
+        match prop {
+            liveness::Property::Eventually(_) => Self::Finally(atom),
+            liveness::Property::InfinitelyOften(_) => Self::Globally(Box::new(Self::Finally(atom))),
+        }
+    }
+}
+impl fmt::Display for Ltl {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let sub = |fmt: &mut fmt::Formatter, f: &Self, min: u8| {
+            if f.precedence() < min {
+                write!(fmt, "({})", f)
+            } else {
+                write!(fmt, "{}", f)
+            }
+        };
+        match self {
+            Self::Atom(expr) => match expr.to_hsmt() {
+                Ok(hsmt) => write!(fmt, "{{ {} }}", hsmt),
+                Err(_) => write!(fmt, "{{ {} }}", expr),
+            },
+            Self::Not(f) | Self::Next(f) | Self::Globally(f) | Self::Finally(f) => {
+                let op = match self {
+                    Self::Not(_) => "¬",
+                    Self::Next(_) => "X ",
+                    Self::Globally(_) => "G ",
+                    _ => "F ",
+                };
+                write!(fmt, "{}", op)?;
+                sub(fmt, f, 4)
+            }
+            Self::And(lft, rgt) | Self::Or(lft, rgt) | Self::Implies(lft, rgt) => {
+                let (op, prec) = match self {
+                    Self::And(_, _) => ("⋀", 2),
+                    Self::Or(_, _) => ("⋁", 1),
+                    _ => ("⇒", 0),
+                };
+                // Implication is right-associative, conjunction and disjunction are associative.
+                let lft_min = if prec == 0 { 1 } else { prec };
+                sub(fmt, lft, lft_min)?;
+                write!(fmt, " {} ", op)?;
+                sub(fmt, rgt, prec)
+            }
+            Self::Until(lft, rgt) => {
+                sub(fmt, lft, 4)?;
+                write!(fmt, " U ")?;
+                sub(fmt, rgt, 3)
+            }
+        }
+    }
+}
+
+/// Recursive-descent parser for LTL formulas.
+struct Parser<'a> {
+    /// Text to parse.
+    txt: &'a str,
+    /// Current position in the text.
+    pos: usize,
+    /// Declarations, for atoms.
+    decls: &'a Decls,
+}
+impl Parser<'_> {
+    /// Error at the current position.
+    fn error(&self, msg: impl Into<String>) -> Error {
+        Error::parse_span(msg, Span::new(self.pos, self.pos), self.txt)
+    }
+
+    /// Skips whitespaces.
+    fn ws(&mut self) {
+        let rest = &self.txt[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Parses one of some tags, if any.
+    ///
+    /// Tags that are words must not be followed by an identifier character.
+    fn tag(&mut self, tags: &[&str]) -> bool {
+        self.ws();
+        let rest = &self.txt[self.pos..];
+        for tag in tags {
+            if let Some(after) = rest.strip_prefix(tag) {
+                let is_word = tag.starts_with(|c: char| c.is_ascii_alphabetic());
+                if is_word && after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+                    continue;
+                }
+                self.pos += tag.len();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Parses an implication, the top-level rule.
+    fn implies(&mut self) -> Res<Ltl> {
+        let lft = self.or()?;
+        if self.tag(&["⇒", "=>", "→"]) {
+            let rgt = self.implies()?;
+            Ok(Ltl::Implies(Box::new(lft), Box::new(rgt)))
+        } else {
+            Ok(lft)
+        }
+    }
+    /// Parses a disjunction.
+    fn or(&mut self) -> Res<Ltl> {
+        let mut res = self.and()?;
+        while self.tag(&["⋁", "∨", "||", "or"]) {
+            res = Ltl::Or(Box::new(res), Box::new(self.and()?))
+        }
+        Ok(res)
+    }
+    /// Parses a conjunction.
+    fn and(&mut self) -> Res<Ltl> {
+        let mut res = self.until()?;
+        while self.tag(&["⋀", "∧", "&&", "and"]) {
+            res = Ltl::And(Box::new(res), Box::new(self.until()?))
+        }
+        Ok(res)
+    }
+    /// Parses an until.
+    fn until(&mut self) -> Res<Ltl> {
+        let lft = self.unary()?;
+        if self.tag(&["U"]) {
+            let rgt = self.until()?;
+            Ok(Ltl::Until(Box::new(lft), Box::new(rgt)))
+        } else {
+            Ok(lft)
+        }
+    }
+    /// Parses a unary formula, a parenthesized formula or an atom.
+    fn unary(&mut self) -> Res<Ltl> {
+        if self.tag(&["¬", "!", "not"]) {
+            Ok(Ltl::Not(Box::new(self.unary()?)))
+        } else if self.tag(&["X"]) {
+            Ok(Ltl::Next(Box::new(self.unary()?)))
+        } else if self.tag(&["G"]) {
+            Ok(Ltl::Globally(Box::new(self.unary()?)))
+        } else if self.tag(&["F"]) {
+            Ok(Ltl::Finally(Box::new(self.unary()?)))
+        } else if self.tag(&["("]) {
+            let res = self.implies()?;
+            if !self.tag(&[")"]) {
+                return Err(self.error("expected `)`").into());
+            }
+            Ok(res)
+        } else if self.tag(&["{"]) {
+            self.atom()
+        } else {
+            Err(self.error("expected an LTL formula").into())
+        }
+    }
+    /// Parses an atom, after its opening brace.
+    fn atom(&mut self) -> Res<Ltl> {
+        let start = self.pos;
+        let mut depth = 0;
+        for (idx, c) in self.txt[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                '}' => {
+                    let span = Span::new(start, start + idx);
+                    let expr = crate::parse::expr(&self.txt[start..start + idx], self.decls)
+                        .chain_err(|| Error::parse_span("in this LTL atom", span, self.txt))?;
+                    if expr.typ() != Typ::Bool {
+                        bail!("LTL atom has type `{}`, expected `bool`", expr.typ())
+                    }
+                    self.pos = start + idx + 1;
+                    return Ok(Ltl::Atom(expr));
+                }
+                _ => (),
+            }
+        }
+        Err(self.error("unclosed LTL atom").into())
+    }
+}
+
+/// Tableau of the negation of an LTL formula, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::ltl::{Ltl, Monitor}, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let ltl = Ltl::parse("G F {cnt = 0}", sys.decls()).unwrap();
+/// let monitor = Monitor::new(&ltl, sys.decls()).unwrap();
+/// assert_eq!(monitor.vars.len(), 2);
+/// assert_eq!(monitor.fairness.len(), 2);
+/// let product = monitor.product(&sys).unwrap();
+/// assert_eq!(product.decls().all().count(), sys.decls().all().count() + 2);
+/// assert!(product.po_s().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    /// Fresh boolean state variables, one for each `X` formula of the tableau.
+    pub vars: Vec<Var>,
+    /// Transition constraints of the variables.
+    pub trans: Vec<SExpr>,
+    /// Fairness constraints, each of them must hold infinitely often.
+    pub fairness: Vec<Expr>,
+    /// Holds on the first state of the runs violating the formula.
+    pub violation: Expr,
+}
+impl Monitor {
+    /// Builds the monitor of a formula, its variables are named `ltl_<n>` and fresh in `decls`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::ltl::{Ltl, Monitor}, parse};
+    /// let sys = parse::trans(
+    ///     "svars { a ltl_1: bool } init { a } trans { 'a = ltl_1 } candidates { \"a\": a }",
+    /// )
+    /// .unwrap();
+    /// let ltl = Ltl::parse("X X X {a}", sys.decls()).unwrap();
+    /// let monitor = Monitor::new(&ltl, sys.decls()).unwrap();
+    /// let names: Vec<_> = monitor.vars.iter().map(|var| var.id().to_string()).collect();
+    /// assert_eq!(names, vec!["ltl_0", "ltl_2", "ltl_3"]);
+    /// assert!(monitor.product(&sys).is_ok());
+    /// ```
+    pub fn new(ltl: &Ltl, decls: &Decls) -> Res<Self> {
+        let mut tableau = Tableau {
+            decls,
+            elementary: vec![],
+            trans: vec![],
+            fairness: vec![],
+        };
+        let neg = Ltl::Not(Box::new(ltl.desugar()));
+        let violation = tableau.sat(&neg)?;
+        Ok(Self {
+            vars: tableau.elementary.into_iter().map(|(_, var)| var).collect(),
+            trans: tableau.trans,
+            fairness: tableau.fairness,
+            violation,
+        })
+    }
+
+    /// Composes the monitor with a system.
+    ///
+    /// The initial states of the product are the ones where [`Self::violation`] holds. The product
+    /// has no candidates.
+    pub fn product(&self, sys: &Sys) -> Res<Sys> {
+        let mut decls = sys.decls().clone();
+        for var in self.vars.iter() {
+            if decls.register(var.id(), var.typ()).is_some() {
+                bail!("monitor variable `{}` is already declared", var.id())
+            }
+        }
+        let init = Expr::new_op(Op::And, vec![sys.init().clone(), self.violation.clone()])?;
+        let mut trans = vec![sys.trans().clone()];
+        trans.extend(self.trans.iter().cloned());
+        let trans = SExpr::new_op(Op::And, trans)?;
+        Ok(Sys::new(decls, init, trans, Map::new()))
+    }
+}
+
+/// Tableau construction.
+struct Tableau<'a> {
+    /// Declarations of the system, to generate fresh names.
+    decls: &'a Decls,
+    /// Maps the formulas `f` of the `X f` formulas to their variable.
+    elementary: Vec<(Ltl, Var)>,
+    /// Transition constraints.
+    trans: Vec<SExpr>,
+    /// Fairness constraints.
+    fairness: Vec<Expr>,
+}
+impl Tableau<'_> {
+    /// Variable of an `X f` formula, created if needed.
+    fn next(&mut self, f: &Ltl) -> Res<Expr> {
+        if let Some((_, var)) = self.elementary.iter().find(|(g, _)| g == f) {
+            return Ok(PExpr::new_var(var.clone()));
+        }
+        let mut idx = self.elementary.len();
+        let mut name = format!("ltl_{}", idx);
+        let taken = |name: &str| {
+            self.decls.get_var(name).is_some()
+                || self.elementary.iter().any(|(_, var)| var.id() == name)
+        };
+        while taken(&name) {
+            idx += 1;
+            name = format!("ltl_{}", idx);
+        }
+        let var = Var::new(name, Typ::Bool);
+        // Registered before computing `sat(f)`, which mentions `X f` when `f` is an until.
+        self.elementary.push((f.clone(), var.clone()));
+        let sat = self.sat(f)?;
+        let var = PExpr::new_var(var);
+        self.trans.push(SExpr::new_op(
+            Op::Eq,
+            vec![var.clone().into_sexpr(false), sat.into_sexpr(true)],
+        )?);
+        Ok(var)
+    }
+
+    /// Expression over the system and monitor variables equivalent to a desugared formula.
+    fn sat(&mut self, f: &Ltl) -> Res<Expr> {
+        let res = match f {
+            Ltl::Atom(expr) => expr.clone(),
+            Ltl::Not(f) => Expr::new_op(Op::Not, vec![self.sat(f)?])?,
+            Ltl::And(lft, rgt) => Expr::new_op(Op::And, vec![self.sat(lft)?, self.sat(rgt)?])?,
+            Ltl::Next(f) => self.next(f)?,
+            Ltl::Until(lft, rgt) => {
+                let (lft_sat, rgt_sat) = (self.sat(lft)?, self.sat(rgt)?);
+                let next = self.next(f)?;
+                let res = Expr::new_op(
+                    Op::Or,
+                    vec![rgt_sat.clone(), Expr::new_op(Op::And, vec![lft_sat, next])?],
+                )?;
+                // The until cannot be postponed forever.
+                let fair = Expr::new_op(
+                    Op::Or,
+                    vec![Expr::new_op(Op::Not, vec![res.clone()])?, rgt_sat],
+                )?;
+                if !self.fairness.contains(&fair) {
+                    self.fairness.push(fair)
+                }
+                res
+            }
+            Ltl::Or(_, _) | Ltl::Implies(_, _) | Ltl::Globally(_) | Ltl::Finally(_) => {
+                bail!("[bug] tableau of a formula that is not desugared")
+            }
+        };
+        Ok(res)
+    }
+}
+
+/// Liveness-to-safety reduction of an LTL formula over a system.
+///
+/// The [product](Monitor::product) of the system with the monitor of the formula is extended so
+/// that a safety candidate is falsified exactly when the product reaches a fair lasso, *i.e.* a
+/// run violating the formula. The extension guesses a step where the loop starts and saves the
+/// state there. The candidate is falsified once the current state is the saved one and each
+/// fairness constraint held at some step since the save. A counterexample is thus a lasso, see
+/// [`Self::lasso`].
+///
+/// The candidate is an ordinary safety property: the [engines](super::engine) of the
+/// [driver](super::driver) can falsify it, but also prove it, which proves the formula.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::ltl::{Ltl, Safety}, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let ltl = Ltl::parse("G F {cnt = 0}", sys.decls()).unwrap();
+/// let safety = Safety::new(&sys, &ltl, "resets").unwrap();
+/// assert_eq!(safety.sys.po_s().keys().collect::<Vec<_>>(), vec!["resets"]);
+/// // Monitor variables, their copy and the one of each variable of `sys`, a "seen" flag for each
+/// // fairness constraint, and the save input and flag.
+/// assert_eq!(
+///     safety.sys.decls().all().count(),
+///     2 * (sys.decls().all().count() + 2) + 2 + 2,
+/// );
+/// ```
+pub struct Safety {
+    /// Safety system, its only candidate is named after the formula.
+    pub sys: Sys,
+    /// Variable that is true once the state is saved.
+    saved: Var,
+}
+impl Safety {
+    /// Builds the reduction of a formula, the candidate of the result is named `name`.
+    ///
+    /// Fresh variables are named `l2s_<n>`.
+    pub fn new(sys: &Sys, ltl: &Ltl, name: impl Into<String>) -> Res<Self> {
+        let monitor = Monitor::new(ltl, sys.decls())?;
+        let product = monitor.product(sys)?;
+        let mut decls = product.decls().clone();
+        let mut fresh = |typ: Typ| {
+            let mut idx = 0;
+            while decls.get_var(format!("l2s_{}", idx)).is_some() {
+                idx += 1
+            }
+            let id = format!("l2s_{}", idx);
+            let _ = decls.register(id.clone(), typ.clone());
+            Var::new(id, typ)
+        };
+        let (save, saved) = (fresh(Typ::Bool), fresh(Typ::Bool));
+        let copies: Vec<(Var, Var)> = product
+            .decls()
+            .all()
+            .map(|var| {
+                let copy = fresh(var.typ());
+                (var, copy)
+            })
+            .collect();
+        let seen: Vec<Var> = monitor.fairness.iter().map(|_| fresh(Typ::Bool)).collect();
+
+        let var = |var: &Var| PExpr::new_var(var.clone());
+        let (curr, next) = (
+            |v: &Var| var(v).into_sexpr(false),
+            |v: &Var| var(v).into_sexpr(true),
+        );
+        let not = |expr: Expr| Expr::new_op(Op::Not, vec![expr]);
+
+        let mut init = vec![product.init().clone(), not(var(&saved))?];
+        for seen in seen.iter() {
+            init.push(not(var(seen))?)
+        }
+        let init = Expr::new_op(Op::And, init)?;
+
+        // Saving now, or saved before.
+        let saving = SExpr::new_op(
+            Op::And,
+            vec![not(var(&saved))?.into_sexpr(false), curr(&save)],
+        )?;
+        let recording = SExpr::new_op(Op::Or, vec![curr(&saved), curr(&save)])?;
+        let mut trans = vec![
+            product.trans().clone(),
+            SExpr::new_op(Op::Eq, vec![next(&saved), recording.clone()])?,
+        ];
+        for (var, copy) in copies.iter() {
+            let update = SExpr::new_op(Op::Ite, vec![saving.clone(), curr(var), curr(copy)])?;
+            trans.push(SExpr::new_op(Op::Eq, vec![next(copy), update])?)
+        }
+        for (seen, fair) in seen.iter().zip(monitor.fairness.iter()) {
+            let now = SExpr::new_op(Op::Or, vec![curr(seen), fair.clone().into_sexpr(false)])?;
+            let now = SExpr::new_op(Op::And, vec![now, recording.clone()])?;
+            trans.push(SExpr::new_op(Op::Eq, vec![next(seen), now])?)
+        }
+        let trans = SExpr::new_op(Op::And, trans)?;
+
+        let mut lasso = vec![var(&saved)];
+        for (var, copy) in copies.iter() {
+            lasso.push(Expr::new_op(
+                Op::Eq,
+                vec![PExpr::new_var(var.clone()), PExpr::new_var(copy.clone())],
+            )?)
+        }
+        lasso.extend(seen.iter().map(var));
+        let candidate = not(Expr::new_op(Op::And, lasso)?)?;
+
+        let mut po_s = Map::new();
+        let _ = po_s.insert(name.into(), candidate);
+        Ok(Self {
+            sys: Sys::new(decls, init, trans, po_s),
+            saved,
+        })
+    }
+
+    /// Lasso over the variables of `sys` of a counterexample of the reduction.
+    ///
+    /// The last state of the counterexample is the saved one, it is dropped and the lasso loops
+    /// back to the step where the state was saved. Yields `None` if `cex` does not end with such a
+    /// state.
+    ///
+    /// # Examples
+    ///
+    /// Explicit-state exploration, see [`super::explicit`], is one of the engines the reduction
+    /// works with.
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{explicit::Explorer, ltl::{Ltl, Safety}, Verdict}, parse};
+    /// let sys = parse::trans(
+    ///     "svars { req ack: bool } init { ¬ack } trans { 'ack = req } \
+    ///     candidates { \"ack\": ack ⇒ ack }",
+    /// )
+    /// .unwrap();
+    /// let ltl = Ltl::parse("{¬ack}", sys.decls()).unwrap();
+    /// let safety = Safety::new(&sys, &ltl, "starts low").unwrap();
+    /// let exploration = Explorer::new(&safety.sys).run().unwrap();
+    /// assert!(exploration.verdicts["starts low"].is_proved());
+    ///
+    /// // Requests can stop, and so do acknowledgements.
+    /// let ltl = Ltl::parse("F {ack}", sys.decls()).unwrap();
+    /// let safety = Safety::new(&sys, &ltl, "acks").unwrap();
+    /// let exploration = Explorer::new(&safety.sys).run().unwrap();
+    /// let lasso = match &exploration.verdicts["acks"] {
+    ///     Verdict::Falsified { cex, .. } => safety.lasso(&sys, cex).unwrap(),
+    ///     verdict => panic!("expected a falsification, got {}", verdict),
+    /// };
+    /// assert_eq!((lasso.cex.len(), lasso.loop_start), (1, 0));
+    /// assert_eq!(lasso.cex.get(0, "ack").unwrap().to_string(), "false");
+    /// assert_eq!(lasso.cex.get(0, "req").unwrap().to_string(), "false");
+    /// ```
+    pub fn lasso(&self, sys: &Sys, cex: &Cex) -> Option<Lasso> {
+        let last = cex.last_step()?;
+        let saved_at = (0..=last).find(|step| {
+            cex.get(*step, self.saved.id())
+                .is_some_and(|val| *val == Cst::bool(true))
+        })?;
+        // The state is saved at the step before the first step where `saved` holds.
+        let loop_start = saved_at.checked_sub(1)?;
+        let mut cex = cex.clone();
+        cex.trace.truncate(last);
+        cex.trace = cex
+            .trace
+            .select(sys.decls().all().map(|var| var.id().to_string()));
+        Some(Lasso { cex, loop_start })
+    }
+}
+
+/// Looks for a shortest lasso of at most `depth + 1` states violating an LTL formula.
+///
+/// Builds the [`Monitor`] of the formula and looks for a fair lasso in the product. The lasso only
+/// mentions the variables of `sys`. Yields `None` if no such lasso exists.
+///
+/// # Errors
+///
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::ltl::{self, Ltl}, expr::display::Formatters, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { req ack: bool } init { ¬ack } trans { 'ack = req } \
+///     candidates { \"ack\": ack ⇒ ack }",
+/// )
+/// .unwrap();
+/// // Acknowledgements follow requests.
+/// let ltl = Ltl::parse("G ({req} ⇒ X {ack})", sys.decls()).unwrap();
+/// assert!(ltl::bmc(&sys, &ltl, 5, SmtConf::z3("z3")).unwrap().is_none());
+///
+/// // But requests can stop.
+/// let ltl = Ltl::parse("G F {req}", sys.decls()).unwrap();
+/// let lasso = ltl::bmc(&sys, &ltl, 5, SmtConf::z3("z3")).unwrap().unwrap();
+/// println!("{}", lasso.render(sys.decls(), &Formatters::new()));
+/// ```
+pub fn bmc(sys: &Sys, ltl: &Ltl, depth: Unroll, conf: SmtConf) -> Res<Option<Lasso>> {
+    let monitor = Monitor::new(ltl, sys.decls())?;
+    let product = monitor.product(sys)?;
+    let constraints = Constraints {
+        fair: &monitor.fairness,
+        ..Constraints::default()
+    };
+    let lasso = liveness::lasso(&product, &constraints, depth, conf)?;
+    Ok(lasso.map(|mut lasso| {
+        lasso.cex.trace = lasso
+            .cex
+            .trace
+            .select(sys.decls().all().map(|var| var.id().to_string()));
+        lasso
+    }))
+}
@@ -0,0 +1,128 @@
+//! Counterexample minimization, small values for readable counterexamples.
+//!
+//! Solvers pick *some* value for every variable of a counterexample, often huge integers that
+//! obscure why the candidate is falsified. [`minimize`] looks for a counterexample of the same
+//! length where integer values are as close to zero as possible.
+//!
+//! Values are minimized one after the other, step by step and in the order of the declarations.
+//! The absolute value of each one is minimized by binary search, with one check-sat per halving,
+//! and then bounded for the remaining searches. So a value is minimal given the values before it,
+//! later values are not traded for smaller earlier ones.
+
+crate::prelude!();
+
+use super::{cexs::Cex, driver::cex_depth, InternalChecker};
+use expr::{Cst, Expr, Op, PExpr, Typ, Var};
+use num::Signed;
+use trans::Sys;
+
+/// Minimizes the absolute values of the integer variables of a counterexample for a candidate.
+///
+/// The counterexample must falsify `candidate` at its last step, like the counterexamples of
+/// [`Verdict::Falsified`](super::Verdict::Falsified). The result has the same length and
+/// falsifies `candidate` at its last step too. Non-integer values may change.
+///
+/// # Errors
+///
+/// - when `candidate` is not a candidate of `sys`,
+/// - when no trace of the length of `cex` falsifies `candidate`, or
+/// - when the solver fails, for instance if it answers `unknown`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int, inc: int }
+/// init { cnt ≥ 1000 }
+/// trans { 'cnt = cnt + inc }
+/// candidates { "cnt is not 0": ¬(cnt = 0) }
+/// "#).unwrap();
+/// let conf = SmtConf::z3("z3");
+/// let outcome = check::run(&sys, &check::Config::new(conf.clone()).bmc_max(5)).unwrap();
+/// let (_, _, cex) = outcome.falsified().next().unwrap();
+/// let cex = check::minimize::minimize(&sys, "cnt is not 0", cex, &conf).unwrap();
+/// // Falsified at step 1 with `inc` the opposite of `cnt`, which is as small as possible.
+/// assert_eq!(cex.trace.get_val(0, "cnt").unwrap().to_string(), "1000");
+/// assert_eq!(cex.trace.get_val(0, "inc").unwrap().to_string(), "(- 1000)");
+/// assert_eq!(cex.trace.get_val(1, "inc").unwrap().to_string(), "0");
+/// ```
+pub fn minimize(sys: &Sys, candidate: &str, cex: &Cex, conf: &SmtConf) -> Res<Cex> {
+    let po = match sys.po_s().get(candidate) {
+        Some(po) => po,
+        None => bail!("unknown candidate `{}`", candidate),
+    };
+    let depth = cex_depth(cex);
+
+    let mut checker = InternalChecker::new(sys, conf.clone(), None)?;
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for step in 0..depth {
+        checker.declare_vars(step + 1)?;
+        checker.assert_trans(step)?;
+    }
+    checker
+        .solver()
+        .assert_with(po.negated(), depth)
+        .chain_err(|| format!("while asserting the negation of `{}`", candidate))?;
+    if !checker.check_sat()? {
+        bail!(
+            "no trace of length {} falsifies candidate `{}`",
+            depth + 1,
+            candidate
+        )
+    }
+    let mut current = Cex::new();
+    current.populate(checker.solver())?;
+
+    let ints: Vec<(Unroll, Var)> = cex
+        .trace
+        .range(0..=depth)
+        .flat_map(|(step, vals)| {
+            vals.keys()
+                .filter(|var| var.typ() == Typ::Int)
+                .map(move |var| (*step, var.clone()))
+        })
+        .collect();
+
+    for (step, var) in ints {
+        // Invariant: some trace has `|var| ≤ hi`, none has `|var| < lo`.
+        let mut hi = match current.trace.get_val(step, var.id()) {
+            Some(Cst::I(val)) => val.abs(),
+            _ => continue,
+        };
+        let mut lo = Int::zero();
+        while lo < hi {
+            let mid: Int = (&lo + &hi) / 2;
+            checker.solver().push(1)?;
+            checker.assert_expr(&abs_le(&var, mid.clone())?, step)?;
+            if checker.check_sat()? {
+                current = Cex::new();
+                current.populate(checker.solver())?;
+                hi = match current.trace.get_val(step, var.id()) {
+                    Some(Cst::I(val)) => val.abs(),
+                    _ => mid,
+                };
+            } else {
+                lo = mid + 1;
+            }
+            checker.solver().pop(1)?;
+        }
+        checker.assert_expr(&abs_le(&var, hi)?, step)?;
+    }
+    checker.solver().kill()?;
+
+    Ok(current)
+}
+
+/// Expression stating that the absolute value of `var` is at most `bound`.
+fn abs_le(var: &Var, bound: Int) -> Res<Expr> {
+    let var = PExpr::new_var(var.clone());
+    Expr::new_op(
+        Op::And,
+        vec![
+            Expr::new_op(Op::Le, vec![PExpr::Cst(Cst::I(-&bound)), var.clone()])?,
+            Expr::new_op(Op::Le, vec![var, PExpr::Cst(Cst::I(bound))])?,
+        ],
+    )
+}
@@ -0,0 +1,199 @@
+//! Counterexample narrowing, which values of a counterexample matter.
+//!
+//! The solver picks *some* value for every variable at every step of a counterexample, most of
+//! them are irrelevant to the falsification. [`narrow`] labels each value of a counterexample as
+//!
+//! - [forced](Label::Forced): all the traces of the same length falsifying the candidate have this
+//!   value, or
+//! - [arbitrary](Label::Arbitrary): some trace of the same length falsifying the candidate has a
+//!   different value, which is reported.
+//!
+//! Each value is checked independently by asking the solver for a falsifying trace where it is
+//! excluded, all the other values being free. So two arbitrary values might not be changeable at
+//! the same time.
+
+crate::prelude!();
+
+use super::{cexs::Cex, driver::cex_depth, InternalChecker};
+use expr::{Cst, Expr, Op, PExpr, Var};
+use trans::Sys;
+
+/// Label of a value of a counterexample, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Label {
+    /// All falsifying traces of the same length have this value.
+    Forced,
+    /// Some falsifying trace of the same length has a different value.
+    Arbitrary {
+        /// Value of the variable in such a trace, if the solver reported one.
+        alternative: Option<Cst>,
+    },
+}
+impl Label {
+    /// True if the label is [`Self::Forced`].
+    pub fn is_forced(&self) -> bool {
+        matches!(self, Self::Forced)
+    }
+    /// True if the label is [`Self::Arbitrary`].
+    pub fn is_arbitrary(&self) -> bool {
+        matches!(self, Self::Arbitrary { .. })
+    }
+}
+
+/// A labeled value of a counterexample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    /// Step of the value.
+    pub step: Unroll,
+    /// Variable.
+    pub var: Var,
+    /// Value in the counterexample.
+    pub value: Cst,
+    /// Label.
+    pub label: Label,
+}
+
+/// Labeled values of a counterexample, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Narrowing {
+    /// Falsified candidate.
+    pub candidate: String,
+    /// Labeled values, by step and then variable.
+    pub values: Vec<Value>,
+}
+impl Narrowing {
+    /// Forced values.
+    pub fn forced(&self) -> impl Iterator<Item = &Value> + '_ {
+        self.values.iter().filter(|v| v.label.is_forced())
+    }
+    /// Arbitrary values.
+    pub fn arbitrary(&self) -> impl Iterator<Item = &Value> + '_ {
+        self.values.iter().filter(|v| v.label.is_arbitrary())
+    }
+    /// Label of the value of a variable at some step, if any.
+    pub fn label_of(&self, step: Unroll, var: impl AsRef<str>) -> Option<&Label> {
+        self.values
+            .iter()
+            .find(|v| v.step == step && v.var.id() == var.as_ref())
+            .map(|v| &v.label)
+    }
+}
+impl fmt::Display for Narrowing {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut step = None;
+        for value in self.values.iter() {
+            if step != Some(value.step) {
+                writeln!(fmt, "step {}", value.step)?;
+                step = Some(value.step)
+            }
+            match &value.label {
+                Label::Forced => writeln!(fmt, "       forced  {} = {}", value.var, value.value)?,
+                Label::Arbitrary {
+                    alternative: Some(alt),
+                } => writeln!(
+                    fmt,
+                    "    arbitrary  {} = {} (could be {})",
+                    value.var, value.value, alt
+                )?,
+                Label::Arbitrary { alternative: None } => {
+                    writeln!(fmt, "    arbitrary  {} = {}", value.var, value.value)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Labels the values of a counterexample for a candidate.
+///
+/// The counterexample must falsify `candidate` at its last step, like the counterexamples of
+/// [`Verdict::Falsified`](super::Verdict::Falsified).
+///
+/// # Errors
+///
+/// - when `candidate` is not a candidate of `sys`,
+/// - when no trace of the length of `cex` falsifies `candidate`, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int, inc: bool, noise: int }
+/// init { cnt = 0 }
+/// trans { 'cnt = (if inc { cnt + 1 } else { cnt }) }
+/// candidates { "cnt is not 2": ¬(cnt = 2) }
+/// "#).unwrap();
+/// let conf = SmtConf::z3("z3");
+/// let outcome = check::run(&sys, &check::Config::new(conf.clone()).bmc_max(5)).unwrap();
+/// let (_, _, cex) = outcome.falsified().next().unwrap();
+/// let narrowing = check::narrow::narrow(&sys, "cnt is not 2", cex, &conf).unwrap();
+/// // Counting is forced, noise is not.
+/// assert!(narrowing.label_of(0, "inc").unwrap().is_forced());
+/// assert!(narrowing.label_of(0, "noise").unwrap().is_arbitrary());
+/// println!("{}", narrowing);
+/// ```
+pub fn narrow(sys: &Sys, candidate: &str, cex: &Cex, conf: &SmtConf) -> Res<Narrowing> {
+    let po = match sys.po_s().get(candidate) {
+        Some(po) => po,
+        None => bail!("unknown candidate `{}`", candidate),
+    };
+    let depth = cex_depth(cex);
+
+    let mut checker = InternalChecker::new(sys, conf.clone(), None)?;
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for step in 0..depth {
+        checker.declare_vars(step + 1)?;
+        checker.assert_trans(step)?;
+    }
+    checker
+        .solver()
+        .assert_with(po.negated(), depth)
+        .chain_err(|| format!("while asserting the negation of `{}`", candidate))?;
+    if !checker.check_sat()? {
+        bail!(
+            "no trace of length {} falsifies candidate `{}`",
+            depth + 1,
+            candidate
+        )
+    }
+
+    let mut values = vec![];
+    for (step, vals) in cex.trace.range(0..=depth) {
+        for (var, value) in vals {
+            let excluded = Expr::new_op(
+                Op::Not,
+                vec![Expr::new_op(
+                    Op::Eq,
+                    vec![PExpr::new_var(var.clone()), PExpr::Cst(value.clone())],
+                )?],
+            )?;
+            checker.solver().push(1)?;
+            checker.assert_expr(&excluded, *step)?;
+            let label = if checker.check_sat()? {
+                let mut alt = Cex::new();
+                alt.populate(checker.solver())?;
+                Label::Arbitrary {
+                    alternative: alt.trace.get_val(*step, var.id()).cloned(),
+                }
+            } else {
+                Label::Forced
+            };
+            checker.solver().pop(1)?;
+            values.push(Value {
+                step: *step,
+                var: var.clone(),
+                value: value.clone(),
+                label,
+            })
+        }
+    }
+    checker.solver().kill()?;
+
+    Ok(Narrowing {
+        candidate: candidate.into(),
+        values,
+    })
+}
@@ -0,0 +1,509 @@
+//! IC3/PDR, proves candidates that are not inductive without manual strengthening.
+//!
+//! *Property Directed Reachability* maintains a sequence of *frames* `F_0, F_1, ..., F_k` where
+//! `F_0` is the initial states and `F_i` over-approximates the states reachable in at most `i`
+//! steps. Frames other than `F_0` are conjunctions of *lemmas*, each lemma being the negation of a
+//! *cube*: a conjunction of literals over the state variables. Checking a candidate `P` alternates
+//! between
+//!
+//! - *blocking*: each state of `F_k` falsifying `P` is blocked by blocking its predecessors in
+//!   `F_{k-1}`, recursively. A predecessor in the initial states yields a counterexample. A state
+//!   with no predecessor is generalized to a cube with no predecessor either, the negation of which
+//!   becomes a lemma of its frame;
+//! - *propagation*: a new frame is added, and lemmas of `F_i` that also hold in `F_{i+1}` are pushed
+//!   forward. When two consecutive frames are equal, they are an inductive invariant implying `P`.
+//!
+//! The literals of a cube are `b` or `¬b` for bool variables, `x ≥ v` and `x ≤ v` for int and rat
+//! variables, so that generalization can drop one of the bounds, and `x = v` otherwise.
+//!
+//! PDR is available as the [`Pdr`] engine, named `"pdr"` in [configurations](crate::config), and
+//! as the [`pdr`] function. It does not support uninterpreted functions.
+
+crate::prelude!();
+
+use std::{cmp::Reverse, collections::BinaryHeap, slice, time::Instant};
+
+use super::{
+    cexs::Cex,
+    driver::{Config, Outcome, Verdict},
+    engine::{setup, Engine, Events, Setup},
+    suggest::State,
+    InternalChecker,
+};
+use expr::{Cst, Expr, Op, PExpr};
+use trans::Sys;
+
+/// Default maximal number of frames, see [`Pdr::max_frames`].
+pub const DEFAULT_MAX_FRAMES: usize = 64;
+
+/// PDR engine, see the [module-level documentation](self).
+pub struct Pdr {
+    /// Setup, `None` until prepared.
+    setup: Option<Setup>,
+    /// Maximal number of frames, candidates still undecided after that are unknown.
+    max_frames: usize,
+}
+impl Default for Pdr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Pdr {
+    /// Name of the engine.
+    pub const NAME: &'static str = "pdr";
+    /// Constructor.
+    pub fn new() -> Self {
+        Self {
+            setup: None,
+            max_frames: DEFAULT_MAX_FRAMES,
+        }
+    }
+    /// Sets the maximal number of frames, [`DEFAULT_MAX_FRAMES`] by default.
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+}
+impl Engine for Pdr {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+    fn prepare(&mut self, sys: &Sys, config: &Config) -> Res<()> {
+        self.setup = Some(Setup::new(sys, config)?);
+        Ok(())
+    }
+    fn run(
+        &mut self,
+        candidates: &Set<String>,
+        budget: Option<time::Duration>,
+        events: &Events,
+    ) -> Res<Outcome> {
+        let setup = setup(&self.setup, Self::NAME)?;
+        let Setup {
+            sys,
+            conf,
+            tee,
+            cancel,
+            ..
+        } = setup;
+        if !sys.decls().funs().is_empty() {
+            bail!(
+                "engine `{}` does not support uninterpreted functions",
+                Self::NAME
+            )
+        }
+        let deadline = budget.map(|budget| Instant::now() + budget);
+        let mut outcome = Outcome::new();
+
+        for (idx, (name, po)) in sys
+            .po_s()
+            .iter()
+            .filter(|(name, _)| candidates.contains(*name))
+            .enumerate()
+        {
+            let tee_file = |file: String| {
+                tee.clone().map(|mut path| {
+                    path.push(file);
+                    path
+                })
+            };
+            let mut frames =
+                Frames::new(sys, conf.clone(), tee_file(format!("pdr_{}.smt2", idx)), po)?;
+            frames.checker.solver().watch(cancel);
+            let verdict = frames.check(self.max_frames, &|| {
+                cancel.is_cancelled()
+                    || deadline
+                        .map(|deadline| Instant::now() >= deadline)
+                        .unwrap_or(false)
+            });
+            frames.checker.solver().kill()?;
+            let verdict = match verdict {
+                Ok(verdict) => verdict,
+                Err(_) if cancel.is_cancelled() => Check::Stopped("cancelled"),
+                Err(e) if setup.is_timeout(&e) => {
+                    events.progress(Self::NAME, format!("`{}`: query timed out", name));
+                    let _ = outcome
+                        .verdicts
+                        .insert(name.clone(), Verdict::Timeout { bmc_depth: None });
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e.chain_err(|| format!("while running PDR on candidate `{}`", name)))
+                }
+            };
+
+            let verdict = match verdict {
+                Check::Frames(k) => {
+                    events.progress(
+                        Self::NAME,
+                        format!("`{}`: proved with {} frame(s)", name, k),
+                    );
+                    Verdict::Proved
+                }
+                Check::Chain(chain) => {
+                    let tee = tee_file(format!("pdr_{}_cex.smt2", idx));
+                    let depth = chain.len() - 1;
+                    let cex = concretize(sys, conf.clone(), tee, po, &chain)
+                        .chain_err(|| format!("while building a counterexample for `{}`", name))?;
+                    Verdict::Falsified { depth, cex }
+                }
+                Check::Stopped(why) => {
+                    events.progress(Self::NAME, format!("`{}`: {}", name, why));
+                    Verdict::Unknown { bmc_depth: None }
+                }
+            };
+            let _ = outcome.verdicts.insert(name.clone(), verdict);
+        }
+        Ok(outcome)
+    }
+}
+
+/// Checks some candidates of a system with PDR, see the [module-level documentation](self).
+///
+/// Uses the solver, tee directory, budget, events and cancellation handle of `config`, and ignores
+/// its engines and pre-processing pipeline. Counterexamples are not minimal in general: PDR looks
+/// for *some* falsification, which might not be the shortest.
+///
+/// # Errors
+///
+/// - when some candidate is not a candidate of `sys`,
+/// - when `sys` declares uninterpreted functions, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// Unknown candidates and uninterpreted functions are reported before spawning any solver.
+///
+/// ```rust
+/// # use mikino_api::{check, parse, rsmt2::SmtConf};
+/// let config = check::Config::new(SmtConf::z3("z3"));
+/// let sys = parse::trans(r#"
+/// svars { x: int, y: int }
+/// init { x = 0 ∧ y = 1 }
+/// trans { 'x = x + y ∧ 'y = y }
+/// candidates { "x is positive": x ≥ 0 }
+/// "#).unwrap();
+/// let err = check::pdr(&sys, &["x is negative"], &config).unwrap_err();
+/// assert_eq!(err.to_string(), "unknown candidate `x is negative`");
+///
+/// let sys = parse::trans(r#"
+/// svars { x: int }
+/// funs { f: (int) -> int }
+/// init { x = 0 }
+/// trans { 'x = x + 1 }
+/// candidates { "f is constant": f(x) = f(0) }
+/// "#).unwrap();
+/// let err = check::pdr(&sys, &["f is constant"], &config).unwrap_err();
+/// assert_eq!(err.to_string(), "engine `pdr` does not support uninterpreted functions");
+/// ```
+///
+/// `x ≥ 0` is not inductive since `y` could be negative, PDR discovers the strengthening `y ≥ 0`.
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(r#"
+/// svars { x: int, y: int }
+/// init { x = 0 ∧ y = 1 }
+/// trans { 'x = x + y ∧ 'y = y }
+/// candidates { "x is positive": x ≥ 0, "x is small": x ≤ 3 }
+/// "#).unwrap();
+/// let config = check::Config::new(SmtConf::z3("z3"));
+/// let outcome = check::pdr(&sys, &["x is positive", "x is small"], &config).unwrap();
+/// assert!(outcome["x is positive"].is_proved());
+/// assert!(outcome["x is small"].is_falsified());
+/// ```
+pub fn pdr<S: AsRef<str>>(
+    sys: &Sys,
+    props: impl IntoIterator<Item = S>,
+    config: &Config,
+) -> Res<Outcome> {
+    let mut candidates = Set::new();
+    for prop in props {
+        let prop = prop.as_ref();
+        if !sys.po_s().contains_key(prop) {
+            bail!("unknown candidate `{}`", prop)
+        }
+        let _ = candidates.insert(prop.to_string());
+    }
+
+    let mut engine = Pdr::new();
+    engine.prepare(sys, config)?;
+    let mut outcome = engine.run(&candidates, config.budget, &config.events)?;
+    outcome.warnings = sys
+        .warnings()
+        .iter()
+        .filter(|name| candidates.contains(*name))
+        .cloned()
+        .collect();
+    outcome.labels = sys
+        .labels()
+        .iter()
+        .filter(|(name, _)| candidates.contains(*name))
+        .map(|(name, label)| (name.clone(), label.clone()))
+        .collect();
+    Ok(outcome)
+}
+
+/// A cube, conjunction of literals.
+type Cube = Vec<Expr>;
+
+/// Cube describing exactly a state.
+fn cube_of(state: &State) -> Res<Cube> {
+    let mut cube = Vec::with_capacity(state.len());
+    for (var, val) in state {
+        let typ = var.typ();
+        let var = PExpr::new_var(var.clone());
+        let cst = PExpr::Cst(val.clone());
+        match (typ, val) {
+            (_, Cst::B(true)) => cube.push(var),
+            (_, Cst::B(false)) => cube.push(Expr::new_op(Op::Not, vec![var])?),
+            (Typ::Int, _) | (Typ::Rat, _) => {
+                cube.push(Expr::new_op(Op::Ge, vec![var.clone(), cst.clone()])?);
+                cube.push(Expr::new_op(Op::Le, vec![var, cst])?);
+            }
+            _ => cube.push(Expr::new_op(Op::Eq, vec![var, cst])?),
+        }
+    }
+    Ok(cube)
+}
+
+/// Conjunction of the literals of a cube.
+fn conj(cube: &[Expr]) -> Res<Expr> {
+    match cube {
+        [] => Ok(PExpr::Cst(Cst::B(true))),
+        [lit] => Ok(lit.clone()),
+        _ => Expr::new_op(Op::And, cube.to_vec()),
+    }
+}
+
+/// Negation of a cube, a lemma.
+fn neg(cube: &[Expr]) -> Res<Expr> {
+    Expr::new_op(Op::Not, vec![conj(cube)?])
+}
+
+/// A proof obligation: a state to block at some frame.
+struct Obligation {
+    /// State to block.
+    state: State,
+    /// Index of the obligation of the successor of the state, `None` if the state is bad.
+    succ: Option<usize>,
+}
+
+/// Result of a PDR check for a single candidate.
+enum Check {
+    /// Proved, with the number of frames.
+    Frames(usize),
+    /// Falsified, the states of a counterexample starting from an initial state.
+    Chain(Vec<State>),
+    /// Stopped before deciding the candidate, with the reason.
+    Stopped(&'static str),
+}
+
+/// PDR frames for a single candidate.
+struct Frames<'sys> {
+    /// Solver, with the variables of steps `0` and `1` and the transition relation asserted.
+    checker: InternalChecker<'sys>,
+    /// Negation of the candidate.
+    bad: Expr,
+    /// Cubes blocked in each frame: `F_i` blocks the cubes of `lemmas[i..]`.
+    ///
+    /// `lemmas[0]` is always empty since `F_0` is the initial states.
+    lemmas: Vec<Vec<Cube>>,
+}
+impl<'sys> Frames<'sys> {
+    /// Constructor.
+    fn new(sys: &'sys Sys, conf: SmtConf, tee: Option<PathBuf>, po: &Expr) -> Res<Self> {
+        let mut checker = InternalChecker::new(sys, conf, tee)?;
+        checker.declare_vars(0)?;
+        checker.declare_vars(1)?;
+        checker.assert_trans(0)?;
+        Ok(Self {
+            checker,
+            bad: Expr::new_op(Op::Not, vec![po.clone()])?,
+            lemmas: vec![vec![], vec![]],
+        })
+    }
+
+    /// Frontier, index of the last frame.
+    fn frontier(&self) -> usize {
+        self.lemmas.len() - 1
+    }
+
+    /// Checks `F_level ∧ curr ∧ T ∧ next'`, yields the current state of a model if sat.
+    fn query(&mut self, level: usize, curr: &[Expr], next: &[Expr]) -> Res<Option<State>> {
+        self.checker.solver().push(1)?;
+        if level == 0 {
+            self.checker.assert_init()?;
+        } else {
+            for cubes in &self.lemmas[level..] {
+                for cube in cubes {
+                    self.checker.assert_expr(&neg(cube)?, 0)?;
+                }
+            }
+        }
+        for expr in curr {
+            self.checker.assert_expr(expr, 0)?;
+        }
+        for expr in next {
+            self.checker.assert_expr(expr, 1)?;
+        }
+        let res = if self.checker.check_sat()? {
+            let mut cex = Cex::new();
+            cex.populate(self.checker.solver())?;
+            Some(cex.trace.steps.remove(&0).unwrap_or_default())
+        } else {
+            None
+        };
+        self.checker.solver().pop(1)?;
+        Ok(res)
+    }
+
+    /// Decides the candidate, stops after `max_frames` frames or when `stop` is true.
+    fn check(&mut self, max_frames: usize, stop: &dyn Fn() -> bool) -> Res<Check> {
+        let bad = self.bad.clone();
+        if let Some(state) = self.query(0, slice::from_ref(&bad), &[])? {
+            return Ok(Check::Chain(vec![state]));
+        }
+
+        loop {
+            let k = self.frontier();
+            if k > max_frames {
+                return Ok(Check::Stopped("reached the maximum number of frames"));
+            }
+            while let Some(state) = self.query(k, slice::from_ref(&bad), &[])? {
+                if let Some(res) = self.block(state, stop)? {
+                    return Ok(res);
+                }
+            }
+
+            self.lemmas.push(vec![]);
+            for level in 1..=k {
+                let cubes = std::mem::take(&mut self.lemmas[level]);
+                for cube in cubes {
+                    if stop() {
+                        return Ok(Check::Stopped("stopped"));
+                    }
+                    let pushed = self.query(level, &[], &[conj(&cube)?])?.is_none();
+                    self.lemmas[if pushed { level + 1 } else { level }].push(cube)
+                }
+                if self.lemmas[level].is_empty() {
+                    return Ok(Check::Frames(level));
+                }
+            }
+        }
+    }
+
+    /// Blocks a bad state at the frontier, yields a counterexample or the reason for stopping.
+    fn block(&mut self, bad: State, stop: &dyn Fn() -> bool) -> Res<Option<Check>> {
+        let k = self.frontier();
+        let mut obligations = vec![Obligation {
+            state: bad,
+            succ: None,
+        }];
+        let mut todo = BinaryHeap::new();
+        todo.push(Reverse((k, 0)));
+
+        while let Some(Reverse((level, idx))) = todo.pop() {
+            if stop() {
+                return Ok(Some(Check::Stopped("stopped")));
+            }
+            let cube = cube_of(&obligations[idx].state)?;
+            let lits = conj(&cube)?;
+            if self.query(level, slice::from_ref(&lits), &[])?.is_none() {
+                // Already blocked.
+                continue;
+            }
+            if self.query(0, slice::from_ref(&lits), &[])?.is_some() {
+                return Ok(Some(Check::Chain(chain(&obligations, idx))));
+            }
+
+            if let Some(pred) = self.query(level - 1, &[neg(&cube)?], &[lits])? {
+                obligations.push(Obligation {
+                    state: pred,
+                    succ: Some(idx),
+                });
+                if level == 1 {
+                    // The predecessor is initial.
+                    return Ok(Some(Check::Chain(chain(
+                        &obligations,
+                        obligations.len() - 1,
+                    ))));
+                }
+                todo.push(Reverse((level - 1, obligations.len() - 1)));
+                todo.push(Reverse((level, idx)));
+            } else {
+                let cube = self.generalize(level, cube)?;
+                let mut at = level;
+                while at < k && self.query(at, &[neg(&cube)?], &[conj(&cube)?])?.is_none() {
+                    at += 1
+                }
+                self.lemmas[at].push(cube);
+                if at < k {
+                    todo.push(Reverse((at + 1, idx)))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Drops literals of a cube blocked at some level, while the cube stays blocked.
+    fn generalize(&mut self, level: usize, mut cube: Cube) -> Res<Cube> {
+        let mut idx = 0;
+        while idx < cube.len() && cube.len() > 1 {
+            let mut smaller = cube.clone();
+            let _ = smaller.remove(idx);
+            let lits = conj(&smaller)?;
+            let blocked = self.query(0, slice::from_ref(&lits), &[])?.is_none()
+                && self.query(level - 1, &[neg(&smaller)?], &[lits])?.is_none();
+            if blocked {
+                cube = smaller
+            } else {
+                idx += 1
+            }
+        }
+        Ok(cube)
+    }
+}
+
+/// States of the counterexample starting at some obligation.
+fn chain(obligations: &[Obligation], mut idx: usize) -> Vec<State> {
+    let mut chain = vec![obligations[idx].state.clone()];
+    while let Some(succ) = obligations[idx].succ {
+        chain.push(obligations[succ].state.clone());
+        idx = succ
+    }
+    chain
+}
+
+/// Turns the states of a counterexample into an actual counterexample.
+///
+/// Unrolls the system along the states, and retrieves a model falsifying `po` at the last state.
+fn concretize(
+    sys: &Sys,
+    conf: SmtConf,
+    tee: Option<PathBuf>,
+    po: &Expr,
+    chain: &[State],
+) -> Res<Cex> {
+    let mut checker = InternalChecker::new(sys, conf, tee)?;
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for (step, state) in chain.iter().enumerate() {
+        if step > 0 {
+            checker.declare_vars(step)?;
+            checker.assert_trans(step - 1)?;
+        }
+        checker.assert_expr(&conj(&cube_of(state)?)?, step)?;
+    }
+    checker
+        .solver()
+        .assert_with(po.negated(), chain.len() - 1)
+        .chain_err(|| "while asserting the negation of the candidate")?;
+    if !checker.check_sat()? {
+        bail!("[internal] PDR counterexample is not a falsification")
+    }
+    let mut cex = Cex::new();
+    cex.populate(checker.solver())?;
+    checker.solver().kill()?;
+    Ok(cex)
+}
@@ -0,0 +1,692 @@
+//! Pre-processing of systems before they are sent to the solver.
+//!
+//! A [`Pipeline`] is a sequence of [`Pass`]es, each pass transforms a system into a system that is
+//! simpler to check and has the same candidates. The [standard pipeline](Pipeline::standard)
+//! performs
+//!
+//! - definition inlining, [`Inline`];
+//! - constant propagation, [`ConstProp`];
+//! - simplification, [`Simplify`];
+//! - flattening of associative operators, [`Flatten`];
+//! - cone of influence reduction, [`Coi`].
+//!
+//! Users can insert their own transformations by implementing [`Pass`].
+//!
+//! Passes never remove variable declarations, so that counterexamples still mention all the
+//! variables of the original system. They also never touch the conjuncts that define a variable
+//! they propagate or inline, so that the values of these variables in counterexamples are still
+//! meaningful.
+
+crate::prelude!();
+
+use std::sync::Arc;
+
+use expr::{Cst, Op, PExpr, SVar, Var};
+use trans::Sys;
+
+/// A transformation over systems.
+///
+/// The candidates of the output system must have the same names as the ones of the input system.
+/// A candidate must be an invariant of the output system if and only if it is an invariant of the
+/// input system.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::{Pass, Pipeline}, parse, trans::Sys};
+/// /// Drops the transition relation, very much **not** a legal pass.
+/// struct Havoc;
+/// impl Pass for Havoc {
+///     fn name(&self) -> &str {
+///         "havoc"
+///     }
+///     fn run(&self, sys: &Sys) -> mikino_api::prelude::Res<Sys> {
+///         let po_s = sys.po_s().clone();
+///         Ok(Sys::new(sys.decls().clone(), sys.init().clone(), true.into(), po_s))
+///     }
+/// }
+///
+/// let mut pipeline = Pipeline::standard();
+/// pipeline.push(Havoc);
+/// assert_eq!(
+///     pipeline.names().collect::<Vec<_>>(),
+///     vec!["inline", "const-prop", "simplify", "flatten", "coi", "havoc"],
+/// );
+///
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(sys.trans().to_string(), "true");
+/// ```
+pub trait Pass: Send + Sync {
+    /// Name of the pass, used in error messages.
+    fn name(&self) -> &str;
+    /// Applies the pass.
+    fn run(&self, sys: &Sys) -> Res<Sys>;
+}
+
+/// A sequence of passes.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    /// Passes, in application order.
+    passes: Vec<Arc<dyn Pass>>,
+}
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.names()).finish()
+    }
+}
+impl Pipeline {
+    /// Empty pipeline, leaves systems untouched.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Standard pipeline.
+    pub fn standard() -> Self {
+        let mut slf = Self::new();
+        slf.push(Inline)
+            .push(ConstProp)
+            .push(Simplify)
+            .push(Flatten)
+            .push(Coi);
+        slf
+    }
+
+    /// True if the pipeline has no passes.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+    /// Number of passes.
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+    /// Names of the passes, in application order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    /// Appends a pass.
+    pub fn push(&mut self, pass: impl Pass + 'static) -> &mut Self {
+        self.passes.push(Arc::new(pass));
+        self
+    }
+    /// Inserts a pass at some position, panics if `idx > self.len()`.
+    pub fn insert(&mut self, idx: usize, pass: impl Pass + 'static) -> &mut Self {
+        self.passes.insert(idx, Arc::new(pass));
+        self
+    }
+
+    /// Applies all the passes, in order.
+    ///
+    /// Fails if a pass fails or changes the names of the candidates.
+    pub fn run(&self, original: &Sys) -> Res<Sys> {
+        let mut sys = original.restrict(|_| true);
+        for pass in self.passes.iter() {
+            let next = pass
+                .run(&sys)
+                .chain_err(|| format!("during pre-processing pass `{}`", pass.name()))?;
+            if !next.po_s().keys().eq(sys.po_s().keys()) {
+                bail!(
+                    "pre-processing pass `{}` changed the candidates of the system",
+                    pass.name()
+                )
+            }
+            sys = next;
+        }
+        Ok(sys.with_annotations_of(original))
+    }
+}
+
+/// Variables that can appear in the expressions of a system.
+pub(crate) trait SysVar: Clone + HasTyp + PartialEq {
+    /// Identifier of the underlying variable.
+    fn ident(&self) -> &str;
+}
+impl SysVar for Var {
+    fn ident(&self) -> &str {
+        self.id()
+    }
+}
+impl SysVar for SVar {
+    fn ident(&self) -> &str {
+        self.id()
+    }
+}
+
+/// Rebuilds an expression bottom-up.
+///
+/// Function `var` rewrites variables, and `app` rebuilds applications from their rebuilt
+/// arguments.
+fn rebuild<V: SysVar>(
+    expr: &PExpr<V>,
+    mut var: impl FnMut(&V) -> PExpr<V>,
+    mut app: impl FnMut(Op, Vec<PExpr<V>>) -> Res<PExpr<V>>,
+) -> Res<PExpr<V>> {
+    expr.fold(
+        |v| Ok(var(v)),
+        |cst| Ok(PExpr::new_cst(cst.clone())),
+        |op, args: Vec<Res<PExpr<V>>>| app(op, args.into_iter().collect::<Res<_>>()?),
+    )
+}
+
+/// Rewrites all the expressions of a system.
+fn rebuild_sys(
+    sys: &Sys,
+    mut init: impl FnMut(&expr::Expr) -> Res<expr::Expr>,
+    mut trans: impl FnMut(&expr::SExpr) -> Res<expr::SExpr>,
+    mut po: impl FnMut(&expr::Expr) -> Res<expr::Expr>,
+) -> Res<Sys> {
+    let mut po_s = Map::new();
+    for (name, expr) in sys.po_s().iter() {
+        let _ = po_s.insert(name.clone(), po(expr)?);
+    }
+    Ok(Sys::new(
+        sys.decls().clone(),
+        init(sys.init())?,
+        trans(sys.trans())?,
+        po_s,
+    ))
+}
+
+/// Top-level conjuncts of an expression.
+pub(crate) fn conjuncts<V: Clone>(expr: &PExpr<V>) -> Vec<PExpr<V>> {
+    match expr {
+        PExpr::App {
+            op: Op::And, args, ..
+        } => args.clone(),
+        expr => vec![expr.clone()],
+    }
+}
+
+/// Conjunction of some expressions.
+pub(crate) fn conjunction<V: SysVar>(mut conjs: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+    match conjs.len() {
+        0 => Ok(Cst::bool(true).into()),
+        1 => Ok(conjs.pop().expect("[unreachable] pop on vec of len `1`")),
+        _ => PExpr::new_op(Op::And, conjs),
+    }
+}
+
+/// Identifiers of the variables appearing in an expression.
+pub(crate) fn idents<V: SysVar>(expr: &PExpr<V>) -> Set<String> {
+    let mut set = Set::new();
+    expr.fold(
+        |var| {
+            let _ = set.insert(var.ident().to_string());
+        },
+        |_| (),
+        |_, _| (),
+    );
+    set
+}
+
+/// Substitutes some variables in an expression, `map` yields the replacement of a variable if any.
+fn subst<V: SysVar>(expr: &PExpr<V>, map: impl Fn(&V) -> Option<PExpr<V>>) -> Res<PExpr<V>> {
+    rebuild(
+        expr,
+        |var| map(var).unwrap_or_else(|| PExpr::new_var(var.clone())),
+        PExpr::new_op,
+    )
+}
+
+/// If `expr` is `v = e` or `e = v` with `v` verifying `pred`, returns `v` and `e`.
+pub(crate) fn as_def<V: SysVar>(
+    expr: &PExpr<V>,
+    pred: impl Fn(&V) -> bool,
+) -> Option<(&V, &PExpr<V>)> {
+    match expr {
+        PExpr::App {
+            op: Op::Eq, args, ..
+        } if args.len() == 2 => match (&args[0], &args[1]) {
+            (PExpr::Var(var), def) | (def, PExpr::Var(var)) if pred(var) => Some((var, def)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Algebraic simplifications.
+///
+/// Removes neutral elements of conjunctions and disjunctions, collapses them on absorbing
+/// elements, removes double negations and trivial if-then-else-s and equalities.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::{Pipeline, Simplify}, parse};
+/// let sys = parse::trans(
+///     "svars { x: int, b: bool } \
+///     init { x = 0 ∧ ⊤ } \
+///     trans { 'x = if b { x } else { x }, ¬¬'b } \
+///     candidates { \"x = x\": x = x ∨ b, \"b\": b ∧ b }",
+/// )
+/// .unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(Simplify);
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(sys.init().to_string(), "(= x 0)");
+/// assert_eq!(sys.trans().to_string(), "(and (= x@1 x@0) b@1)");
+/// assert_eq!(sys.po_s()["x = x"].to_string(), "true");
+/// assert_eq!(sys.po_s()["b"].to_string(), "b");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Simplify;
+impl Simplify {
+    /// Simplifies an application.
+    fn app<V: SysVar>(op: Op, mut args: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+        match op {
+            Op::And | Op::Or => {
+                // `false` for `and`, `true` for `or`.
+                let absorbing = op == Op::Or;
+                let mut kept: Vec<PExpr<V>> = Vec::with_capacity(args.len());
+                for arg in args {
+                    match arg {
+                        PExpr::Cst(Cst::B(b)) if b == absorbing => return Ok(Cst::bool(b).into()),
+                        PExpr::Cst(Cst::B(_)) => (),
+                        arg if kept.contains(&arg) => (),
+                        arg => kept.push(arg),
+                    }
+                }
+                match kept.len() {
+                    0 => Ok(Cst::bool(!absorbing).into()),
+                    1 => Ok(kept.pop().expect("[unreachable] pop on vec of len `1`")),
+                    _ => PExpr::new_op(op, kept),
+                }
+            }
+            Op::Not if args.len() == 1 => match args.pop().expect("[unreachable] len is `1`") {
+                PExpr::Cst(Cst::B(b)) => Ok(Cst::bool(!b).into()),
+                PExpr::App {
+                    op: Op::Not,
+                    mut args,
+                    ..
+                } if args.len() == 1 => Ok(args.pop().expect("[unreachable] len is `1`")),
+                arg => PExpr::new_op(op, vec![arg]),
+            },
+            Op::Ite if args.len() == 3 => match &args[0] {
+                PExpr::Cst(Cst::B(cnd)) => Ok(args.swap_remove(if *cnd { 1 } else { 2 })),
+                _ if args[1] == args[2] => Ok(args.swap_remove(1)),
+                _ => PExpr::new_op(op, args),
+            },
+            Op::Eq if args.len() > 1 && args.iter().all(|arg| *arg == args[0]) => {
+                Ok(Cst::bool(true).into())
+            }
+            _ => PExpr::new_op(op, args),
+        }
+    }
+}
+impl Pass for Simplify {
+    fn name(&self) -> &str {
+        "simplify"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        let var = |v: &Var| PExpr::new_var(v.clone());
+        let svar = |v: &SVar| PExpr::new_var(v.clone());
+        rebuild_sys(
+            sys,
+            |init| rebuild(init, var, Self::app),
+            |trans| rebuild(trans, svar, Self::app),
+            |po| rebuild(po, var, Self::app),
+        )
+    }
+}
+
+/// Constant propagation.
+///
+/// Evaluates applications to constants, and replaces variables that are constant over all
+/// reachable states by their value. A variable `v` is constant if `init` has a top-level conjunct
+/// `v = c` and `trans` has a top-level conjunct `'v = v` or `'v = c`, with `c` a constant.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::{ConstProp, Pipeline}, parse};
+/// let sys = parse::trans(
+///     "svars { x k: int } \
+///     init { x = 0, k = 2 + 1 } \
+///     trans { 'x = x + k, 'k = k } \
+///     candidates { \"k\": k > 0 }",
+/// )
+/// .unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(ConstProp);
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(sys.init().to_string(), "(and (= x 0) (= k 3))");
+/// assert_eq!(sys.trans().to_string(), "(and (= x@1 (+ x@0 3)) (= k@1 k@0))");
+/// assert_eq!(sys.po_s()["k"].to_string(), "true");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstProp;
+impl ConstProp {
+    /// Evaluates an application if all its arguments are constants.
+    fn app<V: SysVar>(op: Op, args: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+        let csts: Option<Vec<Cst>> = args
+            .iter()
+            .map(|arg| match arg {
+                PExpr::Cst(cst) => Some(cst.clone()),
+                _ => None,
+            })
+            .collect();
+        // Division by zero is left to the solver, evaluation panics on it.
+        let div_by_zero = |csts: &[Cst]| {
+            matches!(op, Op::Div | Op::IDiv | Op::Mod)
+                && csts[1..].iter().any(|cst| match cst {
+                    Cst::I(i) => i.is_zero(),
+                    Cst::R(r) => r.is_zero(),
+                    Cst::B(_) | Cst::BV(_, _) | Cst::Array { .. } | Cst::Enum { .. } => false,
+                })
+        };
+        match csts
+            .filter(|csts| !div_by_zero(csts))
+            .map(|csts| op.eval(csts))
+        {
+            Some(Ok(cst)) => Ok(cst.into()),
+            Some(Err(_)) | None => PExpr::new_op(op, args),
+        }
+    }
+
+    /// Evaluates constant sub-expressions.
+    fn eval<V: SysVar>(expr: &PExpr<V>) -> Res<PExpr<V>> {
+        rebuild(expr, |var| PExpr::new_var(var.clone()), Self::app)
+    }
+}
+impl Pass for ConstProp {
+    fn name(&self) -> &str {
+        "const-prop"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        let init = conjuncts(&Self::eval(sys.init())?);
+        let trans = conjuncts(&Self::eval(sys.trans())?);
+
+        let mut init_vals: Map<String, (usize, Cst)> = Map::new();
+        for (idx, conj) in init.iter().enumerate() {
+            if let Some((var, PExpr::Cst(cst))) = as_def(conj, |_| true) {
+                let _ = init_vals
+                    .entry(var.id().to_string())
+                    .or_insert_with(|| (idx, cst.clone()));
+            }
+        }
+
+        let (mut consts, mut init_defs, mut trans_defs) = (Map::new(), Set::new(), Set::new());
+        for (idx, conj) in trans.iter().enumerate() {
+            let (var, def) = match as_def(conj, |var: &SVar| var.is_next()) {
+                Some(def) => def,
+                None => continue,
+            };
+            let (init_idx, cst) = match init_vals.get(var.id()) {
+                Some(val) => val,
+                None => continue,
+            };
+            let is_const = match def {
+                PExpr::Cst(def) => def == cst,
+                PExpr::Var(def) => def.id() == var.id() && !def.is_next(),
+                PExpr::App { .. } => false,
+            };
+            if is_const && !consts.contains_key(var.id()) {
+                let _ = consts.insert(var.id().to_string(), cst.clone());
+                let _ = init_defs.insert(*init_idx);
+                let _ = trans_defs.insert(idx);
+            }
+        }
+
+        let get = |var: &Var| consts.get(var.id()).map(|cst| cst.clone().into());
+        let sget = |var: &SVar| consts.get(var.id()).map(|cst| cst.clone().into());
+        let mut init_res = Vec::with_capacity(init.len());
+        for (idx, conj) in init.iter().enumerate() {
+            if init_defs.contains(&idx) {
+                init_res.push(conj.clone())
+            } else {
+                init_res.push(Self::eval(&subst(conj, get)?)?)
+            }
+        }
+        let mut trans_res = Vec::with_capacity(trans.len());
+        for (idx, conj) in trans.iter().enumerate() {
+            if trans_defs.contains(&idx) {
+                trans_res.push(conj.clone())
+            } else {
+                trans_res.push(Self::eval(&subst(conj, sget)?)?)
+            }
+        }
+
+        rebuild_sys(
+            sys,
+            |_| conjunction(mem::take(&mut init_res)),
+            |_| conjunction(mem::take(&mut trans_res)),
+            |po| Self::eval(&subst(po, get)?),
+        )
+    }
+}
+
+/// Flattening of nested applications of associative operators.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::{Flatten, Pipeline}, parse};
+/// let sys = parse::trans(
+///     "svars { x: int, a b c: bool } \
+///     init { a ∧ (b ∧ (c ∨ (a ∨ b))) } \
+///     trans { 'x = x + (1 + (x + 2)) } \
+///     candidates { \"x\": x ≥ 0 }",
+/// )
+/// .unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(Flatten);
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(sys.init().to_string(), "(and a b (or c a b))");
+/// assert_eq!(sys.trans().to_string(), "(= x@1 (+ x@0 1 x@0 2))");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flatten;
+impl Flatten {
+    /// Flattens an application.
+    fn app<V: SysVar>(op: Op, args: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+        match op {
+            Op::And | Op::Or | Op::Add | Op::Mul | Op::BvAnd | Op::BvOr | Op::BvXor => {
+                let mut flat = Vec::with_capacity(args.len());
+                for arg in args {
+                    match arg {
+                        PExpr::App {
+                            op: sub_op, args, ..
+                        } if sub_op == op => flat.extend(args),
+                        arg => flat.push(arg),
+                    }
+                }
+                PExpr::new_op(op, flat)
+            }
+            _ => PExpr::new_op(op, args),
+        }
+    }
+}
+impl Pass for Flatten {
+    fn name(&self) -> &str {
+        "flatten"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        let var = |v: &Var| PExpr::new_var(v.clone());
+        let svar = |v: &SVar| PExpr::new_var(v.clone());
+        rebuild_sys(
+            sys,
+            |init| rebuild(init, var, Self::app),
+            |trans| rebuild(trans, svar, Self::app),
+            |po| rebuild(po, var, Self::app),
+        )
+    }
+}
+
+/// Definition inlining.
+///
+/// A top-level conjunct `v = e` of `init` (resp. `'v = e` of `trans`) is a *definition* of `v` if
+/// `e` does not mention any defined variable. Definitions are inlined in the other top-level
+/// conjuncts of `init` (resp. `trans`).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::{Inline, Pipeline}, parse};
+/// let sys = parse::trans(
+///     "svars { x y: int } \
+///     init { y = 7, x ≥ y } \
+///     trans { 'y = y + 1, 'x > 'y } \
+///     candidates { \"x\": x > 0 }",
+/// )
+/// .unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(Inline);
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(sys.init().to_string(), "(and (= y 7) (>= x 7))");
+/// assert_eq!(sys.trans().to_string(), "(and (= y@1 (+ y@0 1)) (> x@1 (+ y@0 1)))");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inline;
+impl Inline {
+    /// Inlines definitions of variables verifying `definable` in some conjuncts.
+    fn conjuncts<V: SysVar>(
+        conjs: Vec<PExpr<V>>,
+        definable: impl Fn(&V) -> bool,
+    ) -> Res<Vec<PExpr<V>>> {
+        let mut defs: Map<String, (usize, PExpr<V>)> = Map::new();
+        for (idx, conj) in conjs.iter().enumerate() {
+            if let Some((var, def)) = as_def(conj, &definable) {
+                let _ = defs
+                    .entry(var.ident().to_string())
+                    .or_insert_with(|| (idx, def.clone()));
+            }
+        }
+        // Only keep definitions that do not mention defined variables, avoids cycles.
+        let mentions_defined = |def: &PExpr<V>| {
+            let mut defined = false;
+            def.fold(
+                |var| defined = defined || (definable(var) && defs.contains_key(var.ident())),
+                |_| (),
+                |_, _| (),
+            );
+            defined
+        };
+        let defs: Map<String, (usize, PExpr<V>)> = defs
+            .iter()
+            .filter(|(_, (_, def))| !mentions_defined(def))
+            .map(|(var, def)| (var.clone(), def.clone()))
+            .collect();
+        if defs.is_empty() {
+            return Ok(conjs);
+        }
+
+        let def_idxs: Set<usize> = defs.values().map(|(idx, _)| *idx).collect();
+        let map = |var: &V| {
+            if definable(var) {
+                defs.get(var.ident()).map(|(_, def)| def.clone())
+            } else {
+                None
+            }
+        };
+        let mut res = Vec::with_capacity(conjs.len());
+        for (idx, conj) in conjs.into_iter().enumerate() {
+            if def_idxs.contains(&idx) {
+                res.push(conj)
+            } else {
+                res.push(subst(&conj, map)?)
+            }
+        }
+        Ok(res)
+    }
+}
+impl Pass for Inline {
+    fn name(&self) -> &str {
+        "inline"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        rebuild_sys(
+            sys,
+            |init| conjunction(Self::conjuncts(conjuncts(init), |_| true)?),
+            |trans| conjunction(Self::conjuncts(conjuncts(trans), |var| var.is_next())?),
+            |po| Ok(po.clone()),
+        )
+    }
+}
+
+/// Cone Of Influence (COI) reduction.
+///
+/// Removes the top-level conjuncts of `init` and `trans` that do not (transitively) constrain the
+/// variables of the candidates. Conjuncts mentioning no variable are always kept.
+///
+/// This pass assumes that the constraints over the variables outside of the cone are satisfiable
+/// and do not block transitions. Otherwise, removing them adds behaviors to the system: proofs
+/// remain valid, but counterexamples might be spurious.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::{Coi, Pipeline}, parse};
+/// let sys = parse::trans(
+///     "svars { x y z: int } \
+///     init { x = 0, y = 0, z = 0 } \
+///     trans { 'x = x + y, 'y = y, 'z = z + 1 } \
+///     candidates { \"x\": x ≥ 0 }",
+/// )
+/// .unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(Coi);
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(sys.init().to_string(), "(and (= x 0) (= y 0))");
+/// assert_eq!(sys.trans().to_string(), "(and (= x@1 (+ x@0 y@0)) (= y@1 y@0))");
+/// // Variable declarations are left untouched.
+/// assert!(sys.decls().contains("z"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coi;
+impl Pass for Coi {
+    fn name(&self) -> &str {
+        "coi"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        let init: Vec<_> = conjuncts(sys.init())
+            .into_iter()
+            .map(|conj| {
+                let vars = idents(&conj);
+                (conj, vars)
+            })
+            .collect();
+        let trans: Vec<_> = conjuncts(sys.trans())
+            .into_iter()
+            .map(|conj| {
+                let vars = idents(&conj);
+                (conj, vars)
+            })
+            .collect();
+
+        let mut cone: Set<String> = sys.po_s().values().flat_map(idents).collect();
+        loop {
+            let size = cone.len();
+            for vars in init
+                .iter()
+                .map(|(_, vars)| vars)
+                .chain(trans.iter().map(|(_, vars)| vars))
+            {
+                if !vars.is_disjoint(&cone) {
+                    cone.extend(vars.iter().cloned())
+                }
+            }
+            if cone.len() == size {
+                break;
+            }
+        }
+
+        let in_cone = |vars: &Set<String>| vars.is_empty() || !vars.is_disjoint(&cone);
+        let init = init
+            .into_iter()
+            .filter(|(_, vars)| in_cone(vars))
+            .map(|(conj, _)| conj)
+            .collect();
+        let trans = trans
+            .into_iter()
+            .filter(|(_, vars)| in_cone(vars))
+            .map(|(conj, _)| conj)
+            .collect();
+        Ok(Sys::new(
+            sys.decls().clone(),
+            conjunction(init)?,
+            conjunction(trans)?,
+            sys.po_s().clone(),
+        ))
+    }
+}
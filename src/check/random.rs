@@ -0,0 +1,399 @@
+//! Random testing of systems, no solver involved.
+//!
+//! A [`Tester`] runs the system on random behaviors, one per seed: it samples an initial state,
+//! then successor states for some number of steps, and evaluates the candidates on every state it
+//! visits. A candidate that does not hold is falsified, with the run leading to the violation as
+//! counterexample. This finds shallow bugs quickly on systems too big for BMC, but proves nothing:
+//! candidates that hold on all the runs are [unknown](Verdict::Unknown).
+//!
+//! States are sampled from the top-level conjuncts of the initial predicate and of the transition
+//! relation. A conjunct `v = e` (`'v = e` in the transition relation) *defines* `v`: its value is
+//! the value of `e` as soon as the variables of `e` have one. Variables without a usable definition
+//! take random values, and so do [input](trans::Role::Input) variables, which are chosen freely at
+//! each step. The remaining conjuncts then decide whether the sample is kept. Samples that
+//! violate some conjunct are dropped, and the run stops if no sample is kept after
+//! [`Tester::attempts`] attempts.
+//!
+//! Random values of integer variables are within their [bounds](Tester::bound), between
+//! [`-DEFAULT_BOUND`](DEFAULT_BOUND) and [`DEFAULT_BOUND`] by default. Rational variables take
+//! values `n / d` with `n` within the bounds and `d` between `1` and `4`. Array variables are not
+//! supported. Runs only depend on their seed.
+
+crate::prelude!();
+
+use num::ToPrimitive;
+
+use super::{
+    cexs::Cex,
+    driver::Verdict,
+    preprocess::{as_def, conjuncts, SysVar},
+    replay,
+    trace::Trace,
+};
+use crate::demo::Rng;
+use expr::{Cst, PExpr, SVar, Var};
+use trans::Sys;
+
+/// Default bound of the random values of integer and rational variables, see [`Tester::bound`].
+pub const DEFAULT_BOUND: u64 = 100;
+/// Default number of steps of each run, see [`Tester::steps`].
+pub const DEFAULT_STEPS: Unroll = 20;
+/// Default number of seeds, see [`Tester::seeds`].
+pub const DEFAULT_SEEDS: u64 = 100;
+/// Default number of attempts at sampling a state, see [`Tester::attempts`].
+pub const DEFAULT_ATTEMPTS: usize = 100;
+
+/// Result of a random testing campaign.
+#[derive(Debug, Clone)]
+pub struct Testing {
+    /// Number of runs, one per seed.
+    pub runs: usize,
+    /// Number of runs that stopped before the last step, because no initial or successor state
+    /// was found.
+    pub stuck: usize,
+    /// Number of states visited, over all runs.
+    pub states: usize,
+    /// Verdict for each candidate, either falsified or unknown.
+    ///
+    /// Falsifications are the shortest ones over all runs.
+    pub verdicts: Map<String, Verdict>,
+}
+
+/// Random values of a variable.
+#[derive(Debug, Clone)]
+enum Domain {
+    /// Booleans.
+    Bool,
+    /// Integers between `lo` and `lo + width`.
+    Int { lo: Int, width: u64 },
+    /// Rationals `n / d` with `n` between `lo` and `lo + width`.
+    Rat { lo: Int, width: u64 },
+    /// Bitvectors of some width.
+    BitVec(u32),
+    /// Enumeration variants.
+    Enum(Vec<Cst>),
+}
+impl Domain {
+    /// Random value.
+    fn pick(&self, rng: &mut Rng) -> Cst {
+        match self {
+            Self::Bool => Cst::bool(rng.range(0, 1) == 1),
+            Self::Int { lo, width } => Cst::int(lo + rng.range(0, *width)),
+            Self::Rat { lo, width } => {
+                let n = lo + rng.range(0, *width);
+                Cst::rat(Rat::new(n, rng.range(1, 4).into()))
+            }
+            Self::BitVec(width) => Cst::bv(*width, rng.next()),
+            Self::Enum(variants) => {
+                variants[rng.range(0, variants.len() as u64 - 1) as usize].clone()
+            }
+        }
+    }
+}
+
+/// Random tester, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::{random::Tester, replay, Verdict}, parse};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int, inc: bool }
+/// init { cnt = 0 }
+/// trans { 'cnt = (if inc { cnt + 1 } else { 0 }) }
+/// candidates {
+///     "cnt is positive": cnt ≥ 0,
+///     "cnt is less than 3": cnt < 3,
+/// }
+/// "#).unwrap();
+/// let testing = Tester::new(&sys).steps(10).seeds(50).run().unwrap();
+/// assert_eq!(testing.runs, 50);
+/// assert_eq!(testing.stuck, 0);
+/// assert_eq!(testing.states, 50 * 11);
+/// assert_eq!(testing.verdicts["cnt is positive"].to_string(), "unknown");
+/// // Shortest falsification over all runs.
+/// match &testing.verdicts["cnt is less than 3"] {
+///     Verdict::Falsified { depth, cex } => {
+///         assert_eq!(*depth, 3);
+///         assert!(replay::check_run(&sys, &cex.trace).is_ok());
+///     }
+///     verdict => panic!("unexpected verdict {:?}", verdict),
+/// }
+///
+/// // Random values are within `[-100, 100]` by default, no initial state is ever found.
+/// let sys = parse::trans(
+///     "svars { x: int } init { x > 200 } trans { 'x = x + 1 } candidates { \"x\": x > 0 }",
+/// )
+/// .unwrap();
+/// let testing = Tester::new(&sys).seeds(10).run().unwrap();
+/// assert_eq!((testing.stuck, testing.states), (10, 0));
+/// let testing = Tester::new(&sys).bound("x", 0, 300).seeds(10).run().unwrap();
+/// assert_eq!((testing.stuck, testing.states), (0, 10 * 21));
+/// ```
+#[derive(Clone)]
+pub struct Tester<'sys> {
+    /// System to test.
+    sys: &'sys Sys,
+    /// Bounds for integer and rational variables, inclusive.
+    bounds: Map<String, (Int, Int)>,
+    /// Number of steps of each run.
+    steps: Unroll,
+    /// Seeds, one run per seed.
+    seeds: std::ops::Range<u64>,
+    /// Number of attempts at sampling a state.
+    attempts: usize,
+}
+impl<'sys> Tester<'sys> {
+    /// Constructor, [`DEFAULT_STEPS`] steps and [`DEFAULT_SEEDS`] seeds.
+    pub fn new(sys: &'sys Sys) -> Self {
+        Self {
+            sys,
+            bounds: Map::new(),
+            steps: DEFAULT_STEPS,
+            seeds: 0..DEFAULT_SEEDS,
+            attempts: DEFAULT_ATTEMPTS,
+        }
+    }
+
+    /// Bounds the random values of an integer or rational variable, both bounds are inclusive.
+    pub fn bound(mut self, var: impl Into<String>, lo: impl Into<Int>, hi: impl Into<Int>) -> Self {
+        let _ = self.bounds.insert(var.into(), (lo.into(), hi.into()));
+        self
+    }
+    /// Sets the number of steps of each run, runs visit `steps + 1` states.
+    pub fn steps(mut self, steps: Unroll) -> Self {
+        self.steps = steps;
+        self
+    }
+    /// Sets the number of runs, the seeds are `0..seeds`.
+    pub fn seeds(mut self, seeds: u64) -> Self {
+        self.seeds = 0..seeds;
+        self
+    }
+    /// Sets the first seed, the seeds are `first..first + n` for `n` runs.
+    pub fn first_seed(mut self, first: u64) -> Self {
+        let runs = self.seeds.end - self.seeds.start;
+        self.seeds = first..first.saturating_add(runs);
+        self
+    }
+    /// Sets the number of attempts at sampling a state before a run stops.
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Random values of each variable of the system.
+    fn domains(&self) -> Res<Vec<(Var, Domain)>> {
+        for var in self.bounds.keys() {
+            match self.sys.decls().get_var(var) {
+                Some(v) if v.typ() == Typ::Int || v.typ() == Typ::Rat => (),
+                Some(v) => bail!("cannot bound variable `{}` of type `{}`", var, v.typ()),
+                None => bail!("cannot bound unknown variable `{}`", var),
+            }
+        }
+        let range = |var: &Var| -> Res<(Int, u64)> {
+            let (lo, hi) = match self.bounds.get(var.id()) {
+                Some((lo, hi)) => (lo.clone(), hi.clone()),
+                None => (-Int::from(DEFAULT_BOUND), Int::from(DEFAULT_BOUND)),
+            };
+            match (&hi - &lo).to_u64() {
+                Some(width) if width < u64::MAX => Ok((lo, width)),
+                _ => bail!(
+                    "illegal bounds [{}, {}] for variable `{}`",
+                    lo,
+                    hi,
+                    var.id()
+                ),
+            }
+        };
+        let mut res = vec![];
+        for var in self.sys.decls().all() {
+            let domain = match var.typ() {
+                Typ::Bool => Domain::Bool,
+                Typ::Int => {
+                    let (lo, width) = range(&var)?;
+                    Domain::Int { lo, width }
+                }
+                Typ::Rat => {
+                    let (lo, width) = range(&var)?;
+                    Domain::Rat { lo, width }
+                }
+                Typ::BitVec(width) => Domain::BitVec(width),
+                Typ::Enum(name) => match self.sys.decls().enum_variants(&name) {
+                    Some(variants) if !variants.is_empty() => Domain::Enum(
+                        variants
+                            .iter()
+                            .map(|variant| Cst::variant(name.clone(), variant.clone()))
+                            .collect(),
+                    ),
+                    _ => bail!("unknown enumeration type `{}`", name),
+                },
+                Typ::Array(_, _) => bail!("cannot test array variable `{}` randomly", var.id()),
+            };
+            res.push((var, domain))
+        }
+        Ok(res)
+    }
+
+    /// Samples values for the variables verifying `target` so that all the conjuncts hold.
+    ///
+    /// Other variables have their value in `prev`. Yields `None` if no sample is kept after
+    /// [`Self::attempts`] attempts.
+    fn sample<V: SysVar>(
+        &self,
+        rng: &mut Rng,
+        domains: &[(Var, Domain)],
+        conjs: &[PExpr<V>],
+        target: impl Fn(&V) -> bool,
+        prev: &Map<String, Cst>,
+    ) -> Res<Option<Map<String, Cst>>> {
+        let defs: Vec<(&V, &PExpr<V>)> = conjs
+            .iter()
+            .filter_map(|conj| as_def(conj, &target))
+            .collect();
+        // Value of a variable, if any.
+        let val = |vals: &Map<String, Cst>, var: &V| {
+            if target(var) {
+                vals.get(var.ident()).cloned()
+            } else {
+                prev.get(var.ident()).cloned()
+            }
+        };
+        // Value of an expression, `None` if some of its variables have no value.
+        let eval = |vals: &Map<String, Cst>, expr: &PExpr<V>| -> Res<Option<Cst>> {
+            let mut complete = true;
+            expr.fold(
+                |var| complete = complete && val(vals, var).is_some(),
+                |_| (),
+                |_, _| (),
+            );
+            if !complete {
+                return Ok(None);
+            }
+            replay::eval(expr, |var| Ok(val(vals, var).expect("checked above"))).map(Some)
+        };
+
+        for _ in 0..self.attempts {
+            let mut vals = Map::new();
+            // Inputs are chosen freely, even if some conjunct looks like a definition.
+            for (var, domain) in domains {
+                if self.sys.decls().role(var.id()) == Some(trans::Role::Input) {
+                    let _ = vals.insert(var.id().to_string(), domain.pick(rng));
+                }
+            }
+            loop {
+                // Resolve definitions as long as possible.
+                let mut progress = true;
+                while progress {
+                    progress = false;
+                    for (var, def) in defs.iter() {
+                        if vals.contains_key(var.ident()) {
+                            continue;
+                        }
+                        if let Some(cst) = eval(&vals, def)? {
+                            let _ = vals.insert(var.ident().to_string(), cst);
+                            progress = true;
+                        }
+                    }
+                }
+                // Pick a random value for an undefined variable, or a defined one if none.
+                let mut unassigned = domains
+                    .iter()
+                    .filter(|(var, _)| !vals.contains_key(var.id()));
+                let undefined = unassigned
+                    .clone()
+                    .find(|(var, _)| defs.iter().all(|(def, _)| def.ident() != var.id()));
+                match undefined.or_else(|| unassigned.next()) {
+                    Some((var, domain)) => {
+                        let _ = vals.insert(var.id().to_string(), domain.pick(rng));
+                    }
+                    None => break,
+                }
+            }
+            let mut holds = true;
+            for conj in conjs {
+                if !eval(&vals, conj)?
+                    .expect("all variables have a value")
+                    .as_bool()?
+                {
+                    holds = false;
+                    break;
+                }
+            }
+            if holds {
+                return Ok(Some(vals));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Runs the random testing campaign.
+    ///
+    /// # Errors
+    ///
+    /// - when the system has array variables,
+    /// - on illegal bounds, or bounds on variables that are not integer or rational variables.
+    pub fn run(&self) -> Res<Testing> {
+        let domains = self.domains()?;
+        let (init, trans) = (conjuncts(self.sys.init()), conjuncts(self.sys.trans()));
+        let mut res = Testing {
+            runs: 0,
+            stuck: 0,
+            states: 0,
+            verdicts: Map::new(),
+        };
+
+        for seed in self.seeds.clone() {
+            let mut rng = Rng::new(seed);
+            res.runs += 1;
+            let mut state = match self.sample(&mut rng, &domains, &init, |_| true, &Map::new())? {
+                Some(state) => state,
+                None => {
+                    res.stuck += 1;
+                    continue;
+                }
+            };
+            let mut trace = Trace::new();
+            loop {
+                let step = trace.push_step(
+                    domains
+                        .iter()
+                        .map(|(var, _)| (var.clone(), state[var.id()].clone())),
+                );
+                res.states += 1;
+                for (name, po) in self.sys.po_s() {
+                    let shorter = match res.verdicts.get(name) {
+                        Some(Verdict::Falsified { depth, .. }) => step < *depth,
+                        _ => true,
+                    };
+                    let holds = || -> Res<bool> { replay::eval_at(po, &trace, step)?.as_bool() };
+                    if shorter && !holds().chain_err(|| format!("on candidate `{}`", name))? {
+                        let mut cex = Cex::new();
+                        cex.trace = trace.clone();
+                        let verdict = Verdict::Falsified { depth: step, cex };
+                        let _ = res.verdicts.insert(name.clone(), verdict);
+                    }
+                }
+                if step >= self.steps {
+                    break;
+                }
+                match self.sample(&mut rng, &domains, &trans, SVar::is_next, &state)? {
+                    Some(next) => state = next,
+                    None => {
+                        res.stuck += 1;
+                        break;
+                    }
+                }
+            }
+        }
+
+        for name in self.sys.po_s().keys() {
+            if !res.verdicts.contains_key(name) {
+                let _ = res
+                    .verdicts
+                    .insert(name.clone(), Verdict::Unknown { bmc_depth: None });
+            }
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,220 @@
+//! Reachability queries, *can the system get here?* and *which initial states lead here?*
+//!
+//! [`reachable`] looks for a shortest run reaching some target, independently of the candidates of
+//! the system: useful as a sanity check that some state is reachable at all.
+//!
+//! A counterexample or a witness shows one concrete way to reach a state. [`initial_states`]
+//! instead characterizes *all* the initial states from which some target is reachable within a
+//! bounded number of steps, by enumerating them with the solver.
+
+crate::prelude!();
+
+use super::{cexs::Cex, suggest::State, InternalChecker};
+use expr::{Cst, Expr, Op, PExpr};
+use trans::Sys;
+
+/// Initial states reaching a target, see [`initial_states`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitStates {
+    /// Initial states reaching the target, in discovery order.
+    ///
+    /// A state only mentions the variables the solver provided a value for, the others can take any
+    /// value.
+    pub states: Vec<State>,
+    /// True if `states` contains all the initial states reaching the target.
+    ///
+    /// False when enumeration stopped because of the limit on the number of states.
+    pub complete: bool,
+}
+impl InitStates {
+    /// Formula over the initial state variables characterizing the states in `self.states`.
+    ///
+    /// This is a disjunction of conjunctions of equalities. It characterizes exactly the initial
+    /// states reaching the target if `self.complete`, and under-approximates them otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{reach::InitStates, suggest::State}, expr::{Cst, Typ, Var}};
+    /// let state = |cnt: i32, reset: Option<bool>| {
+    ///     let mut state = State::new();
+    ///     state.insert(Var::new("cnt", Typ::Int), Cst::int(cnt));
+    ///     if let Some(reset) = reset {
+    ///         state.insert(Var::new("reset", Typ::Bool), Cst::bool(reset));
+    ///     }
+    ///     state
+    /// };
+    /// let mut init = InitStates { states: vec![], complete: true };
+    /// assert_eq!(init.formula().unwrap().to_string(), "false");
+    /// init.states.push(state(0, Some(true)));
+    /// assert_eq!(init.formula().unwrap().to_string(), "(and (= cnt 0) reset)");
+    /// init.states.push(state(3, None));
+    /// assert_eq!(
+    ///     init.formula().unwrap().to_string(),
+    ///     "(or (and (= cnt 0) reset) (= cnt 3))",
+    /// );
+    /// ```
+    pub fn formula(&self) -> Res<Expr> {
+        let mut disjuncts = Vec::with_capacity(self.states.len());
+        for state in self.states.iter() {
+            disjuncts.push(Self::cube(state)?)
+        }
+        match disjuncts.len() {
+            0 => Ok(Cst::bool(false).into()),
+            _ => Expr::new_op(Op::Or, disjuncts),
+        }
+    }
+
+    /// Conjunction of equalities corresponding to a state.
+    fn cube(state: &State) -> Res<Expr> {
+        let mut eqs = Vec::with_capacity(state.len());
+        for (var, cst) in state {
+            let var = PExpr::new_var(var.clone());
+            let eq = match cst {
+                Cst::B(true) => var,
+                Cst::B(false) => Expr::new_op(Op::Not, vec![var])?,
+                cst => Expr::new_op(Op::Eq, vec![var, cst.clone().into()])?,
+            };
+            eqs.push(eq)
+        }
+        match eqs.len() {
+            0 => Ok(Cst::bool(true).into()),
+            _ => Expr::new_op(Op::And, eqs),
+        }
+    }
+}
+
+/// Enumerates the initial states from which `target` is reachable in at most `depth` steps.
+///
+/// Stops after `limit` states, in which case the result is not [complete](InitStates::complete).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let target = parse::expr("cnt = 3 ∧ ¬stop", sys.decls()).unwrap();
+/// let init = check::reach::initial_states(&sys, &target, 2, 10, SmtConf::z3("z3")).unwrap();
+/// println!("{}", init.formula().unwrap());
+/// ```
+pub fn initial_states(
+    sys: &Sys,
+    target: &Expr,
+    depth: Unroll,
+    limit: usize,
+    conf: SmtConf,
+) -> Res<InitStates> {
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    let mut res = InitStates {
+        states: vec![],
+        complete: true,
+    };
+
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for step in 0..=depth {
+        if step > 0 {
+            checker.declare_vars(step)?;
+            checker.assert_trans(step - 1)?;
+        }
+        checker.solver().push(1)?;
+        checker.assert_expr(target, step)?;
+        let mut blocks = vec![];
+        loop {
+            if !checker.check_sat()? {
+                break;
+            }
+            if res.states.len() >= limit {
+                res.complete = false;
+                break;
+            }
+            let mut cex = Cex::new();
+            cex.populate(checker.solver())?;
+            let state = cex.trace.steps.remove(&0).unwrap_or_default();
+            let block = Expr::new_op(Op::Not, vec![InitStates::cube(&state)?])?;
+            checker.assert_expr(&block, 0)?;
+            blocks.push(block);
+            res.states.push(state);
+        }
+        checker.solver().pop(1)?;
+        if !res.complete {
+            break;
+        }
+        // Initial states found for this step are known, no need to find them again later.
+        for block in blocks {
+            checker.assert_expr(&block, 0)?;
+        }
+    }
+    checker.solver().kill()?;
+
+    Ok(res)
+}
+
+/// Looks for a shortest run reaching `target` in at most `depth` steps, with BMC.
+///
+/// Yields `None` if no such run exists. Otherwise, the last step of the witness is the first one
+/// where `target` holds. The candidates of the system play no role.
+///
+/// # Errors
+///
+/// - when `target` is not a boolean expression, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::{self, replay}, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let target = parse::expr("cnt = 3", sys.decls()).unwrap();
+/// let witness = check::reach::reachable(&sys, &target, 10, SmtConf::z3("z3"))
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(witness.len(), 4);
+/// assert!(replay::check_run(&sys, &witness.trace).is_ok());
+///
+/// // Not reachable within 2 steps.
+/// let witness = check::reach::reachable(&sys, &target, 2, SmtConf::z3("z3")).unwrap();
+/// assert!(witness.is_none());
+/// ```
+pub fn reachable(sys: &Sys, target: &Expr, depth: Unroll, conf: SmtConf) -> Res<Option<Cex>> {
+    if target.typ() != Typ::Bool {
+        bail!(
+            "reachability target has type `{}`, expected `bool`",
+            target.typ()
+        )
+    }
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    let mut res = None;
+
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for step in 0..=depth {
+        if step > 0 {
+            checker.declare_vars(step)?;
+            checker.assert_trans(step - 1)?;
+        }
+        checker.solver().push(1)?;
+        checker.assert_expr(target, step)?;
+        if checker.check_sat()? {
+            let mut cex = Cex::new();
+            cex.populate_or_salvage(
+                checker.solver(),
+                &sys.decls().all().collect::<Vec<_>>(),
+                0..=step,
+            );
+            res = Some(cex);
+        }
+        checker.solver().pop(1)?;
+        if res.is_some() {
+            break;
+        }
+    }
+    checker.solver().kill()?;
+
+    Ok(res)
+}
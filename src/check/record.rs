@@ -0,0 +1,298 @@
+//! Externally recorded executions, and conformance of a system to these recordings.
+//!
+//! Recordings are logs of the values of the state variables of a real system, one state per step.
+//! They can be imported as [`Trace`]s from CSV ([`from_csv`]) or JSON ([`from_json`]), and checked
+//! against a model of the system with [`conformance`]: the initial predicate must hold on the first
+//! step, and the transition relation between consecutive steps.
+//!
+//! Values are parsed according to the type of their variable: `true`/`false` for booleans, `-7`
+//! for integers, and `-7`, `0.5` or `1/3` for rationals. Recordings must provide a value for all
+//! the variables mentioned by the initial predicate and the transition relation.
+
+crate::prelude!();
+
+use super::{replay, trace::Trace};
+use crate::json::Json;
+use expr::{Cst, Var};
+use trans::{Decls, Sys};
+
+/// A state as parsed, variable names and values, `None` for missing values.
+type RawState<'txt> = Vec<(&'txt str, Option<&'txt str>)>;
+
+/// Parses the value of a variable.
+fn parse_val(var: &Var, val: &str) -> Res<Cst> {
+    let val = val.trim();
+    let (neg, abs) = match val.strip_prefix('-') {
+        Some(abs) => (true, abs.trim()),
+        None => (false, val),
+    };
+    let sign = |i: Int| if neg { -i } else { i };
+    let cst = match var.typ() {
+        Typ::Rat if abs.contains('/') => abs.parse::<Rat>().ok().map(|r| {
+            let (numer, denom) = r.into();
+            Cst::rat(Rat::new(sign(numer), denom))
+        }),
+        typ => match parse::rules::cst(abs).map(|cst| cst.inner) {
+            Ok(Cst::B(b)) if typ == Typ::Bool && !neg => Some(Cst::bool(b)),
+            Ok(Cst::I(i)) if typ == Typ::Int => Some(Cst::int(sign(i))),
+            Ok(Cst::I(i)) if typ == Typ::Rat => Some(Cst::rat(Rat::from_integer(sign(i)))),
+            Ok(Cst::R(r)) if typ == Typ::Rat => {
+                let (numer, denom) = r.into();
+                Some(Cst::rat(Rat::new(sign(numer), denom)))
+            }
+            Ok(Cst::I(i)) if !neg => match typ {
+                Typ::BitVec(width) if i < Int::one() << width as usize => Some(Cst::bv(width, i)),
+                _ => None,
+            },
+            Ok(cst @ Cst::BV(_, _)) if cst.typ() == typ && !neg => Some(cst),
+            _ => None,
+        },
+    };
+    match cst {
+        Some(cst) => Ok(cst),
+        None => bail!(
+            "illegal value `{}` for variable `{}` of type `{}`",
+            val,
+            var.id(),
+            var.typ()
+        ),
+    }
+}
+
+/// Builds a trace from raw states.
+fn trace_of(decls: &Decls, states: Vec<RawState>) -> Res<Trace> {
+    let mut trace = Trace::new();
+    for (step, state) in states.into_iter().enumerate() {
+        let vals = trace.entry(step).or_default();
+        for (var, val) in state {
+            let var = match decls.get_var(var) {
+                Some(var) => var,
+                None => bail!("unknown variable `{}` at step {}", var, step),
+            };
+            if let Some(val) = val {
+                let cst = parse_val(&var, val).chain_err(|| format!("at step {}", step))?;
+                if vals.insert(var.clone(), cst).is_some() {
+                    bail!("two values for `{}` at step {}", var.id(), step)
+                }
+            }
+        }
+    }
+    Ok(trace)
+}
+
+/// Imports a CSV recording.
+///
+/// The first line lists the variables, each following line is a state. Empty cells are missing
+/// values. Empty lines are ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::record, expr::Cst, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let trace = record::from_csv(sys.decls(), "\
+/// cnt, reset, stop
+/// 0, false, false
+/// 1, false, true
+/// 1, true,
+/// ").unwrap();
+/// assert_eq!(trace.len(), 3);
+/// assert_eq!(trace.get_val(1, "stop"), Some(&Cst::bool(true)));
+/// assert_eq!(trace.get_val(2, "stop"), None);
+///
+/// let err = record::from_csv(sys.decls(), "cnt\n-7\n0.5").unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "illegal value `0.5` for variable `cnt` of type `int`, at step 1",
+/// );
+/// ```
+pub fn from_csv(decls: &Decls, txt: &str) -> Res<Trace> {
+    let mut lines = txt
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let header: Vec<&str> = match lines.next() {
+        Some((_, header)) => header.split(',').map(str::trim).collect(),
+        None => return Ok(Trace::new()),
+    };
+    let mut states = vec![];
+    for (idx, line) in lines {
+        let vals: Vec<&str> = line.split(',').map(str::trim).collect();
+        if vals.len() != header.len() {
+            bail!(
+                "line {} has {} values, expected {}",
+                idx + 1,
+                vals.len(),
+                header.len()
+            )
+        }
+        let state = header
+            .iter()
+            .zip(vals)
+            .map(|(var, val)| (*var, Some(val).filter(|val| !val.is_empty())))
+            .collect();
+        states.push(state)
+    }
+    trace_of(decls, states)
+}
+
+/// Imports a JSON recording.
+///
+/// The recording is an array of states, each state is an object mapping variables to values.
+/// Values are booleans, numbers, strings (*e.g.* `"1/3"`), or `null` for missing values.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::record, expr::Cst, parse, prelude::Rat};
+/// let sys = parse::trans(
+///     "svars { x: rat, on: bool } init { x = 0.0 } trans { 'x = x + 0.5 } \
+///     candidates { \"x is positive\": x ≥ 0.0 }",
+/// )
+/// .unwrap();
+/// let trace = record::from_json(
+///     sys.decls(),
+///     r#"[ { "x": 0, "on": true }, { "x": "1/2", "on": null }, { "x": -1.5 } ]"#,
+/// )
+/// .unwrap();
+/// assert_eq!(trace.get_val(1, "x"), Some(&Cst::rat(Rat::new(1.into(), 2.into()))));
+/// assert_eq!(trace.get_val(1, "on"), None);
+/// assert_eq!(trace.get_val(2, "x"), Some(&Cst::rat(Rat::new((-3).into(), 2.into()))));
+///
+/// let err = record::from_json(sys.decls(), r#"[ { "x": [0] } ]"#).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "expected a boolean, number, string or null value for `x` at step 0, found `[0]`",
+/// );
+/// assert!(record::from_json(sys.decls(), r#"[ { "x": 0 }"#).is_err());
+/// ```
+pub fn from_json(decls: &Decls, txt: &str) -> Res<Trace> {
+    let json = Json::parse(txt)?;
+    let states = match json.as_arr() {
+        Some(states) => states,
+        None => bail!("expected an array of states, found `{}`", json),
+    };
+    let mut raw = Vec::with_capacity(states.len());
+    for (step, state) in states.iter().enumerate() {
+        let fields = match state.as_obj() {
+            Some(fields) => fields,
+            None => bail!("expected an object at step {}, found `{}`", step, state),
+        };
+        let mut vals = Vec::with_capacity(fields.len());
+        for (var, val) in fields {
+            let val = match val {
+                Json::Null => None,
+                Json::Bool(true) => Some("true"),
+                Json::Bool(false) => Some("false"),
+                Json::Num(val) | Json::Str(val) => Some(val.as_str()),
+                Json::Arr(_) | Json::Obj(_) => bail!(
+                    "expected a boolean, number, string or null value for `{}` at step {}, \
+                    found `{}`",
+                    var,
+                    step,
+                    val
+                ),
+            };
+            vals.push((var.as_str(), val))
+        }
+        raw.push(vals)
+    }
+    trace_of(decls, raw)
+}
+
+/// Result of a conformance check, see [`conformance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conformance {
+    /// The recording is a run of the system.
+    Conforms,
+    /// The initial predicate does not hold on the first step.
+    Init,
+    /// The transition relation does not hold between some step and the next one.
+    Trans(Unroll),
+}
+impl fmt::Display for Conformance {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Conforms => "recording conforms to the system".fmt(fmt),
+            Self::Init => "initial predicate does not hold on step 0".fmt(fmt),
+            Self::Trans(step) => write!(
+                fmt,
+                "transition relation does not hold between steps {} and {}",
+                step,
+                step + 1
+            ),
+        }
+    }
+}
+impl Conformance {
+    /// True if the recording conforms to the system.
+    pub fn is_ok(self) -> bool {
+        self == Self::Conforms
+    }
+
+    /// First step at which the recording diverges from the system, if any.
+    pub fn first_divergence(self) -> Option<Unroll> {
+        match self {
+            Self::Conforms => None,
+            Self::Init => Some(0),
+            Self::Trans(step) => Some(step + 1),
+        }
+    }
+}
+
+/// Checks that a recording is a run of a system.
+///
+/// Unlike [`replay::check_run`], a recording that is not a run of the system is not an error: the
+/// result reports where it diverges. Fails if the recording is empty or lacks values needed to
+/// evaluate the system.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::record::{self, Conformance}, parse};
+/// let sys = parse::trans(
+///     "svars { cnt: int, rst: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if 'rst { 0 } else { cnt + 1 }) } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+///
+/// let logs = vec![
+///     "cnt, rst\n0, false\n1, false\n0, true\n1, false",
+///     "cnt, rst\n0, false\n1, false\n3, false\n4, false",
+///     "cnt, rst\n1, false\n2, false",
+/// ];
+/// let mut results = vec![];
+/// for log in logs {
+///     let recording = record::from_csv(sys.decls(), log).unwrap();
+///     results.push(record::conformance(&sys, &recording).unwrap());
+/// }
+/// assert_eq!(
+///     results,
+///     vec![Conformance::Conforms, Conformance::Trans(1), Conformance::Init],
+/// );
+/// assert_eq!(results[1].first_divergence(), Some(2));
+/// assert_eq!(
+///     results[1].to_string(),
+///     "transition relation does not hold between steps 1 and 2",
+/// );
+/// ```
+pub fn conformance(sys: &Sys, recording: &Trace) -> Res<Conformance> {
+    let last = match recording.keys().next_back() {
+        Some(last) => *last,
+        None => bail!("cannot replay an empty trace"),
+    };
+    let init = replay::eval_at(sys.init(), recording, 0)
+        .chain_err(|| "while evaluating the initial predicate")?;
+    if !init.as_bool()? {
+        return Ok(Conformance::Init);
+    }
+    for step in 0..last {
+        let trans = replay::eval_between(sys.trans(), recording, step)
+            .chain_err(|| format!("while evaluating the transition relation at step {}", step))?;
+        if !trans.as_bool()? {
+            return Ok(Conformance::Trans(step));
+        }
+    }
+    Ok(Conformance::Conforms)
+}
@@ -0,0 +1,170 @@
+//! Trace replay: evaluates a system on a concrete trace, without a solver.
+//!
+//! Replaying a trace checks that it is an actual run of the system: the initial predicate holds on
+//! its first step, and the transition relation holds between consecutive steps. Counterexamples can
+//! be replayed to confirm the candidates they falsify, see [`falsified`].
+//!
+//! Partial traces, built with the editing functions of [`Trace`] for instance, can be completed into
+//! runs of the system with [`extend`], which uses a solver.
+
+crate::prelude!();
+
+use super::trace::Trace;
+use expr::{Cst, Expr, SExpr};
+use trans::Sys;
+
+/// Evaluates an expression given a value for each variable.
+pub(crate) fn eval<V>(expr: &expr::PExpr<V>, val: impl FnMut(&V) -> Res<Cst>) -> Res<Cst> {
+    expr.fold(
+        val,
+        |cst| Ok(cst.clone()),
+        |op, args| op.eval(args.into_iter().collect::<Res<_>>()?),
+    )
+}
+
+/// Evaluates a stateless expression on some step of a trace.
+///
+/// Fails if the trace has no value for some variable of the expression at this step.
+pub fn eval_at(expr: &Expr, trace: &Trace, step: Unroll) -> Res<Cst> {
+    eval(expr, |var| match trace.get_val(step, var.id()) {
+        Some(cst) => Ok(cst.clone()),
+        None => bail!("no value for `{}` at step {}", var.id(), step),
+    })
+}
+
+/// Evaluates a stateful expression between `step` and `step + 1` in a trace.
+///
+/// Fails if the trace has no value for some variable of the expression.
+pub fn eval_between(expr: &SExpr, trace: &Trace, step: Unroll) -> Res<Cst> {
+    eval(expr, |var| {
+        let step = if var.is_next() { step + 1 } else { step };
+        match trace.get_val(step, var.id()) {
+            Some(cst) => Ok(cst.clone()),
+            None => bail!("no value for `{}` at step {}", var.id(), step),
+        }
+    })
+}
+
+/// Checks that a trace is a run of a system.
+///
+/// Fails if the initial predicate does not hold on the first step, or if the transition relation
+/// does not hold between two consecutive steps.
+pub fn check_run(sys: &Sys, trace: &Trace) -> Res<()> {
+    match super::record::conformance(sys, trace)? {
+        super::record::Conformance::Conforms => Ok(()),
+        divergence => bail!("{}", divergence),
+    }
+}
+
+/// Replays a trace and yields the candidates it falsifies on its last step.
+///
+/// Fails if the trace is not a run of the system, see [`check_run`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::{replay, trace::Trace}, expr::{Cst, Typ, Var}, parse};
+/// let sys = parse::trans(
+///     "svars { cnt: int } init { cnt = 0 } trans { 'cnt = cnt + 2 } \
+///     candidates { \"cnt is not 4\": ¬(cnt = 4), \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let mut trace = Trace::new();
+/// for step in 0..3 {
+///     let state = trace.entry(step).or_default();
+///     let _ = state.insert(Var::new("cnt", Typ::Int), Cst::int(2 * step));
+/// }
+/// assert_eq!(replay::falsified(&sys, &trace).unwrap(), vec!["cnt is not 4"]);
+///
+/// let _ = trace.get_mut(&2).unwrap().insert(Var::new("cnt", Typ::Int), Cst::int(3));
+/// let err = replay::falsified(&sys, &trace).unwrap_err();
+/// assert_eq!(err.to_string(), "transition relation does not hold between steps 1 and 2");
+/// ```
+pub fn falsified(sys: &Sys, trace: &Trace) -> Res<Vec<String>> {
+    check_run(sys, trace)?;
+    let last = *trace
+        .keys()
+        .next_back()
+        .expect("[unreachable] `check_run` fails on empty traces");
+    let mut res = vec![];
+    for (name, po) in sys.po_s() {
+        let val = eval_at(po, trace, last)
+            .chain_err(|| format!("while evaluating candidate `{}`", name))?;
+        if !val.as_bool()? {
+            res.push(name.clone())
+        }
+    }
+    Ok(res)
+}
+
+/// Extends a partial trace into a run of the system of length `len`, using a solver.
+///
+/// The run agrees with all the values of `trace`, and has a value for every state variable at
+/// every step. Yields `None` if no such run exists. Steps of `trace` after `len` are ignored.
+///
+/// # Errors
+///
+/// - when `trace` is not [valid](Trace::validate) for `sys`,
+/// - when `len` is `0`, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::{replay, trace::Trace}, expr::{Cst, Typ, Var}, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let conf = SmtConf::z3("z3");
+/// let mut trace = Trace::new();
+/// trace.set(2, Var::new("cnt", Typ::Int), Cst::int(2));
+/// let run = replay::extend(&sys, &trace, 3, &conf).unwrap().unwrap();
+/// assert_eq!(run.get_val(0, "inc"), Some(&Cst::bool(true)));
+/// assert!(replay::check_run(&sys, &run).is_ok());
+///
+/// trace.set(1, Var::new("cnt", Typ::Int), Cst::int(0));
+/// assert!(replay::extend(&sys, &trace, 3, &conf).unwrap().is_none());
+/// ```
+pub fn extend(sys: &Sys, trace: &Trace, len: Unroll, conf: &SmtConf) -> Res<Option<Trace>> {
+    if len == 0 {
+        bail!("cannot extend a trace to length 0")
+    }
+    let mut pinned = trace.clone();
+    pinned.truncate(len);
+    pinned.validate(sys)?;
+
+    let mut checker = super::InternalChecker::new(sys, conf.clone(), None)?;
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for step in 0..len - 1 {
+        checker.declare_vars(step + 1)?;
+        checker.assert_trans(step)?;
+    }
+    for (step, vals) in pinned.iter() {
+        for (var, val) in vals {
+            let eq = Expr::new_op(
+                expr::Op::Eq,
+                vec![
+                    expr::PExpr::new_var(var.clone()),
+                    expr::PExpr::Cst(val.clone()),
+                ],
+            )?;
+            checker
+                .assert_expr(&eq, *step)
+                .chain_err(|| format!("while pinning `{}` at step {}", var.id(), step))?;
+        }
+    }
+
+    let res = if checker.check_sat()? {
+        let mut cex = super::cexs::Cex::new();
+        cex.populate(checker.solver())?;
+        Some(cex.trace.window(0, len))
+    } else {
+        None
+    };
+    checker.solver().kill()?;
+    Ok(res)
+}
@@ -0,0 +1,320 @@
+//! State-space statistics: how many states verify the initial predicate, a candidate...
+//!
+//! Counting is exact for small boolean slices: when a predicate mentions at most
+//! [`EXACT_BOOL_MAX`] variables, all of them boolean, it is evaluated on all their valuations. Other
+//! predicates go through the solver, which enumerates their models up to some limit. Variables a
+//! predicate does not mention are *free*: a free boolean variable doubles the count, and a free
+//! non-boolean variable makes it infinite (if the predicate is satisfiable).
+//!
+//! Transitions are counted as pairs of states, so the number of transitions of a system with `n`
+//! states is at most `n²`.
+
+crate::prelude!();
+
+use super::{cexs::Cex, replay, trace::Trace, InternalChecker};
+use expr::{Cst, Expr, HasTyp, Op, PExpr, SVar, Var};
+use rsmt2::print::Sym2Smt;
+use std::convert::TryFrom;
+use trans::Sys;
+
+/// Maximal number of boolean variables for exact counting by evaluation.
+pub const EXACT_BOOL_MAX: usize = 16;
+
+/// A number of states, or of pairs of states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    /// Exact count.
+    Exact(u128),
+    /// At least this many, enumeration stopped at the limit.
+    AtLeast(u128),
+    /// Infinitely many.
+    Infinite,
+}
+impl fmt::Display for Count {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exact(n) => n.fmt(fmt),
+            Self::AtLeast(n) => write!(fmt, "≥ {}", n),
+            Self::Infinite => "∞".fmt(fmt),
+        }
+    }
+}
+impl Count {
+    /// Exact count, if any.
+    pub fn exact(self) -> Option<u128> {
+        match self {
+            Self::Exact(n) => Some(n),
+            Self::AtLeast(_) | Self::Infinite => None,
+        }
+    }
+
+    /// Multiplies by `2^exp`, saturates to `AtLeast(u128::MAX)` on overflow.
+    fn times_pow2(self, exp: usize) -> Self {
+        let mul = |n: u128| {
+            u32::try_from(exp)
+                .ok()
+                .and_then(|exp| 2u128.checked_pow(exp))
+                .and_then(|pow| n.checked_mul(pow))
+        };
+        match self {
+            Self::Exact(n) => mul(n).map(Self::Exact).unwrap_or(Self::AtLeast(u128::MAX)),
+            Self::AtLeast(n) => Self::AtLeast(mul(n).unwrap_or(u128::MAX)),
+            Self::Infinite => Self::Infinite,
+        }
+    }
+}
+
+/// State-space statistics of a system, see [`count`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of states, infinite if the system has non-boolean variables.
+    pub states: Count,
+    /// Number of initial states.
+    pub init: Count,
+    /// Number of transitions, *i.e.* pairs of states verifying the transition relation.
+    pub trans: Count,
+    /// Number of states verifying each candidate.
+    pub candidates: Map<String, Count>,
+}
+impl Stats {
+    /// Ratio of two exact counts.
+    fn ratio(num: Count, den: Count) -> Option<f64> {
+        match (num.exact()?, den.exact()?) {
+            (_, 0) => None,
+            (num, den) => Some(num as f64 / den as f64),
+        }
+    }
+
+    /// Ratio of transitions over pairs of states, `1.0` for an unconstrained transition relation.
+    ///
+    /// `None` if the counts are not exact.
+    pub fn trans_density(&self) -> Option<f64> {
+        let pairs = self.states.exact()?.checked_mul(self.states.exact()?)?;
+        Self::ratio(self.trans, Count::Exact(pairs))
+    }
+
+    /// Ratio of states verifying a candidate over all states.
+    ///
+    /// `None` if the candidate does not exist or the counts are not exact.
+    pub fn coverage(&self, candidate: &str) -> Option<f64> {
+        Self::ratio(*self.candidates.get(candidate)?, self.states)
+    }
+}
+
+/// A variable at some step, `0` or `1`.
+type Slot = (Var, Unroll);
+
+/// Counting context.
+struct Counter<'a> {
+    /// System the counted predicates are over.
+    sys: &'a Sys,
+    /// Solver configuration.
+    conf: &'a SmtConf,
+    /// Maximal number of models enumerated by the solver.
+    limit: usize,
+}
+
+/// Counts the valuations of `slots` verifying a predicate.
+///
+/// - `holds` evaluates the predicate on a trace, used for exact boolean counting;
+/// - `var` turns a slot into a variable of the predicate, used to block models.
+fn count_pred<V>(
+    counter: Counter,
+    pred: &PExpr<V>,
+    slots: &Set<Slot>,
+    slot_of: impl Fn(&V) -> Slot,
+    holds: impl Fn(&Trace) -> Res<bool>,
+    var: impl Fn(&Slot) -> Res<PExpr<V>>,
+) -> Res<Count>
+where
+    V: Sym2Smt<Unroll> + HasTyp + Clone,
+{
+    let mut relevant = Set::new();
+    pred.fold(
+        |v| {
+            let _ = relevant.insert(slot_of(v));
+        },
+        |_| (),
+        |_, _| (),
+    );
+    let (mut free_bools, mut free_others) = (0, 0);
+    for (var, _) in slots.difference(&relevant) {
+        if var.typ() == Typ::Bool {
+            free_bools += 1
+        } else {
+            free_others += 1
+        }
+    }
+
+    let count = if relevant.len() <= EXACT_BOOL_MAX
+        && relevant.iter().all(|(var, _)| var.typ() == Typ::Bool)
+    {
+        let relevant: Vec<&Slot> = relevant.iter().collect();
+        let mut count = 0;
+        for valuation in 0..(1u128 << relevant.len()) {
+            let mut trace = Trace::new();
+            for (idx, (var, step)) in relevant.iter().enumerate() {
+                let val = Cst::bool(valuation & (1 << idx) != 0);
+                let _ = trace.entry(*step).or_default().insert(var.clone(), val);
+            }
+            if holds(&trace)? {
+                count += 1
+            }
+        }
+        Count::Exact(count)
+    } else {
+        count_smt(counter, pred, &relevant, var)?
+    };
+
+    if count == Count::Exact(0) {
+        Ok(count)
+    } else if free_others > 0 {
+        Ok(Count::Infinite)
+    } else {
+        Ok(count.times_pow2(free_bools))
+    }
+}
+
+/// Counts the valuations of `relevant` verifying a predicate by enumerating models.
+fn count_smt<V>(
+    Counter { sys, conf, limit }: Counter,
+    pred: &PExpr<V>,
+    relevant: &Set<Slot>,
+    var: impl Fn(&Slot) -> Res<PExpr<V>>,
+) -> Res<Count>
+where
+    V: Sym2Smt<Unroll> + HasTyp + Clone,
+{
+    let mut checker = InternalChecker::new(sys, conf.clone(), None)?;
+    let steps: Set<Unroll> = relevant.iter().map(|(_, step)| *step).collect();
+    for step in steps {
+        checker.declare_vars(step)?;
+    }
+    checker
+        .solver()
+        .assert_with(pred, 0)
+        .chain_err(|| "while asserting predicate to count")?;
+
+    let mut count = 0;
+    let complete = loop {
+        if !checker.check_sat()? {
+            break true;
+        }
+        if count >= limit {
+            break false;
+        }
+        count += 1;
+        let mut cex = Cex::new();
+        cex.populate(checker.solver())?;
+        let mut eqs = vec![];
+        for slot @ (v, step) in relevant {
+            if let Some(cst) = cex.trace.get_val(*step, v.id()) {
+                eqs.push(PExpr::new_op(Op::Eq, vec![var(slot)?, cst.clone().into()])?)
+            }
+        }
+        if eqs.is_empty() {
+            // No value to block, the solver did not provide a model for the relevant variables.
+            break false;
+        }
+        let block = PExpr::new_op(Op::Not, vec![PExpr::new_op(Op::And, eqs)?])?;
+        checker
+            .solver()
+            .assert_with(&block, 0)
+            .chain_err(|| "while blocking a model")?;
+    };
+    checker.solver().kill()?;
+
+    let count = count as u128;
+    Ok(if complete {
+        Count::Exact(count)
+    } else {
+        Count::AtLeast(count)
+    })
+}
+
+/// Counts the states verifying a stateless expression.
+///
+/// Uses the solver (at most `limit` models) unless the expression is a small boolean slice, see the
+/// [module-level documentation](self).
+pub fn count_states(sys: &Sys, expr: &Expr, conf: &SmtConf, limit: usize) -> Res<Count> {
+    let slots = sys.decls().all().map(|var| (var, 0)).collect();
+    count_pred(
+        Counter { sys, conf, limit },
+        expr,
+        &slots,
+        |var: &Var| (var.clone(), 0),
+        |trace| replay::eval_at(expr, trace, 0)?.as_bool(),
+        |(var, _)| Ok(PExpr::new_var(var.clone())),
+    )
+}
+
+/// Counts the transitions of a system, *i.e.* the pairs of states verifying its transition relation.
+pub fn count_transitions(sys: &Sys, conf: &SmtConf, limit: usize) -> Res<Count> {
+    let slots = sys
+        .decls()
+        .all()
+        .flat_map(|var| vec![(var.clone(), 0), (var, 1)])
+        .collect();
+    let decls = sys.decls();
+    count_pred(
+        Counter { sys, conf, limit },
+        sys.trans(),
+        &slots,
+        |var: &SVar| (Var::new(var.id(), var.typ()), var.is_next() as Unroll),
+        |trace| replay::eval_between(sys.trans(), trace, 0)?.as_bool(),
+        |(var, step)| {
+            let svar = if *step == 0 {
+                decls.get_curr_var(var.id())
+            } else {
+                decls.get_next_var(var.id())
+            };
+            match svar {
+                Some(svar) => Ok(PExpr::new_var(svar)),
+                None => bail!("unknown state variable `{}`", var.id()),
+            }
+        },
+    )
+}
+
+/// Computes the state-space statistics of a system.
+///
+/// Each count that cannot be computed by evaluation enumerates at most `limit` models.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::stats::{self, Count}, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(r#"
+/// svars { a b c: bool }
+/// init { a ∨ b, ¬c }
+/// trans { 'a = b, 'b = a }
+/// candidates { "a or b": a ∨ b, "not c": ¬c }
+/// "#).unwrap();
+/// let stats = stats::count(&sys, &SmtConf::z3("z3"), 100).unwrap();
+/// assert_eq!(stats.states, Count::Exact(8));
+/// assert_eq!(stats.init, Count::Exact(3));
+/// // `'a` and `'b` are fixed by `a` and `b`, `c` and `'c` are free.
+/// assert_eq!(stats.trans, Count::Exact(16));
+/// assert_eq!(stats.trans_density(), Some(0.25));
+/// assert_eq!(stats.candidates["a or b"], Count::Exact(6));
+/// assert_eq!(stats.coverage("not c"), Some(0.5));
+/// ```
+pub fn count(sys: &Sys, conf: &SmtConf, limit: usize) -> Res<Stats> {
+    let states = count_states(sys, &Cst::bool(true).into(), conf, limit)
+        .chain_err(|| "while counting states")?;
+    let init =
+        count_states(sys, sys.init(), conf, limit).chain_err(|| "while counting initial states")?;
+    let trans = count_transitions(sys, conf, limit).chain_err(|| "while counting transitions")?;
+    let mut candidates = Map::new();
+    for (name, po) in sys.po_s() {
+        let count = count_states(sys, po, conf, limit)
+            .chain_err(|| format!("while counting the states verifying `{}`", name))?;
+        let _ = candidates.insert(name.clone(), count);
+    }
+    Ok(Stats {
+        states,
+        init,
+        trans,
+        candidates,
+    })
+}
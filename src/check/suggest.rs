@@ -0,0 +1,472 @@
+//! Witness-guided invariant suggestion.
+//!
+//! When a candidate is not inductive, the step check produces a *Counterexample To Induction*
+//! (CTI): a state verifying the candidate with a successor that does not. A strengthening invariant
+//! must exclude this state. This module compares the CTI with *reachable* states sampled by a short
+//! BMC run, and suggests simple predicates that hold in all reachable samples but not in the CTI.
+//!
+//! Suggestions are heuristic: a predicate holding on a few reachable samples is not necessarily an
+//! invariant. They are meant as hints, to be added as candidates and checked.
+//!
+//! [`suggest_invariants`] is a cheaper alternative that does not run the solver: it only looks at
+//! the CTI, the initial predicate and the [invariant bounds](super::bounds) of the system.
+
+crate::prelude!();
+
+use super::{cexs::Cex, InternalChecker, Step};
+use expr::{Cst, Expr, Op, PExpr, Var};
+use trans::{Decls, Sys};
+
+/// A state: values of the state variables.
+pub type State = Map<Var, Cst>;
+
+/// Samples reachable states by unrolling the system up to `depth`.
+///
+/// Produces at most `per_step` distinct states for each step, states can appear more than once
+/// when they are reachable at different steps.
+pub fn reachable_samples(
+    sys: &Sys,
+    conf: SmtConf,
+    depth: Unroll,
+    per_step: usize,
+) -> Res<Vec<State>> {
+    let mut checker = InternalChecker::new(sys, conf, None)?;
+    let mut samples = vec![];
+
+    checker.declare_vars(0)?;
+    checker.assert_init()?;
+    for step in 0..=depth {
+        if step > 0 {
+            checker.declare_vars(step)?;
+            checker.assert_trans(step - 1)?;
+        }
+        checker.solver().push(1)?;
+        for _ in 0..per_step {
+            if !checker.check_sat()? {
+                break;
+            }
+            let mut cex = Cex::new();
+            cex.populate(checker.solver())?;
+            let state = cex.trace.steps.remove(&step).unwrap_or_default();
+            if state.is_empty() {
+                break;
+            }
+            // Block this state to get a different one.
+            let eqs = state
+                .iter()
+                .map(|(var, cst)| {
+                    Expr::new_op(
+                        Op::Eq,
+                        vec![PExpr::new_var(var.clone()), cst.clone().into()],
+                    )
+                })
+                .collect::<Res<_>>()?;
+            let block = Expr::new_op(Op::Not, vec![Expr::new_op(Op::And, eqs)?])?;
+            checker.assert_expr(&block, step)?;
+            samples.push(state);
+        }
+        checker.solver().pop(1)?;
+    }
+    checker.solver().kill()?;
+
+    Ok(samples)
+}
+
+/// Evaluates an expression on a state, `None` if the state does not have a value for some variable.
+fn value(expr: &Expr, state: &State) -> Option<Cst> {
+    expr.fold(
+        |var| state.get(var).cloned(),
+        |cst| Some(cst.clone()),
+        |op, args| op.eval(args.into_iter().collect::<Option<_>>()?).ok(),
+    )
+}
+
+/// Evaluates a predicate on a state, `None` if the state does not have a value for some variable.
+fn eval(expr: &Expr, state: &State) -> Option<bool> {
+    value(expr, state)?.as_bool().ok()
+}
+
+/// Applies an operator, panics on ill-typed applications.
+fn app(op: Op, args: Vec<Expr>) -> Expr {
+    Expr::new_op(op, args).expect("[bug] ill-typed suggestion template")
+}
+
+/// Suggests strengthening predicates excluding `cti`, a state of a CTI.
+///
+/// Suggestions are instances of simple templates that evaluate to `true` on all `samples` and to
+/// `false` on `cti`:
+///
+/// - boolean constants, `b` or `¬b`;
+/// - bounds and equalities with constants, `v ≥ min`, `v ≤ max` and `v = c`;
+/// - modulo constraints for integers, `v % k = r` with `k` between `2` and `5`, only considered
+///   when all values are positive;
+/// - comparisons between variables of the same type, `v = w`, `v ≤ w` and `v ≥ w`;
+/// - guarded equalities, `b ⇒ v = c`.
+///
+/// Suggestions are ordered by template, then by variable identifier. There are no suggestions
+/// without samples.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::suggest, expr::{Cst, Typ, Var}, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let state = |stop: bool, reset: bool, cnt: i32| {
+///     let mut state = suggest::State::new();
+///     state.insert(Var::new("stop", Typ::Bool), Cst::bool(stop));
+///     state.insert(Var::new("reset", Typ::Bool), Cst::bool(reset));
+///     state.insert(Var::new("cnt", Typ::Int), Cst::int(cnt));
+///     state
+/// };
+/// let samples = vec![
+///     state(false, false, 0),
+///     state(true, true, 0),
+///     state(true, false, 3),
+///     state(false, false, 7),
+/// ];
+/// // CTI for "if reset then cnt is 0", where `cnt` is negative.
+/// let cti = state(false, false, -1);
+/// let suggestions: Vec<_> = suggest::suggestions(sys.decls(), &samples, &cti)
+///     .into_iter()
+///     .map(|expr| expr.to_string())
+///     .collect();
+/// assert_eq!(suggestions, vec!["(>= cnt 0)"]);
+///
+/// // Counter incremented by `3`, CTI for `¬(cnt = 7)`.
+/// let sys = parse::trans(
+///     "svars { cnt: int } init { cnt = 0 } trans { 'cnt = cnt + 3 } \
+///     candidates { \"cnt is not 7\": ¬(cnt = 7) }",
+/// )
+/// .unwrap();
+/// let cnt = |cnt: i32| {
+///     let mut state = suggest::State::new();
+///     state.insert(Var::new("cnt", Typ::Int), Cst::int(cnt));
+///     state
+/// };
+/// let samples: Vec<_> = (0..4).map(|i| cnt(3 * i)).collect();
+/// let suggestions: Vec<_> = suggest::suggestions(sys.decls(), &samples, &cnt(4))
+///     .into_iter()
+///     .map(|expr| expr.to_string())
+///     .collect();
+/// assert_eq!(suggestions, vec!["(= (% cnt 3) 0)"]);
+/// ```
+pub fn suggestions(decls: &Decls, samples: &[State], cti: &State) -> Vec<Expr> {
+    if samples.is_empty() {
+        return vec![];
+    }
+    let vars: Vec<Var> = decls.all().collect();
+    let values = |var: &Var| -> Vec<&Cst> { samples.iter().filter_map(|s| s.get(var)).collect() };
+    let mut templates: Vec<Expr> = vec![];
+
+    // Boolean constants.
+    for var in vars.iter().filter(|var| var.typ() == Typ::Bool) {
+        let var = PExpr::new_var(var.clone());
+        templates.push(var.clone());
+        templates.push(app(Op::Not, vec![var]));
+    }
+
+    // Bounds and equalities with constants.
+    for var in vars.iter().filter(|var| var.typ().is_arith()) {
+        let vals = values(var);
+        let (min, max) = match (vals.iter().min(), vals.iter().max()) {
+            (Some(min), Some(max)) => ((*min).clone(), (*max).clone()),
+            _ => continue,
+        };
+        let v = PExpr::new_var(var.clone());
+        if min == max {
+            templates.push(app(Op::Eq, vec![v, min.into()]));
+        } else {
+            templates.push(app(Op::Ge, vec![v.clone(), min.into()]));
+            templates.push(app(Op::Le, vec![v, max.into()]));
+        }
+    }
+
+    // Modulo constraints.
+    for var in vars.iter().filter(|var| var.typ() == Typ::Int) {
+        let mut ints = vec![];
+        for cst in values(var).into_iter().chain(cti.get(var)) {
+            match cst.as_int() {
+                Ok(i) if i.sign() != Sign::Minus => ints.push(i.clone()),
+                _ => break,
+            }
+        }
+        if ints.len() != samples.len() + 1 {
+            continue;
+        }
+        for k in 2..=5 {
+            let k = Int::from(k);
+            let rem = &ints[0] % &k;
+            let v = PExpr::new_var(var.clone());
+            let modulo = app(Op::Mod, vec![v, Cst::int(k).into()]);
+            templates.push(app(Op::Eq, vec![modulo, Cst::int(rem).into()]));
+        }
+    }
+
+    // Comparisons between variables.
+    for (idx, lft) in vars.iter().enumerate() {
+        for rgt in vars[idx + 1..].iter() {
+            if lft.typ() != rgt.typ() || !lft.typ().is_arith() {
+                continue;
+            }
+            let (l, r) = (PExpr::new_var(lft.clone()), PExpr::new_var(rgt.clone()));
+            templates.push(app(Op::Eq, vec![l.clone(), r.clone()]));
+            templates.push(app(Op::Le, vec![l.clone(), r.clone()]));
+            templates.push(app(Op::Ge, vec![l, r]));
+        }
+    }
+
+    // Guarded equalities.
+    for guard in vars.iter().filter(|var| var.typ() == Typ::Bool) {
+        for var in vars.iter().filter(|var| var.typ().is_arith()) {
+            let vals: Set<&Cst> = samples
+                .iter()
+                .filter(|s| s.get(guard) == Some(&Cst::bool(true)))
+                .filter_map(|s| s.get(var))
+                .collect();
+            if vals.len() != 1 {
+                continue;
+            }
+            let val = vals.into_iter().next().expect("[unreachable] len is `1`");
+            let eq = app(
+                Op::Eq,
+                vec![PExpr::new_var(var.clone()), val.clone().into()],
+            );
+            templates.push(app(Op::Implies, vec![PExpr::new_var(guard.clone()), eq]));
+        }
+    }
+
+    let mut res: Vec<Expr> = vec![];
+    for template in templates {
+        let excludes_cti = eval(&template, cti) == Some(false);
+        if excludes_cti
+            && !res.contains(&template)
+            && samples.iter().all(|s| eval(&template, s) == Some(true))
+        {
+            res.push(template)
+        }
+    }
+    res
+}
+
+/// Suggests strengthening predicates for a candidate, see [`suggestions`].
+///
+/// Runs the step check on `candidate` alone. If it fails, samples reachable states up to
+/// `depth` and uses them to generate suggestions excluding the CTI. Yields no suggestions if the
+/// candidate is inductive.
+///
+/// The [invariant bounds](super::bounds::lemmas) excluding the CTI come first. Unlike the other
+/// suggestions, they are known to be invariants.
+///
+/// # Errors
+///
+/// - when `candidate` is not a candidate of `sys`, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check, rsmt2::SmtConf};
+/// let sys = mikino_api::parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let suggestions =
+///     check::suggest::strengthenings(&sys, "cnt is not -7", SmtConf::z3("z3"), 5).unwrap();
+/// for expr in suggestions {
+///     println!("{}", expr);
+/// }
+/// ```
+pub fn strengthenings(sys: &Sys, candidate: &str, conf: SmtConf, depth: Unroll) -> Res<Vec<Expr>> {
+    if !sys.po_s().contains_key(candidate) {
+        bail!("unknown candidate `{}`", candidate)
+    }
+    let restricted = sys.restrict(|name| name == candidate);
+    let step = Step::new(&restricted, conf.clone(), None)?
+        .check()
+        .chain_err(|| "during step check")?;
+    let cti = match step.cexs.values().next().and_then(|cex| cex.trace.get(&0)) {
+        Some(cti) => cti.clone(),
+        None => return Ok(vec![]),
+    };
+    let mut res: Vec<Expr> = super::bounds::lemmas(sys)?
+        .into_iter()
+        .filter(|lemma| eval(lemma, &cti) == Some(false))
+        .collect();
+    let samples = reachable_samples(sys, conf, depth, 3).chain_err(|| "while sampling states")?;
+    for suggestion in suggestions(sys.decls(), &samples, &cti) {
+        if !res.contains(&suggestion) {
+            res.push(suggestion)
+        }
+    }
+    Ok(res)
+}
+
+/// Values of the variables fixed by an initial predicate.
+///
+/// Only looks at the top-level conjuncts of `init` of shape `v`, `¬v`, `v = e` and `e = v`, where
+/// `e` mentions no variable.
+fn initial_values(init: &Expr) -> State {
+    let (mut state, empty) = (State::new(), State::new());
+    let mut todo = vec![init];
+    while let Some(expr) = todo.pop() {
+        let (var, val) = match expr {
+            PExpr::Var(var) => (var, Cst::bool(true)),
+            PExpr::App {
+                op: Op::And, args, ..
+            } => {
+                todo.extend(args.iter());
+                continue;
+            }
+            PExpr::App {
+                op: Op::Not, args, ..
+            } => match args.as_slice() {
+                [PExpr::Var(var)] => (var, Cst::bool(false)),
+                _ => continue,
+            },
+            PExpr::App {
+                op: Op::Eq, args, ..
+            } => match args.as_slice() {
+                [PExpr::Var(var), expr] | [expr, PExpr::Var(var)] => match value(expr, &empty) {
+                    Some(val) => (var, val),
+                    None => continue,
+                },
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let _ = state.insert(var.clone(), val);
+    }
+    state
+}
+
+/// Suggests auxiliary invariants from a counterexample to induction, without a solver.
+///
+/// `cex` is a failed step check counterexample, like the ones of [`Step`]: state `0` verifies the
+/// candidate, and its successor state `1` does not. Suggestions exclude state `0`, and are ordered
+/// as follows.
+///
+/// - [Invariant bounds](super::bounds::lemmas) of the system, which are known to be invariants.
+/// - For each variable with an initial value `c` (*i.e.* the initial predicate has a conjunct
+///   `v = c`, `v` or `¬v`) that is different in state `0`:
+///   - `v = c` when the variable keeps its value in the counterexample;
+///   - `v ≥ c` for arithmetic variables below `c` in state `0` that do not decrease in the
+///     counterexample, symmetrically `v ≤ c` for the ones above `c` that do not increase.
+/// - For pairs of arithmetic variables `v` and `w` of the same type with initial values: `v = w`
+///   when they are equal initially but not in state `0`, `v ≤ w` or `v ≥ w` when the order of their
+///   initial values is reversed in state `0`.
+///
+/// Apart from the bounds, suggestions are guesses and must be checked, typically by adding them as
+/// candidates. See [`strengthenings`] for suggestions based on reachable states, at the cost of
+/// running the solver.
+///
+/// # Errors
+///
+/// - when `cex` has no state `0`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::{self, cexs::Cex}, expr::{Cst, Typ, Var}, parse};
+/// let sys = parse::trans(r#"
+/// svars { x: int, y: int, lock: bool }
+/// init { x = 0 ∧ y = 0 ∧ ¬lock }
+/// trans { 'x = x + 1 ∧ 'y = y + 1 ∧ 'lock = lock }
+/// candidates { "x is small when locked": lock ⇒ x ≤ 5 }
+/// "#).unwrap();
+/// let cti = |cex: &mut Cex, step, x: i32, y: i32, lock: bool| {
+///     cex.insert(step, Var::new("x", Typ::Int), Cst::int(x)).unwrap();
+///     cex.insert(step, Var::new("y", Typ::Int), Cst::int(y)).unwrap();
+///     cex.insert(step, Var::new("lock", Typ::Bool), Cst::bool(lock)).unwrap();
+/// };
+///
+/// let mut cex = Cex::new();
+/// cti(&mut cex, 0, 5, 2, true);
+/// cti(&mut cex, 1, 6, 3, true);
+/// let suggestions: Vec<_> = check::suggest_invariants(&sys, &cex)
+///     .unwrap()
+///     .into_iter()
+///     .map(|expr| expr.to_string())
+///     .collect();
+/// assert_eq!(suggestions, vec!["(not lock)", "(= x y)"]);
+///
+/// // The invariant bounds come first.
+/// let mut cex = Cex::new();
+/// cti(&mut cex, 0, 5, -2, true);
+/// cti(&mut cex, 1, 6, -1, true);
+/// let suggestions: Vec<_> = check::suggest_invariants(&sys, &cex)
+///     .unwrap()
+///     .into_iter()
+///     .map(|expr| expr.to_string())
+///     .collect();
+/// assert_eq!(suggestions, vec!["(>= y 0)", "(not lock)", "(= x y)"]);
+///
+/// let err = check::suggest_invariants(&sys, &Cex::new()).unwrap_err();
+/// assert_eq!(err.to_string(), "counterexample to induction has no state `0`");
+/// ```
+pub fn suggest_invariants(sys: &Sys, cex: &Cex) -> Res<Vec<Expr>> {
+    let cti = match cex.trace.get(&0) {
+        Some(cti) => cti,
+        None => bail!("counterexample to induction has no state `0`"),
+    };
+    let succ = cex.trace.get(&1);
+    let mut res: Vec<Expr> = super::bounds::lemmas(sys)?
+        .into_iter()
+        .filter(|lemma| eval(lemma, cti) == Some(false))
+        .collect();
+    let mut push = |expr: Expr| {
+        if !res.contains(&expr) {
+            res.push(expr)
+        }
+    };
+    let init = initial_values(sys.init());
+
+    // Variables that are not at their initial value.
+    for (var, val) in init.iter() {
+        let now = match cti.get(var) {
+            Some(now) if now != val => now,
+            _ => continue,
+        };
+        let next = succ.and_then(|succ| succ.get(var));
+        let v = PExpr::new_var(var.clone());
+        if next == Some(now) {
+            push(match val {
+                Cst::B(true) => v,
+                Cst::B(false) => app(Op::Not, vec![v]),
+                _ => app(Op::Eq, vec![v, val.clone().into()]),
+            })
+        } else if var.typ().is_arith() {
+            if now < val && next.map(|next| next >= now).unwrap_or(true) {
+                push(app(Op::Ge, vec![v, val.clone().into()]))
+            } else if now > val && next.map(|next| next <= now).unwrap_or(true) {
+                push(app(Op::Le, vec![v, val.clone().into()]))
+            }
+        }
+    }
+
+    // Pairs of variables whose initial order is not respected.
+    let arith: Vec<(&Var, &Cst)> = init
+        .iter()
+        .filter(|(var, _)| var.typ().is_arith())
+        .collect();
+    for (idx, (lft, lft_init)) in arith.iter().enumerate() {
+        for (rgt, rgt_init) in arith[idx + 1..].iter() {
+            let (lft_now, rgt_now) = match (cti.get(*lft), cti.get(*rgt)) {
+                (Some(lft_now), Some(rgt_now)) if lft.typ() == rgt.typ() => (lft_now, rgt_now),
+                _ => continue,
+            };
+            let op = if lft_init == rgt_init && lft_now != rgt_now {
+                Op::Eq
+            } else if lft_init < rgt_init && lft_now > rgt_now {
+                Op::Le
+            } else if lft_init > rgt_init && lft_now < rgt_now {
+                Op::Ge
+            } else {
+                continue;
+            };
+            let (l, r) = (
+                PExpr::new_var((*lft).clone()),
+                PExpr::new_var((*rgt).clone()),
+            );
+            push(app(op, vec![l, r]))
+        }
+    }
+
+    Ok(res)
+}
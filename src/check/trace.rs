@@ -0,0 +1,987 @@
+//! Traces of values, and trace comparison.
+
+crate::prelude!();
+
+use expr::{Cst, Expr, Var};
+
+/// A trace: values of the variables, organized by steps.
+///
+/// Dereferences to the underlying map from steps to values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// Values for each variable, organized by steps.
+    pub steps: Map<Unroll, Map<Var, Cst>>,
+}
+impl Deref for Trace {
+    type Target = Map<Unroll, Map<Var, Cst>>;
+    fn deref(&self) -> &Map<Unroll, Map<Var, Cst>> {
+        &self.steps
+    }
+}
+impl DerefMut for Trace {
+    fn deref_mut(&mut self) -> &mut Map<Unroll, Map<Var, Cst>> {
+        &mut self.steps
+    }
+}
+impl From<Map<Unroll, Map<Var, Cst>>> for Trace {
+    fn from(steps: Map<Unroll, Map<Var, Cst>>) -> Self {
+        Self { steps }
+    }
+}
+impl Trace {
+    /// Empty trace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value of a variable at some step.
+    pub fn get_val(&self, step: Unroll, var: impl AsRef<str>) -> Option<&Cst> {
+        let var = var.as_ref();
+        self.steps
+            .get(&step)?
+            .iter()
+            .find(|(v, _)| v.id() == var)
+            .map(|(_, cst)| cst)
+    }
+
+    /// Step after the last step of the trace, `0` if the trace is empty.
+    pub fn next_step(&self) -> Unroll {
+        self.steps
+            .keys()
+            .next_back()
+            .map(|last| last + 1)
+            .unwrap_or(0)
+    }
+
+    /// Appends a step after the last step of the trace, yields its index.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let cnt = Var::new("cnt", Typ::Int);
+    /// let mut trace = Trace::new();
+    /// assert_eq!(trace.push_step(vec![(cnt.clone(), Cst::int(0))]), 0);
+    /// assert_eq!(trace.push_step(vec![(cnt.clone(), Cst::int(1))]), 1);
+    /// assert_eq!(trace.next_step(), 2);
+    /// assert_eq!(trace.get_val(1, "cnt"), Some(&Cst::int(1)));
+    /// ```
+    pub fn push_step(&mut self, vals: impl IntoIterator<Item = (Var, Cst)>) -> Unroll {
+        let step = self.next_step();
+        let _ = self.steps.insert(step, vals.into_iter().collect());
+        step
+    }
+
+    /// Sets the value of a variable at some step, yields its previous value if any.
+    ///
+    /// Replaces any previous value for a variable with the same identifier, whatever its type.
+    /// Creates the step if needed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// assert_eq!(trace.set(3, Var::new("cnt", Typ::Int), Cst::int(7)), None);
+    /// assert_eq!(trace.set(3, Var::new("cnt", Typ::Int), Cst::int(8)), Some(Cst::int(7)));
+    /// assert_eq!(trace.get_val(3, "cnt"), Some(&Cst::int(8)));
+    /// assert_eq!(trace.unset(3, "cnt"), Some(Cst::int(8)));
+    /// assert_eq!(trace.get_val(3, "cnt"), None);
+    /// ```
+    pub fn set(&mut self, step: Unroll, var: Var, val: Cst) -> Option<Cst> {
+        let prev = self.unset(step, var.id());
+        let _ = self.steps.entry(step).or_default().insert(var, val);
+        prev
+    }
+
+    /// Removes the value of a variable at some step, yields it if any.
+    ///
+    /// The step itself is kept, even if it has no values left.
+    pub fn unset(&mut self, step: Unroll, var: impl AsRef<str>) -> Option<Cst> {
+        let vals = self.steps.get_mut(&step)?;
+        let var = vals.keys().find(|v| v.id() == var.as_ref())?.clone();
+        vals.remove(&var)
+    }
+
+    /// Drops all the steps greater than or equal to `len`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// for step in 0..5 {
+    ///     trace.push_step(vec![(Var::new("cnt", Typ::Int), Cst::int(step))]);
+    /// }
+    /// trace.truncate(2);
+    /// assert_eq!(trace.keys().cloned().collect::<Vec<_>>(), vec![0, 1]);
+    /// ```
+    pub fn truncate(&mut self, len: Unroll) {
+        let _ = self.steps.split_off(&len);
+    }
+
+    /// JSON representation.
+    ///
+    /// Values are strings, in the syntax of the [`Cst`] display.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// trace.push_step(vec![
+    ///     (Var::new("cnt", Typ::Int), Cst::int(-1)),
+    ///     (Var::new("rst", Typ::Bool), Cst::bool(true)),
+    /// ]);
+    /// trace.push_step(None);
+    /// assert_eq!(
+    ///     trace.to_json(),
+    ///     r#"{"steps":[{"step":0,"vars":[{"var":"cnt","typ":"int","value":"(- 1)"},{"var":"rst","typ":"bool","value":"true"}]},{"step":1,"vars":[]}]}"#,
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut s = String::from("{\"steps\":[");
+        for (idx, (step, vals)) in self.steps.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!("{{\"step\":{},\"vars\":[", step));
+            for (idx, (var, val)) in vals.iter().enumerate() {
+                if idx > 0 {
+                    s.push(',');
+                }
+                s.push_str(&format!(
+                    "{{\"var\":{},\"typ\":{},\"value\":{}}}",
+                    json_str(var.id()),
+                    json_str(&var.typ().to_string()),
+                    json_str(&val.to_string())
+                ));
+            }
+            s.push_str("]}");
+        }
+        s.push_str("]}");
+        s
+    }
+
+    /// VCD (value change dump) representation, for waveform viewers such as GTKWave.
+    ///
+    /// Step `n` is at time `n`, with a time unit of one nanosecond, and the dump ends at time
+    /// [`Self::next_step`] so that the last step has a duration. Booleans are 1-bit wires,
+    /// bitvectors are wires of their width, integers are 64-bit two's complement integers and
+    /// rationals are reals. Values of other types are strings, a GTKWave extension. Only the
+    /// values that change are dumped, a missing value is unknown (`x`).
+    ///
+    /// Fails if some integer does not fit on 64 bits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let (cnt, rst) = (Var::new("cnt", Typ::Int), Var::new("rst", Typ::Bool));
+    /// let mut trace = Trace::new();
+    /// trace.push_step(vec![(cnt.clone(), Cst::int(-1)), (rst.clone(), Cst::bool(true))]);
+    /// trace.push_step(vec![(cnt.clone(), Cst::int(2)), (rst.clone(), Cst::bool(true))]);
+    /// trace.push_step(vec![(rst.clone(), Cst::bool(false))]);
+    /// assert_eq!(
+    ///     trace.to_vcd().unwrap(),
+    ///     "\
+    /// $version mikino $end
+    /// $timescale 1 ns $end
+    /// $scope module trace $end
+    /// $var integer 64 ! cnt $end
+    /// $var wire 1 \" rst $end
+    /// $upscope $end
+    /// $enddefinitions $end
+    /// #0
+    /// $dumpvars
+    /// b1111111111111111111111111111111111111111111111111111111111111111 !
+    /// 1\"
+    /// $end
+    /// #1
+    /// b10 !
+    /// #2
+    /// bx !
+    /// 0\"
+    /// #3
+    /// ",
+    /// );
+    /// ```
+    pub fn to_vcd(&self) -> Res<String> {
+        use num::ToPrimitive;
+
+        // VCD identifiers are strings over the printable ASCII characters `!` to `~`.
+        let code = |mut idx: usize| {
+            let mut code = String::new();
+            loop {
+                code.push((b'!' + (idx % 94) as u8) as char);
+                idx /= 94;
+                if idx == 0 {
+                    return code;
+                }
+                idx -= 1;
+            }
+        };
+        let vars: Set<&Var> = self.steps.values().flat_map(Map::keys).collect();
+        let vars: Vec<(&Var, String)> = vars
+            .into_iter()
+            .enumerate()
+            .map(|(idx, var)| (var, code(idx)))
+            .collect();
+
+        let mut s = String::from("$version mikino $end\n$timescale 1 ns $end\n");
+        s.push_str("$scope module trace $end\n");
+        for (var, code) in vars.iter() {
+            let kind = match var.typ() {
+                Typ::Bool => "wire 1".into(),
+                Typ::BitVec(width) => format!("wire {}", width),
+                Typ::Int => "integer 64".into(),
+                Typ::Rat => "real 64".into(),
+                Typ::Enum(_) | Typ::Array(_, _) => "string 1".into(),
+            };
+            // Names cannot contain whitespaces.
+            let name: String = var
+                .id()
+                .chars()
+                .map(|c| if c.is_whitespace() { '_' } else { c })
+                .collect();
+            s.push_str(&format!("$var {} {} {} $end\n", kind, code, name))
+        }
+        s.push_str("$upscope $end\n$enddefinitions $end\n");
+
+        let value = |var: &Var, cst: Option<&Cst>| -> Res<String> {
+            let val = match (var.typ(), cst) {
+                (Typ::Bool, Some(Cst::B(b))) => (if *b { "1" } else { "0" }).into(),
+                (Typ::Bool, _) => "x".into(),
+                (Typ::BitVec(_), Some(Cst::BV(_, val))) => format!("b{} ", val.to_str_radix(2)),
+                (Typ::Int, Some(Cst::I(val))) => match val.to_i64() {
+                    Some(val) => format!("b{:b} ", val),
+                    None => bail!("value `{}` of `{}` does not fit on 64 bits", val, var.id()),
+                },
+                (Typ::BitVec(_) | Typ::Int, _) => "bx ".into(),
+                (Typ::Rat, Some(Cst::R(val))) => {
+                    format!("r{} ", val.to_f64().unwrap_or(f64::NAN))
+                }
+                (Typ::Rat, _) => "rnan ".into(),
+                (_, Some(cst)) => {
+                    let cst: String = cst
+                        .to_string()
+                        .chars()
+                        .map(|c| if c.is_whitespace() { '_' } else { c })
+                        .collect();
+                    format!("s{} ", cst)
+                }
+                (_, None) => "sx ".into(),
+            };
+            Ok(val)
+        };
+        let mut prev: Map<&Var, String> = Map::new();
+        for step in 0..self.next_step() {
+            let vals = self.steps.get(&step);
+            let mut changes = String::new();
+            for (var, code) in vars.iter() {
+                let val = value(var, vals.and_then(|vals| vals.get(*var)))?;
+                if prev.get(*var) != Some(&val) {
+                    changes.push_str(&format!("{}{}\n", val, code));
+                    let _ = prev.insert(var, val);
+                }
+            }
+            s.push_str(&format!("#{}\n", step));
+            if step == 0 {
+                s.push_str(&format!("$dumpvars\n{}$end\n", changes))
+            } else {
+                s.push_str(&changes)
+            }
+        }
+        s.push_str(&format!("#{}\n", self.next_step()));
+        Ok(s)
+    }
+
+    /// Checks that the trace makes sense for a system.
+    ///
+    /// Fails if
+    ///
+    /// - the steps are not `0`, `1`, ... without gaps,
+    /// - some variable is not a state variable of the system, or has the wrong type, or
+    /// - some value does not have the type of its variable.
+    ///
+    /// A valid trace can still be partial: it does not need a value for every variable at every
+    /// step. It is not necessarily a run of the system either, see
+    /// [`replay::check_run`](super::replay::check_run) and [`replay::extend`](super::replay::extend).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}, parse};
+    /// let sys = parse::trans(
+    ///     "svars { cnt: int } init { cnt = 0 } trans { 'cnt = cnt + 1 } \
+    ///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+    /// )
+    /// .unwrap();
+    /// let mut trace = Trace::new();
+    /// trace.push_step(vec![(Var::new("cnt", Typ::Int), Cst::int(0))]);
+    /// trace.push_step(None);
+    /// assert!(trace.validate(&sys).is_ok());
+    ///
+    /// let _ = trace.set(1, Var::new("cnt", Typ::Int), Cst::bool(true));
+    /// assert_eq!(
+    ///     trace.validate(&sys).unwrap_err().to_string(),
+    ///     "illegal value `true` for variable `cnt` of type `int`, at step 1",
+    /// );
+    /// trace.truncate(1);
+    /// let _ = trace.set(2, Var::new("cnt", Typ::Int), Cst::int(2));
+    /// assert_eq!(trace.validate(&sys).unwrap_err().to_string(), "trace has no step 1");
+    /// ```
+    pub fn validate(&self, sys: &trans::Sys) -> Res<()> {
+        for (expected, (step, vals)) in self.steps.iter().enumerate() {
+            if *step != expected {
+                bail!("trace has no step {}", expected)
+            }
+            for (var, val) in vals {
+                let svar = match sys.decls().get_var(var.id()) {
+                    Some(svar) => svar,
+                    None => bail!("unknown variable `{}` at step {}", var.id(), step),
+                };
+                if svar.typ() != var.typ() {
+                    bail!(
+                        "variable `{}` has type `{}`, not `{}`, at step {}",
+                        var.id(),
+                        svar.typ(),
+                        var.typ(),
+                        step
+                    )
+                }
+                if val.typ() != var.typ() {
+                    bail!(
+                        "illegal value `{}` for variable `{}` of type `{}`, at step {}",
+                        val,
+                        var.id(),
+                        var.typ(),
+                        step
+                    )
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Projection of the trace on some variables, identified by name.
+    ///
+    /// Steps keep their numbering, unknown variables are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// for step in 0..3 {
+    ///     let vals = trace.entry(step).or_default();
+    ///     vals.insert(Var::new("cnt", Typ::Int), Cst::int(step));
+    ///     vals.insert(Var::new("rst", Typ::Bool), Cst::bool(step == 2));
+    /// }
+    /// let cnt = trace.select(&["cnt"]);
+    /// assert_eq!(cnt.len(), 3);
+    /// assert_eq!(cnt.get_val(2, "cnt"), Some(&Cst::int(2)));
+    /// assert_eq!(cnt.get_val(2, "rst"), None);
+    /// ```
+    pub fn select<S: AsRef<str>>(&self, vars: impl IntoIterator<Item = S>) -> Self {
+        let vars: Set<String> = vars.into_iter().map(|v| v.as_ref().to_string()).collect();
+        self.steps
+            .iter()
+            .map(|(step, vals)| {
+                let vals = vals
+                    .iter()
+                    .filter(|(var, _)| vars.contains(var.id()))
+                    .map(|(var, cst)| (var.clone(), cst.clone()))
+                    .collect();
+                (*step, vals)
+            })
+            .collect::<Map<_, _>>()
+            .into()
+    }
+
+    /// Steps of the trace where a stateless expression holds.
+    ///
+    /// Steps keep their numbering. Fails if the expression is not boolean, or if the trace has no
+    /// value for some variable of the expression at some step.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, HasTyp, Typ}, parse};
+    /// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+    /// let mut trace = Trace::new();
+    /// for step in 0..5 {
+    ///     let vals = trace.entry(step).or_default();
+    ///     for var in sys.decls().all() {
+    ///         let val = match var.typ() {
+    ///             Typ::Int => Cst::int(step),
+    ///             _ => Cst::bool(false),
+    ///         };
+    ///         vals.insert(var, val);
+    ///     }
+    /// }
+    /// let expr = parse::expr("cnt ≥ 3", sys.decls()).unwrap();
+    /// let late = trace.steps_where(&expr).unwrap();
+    /// assert_eq!(late.keys().cloned().collect::<Vec<_>>(), vec![3, 4]);
+    /// ```
+    pub fn steps_where(&self, expr: &Expr) -> Res<Self> {
+        let mut res = Self::new();
+        for (step, vals) in self.steps.iter() {
+            let holds = super::replay::eval_at(expr, self, *step)
+                .and_then(|cst| cst.as_bool())
+                .chain_err(|| format!("while evaluating `{}` at step {}", expr, step))?;
+            if holds {
+                let _ = res.insert(*step, vals.clone());
+            }
+        }
+        Ok(res)
+    }
+
+    /// Steps of the trace between `from` (inclusive) and `to` (exclusive).
+    ///
+    /// Steps keep their numbering.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// for step in 0..10 {
+    ///     trace.entry(step).or_default().insert(Var::new("cnt", Typ::Int), Cst::int(step));
+    /// }
+    /// let window = trace.window(3, 5);
+    /// assert_eq!(window.keys().cloned().collect::<Vec<_>>(), vec![3, 4]);
+    /// assert!(trace.window(5, 3).is_empty());
+    /// ```
+    pub fn window(&self, from: Unroll, to: Unroll) -> Self {
+        if from >= to {
+            return Self::new();
+        }
+        self.steps
+            .range(from..to)
+            .map(|(step, vals)| (*step, vals.clone()))
+            .collect::<Map<_, _>>()
+            .into()
+    }
+
+    /// Only keeps, at each step, the variables whose value changed since the previous step.
+    ///
+    /// Steps keep their numbering. A step whose previous step is not in the trace keeps all its
+    /// variables, so the first step is complete. Steps where nothing changed are kept, empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// for step in 0..4 {
+    ///     let vals = trace.entry(step).or_default();
+    ///     vals.insert(Var::new("cnt", Typ::Int), Cst::int(step / 2));
+    ///     vals.insert(Var::new("rst", Typ::Bool), Cst::bool(step == 3));
+    /// }
+    /// let changes = trace.changes();
+    /// assert_eq!(changes[&0].len(), 2);
+    /// assert!(changes[&1].is_empty());
+    /// assert_eq!(changes.get_val(2, "cnt"), Some(&Cst::int(1)));
+    /// assert_eq!(changes.get_val(2, "rst"), None);
+    /// assert_eq!(changes.get_val(3, "rst"), Some(&Cst::bool(true)));
+    /// assert_eq!(changes.get_val(3, "cnt"), None);
+    /// ```
+    pub fn changes(&self) -> Self {
+        self.steps
+            .iter()
+            .map(|(step, vals)| {
+                let prev = step.checked_sub(1).and_then(|prev| self.steps.get(&prev));
+                let vals = vals
+                    .iter()
+                    .filter(|(var, cst)| prev.is_none_or(|prev| prev.get(*var) != Some(*cst)))
+                    .map(|(var, cst)| (var.clone(), cst.clone()))
+                    .collect();
+                (*step, vals)
+            })
+            .collect::<Map<_, _>>()
+            .into()
+    }
+
+    /// Compares two steps of the trace.
+    ///
+    /// Yields the variables with different values at steps `lft` and `rgt`, with their values.
+    /// Variables are compared by identifier, a variable that has a value at only one of the steps
+    /// is a difference.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// for step in 0..3 {
+    ///     let vals = trace.entry(step).or_default();
+    ///     vals.insert(Var::new("cnt", Typ::Int), Cst::int(step));
+    ///     vals.insert(Var::new("rst", Typ::Bool), Cst::bool(false));
+    /// }
+    /// let diff = trace.diff_steps(0, 2);
+    /// assert_eq!(diff.len(), 1);
+    /// assert_eq!(diff["cnt"], (Some(Cst::int(0)), Some(Cst::int(2))));
+    /// assert!(trace.diff_steps(1, 1).is_empty());
+    /// assert_eq!(trace.diff_steps(2, 3)["rst"], (Some(Cst::bool(false)), None));
+    /// ```
+    pub fn diff_steps(&self, lft: Unroll, rgt: Unroll) -> Map<String, ValDiff> {
+        let empty = Map::new();
+        let lft_vals = self.steps.get(&lft).unwrap_or(&empty);
+        let rgt_vals = self.steps.get(&rgt).unwrap_or(&empty);
+        let vars: Set<&str> = lft_vals
+            .keys()
+            .chain(rgt_vals.keys())
+            .map(Var::id)
+            .collect();
+        let mut res = Map::new();
+        for var in vars {
+            let lft_val = self.get_val(lft, var);
+            let rgt_val = self.get_val(rgt, var);
+            if lft_val != rgt_val {
+                let _ = res.insert(var.into(), (lft_val.cloned(), rgt_val.cloned()));
+            }
+        }
+        res
+    }
+
+    /// Renumbers the steps of the trace, steps mapped to `None` are dropped.
+    ///
+    /// Steps mapped to the same step are merged, later steps win on conflicting values.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let mut trace = Trace::new();
+    /// for step in 7..10 {
+    ///     trace.entry(step).or_default().insert(Var::new("cnt", Typ::Int), Cst::int(step));
+    /// }
+    /// let shifted = trace.renumber(|step| step.checked_sub(8));
+    /// assert_eq!(shifted.keys().cloned().collect::<Vec<_>>(), vec![0, 1]);
+    /// assert_eq!(shifted.get_val(1, "cnt"), Some(&Cst::int(9)));
+    /// ```
+    pub fn renumber(&self, mut f: impl FnMut(Unroll) -> Option<Unroll>) -> Self {
+        let mut res = Self::new();
+        for (step, vals) in self.steps.iter() {
+            if let Some(step) = f(*step) {
+                res.entry(step)
+                    .or_default()
+                    .extend(vals.iter().map(|(var, cst)| (var.clone(), cst.clone())))
+            }
+        }
+        res
+    }
+
+    /// Applies a filter, see [`TraceFilter`].
+    pub fn filter(&self, filter: &TraceFilter) -> Res<Self> {
+        let mut res = match filter.window {
+            Some((from, to)) => self.window(from, to),
+            None => self.clone(),
+        };
+        if let Some(expr) = filter.when.as_ref() {
+            res = res.steps_where(expr)?
+        }
+        if filter.changed {
+            let changes = self.changes();
+            for (step, vals) in res.steps.iter_mut() {
+                if let Some(changed) = changes.get(step) {
+                    vals.retain(|var, _| changed.contains_key(var))
+                }
+            }
+        }
+        if let Some(vars) = filter.vars.as_ref() {
+            res = res.select(vars)
+        }
+        Ok(res)
+    }
+
+    /// Compares two traces.
+    ///
+    /// Variables are compared by identifier. A variable that has a value at some step in one trace
+    /// but not in the other is a difference.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}};
+    /// let (cnt, rst) = (Var::new("cnt", Typ::Int), Var::new("rst", Typ::Bool));
+    /// let mut lft = Trace::new();
+    /// let mut rgt = Trace::new();
+    /// for step in 0..3 {
+    ///     for trace in [&mut lft, &mut rgt] {
+    ///         let vals = trace.entry(step).or_default();
+    ///         vals.insert(cnt.clone(), Cst::int(step));
+    ///         vals.insert(rst.clone(), Cst::bool(false));
+    ///     }
+    /// }
+    /// rgt.get_mut(&2).unwrap().insert(cnt.clone(), Cst::int(0));
+    /// rgt.get_mut(&2).unwrap().insert(rst.clone(), Cst::bool(true));
+    /// rgt.entry(3).or_default().insert(cnt.clone(), Cst::int(1));
+    ///
+    /// let diff = lft.diff(&rgt);
+    /// assert_eq!(diff.first_divergence(), Some(2));
+    /// assert_eq!(
+    ///     diff.to_string(),
+    ///     "\
+    /// traces diverge at step 2
+    /// step 2
+    ///     cnt: 2 | 0
+    ///     rst: false | true
+    /// step 3
+    ///     cnt: _ | 1
+    /// ",
+    /// );
+    /// assert_eq!(
+    ///     diff.to_json(),
+    ///     r#"{"first_divergence":2,"steps":[{"step":2,"vars":[{"var":"cnt","left":"2","right":"0"},{"var":"rst","left":"false","right":"true"}]},{"step":3,"vars":[{"var":"cnt","left":null,"right":"1"}]}]}"#,
+    /// );
+    ///
+    /// assert!(lft.diff(&lft).is_empty());
+    /// ```
+    pub fn diff(&self, other: &Self) -> TraceDiff {
+        let mut steps: Map<Unroll, Map<String, ValDiff>> = Map::new();
+
+        let all_steps: Set<Unroll> = self
+            .steps
+            .keys()
+            .chain(other.steps.keys())
+            .cloned()
+            .collect();
+        for step in all_steps {
+            let empty = Map::new();
+            let lft = self.steps.get(&step).unwrap_or(&empty);
+            let rgt = other.steps.get(&step).unwrap_or(&empty);
+            let vars: Set<&str> = lft.keys().chain(rgt.keys()).map(Var::id).collect();
+            for var in vars {
+                let lft_val = self.get_val(step, var);
+                let rgt_val = other.get_val(step, var);
+                if lft_val != rgt_val {
+                    let _ = steps
+                        .entry(step)
+                        .or_default()
+                        .insert(var.into(), (lft_val.cloned(), rgt_val.cloned()));
+                }
+            }
+        }
+
+        TraceDiff { steps }
+    }
+
+    /// Generates a Rust unit test replaying this trace on a system.
+    ///
+    /// The test asserts that the trace is a run of the system that falsifies, on its last step,
+    /// the candidates it currently falsifies, see [`replay::falsified`]. It can be added to a
+    /// test suite depending on `mikino_api` to prevent regressions.
+    ///
+    /// - `test_name`: name of the test function;
+    /// - `sys_expr`: Rust expression producing the system in the test, *e.g.*
+    ///   `mikino_api::parse::trans(include_str!("sys.mkn")).unwrap()`.
+    ///
+    /// Fails if the trace is not a run of `sys`, or if it does not falsify any candidate.
+    ///
+    /// [`replay::falsified`]: super::replay::falsified
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}, parse};
+    /// let sys = parse::trans(
+    ///     "svars { cnt: int, on: bool } init { cnt = 0, on } trans { 'cnt = cnt + 7, 'on = on } \
+    ///     candidates { \"cnt is not 7\": ¬(cnt = 7) }",
+    /// )
+    /// .unwrap();
+    /// let mut trace = Trace::new();
+    /// for step in 0..2 {
+    ///     let state = trace.entry(step).or_default();
+    ///     let _ = state.insert(Var::new("cnt", Typ::Int), Cst::int(7 * step));
+    ///     let _ = state.insert(Var::new("on", Typ::Bool), Cst::bool(true));
+    /// }
+    /// let test = trace.to_rust_test(&sys, "cnt_reaches_7", "sys()").unwrap();
+    /// assert_eq!(
+    ///     test,
+    ///     r#"#[test]
+    /// fn cnt_reaches_7() {
+    ///     use mikino_api::{check::{replay, trace::Trace}, expr::{Cst, Typ, Var}};
+    ///     let sys = sys();
+    ///     let mut trace = Trace::new();
+    ///
+    ///     let state = trace.entry(0).or_default();
+    ///     let _ = state.insert(Var::new("cnt", Typ::Int), Cst::int(0));
+    ///     let _ = state.insert(Var::new("on", Typ::Bool), Cst::bool(true));
+    ///
+    ///     let state = trace.entry(1).or_default();
+    ///     let _ = state.insert(Var::new("cnt", Typ::Int), Cst::int(7));
+    ///     let _ = state.insert(Var::new("on", Typ::Bool), Cst::bool(true));
+    ///
+    ///     let falsified = replay::falsified(&sys, &trace).expect("trace is not a run of the system");
+    ///     assert_eq!(falsified, vec!["cnt is not 7"]);
+    /// }
+    /// "#,
+    /// );
+    /// ```
+    pub fn to_rust_test(&self, sys: &trans::Sys, test_name: &str, sys_expr: &str) -> Res<String> {
+        let falsified = super::replay::falsified(sys, self)
+            .chain_err(|| "while replaying the trace on the system")?;
+        if falsified.is_empty() {
+            bail!("trace does not falsify any candidate")
+        }
+
+        let mut s = format!(
+            "\
+#[test]
+fn {}() {{
+    use mikino_api::{{check::{{replay, trace::Trace}}, expr::{{Cst, Typ, Var}}}};
+    let sys = {};
+    let mut trace = Trace::new();
+",
+            test_name, sys_expr,
+        );
+        for (step, vals) in self.steps.iter() {
+            s.push_str(&format!(
+                "\n    let state = trace.entry({}).or_default();\n",
+                step
+            ));
+            for (var, cst) in vals {
+                s.push_str(&format!(
+                    "    let _ = state.insert(Var::new({:?}, {}), {});\n",
+                    var.id(),
+                    rust_typ(&var.typ()),
+                    rust_cst(cst),
+                ));
+            }
+        }
+        s.push_str(&format!(
+            "
+    let falsified = replay::falsified(&sys, &trace).expect(\"trace is not a run of the system\");
+    assert_eq!(falsified, vec!{:?});
+}}
+",
+            falsified
+        ));
+        Ok(s)
+    }
+}
+
+/// Trace filter, combines [`Trace::window`], [`Trace::steps_where`], [`Trace::changes`] and
+/// [`Trace::select`].
+///
+/// Changes are computed on the full trace, a variable is kept at some step if its value differs
+/// from the one at the previous step of the full trace.
+///
+/// Used to print parts of a trace, see [`Cex::render_filtered`](super::cexs::Cex::render_filtered).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::trace::{Trace, TraceFilter}, expr::{Cst, HasTyp, Typ}, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let mut trace = Trace::new();
+/// for step in 0..10 {
+///     let vals = trace.entry(step).or_default();
+///     for var in sys.decls().all() {
+///         let val = match var.typ() {
+///             Typ::Int => Cst::int(step % 4),
+///             _ => Cst::bool(step % 4 == 3),
+///         };
+///         vals.insert(var, val);
+///     }
+/// }
+/// let filter = TraceFilter::new()
+///     .window(2, 8)
+///     .when(parse::expr("cnt = 3", sys.decls()).unwrap())
+///     .select(vec!["reset"]);
+/// let filtered = trace.filter(&filter).unwrap();
+/// assert_eq!(filtered.keys().cloned().collect::<Vec<_>>(), vec![3, 7]);
+/// assert_eq!(filtered.get_val(7, "reset"), Some(&Cst::bool(true)));
+/// assert_eq!(filtered.get_val(7, "cnt"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TraceFilter {
+    /// Variables to keep, all if `None`.
+    pub vars: Option<Set<String>>,
+    /// Steps to keep, from (inclusive) to (exclusive), all if `None`.
+    pub window: Option<(Unroll, Unroll)>,
+    /// Only keeps the steps where this stateless expression holds.
+    pub when: Option<Expr>,
+    /// Only keeps the variables whose value changed since the previous step.
+    pub changed: bool,
+}
+impl TraceFilter {
+    /// Filter keeping everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keeps some variables, see [`Trace::select`].
+    pub fn select<S: Into<String>>(mut self, vars: impl IntoIterator<Item = S>) -> Self {
+        self.vars = Some(vars.into_iter().map(Into::into).collect());
+        self
+    }
+    /// Only keeps some steps, see [`Trace::window`].
+    pub fn window(mut self, from: Unroll, to: Unroll) -> Self {
+        self.window = Some((from, to));
+        self
+    }
+    /// Only keeps the steps where an expression holds, see [`Trace::steps_where`].
+    pub fn when(mut self, expr: Expr) -> Self {
+        self.when = Some(expr);
+        self
+    }
+    /// Only keeps the variables whose value changed, see [`Trace::changes`].
+    pub fn changed(mut self) -> Self {
+        self.changed = true;
+        self
+    }
+
+    /// True if the filter keeps everything.
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_none() && self.window.is_none() && self.when.is_none() && !self.changed
+    }
+}
+
+/// Rust expression building a constant.
+fn rust_cst(cst: &Cst) -> String {
+    use num::ToPrimitive;
+    let int = |i: &Int| match i.to_i32() {
+        Some(i) => format!("mikino_api::prelude::Int::from({})", i),
+        None => format!(
+            "{:?}.parse::<mikino_api::prelude::Int>().unwrap()",
+            i.to_string()
+        ),
+    };
+    match cst {
+        Cst::B(b) => format!("Cst::bool({})", b),
+        Cst::I(i) => match i.to_i32() {
+            Some(i) => format!("Cst::int({})", i),
+            None => format!("Cst::int({})", int(i)),
+        },
+        Cst::R(r) => format!(
+            "Cst::rat(mikino_api::prelude::Rat::new({}, {}))",
+            int(r.numer()),
+            int(r.denom()),
+        ),
+        Cst::BV(width, val) => format!("Cst::bv({}, {})", width, int(val)),
+        Cst::Array {
+            idx,
+            default,
+            entries,
+        } => {
+            let mut res = format!("Cst::array({}, {})", rust_typ(idx), rust_cst(default));
+            for (idx, val) in entries.iter() {
+                res = format!(
+                    "{}.store({}, {}).unwrap()",
+                    res,
+                    rust_cst(idx),
+                    rust_cst(val)
+                )
+            }
+            res
+        }
+        Cst::Enum { typ, variant } => format!("Cst::variant({:?}, {:?})", typ, variant),
+    }
+}
+
+/// Rust expression building a type.
+fn rust_typ(typ: &Typ) -> String {
+    match typ {
+        Typ::Array(idx, elm) => format!("Typ::array({}, {})", rust_typ(idx), rust_typ(elm)),
+        Typ::Enum(name) => format!("Typ::enumeration({:?})", name),
+        Typ::Bool | Typ::Int | Typ::Rat | Typ::BitVec(_) => format!("Typ::{:?}", typ),
+    }
+}
+
+/// Left and right values of a variable that differs between two traces, `None` if missing.
+pub type ValDiff = (Option<Cst>, Option<Cst>);
+
+/// Differences between two traces, see [`Trace::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDiff {
+    /// Maps steps to the variables that have different values, with their left and right values.
+    ///
+    /// Steps without differences do not appear.
+    pub steps: Map<Unroll, Map<String, ValDiff>>,
+}
+impl TraceDiff {
+    /// True if the traces are the same.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// First step at which the traces diverge, if any.
+    pub fn first_divergence(&self) -> Option<Unroll> {
+        self.steps.keys().next().cloned()
+    }
+
+    /// JSON representation.
+    pub fn to_json(&self) -> String {
+        let val = |cst: &Option<Cst>| match cst {
+            Some(cst) => json_str(&cst.to_string()),
+            None => "null".into(),
+        };
+        let mut s = String::from("{\"first_divergence\":");
+        match self.first_divergence() {
+            Some(step) => s.push_str(&step.to_string()),
+            None => s.push_str("null"),
+        }
+        s.push_str(",\"steps\":[");
+        for (idx, (step, vars)) in self.steps.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!("{{\"step\":{},\"vars\":[", step));
+            for (idx, (var, (lft, rgt))) in vars.iter().enumerate() {
+                if idx > 0 {
+                    s.push(',');
+                }
+                s.push_str(&format!(
+                    "{{\"var\":{},\"left\":{},\"right\":{}}}",
+                    json_str(var),
+                    val(lft),
+                    val(rgt)
+                ));
+            }
+            s.push_str("]}");
+        }
+        s.push_str("]}");
+        s
+    }
+}
+impl fmt::Display for TraceDiff {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let val = |cst: &Option<Cst>| match cst {
+            Some(cst) => cst.to_string(),
+            None => "_".into(),
+        };
+        match self.first_divergence() {
+            Some(step) => writeln!(fmt, "traces diverge at step {}", step)?,
+            None => return writeln!(fmt, "traces are identical"),
+        }
+        for (step, vars) in self.steps.iter() {
+            writeln!(fmt, "step {}", step)?;
+            for (var, (lft, rgt)) in vars {
+                writeln!(fmt, "    {}: {} | {}", var, val(lft), val(rgt))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// JSON string literal.
+pub(crate) fn json_str(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
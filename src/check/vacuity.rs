@@ -0,0 +1,129 @@
+//! Vacuity detection for proved candidates.
+//!
+//! A candidate `a ⇒ b` holds vacuously if `a` never holds: the proof says nothing about `b`, which
+//! usually means the candidate or the system is wrong. [`check()`] looks for a run reaching each
+//! [antecedent](antecedents) of a candidate with [BMC](super::reach::reachable), and reports the
+//! ones no run reaches within some depth.
+//!
+//! Not reaching an antecedent within some depth does not mean it is unreachable, vacuity reports
+//! are thus warnings. [`check::run`](super::run()) reports them for proved candidates as findings
+//! of the [`VacuousProof`](crate::lint::Lint::VacuousProof) lint, when it is not allowed.
+
+crate::prelude!();
+
+use expr::{Expr, Op, PExpr};
+use trans::Sys;
+
+/// Default depth of the reachability queries for antecedents.
+pub const DEFAULT_DEPTH: Unroll = 10;
+
+/// An antecedent of a candidate that no run reaches within some depth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vacuity {
+    /// Candidate.
+    pub candidate: String,
+    /// Antecedent of the candidate that never holds.
+    pub antecedent: Expr,
+    /// Depth up to which the antecedent never holds.
+    pub depth: Unroll,
+}
+impl fmt::Display for Vacuity {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "candidate `{}` might hold vacuously, antecedent `{}` does not hold within {} steps",
+            self.candidate, self.antecedent, self.depth
+        )
+    }
+}
+
+/// Antecedents of the implications of a candidate.
+///
+/// Goes through the top-level conjunctions of the candidate, and through the consequents of its
+/// implications. The antecedent of `a_1 ⇒ ... ⇒ a_n ⇒ b` is the conjunction of the `a_i`s.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::vacuity, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let txt = "cnt ≥ 0 ∧ (reset ⇒ cnt = 0) ∧ (stop ⇒ cnt > 2 ⇒ ¬reset)";
+/// let candidate = parse::expr(txt, sys.decls()).unwrap();
+/// let antecedents: Vec<_> = vacuity::antecedents(&candidate)
+///     .iter()
+///     .map(|a| a.to_string())
+///     .collect();
+/// assert_eq!(antecedents, vec!["reset", "(and stop (> cnt 2))"]);
+/// ```
+pub fn antecedents(candidate: &Expr) -> Vec<Expr> {
+    let mut res = vec![];
+    let mut todo = vec![candidate];
+    while let Some(expr) = todo.pop() {
+        match expr {
+            PExpr::App {
+                op: Op::And, args, ..
+            } => todo.extend(args.iter().rev()),
+            PExpr::App {
+                op: Op::Implies,
+                args,
+                ..
+            } if args.len() > 1 => {
+                let (consequent, hyps) = args.split_last().expect("[unreachable] checked above");
+                let antecedent = match hyps {
+                    [hyp] => hyp.clone(),
+                    _ => Expr::new_op(Op::And, hyps.to_vec())
+                        .expect("[unreachable] conjunction of boolean expressions"),
+                };
+                res.push(antecedent);
+                todo.push(consequent)
+            }
+            _ => (),
+        }
+    }
+    res
+}
+
+/// Looks for the antecedents of a candidate that no run reaches within `depth` steps.
+///
+/// # Errors
+///
+/// - when the candidate does not exist, or
+/// - when the solver fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::vacuity, parse, rsmt2::SmtConf};
+/// let sys = parse::trans(
+///     "svars { cnt: int, inc: bool } init { cnt = 0 } \
+///     trans { 'cnt = (if inc { cnt + 1 } else { cnt }) } \
+///     candidates { \"negative\": cnt < 0 ⇒ inc, \"inc\": inc ⇒ cnt ≥ 0 }",
+/// )
+/// .unwrap();
+/// let vacuities = vacuity::check(&sys, "negative", 5, SmtConf::z3("z3")).unwrap();
+/// assert_eq!(
+///     vacuities[0].to_string(),
+///     "candidate `negative` might hold vacuously, \
+///     antecedent `(< cnt 0)` does not hold within 5 steps",
+/// );
+/// assert!(vacuity::check(&sys, "inc", 5, SmtConf::z3("z3")).unwrap().is_empty());
+/// ```
+pub fn check(sys: &Sys, candidate: &str, depth: Unroll, conf: SmtConf) -> Res<Vec<Vacuity>> {
+    let expr = match sys.po_s().get(candidate) {
+        Some(expr) => expr,
+        None => bail!("unknown candidate `{}`", candidate),
+    };
+    let mut res = vec![];
+    for antecedent in antecedents(expr) {
+        let witness = super::reach::reachable(sys, &antecedent, depth, conf.clone())
+            .chain_err(|| format!("while checking the vacuity of `{}`", candidate))?;
+        if witness.is_none() {
+            res.push(Vacuity {
+                candidate: candidate.into(),
+                antecedent,
+                depth,
+            })
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,367 @@
+//! Incremental re-checking of a model file.
+//!
+//! A [`Watcher`] polls a file containing a system. When the file changes, it is re-parsed and only
+//! the candidates *affected* by the change are re-checked, the others keep their previous verdict.
+//! Events are streamed to a callback, see [`Event`]. Function [`watch`] runs a watcher until the
+//! callback asks to stop.
+//!
+//! A candidate is affected when its [fingerprint](fingerprints) changes. The fingerprint of a
+//! candidate is a hash of its definition, of the declarations, initial and transition predicates
+//! of the system, and of the candidates mentioning variables of its cone of influence (see
+//! [`Coi`]), which can act as lemmas in its proof. Re-checking a candidate also re-checks these
+//! lemmas.
+//!
+//! The constraints outside of the cone of a candidate are part of its fingerprint: they can make
+//! the initial predicate unsatisfiable or block transitions, which changes the verdict of the
+//! candidate. Editing the initial or transition predicate thus re-checks all the candidates, while
+//! editing a candidate only re-checks the candidates it can act as a lemma for.
+
+crate::prelude!();
+
+use std::time::Duration;
+
+use super::{
+    cache::fnv,
+    driver::{run, Config, Outcome, OutcomeDiff, Verdict},
+    preprocess::{conjuncts, idents, Coi, Pass},
+};
+use crate::meta::Provenance;
+use trans::Sys;
+
+/// Delay between two polls of the watched file in [`watch`].
+pub const POLL_DELAY: Duration = Duration::from_millis(250);
+
+/// Dependencies of a candidate.
+struct Deps {
+    /// Fingerprint of the candidate.
+    fingerprint: u64,
+    /// Other candidates mentioning variables of the cone of the candidate.
+    lemmas: Set<String>,
+}
+
+/// Computes the dependencies of all the candidates of a system.
+fn dependencies(sys: &Sys) -> Res<Map<String, Deps>> {
+    let po_idents: Map<&String, Set<String>> = sys
+        .po_s()
+        .iter()
+        .map(|(name, po)| (name, idents(po)))
+        .collect();
+
+    let decls: Vec<String> = sys
+        .decls()
+        .all()
+        .map(|var| format!("{}: {}", var.id(), var.typ()))
+        .collect();
+
+    let mut res = Map::new();
+    for (name, po) in sys.po_s() {
+        let sliced = Coi.run(&sys.restrict(|that| that == name))?;
+        let mut cone = idents(po);
+        cone.extend(idents(sliced.init()));
+        cone.extend(idents(sliced.trans()));
+
+        let lemmas: Set<String> = po_idents
+            .iter()
+            .filter(|(that, vars)| that.as_str() != name.as_str() && !vars.is_disjoint(&cone))
+            .map(|(that, _)| that.to_string())
+            .collect();
+
+        let mut strs = vec![name.clone(), po.to_string()];
+        strs.extend(decls.iter().cloned());
+        // All conjuncts, not only the ones in the cone, see the module-level documentation.
+        strs.extend(conjuncts(sys.init()).iter().map(|e| e.to_string()));
+        strs.extend(conjuncts(sys.trans()).iter().map(|e| e.to_string()));
+        for lemma in lemmas.iter() {
+            strs.push(lemma.clone());
+            strs.push(sys.po_s()[lemma].to_string());
+        }
+
+        let fingerprint = fnv(strs.iter().map(|s| s as &str));
+        let _ = res.insert(
+            name.clone(),
+            Deps {
+                fingerprint,
+                lemmas,
+            },
+        );
+    }
+    Ok(res)
+}
+
+/// Fingerprints of the candidates of a system, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::watch, parse};
+/// let sys = parse::trans(
+///     "svars { x y: int } init { x = 0, y = 0 } trans { 'x = x + 1, 'y = y + 1 } \
+///     candidates { \"x\": x ≥ 0, \"y\": y ≥ 0 }",
+/// )
+/// .unwrap();
+/// let before = watch::fingerprints(&sys).unwrap();
+///
+/// let sys = parse::trans(
+///     "svars { x y: int } init { x = 0, y = 0 } trans { 'x = x + 1, 'y = y + 1 } \
+///     candidates { \"x\": x ≥ 0, \"y\": y ≥ 1 }",
+/// )
+/// .unwrap();
+/// let after = watch::fingerprints(&sys).unwrap();
+/// assert_eq!(before["x"], after["x"]);
+/// assert_ne!(before["y"], after["y"]);
+///
+/// // `'y = y + 2` is outside the cone of `x`, but it could block transitions.
+/// let sys = parse::trans(
+///     "svars { x y: int } init { x = 0, y = 0 } trans { 'x = x + 1, 'y = y + 2 } \
+///     candidates { \"x\": x ≥ 0, \"y\": y ≥ 1 }",
+/// )
+/// .unwrap();
+/// let edited = watch::fingerprints(&sys).unwrap();
+/// assert_ne!(after["x"], edited["x"]);
+/// assert_ne!(after["y"], edited["y"]);
+/// ```
+pub fn fingerprints(sys: &Sys) -> Res<Map<String, u64>> {
+    Ok(dependencies(sys)?
+        .into_iter()
+        .map(|(name, deps)| (name, deps.fingerprint))
+        .collect())
+}
+
+/// Events produced by a [`Watcher`].
+#[derive(Debug)]
+pub enum Event {
+    /// The file changed and was parsed successfully.
+    Changed {
+        /// Candidates about to be re-checked.
+        recheck: Set<String>,
+        /// Candidates that do not exist anymore.
+        removed: Set<String>,
+    },
+    /// Verdict of a re-checked candidate.
+    Verdict {
+        /// Candidate.
+        candidate: String,
+        /// Its new verdict.
+        verdict: Verdict,
+    },
+    /// Changes of the verdicts since the last version of the file checked successfully, right
+    /// before [`Event::Done`]. Candidates of the first version of the file are all new.
+    Diff(OutcomeDiff),
+    /// Re-checking is over, outcome for all the candidates of the current version of the file.
+    Done(Outcome),
+    /// Reading, parsing or checking the file failed, previous verdicts are kept.
+    Error(ErrorChain),
+}
+
+/// Watches a file and incrementally re-checks its candidates.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::{self, watch::{Event, Watcher}}, rsmt2::SmtConf};
+/// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(10);
+/// let mut watcher = Watcher::new("model.mnk", config);
+/// loop {
+///     watcher.update(&mut |event| match event {
+///         Event::Verdict { candidate, verdict } => println!("{}: {}", candidate, verdict),
+///         Event::Diff(diff) => print!("{}", diff),
+///         Event::Error(e) => println!("error: {}", e),
+///         _ => (),
+///     });
+///     std::thread::sleep(check::watch::POLL_DELAY);
+/// }
+/// ```
+pub struct Watcher {
+    /// Watched file.
+    path: PathBuf,
+    /// Check configuration.
+    config: Config,
+    /// Content of the file when it was last read.
+    content: Option<String>,
+    /// Fingerprints of the candidates of the last version of the file checked successfully.
+    fingerprints: Map<String, u64>,
+    /// Outcome for the last version of the file checked successfully.
+    outcome: Outcome,
+}
+impl Watcher {
+    /// Constructor, does not read the file.
+    pub fn new(path: impl Into<PathBuf>, config: Config) -> Self {
+        Self {
+            path: path.into(),
+            config,
+            content: None,
+            fingerprints: Map::new(),
+            outcome: Outcome::new(),
+        }
+    }
+
+    /// Watched file.
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+    /// Outcome for the last version of the file checked successfully.
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+
+    /// Reads the file and re-checks the affected candidates if it changed.
+    ///
+    /// Returns `true` if the file changed. Errors are reported as [`Event::Error`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{self, filter::Filter, watch::{Event, Watcher}}, rsmt2::SmtConf};
+    /// # let path = std::env::temp_dir().join(format!("mikino_watch_{}.mnk", std::process::id()));
+    /// let write = |trans: &str, y: &str| {
+    ///     let model = format!(
+    ///         "svars {{ x y: int }} init {{ x = 0, y = 0 }} trans {{ {} }} \
+    ///         candidates {{ \"x\": x ≥ 0, \"y\": {} }}",
+    ///         trans, y,
+    ///     );
+    ///     std::fs::write(&path, model).unwrap()
+    /// };
+    /// // Let's not actually check anything in this example, we are only interested in which
+    /// // candidates need re-checking.
+    /// let config = check::Config::new(SmtConf::z3("z3")).filter(Filter::new().exclude_name("*"));
+    /// let mut watcher = Watcher::new(&path, config);
+    /// let mut rechecks = vec![];
+    /// let mut callback = |event| {
+    ///     if let Event::Changed { recheck, .. } = event {
+    ///         rechecks.push(recheck.into_iter().collect::<Vec<_>>())
+    ///     }
+    /// };
+    ///
+    /// write("'x = x + 1, 'y = y + 1", "y ≥ 0");
+    /// assert!(watcher.update(&mut callback));
+    /// assert!(!watcher.update(&mut callback));
+    /// // Only `y` is affected by an edit of its definition.
+    /// write("'x = x + 1, 'y = y + 1", "y ≥ 1");
+    /// assert!(watcher.update(&mut callback));
+    /// // All candidates are affected by an edit of the transition predicate.
+    /// write("'x = x + 1, 'y = y + 2", "y ≥ 1");
+    /// assert!(watcher.update(&mut callback));
+    ///
+    /// assert_eq!(rechecks, vec![vec!["x", "y"], vec!["y"], vec!["x", "y"]]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn update(&mut self, callback: &mut dyn FnMut(Event)) -> bool {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) => {
+                let e = ErrorChain::from(e)
+                    .chain_err(|| format!("while reading `{}`", self.path.display()));
+                callback(Event::Error(e));
+                return false;
+            }
+        };
+        if self.content.as_ref() == Some(&content) {
+            return false;
+        }
+        let res = self.recheck(&content, callback);
+        self.content = Some(content);
+        if let Err(e) = res {
+            callback(Event::Error(e))
+        }
+        true
+    }
+
+    /// Re-checks the candidates affected by a new version of the file.
+    fn recheck(&mut self, content: &str, callback: &mut dyn FnMut(Event)) -> Res<()> {
+        let sys = parse::trans(content)
+            .chain_err(|| format!("while parsing `{}`", self.path.display()))?;
+        let deps = dependencies(&sys)?;
+
+        let mut recheck = Set::new();
+        for (name, deps) in deps.iter() {
+            if self.fingerprints.get(name) != Some(&deps.fingerprint) {
+                let _ = recheck.insert(name.clone());
+                recheck.extend(deps.lemmas.iter().cloned());
+            }
+        }
+        let removed: Set<String> = self
+            .fingerprints
+            .keys()
+            .filter(|name| !deps.contains_key(*name))
+            .cloned()
+            .collect();
+        callback(Event::Changed {
+            recheck: recheck.clone(),
+            removed,
+        });
+
+        let mut outcome = if recheck.is_empty() {
+            Outcome::new()
+        } else {
+            run(&sys.restrict(|name| recheck.contains(name)), &self.config)?
+        };
+        for (name, verdict) in outcome.verdicts.iter() {
+            callback(Event::Verdict {
+                candidate: name.clone(),
+                verdict: verdict.clone(),
+            })
+        }
+        let previous = std::mem::take(&mut self.outcome);
+        for (name, verdict) in previous.verdicts.iter() {
+            if deps.contains_key(name) && !recheck.contains(name) {
+                let _ = outcome.verdicts.insert(name.clone(), verdict.clone());
+            }
+        }
+        outcome.warnings = sys
+            .warnings()
+            .iter()
+            .filter(|name| outcome.verdicts.contains_key(*name))
+            .cloned()
+            .collect();
+        outcome.labels = sys
+            .labels()
+            .iter()
+            .filter(|(name, _)| outcome.verdicts.contains_key(*name))
+            .map(|(name, label)| (name.clone(), label.clone()))
+            .collect();
+        outcome.provenance = Some(
+            Provenance::of_config(&self.config).input(self.path.display().to_string(), content),
+        );
+
+        self.fingerprints = deps
+            .into_iter()
+            .map(|(name, deps)| (name, deps.fingerprint))
+            .collect();
+        callback(Event::Diff(previous.diff(&outcome)));
+        self.outcome = outcome;
+        callback(Event::Done(self.outcome.clone()));
+        Ok(())
+    }
+}
+
+/// Watches a file and re-checks its candidates on each change, see [`Watcher`].
+///
+/// The file is polled every [`POLL_DELAY`]. The callback returns `false` to stop watching, in which
+/// case this function returns the outcome for the last version of the file checked successfully.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{check::{self, watch::Event}, rsmt2::SmtConf};
+/// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(10);
+/// let outcome = check::watch("model.mnk", config, |event| match event {
+///     Event::Done(outcome) => !outcome.is_success(),
+///     _ => true,
+/// });
+/// assert!(outcome.is_success());
+/// ```
+pub fn watch(
+    path: impl Into<PathBuf>,
+    config: Config,
+    mut callback: impl FnMut(Event) -> bool,
+) -> Outcome {
+    let mut watcher = Watcher::new(path, config);
+    let mut keep_going = true;
+    loop {
+        let _ = watcher.update(&mut |event| keep_going = keep_going && callback(event));
+        if !keep_going {
+            return watcher.outcome;
+        }
+        std::thread::sleep(POLL_DELAY)
+    }
+}
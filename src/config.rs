@@ -0,0 +1,816 @@
+//! Global configuration, with profiles.
+//!
+//! A [`Config`] gathers everything that is usually scattered across function arguments: the
+//! solver, the budgets, the engines, the output format and styling, and the seed of generated
+//! inputs. [`Config::check_config`] and [`Config::session`] turn it into what [`check::run`] and
+//! [`script::Session`] expect; the output format and styling are for front-ends, since mikino's
+//! API does not print anything.
+//!
+//! Configurations can be loaded from a TOML file. Top-level keys configure the default profile,
+//! `[profile.<name>]` tables override them for profile `<name>`. Lint levels go in a `[lints]`
+//! table, or `[profile.<name>.lints]`.
+//!
+//! ```toml
+//! solver = "z3"
+//! bmc_max = 10
+//! engines = ["induction", "bmc"]
+//!
+//! [lints]
+//! unused_variable = "deny"
+//!
+//! [profile.ci]
+//! budget = 60
+//! jobs = 4
+//! format = "json"
+//! color = false
+//! ```
+//!
+//! Only the subset of TOML above is supported: strings, integers, decimals, booleans and arrays,
+//! table headers and comments.
+//!
+//! Later settings override earlier ones: defaults, then the top-level keys, then the keys of the
+//! profile, then programmatic overrides with the builder methods or [`Config::set`].
+//!
+//! | key          | value                                         | default                  |
+//! |:-------------|:----------------------------------------------|:-------------------------|
+//! | `backend`    | `"z3"`, `"cvc5"` or `"yices2"`                | `"z3"`                   |
+//! | `solver`     | solver command                                | default of the backend   |
+//! | `tee`        | directory where to tee solver interactions    | none                     |
+//! | `bmc_max`    | maximum BMC depth                             | none, no BMC             |
+//! | `unrolling`  | `"forward"`, `"backward"` or `"bidirectional"`| `"forward"`              |
+//! | `jobs`       | candidates checked at the same time           | `1`                      |
+//! | `budget`     | global time budget in seconds                 | none                     |
+//! | `query_timeout` | time limit of each solver query in seconds | none                     |
+//! | `memory_limit` | memory limit of the solvers in megabytes    | none                     |
+//! | `cache`      | cache directory for proved candidates         | none                     |
+//! | `engines`    | engine names, see [`Config::ENGINES`]         | `["induction", "bmc"]`   |
+//! | `portfolio`  | true to run the engines at the same time      | `false`                  |
+//! | `alt_backends` | backends the engines also run with in portfolio mode | `[]`            |
+//! | `minimize_cexs` | true to minimize counterexamples             | `false`                  |
+//! | `check_init` | true to fail on an unsatisfiable `init`       | `false`                  |
+//! | `explain`    | true to explain each solver query             | `false`                  |
+//! | `certificate` | file where to write a proof certificate      | none                     |
+//! | `format`     | `"text"` or `"json"`                          | `"text"`                 |
+//! | `color`      | true for styled output                        | `true`                   |
+//! | `seed`       | seed of generated inputs                      | none                     |
+//! | `lints.<l>`  | level of lint `<l>`, see [`Lints::set_str`]   | [`Lint::default_level`]  |
+//!
+//! Setting the backend also sets the solver command to the default command of the new backend,
+//! unless the command is not the default command of the previous backend. See
+//! [`solver::Backend`] for the backends.
+//!
+//! [`Lint::default_level`]: crate::lint::Lint::default_level
+
+prelude!();
+
+use std::path::Path;
+
+use crate::{lint::Lints, solver};
+use check::{
+    engine::{Bmc, Engines, Induction},
+    interpolation::Interpolation,
+    pdr::Pdr,
+    Unrolling,
+};
+use script::Session;
+use solver::Backend;
+
+/// Output format, see [`Config::format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable text.
+    Text,
+    /// JSON, see for instance [`check::Outcome::to_json`].
+    Json,
+}
+impl Format {
+    /// Format from its name, `text` or `json`.
+    pub fn of_str(s: impl AsRef<str>) -> Option<Self> {
+        match s.as_ref() {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Format {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Text => "text".fmt(fmt),
+            Self::Json => "json".fmt(fmt),
+        }
+    }
+}
+
+/// A value in a configuration file.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    /// Boolean.
+    Bool(bool),
+    /// Integer.
+    Int(i64),
+    /// Decimal.
+    Float(f64),
+    /// String.
+    Str(String),
+    /// Array.
+    Array(Vec<Value>),
+}
+impl fmt::Display for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bool(b) => b.fmt(fmt),
+            Self::Int(i) => i.fmt(fmt),
+            Self::Float(f) => f.fmt(fmt),
+            Self::Str(s) => write!(fmt, "{:?}", s),
+            Self::Array(vals) => {
+                write!(fmt, "[")?;
+                for (idx, val) in vals.iter().enumerate() {
+                    if idx > 0 {
+                        write!(fmt, ", ")?
+                    }
+                    val.fmt(fmt)?
+                }
+                write!(fmt, "]")
+            }
+        }
+    }
+}
+
+/// A line of a configuration file.
+enum Line {
+    /// Table header, dotted path.
+    Table(Vec<String>),
+    /// Key-value pair, dotted key.
+    Entry(Vec<String>, Value),
+}
+
+peg::parser! {
+    /// The subset of TOML used by configuration files.
+    grammar toml() for str {
+        rule ws() = quiet! { [' ' | '\t']* }
+        rule cmt() = "#" [^ '\n']*
+        rule nl() = ws() cmt()? ("\r"? "\n" / ![_])
+        rule blank() = quiet! { (ws() cmt()? "\r"? "\n")* }
+
+        rule bare() -> String
+        = s:$(['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-']+) { s.into() }
+        / expected!("key")
+
+        rule key() -> String
+        = bare() / string()
+
+        rule path() -> Vec<String>
+        = key() ++ (ws() "." ws())
+
+        rule string() -> String
+        = "\"" chars:(
+            "\\\\" { '\\' }
+            / "\\\"" { '"' }
+            / "\\n" { '\n' }
+            / "\\t" { '\t' }
+            / c:[^ '"' | '\\' | '\n'] { c }
+        )* "\"" { chars.into_iter().collect() }
+        / expected!("string")
+
+        rule number() -> Value
+        = n:$("-"? ['0'..='9']+ "." ['0'..='9']+) {? n.parse().map(Value::Float).or(Err("decimal")) }
+        / n:$(['+' | '-']? ['0'..='9'] ['0'..='9' | '_']*) {?
+            n.replace('_', "").parse().map(Value::Int).or(Err("integer"))
+        }
+
+        rule sep() = quiet! { ([' ' | '\t' | '\r' | '\n'] / cmt())* }
+
+        pub rule value() -> Value
+        = quiet! {
+            "true" { Value::Bool(true) }
+            / "false" { Value::Bool(false) }
+            / s:string() { Value::Str(s) }
+            / number()
+            / "[" sep() vals:(value() ** (sep() "," sep())) sep() ","? sep() "]" {
+                Value::Array(vals)
+            }
+        }
+        / expected!("value")
+
+        rule line() -> Line
+        = ws() "[" ws() path:path() ws() "]" nl() { Line::Table(path) }
+        / ws() key:path() ws() "=" ws() val:value() nl() { Line::Entry(key, val) }
+
+        pub rule lines() -> Vec<Line>
+        = blank() lines:(line() ** blank()) blank() ws() cmt()? { lines }
+    }
+}
+
+/// Parses the TOML value of a key.
+fn parse_value(key: &str, txt: &str) -> Res<Value> {
+    toml::value(txt.trim()).map_err(|e| {
+        format!(
+            "illegal value `{}` for `{}`, expected {} at column {}",
+            txt, key, e.expected, e.location.column
+        )
+        .into()
+    })
+}
+
+/// Global configuration, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::config::{Config, Format};
+/// let toml = r#"
+/// bmc_max = 10
+/// engines = ["induction", "bmc"]
+///
+/// [lints]
+/// unused_variable = "deny"
+///
+/// [profile.ci]
+/// budget = 1.5 # seconds
+/// query_timeout = 0.5
+/// format = "json"
+/// portfolio = true
+/// alt_backends = ["cvc5"]
+/// minimize_cexs = true
+/// check_init = true
+/// explain = true
+/// certificate = "proof.cert"
+/// "#;
+/// let config = Config::of_toml(toml, None).unwrap();
+/// assert_eq!(config.bmc_max, Some(10));
+/// assert_eq!(config.format, Format::Text);
+///
+/// let config = Config::of_toml(toml, Some("ci")).unwrap().jobs(4);
+/// assert_eq!(config.bmc_max, Some(10));
+/// assert_eq!(config.budget, Some(std::time::Duration::from_millis(1500)));
+/// assert_eq!(config.format, Format::Json);
+///
+/// let check = config.check_config().unwrap();
+/// assert_eq!(check.jobs, 4);
+/// assert!(check.engines.is_standard());
+/// assert!(check.portfolio);
+/// assert_eq!(check.query_timeout, Some(std::time::Duration::from_millis(500)));
+/// assert!(check.limited_conf().unwrap().get_options().iter().any(|opt| opt == "-t:500"));
+/// assert_eq!(check.alt_confs[0].get_cmd(), "cvc5");
+/// assert!(check.minimize_cexs);
+/// assert!(check.check_init);
+/// assert!(check.explain);
+/// assert_eq!(check.certificate, Some("proof.cert".into()));
+///
+/// let err = Config::of_toml(toml, Some("release")).unwrap_err();
+/// assert_eq!(err.to_string(), "unknown profile `release`, available profiles: ci");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Solver backend.
+    pub backend: &'static dyn Backend,
+    /// Solver command.
+    pub solver: String,
+    /// Directory where to tee the solver interactions, if any.
+    pub tee: Option<PathBuf>,
+    /// Maximum BMC depth, `None` deactivates BMC.
+    pub bmc_max: Option<Unroll>,
+    /// BMC unrolling strategy.
+    pub unrolling: Unrolling,
+    /// Maximum number of candidates checked at the same time, see [`check::Config::jobs`].
+    pub jobs: usize,
+    /// Global time budget, `None` for no budget.
+    pub budget: Option<time::Duration>,
+    /// Time limit of each solver query, `None` for no limit.
+    pub query_timeout: Option<time::Duration>,
+    /// Memory limit of the solvers in megabytes, `None` for no limit.
+    pub memory_limit: Option<u64>,
+    /// Cache directory for proved candidates, if any.
+    pub cache: Option<PathBuf>,
+    /// Engine names, in order, see [`Config::ENGINES`].
+    pub engines: Vec<String>,
+    /// True if the engines run at the same time, see [`check::Config::portfolio`].
+    pub portfolio: bool,
+    /// Backends the engines also run with in portfolio mode, with their default command.
+    pub alt_backends: Vec<&'static dyn Backend>,
+    /// True if counterexamples are minimized, see [`check::Config::minimize_cexs`].
+    pub minimize_cexs: bool,
+    /// True if the initial predicate is checked, see [`check::Config::check_init`].
+    pub check_init: bool,
+    /// True if the engines explain their queries, see [`check::Config::explain`].
+    pub explain: bool,
+    /// Certificate file, see [`check::Config::certificate`].
+    pub certificate: Option<PathBuf>,
+    /// Output format.
+    pub format: Format,
+    /// True if the output should be styled.
+    pub color: bool,
+    /// Seed of generated inputs, if any.
+    pub seed: Option<u64>,
+    /// Lint levels.
+    pub lints: Lints,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Config {
+    /// Names of the engines.
+    pub const ENGINES: [&'static str; 4] =
+        [Induction::NAME, Bmc::NAME, Pdr::NAME, Interpolation::NAME];
+
+    /// Constructor, see the [module-level documentation](self) for the defaults.
+    pub fn new() -> Self {
+        Self {
+            backend: &solver::Z3,
+            solver: solver::Z3.default_cmd().into(),
+            tee: None,
+            bmc_max: None,
+            unrolling: Unrolling::Forward,
+            jobs: 1,
+            budget: None,
+            query_timeout: None,
+            memory_limit: None,
+            cache: None,
+            engines: vec![Induction::NAME.into(), Bmc::NAME.into()],
+            portfolio: false,
+            alt_backends: vec![],
+            minimize_cexs: false,
+            check_init: false,
+            explain: false,
+            certificate: None,
+            format: Format::Text,
+            color: true,
+            seed: None,
+            lints: Lints::new(),
+        }
+    }
+
+    /// Loads a configuration file, with some profile if any.
+    ///
+    /// # Errors
+    ///
+    /// - when the file cannot be read, or
+    /// - in the cases of [`Self::of_toml`].
+    pub fn load(path: impl AsRef<Path>, profile: Option<&str>) -> Res<Self> {
+        let path = path.as_ref();
+        let txt = std::fs::read_to_string(path)
+            .chain_err(|| format!("while reading `{}`", path.display()))?;
+        Self::of_toml(&txt, profile).chain_err(|| format!("while loading `{}`", path.display()))
+    }
+
+    /// Configuration from the text of a configuration file, with some profile if any.
+    ///
+    /// # Errors
+    ///
+    /// - when the text does not parse,
+    /// - when a key is unknown, appears twice, or has an illegal value, or
+    /// - when `profile` is not a profile of the file.
+    pub fn of_toml(txt: &str, profile: Option<&str>) -> Res<Self> {
+        let lines = toml::lines(txt).map_err(|e| {
+            format!(
+                "TOML parse error at {}:{}, expected {}",
+                e.location.line, e.location.column, e.expected
+            )
+        })?;
+
+        let mut base: Vec<(String, Value)> = vec![];
+        let mut profiles: Map<String, Vec<(String, Value)>> = Map::new();
+        let mut seen: Set<Vec<String>> = Set::new();
+        let mut table: Vec<String> = vec![];
+        for line in lines {
+            match line {
+                Line::Table(path) => {
+                    if path.len() == 1 && path[0] == "profile" {
+                        bail!("expected `[profile.<name>]`, found `[profile]`")
+                    }
+                    table = path
+                }
+                Line::Entry(key, val) => {
+                    let mut path = table.clone();
+                    path.extend(key);
+                    if !seen.insert(path.clone()) {
+                        bail!("key `{}` appears twice", path.join("."))
+                    }
+                    match path.split_first() {
+                        Some((head, tail)) if head == "profile" && tail.len() > 1 => profiles
+                            .entry(tail[0].clone())
+                            .or_default()
+                            .push((tail[1..].join("."), val)),
+                        _ => base.push((path.join("."), val)),
+                    }
+                }
+            }
+        }
+
+        let mut entries = base;
+        if let Some(profile) = profile {
+            match profiles.remove(profile) {
+                Some(overrides) => entries.extend(overrides),
+                None => {
+                    let available: Vec<&str> = profiles.keys().map(|s| s as &str).collect();
+                    bail!(
+                        "unknown profile `{}`, available profiles: {}",
+                        profile,
+                        if available.is_empty() {
+                            "none".into()
+                        } else {
+                            available.join(", ")
+                        }
+                    )
+                }
+            }
+        }
+
+        let mut config = Self::new();
+        for (key, val) in entries {
+            config.set_value(&key, &val)?
+        }
+        Ok(config)
+    }
+
+    /// Sets a key to a value written in TOML, see the [module-level documentation](self).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{config::Config, lint::{Level, Lint}};
+    /// let mut config = Config::new();
+    /// config.set("engines", r#"["bmc"]"#).unwrap();
+    /// config.set("lints.vacuous_candidate", r#""allow""#).unwrap();
+    /// assert_eq!(config.engines, vec!["bmc".to_string()]);
+    /// assert_eq!(config.lints.level(Lint::VacuousCandidate), Level::Allow);
+    ///
+    /// let err = config.set("jobs", "many").unwrap_err();
+    /// assert_eq!(err.to_string(), "illegal value `many` for `jobs`, expected value at column 1");
+    /// let err = config.set("jobs", "-1").unwrap_err();
+    /// assert_eq!(err.to_string(), "illegal value `-1` for `jobs`, expected natural number");
+    /// let err = config.set("engines", r#"["kind2"]"#).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "unknown engine `kind2`, expected induction or bmc or pdr or interpolation"
+    /// );
+    /// ```
+    pub fn set(&mut self, key: &str, val: &str) -> Res<()> {
+        let val = parse_value(key, val)?;
+        self.set_value(key, &val)
+    }
+
+    /// Sets a key to a value.
+    fn set_value(&mut self, key: &str, val: &Value) -> Res<()> {
+        macro_rules! expected {
+            ($desc:expr) => {
+                bail!("illegal value `{}` for `{}`, expected {}", val, key, $desc)
+            };
+        }
+        let string = || match val {
+            Value::Str(s) => Some(s.clone()),
+            _ => None,
+        };
+        let uint = || match val {
+            Value::Int(i) if *i >= 0 => Some(*i as u64),
+            _ => None,
+        };
+        let secs = || match val {
+            Value::Int(secs) if *secs >= 0 => Some(time::Duration::from_secs(*secs as u64)),
+            Value::Float(secs) if *secs >= 0.0 && secs.is_finite() => {
+                Some(time::Duration::from_secs_f64(*secs))
+            }
+            _ => None,
+        };
+
+        if let Some(lint) = key.strip_prefix("lints.") {
+            match string() {
+                Some(level) => self.lints = self.lints.clone().set_str(lint, level)?,
+                None => expected!("lint level"),
+            }
+            return Ok(());
+        }
+
+        match key {
+            "backend" => match string().and_then(solver::backend) {
+                Some(backend) => *self = self.clone().backend(backend),
+                None => expected!(solver::BACKENDS
+                    .iter()
+                    .map(|backend| format!("`\"{}\"`", backend.name()))
+                    .collect::<Vec<_>>()
+                    .join(", ")),
+            },
+            "solver" => match string() {
+                Some(solver) => self.solver = solver,
+                None => expected!("solver command"),
+            },
+            "tee" => match string() {
+                Some(tee) => self.tee = Some(tee.into()),
+                None => expected!("directory"),
+            },
+            "bmc_max" => match uint() {
+                Some(bmc_max) => self.bmc_max = Some(bmc_max as Unroll),
+                None => expected!("natural number"),
+            },
+            "unrolling" => match string().as_deref() {
+                Some("forward") => self.unrolling = Unrolling::Forward,
+                Some("backward") => self.unrolling = Unrolling::Backward,
+                Some("bidirectional") => self.unrolling = Unrolling::Bidirectional,
+                _ => expected!("`\"forward\"`, `\"backward\"` or `\"bidirectional\"`"),
+            },
+            "jobs" => match uint() {
+                Some(jobs) => self.jobs = jobs as usize,
+                None => expected!("natural number"),
+            },
+            "budget" => match secs() {
+                Some(budget) => self.budget = Some(budget),
+                None => expected!("positive number of seconds"),
+            },
+            "query_timeout" => match secs() {
+                Some(timeout) => self.query_timeout = Some(timeout),
+                None => expected!("positive number of seconds"),
+            },
+            "memory_limit" => match uint() {
+                Some(mb) => self.memory_limit = Some(mb),
+                None => expected!("natural number of megabytes"),
+            },
+            "cache" => match string() {
+                Some(cache) => self.cache = Some(cache.into()),
+                None => expected!("directory"),
+            },
+            "engines" => {
+                let names = match val {
+                    Value::Array(vals) => vals,
+                    _ => expected!("array of engine names"),
+                };
+                let mut engines = Vec::with_capacity(names.len());
+                for name in names {
+                    match name {
+                        Value::Str(name) if Self::ENGINES.contains(&name.as_str()) => {
+                            engines.push(name.clone())
+                        }
+                        Value::Str(name) => bail!(
+                            "unknown engine `{}`, expected {}",
+                            name,
+                            Self::ENGINES.join(" or ")
+                        ),
+                        _ => expected!("array of engine names"),
+                    }
+                }
+                self.engines = engines
+            }
+            "portfolio" => match val {
+                Value::Bool(portfolio) => self.portfolio = *portfolio,
+                _ => expected!("boolean"),
+            },
+            "alt_backends" => {
+                let names = match val {
+                    Value::Array(vals) => vals,
+                    _ => expected!("array of backend names"),
+                };
+                let mut backends = Vec::with_capacity(names.len());
+                for name in names {
+                    match name {
+                        Value::Str(name) => match solver::backend(name) {
+                            Some(backend) => backends.push(backend),
+                            None => bail!(
+                                "unknown backend `{}`, expected {}",
+                                name,
+                                solver::BACKENDS
+                                    .iter()
+                                    .map(|backend| backend.name())
+                                    .collect::<Vec<_>>()
+                                    .join(" or ")
+                            ),
+                        },
+                        _ => expected!("array of backend names"),
+                    }
+                }
+                self.alt_backends = backends
+            }
+            "minimize_cexs" => match val {
+                Value::Bool(minimize) => self.minimize_cexs = *minimize,
+                _ => expected!("boolean"),
+            },
+            "check_init" => match val {
+                Value::Bool(check_init) => self.check_init = *check_init,
+                _ => expected!("boolean"),
+            },
+            "explain" => match val {
+                Value::Bool(explain) => self.explain = *explain,
+                _ => expected!("boolean"),
+            },
+            "certificate" => match string() {
+                Some(certificate) => self.certificate = Some(certificate.into()),
+                None => expected!("file"),
+            },
+            "format" => match string().and_then(Format::of_str) {
+                Some(format) => self.format = format,
+                None => expected!("`\"text\"` or `\"json\"`"),
+            },
+            "color" => match val {
+                Value::Bool(color) => self.color = *color,
+                _ => expected!("boolean"),
+            },
+            "seed" => match uint() {
+                Some(seed) => self.seed = Some(seed),
+                None => expected!("natural number"),
+            },
+            _ => bail!("unknown configuration key `{}`", key),
+        }
+        Ok(())
+    }
+
+    /// Sets the solver backend.
+    ///
+    /// Also sets the solver command to the default command of `backend`, if the current command
+    /// is the default command of the current backend.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{config::Config, solver};
+    /// let config = Config::new().backend(&solver::Cvc5);
+    /// assert_eq!(config.solver, "cvc5");
+    /// assert_eq!(solver::backend_of(&config.smt_conf()).name(), "cvc5");
+    ///
+    /// let config = Config::new().solver("/opt/z3").backend(&solver::Yices2);
+    /// assert_eq!(config.solver, "/opt/z3");
+    ///
+    /// let mut config = Config::new();
+    /// config.set("backend", "\"yices2\"").unwrap();
+    /// assert_eq!(config.solver, "yices-smt2");
+    /// let err = config.set("backend", "\"mathsat\"").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     r#"illegal value `"mathsat"` for `backend`, expected `"z3"`, `"cvc5"`, `"yices2"`"#,
+    /// );
+    /// ```
+    pub fn backend(mut self, backend: &'static dyn Backend) -> Self {
+        if self.solver == self.backend.default_cmd() {
+            self.solver = backend.default_cmd().into();
+        }
+        self.backend = backend;
+        self
+    }
+    /// Sets the solver command.
+    pub fn solver(mut self, solver: impl Into<String>) -> Self {
+        self.solver = solver.into();
+        self
+    }
+    /// Sets the tee directory.
+    pub fn tee(mut self, tee: impl Into<PathBuf>) -> Self {
+        self.tee = Some(tee.into());
+        self
+    }
+    /// Sets the maximum BMC depth.
+    pub fn bmc_max(mut self, bmc_max: Unroll) -> Self {
+        self.bmc_max = Some(bmc_max);
+        self
+    }
+    /// Sets the BMC unrolling strategy.
+    pub fn unrolling(mut self, unrolling: Unrolling) -> Self {
+        self.unrolling = unrolling;
+        self
+    }
+    /// Sets the maximum number of parallel jobs.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+    /// Sets the global time budget.
+    pub fn budget(mut self, budget: time::Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+    /// Sets the time limit of each solver query.
+    pub fn query_timeout(mut self, timeout: time::Duration) -> Self {
+        self.query_timeout = Some(timeout);
+        self
+    }
+    /// Sets the memory limit of the solvers, in megabytes.
+    pub fn memory_limit(mut self, mb: u64) -> Self {
+        self.memory_limit = Some(mb);
+        self
+    }
+    /// Sets the cache directory.
+    pub fn cache(mut self, cache: impl Into<PathBuf>) -> Self {
+        self.cache = Some(cache.into());
+        self
+    }
+    /// Sets the engine names.
+    pub fn engines(mut self, engines: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.engines = engines.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Sets portfolio mode.
+    pub fn portfolio(mut self, portfolio: bool) -> Self {
+        self.portfolio = portfolio;
+        self
+    }
+    /// Sets the backends the engines also run with in portfolio mode.
+    pub fn alt_backends(mut self, backends: Vec<&'static dyn Backend>) -> Self {
+        self.alt_backends = backends;
+        self
+    }
+    /// Sets whether counterexamples are minimized.
+    pub fn minimize_cexs(mut self, minimize: bool) -> Self {
+        self.minimize_cexs = minimize;
+        self
+    }
+    /// Sets whether the initial predicate is checked for satisfiability.
+    pub fn check_init(mut self, check_init: bool) -> Self {
+        self.check_init = check_init;
+        self
+    }
+    /// Sets whether the engines explain the queries they check.
+    pub fn explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+    /// Sets the certificate file.
+    pub fn certificate(mut self, certificate: impl Into<PathBuf>) -> Self {
+        self.certificate = Some(certificate.into());
+        self
+    }
+    /// Sets the output format.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+    /// Sets whether the output is styled.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+    /// Sets the seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Sets the lint levels.
+    pub fn lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// Solver configuration.
+    pub fn smt_conf(&self) -> SmtConf {
+        self.backend.conf(&self.solver)
+    }
+
+    /// Engine portfolio.
+    ///
+    /// # Errors
+    ///
+    /// - when an engine name is unknown.
+    pub fn engine_portfolio(&self) -> Res<Engines> {
+        let mut engines = Engines::new();
+        for name in self.engines.iter() {
+            match name.as_str() {
+                Induction::NAME => engines.push(Induction::new),
+                Bmc::NAME => engines.push(Bmc::new),
+                Pdr::NAME => engines.push(Pdr::new),
+                Interpolation::NAME => engines.push(Interpolation::new),
+                _ => bail!(
+                    "unknown engine `{}`, expected {}",
+                    name,
+                    Self::ENGINES.join(" or ")
+                ),
+            };
+        }
+        Ok(engines)
+    }
+
+    /// Check configuration for [`check::run`].
+    ///
+    /// # Errors
+    ///
+    /// - when an engine name is unknown.
+    pub fn check_config(&self) -> Res<check::Config> {
+        let mut config = check::Config::new(self.smt_conf())
+            .unrolling(self.unrolling)
+            .jobs(self.jobs)
+            .engines(self.engine_portfolio()?)
+            .lints(self.lints.clone());
+        config.tee = self.tee.clone();
+        config.bmc_max = self.bmc_max;
+        config.budget = self.budget;
+        config.query_timeout = self.query_timeout;
+        config.memory_limit = self.memory_limit;
+        config.cache = self.cache.clone();
+        config.portfolio = self.portfolio;
+        config.minimize_cexs = self.minimize_cexs;
+        config.check_init = self.check_init;
+        config.explain = self.explain;
+        config.certificate = self.certificate.clone();
+        config.alt_confs = self
+            .alt_backends
+            .iter()
+            .map(|backend| backend.conf(backend.default_cmd()))
+            .collect();
+        Ok(config)
+    }
+
+    /// Script session.
+    pub fn session(&self) -> Session {
+        let session = Session::new(self.smt_conf());
+        match self.tee.as_ref() {
+            Some(tee) => session.tee(tee),
+            None => session,
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! Symbol tables: scoped declarations with unique identifiers.
+//!
+//! A [`SymbolTable`] maps identifiers to [`Symbol`]s. Each declaration produces a fresh [`SymId`],
+//! so two declarations of the same identifier (in different scopes, or in different namespaces)
+//! are never confused. Lookups go from the innermost scope to the outermost one, and whether a
+//! declaration can shadow an existing one is decided by the table's [`Shadowing`] policy.
+//!
+//! Identifiers are [checked](check_ident) on declaration. In particular, `@` is illegal in
+//! identifiers as it separates identifiers from step indices at SMT-level: a variable called `x@1`
+//! would be indistinguishable from variable `x` at step `1`.
+//!
+//! Namespaces allow to [import](SymbolTable::import) the symbols of one table into another, for
+//! instance when composing systems. Namespaced identifiers have shape `<ns>.<ident>`, which cannot
+//! clash with user identifiers since `.` is illegal in those.
+//!
+//! ```rust
+//! # use mikino_api::{decls::{Shadowing, SymbolTable}, expr::Typ};
+//! let mut table = SymbolTable::new(Shadowing::Scoped);
+//! let outer = table.declare("cnt", Typ::Int).unwrap();
+//! table.push_scope();
+//! let inner = table.declare("cnt", Typ::Bool).unwrap();
+//! assert_ne!(outer, inner);
+//! assert_eq!(table.lookup("cnt"), Some(inner));
+//! // Shadowing is only legal across scopes.
+//! let err = table.declare("cnt", Typ::Rat).unwrap_err();
+//! assert_eq!(err.to_string(), "identifier `cnt` is already declared in this scope");
+//! table.pop_scope().unwrap();
+//! assert_eq!(table.lookup("cnt"), Some(outer));
+//! assert_eq!(table[outer].typ, Typ::Int);
+//!
+//! let err = table.declare("x@1", Typ::Int).unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "illegal identifier `x@1`, unexpected character `@`",
+//! );
+//! ```
+
+crate::prelude!();
+
+use expr::{display::Hint, Var};
+
+/// Separates namespaces from identifiers.
+pub const NS_SEP: char = '.';
+
+/// SMT-LIB reserved words, illegal as identifiers.
+pub const SMT_RESERVED: &[&str] = &[
+    "!",
+    "_",
+    "as",
+    "BINARY",
+    "DECIMAL",
+    "exists",
+    "forall",
+    "HEXADECIMAL",
+    "let",
+    "match",
+    "NUMERAL",
+    "par",
+    "STRING",
+];
+
+/// SMT-LIB command names and symbols predefined by the theories mikino relies on.
+///
+/// Identifiers can be one of these, they are [mangled](write_sym) at SMT-level.
+pub const SMT_BUILTINS: &[&str] = &[
+    "assert", "echo", "exit", "pop", "push", "reset", "true", "false", "not", "=>", "and", "or",
+    "xor", "=", "distinct", "ite", "Bool", "Int", "Real", "-", "+", "*", "/", "div", "mod", "abs",
+    "<=", "<", ">=", ">", "to_real", "to_int", "is_int", "BitVec", "bvand", "bvor", "bvxor",
+    "bvshl", "bvlshr", "Array", "select", "store",
+];
+
+/// Checks that an identifier is legal.
+///
+/// Legal identifiers are either
+///
+/// - *simple*: start with a letter or `_`, followed by letters, digits and `_`, or
+/// - *quoted*: `|...|` where `...` does not contain `|` nor `\`.
+///
+/// They cannot be keywords nor [SMT-LIB reserved words](SMT_RESERVED).
+///
+/// ```rust
+/// # use mikino_api::decls::check_ident;
+/// assert!(check_ident("cnt_1").is_ok());
+/// assert!(check_ident("|x @ y|").is_ok());
+/// assert_eq!(
+///     check_ident("let").unwrap_err().to_string(),
+///     "illegal identifier `let`, this is an SMT-LIB reserved word",
+/// );
+/// assert_eq!(
+///     check_ident("|x|y|").unwrap_err().to_string(),
+///     "illegal identifier `|x|y|`, quoted identifiers cannot contain `|` nor `\\`",
+/// );
+/// ```
+pub fn check_ident(ident: &str) -> Res<()> {
+    if let Some(quoted) = ident
+        .strip_prefix('|')
+        .and_then(|ident| ident.strip_suffix('|'))
+    {
+        if quoted.contains(['|', '\\']) {
+            bail!(
+                "illegal identifier `{}`, quoted identifiers cannot contain `|` nor `\\`",
+                ident
+            )
+        }
+        return Ok(());
+    }
+    let mut chars = ident.chars();
+    match chars.next() {
+        None => bail!("illegal empty identifier"),
+        Some(c) if c.is_ascii_digit() => {
+            bail!("illegal identifier `{}`, cannot start with a digit", ident)
+        }
+        _ => (),
+    }
+    if let Some(c) = ident
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+    {
+        bail!(
+            "illegal identifier `{}`, unexpected character `{}`",
+            ident,
+            c
+        )
+    }
+    if parse::is_kw(ident) {
+        bail!("illegal identifier `{}`, this is a keyword", ident)
+    }
+    if SMT_RESERVED.contains(&ident) {
+        bail!(
+            "illegal identifier `{}`, this is an SMT-LIB reserved word",
+            ident
+        )
+    }
+    Ok(())
+}
+
+/// True if an identifier can be used as is at SMT-level.
+///
+/// Identifiers containing `@` are never safe since `@` separates identifiers from step indices.
+/// Reserved words and builtins are safe when followed by a step index.
+fn is_smt_safe(ident: &str, stateful: bool) -> bool {
+    let legal = |c: char| c.is_ascii_alphanumeric() || "~!$%^&*_-+=<>.?/".contains(c);
+    !ident.is_empty()
+        && !ident.starts_with(|c: char| c.is_ascii_digit())
+        && ident.chars().all(legal)
+        && (stateful || !(SMT_RESERVED.contains(&ident) || SMT_BUILTINS.contains(&ident)))
+}
+
+/// Writes the SMT-level symbol for an identifier, optionally at some step.
+///
+/// Safe identifiers are written as is, *e.g.* `cnt@3`, and so are SMT-LIB
+/// [builtins](SMT_BUILTINS) followed by a step index. Other identifiers are quoted, after
+/// escaping `%`, `|`, `\` and `@` as `%<hex code>`: `|x y|` at step `3` becomes `|%7Cx y%7C@3|`.
+/// The symbol can be parsed back with [`demangle`].
+///
+/// ```rust
+/// # use mikino_api::decls::{demangle, write_sym};
+/// let sym = |ident: &str, step| {
+///     let mut buf: Vec<u8> = vec![];
+///     write_sym(&mut buf, ident, step).unwrap();
+///     String::from_utf8(buf).unwrap()
+/// };
+/// assert_eq!(sym("cnt", Some(3)), "cnt@3");
+/// assert_eq!(sym("assert", None), "|assert|");
+/// assert_eq!(sym("assert", Some(0)), "assert@0");
+/// assert_eq!(sym("x@1", Some(0)), "|x%401@0|");
+/// assert_eq!(sym("|x y|", Some(3)), "|%7Cx y%7C@3|");
+/// for (ident, step) in vec![("cnt", Some(3)), ("x@1", Some(0)), ("|x y|", None), ("or", None)] {
+///     assert_eq!(demangle(&sym(ident, step)).unwrap(), (ident.into(), step));
+/// }
+/// ```
+pub fn write_sym<W: Write>(w: &mut W, ident: &str, step: Option<Unroll>) -> std::io::Result<()> {
+    if is_smt_safe(ident, step.is_some()) {
+        write!(w, "{}", ident)?;
+        if let Some(step) = step {
+            write!(w, "@{}", step)?;
+        }
+        return Ok(());
+    }
+    write!(w, "|")?;
+    for c in ident.chars() {
+        match c {
+            '%' | '|' | '\\' | '@' => write!(w, "%{:02X}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+    if let Some(step) = step {
+        write!(w, "@{}", step)?;
+    }
+    write!(w, "|")
+}
+
+/// Parses an SMT-level symbol produced by [`write_sym`].
+///
+/// Yields the original identifier and the step index, if any.
+pub fn demangle(sym: &str) -> Res<(String, Option<Unroll>)> {
+    let sym = sym.trim();
+    let (quoted, sym) = match sym.strip_prefix('|').and_then(|s| s.strip_suffix('|')) {
+        Some(sym) => (true, sym),
+        None => (false, sym),
+    };
+    let (ident, step) = match sym.rsplit_once('@') {
+        Some((ident, step)) => match usize::from_str_radix(step, 10) {
+            Ok(step) => (ident, Some(step)),
+            Err(_) => (sym, None),
+        },
+        None => (sym, None),
+    };
+    if !quoted {
+        return Ok((ident.into(), step));
+    }
+    let mut res = String::with_capacity(ident.len());
+    let mut chars = ident.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue;
+        }
+        let hex: String = chars.by_ref().take(2).collect();
+        match u8::from_str_radix(&hex, 16) {
+            Ok(code) if hex.len() == 2 => res.push(code as char),
+            _ => bail!("illegal escape sequence `%{}` in symbol `{}`", hex, sym),
+        }
+    }
+    Ok((res, step))
+}
+
+/// Namespaced version of an identifier.
+///
+/// ```rust
+/// assert_eq!(mikino_api::decls::qualify("sys", "cnt"), "sys.cnt");
+/// ```
+pub fn qualify(ns: &str, ident: &str) -> String {
+    format!("{}{}{}", ns, NS_SEP, ident)
+}
+
+/// Unique identifier of a symbol in a [`SymbolTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SymId(usize);
+impl fmt::Display for SymId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "#{}", self.0)
+    }
+}
+
+/// A declared symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Identifier, namespaced if the symbol was imported.
+    pub ident: String,
+    /// Type.
+    pub typ: Typ,
+    /// Display hint, if any.
+    pub hint: Option<Hint>,
+    /// Depth of the scope the symbol was declared in, `0` is the global scope.
+    pub depth: usize,
+}
+impl Symbol {
+    /// Variable corresponding to this symbol.
+    pub fn to_var(&self) -> Var {
+        Var::new(self.ident.clone(), self.typ.clone())
+    }
+}
+
+/// Shadowing policy of a [`SymbolTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shadowing {
+    /// Identifiers cannot be re-declared while visible.
+    Forbid,
+    /// Identifiers can shadow declarations from outer scopes, but not from the same scope.
+    Scoped,
+    /// Identifiers can always be re-declared, the new declaration shadows the old one.
+    Allow,
+}
+
+/// Scoped symbol table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolTable {
+    /// All the symbols ever declared, indexed by [`SymId`].
+    symbols: Vec<Symbol>,
+    /// Stack of scopes, never empty, the first one is the global scope.
+    scopes: Vec<Map<String, SymId>>,
+    /// Shadowing policy.
+    shadowing: Shadowing,
+}
+impl ops::Index<SymId> for SymbolTable {
+    type Output = Symbol;
+    fn index(&self, id: SymId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+}
+impl SymbolTable {
+    /// Constructor.
+    pub fn new(shadowing: Shadowing) -> Self {
+        Self {
+            symbols: vec![],
+            scopes: vec![Map::new()],
+            shadowing,
+        }
+    }
+
+    /// Shadowing policy.
+    pub fn shadowing(&self) -> Shadowing {
+        self.shadowing
+    }
+    /// Depth of the current scope, `0` is the global scope.
+    pub fn depth(&self) -> usize {
+        self.scopes.len() - 1
+    }
+
+    /// Enters a new scope.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Map::new())
+    }
+    /// Leaves the current scope, fails on the global scope.
+    pub fn pop_scope(&mut self) -> Res<()> {
+        if self.scopes.len() == 1 {
+            bail!("[fatal] trying to leave the global scope")
+        }
+        let _ = self.scopes.pop();
+        Ok(())
+    }
+
+    /// Symbol visible for an identifier, if any.
+    pub fn lookup(&self, ident: impl AsRef<str>) -> Option<SymId> {
+        let ident = ident.as_ref();
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(ident).cloned())
+    }
+    /// True if an identifier is visible.
+    pub fn contains(&self, ident: impl AsRef<str>) -> bool {
+        self.lookup(ident).is_some()
+    }
+    /// Variable visible for an identifier, if any.
+    pub fn get_var(&self, ident: impl AsRef<str>) -> Option<Var> {
+        self.lookup(ident).map(|id| self[id].to_var())
+    }
+
+    /// Visible symbols, sorted by identifier.
+    pub fn visible(&self) -> impl Iterator<Item = SymId> {
+        let mut visible = Map::new();
+        for scope in self.scopes.iter() {
+            visible.extend(scope.iter().map(|(ident, id)| (ident.clone(), *id)))
+        }
+        visible.into_values()
+    }
+
+    /// Declares a symbol in the current scope.
+    ///
+    /// Fails if the identifier is illegal, or if the declaration is forbidden by the shadowing
+    /// policy.
+    pub fn declare(&mut self, ident: impl Into<String>, typ: Typ) -> Res<SymId> {
+        let ident = ident.into();
+        check_ident(&ident)?;
+        self.declare_unchecked(ident, typ)
+    }
+
+    /// Declares a symbol without checking the identifier, only checks shadowing.
+    pub(crate) fn declare_unchecked(&mut self, ident: String, typ: Typ) -> Res<SymId> {
+        let depth = self.depth();
+        if let Some(prev) = self.lookup(&ident) {
+            let same_scope = self[prev].depth == depth;
+            match self.shadowing {
+                Shadowing::Allow => (),
+                Shadowing::Scoped if !same_scope => (),
+                Shadowing::Scoped => {
+                    bail!("identifier `{}` is already declared in this scope", ident)
+                }
+                Shadowing::Forbid => bail!("identifier `{}` is already declared", ident),
+            }
+        }
+        let id = SymId(self.symbols.len());
+        self.symbols.push(Symbol {
+            ident: ident.clone(),
+            typ,
+            hint: None,
+            depth,
+        });
+        let _ = self
+            .scopes
+            .last_mut()
+            .expect("[unreachable] scope stack is never empty")
+            .insert(ident, id);
+        Ok(id)
+    }
+
+    /// Sets the display hint of a symbol.
+    pub fn set_hint(&mut self, id: SymId, hint: Hint) {
+        self.symbols[id.0].hint = Some(hint)
+    }
+
+    /// Imports the visible symbols of another table in the current scope, under a namespace.
+    ///
+    /// Returns the map from the symbols of `that` to the imported symbols.
+    ///
+    /// ```rust
+    /// # use mikino_api::{decls::{Shadowing, SymbolTable}, expr::Typ};
+    /// let mut sub = SymbolTable::new(Shadowing::Forbid);
+    /// let cnt = sub.declare("cnt", Typ::Int).unwrap();
+    ///
+    /// let mut table = SymbolTable::new(Shadowing::Forbid);
+    /// let _ = table.declare("cnt", Typ::Int).unwrap();
+    /// let imported = table.import("left", &sub).unwrap();
+    /// assert_eq!(table[imported[&cnt]].ident, "left.cnt");
+    /// assert!(table.import("left", &sub).is_err());
+    /// assert!(table.import("right", &sub).is_ok());
+    /// assert!(table.import("not.legal", &sub).is_err());
+    /// ```
+    pub fn import(&mut self, ns: &str, that: &Self) -> Res<Map<SymId, SymId>> {
+        check_ident(ns).chain_err(|| "illegal namespace")?;
+        let mut res = Map::new();
+        for id in that.visible() {
+            let symbol = &that[id];
+            let new = self.declare_unchecked(qualify(ns, &symbol.ident), symbol.typ.clone())?;
+            self.symbols[new.0].hint = symbol.hint.clone();
+            let _ = res.insert(id, new);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,195 @@
+//! Demo and exercise generation.
+//!
+//! Binaries and tutorials can use this module to write out example files: the [`script`] and
+//! [`system`] demos document mikino's syntax, while [`induction_exercise`] generates varied
+//! systems whose candidates hold but are not inductive, along with a solution.
+
+/// Documented demo hsmt script, same as [`SCRIPT_DEMO`](crate::SCRIPT_DEMO).
+pub fn script() -> &'static str {
+    crate::SCRIPT_DEMO
+}
+
+/// Documented demo hsmt system, same as [`TRANS_DEMO`](crate::TRANS_DEMO).
+pub fn system() -> &'static str {
+    crate::TRANS_DEMO
+}
+
+/// An induction exercise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exercise {
+    /// Markdown statement of the exercise.
+    pub statement: String,
+    /// Exercise system, its candidate holds but is not inductive.
+    pub system: String,
+    /// Solution system, with a strengthening candidate making the original one inductive.
+    pub solution: String,
+    /// Provenance, seed and hash of the exercise system.
+    pub provenance: crate::meta::Provenance,
+}
+
+/// Deterministic pseudo-random generator (xorshift), exercises only depend on their seed.
+///
+/// Also used by [random testing](crate::check::random).
+pub(crate) struct Rng {
+    /// Internal state, never zero.
+    state: u64,
+}
+impl Rng {
+    /// Constructor.
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut slf = Self {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        };
+        // Warm up, consecutive seeds yield very similar first values otherwise.
+        for _ in 0..4 {
+            let _ = slf.next();
+        }
+        slf
+    }
+    /// Next value.
+    pub(crate) fn next(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+    /// Value in `[lo, hi]`.
+    pub(crate) fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        lo + self.next() % (hi - lo + 1)
+    }
+}
+
+/// Generates an induction exercise.
+///
+/// The exercise depends only on `seed`. Its system has a single candidate that holds but is not
+/// inductive, and the solution adds a candidate that makes both candidates inductive.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{demo, parse};
+/// for seed in 0..20 {
+///     let exercise = demo::induction_exercise(seed);
+///     assert_eq!(exercise, demo::induction_exercise(seed));
+///     assert_eq!(exercise.provenance.seed, Some(seed));
+///
+///     let sys = parse::trans(&exercise.system).unwrap();
+///     assert_eq!(sys.po_s().len(), 1);
+///     let sol = parse::trans(&exercise.solution).unwrap();
+///     assert_eq!(sol.po_s().len(), 2);
+/// }
+/// ```
+pub fn induction_exercise(seed: u64) -> Exercise {
+    let mut rng = Rng::new(seed);
+    let mut exercise = match rng.range(0, 1) {
+        0 => stride_exercise(&mut rng),
+        _ => swap_exercise(&mut rng),
+    };
+    exercise.provenance = crate::meta::Provenance::new()
+        .seed(seed)
+        .input("system", &exercise.system);
+    exercise
+}
+
+/// Builds an exercise from a system without candidates, the candidate and the strengthening.
+fn exercise(
+    statement: String,
+    sys: String,
+    candidate: (&str, String),
+    strengthening: (&str, String),
+) -> Exercise {
+    let system = format!(
+        "{}candidates {{\n    \"{}\": {},\n}}\n",
+        sys, candidate.0, candidate.1
+    );
+    let solution = format!(
+        "{}candidates {{\n    \"{}\": {},\n    \"{}\": {},\n}}\n",
+        sys, candidate.0, candidate.1, strengthening.0, strengthening.1,
+    );
+    Exercise {
+        statement,
+        system,
+        solution,
+        provenance: crate::meta::Provenance::new(),
+    }
+}
+
+/// Counter incremented by a constant stride, the candidate forbids a value off the stride.
+fn stride_exercise(rng: &mut Rng) -> Exercise {
+    let stride = rng.range(2, 5);
+    let start = stride * rng.range(0, 3);
+    let forbidden = start + stride * rng.range(1, 5) + rng.range(1, stride - 1);
+    let sys = format!(
+        "//! Counter incremented by {stride} at each step.
+
+svars {{
+    cnt: int,
+}}
+
+init {{
+    cnt = {start},
+}}
+
+trans {{
+    'cnt = cnt + {stride},
+}}
+
+",
+        stride = stride,
+        start = start,
+    );
+    let statement = format!(
+        "Candidate `cnt is not {forbidden}` holds on this system, but it is not inductive. \
+        Find a strengthening candidate so that all candidates are inductive. \
+        *Hint: what values can `cnt` take?*",
+        forbidden = forbidden,
+    );
+    exercise(
+        statement,
+        sys,
+        (
+            &format!("cnt is not {}", forbidden),
+            format!("¬(cnt = {})", forbidden),
+        ),
+        ("cnt is on stride", format!("cnt % {} = 0", stride)),
+    )
+}
+
+/// Two variables swapping values, the candidate only talks about one of them.
+fn swap_exercise(rng: &mut Rng) -> Exercise {
+    let a = rng.range(1, 10);
+    let b = rng.range(1, 10);
+    let sys = format!(
+        "//! Two variables swapping their values at each step.
+
+svars {{
+    a b: int,
+}}
+
+init {{
+    a = {a},
+    b = {b},
+}}
+
+trans {{
+    'a = b,
+    'b = a,
+}}
+
+",
+        a = a,
+        b = b,
+    );
+    let statement = "Candidate `a is positive` holds on this system, but it is not inductive. \
+        Find a strengthening candidate so that all candidates are inductive. \
+        *Hint: where does the next value of `a` come from?*"
+        .to_string();
+    exercise(
+        statement,
+        sys,
+        ("a is positive", "a > 0".into()),
+        ("b is positive", "b > 0".into()),
+    )
+}
@@ -17,6 +17,8 @@ pub enum Error {
         row: usize,
         /// Column where the error occured (starts at `0`).
         col: usize,
+        /// Length of the erroneous snippet of `line` starting at `col`, in bytes (`0` if unknown).
+        len: usize,
         /// Line of the error.
         line: String,
         /// Previous line.
@@ -41,11 +43,44 @@ impl Error {
             msg: msg.into(),
             row,
             col,
+            len: 0,
             line: line.into(),
             prev,
             next,
         }
     }
+    /// Parse error constructor, underlines the span of `txt` in [`Self::pretty`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{err::Error, parse::Span};
+    /// let txt = "init {\n    x + 1,\n}";
+    /// let err = Error::parse_span("expected `bool`", Span::new(11, 16), txt);
+    /// assert_eq!(
+    ///     err.pretty(()),
+    ///     "\
+    /// parse error at 2:5
+    ///   | init {
+    /// 2 |     x + 1,
+    ///   |     ^~~~~ expected `bool`
+    ///   | }",
+    /// );
+    /// ```
+    pub fn parse_span(msg: impl Into<String>, span: crate::parse::Span, txt: &str) -> Self {
+        let (prev, row, col, line, next) = span.pretty_of(txt);
+        // Only the part of the span on the first line is underlined.
+        let len = (span.end - span.start).min(line.len().saturating_sub(col));
+        Self::Parse {
+            msg: msg.into(),
+            row,
+            col,
+            len,
+            line,
+            prev,
+            next,
+        }
+    }
 
     /// Extends an error with a chain of errors.
     pub fn extend(self, errs: impl Iterator<Item = Error>) -> ErrorChain {
@@ -69,11 +104,19 @@ impl Error {
                 msg,
                 row,
                 col,
+                len,
                 line,
                 prev,
                 next,
             } => {
                 let (row, col) = (*row, *col);
+                // At least `^~~~`, even for empty or unknown snippets.
+                let underline = line
+                    .get(col..col + len)
+                    .map(|snippet| snippet.chars().count())
+                    .unwrap_or(0)
+                    .max(4);
+                let underline = format!("^{}", "~".repeat(underline - 1));
                 let (row_str, col_str) = ((row + 1).to_string(), (col + 1).to_string());
                 let offset = {
                     let mut offset = 0;
@@ -107,7 +150,7 @@ impl Error {
                     "\n{} | {}{} {}",
                     " ".repeat(row_str.len()),
                     " ".repeat(offset),
-                    style.red("^~~~"),
+                    style.red(&underline),
                     style.red(if msg.is_empty() { "here" } else { &msg }),
                 ));
                 if let Some(next) = next {
@@ -211,6 +254,14 @@ impl ErrorChain {
         Some(self.source).into_iter().chain(self.chain)
     }
 
+    /// Kinds of the [`rsmt2`] errors in the chain.
+    pub fn smt_kinds(&self) -> impl Iterator<Item = &rsmt2::errors::ErrorKind> {
+        self.iter().filter_map(|e| match e {
+            Error::Smt(e) => Some(e.kind()),
+            _ => None,
+        })
+    }
+
     /// Extends the chain.
     pub fn extend(mut self, errs: impl Iterator<Item = Error>) -> Self {
         self.chain.extend(errs);
@@ -314,6 +365,8 @@ pub struct PError {
     pub span: Span,
     /// Actual error.
     pub error: ErrorChain,
+    /// Secondary spans giving some context to the error, with their message.
+    pub notes: Vec<(Span, String)>,
 }
 impl fmt::Display for PError {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -329,7 +382,19 @@ impl PError {
     pub fn new(error: impl Into<ErrorChain>, span: impl Into<Span>) -> Self {
         let error = error.into();
         let span = span.into();
-        PError { span, error }
+        PError {
+            span,
+            error,
+            notes: vec![],
+        }
+    }
+
+    /// Adds a secondary span giving some context to the error.
+    ///
+    /// Notes are reported after the error and its chain by [`Self::into_error`].
+    pub fn with_note(mut self, span: impl Into<Span>, msg: impl Into<String>) -> Self {
+        self.notes.push((span.into(), msg.into()));
+        self
     }
 
     /// Chains an error.
@@ -343,16 +408,17 @@ impl PError {
 
     /// Turns itself in a nice error.
     pub fn into_error(self, txt: &str) -> ErrorChain {
-        let span = self.span;
-        let (prev, row, col, line, next) = span.pretty_of(txt);
-        let err = Error::parse("", row, col, line, prev, next);
-        err.extend(self.error.into_iter())
+        let err = Error::parse_span("", self.span, txt);
+        let notes = self
+            .notes
+            .into_iter()
+            .map(|(span, msg)| Error::parse_span(msg, span, txt));
+        err.extend(self.error.into_iter().chain(notes))
     }
 
     /// Turns itself in a nice error.
     pub fn new_error(span: Span, txt: &str, msg: impl Into<String>) -> Error {
-        let (prev, row, col, line, next) = span.pretty_of(txt);
-        Error::parse(msg, row, col, line, prev, next)
+        Error::parse_span(msg, span, txt)
     }
 }
 
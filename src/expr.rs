@@ -4,13 +4,20 @@ crate::prelude!();
 
 use rsmt2::print::{Expr2Smt, Sort2Smt, Sym2Smt};
 
+pub mod display;
+pub mod hcons;
+pub mod hsmt;
+pub mod patterns;
+pub mod simplify;
+
 #[cfg(test)]
 mod test;
 
 pub use crate::{build_expr as build, build_typ};
+pub use simplify::simplify;
 
 /// A type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Typ {
     /// Bool type.
     Bool,
@@ -18,6 +25,12 @@ pub enum Typ {
     Int,
     /// Rational type.
     Rat,
+    /// Bitvector type of some width, strictly positive.
+    BitVec(u32),
+    /// Array type, from an index type to an element type.
+    Array(Box<Typ>, Box<Typ>),
+    /// Enumeration type, by name, see [`trans::Decls::declare_enum`].
+    Enum(String),
 }
 impl Typ {
     /// Creates a bool type.
@@ -56,30 +69,184 @@ impl Typ {
     pub fn rat() -> Self {
         Self::Rat
     }
+    /// Creates a bitvector type.
+    ///
+    /// # Panics
+    ///
+    /// - if `width` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::Typ;
+    /// let bv_typ = Typ::bitvec(8);
+    /// assert_eq!(&bv_typ.to_string(), "bv<8>")
+    /// ```
+    pub fn bitvec(width: u32) -> Self {
+        assert!(width > 0, "bitvector types cannot have width zero");
+        Self::BitVec(width)
+    }
+    /// Creates an array type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::Typ;
+    /// let array_typ = Typ::array(Typ::int(), Typ::bool());
+    /// assert_eq!(&array_typ.to_string(), "array<int, bool>")
+    /// ```
+    pub fn array(idx: Typ, elm: Typ) -> Self {
+        Self::Array(Box::new(idx), Box::new(elm))
+    }
+    /// Creates an enumeration type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::Typ;
+    /// let enum_typ = Typ::enumeration("State");
+    /// assert_eq!(&enum_typ.to_string(), "State")
+    /// ```
+    pub fn enumeration(name: impl Into<String>) -> Self {
+        Self::Enum(name.into())
+    }
 
     /// True if the type is an arithmetic one.
-    pub fn is_arith(self) -> bool {
+    pub fn is_arith(&self) -> bool {
         match self {
-            Self::Bool => false,
+            Self::Bool | Self::BitVec(_) | Self::Array(_, _) | Self::Enum(_) => false,
             Self::Int | Self::Rat => true,
         }
     }
+    /// Parses an SMT-LIB sort, `Bool`, `Int`, `Real`, `(_ BitVec <width>)`,
+    /// `(Array <sort> <sort>)`, or a simple symbol for enumeration types.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::Typ;
+    /// assert_eq!(Typ::of_smt_str("Real"), Some(Typ::Rat));
+    /// assert_eq!(Typ::of_smt_str("(_ BitVec 32)"), Some(Typ::BitVec(32)));
+    /// assert_eq!(Typ::of_smt_str("(_ BitVec 0)"), None);
+    /// assert_eq!(
+    ///     Typ::of_smt_str("(Array Int (Array (_ BitVec 8) Bool))"),
+    ///     Some(Typ::array(Typ::Int, Typ::array(Typ::BitVec(8), Typ::Bool))),
+    /// );
+    /// assert_eq!(Typ::of_smt_str("State"), Some(Typ::enumeration("State")));
+    /// assert_eq!(Typ::of_smt_str("(Seq Int)"), None);
+    /// ```
+    pub fn of_smt_str(s: impl AsRef<str>) -> Option<Self> {
+        match s.as_ref().trim() {
+            "Bool" => Some(Self::Bool),
+            "Int" => Some(Self::Int),
+            "Real" => Some(Self::Rat),
+            s if is_smt_simple_symbol(s) => Some(Self::enumeration(s)),
+            s => {
+                let tokens = smt_list(s)?;
+                match tokens.as_slice() {
+                    ["_", "BitVec", width] => {
+                        width.parse().ok().filter(|w| *w > 0).map(Self::BitVec)
+                    }
+                    ["Array", idx, elm] => {
+                        Some(Self::array(Self::of_smt_str(idx)?, Self::of_smt_str(elm)?))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+    /// Width of a bitvector type, `None` if `self` is not a bitvector type.
+    pub fn bv_width(&self) -> Option<u32> {
+        match self {
+            Self::BitVec(width) => Some(*width),
+            Self::Bool | Self::Int | Self::Rat | Self::Array(_, _) | Self::Enum(_) => None,
+        }
+    }
+    /// Index and element types of an array type, `None` if `self` is not an array type.
+    pub fn array_typs(&self) -> Option<(&Typ, &Typ)> {
+        match self {
+            Self::Array(idx, elm) => Some((idx, elm)),
+            Self::Bool | Self::Int | Self::Rat | Self::BitVec(_) | Self::Enum(_) => None,
+        }
+    }
+    /// Name of an enumeration type, `None` if `self` is not an enumeration type.
+    pub fn enum_name(&self) -> Option<&str> {
+        match self {
+            Self::Enum(name) => Some(name),
+            Self::Bool | Self::Int | Self::Rat | Self::BitVec(_) | Self::Array(_, _) => None,
+        }
+    }
 }
 impl Sort2Smt for Typ {
     fn sort_to_smt2<W: Write>(&self, w: &mut W) -> SmtRes<()> {
-        write!(
-            w,
-            "{}",
-            match self {
-                Self::Bool => "Bool",
-                Self::Int => "Int",
-                Self::Rat => "Real",
+        match self {
+            Self::Bool => write!(w, "Bool")?,
+            Self::Int => write!(w, "Int")?,
+            Self::Rat => write!(w, "Real")?,
+            Self::BitVec(width) => write!(w, "(_ BitVec {})", width)?,
+            Self::Array(idx, elm) => {
+                write!(w, "(Array ")?;
+                idx.sort_to_smt2(w)?;
+                write!(w, " ")?;
+                elm.sort_to_smt2(w)?;
+                write!(w, ")")?
             }
-        )?;
+            Self::Enum(name) => write!(w, "{}", name)?,
+        }
         Ok(())
     }
 }
 
+/// True if `s` is a simple SMT-LIB symbol made of letters, digits and `_`, not starting with a
+/// digit.
+fn is_smt_simple_symbol(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits an SMT-LIB list in its top-level elements, `None` if `s` is not a list.
+fn smt_list(s: &str) -> Option<Vec<&str>> {
+    let inner = s.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut res = vec![];
+    let (mut depth, mut start) = (0usize, None);
+    for (idx, c) in inner.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    start = Some(idx)
+                }
+                depth += 1
+            }
+            ')' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    res.push(&inner[start.take()?..=idx])
+                }
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    res.push(&inner[start..idx])
+                }
+            }
+            _ => {
+                if start.is_none() {
+                    start = Some(idx)
+                }
+            }
+        }
+    }
+    if depth > 0 {
+        return None;
+    }
+    if let Some(start) = start {
+        res.push(&inner[start..])
+    }
+    Some(res)
+}
+
 /// Operator precedence.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Precedence {
@@ -119,7 +286,7 @@ impl PartialOrd for Precedence {
 
 /// Constants.
 ///
-/// Currently only booleans, integers and rationals are supported.
+/// Booleans, integers, rationals, bitvectors, arrays and enumeration variants.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Cst {
     /// Bool constant.
@@ -128,6 +295,29 @@ pub enum Cst {
     I(Int),
     /// Rational constant.
     R(Rat),
+    /// Bitvector constant, width and unsigned value.
+    ///
+    /// The value is always in `[0, 2^width)`, use [`Cst::bv`] to build bitvector constants.
+    BV(u32, Int),
+    /// Array constant, maps all indices to `default` except the ones in `entries`.
+    ///
+    /// Entries never map an index to the default value, use [`Cst::array`] and [`Cst::store`] to
+    /// build array constants.
+    Array {
+        /// Index type.
+        idx: Typ,
+        /// Value of the indices not in `entries`.
+        default: Box<Cst>,
+        /// Values of the other indices.
+        entries: Map<Cst, Cst>,
+    },
+    /// Enumeration variant, use [`Cst::variant`] to build variant constants.
+    Enum {
+        /// Name of the enumeration type.
+        typ: String,
+        /// Name of the variant.
+        variant: String,
+    },
 }
 impl HasTyp for Cst {
     fn typ(&self) -> Typ {
@@ -135,6 +325,9 @@ impl HasTyp for Cst {
             Self::B(_) => Typ::Bool,
             Self::I(_) => Typ::Int,
             Self::R(_) => Typ::Rat,
+            Self::BV(width, _) => Typ::BitVec(*width),
+            Self::Array { idx, default, .. } => Typ::array(idx.clone(), default.typ()),
+            Self::Enum { typ, .. } => Typ::enumeration(typ.clone()),
         }
     }
 }
@@ -151,6 +344,128 @@ impl Cst {
     pub fn rat<R: Into<Rat>>(r: R) -> Self {
         Self::R(r.into())
     }
+    /// Creates a bitvector constant, `val` is taken modulo `2^width`.
+    ///
+    /// # Panics
+    ///
+    /// - if `width` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{Cst, Typ};
+    /// # use mikino_api::prelude::HasTyp;
+    /// let cst = Cst::bv(8, 42);
+    /// assert_eq!(cst.typ(), Typ::BitVec(8));
+    /// assert_eq!(cst.to_string(), "(_ bv42 8)");
+    /// assert_eq!(Cst::bv(8, 257), Cst::bv(8, 1));
+    /// assert_eq!(Cst::bv(8, -1), Cst::bv(8, 255));
+    /// ```
+    pub fn bv<I: Into<Int>>(width: u32, val: I) -> Self {
+        use num::Integer;
+        assert!(width > 0, "bitvector constants cannot have width zero");
+        Self::BV(width, val.into().mod_floor(&Self::bv_modulus(width)))
+    }
+    /// `2^width`, the number of values of bitvectors of some width.
+    pub(crate) fn bv_modulus(width: u32) -> Int {
+        Int::one() << width as usize
+    }
+    /// Creates a constant array, maps all indices of type `idx` to `default`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{Cst, Typ};
+    /// # use mikino_api::prelude::HasTyp;
+    /// let arr = Cst::array(Typ::Int, Cst::int(0));
+    /// assert_eq!(arr.typ(), Typ::array(Typ::Int, Typ::Int));
+    /// assert_eq!(arr.to_string(), "((as const (Array Int Int)) 0)");
+    ///
+    /// let arr = arr.store(Cst::int(3), Cst::int(7)).unwrap();
+    /// assert_eq!(arr.to_string(), "(store ((as const (Array Int Int)) 0) 3 7)");
+    /// assert_eq!(arr.select(&Cst::int(3)).unwrap(), Cst::int(7));
+    /// assert_eq!(arr.select(&Cst::int(4)).unwrap(), Cst::int(0));
+    ///
+    /// // Arrays are equal when they have the same values at all indices.
+    /// let arr = arr.store(Cst::int(3), Cst::int(0)).unwrap();
+    /// assert_eq!(arr, Cst::array(Typ::Int, Cst::int(0)));
+    /// assert!(arr.store(Cst::bool(true), Cst::int(0)).is_err());
+    /// ```
+    pub fn array(idx: Typ, default: Cst) -> Self {
+        Self::Array {
+            idx,
+            default: Box::new(default),
+            entries: Map::new(),
+        }
+    }
+    /// Creates an enumeration variant constant.
+    ///
+    /// Displayed as `<typ>::<variant>`, and as the quoted symbol `|<typ>::<variant>|` in SMT-LIB
+    /// where it is the constructor of the variant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{Cst, Typ};
+    /// # use mikino_api::prelude::HasTyp;
+    /// let idle = Cst::variant("State", "Idle");
+    /// assert_eq!(idle.typ(), Typ::enumeration("State"));
+    /// assert_eq!(idle.to_string(), "State::Idle");
+    /// assert_eq!(idle.as_variant().unwrap(), ("State", "Idle"));
+    /// ```
+    pub fn variant(typ: impl Into<String>, variant: impl Into<String>) -> Self {
+        Self::Enum {
+            typ: typ.into(),
+            variant: variant.into(),
+        }
+    }
+    /// Value of an array constant at some index.
+    pub fn select(&self, index: &Cst) -> Res<Cst> {
+        match self {
+            Self::Array {
+                idx,
+                default,
+                entries,
+            } if index.typ() == *idx => Ok(entries.get(index).unwrap_or(default).clone()),
+            _ => bail!(
+                "cannot select index `{}: {}` in `{}: {}`",
+                index,
+                index.typ(),
+                self,
+                self.typ()
+            ),
+        }
+    }
+    /// Array constant with a different value at some index.
+    pub fn store(self, index: Cst, val: Cst) -> Res<Cst> {
+        match self {
+            Self::Array {
+                idx,
+                default,
+                mut entries,
+            } if index.typ() == idx && val.typ() == default.typ() => {
+                if val == *default {
+                    let _ = entries.remove(&index);
+                } else {
+                    let _ = entries.insert(index, val);
+                }
+                Ok(Self::Array {
+                    idx,
+                    default,
+                    entries,
+                })
+            }
+            arr => bail!(
+                "cannot store `{}: {}` at index `{}: {}` in `{}: {}`",
+                val,
+                val.typ(),
+                index,
+                index.typ(),
+                arr,
+                arr.typ()
+            ),
+        }
+    }
 
     /// Unwraps a boolean constant.
     pub fn as_bool(&self) -> Res<bool> {
@@ -173,6 +488,20 @@ impl Cst {
             _ => bail!("expect integer, found `{}`", self),
         }
     }
+    /// Unwraps a bitvector constant, yields its width and value.
+    pub fn as_bv(&self) -> Res<(u32, &Int)> {
+        match self {
+            Self::BV(width, val) => Ok((*width, val)),
+            _ => bail!("expected bitvector, found `{}`", self),
+        }
+    }
+    /// Unwraps an enumeration variant, yields its type and variant names.
+    pub fn as_variant(&self) -> Res<(&str, &str)> {
+        match self {
+            Self::Enum { typ, variant } => Ok((typ, variant)),
+            _ => bail!("expected enumeration variant, found `{}`", self),
+        }
+    }
 }
 impl Expr2Smt<()> for Cst {
     fn expr_to_smt2<W: Write>(&self, w: &mut W, _: ()) -> SmtRes<()> {
@@ -180,13 +509,162 @@ impl Expr2Smt<()> for Cst {
             Self::B(b) => write!(w, "{}", b)?,
             Self::I(i) => write!(w, "{}", i)?,
             Self::R(r) => write!(w, "(/ {} {})", r.numer(), r.denom())?,
+            Self::BV(width, val) => write!(w, "(_ bv{} {})", val, width)?,
+            Self::Array {
+                idx,
+                default,
+                entries,
+            } => {
+                for _ in entries.iter() {
+                    write!(w, "(store ")?
+                }
+                write!(w, "((as const ")?;
+                Typ::array(idx.clone(), default.typ()).sort_to_smt2(w)?;
+                write!(w, ") ")?;
+                default.expr_to_smt2(w, ())?;
+                write!(w, ")")?;
+                for (idx, val) in entries.iter() {
+                    write!(w, " ")?;
+                    idx.expr_to_smt2(w, ())?;
+                    write!(w, " ")?;
+                    val.expr_to_smt2(w, ())?;
+                    write!(w, ")")?
+                }
+            }
+            Self::Enum { typ, variant } => write!(w, "|{}::{}|", typ, variant)?,
+        }
+        Ok(())
+    }
+}
+
+/// Signature of an uninterpreted function, see [`Op::Fun`].
+///
+/// Signatures are shared behind an [`Arc`](std::sync::Arc) so that cloning an operator is cheap.
+/// They are not interned: two signatures are equal if their name and types are equal, and they
+/// are freed with the last expression mentioning them.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::{Fun, Typ};
+/// let f = Fun::new("f", vec![Typ::Int, Typ::Bool], Typ::Int);
+/// assert_eq!(f.name(), "f");
+/// assert_eq!(f.to_string(), "f: (int, bool) -> int");
+/// assert_eq!(f, Fun::new("f", vec![Typ::Int, Typ::Bool], Typ::Int));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fun {
+    /// Name of the function.
+    name: String,
+    /// Types of the arguments.
+    args: Vec<Typ>,
+    /// Type of the result.
+    out: Typ,
+}
+impl Fun {
+    /// Shared signature of a function.
+    pub fn new(name: impl Into<String>, args: Vec<Typ>, out: Typ) -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self {
+            name: name.into(),
+            args,
+            out,
+        })
+    }
+
+    /// Name of the function.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Types of the arguments.
+    pub fn args(&self) -> &[Typ] {
+        &self.args
+    }
+    /// Type of the result.
+    pub fn out(&self) -> &Typ {
+        &self.out
+    }
+}
+impl fmt::Display for Fun {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}: (", self.name)?;
+        for (idx, typ) in self.args.iter().enumerate() {
+            if idx > 0 {
+                write!(fmt, ", ")?
+            }
+            typ.fmt(fmt)?
+        }
+        write!(fmt, ") -> {}", self.out)
+    }
+}
+impl<T> Sym2Smt<T> for Fun {
+    fn sym_to_smt2<W: Write>(&self, w: &mut W, _: T) -> SmtRes<()> {
+        decls::write_sym(w, &self.name, None)?;
+        Ok(())
+    }
+}
+
+/// Variables bound by a quantifier, see [`Op::Forall`] and [`Op::Exists`].
+///
+/// Like [`Fun`], binders are shared behind an [`Arc`](std::sync::Arc) so that cloning an operator
+/// is cheap. Bound variables appear in the body of the quantifier as regular [`Var`]s, and are
+/// written without unrolling step: quantified expressions are meant for scripts.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::{Binders, Typ, Var};
+/// let binders = Binders::new(vec![Var::new("i", Typ::Int), Var::new("b", Typ::Bool)]);
+/// assert_eq!(binders.to_string(), "((i int) (b bool))");
+/// assert_eq!(binders, Binders::new(binders.vars().to_vec()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Binders {
+    /// Bound variables.
+    vars: Vec<Var>,
+}
+impl Binders {
+    /// Shared binders.
+    pub fn new(vars: Vec<Var>) -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self { vars })
+    }
+
+    /// Bound variables.
+    pub fn vars(&self) -> &[Var] {
+        &self.vars
+    }
+}
+impl fmt::Display for Binders {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "(")?;
+        for (idx, var) in self.vars.iter().enumerate() {
+            if idx > 0 {
+                write!(fmt, " ")?
+            }
+            write!(fmt, "({} {})", var.id(), var.typ())?
         }
+        write!(fmt, ")")
+    }
+}
+impl Expr2Smt<()> for Binders {
+    fn expr_to_smt2<W: Write>(&self, w: &mut W, _: ()) -> SmtRes<()> {
+        write!(w, "(")?;
+        for (idx, var) in self.vars.iter().enumerate() {
+            if idx > 0 {
+                write!(w, " ")?
+            }
+            write!(w, "(")?;
+            var.sym_to_smt2(w, ())?;
+            write!(w, " ")?;
+            var.typ().sort_to_smt2(w)?;
+            write!(w, ")")?
+        }
+        write!(w, ")")?;
         Ok(())
     }
 }
 
 /// Operators.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Op {
     /// If-then-else.
     Ite,
@@ -220,6 +698,26 @@ pub enum Op {
     And,
     /// Logical disjunction.
     Or,
+    /// Bitwise conjunction.
+    BvAnd,
+    /// Bitwise disjunction.
+    BvOr,
+    /// Bitwise exclusive disjunction.
+    BvXor,
+    /// Left shift.
+    Shl,
+    /// Logical right shift.
+    Shr,
+    /// Array read, `select(array, index)`.
+    Select,
+    /// Array write, `store(array, index, value)`.
+    Store,
+    /// Uninterpreted function application, `f(arg, ...)`.
+    Fun(std::sync::Arc<Fun>),
+    /// Universal quantification of its only argument.
+    Forall(std::sync::Arc<Binders>),
+    /// Existential quantification of its only argument.
+    Exists(std::sync::Arc<Binders>),
 }
 impl Op {
     /// Tries to parse an operator.
@@ -231,6 +729,8 @@ impl Op {
     /// assert_eq!(Op::of_str("+"), Some(Op::Add));
     /// assert_eq!(Op::of_str("and"), Some(Op::And));
     /// assert_eq!(Op::of_str("⋀"), Some(Op::And));
+    /// assert_eq!(Op::of_str("&"), Some(Op::BvAnd));
+    /// assert_eq!(Op::of_str("bvlshr"), Some(Op::Shr));
     /// assert_eq!(Op::of_str("add"), None);
     /// ```
     pub fn of_str<Str: AsRef<str>>(s: Str) -> Option<Self> {
@@ -252,14 +752,21 @@ impl Op {
             "not" | "!" | "¬" => Not,
             "and" | "&&" | "⋀" => And,
             "or" | "||" | "⋁" => Or,
+            "&" | "bvand" => BvAnd,
+            "|" | "bvor" => BvOr,
+            "^" | "bvxor" => BvXor,
+            "<<" | "bvshl" => Shl,
+            ">>" | "bvlshr" => Shr,
+            "select" => Select,
+            "store" => Store,
             _ => return None,
         };
         Some(res)
     }
 
     /// Human-SMT string representation.
-    pub fn hsmt_str(self) -> &'static [&'static str] {
-        match self {
+    pub fn hsmt_str(&self) -> std::borrow::Cow<'_, [&str]> {
+        let strs: &'static [&'static str] = match self {
             Self::Ite => &["if", "then", "else"],
             Self::Implies => &["⇒"],
             Self::Add => &["+"],
@@ -276,11 +783,22 @@ impl Op {
             Self::Not => &["¬"],
             Self::And => &["⋀"],
             Self::Or => &["⋁"],
-        }
+            Self::BvAnd => &["&"],
+            Self::BvOr => &["|"],
+            Self::BvXor => &["^"],
+            Self::Shl => &["<<"],
+            Self::Shr => &[">>"],
+            Self::Select => &["select"],
+            Self::Store => &["store"],
+            Self::Fun(fun) => return vec![fun.name()].into(),
+            Self::Forall(_) => &["forall"],
+            Self::Exists(_) => &["exists"],
+        };
+        strs.into()
     }
 
     /// Human-SMT string representation.
-    pub fn smt_str(self) -> &'static str {
+    pub fn smt_str(&self) -> &str {
         match self {
             Self::Ite => "ite",
             Self::Implies => "=>",
@@ -298,11 +816,57 @@ impl Op {
             Self::Not => "¬",
             Self::And => "⋀",
             Self::Or => "⋁",
+            Self::BvAnd => "&",
+            Self::BvOr => "|",
+            Self::BvXor => "^",
+            Self::Shl => "<<",
+            Self::Shr => ">>",
+            Self::Select => "select",
+            Self::Store => "store",
+            Self::Fun(fun) => fun.name(),
+            Self::Forall(_) => "forall",
+            Self::Exists(_) => "exists",
+        }
+    }
+
+    /// Precedence of the operator in hsmt syntax, the higher the tighter the operator binds.
+    ///
+    /// Operators applied with a prefix syntax, such as `if` or `select(...)`, have maximal
+    /// precedence. Unary minus binds as tight as negation, see [`PExpr::to_hsmt`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{Op, Precedence};
+    /// assert!(Op::Mul.precedence() > Op::Add.precedence());
+    /// assert!(Op::Or.precedence() > Op::Implies.precedence());
+    /// assert!(Op::Add.precedence() == Op::Mod.precedence());
+    /// assert_eq!(Op::Ite.precedence(), Precedence::max());
+    /// ```
+    pub fn precedence(&self) -> Precedence {
+        match self {
+            Self::Implies => Precedence::new(1),
+            Self::Or => Precedence::new(2),
+            Self::And => Precedence::new(3),
+            Self::Eq | Self::Le | Self::Lt | Self::Ge | Self::Gt => Precedence::new(4),
+            Self::BvOr => Precedence::new(5),
+            Self::BvXor => Precedence::new(6),
+            Self::BvAnd => Precedence::new(7),
+            Self::Shl | Self::Shr => Precedence::new(8),
+            Self::Add | Self::Sub | Self::Mod => Precedence::new(9),
+            Self::Mul | Self::Div | Self::IDiv => Precedence::new(10),
+            Self::Not => Precedence::new(11),
+            Self::Ite
+            | Self::Select
+            | Self::Store
+            | Self::Fun(_)
+            | Self::Forall(_)
+            | Self::Exists(_) => Precedence::max(),
         }
     }
 
     /// True if `self` is an arithmetic relation.
-    pub fn is_arith_relation(self) -> bool {
+    pub fn is_arith_relation(&self) -> bool {
         match self {
             Self::Ge | Self::Le | Self::Gt | Self::Lt => true,
             Self::Ite
@@ -316,14 +880,30 @@ impl Op {
             | Self::Eq
             | Self::Not
             | Self::And
-            | Self::Or => false,
+            | Self::Or
+            | Self::BvAnd
+            | Self::BvOr
+            | Self::BvXor
+            | Self::Shl
+            | Self::Shr
+            | Self::Select
+            | Self::Store
+            | Self::Fun(_)
+            | Self::Forall(_)
+            | Self::Exists(_) => false,
         }
     }
 
     /// Minimal arity of `self`.
-    pub fn min_arity(self) -> usize {
+    pub fn min_arity(&self) -> usize {
         match self {
-            Self::Not | Self::Add | Self::Sub | Self::And | Self::Or => 1,
+            Self::Not
+            | Self::Add
+            | Self::Sub
+            | Self::And
+            | Self::Or
+            | Self::Forall(_)
+            | Self::Exists(_) => 1,
             Self::Mod
             | Self::Mul
             | Self::Div
@@ -333,15 +913,22 @@ impl Op {
             | Self::Le
             | Self::Lt
             | Self::Ge
-            | Self::Gt => 2,
-            Self::Ite => 3,
+            | Self::Gt
+            | Self::BvAnd
+            | Self::BvOr
+            | Self::BvXor
+            | Self::Shl
+            | Self::Shr
+            | Self::Select => 2,
+            Self::Ite | Self::Store => 3,
+            Self::Fun(fun) => fun.args.len(),
         }
     }
 
     /// Maximal arity for `self`, `None` if infinite.
-    pub fn max_arity(self) -> Option<usize> {
+    pub fn max_arity(&self) -> Option<usize> {
         match self {
-            Self::Not => Some(1),
+            Self::Not | Self::Forall(_) | Self::Exists(_) => Some(1),
             Self::Add
             | Self::Sub
             | Self::Mul
@@ -352,14 +939,50 @@ impl Op {
             | Self::Le
             | Self::Lt
             | Self::Ge
-            | Self::Gt => None,
-            Self::Mod | Self::Div | Self::IDiv => Some(2),
-            Self::Ite => Some(3),
+            | Self::Gt
+            | Self::BvAnd
+            | Self::BvOr
+            | Self::BvXor => None,
+            Self::Mod | Self::Div | Self::IDiv | Self::Shl | Self::Shr | Self::Select => Some(2),
+            Self::Ite | Self::Store => Some(3),
+            Self::Fun(fun) => Some(fun.args.len()),
+        }
+    }
+
+    /// True if the operator is associative, *i.e.* nested applications can be flattened.
+    pub fn is_associative(&self) -> bool {
+        match self {
+            Self::Add
+            | Self::Mul
+            | Self::And
+            | Self::Or
+            | Self::BvAnd
+            | Self::BvOr
+            | Self::BvXor => true,
+            Self::Sub
+            | Self::Implies
+            | Self::Eq
+            | Self::Le
+            | Self::Lt
+            | Self::Ge
+            | Self::Gt
+            | Self::Not
+            | Self::Mod
+            | Self::Div
+            | Self::IDiv
+            | Self::Ite
+            | Self::Shl
+            | Self::Shr
+            | Self::Select
+            | Self::Store
+            | Self::Fun(_)
+            | Self::Forall(_)
+            | Self::Exists(_) => false,
         }
     }
 
     /// True if the operator is left associative.
-    pub fn is_left_associative(self) -> bool {
+    pub fn is_left_associative(&self) -> bool {
         match self {
             Self::Add
             | Self::Sub
@@ -371,13 +994,27 @@ impl Op {
             | Self::Le
             | Self::Lt
             | Self::Ge
-            | Self::Gt => true,
-            Self::Not | Self::Mod | Self::Div | Self::IDiv | Self::Ite => false,
+            | Self::Gt
+            | Self::BvAnd
+            | Self::BvOr
+            | Self::BvXor => true,
+            Self::Not
+            | Self::Mod
+            | Self::Div
+            | Self::IDiv
+            | Self::Ite
+            | Self::Shl
+            | Self::Shr
+            | Self::Select
+            | Self::Store
+            | Self::Fun(_)
+            | Self::Forall(_)
+            | Self::Exists(_) => false,
         }
     }
 
     /// Type-checks an operator application.
-    pub fn type_check<V: HasTyp>(self, args: &[PExpr<V>]) -> Res<Typ> {
+    pub fn type_check<E: HasTyp>(&self, args: &[E]) -> Res<Typ> {
         if args.len() < self.min_arity() {
             bail!(
                 "`{}` expects at least {} argument(s)",
@@ -412,7 +1049,12 @@ impl Op {
 
                 thn_typ
             }
-            Self::Implies | Self::And | Self::Or | Self::Not => {
+            Self::Implies
+            | Self::And
+            | Self::Or
+            | Self::Not
+            | Self::Forall(_)
+            | Self::Exists(_) => {
                 if args.iter().any(|e| e.typ() != Typ::Bool) {
                     bail!("`{}`'s arguments must all be boolean expressions", self)
                 }
@@ -429,7 +1071,7 @@ impl Op {
             | Self::Ge
             | Self::Lt
             | Self::Gt => {
-                let mut typs = args.iter().map(PExpr::typ);
+                let mut typs = args.iter().map(E::typ);
                 let first = typs.next().expect("at least one argument");
                 if !first.is_arith() {
                     bail!(
@@ -448,7 +1090,7 @@ impl Op {
                         )
                     }
                 }
-                if (self == Self::IDiv || self == Self::Mod) && first != Typ::Int {
+                if (*self == Self::IDiv || *self == Self::Mod) && first != Typ::Int {
                     bail!(
                         "`{}` can only be applied to integer arguments, found `{}`",
                         self,
@@ -456,9 +1098,9 @@ impl Op {
                     )
                 }
 
-                if self == Self::Div {
+                if *self == Self::Div {
                     Typ::Rat
-                } else if self == Self::Mod {
+                } else if *self == Self::Mod {
                     Typ::Int
                 } else if self.is_arith_relation() {
                     Typ::Bool
@@ -468,7 +1110,7 @@ impl Op {
             }
 
             Self::Eq => {
-                let mut typs = args.iter().map(PExpr::typ);
+                let mut typs = args.iter().map(E::typ);
                 let first = typs.next().unwrap();
                 for typ in typs {
                     if typ != first {
@@ -482,6 +1124,80 @@ impl Op {
                 }
                 Typ::Bool
             }
+
+            Self::BvAnd | Self::BvOr | Self::BvXor | Self::Shl | Self::Shr => {
+                let mut typs = args.iter().map(E::typ);
+                let first = typs.next().expect("at least one argument");
+                if first.bv_width().is_none() {
+                    bail!(
+                        "`{}`'s arguments must have a bitvector type, unexpected type `{}`",
+                        self,
+                        first,
+                    )
+                }
+                for typ in typs {
+                    if typ != first {
+                        bail!(
+                            "`{}`'s arguments must all have the same type, found `{}` and `{}`",
+                            self,
+                            first,
+                            typ,
+                        )
+                    }
+                }
+                first
+            }
+
+            Self::Select | Self::Store => {
+                let arr_typ = args[0].typ();
+                let (idx_typ, elm_typ) = match arr_typ.array_typs() {
+                    Some(typs) => typs,
+                    None => bail!(
+                        "`{}`'s first argument must be an array, unexpected type `{}`",
+                        self,
+                        arr_typ,
+                    ),
+                };
+                let typ = args[1].typ();
+                if typ != *idx_typ {
+                    bail!(
+                        "`{}`'s second argument must be an index of type `{}`, found `{}`",
+                        self,
+                        idx_typ,
+                        typ,
+                    )
+                }
+                if *self == Self::Select {
+                    elm_typ.clone()
+                } else {
+                    let typ = args[2].typ();
+                    if typ != *elm_typ {
+                        bail!(
+                            "`{}`'s third argument must be an element of type `{}`, found `{}`",
+                            self,
+                            elm_typ,
+                            typ,
+                        )
+                    }
+                    arr_typ.clone()
+                }
+            }
+
+            Self::Fun(fun) => {
+                for (idx, (arg, expected)) in args.iter().zip(fun.args.iter()).enumerate() {
+                    let typ = arg.typ();
+                    if typ != *expected {
+                        bail!(
+                            "`{}`'s argument {} must have type `{}`, found `{}`",
+                            self,
+                            idx + 1,
+                            expected,
+                            typ,
+                        )
+                    }
+                }
+                fun.out.clone()
+            }
         };
 
         Ok(typ)
@@ -502,7 +1218,7 @@ impl Op {
             // Is `prev` the last argument?
             if let Some(next) = tail.next() {
                 // No, yield `true` if `prev` is false, implication is trivally true.
-                if !next.as_bool()? {
+                if !prev.as_bool()? {
                     break Ok(Cst::bool(true));
                 } else {
                     prev = next;
@@ -531,7 +1247,7 @@ impl Op {
     }
 
     /// Applies the operator to a vector of constants.
-    pub fn eval(self, args: Vec<Cst>) -> Res<Cst> {
+    pub fn eval(&self, args: Vec<Cst>) -> Res<Cst> {
         let arg_count = args.len();
         if arg_count < self.min_arity() {
             bail!(
@@ -595,14 +1311,14 @@ impl Op {
             (rel $op:tt) => ({
                 let (fst, mut prev) = (args.next().unwrap(), args.next().unwrap());
                 let snd = prev.clone();
-                let mut res = arith_op!(rel fst <= snd)?;
+                let mut res = arith_op!(rel fst $op snd)?;
                 if !res.as_bool()? {
                     Ok(Cst::B(false))
                 } else {
                     loop {
                         if let Some(next) = args.next() {
                             let current = next.clone();
-                            res = arith_op!(rel prev <= current)?;
+                            res = arith_op!(rel prev $op current)?;
                             if !res.as_bool()? {
                                 break Ok(Cst::B(false));
                             }
@@ -701,6 +1417,9 @@ impl Op {
 
             Self::Not => Ok(Cst::B(!args.next().unwrap().as_bool()?)),
 
+            Self::Fun(fun) => bail!("cannot evaluate uninterpreted function `{}`", fun.name),
+            Self::Forall(_) | Self::Exists(_) => bail!("cannot evaluate quantifier `{}`", self),
+
             Self::And => loop {
                 if let Some(next) = args.next() {
                     if !next.as_bool()? {
@@ -723,6 +1442,63 @@ impl Op {
                     break Ok(Cst::B(false));
                 }
             },
+
+            Self::BvAnd => Self::eval_nary_binop(
+                |lft, rgt| Self::eval_bv_binop(&Self::BvAnd, lft, rgt, |l, r| l & r),
+                args.next().unwrap(),
+                args.next().unwrap(),
+                args,
+            ),
+            Self::BvOr => Self::eval_nary_binop(
+                |lft, rgt| Self::eval_bv_binop(&Self::BvOr, lft, rgt, |l, r| l | r),
+                args.next().unwrap(),
+                args.next().unwrap(),
+                args,
+            ),
+            Self::BvXor => Self::eval_nary_binop(
+                |lft, rgt| Self::eval_bv_binop(&Self::BvXor, lft, rgt, |l, r| l ^ r),
+                args.next().unwrap(),
+                args.next().unwrap(),
+                args,
+            ),
+            Self::Shl | Self::Shr => {
+                let (lft, rgt) = (args.next().unwrap(), args.next().unwrap());
+                let width = lft.typ().bv_width().unwrap_or(0);
+                Self::eval_bv_binop(self, lft, rgt, |val, shift| {
+                    use num::ToPrimitive;
+                    // Shifting by the width or more yields zero, `shift` might be huge.
+                    match shift.to_usize() {
+                        Some(shift) if shift >= width as usize => Int::zero(),
+                        Some(shift) if *self == Self::Shl => val << shift,
+                        Some(shift) => val >> shift,
+                        None => Int::zero(),
+                    }
+                })
+            }
+
+            Self::Select => {
+                let arr = args.next().unwrap();
+                arr.select(&args.next().unwrap())
+            }
+            Self::Store => {
+                let arr = args.next().unwrap();
+                arr.store(args.next().unwrap(), args.next().unwrap())
+            }
+        }
+    }
+
+    /// Applies a binary bitvector operator, the result is taken modulo `2^width`.
+    fn eval_bv_binop(&self, lft: Cst, rgt: Cst, op: impl Fn(Int, Int) -> Int) -> Res<Cst> {
+        match (lft, rgt) {
+            (Cst::BV(w_1, lft), Cst::BV(w_2, rgt)) if w_1 == w_2 => Ok(Cst::bv(w_1, op(lft, rgt))),
+            (lft, rgt) => bail!(
+                "cannot apply `{}` to `{}: {}` and `{}: {}`",
+                self,
+                lft,
+                lft.typ(),
+                rgt,
+                rgt.typ(),
+            ),
         }
     }
 }
@@ -748,6 +1524,22 @@ impl Expr2Smt<()> for Op {
                 Self::Not => "not",
                 Self::And => "and",
                 Self::Or => "or",
+                Self::BvAnd => "bvand",
+                Self::BvOr => "bvor",
+                Self::BvXor => "bvxor",
+                Self::Shl => "bvshl",
+                Self::Shr => "bvlshr",
+                Self::Select => "select",
+                Self::Store => "store",
+                Self::Fun(fun) => return fun.sym_to_smt2(w, ()),
+                Self::Forall(binders) => {
+                    write!(w, "forall ")?;
+                    return binders.expr_to_smt2(w, ());
+                }
+                Self::Exists(binders) => {
+                    write!(w, "exists ")?;
+                    return binders.expr_to_smt2(w, ());
+                }
             }
         )?;
         Ok(())
@@ -808,18 +1600,18 @@ impl HasTyp for Var {
     /// assert_eq!(var.typ(), Typ::Bool);
     /// ```
     fn typ(&self) -> Typ {
-        self.typ
+        self.typ.clone()
     }
 }
 impl Sym2Smt<Unroll> for Var {
     fn sym_to_smt2<W: Write>(&self, w: &mut W, step: Unroll) -> SmtRes<()> {
-        write!(w, "{}@{}", self.id, step)?;
+        decls::write_sym(w, &self.id, Some(step))?;
         Ok(())
     }
 }
 impl Sym2Smt<()> for Var {
     fn sym_to_smt2<W: Write>(&self, w: &mut W, _step: ()) -> SmtRes<()> {
-        write!(w, "{}", self.id)?;
+        decls::write_sym(w, &self.id, None)?;
         Ok(())
     }
 }
@@ -894,13 +1686,51 @@ impl SVar {
 }
 impl Sym2Smt<Unroll> for SVar {
     fn sym_to_smt2<W: Write>(&self, w: &mut W, step: Unroll) -> SmtRes<()> {
-        write!(w, "{}@{}", self.id, if self.nxt { step + 1 } else { step })?;
+        let step = if self.nxt { step + 1 } else { step };
+        decls::write_sym(w, &self.id, Some(step))?;
         Ok(())
     }
 }
 impl HasTyp for SVar {
     fn typ(&self) -> Typ {
-        self.typ
+        self.typ.clone()
+    }
+}
+
+/// Type of an operator application, computed when the application is type-checked.
+///
+/// Applications can only be built by type-checking them, see [`PExpr::new_op`] and
+/// [`PExpr::new_app`], so that querying the type of an expression never fails. Completely
+/// transparent to comparisons: two applications are equal regardless of their cached type.
+#[derive(Debug, Clone)]
+pub struct TypCache {
+    /// Type of the application.
+    typ: Typ,
+}
+impl TypCache {
+    /// Cache storing the type of a type-checked application.
+    pub(crate) fn of_typ(typ: Typ) -> Self {
+        Self { typ }
+    }
+    /// Type of the application.
+    pub fn get(&self) -> Typ {
+        self.typ.clone()
+    }
+}
+impl PartialEq for TypCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+impl Eq for TypCache {}
+impl PartialOrd for TypCache {
+    fn partial_cmp(&self, that: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(that))
+    }
+}
+impl Ord for TypCache {
+    fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
     }
 }
 
@@ -924,6 +1754,8 @@ pub enum PExpr<V> {
         op: Op,
         /// The arguments.
         args: Vec<PExpr<V>>,
+        /// Type of the application, set when it is type-checked.
+        typ: TypCache,
     },
 }
 impl<V> PExpr<V> {
@@ -967,28 +1799,50 @@ impl<V> PExpr<V> {
     where
         V: HasTyp,
     {
-        op.type_check(&args)?;
-        Ok(Self::simplify_app(op, args))
+        let typ = op.type_check(&args)?;
+        Ok(Self::simplify_app(op, args, typ))
+    }
+
+    /// Operator application constructor, without simplification.
+    ///
+    /// Unlike [`PExpr::new_op`], the application is kept as is even if it could be simplified.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, Cst, Op, PExpr};
+    /// let expr: expr::Expr = PExpr::new_app(Op::Sub, vec![Cst::int(7).into()]).unwrap();
+    /// assert_eq!(expr.to_string(), "(- 7)");
+    /// let folded: expr::Expr = PExpr::new_op(Op::Sub, vec![Cst::int(7).into()]).unwrap();
+    /// assert_eq!(folded, Cst::int(-7).into());
+    ///
+    /// let bad = PExpr::new_app(Op::And, vec![expr, Cst::bool(true).into()]).unwrap_err();
+    /// assert_eq!(bad.to_string(), "`and`'s arguments must all be boolean expressions");
+    /// ```
+    pub fn new_app(op: Op, args: Vec<Self>) -> Res<Self>
+    where
+        V: HasTyp,
+    {
+        let typ = TypCache::of_typ(op.type_check(&args)?);
+        Ok(Self::App { op, args, typ })
     }
 
     /// Simplifies the application of `op` to `args`, **non-recursively**.
-    fn simplify_app(op: Op, mut args: Vec<Self>) -> Self {
-        match (op, args.len()) {
+    ///
+    /// Argument `typ` is the type of the application.
+    fn simplify_app(op: Op, mut args: Vec<Self>, typ: Typ) -> Self {
+        let typ = TypCache::of_typ(typ);
+        match (&op, args.len()) {
             (Op::Sub, 1) if args[0].is_cst() => match &args[0] {
                 Self::Cst(Cst::I(i)) => Cst::I(-i).into(),
                 Self::Cst(Cst::R(r)) => Cst::R(-r).into(),
                 Self::Cst(Cst::B(_)) => panic!("trying to apply `{}` to a boolean", op),
-                _ => Self::App { op, args },
-            },
-            (Op::Div, 2) if args[0].is_cst() && args[1].is_cst() => match (&args[0], &args[1]) {
-                (Self::Cst(Cst::I(lft)), Self::Cst(Cst::I(rgt))) => Cst::I(lft - rgt).into(),
-                (Self::Cst(Cst::R(lft)), Self::Cst(Cst::R(rgt))) => Cst::R(lft - rgt).into(),
-                _ => panic!("trying to apply `{}` to arguments of unexpected type", op),
+                _ => Self::App { op, args, typ },
             },
             (Op::Add, 1) | (Op::And, 1) | (Op::Or, 1) => {
                 args.pop().expect("[unreachable] pop on vec of len `1`")
             }
-            _ => Self::App { op, args },
+            _ => Self::App { op, args, typ },
         }
     }
 
@@ -1078,7 +1932,7 @@ impl<V> PExpr<V> {
             let mut acc = match current {
                 Self::Var(var) => var_action(var),
                 Self::Cst(cst) => cst_action(cst),
-                Self::App { op, args } => {
+                Self::App { op, args, .. } => {
                     let mut todo = args.iter();
 
                     // At least one argument?
@@ -1088,13 +1942,13 @@ impl<V> PExpr<V> {
                         // Empty-for-now vector of accumulators.
                         let res = Vec::with_capacity(args.len());
                         // Push frame on the stack for when we go up.
-                        stack.push((*op, res, todo));
+                        stack.push((op.clone(), res, todo));
 
                         continue 'go_down;
                     } else {
                         // No argument, this should actually not happen, but it's not a problem for
                         // folding so we might as well just handle it.
-                        app_action(*op, vec![])
+                        app_action(op.clone(), vec![])
                     }
                 }
             };
@@ -1127,16 +1981,117 @@ impl<V> PExpr<V> {
             return acc;
         }
     }
+
+    /// Variables appearing in an expression.
+    ///
+    /// Variables bound by quantifiers are included.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, Expr, Typ, Var};
+    /// let expr: Expr = expr::build!((and (b: bool) (> (x: int) (y: int)) (= (x: int) 0)));
+    /// let vars: Vec<Var> = expr.vars().into_iter().collect();
+    /// assert_eq!(
+    ///     vars,
+    ///     vec![Var::new("b", Typ::Bool), Var::new("x", Typ::Int), Var::new("y", Typ::Int)],
+    /// );
+    /// ```
+    pub fn vars(&self) -> Set<V>
+    where
+        V: Clone + Ord,
+    {
+        let mut vars = Set::new();
+        self.fold(
+            |var| {
+                let _ = vars.insert(var.clone());
+            },
+            |_| (),
+            |_, _| (),
+        );
+        vars
+    }
+}
+impl<V: HasTyp> PExpr<V> {
+    /// Replaces some variables by expressions, simultaneously.
+    ///
+    /// Replacements are not substituted themselves, so `x ↦ y, y ↦ x` swaps `x` and `y`. Variables
+    /// bound by quantifiers are not special, they are substituted like the other variables.
+    ///
+    /// # Errors
+    ///
+    /// - when a replacement does not have the type its variable has in the expression.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, Expr, Typ, Var};
+    /// # use std::collections::BTreeMap as Map;
+    /// let (x, y) = (Var::new("x", Typ::Int), Var::new("y", Typ::Int));
+    /// let expr: Expr = expr::build!((>= (x: int) (+ (y: int) 1)));
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(x.clone(), expr::build!((* 2 (y: int))));
+    /// map.insert(y.clone(), expr::build!((x: int)));
+    /// assert_eq!(expr.substitute(&map).unwrap().to_string(), "(>= (* 2 y) (+ x 1))");
+    ///
+    /// map.insert(y, expr::build!((b: bool)));
+    /// assert!(expr.substitute(&map).is_err());
+    /// ```
+    pub fn substitute(&self, map: &Map<V, Self>) -> Res<Self>
+    where
+        V: Clone + Ord,
+    {
+        self.rebuild(|var| {
+            map.get(var)
+                .cloned()
+                .unwrap_or_else(|| Self::new_var(var.clone()))
+        })
+    }
+
+    /// Renames the variables of an expression, possibly to a different kind of variables.
+    ///
+    /// Variables bound by quantifiers are renamed like the other variables.
+    ///
+    /// # Errors
+    ///
+    /// - when a variable is renamed to a variable of a different type, making an application
+    ///   ill-typed.
+    ///
+    /// # Examples
+    ///
+    /// Priming a stateless expression, for instance to use it in a transition relation.
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, Expr, HasTyp, SExpr, SVar, Typ, Var};
+    /// let expr: Expr = expr::build!((and (b: bool) (> (x: int) 0)));
+    /// let primed: SExpr = expr.rename(|var| SVar::new_next(var.clone())).unwrap();
+    /// assert_eq!(primed.to_string(), "(and b@1 (> x@1 0))");
+    ///
+    /// let renamed = expr.rename(|var| Var::new(format!("{}_0", var.id()), var.typ())).unwrap();
+    /// assert_eq!(renamed.to_string(), "(and b_0 (> x_0 0))");
+    ///
+    /// assert!(expr.rename(|var| Var::new(var.id(), Typ::Bool)).is_err());
+    /// ```
+    pub fn rename<W: HasTyp>(&self, mut rename: impl FnMut(&V) -> W) -> Res<PExpr<W>> {
+        self.rebuild(|var| PExpr::new_var(rename(var)))
+    }
+
+    /// Rebuilds an expression bottom-up, replacing variables with `var`.
+    fn rebuild<W: HasTyp>(&self, mut var: impl FnMut(&V) -> PExpr<W>) -> Res<PExpr<W>> {
+        self.fold(
+            |v| Ok(var(v)),
+            |cst| Ok(PExpr::new_cst(cst.clone())),
+            |op, args: Vec<Res<PExpr<W>>>| PExpr::new_op(op, args.into_iter().collect::<Res<_>>()?),
+        )
+    }
 }
 impl<V: HasTyp> HasTyp for PExpr<V> {
     fn typ(&self) -> Typ {
         match self {
             Self::Var(var) => var.typ(),
             Self::Cst(cst) => cst.typ(),
-            Self::App { op, args } => match op.type_check(args) {
-                Ok(typ) => typ,
-                Err(e) => panic!("illegal operator application `{}`: {}", self, e),
-            },
+            Self::App { typ, .. } => typ.get(),
         }
     }
 }
@@ -1145,7 +2100,7 @@ impl<Info: Copy, V: Sym2Smt<Info>> Expr2Smt<Info> for PExpr<V> {
         match self {
             Self::Cst(cst) => cst.expr_to_smt2(w, ()),
             Self::Var(var) => var.sym_to_smt2(w, i),
-            Self::App { op, args } => {
+            Self::App { op, args, .. } => {
                 write!(w, "(")?;
                 op.expr_to_smt2(w, ())?;
                 for arg in args {
@@ -1161,9 +2116,133 @@ impl<Info: Copy, V: Sym2Smt<Info>> Expr2Smt<Info> for PExpr<V> {
 
 /// A simple (stateless) expression.
 pub type Expr = PExpr<Var>;
+impl Expr {
+    /// Turns a stateless expression into a stateful one, at the next step if `at_next`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, Expr};
+    /// let expr: Expr = expr::build!((and (b: bool) (> (x: int) 0)));
+    /// assert_eq!(expr.clone().into_sexpr(false).to_string(), "(and b@0 (> x@0 0))");
+    /// assert_eq!(expr.into_sexpr(true).to_string(), "(and b@1 (> x@1 0))");
+    /// ```
+    pub fn into_sexpr(self, at_next: bool) -> SExpr {
+        self.rename(|var| SVar::new(var.clone(), at_next))
+            .expect("[unreachable] renaming to a state variable preserves types")
+    }
+}
 
 /// A stateful expression.
 pub type SExpr = PExpr<SVar>;
+impl SExpr {
+    /// Variables appearing in an expression at the current step.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse;
+    /// let sys = parse::trans(r#"
+    /// svars { x y: int, b: bool }
+    /// init { x = 0 }
+    /// trans { 'x = x + y, 'b = b }
+    /// candidates { "x": x ≥ 0 }
+    /// "#).unwrap();
+    /// let ids = |vars: std::collections::BTreeSet<mikino_api::expr::Var>| {
+    ///     vars.iter().map(|var| var.id().to_string()).collect::<Vec<_>>()
+    /// };
+    /// assert_eq!(ids(sys.trans().current_vars()), vec!["b", "x", "y"]);
+    /// assert_eq!(ids(sys.trans().next_vars()), vec!["b", "x"]);
+    /// ```
+    pub fn current_vars(&self) -> Set<Var> {
+        self.step_vars(false)
+    }
+    /// Variables appearing in an expression at the next step.
+    ///
+    /// See [`Self::current_vars`] for an example.
+    pub fn next_vars(&self) -> Set<Var> {
+        self.step_vars(true)
+    }
+
+    /// Turns a stateful expression into a stateless one.
+    ///
+    /// # Errors
+    ///
+    /// - when the expression mentions both current and next state variables.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, SExpr};
+    /// let sexpr: SExpr = expr::build!((and (b @ 0: bool) (> (x @ 0: int) 0)));
+    /// assert_eq!(sexpr.try_into_expr().unwrap().to_string(), "(and b (> x 0))");
+    ///
+    /// let sexpr: SExpr = expr::build!((and (b @ 1: bool) (> (x @ 1: int) 0)));
+    /// assert_eq!(sexpr.try_into_expr().unwrap().to_string(), "(and b (> x 0))");
+    ///
+    /// let sexpr: SExpr = expr::build!((= (x @ 0: int) (x @ 1: int)));
+    /// let err = sexpr.try_into_expr().unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "cannot turn `(= x@0 x@1)` into a stateless expression, \
+    ///     it mentions both current and next state variables",
+    /// );
+    /// ```
+    pub fn try_into_expr(self) -> Res<Expr> {
+        if !self.current_vars().is_empty() && !self.next_vars().is_empty() {
+            bail!(
+                "cannot turn `{}` into a stateless expression, \
+                it mentions both current and next state variables",
+                self
+            )
+        }
+        self.rename(|svar| svar.var.clone())
+    }
+
+    /// Moves a stateful expression one step forward, current state variables become next state
+    /// variables.
+    ///
+    /// # Errors
+    ///
+    /// - when the expression mentions next state variables.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, SExpr};
+    /// let sexpr: SExpr = expr::build!((and (b @ 0: bool) (> (x @ 0: int) 0)));
+    /// assert_eq!(sexpr.shift().unwrap().to_string(), "(and b@1 (> x@1 0))");
+    ///
+    /// let sexpr: SExpr = expr::build!((= (x @ 1: int) (+ (x @ 0: int) 1)));
+    /// let err = sexpr.shift().unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "cannot shift `(= x@1 (+ x@0 1))`, it mentions next state variables",
+    /// );
+    /// ```
+    pub fn shift(self) -> Res<Self> {
+        if !self.next_vars().is_empty() {
+            bail!("cannot shift `{}`, it mentions next state variables", self)
+        }
+        self.rename(|svar| SVar::new_next(svar.var.clone()))
+    }
+
+    /// Variables appearing in an expression at the next step if `next`, at the current step
+    /// otherwise.
+    fn step_vars(&self, next: bool) -> Set<Var> {
+        let mut vars = Set::new();
+        self.fold(
+            |svar| {
+                if svar.is_next() == next {
+                    let _ = vars.insert(svar.var.clone());
+                }
+            },
+            |_| (),
+            |_, _| (),
+        );
+        vars
+    }
+}
 
 /// Represents the negation of a borrowed expression.
 ///
@@ -1215,6 +2294,9 @@ mod trait_impls {
                 Self::Bool => write!(fmt, "bool"),
                 Self::Int => write!(fmt, "int"),
                 Self::Rat => write!(fmt, "rat"),
+                Self::BitVec(width) => write!(fmt, "bv<{}>", width),
+                Self::Array(idx, elm) => write!(fmt, "array<{}, {}>", idx, elm),
+                Self::Enum(name) => name.fmt(fmt),
             }
         }
     }
@@ -1238,6 +2320,16 @@ mod trait_impls {
                 Self::Not => write!(fmt, "not"),
                 Self::And => write!(fmt, "and"),
                 Self::Or => write!(fmt, "or"),
+                Self::BvAnd => write!(fmt, "bvand"),
+                Self::BvOr => write!(fmt, "bvor"),
+                Self::BvXor => write!(fmt, "bvxor"),
+                Self::Shl => write!(fmt, "bvshl"),
+                Self::Shr => write!(fmt, "bvlshr"),
+                Self::Select => write!(fmt, "select"),
+                Self::Store => write!(fmt, "store"),
+                Self::Fun(fun) => fun.name.fmt(fmt),
+                Self::Forall(binders) => write!(fmt, "forall {}", binders),
+                Self::Exists(binders) => write!(fmt, "exists {}", binders),
             }
         }
     }
@@ -1262,6 +2354,31 @@ mod trait_impls {
                         _ => write!(fmt, "(/ {} {})", num, den),
                     }
                 }
+                Self::BV(width, val) => write!(fmt, "(_ bv{} {})", val, width),
+                Self::Array {
+                    idx,
+                    default,
+                    entries,
+                } => {
+                    for _ in entries.iter() {
+                        write!(fmt, "(store ")?
+                    }
+                    let mut sort = vec![];
+                    Typ::array(idx.clone(), default.typ())
+                        .sort_to_smt2(&mut sort)
+                        .map_err(|_| fmt::Error)?;
+                    write!(
+                        fmt,
+                        "((as const {}) {})",
+                        String::from_utf8_lossy(&sort),
+                        default
+                    )?;
+                    for (idx, val) in entries.iter() {
+                        write!(fmt, " {} {})", idx, val)?
+                    }
+                    Ok(())
+                }
+                Self::Enum { typ, variant } => write!(fmt, "{}::{}", typ, variant),
             }
         }
     }
@@ -1315,7 +2432,7 @@ mod trait_impls {
             match self {
                 Self::Cst(cst) => cst.fmt(fmt),
                 Self::Var(var) => var.fmt(fmt),
-                Self::App { op, args } => {
+                Self::App { op, args, .. } => {
                     write!(fmt, "({}", op)?;
                     for arg in args {
                         write!(fmt, " {}", arg)?
@@ -1333,11 +2450,6 @@ mod trait_impls {
             Self::Cst(cst.into())
         }
     }
-    impl<V> From<(Op, Vec<PExpr<V>>)> for PExpr<V> {
-        fn from((op, args): (Op, Vec<PExpr<V>>)) -> Self {
-            Self::App { op, args }
-        }
-    }
 }
 
 /// A meta-variable.
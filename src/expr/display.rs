@@ -0,0 +1,193 @@
+//! Display hints and value formatters.
+//!
+//! Variable declarations can carry display hints as lines of their documentation:
+//!
+//! ```text
+//! svars {
+//!     /// Elapsed time.
+//!     /// @unit: ms
+//!     cnt: int,
+//!     /// Status register.
+//!     /// @display: hex
+//!     status: int,
+//! }
+//! ```
+//!
+//! A [`Hint`] is honored by [`Formatters::render`], which counterexample and model renderers use
+//! to print values. Formatters are looked up by name in a [`Formatters`] registry, which comes with
+//! a few builtin formatters (see [`Formatters::new`]) and can be extended programmatically.
+
+crate::prelude!();
+
+use std::sync::Arc;
+
+use num::Signed;
+
+use super::Cst;
+
+/// Display hints for a variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hint {
+    /// Name of the formatter to use, from `@display: <name>`.
+    pub display: Option<String>,
+    /// Unit of the variable, from `@unit: <unit>`.
+    pub unit: Option<String>,
+}
+impl Hint {
+    /// True if the hint is empty.
+    pub fn is_empty(&self) -> bool {
+        self.display.is_none() && self.unit.is_none()
+    }
+
+    /// Extracts hints from some documentation lines.
+    ///
+    /// Lines of the form `@<key>: <value>` are hints, all other lines are ignored.
+    ///
+    /// # Errors
+    ///
+    /// - on unknown keys, and
+    /// - when the same key appears twice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::display::Hint;
+    /// let hint = Hint::of_doc(&["Time counter.", "@display: hex", "@unit: ms"]).unwrap();
+    /// assert_eq!(hint.display.as_deref(), Some("hex"));
+    /// assert_eq!(hint.unit.as_deref(), Some("ms"));
+    ///
+    /// let err = Hint::of_doc(&["@colour: red"]).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "unknown display hint `@colour`, expected `@display` or `@unit`",
+    /// );
+    /// ```
+    pub fn of_doc<S: AsRef<str>>(lines: &[S]) -> Res<Self> {
+        let mut hint = Self::default();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if !line.starts_with('@') {
+                continue;
+            }
+            let (key, val) = match line.find(':') {
+                Some(idx) => (line[1..idx].trim(), line[idx + 1..].trim()),
+                None => continue,
+            };
+            let target = match key {
+                "display" => &mut hint.display,
+                "unit" => &mut hint.unit,
+                _ => bail!(
+                    "unknown display hint `@{}`, expected `@display` or `@unit`",
+                    key
+                ),
+            };
+            if target.is_some() {
+                bail!("display hint `@{}` is specified twice", key)
+            }
+            *target = Some(val.to_string());
+        }
+        Ok(hint)
+    }
+}
+
+/// A value formatter, yields `None` if it does not apply to a value.
+pub type Formatter = Arc<dyn Fn(&Cst) -> Option<String> + Send + Sync>;
+
+/// Registry of formatters, see the [module-level documentation](self).
+#[derive(Clone)]
+pub struct Formatters {
+    /// Maps names to formatters.
+    fmts: Map<String, Formatter>,
+}
+impl fmt::Debug for Formatters {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_set().entries(self.fmts.keys()).finish()
+    }
+}
+impl Default for Formatters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Formatters {
+    /// Empty registry.
+    pub fn empty() -> Self {
+        Self { fmts: Map::new() }
+    }
+
+    /// Registry with the builtin formatters.
+    ///
+    /// - `hex`, `oct` and `bin`: integers in base 16, 8 and 2;
+    /// - `decimal`: rationals as (truncated) decimal numbers with six digits after the dot.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{display::{Formatters, Hint}, Cst};
+    /// let fmts = Formatters::new();
+    /// let hint = Hint {
+    ///     display: Some("hex".into()),
+    ///     unit: Some("ms".into()),
+    /// };
+    /// assert_eq!(fmts.render(Some(&hint), &Cst::int(255)), "0xff ms");
+    /// assert_eq!(fmts.render(Some(&hint), &Cst::int(-255)), "-0xff ms");
+    /// // Formatter does not apply to booleans, fall back on the default.
+    /// assert_eq!(fmts.render(Some(&hint), &Cst::bool(true)), "true ms");
+    /// assert_eq!(fmts.render(None, &Cst::int(255)), "255");
+    /// ```
+    pub fn new() -> Self {
+        let mut slf = Self::empty();
+        for (name, prefix, radix) in [("hex", "0x", 16), ("oct", "0o", 8), ("bin", "0b", 2)] {
+            slf.register(name, move |cst| {
+                let i = cst.as_int().ok()?;
+                let sign = if i.is_negative() { "-" } else { "" };
+                Some(format!("{}{}{}", sign, prefix, i.abs().to_str_radix(radix)))
+            });
+        }
+        slf.register("decimal", |cst| {
+            let r = cst.as_rat().ok()?;
+            let sign = if r.is_negative() { "-" } else { "" };
+            let r = r.abs();
+            let int = r.trunc().to_integer();
+            let frac = ((r.fract() * Int::from(1_000_000)).trunc()).to_integer();
+            Some(format!("{}{}.{:0>6}", sign, int, frac))
+        });
+        slf
+    }
+
+    /// Registers a formatter, returns the formatter previously registered under `name`, if any.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        fmt: impl Fn(&Cst) -> Option<String> + Send + Sync + 'static,
+    ) -> Option<Formatter> {
+        self.fmts.insert(name.into(), Arc::new(fmt))
+    }
+
+    /// Retrieves a formatter.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&Formatter> {
+        self.fmts.get(name.as_ref())
+    }
+
+    /// Renders a value given an optional hint.
+    ///
+    /// Falls back on the default representation if the hint's formatter is unknown or does not
+    /// apply to the value.
+    pub fn render(&self, hint: Option<&Hint>, cst: &Cst) -> String {
+        let hint = match hint {
+            Some(hint) => hint,
+            None => return cst.to_string(),
+        };
+        let mut s = hint
+            .display
+            .as_ref()
+            .and_then(|name| self.get(name))
+            .and_then(|fmt| fmt(cst))
+            .unwrap_or_else(|| cst.to_string());
+        if let Some(unit) = hint.unit.as_ref() {
+            s.push(' ');
+            s.push_str(unit);
+        }
+        s
+    }
+}
@@ -0,0 +1,341 @@
+//! Hash-consed expressions, structurally shared and compared in constant time.
+//!
+//! A [`PExpr`] owns its subterms: a subterm appearing ten times in a transition relation is stored
+//! ten times, and checking two expressions for equality goes through both of them. A [`HConsign`]
+//! builds [`HExpr`]s instead, which are created once per distinct subterm and shared behind an
+//! [`Arc`]. Two hash-consed expressions are equal if and only if they are the same
+//! node, equality, ordering and hashing only look at the node's unique identifier.
+//!
+//! Uids are unique across consigns, so hash-consed expressions built by different consigns are
+//! never equal, even if they are structurally equal.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use mikino_api::expr::{self, hcons::HConsign, Var};
+//! let expr: expr::Expr = expr::build!(
+//!     (and (>= (v_1: int) 0) (v_2: bool) (or (v_2: bool) (>= (v_1: int) 0)))
+//! );
+//!
+//! let mut consign: HConsign<Var> = HConsign::new();
+//! let hexpr = consign.add(&expr).unwrap();
+//! // `v_1`, `0`, `(>= v_1 0)`, `v_2`, the disjunction and the conjunction.
+//! assert_eq!(consign.len(), 6);
+//! assert_eq!(hexpr.to_string(), expr.to_string());
+//! assert_eq!(hexpr.to_pexpr(), expr);
+//!
+//! // Building the same expression again yields the same node.
+//! assert_eq!(consign.add(&expr).unwrap(), hexpr);
+//! assert_eq!(consign.len(), 6);
+//! ```
+
+crate::prelude!();
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use rsmt2::print::{Expr2Smt, Sym2Smt};
+
+use super::{Cst, HasTyp, Op, PExpr, TypCache};
+
+/// Next unique identifier of hash-consed expressions, shared by all consigns.
+static NEXT_UID: AtomicUsize = AtomicUsize::new(0);
+
+/// Structure of a hash-consed expression, subterms are hash-consed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HKind<V> {
+    /// A constant.
+    Cst(Cst),
+    /// A variable.
+    Var(V),
+    /// An operator application.
+    App {
+        /// The operator.
+        op: Op,
+        /// The arguments.
+        args: Vec<HExpr<V>>,
+    },
+}
+
+/// Actual data behind a hash-consed expression.
+#[derive(Debug)]
+struct HNode<V> {
+    /// Unique identifier.
+    uid: usize,
+    /// Structure of the expression.
+    kind: HKind<V>,
+    /// Type of the expression.
+    typ: Typ,
+}
+
+/// Stack frame of [`HExpr::to_pexpr`]: operator, results for the first arguments, remaining
+/// arguments.
+type Frame<'a, V> = (Op, Typ, Vec<PExpr<V>>, std::slice::Iter<'a, HExpr<V>>);
+
+/// A hash-consed expression, see the [module-level documentation](self).
+///
+/// Cloning is cheap, it only increments a reference counter.
+#[derive(Debug)]
+pub struct HExpr<V> {
+    /// Shared node.
+    node: Arc<HNode<V>>,
+}
+impl<V> Clone for HExpr<V> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node.clone(),
+        }
+    }
+}
+impl<V> HExpr<V> {
+    /// Unique identifier of the expression.
+    pub fn uid(&self) -> usize {
+        self.node.uid
+    }
+    /// Structure of the expression.
+    pub fn kind(&self) -> &HKind<V> {
+        &self.node.kind
+    }
+
+    /// Rebuilds the polymorphic expression corresponding to a hash-consed expression.
+    ///
+    /// Sharing is lost, the result can be exponentially bigger than the number of nodes of
+    /// `self`. Stackless.
+    pub fn to_pexpr(&self) -> PExpr<V>
+    where
+        V: Clone,
+    {
+        let mut stack: Vec<Frame<V>> = vec![];
+        let mut current = self;
+
+        'go_down: loop {
+            let mut res = match current.kind() {
+                HKind::Cst(cst) => PExpr::Cst(cst.clone()),
+                HKind::Var(var) => PExpr::Var(var.clone()),
+                HKind::App { op, args } => {
+                    let mut todo = args.iter();
+                    let typ = current.node.typ.clone();
+                    if let Some(next) = todo.next() {
+                        current = next;
+                        stack.push((op.clone(), typ, Vec::with_capacity(args.len()), todo));
+                        continue 'go_down;
+                    } else {
+                        PExpr::App {
+                            op: op.clone(),
+                            args: vec![],
+                            typ: TypCache::of_typ(typ),
+                        }
+                    }
+                }
+            };
+
+            while let Some((op, typ, mut args, mut todo)) = stack.pop() {
+                args.push(res);
+                if let Some(next) = todo.next() {
+                    current = next;
+                    stack.push((op, typ, args, todo));
+                    continue 'go_down;
+                } else {
+                    let typ = TypCache::of_typ(typ);
+                    res = PExpr::App { op, args, typ };
+                }
+            }
+
+            return res;
+        }
+    }
+}
+impl<V: fmt::Display> HasTyp for HExpr<V> {
+    fn typ(&self) -> Typ {
+        self.node.typ.clone()
+    }
+}
+impl<V> PartialEq for HExpr<V> {
+    fn eq(&self, that: &Self) -> bool {
+        self.uid() == that.uid()
+    }
+}
+impl<V> Eq for HExpr<V> {}
+impl<V> PartialOrd for HExpr<V> {
+    fn partial_cmp(&self, that: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(that))
+    }
+}
+impl<V> Ord for HExpr<V> {
+    fn cmp(&self, that: &Self) -> std::cmp::Ordering {
+        self.uid().cmp(&that.uid())
+    }
+}
+impl<V> std::hash::Hash for HExpr<V> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.uid().hash(state)
+    }
+}
+impl<V: fmt::Display> fmt::Display for HExpr<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind() {
+            HKind::Cst(cst) => cst.fmt(fmt),
+            HKind::Var(var) => var.fmt(fmt),
+            HKind::App { op, args } => {
+                write!(fmt, "({}", op)?;
+                for arg in args {
+                    write!(fmt, " {}", arg)?
+                }
+                write!(fmt, ")")
+            }
+        }
+    }
+}
+impl<Info: Copy, V: Sym2Smt<Info>> Expr2Smt<Info> for HExpr<V> {
+    fn expr_to_smt2<W: Write>(&self, w: &mut W, i: Info) -> SmtRes<()> {
+        match self.kind() {
+            HKind::Cst(cst) => cst.expr_to_smt2(w, ()),
+            HKind::Var(var) => var.sym_to_smt2(w, i),
+            HKind::App { op, args } => {
+                write!(w, "(")?;
+                op.expr_to_smt2(w, ())?;
+                for arg in args {
+                    write!(w, " ")?;
+                    arg.expr_to_smt2(w, i)?
+                }
+                write!(w, ")")?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Builds hash-consed expressions, see the [module-level documentation](self).
+#[derive(Debug, Clone)]
+pub struct HConsign<V> {
+    /// Hash-consed expressions, indexed by their structure.
+    table: Map<HKind<V>, HExpr<V>>,
+}
+impl<V> Default for HConsign<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<V> HConsign<V> {
+    /// Constructor.
+    pub fn new() -> Self {
+        Self { table: Map::new() }
+    }
+
+    /// Number of distinct expressions in the consign.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+    /// True if the consign is empty.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+}
+impl<V: HasTyp + Clone + Ord> HConsign<V> {
+    /// Retrieves the hash-consed expression for `kind`, creates it if needed.
+    fn mk(&mut self, kind: HKind<V>, typ: impl FnOnce() -> Typ) -> HExpr<V> {
+        if let Some(hexpr) = self.table.get(&kind) {
+            return hexpr.clone();
+        }
+        let hexpr = HExpr {
+            node: Arc::new(HNode {
+                uid: NEXT_UID.fetch_add(1, Ordering::Relaxed),
+                kind: kind.clone(),
+                typ: typ(),
+            }),
+        };
+        let _ = self.table.insert(kind, hexpr.clone());
+        hexpr
+    }
+
+    /// Hash-consed constant.
+    pub fn cst(&mut self, cst: Cst) -> HExpr<V> {
+        let typ = cst.typ();
+        self.mk(HKind::Cst(cst), || typ)
+    }
+    /// Hash-consed variable.
+    pub fn var(&mut self, var: V) -> HExpr<V> {
+        let typ = var.typ();
+        self.mk(HKind::Var(var), || typ)
+    }
+    /// Hash-consed operator application.
+    ///
+    /// Unlike [`PExpr::new_op`], does not simplify the application.
+    ///
+    /// # Errors
+    ///
+    /// - when the application is ill-typed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{hcons::HConsign, Op, Typ, Var};
+    /// let mut consign: HConsign<Var> = HConsign::new();
+    /// let x = consign.var(Var::new("x", Typ::Int));
+    /// let zero = consign.cst(0.into());
+    /// let x_pos = consign.app(Op::Ge, vec![x.clone(), zero.clone()]).unwrap();
+    /// assert_eq!(x_pos.to_string(), "(>= x 0)");
+    /// assert_eq!(consign.app(Op::Ge, vec![x, zero]).unwrap(), x_pos);
+    ///
+    /// let b = consign.var(Var::new("b", Typ::Bool));
+    /// assert!(consign.app(Op::And, vec![b, x_pos.clone()]).is_ok());
+    /// assert!(consign.app(Op::Add, vec![x_pos]).is_err());
+    /// ```
+    pub fn app(&mut self, op: Op, args: Vec<HExpr<V>>) -> Res<HExpr<V>> {
+        let typ = op.type_check(&args)?;
+        Ok(self.mk(HKind::App { op, args }, || typ))
+    }
+
+    /// Hash-consed version of a polymorphic expression.
+    ///
+    /// # Errors
+    ///
+    /// - when `expr` has ill-typed applications.
+    pub fn add(&mut self, expr: &PExpr<V>) -> Res<HExpr<V>> {
+        let consign = std::cell::RefCell::new(self);
+        expr.fold(
+            |var| Ok(consign.borrow_mut().var(var.clone())),
+            |cst| Ok(consign.borrow_mut().cst(cst.clone())),
+            |op, args: Vec<Res<HExpr<V>>>| {
+                let args = args.into_iter().collect::<Res<_>>()?;
+                consign.borrow_mut().app(op, args)
+            },
+        )
+    }
+
+    /// Forgets the expressions that are only referenced by the consign.
+    ///
+    /// Expressions still in use elsewhere, and their subterms, are kept. Returns the number of
+    /// expressions forgotten.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, hcons::HConsign, Var};
+    /// let mut consign: HConsign<Var> = HConsign::new();
+    /// let kept = consign.add(&expr::build!((> (x: int) 0))).unwrap();
+    /// let _ = consign.add(&expr::build!((< (x: int) 7))).unwrap();
+    /// assert_eq!(consign.len(), 5);
+    /// // Only `7` and `(< x 7)` are not used outside of the consign.
+    /// assert_eq!(consign.collect(), 2);
+    /// assert_eq!(consign.len(), 3);
+    /// assert_eq!(consign.add(&expr::build!((> (x: int) 0))).unwrap(), kept);
+    /// ```
+    pub fn collect(&mut self) -> usize {
+        let len = self.table.len();
+        // An expression is referenced by its entry in the table, and twice by each parent: in
+        // the parent's node and in the parent's key. Removing an expression can orphan its
+        // arguments.
+        loop {
+            let before = self.table.len();
+            self.table
+                .retain(|_, hexpr| Arc::strong_count(&hexpr.node) > 1);
+            if self.table.len() == before {
+                break;
+            }
+        }
+        len - self.table.len()
+    }
+}
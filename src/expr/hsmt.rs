@@ -0,0 +1,314 @@
+//! Human-SMT-LIB 2 (hsmt) syntax of expressions.
+//!
+//! The [`fmt::Display`] implementation of [`PExpr`] yields SMT-LIB 2 s-expressions, which the hsmt
+//! parser rejects. [`PExpr::to_hsmt`] yields hsmt syntax instead, so that expressions can be
+//! parsed, transformed and printed back. Parentheses are only inserted where the
+//! [precedence](Op::precedence) of the operators requires them.
+//!
+//! Some expressions have no hsmt syntax: integer divisions (`div`) and array constants only
+//! appear in expressions coming from SMT-LIB 2 or from a solver. Rational constants are written as
+//! decimals when they have a finite decimal expansion, as a division otherwise: `1.0 / 3.0` parses
+//! back as an application of `/`, not as a constant.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use mikino_api::{expr::{self, Expr, SExpr}, parse, trans};
+//! let expr: Expr = expr::build!(
+//!     (and (or (a: bool) (b: bool)) (>= (* (+ (x: int) 1) 2) (- 7)))
+//! );
+//! assert_eq!(expr.to_string(), "(and (or a b) (>= (* (+ x 1) 2) (- 7)))");
+//! assert_eq!(expr.to_hsmt().unwrap(), "(a ⋁ b) ⋀ (x + 1) * 2 ≥ -7");
+//!
+//! let sexpr: SExpr = expr::build!(
+//!     (= (x @ 1: int) (ite (> (x @ 0: int) 0) (- (x @ 0: int) 1) (x @ 0: int)))
+//! );
+//! assert_eq!(sexpr.to_hsmt().unwrap(), "'x = if x > 0 { x - 1 } else { x }");
+//!
+//! let mut decls = trans::Decls::new();
+//! let _ = decls.register("r", expr::Typ::Rat);
+//! let expr = parse::expr("r ≥ -0.125 ⋀ r < 2.", &decls).unwrap();
+//! assert_eq!(expr.to_hsmt().unwrap(), "r ≥ -0.125 ⋀ r < 2.0");
+//! ```
+
+crate::prelude!();
+
+use std::borrow::Cow;
+
+use num::Signed;
+
+use super::{Cst, HasTyp, Op, PExpr, Precedence, SVar, Var};
+
+/// Variables that have an hsmt syntax.
+pub trait HsmtVar {
+    /// Appends the hsmt syntax of the variable to `out`.
+    fn write_hsmt(&self, out: &mut String) -> Res<()>;
+}
+impl HsmtVar for Var {
+    fn write_hsmt(&self, out: &mut String) -> Res<()> {
+        out.push_str(&ident(self.id())?);
+        Ok(())
+    }
+}
+impl HsmtVar for SVar {
+    /// Next state variables are primed.
+    fn write_hsmt(&self, out: &mut String) -> Res<()> {
+        if self.is_next() {
+            out.push('\'')
+        }
+        out.push_str(&ident(self.id())?);
+        Ok(())
+    }
+}
+
+/// Hsmt syntax of an identifier, quoted if it is not a legal unquoted identifier.
+///
+/// # Errors
+///
+/// - when the identifier cannot be quoted, *i.e.* it contains `|` or `\`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::hsmt::ident;
+/// assert_eq!(ident("cnt_1").unwrap(), "cnt_1");
+/// assert_eq!(ident("|so free|").unwrap(), "|so free|");
+/// assert_eq!(ident("sub.cnt").unwrap(), "|sub.cnt|");
+/// assert_eq!(ident("let").unwrap(), "|let|");
+/// assert!(ident("a|b").is_err());
+/// ```
+pub fn ident(id: &str) -> Res<Cow<'_, str>> {
+    if crate::decls::check_ident(id).is_ok() {
+        return Ok(Cow::Borrowed(id));
+    }
+    let quoted = format!("|{}|", id);
+    if crate::decls::check_ident(&quoted).is_err() {
+        bail!("identifier `{}` has no hsmt syntax", id)
+    }
+    Ok(Cow::Owned(quoted))
+}
+
+/// Hsmt syntax of a double-quoted string, used for candidate names, labels and messages.
+///
+/// # Errors
+///
+/// - when the string contains an unescaped `"`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::hsmt::dbl_quoted;
+/// assert_eq!(dbl_quoted("cnt is positive").unwrap(), "\"cnt is positive\"");
+/// assert_eq!(dbl_quoted(r#"cnt is \"big\""#).unwrap(), r#""cnt is \"big\"""#);
+/// assert!(dbl_quoted(r#"cnt is "big""#).is_err());
+/// ```
+pub fn dbl_quoted(s: &str) -> Res<String> {
+    let quoted = format!("\"{}\"", s);
+    if crate::parse::rules::dbl_quoted(&quoted).is_err() {
+        bail!("string `{}` has no hsmt syntax", s)
+    }
+    Ok(quoted)
+}
+
+impl<V: HsmtVar> PExpr<V> {
+    /// Hsmt syntax of an expression, see the [module-level documentation](self).
+    ///
+    /// # Errors
+    ///
+    /// - on integer divisions and array constants, and
+    /// - on applications to no arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::expr::{self, Expr};
+    /// let expr: Expr = expr::build!((- (x: int) (- (y: int) 1) (* 2 (z: int))));
+    /// assert_eq!(expr.to_hsmt().unwrap(), "x - (y - 1) - 2 * z");
+    ///
+    /// let expr: Expr = expr::build!((and (or (a: bool) (b: bool)) (not (and (a: bool) (b: bool)))));
+    /// assert_eq!(expr.to_hsmt().unwrap(), "(a ⋁ b) ⋀ ¬(a ⋀ b)");
+    ///
+    /// let expr: Expr = expr::build!((% (x: int) 0));
+    /// assert_eq!(expr.to_hsmt().unwrap(), "x % 0");
+    /// ```
+    pub fn to_hsmt(&self) -> Res<String> {
+        let mut out = String::new();
+        write(self, &mut out)?;
+        Ok(out)
+    }
+}
+
+/// Precedence of the hsmt syntax of an expression.
+fn precedence<V>(expr: &PExpr<V>) -> Precedence {
+    match expr {
+        PExpr::Var(_) => Precedence::max(),
+        PExpr::Cst(Cst::R(r)) if decimal(r).is_none() => Op::Div.precedence(),
+        PExpr::Cst(Cst::I(i)) if i.is_negative() => Op::Not.precedence(),
+        PExpr::Cst(Cst::R(r)) if r.is_negative() => Op::Not.precedence(),
+        PExpr::Cst(_) => Precedence::max(),
+        PExpr::App {
+            op: Op::Sub, args, ..
+        } if args.len() == 1 => Op::Not.precedence(),
+        // Printed as their argument, see `app`.
+        PExpr::App { op, args, .. } if args.len() == 1 && is_infix(op) => precedence(&args[0]),
+        PExpr::App { op, .. } => op.precedence(),
+    }
+}
+
+/// True if an operator has an infix syntax.
+fn is_infix(op: &Op) -> bool {
+    *op != Op::Not && op.precedence() < Precedence::max()
+}
+
+/// Appends the hsmt syntax of an expression to `out`.
+fn write<V: HsmtVar>(expr: &PExpr<V>, out: &mut String) -> Res<()> {
+    match expr {
+        PExpr::Var(var) => var.write_hsmt(out),
+        PExpr::Cst(cst) => write_cst(cst, out),
+        PExpr::App { op, args, .. } => app(op, args, out),
+    }
+}
+
+/// Appends the hsmt syntax of an expression to `out`, between parentheses if `parens`.
+fn write_parens<V: HsmtVar>(expr: &PExpr<V>, parens: bool, out: &mut String) -> Res<()> {
+    if parens {
+        out.push('(');
+        write(expr, out)?;
+        out.push(')');
+        Ok(())
+    } else {
+        write(expr, out)
+    }
+}
+
+/// Appends the hsmt syntax of a constant to `out`.
+fn write_cst(cst: &Cst, out: &mut String) -> Res<()> {
+    match cst {
+        Cst::B(b) => out.push_str(if *b { "true" } else { "false" }),
+        Cst::I(i) => {
+            if i.is_negative() {
+                out.push('-')
+            }
+            out.push_str(&i.abs().to_string())
+        }
+        Cst::R(r) => {
+            if r.is_negative() {
+                out.push('-')
+            }
+            match decimal(r) {
+                Some(decimal) => out.push_str(&decimal),
+                None => out.push_str(&format!("{}.0 / {}.0", r.numer().abs(), r.denom())),
+            }
+        }
+        Cst::BV(width, val) => out.push_str(&format!("{}bv{}", val, width)),
+        Cst::Enum { typ, variant } => out.push_str(&format!("{}::{}", typ, variant)),
+        Cst::Array { .. } => bail!("array constant `{}` has no hsmt syntax", cst),
+    }
+    Ok(())
+}
+
+/// Decimal notation of the absolute value of a rational, if it has one.
+///
+/// Only rationals whose denominator has no prime factors other than `2` and `5` have one.
+fn decimal(r: &Rat) -> Option<String> {
+    let (mut denom, mut digits) = (r.denom().clone(), 0);
+    let (two, five, ten) = (Int::from(2), Int::from(5), Int::from(10));
+    let mut scale = Int::one();
+    while !denom.is_one() {
+        if (&denom % &two).is_zero() {
+            denom /= &two
+        } else if (&denom % &five).is_zero() {
+            denom /= &five
+        } else {
+            return None;
+        }
+        digits += 1;
+        scale *= &ten;
+    }
+    // `scale` is a multiple of the denominator, ten to the power of the number of its factors.
+    let scaled = (r.numer().abs() * &scale / r.denom()).to_string();
+    if digits == 0 {
+        return Some(format!("{}.0", scaled));
+    }
+    let scaled = format!("{:0>width$}", scaled, width = digits + 1);
+    let (int, frac) = scaled.split_at(scaled.len() - digits);
+    Some(format!("{}.{}", int, frac.trim_end_matches('0')))
+}
+
+/// Appends the hsmt syntax of an application to `out`.
+fn app<V: HsmtVar>(op: &Op, args: &[PExpr<V>], out: &mut String) -> Res<()> {
+    if args.is_empty() {
+        bail!("application of `{}` to no arguments has no hsmt syntax", op)
+    }
+    match op {
+        Op::IDiv => bail!("integer division `{}` has no hsmt syntax", op),
+        Op::Ite => {
+            if args.len() != 3 {
+                bail!("ill-formed if-then-else with {} argument(s)", args.len())
+            }
+            out.push_str("if ");
+            write(&args[0], out)?;
+            out.push_str(" { ");
+            write(&args[1], out)?;
+            out.push_str(" } else ");
+            match &args[2] {
+                PExpr::App {
+                    op: Op::Ite,
+                    args: els,
+                    ..
+                } if els.len() == 3 => app(&Op::Ite, els, out)?,
+                els => {
+                    out.push_str("{ ");
+                    write(els, out)?;
+                    out.push_str(" }")
+                }
+            }
+        }
+        Op::Select | Op::Store | Op::Fun(_) => {
+            match op {
+                Op::Fun(fun) => out.push_str(&ident(fun.name())?),
+                _ => out.push_str(op.hsmt_str()[0]),
+            }
+            out.push('(');
+            for (idx, arg) in args.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ")
+                }
+                write(arg, out)?
+            }
+            out.push(')')
+        }
+        Op::Forall(binders) | Op::Exists(binders) => {
+            if args.len() != 1 {
+                bail!("ill-formed quantifier with {} argument(s)", args.len())
+            }
+            out.push_str(op.hsmt_str()[0]);
+            out.push_str(" (");
+            for (idx, var) in binders.vars().iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ")
+                }
+                out.push_str(&format!("{}: {}", ident(var.id())?, var.typ()))
+            }
+            out.push_str(") { ");
+            write(&args[0], out)?;
+            out.push_str(" }")
+        }
+        Op::Not | Op::Sub if args.len() == 1 => {
+            out.push_str(op.hsmt_str()[0]);
+            let prec = Op::Not.precedence();
+            write_parens(&args[0], precedence(&args[0]) < prec, out)?
+        }
+        _ if args.len() == 1 => write(&args[0], out)?,
+        _ => {
+            let prec = op.precedence();
+            for (idx, arg) in args.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(&format!(" {} ", op.hsmt_str()[0]))
+                }
+                write_parens(arg, precedence(arg) <= prec, out)?
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Constructors for frequent idioms.
+//!
+//! These patterns are also available in hsmt as *intrinsic functions*, with the same names and
+//! arguments: `at_most_one(a, b, c)`, `exactly_one(a, b, c)`, `in_range(x, 0, 10)`,
+//! `monotonic(x)` and `unchanged(x, y)`. The last two are stateful, they only make sense in
+//! transition relations.
+//!
+//! ```rust
+//! # use mikino_api::parse;
+//! let sys = parse::trans(r#"
+//! svars { a b c: bool, x: int }
+//! init { exactly_one(a, b, c), in_range(x, 0, 3) }
+//! trans { monotonic(x), unchanged(a, b ∨ c) }
+//! candidates { "a or b or c": at_most_one(a, b, c) }
+//! "#).unwrap();
+//! assert_eq!(
+//!     sys.init().to_string(),
+//!     "(and (or a b c) (not (and a b)) (not (and a c)) (not (and b c)) (<= 0 x) (<= x 3))",
+//! );
+//! assert_eq!(
+//!     sys.trans().to_string(),
+//!     "(and (>= x@1 x@0) (= a@1 a@0) (= (or b@1 c@1) (or b@0 c@0)))",
+//! );
+//! ```
+
+crate::prelude!();
+
+use super::{Cst, Expr, HasTyp, Op, PExpr, SExpr};
+use trans::Decls;
+
+/// Conjunction, `true` if empty.
+fn and<V: HasTyp>(mut args: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+    match args.len() {
+        0 => Ok(Cst::bool(true).into()),
+        1 => Ok(args.pop().expect("[unreachable] pop on vec of len `1`")),
+        _ => PExpr::new_op(Op::And, args),
+    }
+}
+
+/// At most one of some boolean expressions is true.
+///
+/// Pairwise encoding: `¬(a ∧ b)` for each pair of arguments, `true` if there are less than two
+/// arguments.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::{self, patterns, Expr};
+/// let args: Vec<Expr> = vec![expr::build!((a: bool)), expr::build!((b: bool))];
+/// let expr = patterns::at_most_one(args).unwrap();
+/// assert_eq!(expr.to_string(), "(not (and a b))");
+/// ```
+pub fn at_most_one<V: HasTyp + Clone>(args: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+    let mut conjs = vec![];
+    for (idx, lft) in args.iter().enumerate() {
+        for rgt in args[idx + 1..].iter() {
+            let both = PExpr::new_op(Op::And, vec![lft.clone(), rgt.clone()])?;
+            conjs.push(PExpr::new_op(Op::Not, vec![both])?)
+        }
+    }
+    and(conjs)
+}
+
+/// Exactly one of some boolean expressions is true, `false` if there are no arguments.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::{self, patterns, Expr};
+/// let args: Vec<Expr> = vec![expr::build!((a: bool)), expr::build!((b: bool))];
+/// let expr = patterns::exactly_one(args).unwrap();
+/// assert_eq!(expr.to_string(), "(and (or a b) (not (and a b)))");
+/// ```
+pub fn exactly_one<V: HasTyp + Clone>(args: Vec<PExpr<V>>) -> Res<PExpr<V>> {
+    let at_least_one = match args.len() {
+        0 => return Ok(Cst::bool(false).into()),
+        1 => args[0].clone(),
+        _ => PExpr::new_op(Op::Or, args.clone())?,
+    };
+    match at_most_one(args)? {
+        PExpr::Cst(Cst::B(true)) => Ok(at_least_one),
+        at_most_one => PExpr::new_op(Op::And, vec![at_least_one, at_most_one]),
+    }
+}
+
+/// An arithmetic expression is between two bounds, inclusive.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::{self, patterns, Expr};
+/// let x: Expr = expr::build!((x: int));
+/// let expr = patterns::in_range(x, expr::build!(0), expr::build!(10)).unwrap();
+/// assert_eq!(expr.to_string(), "(and (<= 0 x) (<= x 10))");
+/// ```
+pub fn in_range<V: HasTyp + Clone>(x: PExpr<V>, lo: PExpr<V>, hi: PExpr<V>) -> Res<PExpr<V>> {
+    let lo = PExpr::new_op(Op::Le, vec![lo, x.clone()])?;
+    let hi = PExpr::new_op(Op::Le, vec![x, hi])?;
+    PExpr::new_op(Op::And, vec![lo, hi])
+}
+
+/// Stateful version of a stateless expression, over the current or next state.
+fn lift(decls: &Decls, expr: &Expr, next: bool) -> Res<SExpr> {
+    expr.fold(
+        |var| {
+            let svar = if next {
+                decls.get_next_var(var.id())
+            } else {
+                decls.get_curr_var(var.id())
+            };
+            match svar {
+                Some(svar) => Ok(PExpr::new_var(svar)),
+                None => bail!("unknown state variable `{}`", var.id()),
+            }
+        },
+        |cst| Ok(cst.clone().into()),
+        |op, args| PExpr::new_op(op, args.into_iter().collect::<Res<_>>()?),
+    )
+}
+
+/// An arithmetic expression does not decrease: `'x ≥ x`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{expr::{self, patterns, Expr}, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let cnt: Expr = expr::build!((cnt: int));
+/// let expr = patterns::monotonic(sys.decls(), &cnt).unwrap();
+/// assert_eq!(expr.to_string(), "(>= cnt@1 cnt@0)");
+/// ```
+pub fn monotonic(decls: &Decls, x: &Expr) -> Res<SExpr> {
+    PExpr::new_op(Op::Ge, vec![lift(decls, x, true)?, lift(decls, x, false)?])
+}
+
+/// Some expressions do not change: `'x = x` for each argument `x`, `true` if there are no
+/// arguments.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{expr::{self, patterns, Expr}, parse};
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let args: Vec<Expr> = vec![expr::build!((cnt: int)), expr::build!((stop: bool))];
+/// let expr = patterns::unchanged(sys.decls(), &args).unwrap();
+/// assert_eq!(expr.to_string(), "(and (= cnt@1 cnt@0) (= stop@1 stop@0))");
+/// ```
+pub fn unchanged(decls: &Decls, args: &[Expr]) -> Res<SExpr> {
+    let mut eqs = vec![];
+    for x in args {
+        eqs.push(PExpr::new_op(
+            Op::Eq,
+            vec![lift(decls, x, true)?, lift(decls, x, false)?],
+        )?)
+    }
+    and(eqs)
+}
@@ -0,0 +1,159 @@
+//! Recursive simplification of expressions.
+//!
+//! Expression constructors only simplify the application they build, see [`PExpr::new_op`]:
+//! `(and a (and b c))` or `(+ x 0)` are kept as they are. [`simplify`] goes through the whole
+//! expression instead, which keeps the SMT-LIB queries short.
+
+crate::prelude!();
+
+use super::{Cst, HasTyp, Op, PExpr};
+
+/// Simplifies an expression, recursively.
+///
+/// - Applications to constants are evaluated, except for divisions by zero which are left to the
+///   solver.
+/// - Nested conjunctions and disjunctions are flattened. Their neutral elements and duplicate
+///   arguments are removed, and they collapse on absorbing elements.
+/// - Double negations are eliminated.
+/// - `0` is removed from sums and from the subtracted terms of subtractions, `1` from products.
+/// - If-then-else-s with a constant condition or equal branches are replaced by a branch.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::expr::{self, Expr};
+/// let expr: Expr = expr::build!(
+///     (and (a: bool) (and (not (not (b: bool))) (>= (+ (x: int) 0 (* 1 (y: int))) (+ 2 3))))
+/// );
+/// assert_eq!(expr::simplify(&expr).to_string(), "(and a b (>= (+ x y) 5))");
+///
+/// let expr: Expr = expr::build!((or (a: bool) (> 7 (- 4 1)) (b: bool)));
+/// assert_eq!(expr::simplify(&expr).to_string(), "true");
+///
+/// let expr: Expr = expr::build!((ite (> 1 2) (x: int) (- (y: int) 0)));
+/// assert_eq!(expr::simplify(&expr).to_string(), "y");
+///
+/// // Left to the solver.
+/// let expr: Expr = expr::build!((% (x: int) (- 2 2)));
+/// assert_eq!(expr::simplify(&expr).to_string(), "(% x 0)");
+/// ```
+pub fn simplify<V: HasTyp + Clone + PartialEq>(expr: &PExpr<V>) -> PExpr<V> {
+    expr.fold(
+        |var| PExpr::new_var(var.clone()),
+        |cst| PExpr::new_cst(cst.clone()),
+        app,
+    )
+}
+
+/// Simplifies an application, its arguments are already simplified.
+fn app<V: HasTyp + Clone + PartialEq>(op: Op, mut args: Vec<PExpr<V>>) -> PExpr<V> {
+    if let Some(cst) = eval(&op, &args) {
+        return cst.into();
+    }
+    match op {
+        Op::And | Op::Or => {
+            // `false` for `and`, `true` for `or`.
+            let absorbing = op == Op::Or;
+            let mut kept: Vec<PExpr<V>> = Vec::with_capacity(args.len());
+            for arg in args {
+                let flat = match arg {
+                    PExpr::App {
+                        op: sub_op, args, ..
+                    } if sub_op == op => args,
+                    arg => vec![arg],
+                };
+                for arg in flat {
+                    match arg {
+                        PExpr::Cst(Cst::B(b)) if b == absorbing => return Cst::bool(b).into(),
+                        PExpr::Cst(Cst::B(_)) => (),
+                        arg if kept.contains(&arg) => (),
+                        arg => kept.push(arg),
+                    }
+                }
+            }
+            if kept.is_empty() {
+                Cst::bool(!absorbing).into()
+            } else {
+                new_op(op, kept)
+            }
+        }
+        Op::Not if args.len() == 1 => match args.pop().expect("[unreachable] len is `1`") {
+            PExpr::App {
+                op: Op::Not,
+                mut args,
+                ..
+            } if args.len() == 1 => args.pop().expect("[unreachable] len is `1`"),
+            arg => new_op(op, vec![arg]),
+        },
+        Op::Add | Op::Mul => {
+            let neutral = if op == Op::Add { is_zero } else { is_one };
+            // Not all arguments are constants, otherwise the application would be evaluated.
+            args.retain(|arg| !matches!(arg, PExpr::Cst(cst) if neutral(cst)));
+            if args.len() == 1 {
+                args.pop().expect("[unreachable] len is `1`")
+            } else {
+                new_op(op, args)
+            }
+        }
+        Op::Sub if args.len() > 1 => {
+            let mut args = args.into_iter();
+            let head = args.next().expect("[unreachable] len is more than `1`");
+            let tail: Vec<_> = args
+                .filter(|arg| !matches!(arg, PExpr::Cst(cst) if is_zero(cst)))
+                .collect();
+            if tail.is_empty() {
+                head
+            } else {
+                new_op(op, Some(head).into_iter().chain(tail).collect())
+            }
+        }
+        Op::Ite if args.len() == 3 => match &args[0] {
+            PExpr::Cst(Cst::B(cnd)) => args.swap_remove(if *cnd { 1 } else { 2 }),
+            _ if args[1] == args[2] => args.swap_remove(1),
+            _ => new_op(op, args),
+        },
+        _ => new_op(op, args),
+    }
+}
+
+/// Builds an application of arguments that type-check.
+///
+/// Simplification preserves types, so the application type-checks since the original one did.
+fn new_op<V: HasTyp>(op: Op, args: Vec<PExpr<V>>) -> PExpr<V> {
+    PExpr::new_op(op, args).expect("[unreachable] simplification preserves types")
+}
+
+/// Evaluates an application if all its arguments are constants.
+///
+/// Divisions by zero are not evaluated, evaluation panics on them.
+fn eval<V>(op: &Op, args: &[PExpr<V>]) -> Option<Cst> {
+    let csts: Vec<Cst> = args
+        .iter()
+        .map(|arg| match arg {
+            PExpr::Cst(cst) => Some(cst.clone()),
+            PExpr::Var(_) | PExpr::App { .. } => None,
+        })
+        .collect::<Option<_>>()?;
+    if matches!(op, Op::Div | Op::IDiv | Op::Mod) && csts.iter().skip(1).any(is_zero) {
+        return None;
+    }
+    op.eval(csts).ok()
+}
+
+/// True if a constant is an arithmetic zero.
+fn is_zero(cst: &Cst) -> bool {
+    match cst {
+        Cst::I(i) => i.is_zero(),
+        Cst::R(r) => r.is_zero(),
+        Cst::B(_) | Cst::BV(_, _) | Cst::Array { .. } | Cst::Enum { .. } => false,
+    }
+}
+
+/// True if a constant is an arithmetic one.
+fn is_one(cst: &Cst) -> bool {
+    match cst {
+        Cst::I(i) => i.is_one(),
+        Cst::R(r) => r.is_one(),
+        Cst::B(_) | Cst::BV(_, _) | Cst::Array { .. } | Cst::Enum { .. } => false,
+    }
+}
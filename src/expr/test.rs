@@ -166,3 +166,528 @@ fn type_check_fail() {
         fail: "[2, 4] `and`'s arguments must all be boolean expressions",
     }
 }
+
+#[test]
+fn checked_typ() {
+    let expr = expr::Expr::new_op(
+        expr::Op::And,
+        vec![build_expr!((a: bool)), build_expr!((> (n: int) 7))],
+    )
+    .unwrap();
+    match &expr {
+        expr::PExpr::App { typ, .. } => assert_eq!(typ.get(), expr::Typ::Bool),
+        expr => panic!("expected application, got `{}`", expr),
+    }
+
+    let app = expr::Expr::new_app(expr::Op::Add, vec![build_expr!((n: int)), 1.into()]).unwrap();
+    assert_eq!(app.typ(), expr::Typ::Int);
+    assert_eq!(app, build_expr!((+ (n: int) 1)));
+
+    let bad = expr::Expr::new_app(expr::Op::Add, vec![build_expr!((a: bool)), 1.into()]);
+    assert!(bad.is_err());
+}
+
+#[test]
+#[should_panic(expected = "ill-typed application in `build_expr!`")]
+fn build_expr_ill_typed() {
+    let _: expr::Expr = build_expr!((+ (a: bool) 1));
+}
+
+#[test]
+fn eval_relations() {
+    use expr::{Cst, Op};
+    let ints = |ints: &[i32]| ints.iter().map(|i| Cst::int(*i)).collect::<Vec<_>>();
+    assert_eq!(Op::Gt.eval(ints(&[3, 0])).unwrap(), Cst::bool(true));
+    assert_eq!(Op::Gt.eval(ints(&[3, 3])).unwrap(), Cst::bool(false));
+    assert_eq!(Op::Lt.eval(ints(&[0, 1, 2])).unwrap(), Cst::bool(true));
+    assert_eq!(Op::Lt.eval(ints(&[0, 2, 2])).unwrap(), Cst::bool(false));
+    assert_eq!(Op::Ge.eval(ints(&[2, 2, 1])).unwrap(), Cst::bool(true));
+    assert_eq!(Op::Le.eval(ints(&[2, 1])).unwrap(), Cst::bool(false));
+}
+
+#[test]
+fn eval_implies() {
+    use expr::{Cst, Op};
+    let bools = |bools: &[bool]| bools.iter().map(|b| Cst::bool(*b)).collect::<Vec<_>>();
+    assert_eq!(
+        Op::Implies.eval(bools(&[true, false])).unwrap(),
+        Cst::bool(false)
+    );
+    assert_eq!(
+        Op::Implies.eval(bools(&[false, false])).unwrap(),
+        Cst::bool(true)
+    );
+    assert_eq!(
+        Op::Implies.eval(bools(&[true, true, false])).unwrap(),
+        Cst::bool(false)
+    );
+    assert_eq!(
+        Op::Implies.eval(bools(&[true, false, false])).unwrap(),
+        Cst::bool(true)
+    );
+}
+
+#[test]
+fn div_not_folded() {
+    use expr::{Cst, Op};
+    let div = expr::Expr::new_op(Op::Div, vec![Cst::int(1).into(), Cst::int(2).into()]).unwrap();
+    assert_eq!(div.to_string(), "(/ 1 2)");
+}
+
+#[test]
+fn bitvectors() {
+    let sys = parse::trans(
+        "
+svars { x y: bv<8> }
+init { x = 1bv8 ⋀ y = 255bv8 }
+trans { 'x = x << 1bv8 | x >> 7bv8 ⋀ 'y = y ^ x & 15bv8 }
+candidates { \"mask\": x & y = x }
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        sys.trans().to_string(),
+        "(and \
+            (= x@1 (bvor (bvshl x@0 (_ bv1 8)) (bvlshr x@0 (_ bv7 8)))) \
+            (= y@1 (bvxor y@0 (bvand x@0 (_ bv15 8))))\
+        )",
+    );
+
+    let err = parse::trans(
+        "
+svars { x: bv<8>, n: int }
+init { x = 1bv8 }
+trans { 'x = x & 1bv4 }
+candidates { \"int\": n = x | 1bv8 }
+        ",
+    )
+    .map(|_| ())
+    .unwrap_err();
+    assert!(err
+        .pretty(())
+        .contains("`bvand`'s arguments must all have the same type, found `bv<8>` and `bv<4>`"));
+}
+
+#[test]
+fn eval_bitvectors() {
+    use expr::{Cst, Op};
+    let bvs = |vals: &[i32]| vals.iter().map(|v| Cst::bv(8, *v)).collect::<Vec<_>>();
+    assert_eq!(
+        Op::BvAnd.eval(bvs(&[0b1100, 0b1010])).unwrap(),
+        Cst::bv(8, 0b1000)
+    );
+    assert_eq!(
+        Op::BvOr.eval(bvs(&[0b1100, 0b1010, 1])).unwrap(),
+        Cst::bv(8, 0b1111)
+    );
+    assert_eq!(
+        Op::BvXor.eval(bvs(&[0b1100, 0b1010])).unwrap(),
+        Cst::bv(8, 0b0110)
+    );
+    assert_eq!(
+        Op::Shl.eval(bvs(&[0b1100_0001, 1])).unwrap(),
+        Cst::bv(8, 0b1000_0010)
+    );
+    assert_eq!(Op::Shl.eval(bvs(&[1, 8])).unwrap(), Cst::bv(8, 0));
+    assert_eq!(
+        Op::Shr.eval(bvs(&[0b1100_0001, 6])).unwrap(),
+        Cst::bv(8, 0b11)
+    );
+    assert_eq!(Op::Shr.eval(bvs(&[255, 200])).unwrap(), Cst::bv(8, 0));
+    for op in [Op::Shl, Op::Shr] {
+        for shift in [Int::from(64), Int::from(u64::MAX)] {
+            let args = vec![Cst::bv(64, u64::MAX), Cst::bv(64, shift)];
+            assert_eq!(op.eval(args).unwrap(), Cst::bv(64, 0));
+        }
+    }
+    assert!(Op::BvAnd.eval(vec![Cst::bv(8, 1), Cst::bv(4, 1)]).is_err());
+    assert!(Op::Shl.eval(vec![Cst::int(1), Cst::int(1)]).is_err());
+}
+
+#[test]
+fn arrays() {
+    let sys = parse::trans(
+        "
+svars { mem: array<int, int>, ptr: int }
+init { ptr = 0 ⋀ select(mem, 0) = 0 }
+trans { 'ptr = ptr + 1 ⋀ 'mem = store(mem, ptr, select(mem, ptr) + 1) }
+candidates { \"pos\": select(mem, 0) ≥ 0 }
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        sys.trans().to_string(),
+        "(and \
+            (= ptr@1 (+ ptr@0 1)) \
+            (= mem@1 (store mem@0 ptr@0 (+ (select mem@0 ptr@0) 1)))\
+        )",
+    );
+
+    let err = parse::trans(
+        "
+svars { mem: array<int, bool>, ptr: int }
+init { ptr = 0 }
+trans { 'ptr = ptr + 1 ⋀ 'mem = store(mem, ptr, 7) }
+candidates { \"pos\": ptr ≥ 0 }
+        ",
+    )
+    .map(|_| ())
+    .unwrap_err();
+    assert!(err.pretty(()).contains("`store`"));
+}
+
+#[test]
+fn eval_arrays() {
+    use expr::{Cst, Op, Typ};
+    let arr = Cst::array(Typ::Int, Cst::int(0));
+    assert_eq!(arr.typ(), Typ::array(Typ::Int, Typ::Int));
+    let arr = Op::Store.eval(vec![arr, Cst::int(3), Cst::int(7)]).unwrap();
+    assert_eq!(
+        Op::Select.eval(vec![arr.clone(), Cst::int(3)]).unwrap(),
+        Cst::int(7)
+    );
+    assert_eq!(
+        Op::Select.eval(vec![arr.clone(), Cst::int(4)]).unwrap(),
+        Cst::int(0)
+    );
+    // Storing the default value yields the same array as never storing anything.
+    let arr = Op::Store.eval(vec![arr, Cst::int(3), Cst::int(0)]).unwrap();
+    assert_eq!(arr, Cst::array(Typ::Int, Cst::int(0)));
+    assert!(Op::Select.eval(vec![arr.clone(), Cst::B(true)]).is_err());
+    assert!(Op::Store
+        .eval(vec![arr, Cst::int(1), Cst::B(true)])
+        .is_err());
+}
+
+#[test]
+fn enums() {
+    use expr::{Cst, Typ};
+    let sys = parse::trans(
+        "
+svars { enum State { Idle, Busy, Done }, s: State, cnt: int }
+init { s = State::Idle ⋀ cnt = 0 }
+trans {
+    'cnt = cnt + 1,
+    's = if s = State::Idle { State::Busy } else if cnt > 10 { State::Done } else { s },
+}
+candidates { \"not done\": ¬(s = State::Done) }
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        sys.decls().enum_variants("State").unwrap(),
+        &["Idle", "Busy", "Done"]
+    );
+    assert_eq!(
+        sys.decls().get_var("s").unwrap().typ(),
+        Typ::enumeration("State")
+    );
+    assert_eq!(sys.init().to_string(), "(and (= s State::Idle) (= cnt 0))");
+    let mut smt = vec![];
+    rsmt2::print::Expr2Smt::expr_to_smt2(sys.init(), &mut smt, ()).unwrap();
+    assert_eq!(
+        String::from_utf8(smt).unwrap(),
+        "(and (= s |State::Idle|) (= cnt 0))"
+    );
+
+    let err = |txt: &str| {
+        parse::trans(txt)
+            .map(|_| ())
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let err = err("
+svars { enum State { Idle, Busy }, s: State, n: int }
+init { s = State::Done }
+trans { 's = s }
+candidates { \"ordered\": s = State::Idle ⋁ n > 0 }
+    ");
+    assert!(err.contains("unknown variant `State::Done`"), "{}", err);
+    let err2 = parse::trans(
+        "
+svars { enum State { Idle, Busy }, s: State, n: int }
+init { n = 0 }
+trans { 's = s }
+candidates { \"ordered\": s > State::Idle }
+        ",
+    )
+    .map(|_| ())
+    .unwrap_err();
+    assert!(err2.pretty(()).contains("arithmetic type"));
+
+    let idle = Cst::variant("State", "Idle");
+    assert_eq!(
+        expr::Op::Eq
+            .eval(vec![idle.clone(), Cst::variant("State", "Busy")])
+            .unwrap(),
+        Cst::bool(false)
+    );
+    assert_eq!(
+        expr::Op::Eq.eval(vec![idle.clone(), idle]).unwrap(),
+        Cst::bool(true)
+    );
+}
+
+#[test]
+fn funs() {
+    use expr::Typ;
+    let sys = parse::trans(
+        "
+svars { x y: int, ok: bool }
+funs { f: (int, int) -> int, p: (int) -> bool }
+init { x = f(0, y) }
+trans { 'x = f(x, 'y), 'ok = p('x) }
+candidates { \"f is stable\": f(x, x) = f(x, x), \"fixed\": p(f(1, 2)) }
+        ",
+    )
+    .unwrap();
+    let f = sys.decls().get_fun("f").unwrap();
+    assert_eq!(f.args(), &[Typ::Int, Typ::Int]);
+    assert_eq!(sys.init().to_string(), "(= x (f 0 y))");
+    let mut smt = vec![];
+    rsmt2::print::Expr2Smt::expr_to_smt2(sys.trans(), &mut smt, 0).unwrap();
+    assert_eq!(
+        String::from_utf8(smt).unwrap(),
+        "(and (= x@1 (f x@0 y@1)) (= ok@1 (p x@1)))"
+    );
+    // Function applications are not vacuous.
+    let warnings = crate::lint::system(&sys, &crate::lint::Lints::new()).unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+
+    let err = |txt: &str| {
+        parse::trans(txt)
+            .map(|_| ())
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let sys = |funs: &str, init: &str| {
+        format!(
+            "svars {{ x: int }} funs {{ {} }} init {{ {} }} trans {{ 'x = x }} candidates {{ \"c\": x = x }}",
+            funs, init
+        )
+    };
+    let msg = err(&sys("f: (int) -> int", "f(x = 0) = 0"));
+    assert!(
+        msg.contains("`f`'s argument 1 must have type `int`"),
+        "{}",
+        msg
+    );
+    let msg = err(&sys("f: (int) -> int", "g(x) = 0"));
+    assert!(msg.contains("unknown function `g`"), "{}", msg);
+    let msg = err(&sys("f: (int) -> int", "f(x, x) = 0"));
+    assert!(msg.contains("`f` expects at most 1 argument(s)"), "{}", msg);
+    let msg = err(&sys("x: (int) -> int", "x = 0"));
+    assert!(
+        msg.contains("function `x` has the name of a variable"),
+        "{}",
+        msg
+    );
+
+    assert_eq!(
+        expr::Op::Fun(f)
+            .eval(vec![expr::Cst::int(1), expr::Cst::int(2)])
+            .unwrap_err()
+            .to_string(),
+        "cannot evaluate uninterpreted function `f`"
+    );
+}
+
+#[test]
+fn quantifiers() {
+    use expr::{Op, PExpr};
+    let mut decls = trans::Decls::new();
+    let _ = decls.register("n", expr::Typ::Int);
+    let expr = parse::expr(
+        "exists (i: int) { n = 2 * i ⋀ forall (j: int) { i ≥ j ⇒ j < n } }",
+        &decls,
+    )
+    .unwrap();
+    let mut smt = vec![];
+    rsmt2::print::Expr2Smt::expr_to_smt2(&expr, &mut smt, ()).unwrap();
+    assert_eq!(
+        String::from_utf8(smt).unwrap(),
+        "(exists ((i Int)) (and (= n (* 2 i)) (forall ((j Int)) (=> (>= i j) (< j n)))))"
+    );
+    match &expr {
+        PExpr::App {
+            op: op @ Op::Exists(binders),
+            ..
+        } => {
+            assert_eq!(binders.vars(), &[expr::Var::new("i", expr::Typ::Int)]);
+            assert!(op.eval(vec![expr::Cst::bool(true)]).is_err());
+        }
+        _ => panic!("expected an existential quantifier, got `{}`", expr),
+    }
+
+    let err = |txt: &str| {
+        parse::expr(txt, &decls)
+            .map(|_| ())
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let msg = err("forall (n: int) { n > 0 }");
+    assert!(
+        msg.contains("bound variable `n` shadows a declaration"),
+        "{}",
+        msg
+    );
+    let msg = err("forall (i: int) { i > 0 } ⋀ i = n");
+    assert!(
+        msg.contains("bound variable `i` is used outside of its quantifier"),
+        "{}",
+        msg
+    );
+    let msg = err("forall (i: int) { i > 0 } ⋀ exists (i: bool) { i }");
+    assert!(
+        msg.contains("variable `i` is bound with types `int` and `bool`"),
+        "{}",
+        msg
+    );
+    let msg = err("forall (i: int) { i + 1 }");
+    assert!(msg.contains("boolean"), "{}", msg);
+
+    let msg = parse::trans(
+        "svars { n: int } init { n = 0 } trans { 'n = n } \
+        candidates { \"c\": forall (i: int) { n ≥ i ⋁ n < i } }",
+    )
+    .map(|_| ())
+    .unwrap_err()
+    .iter()
+    .map(|e| e.to_string())
+    .collect::<Vec<_>>()
+    .join("\n");
+    assert!(
+        msg.contains("quantifiers are only supported in scripts"),
+        "{}",
+        msg
+    );
+    assert!(crate::script::build::of_str(
+        "vars { n: int } assert { forall (i: int) { n ≥ i * i } } check_sat!()"
+    )
+    .is_ok());
+}
+
+#[test]
+fn simplify() {
+    let simplified = |expr: expr::Expr| expr::simplify(&expr).to_string();
+
+    assert_eq!(
+        simplified(build_expr!((or (a: bool) (or false (b: bool)) (or (a: bool) (c: bool))))),
+        "(or a b c)",
+    );
+    assert_eq!(
+        simplified(build_expr!((and (a: bool) (not (and true (not (a: bool))))))),
+        "a",
+    );
+    assert_eq!(
+        simplified(build_expr!((- (x: int) 0 (* (y: int) 1) 0))),
+        "(- x y)",
+    );
+    // The head of a subtraction is not a neutral element.
+    assert_eq!(simplified(build_expr!((- 0 (x: int)))), "(- 0 x)");
+    assert_eq!(
+        simplified(build_expr!((ite (b: bool) (+ (x: int) 0) (x: int)))),
+        "x",
+    );
+}
+
+#[test]
+fn hcons() {
+    use expr::{hcons::HConsign, HasTyp, Op, SVar};
+
+    let mut consign: HConsign<SVar> = HConsign::new();
+    // `(+ e e)` where `e` is the previous expression, `2^64` leaves but only 65 nodes.
+    let mut expr = consign.add(&build_expr!((x @ 0: int))).unwrap();
+    for _ in 0..64 {
+        expr = consign.app(Op::Add, vec![expr.clone(), expr]).unwrap();
+    }
+    assert_eq!(consign.len(), 65);
+    assert_eq!(expr.typ(), expr::Typ::Int);
+
+    let pos = consign.add(&build_expr!((> (x @ 1: int) 0))).unwrap();
+    assert_eq!(consign.len(), 68);
+    let mut smt = vec![];
+    rsmt2::print::Expr2Smt::expr_to_smt2(&pos, &mut smt, 3).unwrap();
+    assert_eq!(String::from_utf8(smt).unwrap(), "(> x@4 0)");
+
+    // Subterms of `expr` are only referenced by `expr` and the consign.
+    assert_eq!(consign.collect(), 0);
+    drop(expr);
+    drop(pos);
+    assert_eq!(consign.collect(), 68);
+    assert!(consign.is_empty());
+}
+
+#[test]
+fn hsmt_round_trip() {
+    let round_trip = |txt: &str| {
+        let sys = parse::trans(txt).unwrap();
+        let hsmt = sys.to_hsmt().unwrap();
+        let reparsed =
+            parse::trans(&hsmt).unwrap_or_else(|e| panic!("failed to reparse\n{}\n{}", hsmt, e));
+        assert_eq!(reparsed.init(), sys.init(), "in\n{}", hsmt);
+        assert_eq!(reparsed.trans(), sys.trans(), "in\n{}", hsmt);
+        assert_eq!(reparsed.po_s(), sys.po_s(), "in\n{}", hsmt);
+        assert_eq!(reparsed.to_hsmt().unwrap(), hsmt);
+    };
+    round_trip(crate::TRANS_DEMO);
+    round_trip(
+        r#"
+svars {
+    enum Mode { Off, On },
+    mode: Mode,
+    x y: int,
+    r: rat,
+    w: bv<8>,
+    a: array<int, bool>,
+    b: bool,
+}
+funs { f: (int, bool) -> int }
+init {
+    mode = Mode::Off,
+    x = -7 ⋀ y = -(x - 1) * 2,
+    r = 1.5 / 2.0 - -r,
+    w = 3bv8 & (w | 1bv8) << 2bv8,
+    (b ⇒ x < y) ⇒ b,
+}
+trans {
+    'x = if b { x % 3 } else if ¬b { f(x, 'b) } else { -x },
+    'a = store(a, x, select(a, y) ⋁ b),
+    select('a, x) ⇒ 'b,
+}
+candidates {
+    "chained": x ≤ y ≤ 0,
+    "parens": ¬(x = y) ⋁ (x + y) * (x - y) ≥ x - (y - 1),
+}
+"#,
+    );
+
+    let mut decls = trans::Decls::new();
+    let _ = decls.register("n", expr::Typ::Int);
+    let expr = parse::expr(
+        "forall (i: int) { i ≥ 0 ⇒ n > i } ⋀ exists (j: int, c: bool) { j = n ⋀ c }",
+        &decls,
+    )
+    .unwrap();
+    let hsmt = expr.to_hsmt().unwrap();
+    assert_eq!(
+        hsmt,
+        "forall (i: int) { i ≥ 0 ⇒ n > i } ⋀ exists (j: int, c: bool) { j = n ⋀ c }"
+    );
+    assert_eq!(parse::expr(&hsmt, &decls).unwrap(), expr);
+
+    let script = crate::script::build::of_str(crate::SCRIPT_DEMO).unwrap();
+    let hsmt = crate::script::to_hsmt(&script).unwrap();
+    let reparsed = crate::script::build::of_str(&hsmt)
+        .unwrap_or_else(|e| panic!("failed to reparse\n{}\n{}", hsmt, e));
+    assert_eq!(crate::script::to_hsmt(&reparsed).unwrap(), hsmt);
+}
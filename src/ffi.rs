@@ -0,0 +1,385 @@
+//! C foreign function interface, requires feature `ffi`.
+//!
+//! A small, stable `extern "C"` surface to embed mikino in other languages. Systems are opaque
+//! [`MikinoSystem`] handles, results are JSON strings:
+//!
+//! - [`mikino_check`] yields the [JSON of the outcome](crate::check::Outcome::to_json);
+//! - [`mikino_run_script`] yields `{"steps":[...],"outcome":...}` where each step is
+//!   `{"kind":...,"text":...}`, `kind` is one of `check_sat`, `model`, `values`, `mus`,
+//!   `unsat_core`, `optimum` or `echo` and `text` is the plain text rendering of the step.
+//!   `check_sat` steps also have a `label`, `null` or `{"title":...,"description":...}`. The
+//!   outcome is `null` when the script runs to completion, `{"exit":<code>}` or
+//!   `{"panic":<message>}` otherwise.
+//!
+//! Checks and scripts can be stopped from another thread with a [`MikinoCancel`] handle, see
+//! [`Cancel`].
+//!
+//! # Conventions
+//!
+//! - Strings are NUL-terminated and UTF-8 encoded.
+//! - Functions that can fail return a null pointer on failure, [`mikino_last_error`] then yields
+//!   the error message. Every call resets the last error of its thread.
+//! - Strings returned by mikino must be released with [`mikino_string_free`], handles with their
+//!   own `free` function. Passing a null pointer to a `free` function does nothing.
+//! - Panics never cross the FFI boundary, they are reported as errors.
+//!
+//! The C declarations are in `include/mikino.h`. To produce a C library, build with
+//! `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`).
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use mikino_api::ffi::*;
+//! # use std::ffi::{CStr, CString};
+//! let model = CString::new(
+//!     "svars { x: int } init { x = 0 } trans { 'x = x + 1 } candidates { \"pos\": x ≥ 0 }",
+//! ).unwrap();
+//! unsafe {
+//!     let sys = mikino_parse_system(model.as_ptr());
+//!     assert!(!sys.is_null());
+//!
+//!     // Let's not actually run a solver in this example.
+//!     let cancel = mikino_cancel_new();
+//!     mikino_cancel(cancel);
+//!     let json = mikino_check(sys, std::ptr::null(), std::ptr::null(), 10, 1, cancel);
+//!     assert!(!json.is_null());
+//!     let outcome = CStr::from_ptr(json).to_str().unwrap().to_string();
+//!     assert!(outcome.ends_with(
+//!         r#""verdicts":[{"candidate":"pos","severity":"error","label":null,"verdict":"unknown","bmc_depth":null}]}"#
+//!     ));
+//!     mikino_string_free(json);
+//!
+//!     let backend = CString::new("mathsat").unwrap();
+//!     let json = mikino_check(sys, backend.as_ptr(), std::ptr::null(), 10, 1, cancel);
+//!     assert!(json.is_null());
+//!     let error = CStr::from_ptr(mikino_last_error()).to_str().unwrap();
+//!     assert!(error.contains("unknown backend `mathsat`, expected `z3`, `cvc5`, `yices2`"));
+//!     mikino_cancel_free(cancel);
+//!     mikino_system_free(sys);
+//!
+//!     let bad = CString::new("svars { x: int } init { x = 0 }").unwrap();
+//!     assert!(mikino_parse_system(bad.as_ptr()).is_null());
+//!     let error = CStr::from_ptr(mikino_last_error()).to_str().unwrap();
+//!     assert!(error.contains("expected \"trans\""));
+//! }
+//! ```
+
+crate::prelude!();
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use crate::{config, meta::Label, solver};
+use check::{engine::Cancel, trace::json_str};
+use script::{Outcome, Session, Step};
+use trans::Sys;
+
+/// Opaque handle to a transition system.
+pub struct MikinoSystem {
+    /// System.
+    sys: Sys,
+}
+
+/// Opaque cancellation handle.
+pub struct MikinoCancel {
+    /// Underlying handle.
+    cancel: Cancel,
+}
+
+thread_local! {
+    /// Error of the last call on this thread, if any.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Sets the last error.
+fn set_last_error(msg: String) {
+    // Error messages cannot contain NUL bytes in C.
+    let msg = CString::new(msg.replace('\0', "\\0")).expect("NUL bytes were escaped");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg))
+}
+
+/// Runs an FFI function body, reports errors and panics as last error and yields `default`.
+fn guard<T>(default: T, body: impl FnOnce() -> Res<T>) -> T {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(res)) => res,
+        Ok(Err(e)) => {
+            set_last_error(e.pretty(()));
+            default
+        }
+        Err(panic) => {
+            let msg = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            set_last_error(format!("[fatal] mikino panicked: {}", msg));
+            default
+        }
+    }
+}
+
+/// Reads a string argument.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Res<&'a str> {
+    if ptr.is_null() {
+        bail!("argument `{}` is null", name)
+    }
+    match CStr::from_ptr(ptr).to_str() {
+        Ok(s) => Ok(s),
+        Err(e) => bail!("argument `{}` is not valid UTF-8: {}", name, e),
+    }
+}
+
+/// Solver configuration for a backend name and a solver command.
+///
+/// Same as the `backend` and `solver` keys of a [configuration](config::Config): the backend is
+/// z3 if `backend` is null, the command is the default command of the backend if `solver` is null.
+///
+/// # Safety
+///
+/// `backend` and `solver` must be null or point to NUL-terminated strings.
+unsafe fn solver_arg(backend: *const c_char, solver: *const c_char) -> Res<SmtConf> {
+    let mut config = config::Config::new();
+    if !backend.is_null() {
+        let name = str_arg(backend, "backend")?;
+        match solver::backend(name) {
+            Some(backend) => config = config.backend(backend),
+            None => bail!(
+                "unknown backend `{}`, expected {}",
+                name,
+                solver::BACKENDS
+                    .iter()
+                    .map(|backend| format!("`{}`", backend.name()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+    if !solver.is_null() {
+        config = config.solver(str_arg(solver, "solver")?)
+    }
+    Ok(config.smt_conf())
+}
+
+/// Cancellation handle of a nullable [`MikinoCancel`] pointer.
+///
+/// # Safety
+///
+/// `cancel` must be null or come from [`mikino_cancel_new`].
+unsafe fn cancel_arg(cancel: *const MikinoCancel) -> Cancel {
+    cancel
+        .as_ref()
+        .map(|cancel| cancel.cancel.clone())
+        .unwrap_or_default()
+}
+
+/// Turns a string into a C string owned by the caller.
+fn string_out(s: String) -> Res<*mut c_char> {
+    match CString::new(s) {
+        Ok(s) => Ok(s.into_raw()),
+        Err(e) => bail!("result contains a NUL byte: {}", e),
+    }
+}
+
+/// Version of mikino, a static string that must not be freed.
+#[no_mangle]
+pub extern "C" fn mikino_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Error message of the last call on this thread, null if it did not fail.
+///
+/// The string is owned by mikino and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn mikino_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map(|msg| msg.as_ptr())
+            .unwrap_or(std::ptr::null())
+    })
+}
+
+/// Releases a string returned by mikino.
+///
+/// # Safety
+///
+/// `s` must be null or come from mikino, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mikino_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s))
+    }
+}
+
+/// Parses a transition system, null on failure.
+///
+/// # Safety
+///
+/// `txt` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mikino_parse_system(txt: *const c_char) -> *mut MikinoSystem {
+    guard(std::ptr::null_mut(), || {
+        let txt = str_arg(txt, "txt")?;
+        let sys = parse::trans(txt)?;
+        Ok(Box::into_raw(Box::new(MikinoSystem { sys })))
+    })
+}
+
+/// Releases a system.
+///
+/// # Safety
+///
+/// `sys` must be null or come from [`mikino_parse_system`], and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mikino_system_free(sys: *mut MikinoSystem) {
+    if !sys.is_null() {
+        drop(Box::from_raw(sys))
+    }
+}
+
+/// Creates a cancellation handle, see [`mikino_cancel`].
+#[no_mangle]
+pub extern "C" fn mikino_cancel_new() -> *mut MikinoCancel {
+    Box::into_raw(Box::new(MikinoCancel {
+        cancel: Cancel::new(),
+    }))
+}
+
+/// Cancels the checks and scripts running with a handle, can be called from any thread.
+///
+/// # Safety
+///
+/// `cancel` must be null or come from [`mikino_cancel_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mikino_cancel(cancel: *const MikinoCancel) {
+    if let Some(cancel) = cancel.as_ref() {
+        cancel.cancel.cancel()
+    }
+}
+
+/// Releases a cancellation handle.
+///
+/// # Safety
+///
+/// `cancel` must be null or come from [`mikino_cancel_new`], must not be used by a running check
+/// or script, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mikino_cancel_free(cancel: *mut MikinoCancel) {
+    if !cancel.is_null() {
+        drop(Box::from_raw(cancel))
+    }
+}
+
+/// Checks the candidates of a system, yields the JSON of the outcome or null on failure.
+///
+/// - `backend`: `z3`, `cvc5` or `yices2`, `z3` if null;
+/// - `solver`: solver command, the default command of the backend if null;
+/// - `bmc_max`: maximum BMC depth, negative to deactivate BMC;
+/// - `jobs`: maximum number of candidates checked at the same time;
+/// - `cancel`: cancellation handle, can be null.
+///
+/// # Safety
+///
+/// `sys` must come from [`mikino_parse_system`], `backend` and `solver` must be null or point to
+/// NUL-terminated strings, `cancel` must be null or come from [`mikino_cancel_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mikino_check(
+    sys: *const MikinoSystem,
+    backend: *const c_char,
+    solver: *const c_char,
+    bmc_max: i64,
+    jobs: usize,
+    cancel: *const MikinoCancel,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let sys = match sys.as_ref() {
+            Some(sys) => &sys.sys,
+            None => bail!("argument `sys` is null"),
+        };
+        let mut config = check::Config::new(solver_arg(backend, solver)?)
+            .jobs(jobs)
+            .cancel(cancel_arg(cancel));
+        if bmc_max >= 0 {
+            config = config.bmc_max(bmc_max as Unroll)
+        }
+        let outcome = check::run(sys, &config)?;
+        string_out(outcome.to_json())
+    })
+}
+
+/// Runs an hsmt script, yields the JSON of its steps and outcome or null on failure.
+///
+/// - `backend`: `z3`, `cvc5` or `yices2`, `z3` if null;
+/// - `solver`: solver command, the default command of the backend if null;
+/// - `cancel`: cancellation handle, can be null.
+///
+/// # Safety
+///
+/// `txt` must point to a NUL-terminated string, `backend` and `solver` must be null or point to
+/// NUL-terminated strings, `cancel` must be null or come from [`mikino_cancel_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mikino_run_script(
+    txt: *const c_char,
+    backend: *const c_char,
+    solver: *const c_char,
+    cancel: *const MikinoCancel,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let txt = str_arg(txt, "txt")?;
+        let mut session = Session::new(solver_arg(backend, solver)?).cancel(cancel_arg(cancel));
+        let cell = session.execute(txt)?;
+
+        let mut s = String::from("{\"steps\":[");
+        let mut first = true;
+        for step in cell.steps.iter() {
+            let mut label = None;
+            let kind = match step {
+                Step::CheckRes(_, _, check_label) => {
+                    label = Some(check_label.as_ref().map(Label::to_json));
+                    "check_sat"
+                }
+                Step::Model { .. } => "model",
+                Step::Eval { .. } => "values",
+                Step::Mus { .. } => "mus",
+                Step::UnsatCore { .. } => "unsat_core",
+                Step::Optimum { .. } => "optimum",
+                Step::Echo(_) => "echo",
+                Step::Done(_) | Step::Nothing => continue,
+            };
+            let text = step.pretty(cell.txt(), (), false).unwrap_or_default();
+            if !first {
+                s.push(',')
+            }
+            first = false;
+            s.push_str(&format!(
+                "{{\"kind\":\"{}\",\"text\":{}",
+                kind,
+                json_str(&text)
+            ));
+            if let Some(label) = label {
+                s.push_str(&format!(
+                    ",\"label\":{}",
+                    label.unwrap_or_else(|| "null".into())
+                ))
+            }
+            s.push('}');
+        }
+        s.push_str("],\"outcome\":");
+        match cell.outcome.as_ref() {
+            None => s.push_str("null"),
+            Some(Outcome::Exit(_, code)) => s.push_str(&format!("{{\"exit\":{}}}", code)),
+            Some(Outcome::Panic(_, msg)) => s.push_str(&format!("{{\"panic\":{}}}", json_str(msg))),
+        }
+        s.push('}');
+        string_out(s)
+    })
+}
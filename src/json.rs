@@ -0,0 +1,194 @@
+//! Minimal JSON values and parser, see [RFC 8259](https://www.rfc-editor.org/rfc/rfc8259).
+//!
+//! Used to read [recordings](crate::check::record::from_json) and the messages of the language
+//! server. Numbers keep their text so that integers and decimals are read exactly.
+
+crate::prelude!();
+
+use check::trace::json_str;
+
+/// A JSON value.
+///
+/// Numbers keep their text, object fields keep their order.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::json::Json;
+/// let json = Json::parse(
+///     r#" {"id": 7, "params": {"text": "ϕ 𝔹\n", "ok": [true, null, -1.5e1]}} "#,
+/// )
+/// .unwrap();
+/// assert_eq!(json.get("id").and_then(Json::as_i64), Some(7));
+/// assert_eq!(json.path(&["params", "text"]).and_then(Json::as_str), Some("ϕ 𝔹\n"));
+/// assert_eq!(
+///     json.path(&["params", "ok"]).unwrap().to_string(),
+///     "[true,null,-1.5e1]",
+/// );
+/// assert_eq!(
+///     json.path(&["params", "ok"]).and_then(Json::as_arr).and_then(|ok| ok[2].as_i64()),
+///     Some(-15),
+/// );
+/// assert_eq!(json.to_string(), r#"{"id":7,"params":{"text":"ϕ 𝔹\n","ok":[true,null,-1.5e1]}}"#);
+///
+/// assert!(Json::parse(r#"{"id": 7"#).is_err());
+/// assert!(Json::parse(r#"{"id": 07}"#).is_err());
+/// assert!(Json::parse(r#""\ud835""#).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    /// `null`.
+    Null,
+    /// Booleans.
+    Bool(bool),
+    /// Numbers, as written.
+    Num(String),
+    /// Strings.
+    Str(String),
+    /// Arrays.
+    Arr(Vec<Json>),
+    /// Objects.
+    Obj(Vec<(String, Json)>),
+}
+impl Json {
+    /// Parses some JSON text.
+    pub fn parse(txt: &str) -> Res<Self> {
+        json::value(txt).map_err(|e| format!("illegal JSON: {}", e).into())
+    }
+
+    /// Value of a field of an object, `None` on anything else.
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Obj(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    /// Value at the end of a path of fields of nested objects.
+    pub fn path(&self, keys: &[&str]) -> Option<&Self> {
+        keys.iter().try_fold(self, |json, key| json.get(key))
+    }
+
+    /// String value, if any.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+    /// Integer value, if any.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Num(n) => n.parse().ok().or_else(|| {
+                let n: f64 = n.parse().ok()?;
+                if n.fract() == 0.0 && n.abs() <= (1u64 << 53) as f64 {
+                    Some(n as i64)
+                } else {
+                    None
+                }
+            }),
+            _ => None,
+        }
+    }
+    /// Array value, if any.
+    pub fn as_arr(&self) -> Option<&[Self]> {
+        match self {
+            Self::Arr(elems) => Some(elems),
+            _ => None,
+        }
+    }
+    /// Fields of an object value, if any.
+    pub fn as_obj(&self) -> Option<&[(String, Self)]> {
+        match self {
+            Self::Obj(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Json {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => "null".fmt(fmt),
+            Self::Bool(b) => b.fmt(fmt),
+            Self::Num(n) => n.fmt(fmt),
+            Self::Str(s) => json_str(s).fmt(fmt),
+            Self::Arr(elems) => {
+                "[".fmt(fmt)?;
+                for (idx, elem) in elems.iter().enumerate() {
+                    if idx > 0 {
+                        ",".fmt(fmt)?
+                    }
+                    elem.fmt(fmt)?
+                }
+                "]".fmt(fmt)
+            }
+            Self::Obj(fields) => {
+                "{".fmt(fmt)?;
+                for (idx, (key, val)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        ",".fmt(fmt)?
+                    }
+                    write!(fmt, "{}:{}", json_str(key), val)?
+                }
+                "}".fmt(fmt)
+            }
+        }
+    }
+}
+
+peg::parser! {
+    /// JSON grammar.
+    grammar json() for str {
+        rule _() = quiet! { [' ' | '\t' | '\n' | '\r']* }
+
+        pub rule value() -> Json = _ json:inner() _ { json }
+
+        rule inner() -> Json
+            = "null" { Json::Null }
+            / "true" { Json::Bool(true) }
+            / "false" { Json::Bool(false) }
+            / s:string() { Json::Str(s) }
+            / n:number() { Json::Num(n.into()) }
+            / "[" _ elems:(value() ** ",") _ "]" { Json::Arr(elems) }
+            / "{" _ fields:(field() ** ",") _ "}" { Json::Obj(fields) }
+            / expected!("a JSON value")
+
+        rule field() -> (String, Json) = _ key:string() _ ":" val:value() { (key, val) }
+
+        rule number() -> &'input str
+            = $(
+                "-"? ("0" / ['1'..='9'] ['0'..='9']*)
+                ("." ['0'..='9']+)?
+                (['e' | 'E'] ['+' | '-']? ['0'..='9']+)?
+            )
+
+        rule string() -> String = "\"" chars:char()* "\"" { chars.into_iter().collect() }
+
+        rule char() -> char
+            = c:[^ '"' | '\\' | '\u{0}'..='\u{1f}'] { c }
+            / "\\" c:escape() { c }
+
+        rule escape() -> char
+            = "\"" { '"' }
+            / "\\" { '\\' }
+            / "/" { '/' }
+            / "b" { '\u{8}' }
+            / "f" { '\u{c}' }
+            / "n" { '\n' }
+            / "r" { '\r' }
+            / "t" { '\t' }
+            / "u" hi:hex() "\\u" lo:hex() {?
+                if (0xD800..0xDC00).contains(&hi) && (0xDC00..0xE000).contains(&lo) {
+                    let code = 0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00);
+                    std::char::from_u32(code).ok_or("a unicode escape")
+                } else {
+                    Err("a surrogate pair")
+                }
+            }
+            / "u" code:hex() {? std::char::from_u32(code).ok_or("a unicode escape") }
+
+        rule hex() -> u32
+            = digits:$(['0'..='9' | 'a'..='f' | 'A'..='F']*<4>) {?
+                u32::from_str_radix(digits, 16).or(Err("four hexadecimal digits"))
+            }
+    }
+}
@@ -12,7 +12,9 @@
 //! module](solver) for more.
 //!
 //! Mikino only handles relatively simple SMT-LIB 2 expressions where the only types are `bool`,
-//! `int` and `rat`. For details regarding the expression structures see [`expr`].
+//! `int`, `rat`, fixed-width bitvectors `bv<n>`, arrays `array<idx, elm>` and enumerations.
+//! Expressions can also apply uninterpreted functions declared in a `funs { ... }` block. For
+//! details regarding the expression structures see [`expr`].
 //!
 //! Also, note that you will find a bunch of examples in `repository/examples` which use the
 //! systems/scripts in `repository/rsc`.
@@ -41,6 +43,12 @@
 //!   and `state'`; `trans` defines the states that legally follow any state `state` as all the
 //!   states `state'` such that `trans(state, state') = true`.
 //!
+//! Systems can also declare *assumptions*, stateless predicates such as constraints over the
+//! environment that hold in all states, and *parameters*, symbolic constants that keep the same
+//! value in all states, see [`parse::trans`]. They can also define *subsystems*
+//! and instantiate them several times, connecting their ports to the system's variables: instances
+//! are flattened into the system before checking, see [`trans::Sys::instantiate`].
+//!
 //! For more, see
 //!
 //! - [`TRANS_DEMO`] for a description of mikino's syntax for hsmt systems;
@@ -49,6 +57,20 @@
 //! - [`check`] for hsmt system `k`-induction-based verification;
 //! - `repository/rsc/trans_demo.rs` for a documented hsmt system demo.
 //!
+//! # Configuration
+//!
+//! A [`Config`] gathers the solver, budgets, engines, output format and seed, and can be loaded
+//! from a TOML file with profiles. See [`config`] for more.
+//!
+//! # Determinism
+//!
+//! Mikino's output does not depend on anything but its input (and the solver's answers). All
+//! collections are ordered: declarations, candidates, models and counterexample traces are always
+//! iterated over in alphabetical order of the variables/candidates, and by increasing step for
+//! traces. Variable `v` at step `k` is always called `v@k` in the solver. When checking candidates
+//! in parallel (see [`check::run`]), the outcome does not depend on the order in which threads
+//! finish.
+//!
 //! [smtlib]: https://smtlib.cs.uiowa.edu/language.shtml
 //! (SMT-LIB's official website)
 //! [SMT solvers]: https://en.wikipedia.org/wiki/Satisfiability_modulo_theories
@@ -69,13 +91,25 @@ pub mod prelude;
 
 pub mod ast;
 pub mod check;
+pub mod config;
+pub mod decls;
+pub mod demo;
 pub mod err;
 pub mod expr;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod json;
+pub mod lint;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod meta;
 pub mod parse;
 pub mod script;
 pub mod solver;
 pub mod trans;
 
+pub use config::Config;
+
 /// String representation of a simple demo system.
 pub const TRANS_DEMO: &str = include_str!("../rsc/trans_demo.rs");
 
@@ -0,0 +1,355 @@
+//! Lints, findings that are neither errors nor silent.
+//!
+//! A [`Lint`] detects something suspicious in a system that is nonetheless legal, like a variable
+//! that is never used. Each lint has a [`Level`]: allowed lints are not checked, the findings of
+//! warned lints are reported as [`Warning`]s, and the findings of denied lints are errors.
+//! [`Lints`] maps each lint to its level, and [`system`] runs the lints over a system.
+//!
+//! [`check::run`] lints the system before checking it, using the lints of its
+//! [configuration](crate::check::Config::lints). Warnings end up in
+//! [`Outcome::lints`](crate::check::Outcome::lints).
+
+prelude!();
+
+use expr::{Op, PExpr, SExpr};
+use trans::Sys;
+
+/// Lints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    /// A state variable that appears neither in the initial predicate, the transition relation nor
+    /// the candidates.
+    UnusedVariable,
+    /// A candidate that mentions no variable nor uninterpreted function, and is thus always true or
+    /// always false.
+    VacuousCandidate,
+    /// A state variable whose next version does not appear in the transition relation, see
+    /// [`Sys::unconstrained`].
+    ///
+    /// Allowed by default since unconstrained variables are the usual way to model inputs.
+    UnconstrainedNext,
+    /// An `if` condition of the transition relation that is an equality over a next state variable,
+    /// as in `if 'x = 0 { ... }`, which usually means an assignment was intended.
+    EqInCondition,
+    /// A proved candidate with an implication whose antecedent no run reaches, see
+    /// [`check::vacuity`].
+    ///
+    /// Allowed by default since it requires solver queries. Unlike the other lints, it is checked
+    /// by [`check::run`] after the candidates are proved, [`system`] ignores it.
+    VacuousProof,
+}
+impl Lint {
+    /// All the lints.
+    pub const ALL: [Self; 5] = [
+        Self::UnusedVariable,
+        Self::VacuousCandidate,
+        Self::UnconstrainedNext,
+        Self::EqInCondition,
+        Self::VacuousProof,
+    ];
+
+    /// Name of the lint, used in configurations and messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UnusedVariable => "unused_variable",
+            Self::VacuousCandidate => "vacuous_candidate",
+            Self::UnconstrainedNext => "unconstrained_next",
+            Self::EqInCondition => "eq_in_condition",
+            Self::VacuousProof => "vacuous_proof",
+        }
+    }
+    /// Lint from its name.
+    pub fn of_str(s: impl AsRef<str>) -> Option<Self> {
+        let s = s.as_ref();
+        Self::ALL.iter().cloned().find(|lint| lint.name() == s)
+    }
+
+    /// Default level of the lint.
+    pub fn default_level(self) -> Level {
+        match self {
+            Self::UnconstrainedNext | Self::VacuousProof => Level::Allow,
+            Self::UnusedVariable | Self::VacuousCandidate | Self::EqInCondition => Level::Warn,
+        }
+    }
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(fmt)
+    }
+}
+
+/// Level of a lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// The lint is not checked.
+    Allow,
+    /// Findings are warnings.
+    Warn,
+    /// Findings are errors.
+    Deny,
+}
+impl Level {
+    /// Level from its name, `allow`, `warn` or `deny`.
+    pub fn of_str(s: impl AsRef<str>) -> Option<Self> {
+        match s.as_ref() {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Level {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Allow => "allow".fmt(fmt),
+            Self::Warn => "warn".fmt(fmt),
+            Self::Deny => "deny".fmt(fmt),
+        }
+    }
+}
+
+/// Level of each lint.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::lint::{Level, Lint, Lints};
+/// let lints = Lints::new().deny(Lint::UnusedVariable).warn(Lint::UnconstrainedNext);
+/// assert_eq!(lints.level(Lint::UnusedVariable), Level::Deny);
+/// assert_eq!(lints.level(Lint::VacuousCandidate), Level::Warn);
+///
+/// let lints = Lints::new().set_str("eq_in_condition", "allow").unwrap();
+/// assert_eq!(lints.level(Lint::EqInCondition), Level::Allow);
+/// assert!(Lints::new().set_str("unused", "deny").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lints {
+    /// Levels that are not the default one.
+    levels: Map<Lint, Level>,
+}
+impl Lints {
+    /// Default levels, see [`Lint::default_level`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Level of a lint.
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels
+            .get(&lint)
+            .cloned()
+            .unwrap_or_else(|| lint.default_level())
+    }
+    /// Sets the level of a lint.
+    pub fn set(mut self, lint: Lint, level: Level) -> Self {
+        let _ = self.levels.insert(lint, level);
+        self
+    }
+    /// Sets the level of a lint from their names.
+    pub fn set_str(self, lint: impl AsRef<str>, level: impl AsRef<str>) -> Res<Self> {
+        let (lint, level) = (lint.as_ref(), level.as_ref());
+        let lint = match Lint::of_str(lint) {
+            Some(lint) => lint,
+            None => bail!("unknown lint `{}`", lint),
+        };
+        let level = match Level::of_str(level) {
+            Some(level) => level,
+            None => bail!(
+                "unknown lint level `{}`, expected allow, warn or deny",
+                level
+            ),
+        };
+        Ok(self.set(lint, level))
+    }
+    /// Allows a lint.
+    pub fn allow(self, lint: Lint) -> Self {
+        self.set(lint, Level::Allow)
+    }
+    /// Warns on a lint.
+    pub fn warn(self, lint: Lint) -> Self {
+        self.set(lint, Level::Warn)
+    }
+    /// Denies a lint.
+    pub fn deny(self, lint: Lint) -> Self {
+        self.set(lint, Level::Deny)
+    }
+}
+
+/// A finding of a lint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// Lint.
+    pub lint: Lint,
+    /// Level of the lint.
+    pub level: Level,
+    /// Description of the finding.
+    pub msg: String,
+}
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.level == Level::Deny {
+            "error"
+        } else {
+            "warning"
+        };
+        write!(fmt, "{}[{}]: {}", kind, self.lint, self.msg)
+    }
+}
+
+/// Runs the lints of a system, yields the warnings.
+///
+/// # Errors
+///
+/// - when a denied lint has some finding, the error lists all of them.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{lint::{self, Lint, Lints}, parse};
+/// let sys = parse::trans(r#"
+/// svars { cnt: int, reset: bool, unused: int }
+/// init { cnt = 0 }
+/// trans { if 'cnt = 0 { 'reset } else { 'cnt = cnt + 1 } }
+/// candidates { "cnt is positive": cnt ≥ 0, "obviously": 7 > 2 }
+/// "#).unwrap();
+///
+/// let warnings = lint::system(&sys, &Lints::new()).unwrap();
+/// let warnings: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+/// assert_eq!(warnings, vec![
+///     "warning[unused_variable]: state variable `unused` is never used",
+///     "warning[vacuous_candidate]: candidate `obviously` mentions no variable, it is always true",
+///     "warning[eq_in_condition]: `if` condition compares next state variable `'cnt` with `=`, \
+///     an assignment might have been intended",
+/// ]);
+///
+/// let err = lint::system(&sys, &Lints::new().deny(Lint::UnusedVariable)).unwrap_err();
+/// assert_eq!(err.to_string(), "error[unused_variable]: state variable `unused` is never used");
+/// ```
+pub fn system(sys: &Sys, lints: &Lints) -> Res<Vec<Warning>> {
+    let mut findings = vec![];
+    for lint in Lint::ALL.iter().cloned() {
+        let level = lints.level(lint);
+        if level == Level::Allow {
+            continue;
+        }
+        for msg in run(lint, sys)? {
+            findings.push(Warning { lint, level, msg })
+        }
+    }
+
+    let denied: Vec<String> = findings
+        .iter()
+        .filter(|w| w.level == Level::Deny)
+        .map(|w| w.to_string())
+        .collect();
+    if !denied.is_empty() {
+        bail!("{}", denied.join("\n"))
+    }
+    Ok(findings)
+}
+
+/// Runs a lint, yields the description of its findings.
+fn run(lint: Lint, sys: &Sys) -> Res<Vec<String>> {
+    let res = match lint {
+        Lint::UnusedVariable => {
+            let mut used = Set::new();
+            sys.init().fold(
+                |var| {
+                    let _ = used.insert(var.id());
+                },
+                |_| (),
+                |_, _| (),
+            );
+            sys.trans().fold(
+                |var| {
+                    let _ = used.insert(var.id());
+                },
+                |_| (),
+                |_, _| (),
+            );
+            for po in sys.po_s().values() {
+                po.fold(
+                    |var| {
+                        let _ = used.insert(var.id());
+                    },
+                    |_| (),
+                    |_, _| (),
+                );
+            }
+            sys.decls()
+                .all()
+                .filter(|var| !used.contains(var.id()))
+                .map(|var| format!("state variable `{}` is never used", var.id()))
+                .collect()
+        }
+        Lint::VacuousCandidate => {
+            let mut res = vec![];
+            for (name, po) in sys.po_s() {
+                // Uninterpreted function applications are not vacuous either.
+                let has_vars = po.fold(
+                    |_| true,
+                    |_| false,
+                    |op, kids| matches!(op, Op::Fun(_)) || kids.into_iter().any(|b| b),
+                );
+                if !has_vars {
+                    let val = check::replay::eval_at(po, &check::trace::Trace::new(), 0)
+                        .chain_err(|| format!("while evaluating candidate `{}`", name))?;
+                    res.push(format!(
+                        "candidate `{}` mentions no variable, it is always {}",
+                        name,
+                        if val.as_bool()? { "true" } else { "false" }
+                    ))
+                }
+            }
+            res
+        }
+        Lint::UnconstrainedNext => sys
+            .unconstrained()
+            .into_iter()
+            .map(|var| {
+                format!(
+                    "the transition relation does not constrain the next value of `{}`",
+                    var.id()
+                )
+            })
+            .collect(),
+        // Checked after the proofs, by `check::run`.
+        Lint::VacuousProof => vec![],
+        Lint::EqInCondition => {
+            let mut res = vec![];
+            let mut todo: Vec<&SExpr> = vec![sys.trans()];
+            while let Some(expr) = todo.pop() {
+                if let PExpr::App { op, args, .. } = expr {
+                    if *op == Op::Ite {
+                        if let Some(cnd) = args.first() {
+                            if let Some(var) = next_eq(cnd) {
+                                res.push(format!(
+                                    "`if` condition compares next state variable `'{}` with `=`, \
+                                    an assignment might have been intended",
+                                    var
+                                ))
+                            }
+                        }
+                    }
+                    todo.extend(args.iter().rev())
+                }
+            }
+            res
+        }
+    };
+    Ok(res)
+}
+
+/// Identifier of a next state variable compared by an equality, if any.
+fn next_eq(expr: &SExpr) -> Option<&str> {
+    match expr {
+        PExpr::App {
+            op: Op::Eq, args, ..
+        } => args.iter().find_map(|arg| match arg {
+            PExpr::Var(var) if var.is_next() => Some(var.id()),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,977 @@
+//! Language server support, requires feature `lsp`.
+//!
+//! A [`Server`] tracks the hsmt documents, systems and scripts, open in an editor and computes
+//! their diagnostics: parse errors, type errors and, for systems, [`lint`] warnings.
+//! Diagnostics are computed when a document is opened and when it is saved, not on each change.
+//! [`Server::check`] checks a system on demand, and reports the verdicts of its candidates as
+//! diagnostics on their names.
+//!
+//! [`Server::serve`] runs the server over the [Language Server Protocol][lsp], usually on the
+//! standard input and output of the process. [`Server::handle`] handles a single JSON-RPC message
+//! and produces the messages to send back:
+//!
+//! - `initialize` and `shutdown` requests, and `initialized` and `exit` notifications manage the
+//!   lifecycle of the server;
+//! - `textDocument/didOpen`, `didChange`, `didSave` and `didClose` notifications update the
+//!   documents and produce `textDocument/publishDiagnostics` notifications;
+//! - other requests fail with `MethodNotFound`, other notifications are ignored.
+//!
+//! [`read_message`] and [`write_message`] implement the base protocol, *i.e.* the
+//! `Content-Length` framing of the messages, messages are read as [`Json`] values.
+//!
+//! Positions follow the protocol: lines and characters start at `0`, and characters are counted in
+//! UTF-16 code units.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use mikino_api::lsp::Server;
+//! let mut server = Server::new();
+//! let publish = server.open("file:///demo.rs", Some(1), mikino_api::TRANS_DEMO);
+//! assert!(publish.diagnostics.is_empty());
+//!
+//! let txt = "svars { cnt: int }\ninit { cnt }\ntrans { 'cnt = cnt + 1 }\n\
+//!     candidates { \"cnt is positive\": cnt ≥ 0 }";
+//! let publish = server.save("file:///demo.rs", Some(txt.into())).unwrap();
+//! assert_eq!(
+//!     publish.params(),
+//!     "{\"uri\":\"file:///demo.rs\",\"version\":1,\"diagnostics\":[{\"range\":\
+//!     {\"start\":{\"line\":1,\"character\":7},\"end\":{\"line\":1,\"character\":10}},\
+//!     \"severity\":1,\"source\":\"mikino\",\"message\":\"expected a boolean expression, got \
+//!     `int`\\nrun mikino in 'demo' mode for more details about the syntax\"}]}",
+//! );
+//! ```
+//!
+//! [lsp]: https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/
+//! (Language Server Protocol specification)
+
+crate::prelude!();
+
+use std::io::{BufRead, Read, Write};
+
+use crate::{
+    check::{trace::json_str, Verdict},
+    json::Json,
+    lint::{self, Lints},
+    meta::VERSION,
+};
+
+/// Result of the `initialize` request, the capabilities of the server.
+///
+/// Documents are synchronized in full, and the text of saved documents is required.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::lsp::initialize_result;
+/// let res = initialize_result();
+/// assert!(res.starts_with(
+///     r#"{"capabilities":{"textDocumentSync":{"openClose":true,"change":1,"save":{"includeText":true}}}"#
+/// ));
+/// assert!(res.contains(r#""serverInfo":{"name":"mikino""#));
+/// ```
+pub fn initialize_result() -> String {
+    format!(
+        "{{\"capabilities\":{{\"textDocumentSync\":{{\"openClose\":true,\"change\":1,\
+        \"save\":{{\"includeText\":true}}}}}},\"serverInfo\":{{\"name\":\"mikino\",\"version\":{}}}}}",
+        json_str(VERSION),
+    )
+}
+
+/// Maximal length of a message body, in bytes, see [`read_message`].
+pub const MAX_CONTENT_LENGTH: usize = 1 << 24;
+
+/// Maximal length of a line of a message header, in bytes, see [`read_message`].
+pub const MAX_HEADER_LENGTH: usize = 1 << 10;
+
+/// Reads a message of the base protocol, `None` at the end of the input.
+///
+/// The input is not trusted: the body of the message is only allocated once its length is known
+/// to be at most [`MAX_CONTENT_LENGTH`], and header lines longer than [`MAX_HEADER_LENGTH`] are
+/// rejected.
+///
+/// # Errors
+///
+/// - on IO errors,
+/// - when the header of the message has no `Content-Length`, is not terminated, or has a line
+///   longer than [`MAX_HEADER_LENGTH`],
+/// - when the `Content-Length` is larger than [`MAX_CONTENT_LENGTH`], and
+/// - when the body of the message is not UTF-8.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::lsp::{read_message, write_message};
+/// let mut bytes = vec![];
+/// write_message(&mut bytes, r#"{"jsonrpc":"2.0","method":"exit"}"#).unwrap();
+/// write_message(&mut bytes, "ϕ").unwrap();
+/// assert_eq!(
+///     String::from_utf8(bytes.clone()).unwrap(),
+///     "Content-Length: 33\r\n\r\n{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}Content-Length: 2\r\n\r\nϕ",
+/// );
+///
+/// let mut input = &bytes[..];
+/// assert_eq!(
+///     read_message(&mut input).unwrap().unwrap(),
+///     r#"{"jsonrpc":"2.0","method":"exit"}"#,
+/// );
+/// assert_eq!(read_message(&mut input).unwrap().unwrap(), "ϕ");
+/// assert_eq!(read_message(&mut input).unwrap(), None);
+///
+/// let mut input = "Content-Type: utf-8\r\n\r\n{}".as_bytes();
+/// assert!(read_message(&mut input).is_err());
+///
+/// let mut input = "Content-Length: 18446744073709551615\r\n\r\n{}".as_bytes();
+/// assert_eq!(
+///     read_message(&mut input).unwrap_err().to_string(),
+///     "`Content-Length` 18446744073709551615 exceeds the maximum of 16777216 bytes",
+/// );
+/// let header = format!("X-Padding: {}\r\n", "-".repeat(2_000));
+/// assert!(read_message(&mut header.as_bytes()).is_err());
+/// ```
+pub fn read_message(input: &mut impl BufRead) -> Res<Option<String>> {
+    let (mut len, mut line, mut started) = (None, String::new(), false);
+    loop {
+        line.clear();
+        let read = (&mut *input)
+            .take(MAX_HEADER_LENGTH as u64)
+            .read_line(&mut line)?;
+        if read == 0 {
+            if started {
+                bail!("unexpected end of input in message header")
+            }
+            return Ok(None);
+        } else if read == MAX_HEADER_LENGTH && !line.ends_with('\n') {
+            bail!(
+                "message header line exceeds the maximum of {} bytes",
+                MAX_HEADER_LENGTH
+            )
+        }
+        started = true;
+        let header = line.trim_end_matches(&['\r', '\n'] as &[char]);
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("illegal message header `{}`", header))?;
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let value = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| format!("illegal `Content-Length` `{}`: {}", value.trim(), e))?;
+            len = Some(value)
+        }
+    }
+    let len = len.ok_or("message header has no `Content-Length`")?;
+    if len > MAX_CONTENT_LENGTH {
+        bail!(
+            "`Content-Length` {} exceeds the maximum of {} bytes",
+            len,
+            MAX_CONTENT_LENGTH
+        )
+    }
+    let mut body = vec![0; len];
+    input.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|e| format!("illegal message body: {}", e))?;
+    Ok(Some(body))
+}
+
+/// Writes a message of the base protocol.
+///
+/// See [`read_message`] for examples.
+pub fn write_message(output: &mut impl Write, msg: &str) -> Res<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{}", msg.len(), msg)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Kind of an hsmt document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A transition system, see [`parse::trans`].
+    System,
+    /// A script, see [`crate::script::build::of_str`].
+    Script,
+}
+impl Kind {
+    /// Kind of a document from its text.
+    ///
+    /// Systems start with a declaration block (`svars`, `inputs`, `outputs` or `state`) or with
+    /// `subsystems`, after comments and documentation, everything else is a script.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::lsp::Kind;
+    /// assert_eq!(Kind::of_text(mikino_api::TRANS_DEMO), Kind::System);
+    /// assert_eq!(Kind::of_text(mikino_api::SCRIPT_DEMO), Kind::Script);
+    /// assert_eq!(Kind::of_text("// system\n/// cnt\nsvars { cnt: int }"), Kind::System);
+    /// assert_eq!(Kind::of_text(""), Kind::Script);
+    /// ```
+    pub fn of_text(txt: &str) -> Self {
+        let start = first_token(txt);
+        if ["svars", "inputs", "outputs", "state", "subsystems"]
+            .iter()
+            .any(|kw| txt[start..].starts_with(kw))
+        {
+            Self::System
+        } else {
+            Self::Script
+        }
+    }
+}
+
+/// Offset of the first token of a text, after whitespaces and comments.
+fn first_token(txt: &str) -> usize {
+    let mut rest = txt;
+    loop {
+        let trimmed = rest.trim_start();
+        if trimmed.starts_with("//") {
+            rest = trimmed.find('\n').map(|idx| &trimmed[idx..]).unwrap_or("");
+        } else {
+            return txt.len() - trimmed.len();
+        }
+    }
+}
+
+/// A position in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    /// Line, starts at `0`.
+    pub line: usize,
+    /// Character in the line in UTF-16 code units, starts at `0`.
+    pub character: usize,
+}
+impl Position {
+    /// Position of a byte offset in a text, offsets past the end of the text are clipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::lsp::Position;
+    /// let txt = "init {\n    x ≥ 0 ⋀ 𝔹\n}";
+    /// assert_eq!(Position::of_offset(txt, 11), Position { line: 1, character: 4 });
+    /// // `≥` and `⋀` are one UTF-16 code unit but three bytes, `𝔹` is two code units.
+    /// assert_eq!(Position::of_offset(txt, 23), Position { line: 1, character: 12 });
+    /// assert_eq!(Position::of_offset(txt, 27), Position { line: 1, character: 14 });
+    /// assert_eq!(Position::of_offset(txt, 100), Position { line: 2, character: 1 });
+    /// ```
+    pub fn of_offset(txt: &str, offset: usize) -> Self {
+        let mut offset = offset.min(txt.len());
+        while !txt.is_char_boundary(offset) {
+            offset -= 1
+        }
+        let before = &txt[..offset];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        Self {
+            line: before.matches('\n').count(),
+            character: before[line_start..].encode_utf16().count(),
+        }
+    }
+
+    /// JSON representation.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"line\":{},\"character\":{}}}",
+            self.line, self.character
+        )
+    }
+}
+
+/// A range in a document, `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Range {
+    /// Start of the range.
+    pub start: Position,
+    /// End of the range.
+    pub end: Position,
+}
+impl Range {
+    /// Range of a span in a text.
+    pub fn of_span(txt: &str, span: Span) -> Self {
+        Self {
+            start: Position::of_offset(txt, span.start),
+            end: Position::of_offset(txt, span.end),
+        }
+    }
+
+    /// JSON representation.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"start\":{},\"end\":{}}}",
+            self.start.to_json(),
+            self.end.to_json()
+        )
+    }
+}
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Errors.
+    Error,
+    /// Warnings.
+    Warning,
+    /// Information.
+    Information,
+    /// Hints.
+    Hint,
+}
+impl Severity {
+    /// Code of the severity in the protocol.
+    pub fn code(self) -> usize {
+        match self {
+            Self::Error => 1,
+            Self::Warning => 2,
+            Self::Information => 3,
+            Self::Hint => 4,
+        }
+    }
+}
+
+/// A diagnostic on a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Range of the diagnostic.
+    pub range: Range,
+    /// Severity.
+    pub severity: Severity,
+    /// Code of the diagnostic, the name of the lint for lint warnings.
+    pub code: Option<String>,
+    /// Message.
+    pub message: String,
+}
+impl Diagnostic {
+    /// Diagnostic of an error on a document.
+    ///
+    /// Parse and type errors are reported on their snippet, other errors at the beginning of the
+    /// document. The message is the rest of the error chain, one error per line.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{lsp::{Diagnostic, Position}, parse};
+    /// let txt = "svars { cnt: int }\ninit { cnt + 1 }\ntrans { 'cnt = cnt }\n\
+    ///     candidates { \"c\": cnt ≥ 0 }";
+    /// let diag = Diagnostic::of_error(txt, &parse::trans(txt).err().unwrap());
+    /// assert_eq!(diag.range.start, Position { line: 1, character: 7 });
+    /// assert_eq!(diag.range.end, Position { line: 1, character: 14 });
+    /// assert_eq!(
+    ///     diag.message,
+    ///     "expected a boolean expression, got `int`\n\
+    ///     run mikino in 'demo' mode for more details about the syntax",
+    /// );
+    /// ```
+    pub fn of_error(txt: &str, err: &ErrorChain) -> Self {
+        let mut range = None;
+        let mut lines = vec![];
+        for e in err.iter() {
+            match e {
+                Error::Parse {
+                    msg, row, col, len, ..
+                } if range.is_none() => {
+                    let start = txt
+                        .split('\n')
+                        .take(*row)
+                        .map(|line| line.len() + 1)
+                        .sum::<usize>()
+                        + col;
+                    // Empty snippets still cover one character, if any.
+                    let end = if *len > 0 {
+                        start + len
+                    } else {
+                        txt.get(start..)
+                            .and_then(|rest| rest.chars().next())
+                            .map(|c| start + c.len_utf8())
+                            .unwrap_or(start)
+                    };
+                    range = Some(Range::of_span(txt, Span::new(start, end)));
+                    if !msg.is_empty() {
+                        lines.push(msg.clone())
+                    }
+                }
+                e => lines.push(e.to_string()),
+            }
+        }
+        Self {
+            range: range.unwrap_or_else(|| Range::of_span(txt, Span::new(0, 0))),
+            severity: Severity::Error,
+            code: None,
+            message: lines.join("\n"),
+        }
+    }
+
+    /// Diagnostic of a lint warning on a system, reported on the first token of the system.
+    pub fn of_warning(txt: &str, warning: &lint::Warning) -> Self {
+        let start = first_token(txt);
+        let end = txt[start..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map(|len| start + len)
+            .unwrap_or(txt.len());
+        Self {
+            range: Range::of_span(txt, Span::new(start, end)),
+            severity: if warning.level == lint::Level::Deny {
+                Severity::Error
+            } else {
+                Severity::Warning
+            },
+            code: Some(warning.lint.name().into()),
+            message: warning.msg.clone(),
+        }
+    }
+
+    /// Diagnostic of the verdict of a candidate of a system, reported on the name of the candidate.
+    ///
+    /// Falsifications are errors, or warnings if the candidate has severity warning (`is_warning`).
+    /// Unknown and timeout verdicts are warnings, and proofs are information.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check::{cexs::Cex, Verdict}, lsp::{Diagnostic, Position, Severity}};
+    /// let txt = "svars { cnt: int }\ninit { cnt = 0 }\ntrans { 'cnt = cnt + 1 }\n\
+    ///     candidates { \"cnt ≥ 0\": cnt ≥ 0, \"cnt < 3\": cnt < 3 }";
+    /// let diag = Diagnostic::of_verdict(txt, "cnt ≥ 0", &Verdict::Proved, false);
+    /// assert_eq!(diag.severity, Severity::Information);
+    /// assert_eq!(diag.message, "candidate `cnt ≥ 0`: proved");
+    /// assert_eq!(diag.range.start, Position { line: 3, character: 13 });
+    /// assert_eq!(diag.range.end, Position { line: 3, character: 22 });
+    ///
+    /// let falsified = Verdict::Falsified { depth: 3, cex: Cex::new() };
+    /// let diag = Diagnostic::of_verdict(txt, "cnt < 3", &falsified, true);
+    /// assert_eq!(diag.severity, Severity::Warning);
+    /// assert_eq!(diag.message, "candidate `cnt < 3`: falsified at step 3");
+    /// assert_eq!(diag.range.start, Position { line: 3, character: 33 });
+    /// ```
+    pub fn of_verdict(txt: &str, name: &str, verdict: &Verdict, is_warning: bool) -> Self {
+        let quoted = format!("\"{}\"", name);
+        // Candidates are defined last, after any label with the same text.
+        let span = txt
+            .rfind(&quoted)
+            .map(|start| Span::new(start, start + quoted.len()))
+            .unwrap_or_else(|| Span::new(0, 0));
+        let severity = match verdict {
+            Verdict::Proved => Severity::Information,
+            Verdict::Falsified { .. } if !is_warning => Severity::Error,
+            Verdict::Falsified { .. } | Verdict::Unknown { .. } | Verdict::Timeout { .. } => {
+                Severity::Warning
+            }
+        };
+        Self {
+            range: Range::of_span(txt, span),
+            severity,
+            code: None,
+            message: format!("candidate `{}`: {}", name, verdict),
+        }
+    }
+
+    /// JSON representation.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"range\":{},\"severity\":{},{}\"source\":\"mikino\",\"message\":{}}}",
+            self.range.to_json(),
+            self.severity.code(),
+            self.code
+                .as_ref()
+                .map(|code| format!("\"code\":{},", json_str(code)))
+                .unwrap_or_default(),
+            json_str(&self.message),
+        )
+    }
+}
+
+/// Diagnostics of a document.
+///
+/// Documents with an error have exactly one diagnostic, valid systems have one diagnostic per lint
+/// warning.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::lint::Lints;
+/// # use mikino_api::lsp::{diagnostics, Kind, Severity};
+/// let txt = "svars { cnt: int, unused: bool }\ninit { cnt = 0 }\ntrans { 'cnt = cnt + 1 }\n\
+///     candidates { \"cnt is positive\": cnt ≥ 0 }";
+/// let diags = diagnostics(txt, Kind::System, &Lints::new());
+/// assert_eq!(diags.len(), 1);
+/// assert_eq!(diags[0].severity, Severity::Warning);
+/// assert_eq!(diags[0].code.as_ref().unwrap(), "unused_variable");
+///
+/// let txt = "vars { n: int }\nassert { n ≥ 0 }\ncheck_sat!()";
+/// let diags = diagnostics(txt, Kind::Script, &Lints::new());
+/// assert!(diags.is_empty());
+/// let diags = diagnostics("vars { n: int }\nassert { n + 1 }", Kind::Script, &Lints::new());
+/// assert_eq!(diags[0].message, "assertion has type int, expected bool");
+/// ```
+pub fn diagnostics(txt: &str, kind: Kind, lints: &Lints) -> Vec<Diagnostic> {
+    let res = match kind {
+        Kind::System => parse::trans(txt).and_then(|sys| lint::system(&sys, lints)),
+        Kind::Script => crate::script::build::of_str(txt).map(|_| vec![]),
+    };
+    match res {
+        Ok(warnings) => warnings
+            .iter()
+            .map(|warning| Diagnostic::of_warning(txt, warning))
+            .collect(),
+        Err(e) => vec![Diagnostic::of_error(txt, &e)],
+    }
+}
+
+/// Diagnostics of a document, the parameters of a `textDocument/publishDiagnostics` notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publish {
+    /// URI of the document.
+    pub uri: String,
+    /// Version of the document, if known.
+    pub version: Option<i64>,
+    /// Diagnostics, replace the previous diagnostics of the document.
+    pub diagnostics: Vec<Diagnostic>,
+}
+impl Publish {
+    /// JSON parameters of the notification.
+    pub fn params(&self) -> String {
+        let diagnostics: Vec<String> = self.diagnostics.iter().map(Diagnostic::to_json).collect();
+        format!(
+            "{{\"uri\":{},{}\"diagnostics\":[{}]}}",
+            json_str(&self.uri),
+            self.version
+                .map(|version| format!("\"version\":{},", version))
+                .unwrap_or_default(),
+            diagnostics.join(","),
+        )
+    }
+
+    /// JSON notification, to send with [`write_message`].
+    pub fn notification(&self) -> String {
+        format!(
+            "{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{}}}",
+            self.params()
+        )
+    }
+}
+
+/// A document open in the editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    /// Text of the document.
+    pub text: String,
+    /// Version of the document, if known.
+    pub version: Option<i64>,
+    /// Kind of the document, as of its last save.
+    pub kind: Kind,
+}
+
+/// JSON-RPC error codes.
+mod code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const SERVER_NOT_INITIALIZED: i64 = -32002;
+}
+
+/// JSON response to a request.
+fn response(id: &Json, res: Result<String, (i64, String)>) -> String {
+    match res {
+        Ok(res) => format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{}}}", id, res),
+        Err((code, msg)) => format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":{},\"error\":{{\"code\":{},\"message\":{}}}}}",
+            id,
+            code,
+            json_str(&msg)
+        ),
+    }
+}
+
+/// JSON `window/logMessage` notification of an error.
+fn log_error(msg: &str) -> String {
+    format!(
+        "{{\"jsonrpc\":\"2.0\",\"method\":\"window/logMessage\",\"params\":\
+        {{\"type\":1,\"message\":{}}}}}",
+        json_str(msg)
+    )
+}
+
+/// URI and version of the `textDocument` of some parameters.
+fn text_document(params: &Json) -> Res<(&str, Option<i64>)> {
+    let uri = params
+        .path(&["textDocument", "uri"])
+        .and_then(Json::as_str)
+        .ok_or("missing `textDocument.uri` parameter")?;
+    let version = params
+        .path(&["textDocument", "version"])
+        .and_then(Json::as_i64);
+    Ok((uri, version))
+}
+
+/// A language server, see the [module-level documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct Server {
+    /// Open documents, by URI.
+    docs: Map<String, Document>,
+    /// Lints of the diagnostics on systems.
+    lints: Lints,
+    /// True once the `initialize` request is handled.
+    initialized: bool,
+    /// True once the `shutdown` request is handled.
+    shutdown: bool,
+    /// Exit code, set by the `exit` notification.
+    exit: Option<i32>,
+}
+impl Server {
+    /// Constructor, with the default lints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the lints of the diagnostics on systems.
+    pub fn with_lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// An open document.
+    pub fn document(&self, uri: &str) -> Option<&Document> {
+        self.docs.get(uri)
+    }
+
+    /// Diagnostics of a document.
+    fn publish(&self, uri: &str, doc: &Document) -> Publish {
+        Publish {
+            uri: uri.into(),
+            version: doc.version,
+            diagnostics: diagnostics(&doc.text, doc.kind, &self.lints),
+        }
+    }
+
+    /// Opens a document, on `textDocument/didOpen`.
+    pub fn open(
+        &mut self,
+        uri: impl Into<String>,
+        version: Option<i64>,
+        text: impl Into<String>,
+    ) -> Publish {
+        let (uri, text) = (uri.into(), text.into());
+        let doc = Document {
+            kind: Kind::of_text(&text),
+            text,
+            version,
+        };
+        let publish = self.publish(&uri, &doc);
+        let _ = self.docs.insert(uri, doc);
+        publish
+    }
+
+    /// Changes the text of a document, on `textDocument/didChange`.
+    ///
+    /// Diagnostics are only computed on save.
+    ///
+    /// # Errors
+    ///
+    /// - when the document is not open.
+    pub fn change(&mut self, uri: &str, version: Option<i64>, text: impl Into<String>) -> Res<()> {
+        let doc = self
+            .docs
+            .get_mut(uri)
+            .ok_or_else(|| format!("document `{}` is not open", uri))?;
+        doc.text = text.into();
+        doc.version = version;
+        Ok(())
+    }
+
+    /// Saves a document, on `textDocument/didSave`.
+    ///
+    /// `text` is the text of the notification, if any.
+    ///
+    /// # Errors
+    ///
+    /// - when the document is not open.
+    pub fn save(&mut self, uri: &str, text: Option<String>) -> Res<Publish> {
+        let doc = self
+            .docs
+            .get_mut(uri)
+            .ok_or_else(|| format!("document `{}` is not open", uri))?;
+        if let Some(text) = text {
+            doc.text = text
+        }
+        doc.kind = Kind::of_text(&doc.text);
+        let doc = doc.clone();
+        Ok(self.publish(uri, &doc))
+    }
+
+    /// Closes a document, on `textDocument/didClose`.
+    ///
+    /// Clears the diagnostics of the document.
+    pub fn close(&mut self, uri: &str) -> Publish {
+        let _ = self.docs.remove(uri);
+        Publish {
+            uri: uri.into(),
+            version: None,
+            diagnostics: vec![],
+        }
+    }
+
+    /// Handles a JSON-RPC message, produces the messages to send back.
+    ///
+    /// Requests always get a response, even on errors. Notification errors, *e.g.* changes on a
+    /// document that is not open, produce a `window/logMessage` notification.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::lsp::Server;
+    /// let mut server = Server::new();
+    /// let replies = server.handle(r#"{"jsonrpc":"2.0","id":0,"method":"shutdown"}"#);
+    /// assert_eq!(
+    ///     replies,
+    ///     vec![
+    ///         "{\"jsonrpc\":\"2.0\",\"id\":0,\"error\":\
+    ///         {\"code\":-32002,\"message\":\"server is not initialized\"}}",
+    ///     ],
+    /// );
+    ///
+    /// let replies = server.handle(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#);
+    /// assert!(replies[0].starts_with(r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":"#));
+    /// assert!(server.handle(r#"{"jsonrpc":"2.0","method":"initialized"}"#).is_empty());
+    ///
+    /// let replies = server.handle(
+    ///     r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+    ///     {"uri":"file:///a.rs","languageId":"mikino","version":3,"text":"vars { n: int }"}}}"#,
+    /// );
+    /// assert_eq!(
+    ///     replies,
+    ///     vec![
+    ///         "{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\
+    ///         \"params\":{\"uri\":\"file:///a.rs\",\"version\":3,\"diagnostics\":[]}}",
+    ///     ],
+    /// );
+    ///
+    /// let replies = server.handle(
+    ///     r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":
+    ///     {"uri":"file:///b.rs","version":4},"contentChanges":[{"text":""}]}}"#,
+    /// );
+    /// assert!(replies[0].contains(r#""message":"document `file:///b.rs` is not open""#));
+    ///
+    /// let replies = server.handle(r#"{"jsonrpc":"2.0","id":"x","method":"mikino/unknown"}"#);
+    /// assert!(replies[0].contains(r#""id":"x","error":{"code":-32601,"#));
+    /// let replies = server.handle(r#"{"jsonrpc":"2.0","id":2,"method":"#);
+    /// assert!(replies[0].contains(r#""id":null,"error":{"code":-32700,"#));
+    ///
+    /// let replies = server.handle(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#);
+    /// assert_eq!(replies, vec![r#"{"jsonrpc":"2.0","id":3,"result":null}"#]);
+    /// assert_eq!(server.exit_code(), None);
+    /// assert!(server.handle(r#"{"jsonrpc":"2.0","method":"exit"}"#).is_empty());
+    /// assert_eq!(server.exit_code(), Some(0));
+    /// ```
+    pub fn handle(&mut self, msg: &str) -> Vec<String> {
+        let msg = match Json::parse(msg) {
+            Ok(msg) => msg,
+            Err(e) => {
+                return vec![response(
+                    &Json::Null,
+                    Err((code::PARSE_ERROR, e.to_string())),
+                )]
+            }
+        };
+        let method = msg.get("method").and_then(Json::as_str);
+        let params = msg.get("params").unwrap_or(&Json::Null);
+        match (msg.get("id"), method) {
+            (Some(id), Some(method)) => vec![response(id, self.request(method))],
+            (None, Some(method)) => match self.notify(method, params) {
+                Ok(replies) => replies,
+                Err(e) => vec![log_error(&e.to_string())],
+            },
+            // Responses to requests, the server never sends any.
+            (Some(_), None) => vec![],
+            (None, None) => vec![response(
+                &Json::Null,
+                Err((code::INVALID_REQUEST, "message has no `method`".into())),
+            )],
+        }
+    }
+
+    /// Handles a request, produces the JSON result.
+    fn request(&mut self, method: &str) -> Result<String, (i64, String)> {
+        if self.shutdown {
+            return Err((code::INVALID_REQUEST, "server is shutting down".into()));
+        }
+        match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(initialize_result())
+            }
+            _ if !self.initialized => Err((
+                code::SERVER_NOT_INITIALIZED,
+                "server is not initialized".into(),
+            )),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok("null".into())
+            }
+            _ => Err((
+                code::METHOD_NOT_FOUND,
+                format!("unsupported method `{}`", method),
+            )),
+        }
+    }
+
+    /// Handles a notification, produces the notifications to send back.
+    fn notify(&mut self, method: &str, params: &Json) -> Res<Vec<String>> {
+        if method == "exit" {
+            self.exit = Some(if self.shutdown { 0 } else { 1 });
+            return Ok(vec![]);
+        } else if !self.initialized || self.shutdown {
+            return Ok(vec![]);
+        }
+        let publish = match method {
+            "textDocument/didOpen" => {
+                let (uri, version) = text_document(params)?;
+                let text = params
+                    .path(&["textDocument", "text"])
+                    .and_then(Json::as_str)
+                    .ok_or("missing `textDocument.text` parameter")?;
+                self.open(uri, version, text)
+            }
+            "textDocument/didChange" => {
+                let (uri, version) = text_document(params)?;
+                // Documents are synchronized in full, the last change is the whole text.
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_arr)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str)
+                    .ok_or("missing `contentChanges` parameter")?;
+                self.change(uri, version, text)?;
+                return Ok(vec![]);
+            }
+            "textDocument/didSave" => {
+                let (uri, _) = text_document(params)?;
+                let text = params.get("text").and_then(Json::as_str).map(String::from);
+                self.save(uri, text)?
+            }
+            "textDocument/didClose" => {
+                let (uri, _) = text_document(params)?;
+                self.close(uri)
+            }
+            _ => return Ok(vec![]),
+        };
+        Ok(vec![publish.notification()])
+    }
+
+    /// Exit code requested by the `exit` notification, if any.
+    ///
+    /// `0` if the server was shut down before exiting, `1` otherwise.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit
+    }
+
+    /// Runs the server until the `exit` notification or the end of the input, yields the exit code.
+    ///
+    /// The exit code is `0` if the server was shut down, `1` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// - when [`read_message`] or [`write_message`] fail, the input cannot be resynchronized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::lsp::{read_message, write_message, Server};
+    /// let mut input = vec![];
+    /// for msg in &[
+    ///     r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+    ///     r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+    ///     {"uri":"file:///a.rs","version":1,"text":"vars { n: int }\nassert { n + 1 }"}}}"#,
+    ///     r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+    ///     r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ///     r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+    /// ] {
+    ///     write_message(&mut input, msg).unwrap();
+    /// }
+    ///
+    /// let mut output = vec![];
+    /// let code = Server::new().serve(&mut &input[..], &mut output).unwrap();
+    /// assert_eq!(code, 0);
+    ///
+    /// let mut output = &output[..];
+    /// let mut replies = vec![];
+    /// while let Some(msg) = read_message(&mut output).unwrap() {
+    ///     replies.push(msg)
+    /// }
+    /// assert_eq!(replies.len(), 3);
+    /// assert!(replies[1].contains(r#""message":"assertion has type int, expected bool""#));
+    /// assert_eq!(replies[2], r#"{"jsonrpc":"2.0","id":2,"result":null}"#);
+    /// ```
+    ///
+    /// On the standard input and output of the process:
+    ///
+    /// ```rust,no_run
+    /// # use mikino_api::lsp::Server;
+    /// let (stdin, stdout) = (std::io::stdin(), std::io::stdout());
+    /// let code = Server::new()
+    ///     .serve(&mut stdin.lock(), &mut stdout.lock())
+    ///     .unwrap();
+    /// std::process::exit(code)
+    /// ```
+    pub fn serve(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> Res<i32> {
+        while let Some(msg) = read_message(input)? {
+            for reply in self.handle(&msg) {
+                write_message(output, &reply)?
+            }
+            if let Some(code) = self.exit {
+                return Ok(code);
+            }
+        }
+        Ok(if self.shutdown { 0 } else { 1 })
+    }
+
+    /// Checks a system on demand, see [`check::run`].
+    ///
+    /// The diagnostics are the [verdicts](Diagnostic::of_verdict) of the candidates, and the lint
+    /// warnings of `config`. Systems with errors only have the diagnostic of the error.
+    ///
+    /// # Errors
+    ///
+    /// - when the document is not open or is not a system, and
+    /// - when the check fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mikino_api::{check::Config, lsp::Server, rsmt2::SmtConf};
+    /// let mut server = Server::new();
+    /// let _ = server.open("file:///demo.rs", None, mikino_api::TRANS_DEMO);
+    /// let config = Config::new(SmtConf::z3("z3"));
+    /// let publish = server.check("file:///demo.rs", &config).unwrap();
+    /// println!("{}", publish.notification());
+    /// ```
+    pub fn check(&self, uri: &str, config: &check::Config) -> Res<Publish> {
+        let doc = self
+            .docs
+            .get(uri)
+            .ok_or_else(|| format!("document `{}` is not open", uri))?;
+        if doc.kind != Kind::System {
+            bail!("document `{}` is not a system", uri)
+        }
+        let txt = &doc.text;
+        let diagnostics = match parse::trans(txt) {
+            Ok(sys) => {
+                let outcome = check::run(&sys, config)?;
+                let lints = outcome
+                    .lints
+                    .iter()
+                    .map(|warning| Diagnostic::of_warning(txt, warning));
+                let verdicts = outcome.verdicts.iter().map(|(name, verdict)| {
+                    Diagnostic::of_verdict(txt, name, verdict, outcome.warnings.contains(name))
+                });
+                lints.chain(verdicts).collect()
+            }
+            Err(e) => vec![Diagnostic::of_error(txt, &e)],
+        };
+        Ok(Publish {
+            uri: uri.into(),
+            version: doc.version,
+            diagnostics,
+        })
+    }
+}
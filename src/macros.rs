@@ -32,7 +32,8 @@ macro_rules! try_to_pres {
 
 /// Convenience macro, provides a DSL for writing expressions.
 ///
-/// - identifiers must be written as `(var_name: var_typ)`, without any quotes.
+/// - identifiers must be written as `(var_name: var_typ)`, without any quotes;
+/// - panics on ill-typed applications.
 #[macro_export]
 macro_rules! build_expr {
     (true) => ( $crate::expr::PExpr::from(true) );
@@ -59,11 +60,13 @@ macro_rules! build_expr {
     );
 
     ( ($op:tt $($args:tt)*) ) => (
-        $crate::expr::PExpr::from((
+        match $crate::expr::PExpr::new_app(
             $crate::build_expr!(@op $op),
             vec![ $($crate::build_expr!($args)),* ],
-
-        ))
+        ) {
+            Ok(expr) => expr,
+            Err(e) => panic!("ill-typed application in `build_expr!`: {}", e),
+        }
     );
 
     ($cst:expr) => ( $crate::expr::PExpr::from($cst) );
@@ -85,6 +88,13 @@ macro_rules! build_expr {
     (@op !) => ( $crate::expr::Op::Not );
     (@op &&) => ( $crate::expr::Op::And );
     (@op ||) => ( $crate::expr::Op::Or );
+    (@op &) => ( $crate::expr::Op::BvAnd );
+    (@op |) => ( $crate::expr::Op::BvOr );
+    (@op ^) => ( $crate::expr::Op::BvXor );
+    (@op <<) => ( $crate::expr::Op::Shl );
+    (@op >>) => ( $crate::expr::Op::Shr );
+    (@op select) => ( $crate::expr::Op::Select );
+    (@op store) => ( $crate::expr::Op::Store );
 }
 
 /// Builds a type.
@@ -101,21 +111,59 @@ macro_rules! build_typ {
     };
 }
 
-/// Builds some declarations.
+/// Builds some declarations, yields a `Res<trans::Decls>`.
+///
+/// Declarations are groups of the form `<ident>, ... : <type>`, optionally separated by commas.
+/// Doc comments on a group set the [display hints](crate::expr::display::Hint) of its variables,
+/// like in the `svars` block of [`parse::trans`](crate::parse::trans()).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{build_decls, expr::{HasTyp, Typ}};
+/// let decls = build_decls! {
+///     /// Counters.
+///     /// @display: hex
+///     cnt, max: int,
+///     reset: bool
+/// }
+/// .unwrap();
+/// assert_eq!(decls.get_var("max").unwrap().typ(), Typ::Int);
+/// assert_eq!(decls.hint("max").unwrap().display.as_deref(), Some("hex"));
+/// assert!(decls.hint("reset").is_none());
+///
+/// let err = build_decls!(cnt: int reset: bool cnt: bool).unwrap_err();
+/// assert_eq!(err.to_string(), "found multiple definitions of identifier `cnt`");
+/// ```
 #[macro_export]
 macro_rules! build_decls {
+    (@doc $lines:ident, doc = $line:expr) => {
+        $lines.push($line)
+    };
+    (@doc $lines:ident, $($attr:tt)*) => {
+        compile_error!(concat!(
+            "unexpected attribute `", stringify!($($attr)*), "` in declarations"
+        ))
+    };
     {
-        $( $($ident:ident),* $(,)* : $typ:tt )*
+        $( $(#[$($attr:tt)*])* $($ident:ident),+ $(,)* : $typ:tt $(,)? )*
     } => {{
         let mut decls: $crate::prelude::Res<$crate::trans::Decls> = Ok($crate::trans::Decls::new());
         $(
             decls = decls.and_then(|mut decls| {
+                #[allow(unused_mut)]
+                let mut doc: Vec<&str> = vec![];
+                $( $crate::build_decls!(@doc doc, $($attr)*); )*
+                let hint = $crate::expr::display::Hint::of_doc(&doc)?;
                 $(
                     let prev = decls.register(stringify!($ident), $crate::build_typ!($typ));
                     if prev.is_some() {
-                        bail!("found multiple definitions of identifier `{}`", stringify!($ident))
+                        $crate::prelude::bail!(
+                            "found multiple definitions of identifier `{}`", stringify!($ident)
+                        )
                     }
-                )*
+                    let _ = decls.set_hint(stringify!($ident), hint.clone());
+                )+
                 Ok(decls)
             });
         )*
@@ -150,28 +198,156 @@ macro_rules! build_decls {
 //     }};
 // }
 
-/// Builds a transition system.
+/// Builds a transition system, yields a `Res<trans::Sys>`.
+///
+/// The system is specified by
+///
+/// - a `decls` block, see [`build_decls!`];
+/// - one or more `init` blocks of stateless expressions, their conjunction is the initial
+///   predicate;
+/// - zero or more `assume` blocks of stateless expressions, assumed to hold in all states: they
+///   are conjoined to the initial predicate, and to the transition relation over both the current
+///   and the next state;
+/// - a `trans` block of stateful expressions, see [`build_trans_expr!`](crate::build_trans_expr!);
+/// - a `candidates` block of `<name> => <expr>` pairs, where names are string literals.
+///
+/// Candidates accept the same attributes as in [`parse::trans`](crate::parse::trans()):
+/// `#[tag(<ident>, ...)]` and `#[warn]`. Doc comments give them a [label](crate::meta::Label),
+/// like in the text syntax. Expressions use the syntax of
+/// [`build_trans_expr!`](crate::build_trans_expr!).
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{build_trans, trans::Severity};
+/// let sys = build_trans! {
+///     decls {
+///         /// @display: hex
+///         cnt: int
+///         reset: bool
+///     }
+///     init { (= cnt 0) }
+///     init { (not reset) }
+///     assume { (>= cnt 0) }
+///     trans {
+///         (= (cnt @ 1) (ite (reset @ 0) 0 (+ (cnt @ 0) 1))),
+///     }
+///     candidates {
+///         /// Never negative.
+///         #[tag(safety)]
+///         "cnt is positive" => (>= cnt 0),
+///         #[warn]
+///         "cnt is not 3" => (not (= cnt 3)),
+///     }
+/// }
+/// .unwrap();
+/// assert_eq!(sys.init().to_string(), "(and (= cnt 0) (not reset) (>= cnt 0))");
+/// assert_eq!(sys.po_s().len(), 2);
+/// assert_eq!(sys.tags_of("cnt is positive").collect::<Vec<_>>(), vec!["safety"]);
+/// assert_eq!(sys.severity_of("cnt is not 3"), Severity::Warning);
+/// assert_eq!(sys.label_of("cnt is positive").unwrap().title, "Never negative.");
+/// assert!(sys.label_of("cnt is not 3").is_none());
+/// assert_eq!(sys.decls().hint("cnt").unwrap().display.as_deref(), Some("hex"));
+/// ```
 #[macro_export]
 macro_rules! build_trans {
+    (@cand_attr $tags:ident, $warn:ident, $doc:ident, doc = $line:expr) => {
+        $doc.push($line)
+    };
+    (@cand_attr $tags:ident, $warn:ident, $doc:ident, tag($($tag:ident),* $(,)?)) => {
+        $( let _ = $tags.insert(stringify!($tag).to_string()); )*
+    };
+    (@cand_attr $tags:ident, $warn:ident, $doc:ident, warn) => {
+        $warn = true;
+    };
+    (@cand_attr $tags:ident, $warn:ident, $doc:ident, $($attr:tt)*) => {
+        compile_error!(concat!("unexpected candidate attribute `", stringify!($($attr)*), "`"))
+    };
+
+    (@conj $exprs:ident) => {
+        match $exprs.len() {
+            0 => $crate::expr::PExpr::from(true),
+            1 => $exprs.pop().expect("[unreachable] pop on vec of len `1`"),
+            _ => $crate::expr::PExpr::new_app($crate::expr::Op::And, $exprs)?,
+        }
+    };
+
+    (
+        decls {
+            $($decls:tt)*
+        }
+        $( init { $($init:tt),* $(,)? } )+
+        $( assume { $($assume:tt),* $(,)? } )*
+        trans { $($trans:tt),* $(,)? }
+        candidates {
+            $( $(#[$($attr:tt)*])* $name:literal => $po:tt ),* $(,)?
+        }
+    ) => {{
+        let build = || -> $crate::prelude::Res<$crate::trans::Sys> {
+            let decls = $crate::build_decls!($($decls)*)?;
+            let mut init: Vec<$crate::expr::Expr> = vec![];
+            let mut trans: Vec<$crate::expr::SExpr> = vec![];
+            $( $( init.push($crate::build_trans_expr!(stateless, decls, $init)); )* )+
+            $( $(
+                init.push($crate::build_trans_expr!(stateless, decls, $assume));
+                trans.push($crate::build_trans_expr!(curr, decls, $assume));
+                trans.push($crate::build_trans_expr!(next, decls, $assume));
+            )* )*
+            $( trans.push($crate::build_trans_expr!(stateful, decls, $trans)); )*
+            let init = $crate::build_trans!(@conj init);
+            let trans = $crate::build_trans!(@conj trans);
+
+            let mut po_s = std::collections::BTreeMap::new();
+            #[allow(unused_mut)]
+            let mut tags = std::collections::BTreeMap::new();
+            #[allow(unused_mut)]
+            let mut warnings = std::collections::BTreeSet::new();
+            #[allow(unused_mut)]
+            let mut labels = std::collections::BTreeMap::new();
+            $(
+                let name: &str = $name;
+                #[allow(unused_mut)]
+                let mut po_tags = std::collections::BTreeSet::<String>::new();
+                #[allow(unused_mut)]
+                let mut warn = false;
+                #[allow(unused_mut)]
+                let mut doc: Vec<&str> = vec![];
+                $( $crate::build_trans!(@cand_attr po_tags, warn, doc, $($attr)*); )*
+                let po = $crate::build_trans_expr!(stateless, decls, $po);
+                if po_s.insert(name.to_string(), po).is_some() {
+                    $crate::prelude::bail!("found two candidates named `{}`", name)
+                }
+                if !po_tags.is_empty() {
+                    let _ = tags.insert(name.to_string(), po_tags);
+                }
+                if warn {
+                    let _ = warnings.insert(name.to_string());
+                }
+                if let Some(label) = $crate::meta::Label::of_doc(&doc) {
+                    let _ = labels.insert(name.to_string(), label);
+                }
+            )*
+            Ok($crate::trans::Sys::new(decls, init, trans, po_s)
+                .with_tags(tags)
+                .with_warnings(warnings)
+                .with_labels(labels))
+        };
+        build()
+    }};
+
     (
         decls {
             $($decls:tt)+
         }
         init: $init:tt
         trans: $trans:tt
-        po_s: $($name:expr => $po:tt)+
-    ) => {{
-        let decls = $crate::build_decls!($($decls)*);
-        let init = $crate::build_trans_expr!(stateless, decls, $init);
-        let trans = $crate::build_trans_expr!(stateful, decls, $trans);
-        let mut po_s = std::collections::BTreeMap::new();
-        $(
-            let name = $name;
-            let expr = $crate::build_trans_expr!(stateless, decls, $po);
-            if let Some(e) = po_s.insert(name.into(), expr) {
-                panic!("found two proof obligations named `{}`", name)
-            }
-        )+
-        $crate::Sys::new(decls, init, trans, po_s)
-    }};
+        po_s: $($name:literal => $po:tt)+
+    ) => {
+        $crate::build_trans! {
+            decls { $($decls)+ }
+            init { $init }
+            trans { $trans }
+            candidates { $($name => $po),+ }
+        }
+    };
 }
@@ -0,0 +1,399 @@
+//! Provenance metadata of verification artifacts.
+//!
+//! A [`Provenance`] records what is needed to reproduce a result: the mikino version, the solver
+//! and its version, the check configuration, the seed of generated inputs, and hashes of the
+//! inputs. It is embedded in [cache certificates](crate::check::cache), in
+//! [proof certificates](crate::check::certificate), in
+//! [outcomes](crate::check::Outcome::to_json) and in [batch reports](crate::check::batch::Report).
+//!
+//! Input hashes use FNV-1a on the text of the inputs, like the [cache](crate::check::cache), so
+//! that they are stable across runs, platforms, and compiler versions.
+//!
+//! A [`Label`] gives a human-readable title, and optionally a description, to a candidate or a
+//! check-sat. Labels come from doc comments and are carried to
+//! [outcomes](crate::check::Outcome::label), [batch reports](crate::check::batch::Report),
+//! [counterexamples](crate::check::cexs::Cex::render_titled) and
+//! [script steps](crate::script::Step::CheckRes).
+
+crate::prelude!();
+
+use check::{cache::fnv, trace::json_str, Config};
+use trans::Sys;
+
+/// Version of mikino.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Provenance of an artifact, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// Version of mikino, [`VERSION`] by default.
+    pub version: String,
+    /// Solver command, if any.
+    pub solver: Option<String>,
+    /// Solver version, as reported by the solver.
+    pub solver_version: Option<String>,
+    /// Configuration entries, ordered by key.
+    pub config: Map<String, String>,
+    /// Seed the input was generated with, if any.
+    pub seed: Option<u64>,
+    /// Hashes of the inputs, by input name.
+    pub inputs: Map<String, u64>,
+}
+impl Default for Provenance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Provenance {
+    /// Constructor, current version of mikino and nothing else.
+    pub fn new() -> Self {
+        Self {
+            version: VERSION.into(),
+            solver: None,
+            solver_version: None,
+            config: Map::new(),
+            seed: None,
+            inputs: Map::new(),
+        }
+    }
+
+    /// Provenance of a check configuration, solver included.
+    ///
+    /// The engines only appear when they are not the [standard
+    /// ones](crate::check::engine::Engines::standard).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{check, meta::Provenance, rsmt2::SmtConf};
+    /// let config = check::Config::new(SmtConf::z3("z3")).bmc_max(10).jobs(4);
+    /// let provenance = Provenance::of_config(&config)
+    ///     .input("model.mnk", "svars { x: int } ...")
+    ///     .seed(7);
+    /// assert_eq!(
+    ///     provenance.to_string(),
+    ///     format!(
+    ///         "\
+    /// mikino {}
+    /// solver z3
+    /// config bmc_max = 10
+    /// config jobs = 4
+    /// config solver_options = -in -smt2
+    /// seed 7
+    /// input model.mnk fnv b84506435dc60fca
+    /// ",
+    ///         mikino_api::meta::VERSION,
+    ///     ),
+    /// );
+    /// ```
+    pub fn of_config(config: &Config) -> Self {
+        let mut slf = Self::new().solver(&config.conf);
+        let mut entry = |key: &str, val: String| {
+            let _ = slf.config.insert(key.into(), val);
+        };
+        if let Some(bmc_max) = config.bmc_max {
+            entry("bmc_max", bmc_max.to_string())
+        }
+        if config.unrolling != check::Unrolling::Forward {
+            entry("unrolling", config.unrolling.to_string())
+        }
+        entry("jobs", config.jobs.to_string());
+        if let Some(cache) = config.cache.as_ref() {
+            entry("cache", cache.display().to_string())
+        }
+        if !config.preprocess.is_empty() {
+            entry(
+                "preprocess",
+                config.preprocess.names().collect::<Vec<_>>().join(", "),
+            )
+        }
+        let filter = &config.filter;
+        let globs = |globs: &[String]| globs.join(" ");
+        let tags = |tags: &Set<String>| tags.iter().cloned().collect::<Vec<_>>().join(" ");
+        if !filter.include_tags.is_empty() {
+            entry("filter.include_tags", tags(&filter.include_tags))
+        }
+        if !filter.exclude_tags.is_empty() {
+            entry("filter.exclude_tags", tags(&filter.exclude_tags))
+        }
+        if !filter.include_names.is_empty() {
+            entry("filter.include_names", globs(&filter.include_names))
+        }
+        if !filter.exclude_names.is_empty() {
+            entry("filter.exclude_names", globs(&filter.exclude_names))
+        }
+        if let Some(budget) = config.budget {
+            entry("budget_ms", budget.as_millis().to_string())
+        }
+        if let Some(timeout) = config.query_timeout {
+            entry("query_timeout_ms", timeout.as_millis().to_string())
+        }
+        if let Some(mb) = config.memory_limit {
+            entry("memory_limit_mb", mb.to_string())
+        }
+        if !config.engines.is_standard() {
+            entry(
+                "engines",
+                config.engines.names().collect::<Vec<_>>().join(", "),
+            )
+        }
+        if config.minimize_cexs {
+            entry("minimize_cexs", "true".into())
+        }
+        if config.check_init {
+            entry("check_init", "true".into())
+        }
+        if config.portfolio {
+            entry("portfolio", "true".into());
+            if !config.alt_confs.is_empty() {
+                entry(
+                    "alt_solvers",
+                    config
+                        .alt_confs
+                        .iter()
+                        .map(|conf| conf.get_cmd())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+            }
+        }
+        slf
+    }
+
+    /// Sets the solver command, and its options as configuration entry `solver_options`.
+    pub fn solver(mut self, conf: &SmtConf) -> Self {
+        self.solver = Some(conf.get_cmd().into());
+        if !conf.get_options().is_empty() {
+            let _ = self
+                .config
+                .insert("solver_options".into(), conf.get_options().join(" "));
+        }
+        self
+    }
+    /// Sets the solver version.
+    pub fn solver_version(mut self, version: impl Into<String>) -> Self {
+        self.solver_version = Some(version.into());
+        self
+    }
+    /// Sets the solver version by running the solver command with `--version`.
+    ///
+    /// Only keeps the first line of the output.
+    pub fn query_solver_version(self) -> Res<Self> {
+        let cmd = match self.solver.as_ref() {
+            Some(cmd) => cmd,
+            None => bail!("cannot query the version of an unknown solver"),
+        };
+        let output = std::process::Command::new(cmd)
+            .arg("--version")
+            .output()
+            .chain_err(|| format!("while querying the version of solver `{}`", cmd))?;
+        let version = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        if !output.status.success() || version.is_empty() {
+            bail!("solver `{}` did not report a version", cmd)
+        }
+        Ok(self.solver_version(version))
+    }
+    /// Sets the seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Adds the hash of an input.
+    pub fn input(mut self, name: impl Into<String>, content: &str) -> Self {
+        let _ = self.inputs.insert(name.into(), fnv(Some(content)));
+        self
+    }
+    /// Adds the hash of a system as input `system`.
+    pub fn system(self, sys: &Sys) -> Self {
+        self.input("system", &sys.to_ml_string())
+    }
+
+    /// JSON representation, hashes are hexadecimal strings.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::meta::Provenance;
+    /// let provenance = Provenance::new().solver_version("Z3 version 4.8.12").seed(3);
+    /// assert_eq!(
+    ///     provenance.to_json(),
+    ///     format!(
+    ///         r#"{{"mikino":"{}","solver":null,"solver_version":"Z3 version 4.8.12","config":{{}},"seed":3,"inputs":{{}}}}"#,
+    ///         mikino_api::meta::VERSION,
+    ///     ),
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let opt_str = |s: &Option<String>| match s {
+            Some(s) => json_str(s),
+            None => "null".into(),
+        };
+        let mut s = format!(
+            "{{\"mikino\":{},\"solver\":{},\"solver_version\":{},\"config\":{{",
+            json_str(&self.version),
+            opt_str(&self.solver),
+            opt_str(&self.solver_version)
+        );
+        for (idx, (key, val)) in self.config.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!("{}:{}", json_str(key), json_str(val)));
+        }
+        s.push_str("},\"seed\":");
+        match self.seed {
+            Some(seed) => s.push_str(&seed.to_string()),
+            None => s.push_str("null"),
+        }
+        s.push_str(",\"inputs\":{");
+        for (idx, (name, hash)) in self.inputs.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!("{}:\"{:016x}\"", json_str(name), hash));
+        }
+        s.push_str("}}");
+        s
+    }
+}
+impl fmt::Display for Provenance {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "mikino {}", self.version)?;
+        match (&self.solver, &self.solver_version) {
+            (Some(solver), Some(version)) => writeln!(fmt, "solver {} ({})", solver, version)?,
+            (Some(solver), None) => writeln!(fmt, "solver {}", solver)?,
+            (None, Some(version)) => writeln!(fmt, "solver ({})", version)?,
+            (None, None) => (),
+        }
+        for (key, val) in self.config.iter() {
+            writeln!(fmt, "config {} = {}", key, val)?;
+        }
+        if let Some(seed) = self.seed {
+            writeln!(fmt, "seed {}", seed)?;
+        }
+        for (name, hash) in self.inputs.iter() {
+            writeln!(fmt, "input {} fnv {:016x}", name, hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// Human-readable label of a candidate or a check-sat, see [`Self::of_doc`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// Title, a single line.
+    pub title: String,
+    /// Description, if any.
+    pub description: Option<String>,
+}
+impl Label {
+    /// Constructor, no description.
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+        }
+    }
+    /// Sets the description.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Label of the lines of a doc comment, `None` if there is no title.
+    ///
+    /// The first paragraph is the title, its lines are joined with spaces. The following
+    /// paragraphs are the description. Lines starting with `@` are
+    /// [display hints](crate::expr::display::Hint) and are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::meta::Label;
+    /// let label = Label::of_doc(&[
+    ///     "The counter never",
+    ///     "reaches `3`.",
+    ///     "",
+    ///     "Reset is not enough,",
+    ///     "",
+    ///     "it also needs to stop.",
+    ///     "@display: hex",
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(label.title, "The counter never reaches `3`.");
+    /// assert_eq!(
+    ///     label.description.as_deref(),
+    ///     Some("Reset is not enough,\n\nit also needs to stop."),
+    /// );
+    ///
+    /// assert_eq!(Label::of_doc(&["", "Title."]), Some(Label::new("Title.")));
+    /// assert_eq!(Label::of_doc(&["@unit: ms"]), None);
+    /// ```
+    pub fn of_doc<S: AsRef<str>>(lines: &[S]) -> Option<Self> {
+        let mut lines = lines
+            .iter()
+            .map(|line| line.as_ref().trim_end())
+            .filter(|line| !line.trim_start().starts_with('@'))
+            .skip_while(|line| line.trim().is_empty());
+        let mut title: Vec<&str> = vec![];
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+            title.push(line.trim());
+        }
+        if title.is_empty() {
+            return None;
+        }
+        let description = lines.collect::<Vec<_>>().join("\n");
+        let description = description.trim();
+        let label = Self::new(title.join(" "));
+        if description.is_empty() {
+            Some(label)
+        } else {
+            Some(label.description(description))
+        }
+    }
+
+    /// Lines of a doc comment yielding this label, inverse of [`Self::of_doc`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::meta::Label;
+    /// let label = Label::new("Title.").description("Some\n\ndescription.");
+    /// let doc = label.to_doc();
+    /// assert_eq!(doc, vec!["Title.", "", "Some", "", "description."]);
+    /// assert_eq!(Label::of_doc(&doc), Some(label));
+    /// ```
+    pub fn to_doc(&self) -> Vec<&str> {
+        let mut lines = vec![self.title.as_str()];
+        if let Some(description) = self.description.as_ref() {
+            lines.push("");
+            lines.extend(description.lines())
+        }
+        lines
+    }
+
+    /// JSON representation, `{"title":...,"description":...}`.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"title\":{},\"description\":{}}}",
+            json_str(&self.title),
+            self.description
+                .as_ref()
+                .map(|desc| json_str(desc))
+                .unwrap_or_else(|| "null".into())
+        )
+    }
+}
+impl fmt::Display for Label {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.title.fmt(fmt)
+    }
+}
@@ -3,10 +3,14 @@
 prelude!();
 
 use expr::{Cst, Expr, Op, PExpr, SExpr, SVar, Typ, Var};
+use num::ToPrimitive;
 use rsmt2::parse::IdentParser;
 use trans::Decls;
 
 pub mod kw;
+pub mod token;
+
+pub use token::{tokens, Token};
 
 #[cfg(test)]
 mod test;
@@ -32,6 +36,18 @@ impl Span {
     pub fn merge(self, other: Self) -> Self {
         (self.start, other.end).into()
     }
+    /// Smallest span containing two spans.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse::Span;
+    /// assert_eq!(Span::new(7, 9).union(Span::new(2, 4)), Span::new(2, 9));
+    /// assert_eq!(Span::new(2, 9).union(Span::new(3, 4)), Span::new(2, 9));
+    /// ```
+    pub fn union(self, other: Self) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
 
     /// Extracts the relevant line of the input, and the previous/next line if any.
     pub fn pretty_of(self, text: &str) -> (Option<String>, usize, usize, String, Option<String>) {
@@ -178,6 +194,9 @@ impl From<Spn<&str>> for Spn<String> {
     }
 }
 
+/// Names of the builtin types, which cannot name enumeration types.
+const BUILTIN_TYPS: [&str; 5] = ["int", "rat", "bool", "bv", "array"];
+
 /// Yields `true` if `ident` is a keyword.
 pub fn is_kw(ident: impl AsRef<str>) -> bool {
     kw::all.contains(ident.as_ref())
@@ -192,6 +211,173 @@ pub fn fail_if_kw(ident: impl AsRef<str>) -> Result<(), String> {
     }
 }
 
+/// A script item: a command or a definition `let <ident> = <expr>;`.
+type Item<'txt> = Either<
+    ast::script::Command<ast::Expr<'txt>, ast::Expr<'txt>>,
+    (Spn<&'txt str>, ast::Expr<'txt>),
+>;
+
+/// Replaces the identifiers of the definitions of a sequence of items in the commands that follow
+/// them, yields the commands.
+///
+/// A definition is replaced until the end of the sequence, or until a variable or meta-variable
+/// with the same name is declared.
+fn resolve_defs<'txt>(
+    items: Vec<PRes<Item<'txt>>>,
+) -> PRes<ast::script::Commands<ast::Expr<'txt>, ast::Expr<'txt>>> {
+    let mut defs: Map<&'txt str, ast::Expr<'txt>> = Map::new();
+    let mut cmds = Vec::with_capacity(items.len());
+    for item in items {
+        match item? {
+            Either::Left(mut cmd) => {
+                if !defs.is_empty() {
+                    cmd.replace_vars(&defs);
+                    match &cmd {
+                        ast::script::Command::Vars(vars) => {
+                            defs.retain(|ident, _| !vars.decls.contains(ident))
+                        }
+                        ast::script::Command::MLet(mlet) => {
+                            let _ = defs.remove(mlet.lhs.inner.as_str());
+                        }
+                        _ => (),
+                    }
+                }
+                cmds.push(cmd)
+            }
+            Either::Right((ident, mut def)) => {
+                let _ = def.replace_vars(&defs);
+                let _ = defs.insert(ident.inner, def);
+            }
+        }
+    }
+    Ok(cmds)
+}
+
+/// Maximal number of commands a loop can unroll to, see [`rules::repeat`].
+pub const MAX_UNROLLED_COMMANDS: usize = 100_000;
+
+/// Number of commands in some commands, including the ones nested in blocks and conditionals.
+fn command_count<E, ME>(cmds: &[ast::script::Command<E, ME>]) -> usize {
+    use ast::script::{Command, Query};
+    cmds.iter()
+        .map(|cmd| match cmd {
+            Command::Query(Query::Block(b)) => 1 + command_count(&b.content),
+            Command::Query(Query::Ite(ite)) => {
+                let otw = ite
+                    .otw
+                    .as_ref()
+                    .map_or(0, |otw| command_count(&otw.content));
+                1 + command_count(&ite.thn.content) + command_count(&ite.els.content) + otw
+            }
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Checks that `count` iterations of a loop over `body` unroll to at most
+/// [`MAX_UNROLLED_COMMANDS`] commands, yields `count`.
+fn check_unrolling<E, ME>(
+    count: u128,
+    body: &ast::script::Block<E, ME>,
+    span: Span,
+) -> PRes<usize> {
+    let size = 1 + command_count(&body.content) as u128;
+    match count.checked_mul(size) {
+        Some(total) if total <= MAX_UNROLLED_COMMANDS as u128 => Ok(count as usize),
+        _ => Err(PError::new(
+            format!(
+                "this loop unrolls to more than {} commands",
+                MAX_UNROLLED_COMMANDS
+            ),
+            span,
+        )),
+    }
+}
+
+/// An item of a declaration block: an enumeration with its variants, or some variables with their
+/// documentation and their type.
+type SVarsItem<'txt> = Either<
+    (Spn<&'txt str>, Vec<Spn<&'txt str>>),
+    (
+        (Spn<&'txt str>, Vec<&'txt str>),
+        Vec<(Spn<&'txt str>, Vec<&'txt str>)>,
+        Spn<Typ>,
+    ),
+>;
+
+/// Builds the declarations of some declaration blocks, each with the role of its variables.
+///
+/// Enumerations are declared before the variables, so that a variable can have the type of an
+/// enumeration declared in another block.
+fn svars_decls<'txt>(sections: Vec<(Option<trans::Role>, Vec<SVarsItem<'txt>>)>) -> PRes<Decls> {
+    let mut decls = trans::Decls::new();
+    let mut svars = vec![];
+    for (role, items) in sections {
+        for item in items {
+            let (name, variants) = match item {
+                Either::Left(enum_decl) => enum_decl,
+                Either::Right(svar) => {
+                    svars.push((role, svar));
+                    continue;
+                }
+            };
+            for ident in Some(&name).into_iter().chain(variants.iter()) {
+                if ident.inner.starts_with('|') {
+                    return Err(PError::new(
+                        "enumerations and their variants cannot have quoted names",
+                        ident.span,
+                    ));
+                }
+            }
+            if BUILTIN_TYPS.contains(&name.inner) {
+                return Err(PError::new(
+                    format!(
+                        "illegal enumeration name `{}`, it is a builtin type",
+                        name.inner
+                    ),
+                    name.span,
+                ));
+            }
+            for (idx, variant) in variants.iter().enumerate() {
+                if variants[..idx].iter().any(|v| v.inner == variant.inner) {
+                    return Err(PError::new(
+                        format!("variant `{}` is already declared", variant.inner),
+                        variant.span,
+                    ));
+                }
+            }
+            let variants = variants.iter().map(|v| v.inner.to_string()).collect();
+            if decls.declare_enum(name.inner, variants).is_some() {
+                return Err(PError::new(
+                    format!("enumeration `{}` is already declared", name.inner),
+                    name.span,
+                ));
+            }
+        }
+    }
+    for (role, (svar, svars, typ)) in svars {
+        decls
+            .check_typ(&typ)
+            .map_err(|e| PError::new(e.to_string(), typ.span))?;
+        for (svar, svar_doc) in Some(svar).into_iter().chain(svars) {
+            let prev = decls.register(svar.inner, typ.inner.clone());
+            if prev.is_some() {
+                return Err(PError::new(
+                    format!("variable `{}` is already declared", svar.inner),
+                    svar.span,
+                ));
+            }
+            let hint = expr::display::Hint::of_doc(&svar_doc)
+                .map_err(|e| PError::new(e.to_string(), svar.span))?;
+            let _ = decls.set_hint(svar.inner, hint);
+            if let Some(role) = role {
+                let _ = decls.set_role(svar.inner, role);
+            }
+        }
+    }
+    Ok(decls)
+}
+
 peg::parser! {
     /// PEG parsing rules, requires the `parser` feature.
     ///
@@ -311,6 +497,8 @@ peg::parser! {
             e:position!() {?
                 if is_kw(ident) {
                     Err("unexpected keyword")
+                } else if decls::SMT_RESERVED.contains(&ident) {
+                    Err("unexpected SMT-LIB reserved word")
                 } else {
                     Ok(Spn::new(ident, (s, e)))
                 }
@@ -494,11 +682,25 @@ peg::parser! {
         / expected!("decimal number")
 
         /// Parses constants.
+        ///
+        /// Bitvector constants are written `<value>bv<width>`, the value is taken modulo
+        /// `2^width`.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Cst, parse::rules::cst};
+        /// assert_eq!(*cst("42bv8").unwrap(), Cst::bv(8, 42));
+        /// assert_eq!(*cst("42").unwrap(), Cst::int(42));
+        /// ```
         pub rule cst() -> Spn<Cst>
         = quiet! {
             rat:decimal() {
                 rat.map(Cst::R)
             }
+            / s:position!() val:uint() "bv" width:bv_width() e:position!() {
+                Spn::new(Cst::bv(width, val.inner), (s, e))
+            }
             / int:uint() {
                 int.map(Cst::I)
             }
@@ -508,6 +710,40 @@ peg::parser! {
         }
         / expected!("int/rat/bool constant")
 
+        /// Parses an enumeration variant, `<type>::<variant>`.
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Cst, parse::rules::enum_cst};
+        /// assert_eq!(*enum_cst("State::Idle").unwrap(), Cst::variant("State", "Idle"));
+        /// assert!(enum_cst("State").is_err());
+        /// ```
+        pub rule enum_cst() -> Spn<Cst>
+        = quiet! {
+            s:position!() typ:ident() "::" variant:ident() e:position!() {
+                Spn::new(Cst::variant(typ.inner, variant.inner), (s, e))
+            }
+        }
+        / expected!("enumeration variant")
+
+        /// Parses an uninterpreted function application, `<ident>(<expr>, ...)`.
+        ///
+        /// There can be no whitespace between the function and the opening parenthesis.
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::hsmt_fun_app;
+        /// let ast = hsmt_fun_app("f(x, 'y + 1)").unwrap();
+        /// assert_eq!(ast.to_string(), "f(x, ('y + 1))");
+        /// assert!(hsmt_fun_app("f ()").is_err());
+        /// assert!(hsmt_fun_app("f()").is_err());
+        /// ```
+        pub rule hsmt_fun_app() -> ast::Expr<'input>
+        = quiet! {
+            ident:ident() "(" _ args:(hsmt_expr() ++ (_ "," _)) _ (",")? _ ")" {
+                ast::Expr::Fun { ident, args }
+            }
+        }
+        / expected!("function application")
+
         /// Parses variables.
         pub rule hsmt_var() -> ast::Expr<'input>
         = quiet! {
@@ -515,7 +751,7 @@ peg::parser! {
                 ps:position!() "'" pe:position!() {
                     Span::from((ps, pe))
                 }
-            )? var:ident() {
+            )? var:(hsmt_qual_ident() / ident()) {
                 ast::Expr::svar(var, prime)
             }
         }
@@ -539,16 +775,129 @@ peg::parser! {
             _ "else" _ "{"
             _ els:hsmt_expr()
             _ "}" {
-                let ite = Op::Ite;
                 let els = elseif.into_iter().rev().fold(
                     els,
-                    |els, (if_span, cnd, thn)| ast::Expr::app(Spn::new(ite, if_span), vec![cnd, thn, els]),
+                    |els, (if_span, cnd, thn)| ast::Expr::app(Spn::new(Op::Ite, if_span), vec![cnd, thn, els]),
                 );
                 ast::Expr::app(Spn::new(Op::Ite, (s,e)), vec![cnd, thn, els])
             }
         }
         / expected!("if-then-else")
 
+        /// Parses a quantified expression, `forall (<ident>: <type>, ...) { <expr> }` or the same
+        /// with `exists`.
+        ///
+        /// Quantifiers are only supported in scripts, see [`ast::Expr::to_sexpr`].
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::hsmt_quant;
+        /// let ast = hsmt_quant("forall (i: int, b: bool) { b ⇒ f(i) > 0 }").unwrap();
+        /// assert_eq!(ast.to_string(), "forall (i: int, b: bool) { (b ⇒ (f(i) > 0)) }");
+        /// let ast = hsmt_quant("exists (i: int,) { 2 * i = n }").unwrap();
+        /// assert_eq!(ast.to_string(), "exists (i: int) { ((2 * i) = n) }");
+        /// assert!(hsmt_quant("forall () { true }").is_err());
+        /// ```
+        pub rule hsmt_quant() -> ast::Expr<'input>
+        = quiet! {
+            s:position!() kind:$("forall" / "exists") e:position!()
+            _ "(" _ vars:(
+                var:ident() _ ":" _ typ:hsmt_typ() { (var, typ) }
+            ) ++ (_ "," _) _ (",")? _ ")"
+            _ "{" _ body:hsmt_expr() _ "}" {
+                ast::Expr::Quant {
+                    span: Span::new(s, e),
+                    forall: kind == "forall",
+                    vars,
+                    body: Box::new(body),
+                }
+            }
+        }
+        / expected!("quantified expression")
+
+        /// Parses an intrinsic function application, see [`expr::patterns`].
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::hsmt_intrinsic;
+        /// let ast = hsmt_intrinsic("in_range(x, 0, n + 1)").unwrap();
+        /// assert_eq!(ast.to_string(), "((0 ≤ x) ⋀ (x ≤ (n + 1)))");
+        /// let ast = hsmt_intrinsic("unchanged(x, y)").unwrap();
+        /// assert_eq!(ast.to_string(), "(('x = x) ⋀ ('y = y))");
+        ///
+        /// assert!(hsmt_intrinsic("in_range(x, 0)").is_err());
+        /// assert!(hsmt_intrinsic("monotonic('x)").is_err());
+        /// assert!(hsmt_intrinsic("exactly_two(a, b)").is_err());
+        /// ```
+        pub rule hsmt_intrinsic() -> ast::Expr<'input>
+        = quiet! {
+            s:position!()
+            name:$("at_most_one" / "exactly_one" / "in_range" / "monotonic" / "unchanged")
+            e:position!()
+            _ "(" _ args:(hsmt_expr() ** (_ "," _)) _ (",")? _ ")" {?
+                ast::Expr::intrinsic(Spn::new(name, (s, e)), args)
+            }
+        }
+        / expected!("intrinsic function application")
+
+        /// Parses an array operator application, `select(<array>, <index>)` or
+        /// `store(<array>, <index>, <value>)`.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::hsmt_array_app;
+        /// let ast = hsmt_array_app("store(mem, 'ptr, select(mem, ptr) + 1)").unwrap();
+        /// assert_eq!(ast.to_string(), "store(mem, 'ptr, (select(mem, ptr) + 1))");
+        /// assert!(hsmt_array_app("select(mem)").is_err());
+        /// ```
+        pub rule hsmt_array_app() -> ast::Expr<'input>
+        = quiet! {
+            s:position!() "select" e:position!()
+            _ "(" _ arr:hsmt_expr() _ "," _ idx:hsmt_expr() _ (",")? _ ")" {
+                ast::Expr::app(Spn::new(Op::Select, (s, e)), vec![arr, idx])
+            }
+            / s:position!() "store" e:position!()
+            _ "(" _ arr:hsmt_expr() _ "," _ idx:hsmt_expr() _ "," _ val:hsmt_expr() _ (",")? _ ")" {
+                ast::Expr::app(Spn::new(Op::Store, (s, e)), vec![arr, idx, val])
+            }
+        }
+        / expected!("array operator application")
+
+        /// Parses a frame condition, `unchanged { <ident>, ... }` or `frame except { <ident>, ... }`.
+        ///
+        /// In a transition relation, `unchanged { x, y }` is sugar for `'x = x ∧ 'y = y`, and
+        /// `frame except { z }` states that all the variables but `z` do not change.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::hsmt_frame;
+        /// let frame = hsmt_frame("unchanged { x, y, }").unwrap();
+        /// assert!(!frame.except);
+        /// assert_eq!(frame.to_string(), "unchanged { x, y }");
+        ///
+        /// let frame = hsmt_frame("frame except {}").unwrap();
+        /// assert!(frame.except);
+        /// assert!(frame.vars.is_empty());
+        ///
+        /// assert!(hsmt_frame("unchanged {}").is_err());
+        /// ```
+        pub rule hsmt_frame() -> ast::Frame<'input>
+        = quiet! {
+            s:position!() "unchanged" e:position!() _ "{" _
+            vars:(ident() ++ (_ "," _)) _ (",")? _ "}" {
+                ast::Frame { span: Span::new(s, e), except: false, vars }
+            }
+            / s:position!() "frame" _ "except" e:position!() _ "{" _
+            vars:(ident() ** (_ "," _)) _ (",")? _ "}" {
+                ast::Frame { span: Span::new(s, e), except: true, vars }
+            }
+        }
+        / expected!("frame condition")
+
         /// Parses polymorphic expressions.
         ///
         /// # Examples
@@ -622,6 +971,25 @@ peg::parser! {
                 ast::Expr::binapp(Spn::new(Op::Eq, (s, e)), lft, rgt)
             }
             --
+            lft:(@) _ s:position!() "|" e:position!() _ rgt:@ {
+                ast::Expr::binapp(Spn::new(Op::BvOr, (s, e)), lft, rgt)
+            }
+            --
+            lft:(@) _ s:position!() "^" e:position!() _ rgt:@ {
+                ast::Expr::binapp(Spn::new(Op::BvXor, (s, e)), lft, rgt)
+            }
+            --
+            lft:(@) _ s:position!() "&" e:position!() _ rgt:@ {
+                ast::Expr::binapp(Spn::new(Op::BvAnd, (s, e)), lft, rgt)
+            }
+            --
+            lft:(@) _ s:position!() "<<" e:position!() _ rgt:@ {
+                ast::Expr::binapp(Spn::new(Op::Shl, (s, e)), lft, rgt)
+            }
+            lft:(@) _ s:position!() ">>" e:position!() _ rgt:@ {
+                ast::Expr::binapp(Spn::new(Op::Shr, (s, e)), lft, rgt)
+            }
+            --
             lft:(@) _ s:position!() "+" e:position!() _ rgt:@ {
                 ast::Expr::binapp(Spn::new(Op::Add, (s, e)), lft, rgt)
             }
@@ -649,6 +1017,21 @@ peg::parser! {
             ite:hsmt_ite() {
                 ite
             }
+            quant:hsmt_quant() {
+                quant
+            }
+            app:hsmt_intrinsic() {
+                app
+            }
+            app:hsmt_array_app() {
+                app
+            }
+            cst:enum_cst() {
+                ast::Expr::cst(cst)
+            }
+            app:hsmt_fun_app() {
+                app
+            }
             var:hsmt_var() {
                 var
             }
@@ -670,14 +1053,46 @@ peg::parser! {
 
         /// Parses a type.
         ///
-        /// Can be `int`, `rat`, or `bool`.
+        /// Can be `int`, `rat`, `bool`, `bv<n>` for bitvectors of width `n > 0`,
+        /// `array<idx, elm>` for arrays from `idx` to `elm`, or the name of an enumeration type.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Typ, parse::rules::hsmt_typ};
+        /// assert_eq!(hsmt_typ("bv<32>").unwrap(), Typ::BitVec(32));
+        /// assert_eq!(
+        ///     hsmt_typ("array<int, array<int, bool>>").unwrap(),
+        ///     Typ::array(Typ::Int, Typ::array(Typ::Int, Typ::Bool)),
+        /// );
+        /// assert_eq!(hsmt_typ("bv < 8 >").unwrap(), Typ::BitVec(8));
+        /// assert!(hsmt_typ("bv<0>").is_err());
+        /// assert_eq!(hsmt_typ("State").unwrap(), Typ::enumeration("State"));
+        /// ```
         pub rule hsmt_typ() -> expr::Typ
         = quiet! {
-            "int" { expr::Typ::Int }
+            name:ident() {?
+                if BUILTIN_TYPS.contains(&name.inner) {
+                    Err("builtin type")
+                } else {
+                    Ok(expr::Typ::enumeration(name.inner))
+                }
+            }
+            / "int" { expr::Typ::Int }
             / "rat" { expr::Typ::Rat }
             / "bool" { expr::Typ::Bool }
+            / "bv" _ "<" _ width:bv_width() _ ">" { expr::Typ::BitVec(width) }
+            / "array" _ "<" _ idx:hsmt_typ() _ "," _ elm:hsmt_typ() _ ">" {
+                expr::Typ::array(idx, elm)
+            }
+        }
+        / expected!("a type (`int`, `rat`, `bool`, `bv<n>`, `array<idx, elm>` or an enumeration)")
+
+        /// Parses the width of a bitvector type or constant, strictly positive.
+        rule bv_width() -> u32
+        = width:uint() {?
+            width.to_u32().filter(|w| *w > 0).ok_or("illegal bitvector width")
         }
-        / expected!("a type (`int`, `rat` or `bool`")
 
         /// Parses some state variables.
         ///
@@ -686,6 +1101,10 @@ peg::parser! {
         /// together, separated by whitespace(s), before the `: <type>`. For instance, `v_1 v_2 v_3:
         /// int`.
         ///
+        /// The list can also declare enumeration types, `enum <ident> { <ident>, ... }`, usable as
+        /// the type of the variables of the list. Variant `V` of enumeration `E` is written
+        /// `E::V`, see [`enum_cst`].
+        ///
         /// # Examples
         ///
         /// ```rust
@@ -709,10 +1128,30 @@ peg::parser! {
         /// p q: bool,\
         ///     "
         /// );
+        ///
+        /// let decls = svars("enum State { Idle, Busy }, s: State, n: int").unwrap().unwrap();
+        /// assert_eq!(decls.enum_variants("State").unwrap(), &["Idle", "Busy"]);
+        /// assert!(svars("s: State").unwrap().is_err());
+        /// assert!(svars("enum State { Idle, Idle }").unwrap().is_err());
         /// ```
         pub rule svars() -> PRes<trans::Decls>
-        = svars:(
+        = items:svars_items() {
+            svars_decls(vec![(None, items)])
+        }
+
+        /// Parses the enumerations and variables of a declaration block, see [`svars`].
+        rule svars_items() -> Vec<SVarsItem<'input>>
+        = items:(
             quiet! {
+                _
+                outer_doc()
+                _
+                "enum" _ name:ident() _ "{" _ variants:(ident() ++ (_ "," _)) _ (",")? _ "}"
+                {
+                    Either::Left((name, variants))
+                }
+            }
+            / quiet! {
                 _
                 svar_doc:outer_doc()
                 _
@@ -721,26 +1160,86 @@ peg::parser! {
                     _
                     svar_doc:outer_doc()
                     _ id:ident() {
-                        id
+                        (id, svar_doc)
                     }
                 )*
-                _ ":" _ svars_typ:hsmt_typ()
+                _ ":" _ typ_s:position!() svars_typ:hsmt_typ() typ_e:position!()
                 {
-                    (svar, svars, svars_typ)
+                    Either::Right(((svar, svar_doc), svars, Spn::new(svars_typ, (typ_s, typ_e))))
                 }
             }
             / expected!(r#"list of "<ident>, <ident>, ... : <type>""#)
+        ) ++ (_ "," _) (",")? {
+            items
+        }
+
+        /// Parses some parameter declarations.
+        ///
+        /// A declaration is a comma-separated list of `<ident> : <type>`, optionally followed by
+        /// `where <expr>` constraining the parameter, with an optional trailing comma. Parameters
+        /// are rigid: they keep the same value in all the states of the system.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Typ, parse::rules::params};
+        /// let params = params("N: int where N > 0, b: bool,").unwrap();
+        /// assert_eq!(params.len(), 2);
+        /// let (name, typ, cnd) = &params[0];
+        /// assert_eq!(name.inner, "N");
+        /// assert_eq!(typ.inner, Typ::Int);
+        /// assert_eq!(cnd.as_ref().unwrap().to_string(), "(N > 0)");
+        /// let (name, typ, cnd) = &params[1];
+        /// assert_eq!(name.inner, "b");
+        /// assert_eq!(typ.inner, Typ::Bool);
+        /// assert!(cnd.is_none());
+        /// ```
+        pub rule params() -> Vec<(Spn<&'input str>, Spn<expr::Typ>, Option<ast::Expr<'input>>)>
+        = quiet! {
+            params:(
+                outer_doc() _ name:ident() _ ":" _ typ_s:position!() typ:hsmt_typ() typ_e:position!()
+                cnd:(_ "where" _ cnd:hsmt_expr() { cnd })? {
+                    (name, Spn::new(typ, (typ_s, typ_e)), cnd)
+                }
+            ) ++ (_ "," _) (",")? {
+                params
+            }
+        }
+        / expected!(r#"list of "<ident> : <type>" or "<ident> : <type> where <expr>""#)
+
+        /// Parses some uninterpreted function declarations.
+        ///
+        /// A declaration is a comma-separated list of `<ident> : (<type>, ...) -> <type>`, with an
+        /// optional trailing comma. Functions take at least one argument, constants are state
+        /// variables. Function `f` is applied as `f(<expr>, ...)`, see [`hsmt_fun_app`].
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Typ, parse::rules::funs};
+        /// let decls = funs("f: (int, int) -> int, p: (rat) -> bool,").unwrap().unwrap();
+        /// assert_eq!(decls.get_fun("f").unwrap().args(), &[Typ::Int, Typ::Int]);
+        /// assert_eq!(decls.get_fun("p").unwrap().out(), &Typ::Bool);
+        /// assert!(funs("f: (int) -> int, f: (bool) -> int").unwrap().is_err());
+        /// assert!(funs("c: () -> int").is_err());
+        /// ```
+        pub rule funs() -> PRes<trans::Decls>
+        = sigs:(
+            quiet! {
+                _ name:ident() _ ":" _ "(" _ args:(hsmt_typ() ++ (_ "," _)) _ (",")? _ ")"
+                _ "->" _ out:hsmt_typ() {
+                    (name, args, out)
+                }
+            }
+            / expected!(r#"list of "<ident> : (<type>, ...) -> <type>""#)
         ) ++ (_ "," _) (",")? {
             let mut decls = trans::Decls::new();
-            for (svar, svars, typ) in svars {
-                for svar in Some(svar).into_iter().chain(svars) {
-                    let prev = decls.register(svar.inner, typ);
-                    if prev.is_some() {
-                        return Err(PError::new(
-                            format!("variable `{}` is already declared", svar.inner),
-                            svar.span
-                        ));
-                    }
+            for (name, args, out) in sigs {
+                if decls.declare_fun(expr::Fun::new(name.inner, args, out)).is_some() {
+                    return Err(PError::new(
+                        format!("function `{}` is already declared", name.inner),
+                        name.span,
+                    ));
                 }
             }
             Ok(decls)
@@ -752,35 +1251,106 @@ peg::parser! {
         /// Names are double-quoted `"..."` strings and must all be distinct. Expressions have to
         /// be stateless (no `'` prime).
         ///
+        /// Each candidate can be preceded by `#[tag(<ident>, ...)]` attributes, see
+        /// [`check::filter`], and by a `#[warn]` attribute setting its
+        /// [severity](trans::Severity) to warning. Doc comments before and after the attributes
+        /// give the candidate a [label](crate::meta::Label).
+        ///
         /// # Examples
         ///
         /// ```rust
-        /// # use mikino_api::parse::rules::candidates;
+        /// # use mikino_api::{parse::rules::candidates, trans::Severity};
         /// let input = r#"
         ///     "some candidate": x ≥ 0,
         ///     "another one": x ≥ y + 2 ⋁ y ≥ -7,
         ///     "tautology": p ⋁ ¬p,"#;
         /// let mut candidates = candidates(input).unwrap().into_iter();
         ///
-        /// let (name, expr) = candidates.next().unwrap();
+        /// let (name, tags, severity, _, expr) = candidates.next().unwrap();
         /// assert_eq!(*name, "some candidate");
+        /// assert!(tags.is_empty());
+        /// assert_eq!(severity, Severity::Error);
         /// assert_eq!(expr.to_string(), "(x ≥ 0)");
         ///
-        /// let (name, expr) = candidates.next().unwrap();
+        /// let (name, _, _, _, expr) = candidates.next().unwrap();
         /// assert_eq!(*name, "another one");
         /// assert_eq!(expr.to_string(), "((x ≥ (y + 2)) ⋁ (y ≥ (-7)))");
         ///
-        /// let (name, expr) = candidates.next().unwrap();
+        /// let (name, _, _, _, expr) = candidates.next().unwrap();
         /// assert_eq!(*name, "tautology");
         /// assert_eq!(expr.to_string(), "(p ⋁ (¬p))");
         /// ```
-        pub rule candidates() -> Vec<(Spn<&'input str>, ast::Expr<'input>)>
+        ///
+        /// Candidates can be tagged with `#[tag(...)]` attributes, and marked `#[warn]`.
+        ///
+        /// ```rust
+        /// # use mikino_api::{parse::rules::candidates, trans::Severity};
+        /// let input = r#"
+        ///     #[tag(liveness, critical)]
+        ///     #[warn]
+        ///     #[tag(slow)]
+        ///     "some candidate": x ≥ 0,
+        ///     "another one": x ≥ y + 2,"#;
+        /// let mut candidates = candidates(input).unwrap().into_iter();
+        ///
+        /// let (name, tags, severity, _, _) = candidates.next().unwrap();
+        /// assert_eq!(*name, "some candidate");
+        /// assert_eq!(tags, vec!["liveness", "critical", "slow"]);
+        /// assert_eq!(severity, Severity::Warning);
+        ///
+        /// let (name, tags, severity, _, _) = candidates.next().unwrap();
+        /// assert_eq!(*name, "another one");
+        /// assert!(tags.is_empty());
+        /// assert_eq!(severity, Severity::Error);
+        /// ```
+        ///
+        /// Doc comments label candidates, see [`Label::of_doc`](crate::meta::Label::of_doc).
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::candidates;
+        /// let input = r#"
+        ///     /// Counter is positive.
+        ///     ///
+        ///     /// Follows from the initial predicate.
+        ///     "some candidate": x ≥ 0,
+        ///     #[warn]
+        ///     /// Counter stays small.
+        ///     "another one": x ≤ 7,"#;
+        /// let mut candidates = candidates(input).unwrap().into_iter();
+        ///
+        /// let (_, _, _, label, _) = candidates.next().unwrap();
+        /// let label = label.unwrap();
+        /// assert_eq!(label.title, "Counter is positive.");
+        /// assert_eq!(label.description.unwrap(), "Follows from the initial predicate.");
+        ///
+        /// let (_, _, _, label, _) = candidates.next().unwrap();
+        /// assert_eq!(label.unwrap().title, "Counter stays small.");
+        /// ```
+        pub rule candidates() -> Vec<(
+            Spn<&'input str>,
+            Vec<&'input str>,
+            trans::Severity,
+            Option<crate::meta::Label>,
+            ast::Expr<'input>,
+        )>
         = quiet! {
             cands:(
+                doc:outer_doc() _ attrs:(cand_attr() ** _) doc_after:outer_doc()
                 _ s:position!() name:dbl_quoted() e:position!() _ ":" _
                 expr:hsmt_expr()
                 {
-                    (Spn::new(name, (s, e)), expr)
+                    let mut tags = vec![];
+                    let mut severity = trans::Severity::Error;
+                    for attr in attrs {
+                        match attr {
+                            Some(attr_tags) => tags.extend(attr_tags),
+                            None => severity = trans::Severity::Warning,
+                        }
+                    }
+                    let mut doc = doc;
+                    doc.extend(doc_after);
+                    let label = crate::meta::Label::of_doc(&doc);
+                    (Spn::new(name, (s, e)), tags, severity, label, expr)
                 }
             ) ++ (_ "," _) (",")? {
                 cands
@@ -788,6 +1358,117 @@ peg::parser! {
         }
         / expected!(r#"list of "<name> : <expr>" where <name> is a double-quoted string"#)
 
+        /// Parses a candidate attribute, `#[tag(<ident>, ...)]` or `#[warn]` (yields `None`).
+        rule cand_attr() -> Option<Vec<&'input str>>
+        =
+            "#[" _ "tag" _ "(" _ tags:(tag:ident() { tag.inner }) ++ (_ "," _) _ (",")? _ ")" _ "]"
+            { Some(tags) }
+            / "#[" _ "warn" _ "]" { None }
+
+        /// Parses a liveness property, `eventually <expr>` or `infinitely_often <expr>`, see
+        /// [`check::liveness::Property`].
+        ///
+        /// Yields the keyword and the expression.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::liveness_property;
+        /// let (keyword, expr) = liveness_property("eventually x > 3").unwrap();
+        /// assert_eq!(*keyword, "eventually");
+        /// assert_eq!(expr.to_string(), "(x > 3)");
+        /// let (keyword, expr) = liveness_property("infinitely_often\n    reset").unwrap();
+        /// assert_eq!(*keyword, "infinitely_often");
+        /// assert_eq!(expr.to_string(), "reset");
+        ///
+        /// assert!(liveness_property("eventuallyx > 3").is_err());
+        /// assert!(liveness_property("always x > 3").is_err());
+        /// ```
+        pub rule liveness_property() -> (Spn<&'input str>, ast::Expr<'input>)
+        = quiet! {
+            s:position!() keyword:$("eventually" / "infinitely_often") e:position!()
+            !['a'..='z' | 'A'..='Z' | '_' | '0'..='9'] _ expr:hsmt_expr() {
+                (Spn::new(keyword, (s, e)), expr)
+            }
+        }
+        / expected!("`eventually <expr>` or `infinitely_often <expr>`")
+
+        /// Parses some LTL properties.
+        ///
+        /// Accepts a list of name/formula pairs of the form `<name> : <formula>`, separated by
+        /// commas. Names are double-quoted `"..."` strings, see [`check::ltl`] for the syntax of
+        /// formulas. Formulas are only delimited here, they are parsed once the variables of the
+        /// system are known.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::ltl;
+        /// let input = r#""response": G ({req} ⇒ F {ack ⋀ f(x, y)}), "fair": G F {req}"#;
+        /// let props = ltl(input).unwrap();
+        /// let props: Vec<_> = props
+        ///     .into_iter()
+        ///     .map(|(name, formula)| (*name, *formula))
+        ///     .collect();
+        /// assert_eq!(
+        ///     props,
+        ///     vec![("response", "G ({req} ⇒ F {ack ⋀ f(x, y)})"), ("fair", "G F {req}")],
+        /// );
+        /// ```
+        pub rule ltl() -> Vec<(Spn<&'input str>, Spn<&'input str>)>
+        =
+            props:(
+                s:position!() name:dbl_quoted() e:position!() _ ":" _
+                f_s:position!() formula:$(quiet! { ltl_chunk()+ } / expected!("LTL formula")) {
+                    let formula = formula.trim_end();
+                    let formula = Spn::new(formula, (f_s, f_s + formula.len()));
+                    (Spn::new(name, (s, e)), formula)
+                }
+            ) ++ ("," _) (",")? {
+                props
+            }
+        /// Part of an LTL formula: a nested atom or parenthesized formula, or a character that
+        /// does not end the formula.
+        rule ltl_chunk()
+        = "{" ltl_nested()* "}" / "(" ltl_nested()* ")" / [^ ',' | '{' | '}' | '(' | ')']
+        /// Part of a nested atom or parenthesized formula.
+        rule ltl_nested()
+        = "{" ltl_nested()* "}" / "(" ltl_nested()* ")" / [^ '{' | '}' | '(' | ')']
+
+        /// Parses some liveness properties.
+        ///
+        /// Accepts a list of name/property pairs of the form `<name> : <property>`, separated by
+        /// commas. Names are double-quoted `"..."` strings, properties are parsed by
+        /// [`liveness_property`].
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::liveness;
+        /// let input = r#"
+        ///     "cnt grows": eventually cnt > 3,
+        ///     "resets": infinitely_often reset,"#;
+        /// let mut props = liveness(input.trim()).unwrap().into_iter();
+        ///
+        /// let (name, keyword, expr) = props.next().unwrap();
+        /// assert_eq!((*name, *keyword), ("cnt grows", "eventually"));
+        /// assert_eq!(expr.to_string(), "(cnt > 3)");
+        ///
+        /// let (name, keyword, expr) = props.next().unwrap();
+        /// assert_eq!((*name, *keyword), ("resets", "infinitely_often"));
+        /// assert_eq!(expr.to_string(), "reset");
+        /// assert!(props.next().is_none());
+        /// ```
+        pub rule liveness() -> Vec<LivenessProp<'input>>
+        =
+            props:(
+                s:position!() name:dbl_quoted() e:position!() _ ":" _ prop:liveness_property() {
+                    (Spn::new(name, (s, e)), prop.0, prop.1)
+                }
+            ) ++ (_ "," _) (",")? {
+                props
+            }
+
         /// Parses a full instance.
         ///
         /// Same documentation as [the `trans` function][crate::parse::trans].
@@ -795,42 +1476,166 @@ peg::parser! {
         =
         sys_doc:inner_doc()
 
-        vars_doc:outer_doc()
-        _ "svars" _ "{" _ decls:svars() _ "}"
-        init_doc:outer_doc()
-        _ init_s:position!() "init" init_e:position!() _ "{" _ hsmt_init:(
-            quiet! {
-                init:(hsmt_expr()) ++ (_ "," _) (",")? { init }
-            }
-            / expected!("comma-separated list of stateless expressions")
-         ) _  "}"
-        trans_doc:outer_doc()
-        _ trans_s:position!() "trans" trans_e:position!() _ "{" _ hsmt_trans:(
-            quiet! {
-                trans:(hsmt_expr()) ++ (_ "," _) (",")? { trans }
+        subsystems:(
+            _ quiet! { "subsystems" } _ "{" _ subsystems:(hsmt_subsystem() ** _) _ "}" {
+                subsystems
             }
-            / expected!("comma-separated list of stateful expressions")
-         ) _ "}"
+        )?
+        sys:hsmt_sys_def()
         candidates_doc:outer_doc()
         _ "candidates" _ "{" _ candidates:candidates() _ "}"
+        liveness:(_ "liveness" _ "{" _ liveness:liveness() _ "}" { liveness })?
+        ltl:(_ "ltl" _ "{" _ ltl:ltl() _ "}" { ltl })?
         _ {
-            let decls = decls?;
-            let init = ast::Expr::app(Spn::new(Op::And, (init_s, init_e)), hsmt_init).to_expr(&decls)?;
-            let trans = ast::Expr::app(Spn::new(Op::And, (trans_s, trans_e)), hsmt_trans).to_sexpr(&decls)?;
-
-            let mut pos = Map::new();
-
-            for (name, expr) in candidates {
-                let candidate = expr.to_expr(&decls).map_err(|e| e.chain_err(|| format!("in candidate `{}`", name.inner)))?;
-                let prev =  pos.insert(name.inner.to_string(), candidate);
+            let mut defined = Map::new();
+            for subsystem in subsystems.into_iter().flatten() {
+                let (name, sub) = subsystem?;
+                let sub = sub.build(&defined, vec![])?;
+                let prev = defined.insert(name.inner, sub);
                 if prev.is_some() {
-                    return Err(PError::new("a candidate with this name is already defined", name.span))
+                    return Err(PError::new("a subsystem with this name is already defined", name.span))
                 }
             }
+            let sys = sys?.build(&defined, candidates)?;
+            let sys = with_liveness(sys, liveness.unwrap_or_default())?;
+            with_ltl(sys, ltl.unwrap_or_default())
+        }
 
-            Ok(trans::Sys::new(decls, init, trans, pos))
+        /// Parses a subsystem definition: a name and a system without candidates.
+        rule hsmt_subsystem() -> PRes<(Spn<&'input str>, SysDef<'input>)>
+        =
+        outer_doc() _ name:ident() _ "{" sys:hsmt_sys_def() _ "}" {
+            Ok((name, sys?))
         }
 
+        /// Parses the instances of a system, `<ns>: <subsystem>(<port> = <var>, ...)`.
+        ///
+        /// A port is a variable of the subsystem, connected to a variable of the parent system. The
+        /// other variables of the subsystem are declared as `<ns>.<var>`.
+        rule hsmt_instances() -> Vec<Instance<'input>>
+        = quiet! {
+            instances:(
+                outer_doc() _ ns:ident() _ ":" _ sub:ident() _ "(" _ ports:(
+                    port:ident() _ "=" _ var:(hsmt_qual_ident() / ident()) {
+                        (port, var)
+                    }
+                ) ** (_ "," _) _ (",")? _ ")" {
+                    (ns, sub, ports)
+                }
+            ) ++ (_ "," _) (",")? {
+                instances
+            }
+        }
+        / expected!("list of \"<ident> : <subsystem>(<port> = <var>, ...)\"")
+
+        /// Parses an optionally labeled expression, `"<label>": <expr>`, used in `init`,
+        /// `assumptions` and `trans`.
+        rule labeled_expr() -> (Option<Spn<&'input str>>, ast::Expr<'input>)
+        =
+            label:(
+                s:position!() label:dbl_quoted() e:position!() _ ":" _ { Spn::new(label, (s, e)) }
+            )? expr:hsmt_expr() {
+                (label, expr)
+            }
+
+        /// Parses a qualified identifier, `<ns>.<ident>`, used to refer to instance variables.
+        ///
+        /// Namespaces can be nested, as in `<ns>.<ns>.<ident>`.
+        rule hsmt_qual_ident() -> Spn<&'input str>
+        = s:position!() id:$(ident() ("." ident())+) e:position!() {
+            Spn::new(id, (s, e))
+        }
+
+        /// Parses the keyword of a declaration block, yields the role of its variables.
+        rule svars_role() -> Option<trans::Role>
+        = quiet! {
+            "svars" { None }
+            / "inputs" { Some(trans::Role::Input) }
+            / "outputs" { Some(trans::Role::Output) }
+            / "state" { Some(trans::Role::State) }
+        }
+
+        /// Parses a system without its candidates.
+        rule hsmt_sys_def() -> PRes<SysDef<'input>>
+        =
+        first:(
+            outer_doc() _ role:(svars_role() / expected!(r#""svars""#))
+            _ "{" _ items:svars_items() _ "}" {
+                (role, items)
+            }
+        )
+        sections:(
+            _ outer_doc() _ role:svars_role() _ "{" _ items:svars_items() _ "}" {
+                (role, items)
+            }
+        )*
+        params:(
+            _ quiet! { "params" } _ "{" _ params:params() _ "}" { params }
+        )?
+        funs:(
+            // Quiet so that a missing `init` is not reported as a missing `funs`.
+            _ s:position!() quiet! { "funs" } e:position!() _ "{" _ funs:funs() _ "}" {
+                (Span::new(s, e), funs)
+            }
+        )?
+        instances:(
+            _ quiet! { "instances" } _ "{" _ instances:hsmt_instances() _ "}" { instances }
+        )?
+        init_doc:outer_doc()
+        _ init_s:position!() "init" init_e:position!() _ "{" _ init:(
+            quiet! {
+                init:(labeled_expr()) ++ (_ "," _) (",")? { init }
+            }
+            / expected!("comma-separated list of stateless expressions")
+         ) _  "}"
+        assumptions:(
+            _ s:position!() quiet! { "assumptions" } e:position!() _ "{" _ assumptions:(
+                quiet! {
+                    assumptions:(labeled_expr()) ++ (_ "," _) (",")? { assumptions }
+                }
+                / expected!("comma-separated list of stateless expressions")
+            ) _ "}" {
+                (Span::new(s, e), assumptions)
+            }
+        )?
+        trans_doc:outer_doc()
+        _ trans_s:position!() "trans" trans_e:position!() _ "{" _ trans:(
+            quiet! {
+                trans:(
+                    frame:hsmt_frame() { Either::Right(frame) }
+                    / expr:labeled_expr() { Either::Left(expr) }
+                ) ++ (_ "," _) (",")? { trans }
+            }
+            / expected!("comma-separated list of stateful expressions")
+         ) _ "}" {
+            let mut sections = sections;
+            sections.insert(0, first);
+            let mut decls = svars_decls(sections)?;
+            let params = params.unwrap_or_default();
+            for (name, typ, _) in params.iter() {
+                decls.check_typ(&typ.inner).map_err(|e| PError::new(e.to_string(), typ.span))?;
+                let prev = decls.register(name.inner, typ.inner.clone());
+                if prev.is_some() {
+                    return Err(PError::new(
+                        format!("variable `{}` is already declared", name.inner),
+                        name.span,
+                    ));
+                }
+            }
+            if let Some((span, funs)) = funs {
+                let funs = funs?;
+                decls.check_funs(&funs).map_err(|e| PError::new(e.to_string(), span))?;
+                let _ = decls.merge(&funs);
+            }
+            Ok(SysDef {
+                decls,
+                params: params.into_iter().map(|(name, _, cnd)| (name, cnd)).collect(),
+                instances: instances.unwrap_or_default(),
+                init: (Span::new(init_s, init_e), init),
+                assumptions,
+                trans: (Span::new(trans_s, trans_e), trans),
+            })
+        }
 
 
 
@@ -838,16 +1643,42 @@ peg::parser! {
 
 
 
-        /// A **non-empty** sequence of commands.
+
+        /// A **non-empty** sequence of commands and definitions, see [`def`].
         pub rule commands() -> PRes<ast::script::Commands<ast::Expr<'input>, ast::Expr<'input>>>
         =
-            head:command() tail:(_ cmd:command() { cmd })* {
-                let mut cmds = Vec::with_capacity(tail.len());
-                cmds.push(head?);
-                for cmd in tail {
-                    cmds.push(cmd?);
-                }
-                Ok(cmds)
+            head:item() tail:(_ item:item() { item })* {
+                let mut items = Vec::with_capacity(tail.len() + 1);
+                items.push(head);
+                items.extend(tail);
+                resolve_defs(items)
+            }
+
+        /// A command or a definition.
+        rule item() -> PRes<Item<'input>>
+        =
+            cmd:command() { cmd.map(Either::Left) }
+            /
+            outer_doc() _ def:def() { Ok(Either::Right(def)) }
+
+        /// A definition `let <ident> = <expr>;`, where `<expr>` is an expression.
+        ///
+        /// Definitions are not commands: in the commands that follow a definition in its block,
+        /// occurrences of `<ident>` are replaced by `<expr>` in assertions and evaluation requests.
+        /// This lasts until the end of the block, or until a variable or meta-variable named
+        /// `<ident>` is declared.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::def;
+        /// let (ident, expr) = def("let inv = cnt ≥ 0 ∧ cnt ≤ 42;").unwrap();
+        /// assert_eq!((*ident, expr.to_string()), ("inv", "((cnt ≥ 0) ⋀ (cnt ≤ 42))".into()));
+        /// ```
+        pub rule def() -> (Spn<&'input str>, ast::Expr<'input>)
+        =
+            "let" _ ident:ident() _ "=" _ expr:hsmt_expr() _ ";" {
+                (ident, expr)
             }
 
         /// Command parser.
@@ -864,14 +1695,34 @@ peg::parser! {
                 /
                 cmd:get_model() { Ok(cmd?.into()) }
                 /
+                cmd:get_mus() { Ok(cmd?.into()) }
+                /
+                cmd:unsat_core() { Ok(cmd?.into()) }
+                /
                 cmd:get_values() { Ok(cmd?.into()) }
                 /
                 cmd:echo() { Ok(cmd?.into()) }
                 /
                 cmd:reset() { Ok(cmd?.into()) }
                 /
+                cmd:snapshot() { Ok(cmd?.into()) }
+                /
+                cmd:restore() { Ok(cmd?.into()) }
+                /
+                cmd:repeat() { Ok(cmd?.into()) }
+                /
+                cmd:push() { Ok(cmd?.into()) }
+                /
+                cmd:pop() { Ok(cmd?.into()) }
+                /
+                cmd:optimize() { Ok(cmd?.into()) }
+                /
                 query:query() { Ok(query?.into()) }
             ) {
+                let mut res: PRes<ast::script::Command<_, _>> = res;
+                if let (Ok(cmd), Some(label)) = (res.as_mut(), crate::meta::Label::of_doc(&odoc)) {
+                    cmd.set_label(label)
+                }
                 res
             }
 
@@ -896,10 +1747,74 @@ peg::parser! {
         /// Block parser.
         pub rule block() -> PRes<ast::script::Block<ast::Expr<'input>, ast::Expr<'input>>>
         =
-            "{" commands:(_ cmd:command() { cmd })* _ "}" {
-                let mut content = Vec::with_capacity(commands.len());
-                for command in commands {
-                    content.push(command?)
+            "{" items:(_ item:item() { item })* _ "}" {
+                Ok(ast::script::Block::new(resolve_defs(items)?))
+            }
+
+        /// A bounded loop, unrolled into a block of blocks, one for each iteration.
+        ///
+        /// `repeat n { ... }` runs its body `n` times. `for i in lo..hi { ... }` runs its body for
+        /// `i` from `lo` to `hi` excluded, or included with `lo..=hi`. In the body, `i` is replaced
+        /// by its value, an integer constant, in the expressions of assertions and evaluation
+        /// requests; it shadows any variable named `i`.
+        ///
+        /// Fails if the loop unrolls to more than [`MAX_UNROLLED_COMMANDS`] commands, counting the
+        /// commands of the body of each iteration.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{ast::script::{Command, Query}, parse::rules::repeat};
+        /// let unrolled = repeat("for i in 0..3 { assert { x > i } }").unwrap().unwrap();
+        /// let asserted: Vec<_> = unrolled
+        ///     .content
+        ///     .iter()
+        ///     .map(|iteration| match iteration {
+        ///         Command::Query(Query::Block(b)) => match &b.content[0] {
+        ///             Command::Assert(a) => a.exprs[0].to_string(),
+        ///             cmd => panic!("expected an assertion, got {:?}", cmd),
+        ///         },
+        ///         cmd => panic!("expected a block, got {:?}", cmd),
+        ///     })
+        ///     .collect();
+        /// assert_eq!(asserted, vec!["(x > 0)", "(x > 1)", "(x > 2)"]);
+        /// assert_eq!(repeat("repeat 2 { check_sat!() }").unwrap().unwrap().content.len(), 2);
+        /// assert_eq!(repeat("for i in -1..=1 { }").unwrap().unwrap().content.len(), 3);
+        /// assert_eq!(repeat("for i in 1..1 { }").unwrap().unwrap().content.len(), 0);
+        ///
+        /// let err = repeat("for i in 0..=9223372036854775807 { }").unwrap().unwrap_err();
+        /// assert_eq!(err.to_string(), "[0, 32] this loop unrolls to more than 100000 commands");
+        /// assert!(repeat("for i in 0..9223372036854775807 { }").unwrap().is_err());
+        /// let err = repeat("repeat 100000000000 { assert { true } }").unwrap().unwrap_err();
+        /// assert_eq!((err.span.start, err.span.end), (7, 19));
+        /// ```
+        pub rule repeat() -> PRes<ast::script::Block<ast::Expr<'input>, ast::Expr<'input>>>
+        =
+            "repeat" _ count:number() _ body:block() {
+                let n = count.inner.parse::<usize>().map_err(|e| {
+                    PError::new(format!("illegal iteration count: {}", e), count.span)
+                })?;
+                let body = body?;
+                let n = check_unrolling(n as u128, &body, count.span)?;
+                Ok(ast::script::Block::new((0..n).map(|_| body.clone().into()).collect()))
+            }
+            /
+            start:position!() "for" _ var:ident() _ "in"
+            _ lo:isize() _ ".." incl:"="? _ hi:isize() end:position!()
+            _ body:block() {
+                let body = body?;
+                let (lo, hi) = (lo as i128, hi as i128);
+                let count = if incl.is_some() { hi + 1 - lo } else { hi - lo };
+                let count = check_unrolling(count.max(0) as u128, &body, Span::new(start, end))?;
+                let body: ast::script::Command<_, _> = body.into();
+                let mut content = Vec::with_capacity(count);
+                let mut map = Map::new();
+                for val in (0..count).map(|offset| lo + offset as i128) {
+                    let val = ast::Expr::cst(Spn::new(expr::Cst::I(val.into()), var.span));
+                    let _ = map.insert(var.inner, val);
+                    let mut iteration = body.clone();
+                    iteration.replace_vars(&map);
+                    content.push(iteration)
                 }
                 Ok(ast::script::Block::new(content))
             }
@@ -908,17 +1823,40 @@ peg::parser! {
         pub rule check_sat() -> PRes<ast::script::CheckSat>
         =
             start:position!() "check_sat" "!"? end:position!()
-            _ "("
-                // (_ key:$(ident()) _ ":" _ ""
-            _ ")" {
-                Ok(ast::script::CheckSat::new((start, end), None, None))
+            _ "(" _ assuming:check_sat_assuming()? _ ")" {
+                Ok(ast::script::CheckSat::new((start, end), assuming, None))
             }
             /
             start:position!() "check_sat" "!"? end:position!()
-            _ "{"
-                // (_ key:$(ident()) _ ":" _ ""
-            _ "}" {
-                Ok(ast::script::CheckSat::new((start, end), None, None))
+            _ "{" _ assuming:check_sat_assuming()? _ "}" {
+                Ok(ast::script::CheckSat::new((start, end), assuming, None))
+            }
+
+        /// Literals a check sat assumes, `assuming: [<lit>, ...]`.
+        ///
+        /// Literals are boolean identifiers, possibly negated.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::check_sat;
+        /// let check = check_sat("check_sat!(assuming: [b1, ¬b2, not b3])").unwrap().unwrap();
+        /// let assuming: Vec<_> = check
+        ///     .assuming
+        ///     .iter()
+        ///     .map(|(lit, pol)| (lit.inner.as_str(), *pol))
+        ///     .collect();
+        /// assert_eq!(assuming, vec![("b1", true), ("b2", false), ("b3", false)]);
+        /// ```
+        pub rule check_sat_assuming() -> Vec<(Spn<String>, bool)>
+        =
+            "assuming" _ ":" _ "[" _ lits:(
+                ("¬" / "!" / "not" !['a'..='z' | 'A'..='Z' | '_' | '0'..='9']) _ lit:ident() {
+                    (lit.map(String::from), false)
+                }
+                / lit:ident() { (lit.map(String::from), true) }
+            ) ** (_ "," _) _ ","? _ "]" {
+                lits
             }
 
         /// Ite.
@@ -1015,6 +1953,10 @@ peg::parser! {
             _ "(" _ decls:svars() _ ")" {
                 Ok(ast::script::Vars::new((start, end), decls?))
             }
+            / start:position!() "funs" end:position!()
+            _ "{" _ decls:funs() _ "}" {
+                Ok(ast::script::Vars::new((start, end), decls?))
+            }
 
         /// A meta-binding.
         pub rule mlet() -> PRes<ast::script::MLet>
@@ -1027,13 +1969,27 @@ peg::parser! {
         pub rule assert() -> PRes<ast::script::Assert<ast::Expr<'input>>>
         =
             start:position!() "assert" end:position!()
-            _ "{" exprs:(_ expr:hsmt_expr() _ { expr })++"," _ ","? _ "}" {
-                Ok(ast::script::Assert::new((start, end), exprs))
+            _ "{" exprs:(_ expr:assert_expr() _ { expr })++"," _ ","? _ "}" {
+                Ok(ast::script::Assert::new_named((start, end), exprs))
             }
             /
             start:position!() "assert" end:position!()
-            _ "(" exprs:(_ expr:hsmt_expr() _ { expr })++"," _ ","? _ ")" {
-                Ok(ast::script::Assert::new((start, end), exprs))
+            _ "(" exprs:(_ expr:assert_expr() _ { expr })++"," _ ","? _ ")" {
+                Ok(ast::script::Assert::new_named((start, end), exprs))
+            }
+
+        /// An optionally named expression in an assert, `<name>: <expr>`.
+        ///
+        /// The name is an identifier or a double-quoted label.
+        rule assert_expr() -> (Option<Spn<String>>, ast::Expr<'input>)
+        =
+            name:(
+                name:ident() _ ":" !":" _ { name.map(String::from) }
+                / s:position!() label:dbl_quoted() e:position!() _ ":" _ {
+                    Spn::new(label.to_string(), (s, e))
+                }
+            )? expr:hsmt_expr() {
+                (name, expr)
             }
 
         /// An assert.
@@ -1047,6 +2003,28 @@ peg::parser! {
                 Ok(ast::script::GetModel::new((start, end), token))
             }
 
+        /// A minimal unsatisfiable subset request.
+        pub rule get_mus() -> PRes<ast::script::GetMus>
+        =
+            start:position!() token:$("get_mus") "!"? end:position!() _ "(" _ ")" {
+                Ok(ast::script::GetMus::new((start, end), token))
+            }
+            /
+            start:position!() token:$("get_mus") "!"? end:position!() _ "{" _ "}" {
+                Ok(ast::script::GetMus::new((start, end), token))
+            }
+
+        /// An unsat core request.
+        pub rule unsat_core() -> PRes<ast::script::UnsatCore>
+        =
+            start:position!() token:$("unsat_core") "!"? end:position!() _ "(" _ ")" {
+                Ok(ast::script::UnsatCore::new((start, end), token))
+            }
+            /
+            start:position!() token:$("unsat_core") "!"? end:position!() _ "{" _ "}" {
+                Ok(ast::script::UnsatCore::new((start, end), token))
+            }
+
         /// An assert.
         pub rule get_values() -> PRes<ast::script::GetValues<ast::Expr<'input>>>
         =
@@ -1073,6 +2051,97 @@ peg::parser! {
                 Ok(ast::script::Reset::new((start, end)))
             }
 
+        /// A snapshot.
+        pub rule snapshot() -> PRes<ast::script::Snapshot>
+        =
+            start:position!() "snapshot" "!"? end:position!() _ "(" _ name:ident() _ ")" {
+                Ok(ast::script::Snapshot::new((start, end), name.map(String::from)))
+            }
+            /
+            start:position!() "snapshot" "!"? end:position!() _ "{" _ name:ident() _ "}" {
+                Ok(ast::script::Snapshot::new((start, end), name.map(String::from)))
+            }
+
+        /// A restore.
+        pub rule restore() -> PRes<ast::script::Restore>
+        =
+            start:position!() "restore" "!"? end:position!() _ "(" _ name:ident() _ ")" {
+                Ok(ast::script::Restore::new((start, end), name.map(String::from)))
+            }
+            /
+            start:position!() "restore" "!"? end:position!() _ "{" _ name:ident() _ "}" {
+                Ok(ast::script::Restore::new((start, end), name.map(String::from)))
+            }
+
+        /// A number of levels for push and pop, `1` if omitted.
+        rule levels() -> usize
+        =
+            count:(
+                n:number() {? n.inner.parse().or(Err("level count")) }
+            )? {
+                count.unwrap_or(1)
+            }
+
+        /// A push.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::push;
+        /// let push = |s| push(s).unwrap().unwrap().count;
+        /// assert_eq!(push("push!()"), 1);
+        /// assert_eq!(push("push!(3)"), 3);
+        /// assert_eq!(push("push { 2 }"), 2);
+        /// ```
+        pub rule push() -> PRes<ast::script::Push>
+        =
+            start:position!() "push" "!"? end:position!() _ "(" _ count:levels() _ ")" {
+                Ok(ast::script::Push::new((start, end), count))
+            }
+            /
+            start:position!() "push" "!"? end:position!() _ "{" _ count:levels() _ "}" {
+                Ok(ast::script::Push::new((start, end), count))
+            }
+
+        /// A pop.
+        pub rule pop() -> PRes<ast::script::Pop>
+        =
+            start:position!() "pop" "!"? end:position!() _ "(" _ count:levels() _ ")" {
+                Ok(ast::script::Pop::new((start, end), count))
+            }
+            /
+            start:position!() "pop" "!"? end:position!() _ "{" _ count:levels() _ "}" {
+                Ok(ast::script::Pop::new((start, end), count))
+            }
+
+        /// A minimization or maximization of an integer objective.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::optimize;
+        /// let opt = optimize("maximize!(x + 1)").unwrap().unwrap();
+        /// assert!(opt.maximize);
+        /// assert_eq!(opt.objective.1, "x + 1");
+        /// let opt = optimize("minimize { x }").unwrap().unwrap();
+        /// assert!(!opt.maximize);
+        /// ```
+        pub rule optimize() -> PRes<ast::script::Optimize<ast::Expr<'input>>>
+        =
+            start:position!() token:$("minimize"/"maximize") "!"? end:position!() _ "("
+                _ objective:hsmt_expr_with_repr() _
+            ")" {
+                let maximize = token == "maximize";
+                Ok(ast::script::Optimize::new((start, end), token, objective, maximize))
+            }
+            /
+            start:position!() token:$("minimize"/"maximize") "!"? end:position!() _ "{"
+                _ objective:hsmt_expr_with_repr() _
+            "}" {
+                let maximize = token == "maximize";
+                Ok(ast::script::Optimize::new((start, end), token, objective, maximize))
+            }
+
         /// An echo.
         pub rule echo() -> PRes<ast::script::Echo>
         =
@@ -1099,6 +2168,737 @@ peg::parser! {
             _ content:commands() _ {
                 Ok(ast::script::Block::new(content?))
             }
+
+        /// SMT-LIB 2 whitespace or `;` comment.
+        rule smt2_() = quiet! { ( whitespace() / "\r" / ";" [^ '\n' ]* ("\n" / ![_]) )* }
+
+        /// Character of an SMT-LIB 2 simple symbol.
+        rule smt2_sym_char() = [
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '~' | '!' | '@' | '$' | '%' | '^' | '&' | '*'
+            | '_' | '-' | '+' | '=' | '<' | '>' | '.' | '?' | '/'
+        ]
+
+        /// Parses an SMT-LIB 2 symbol, simple or quoted.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::smt2_symbol;
+        /// assert_eq!(*smt2_symbol("x!1").unwrap(), "x!1");
+        /// assert_eq!(*smt2_symbol("<=").unwrap(), "<=");
+        /// assert_eq!(*smt2_symbol("|so free|").unwrap(), "|so free|");
+        /// assert!(smt2_symbol("7x").is_err());
+        /// ```
+        pub rule smt2_symbol() -> Spn<&'input str>
+        = quiet! {
+            s:position!() sym:$(!['0'..='9'] smt2_sym_char()+) e:position!() {
+                Spn::new(sym, (s, e))
+            }
+            / s:position!() sym:$("|" [^ '|' | '\\']* "|") e:position!() {
+                Spn::new(sym, (s, e))
+            }
+        }
+        / expected!("SMT-LIB 2 symbol")
+
+        /// Any SMT-LIB 2 s-expression, used to skip or re-parse some input.
+        rule smt2_sexpr()
+        = "(" smt2_() (smt2_sexpr() ** smt2_()) smt2_() ")"
+        / dbl_quoted()
+        / "|" [^ '|']* "|"
+        / [^ '(' | ')' | '"' | '|' | ';' | ' ' | '\n' | '\t' | '\r']+
+
+        /// Parses an SMT-LIB 2 sort.
+        ///
+        /// Only the builtin sorts mikino supports are legal: `Bool`, `Int`, `Real`,
+        /// `(_ BitVec n)` and `(Array s s)`.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Typ, parse::rules::smt2_sort};
+        /// assert_eq!(smt2_sort("Real").unwrap(), Typ::Rat);
+        /// assert_eq!(
+        ///     smt2_sort("(Array Int\n  (_ BitVec 8))").unwrap(),
+        ///     Typ::array(Typ::Int, Typ::BitVec(8)),
+        /// );
+        /// assert!(smt2_sort("List").is_err());
+        /// ```
+        pub rule smt2_sort() -> expr::Typ
+        = quiet! {
+            sort:$(smt2_sexpr()) {?
+                expr::Typ::of_smt_str(sort.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .filter(|typ| trans::Decls::new().check_typ(typ).is_ok())
+                    .ok_or("")
+            }
+        }
+        / expected!("SMT-LIB 2 sort (`Bool`, `Int`, `Real`, `(_ BitVec n)` or `(Array s s)`)")
+
+        /// Parses SMT-LIB 2 constants, numerals, decimals, booleans and bitvector literals.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::{expr::Cst, parse::rules::smt2_cst};
+        /// assert_eq!(*smt2_cst("42").unwrap(), Cst::int(42));
+        /// assert_eq!(*smt2_cst("#b00101010").unwrap(), Cst::bv(8, 42));
+        /// assert_eq!(*smt2_cst("#x2a").unwrap(), Cst::bv(8, 42));
+        /// assert_eq!(*smt2_cst("false").unwrap(), Cst::bool(false));
+        /// assert!(smt2_cst("true_or_false").is_err());
+        /// ```
+        pub rule smt2_cst() -> Spn<Cst>
+        = quiet! {
+            rat:decimal() {
+                rat.map(Cst::R)
+            }
+            / int:uint() {
+                int.map(Cst::I)
+            }
+            / s:position!() "#b" bits:$(['0' | '1']+) e:position!() {?
+                let val = Int::parse_bytes(bits.as_bytes(), 2).ok_or("binary literal")?;
+                let width = bits.len().to_u32().ok_or("binary literal")?;
+                Ok(Spn::new(Cst::bv(width, val), (s, e)))
+            }
+            / s:position!() "#x" hex:$(['0'..='9' | 'a'..='f' | 'A'..='F']+) e:position!() {?
+                let val = Int::parse_bytes(hex.as_bytes(), 16).ok_or("hexadecimal literal")?;
+                let width = (4 * hex.len()).to_u32().ok_or("hexadecimal literal")?;
+                Ok(Spn::new(Cst::bv(width, val), (s, e)))
+            }
+            / s:position!() "true" !smt2_sym_char() e:position!() {
+                Spn::new(Cst::B(true), (s, e))
+            }
+            / s:position!() "false" !smt2_sym_char() e:position!() {
+                Spn::new(Cst::B(false), (s, e))
+            }
+        }
+        / expected!("SMT-LIB 2 constant")
+
+        /// Parses an SMT-LIB 2 term.
+        ///
+        /// Applications of SMT-LIB 2 operators mikino supports, see [`SMT2_OPS`], yield operator
+        /// applications, other applications are uninterpreted function applications. `let`
+        /// bindings are inlined, and annotations `(! <term> ...)` are dropped.
+        ///
+        /// # Examples
+        ///
+        /// ```rust
+        /// # use mikino_api::parse::rules::smt2_expr;
+        /// let ast = smt2_expr("(and (>= x 0) (f x) (not p))").unwrap();
+        /// assert_eq!(ast.to_string(), "((x ≥ 0) ⋀ f(x) ⋀ (¬p))");
+        ///
+        /// let ast = smt2_expr("(let ((y (+ x 1))) (! (< x y) :named lt))").unwrap();
+        /// assert_eq!(ast.to_string(), "(x < (x + 1))");
+        ///
+        /// let ast = smt2_expr("(forall ((i Int)) (=> (> i 0) (p i)))").unwrap();
+        /// assert_eq!(ast.to_string(), "forall (i: int) { ((i > 0) ⇒ p(i)) }");
+        /// ```
+        pub rule smt2_expr() -> ast::Expr<'input>
+        = quiet! {
+            cst:smt2_cst() {
+                ast::Expr::cst(cst)
+            }
+            / ident:smt2_symbol() {
+                ast::Expr::var(ident)
+            }
+            / "(" smt2_() "!" !smt2_sym_char() smt2_() expr:smt2_expr()
+            (smt2_() smt2_sexpr())* smt2_() ")" {
+                expr
+            }
+            / "(" smt2_() "let" !smt2_sym_char() smt2_() "(" smt2_()
+                bindings:(
+                    "(" smt2_() ident:smt2_symbol() smt2_() expr:smt2_expr() smt2_() ")" {
+                        (ident.inner, expr)
+                    }
+                ) ++ smt2_()
+            smt2_() ")" smt2_() body:smt2_expr() smt2_() ")" {
+                smt2_let(&bindings.into_iter().collect(), body)
+            }
+            / "(" smt2_() s:position!() kind:$("forall" / "exists") e:position!()
+            !smt2_sym_char() smt2_() "(" smt2_()
+                vars:(
+                    "(" smt2_() var:smt2_symbol() smt2_() typ:smt2_sort() smt2_() ")" {
+                        (var, typ)
+                    }
+                ) ++ smt2_()
+            smt2_() ")" smt2_() body:smt2_expr() smt2_() ")" {
+                ast::Expr::Quant {
+                    span: Span::new(s, e),
+                    forall: kind == "forall",
+                    vars,
+                    body: Box::new(body),
+                }
+            }
+            / "(" smt2_() head:smt2_symbol() smt2_() args:(smt2_expr() ++ smt2_()) smt2_() ")" {
+                match SMT2_OPS.iter().find(|(name, _)| *name == head.inner) {
+                    Some((_, op)) => ast::Expr::app(Spn::new(op.clone(), head.span), args),
+                    None => ast::Expr::Fun { ident: head, args },
+                }
+            }
+        }
+        / expected!("SMT-LIB 2 term")
+
+        /// Same as [`smt2_expr`], also yields the text of the term.
+        rule smt2_expr_with_repr() -> (ast::Expr<'input>, String)
+        = repr:&($(smt2_expr())) expr:smt2_expr() {
+            (expr, repr.into())
+        }
+
+        /// Parses an SMT-LIB 2 command.
+        ///
+        /// Yields `None` for commands that do not impact the script, `set-logic` and `set-info`.
+        /// Push and pop commands yield `Right(n)`, a push of `n` levels if `n ≥ 0` and a pop of
+        /// `-n` levels otherwise; [`smt2_script`] turns them into snapshots and restores.
+        rule smt2_command() -> PRes<Option<Either<
+            ast::script::Command<ast::Expr<'input>, ast::Expr<'input>>,
+            Spn<isize>,
+        >>>
+        = "(" smt2_() res:(
+            s:position!() "declare-const" e:position!() !smt2_sym_char()
+            smt2_() ident:smt2_symbol() smt2_() typ:smt2_sort() {
+                let mut decls = trans::Decls::new();
+                let _ = decls.register(ident.inner, typ);
+                Ok(Some(Either::Left(ast::script::Vars::new((s, e), decls).into())))
+            }
+            / s:position!() "declare-fun" e:position!() !smt2_sym_char()
+            smt2_() ident:smt2_symbol() smt2_() "(" smt2_() args:(smt2_sort() ** smt2_()) smt2_()
+            ")" smt2_() out:smt2_sort() {
+                let mut decls = trans::Decls::new();
+                if args.is_empty() {
+                    let _ = decls.register(ident.inner, out);
+                } else {
+                    let _ = decls.declare_fun(expr::Fun::new(ident.inner, args, out));
+                }
+                Ok(Some(Either::Left(ast::script::Vars::new((s, e), decls).into())))
+            }
+            / s:position!() "assert" e:position!() !smt2_sym_char() smt2_()
+            "(" smt2_() "!" !smt2_sym_char() smt2_() expr:smt2_expr() smt2_()
+            ":named" !smt2_sym_char() smt2_() name:smt2_symbol() smt2_() ")" {
+                let name = Some(name.map(String::from));
+                let assert = ast::script::Assert::new_named((s, e), vec![(name, expr)]);
+                Ok(Some(Either::Left(assert.into())))
+            }
+            / s:position!() "assert" e:position!() !smt2_sym_char() smt2_() expr:smt2_expr() {
+                let assert = ast::script::Assert::new((s, e), vec![expr]);
+                Ok(Some(Either::Left(assert.into())))
+            }
+            / s:position!() "check-sat-assuming" e:position!() !smt2_sym_char()
+            smt2_() "(" smt2_() lits:(
+                lit:smt2_symbol() { (lit, true) }
+                / "(" smt2_() "not" !smt2_sym_char() smt2_() lit:smt2_symbol() smt2_() ")" {
+                    (lit, false)
+                }
+            ) ** smt2_() smt2_() ")" {
+                let assuming = lits.into_iter().map(|(lit, pol)| (Spn::from(lit), pol)).collect();
+                let check = ast::script::CheckSat::new((s, e), Some(assuming), None);
+                Ok(Some(Either::Left(ast::script::Query::from(check).into())))
+            }
+            / s:position!() "check-sat" e:position!() !smt2_sym_char() {
+                let check = ast::script::CheckSat::new((s, e), None, None);
+                Ok(Some(Either::Left(ast::script::Query::from(check).into())))
+            }
+            / s:position!() token:$("get-model") e:position!() !smt2_sym_char() {
+                Ok(Some(Either::Left(ast::script::GetModel::new((s, e), token).into())))
+            }
+            / s:position!() token:$("get-unsat-core") e:position!() !smt2_sym_char() {
+                Ok(Some(Either::Left(ast::script::UnsatCore::new((s, e), token).into())))
+            }
+            / s:position!() token:$("get-value") e:position!() !smt2_sym_char()
+            smt2_() "(" smt2_() exprs:(smt2_expr_with_repr() ++ smt2_()) smt2_() ")" {
+                let get_values = ast::script::GetValues::new((s, e), token, exprs);
+                Ok(Some(Either::Left(get_values.into())))
+            }
+            / s:position!() "set-option" e:position!() !smt2_sym_char()
+            smt2_() ":" key:$(smt2_sym_char()+) smt2_() vs:position!() val:(
+                cst:smt2_cst() { Either::Left(cst.inner) }
+                / s:dbl_quoted() { Either::Right(s.to_string()) }
+            ) ve:position!() {
+                let opt = ast::script::SetOption::new(
+                    Spn::new(key.to_string(), (s, e)),
+                    Spn::new(val, (vs, ve)),
+                );
+                Ok(Some(Either::Left(ast::script::SetOptions::new((s, e), vec![opt]).into())))
+            }
+            / s:position!() token:$("echo") e:position!() !smt2_sym_char()
+            smt2_() msg:dbl_quoted() {
+                Ok(Some(Either::Left(ast::script::Echo::new((s, e), token, Some(msg)).into())))
+            }
+            / s:position!() "reset" e:position!() !smt2_sym_char() {
+                Ok(Some(Either::Left(ast::script::Reset::new((s, e)).into())))
+            }
+            / s:position!() "exit" e:position!() !smt2_sym_char() {
+                let exit = ast::script::Exit::new((s, e), None);
+                Ok(Some(Either::Left(ast::script::Query::from(exit).into())))
+            }
+            / s:position!() kind:$("push" / "pop") e:position!() !smt2_sym_char()
+            smt2_() n:(n:uint() smt2_() { n })? {
+                let n = match n {
+                    None => 1,
+                    Some(n) => match n.inner.to_isize() {
+                        Some(n) => n,
+                        None => return Err(PError::new("illegal number of levels", n.span)),
+                    },
+                };
+                let n = if kind == "push" { n } else { -n };
+                Ok(Some(Either::Right(Spn::new(n, (s, e)))))
+            }
+            / ("set-logic" / "set-info") !smt2_sym_char() (smt2_() smt2_sexpr())* {
+                Ok(None)
+            }
+            // Supported commands with ill-formed arguments are parse errors, not unsupported.
+            / !(
+                (
+                    "declare-const" / "declare-fun" / "assert" / "check-sat-assuming"
+                    / "check-sat" / "get-model" / "get-value" / "get-unsat-core" / "set-option"
+                    / "echo" / "reset" / "exit" / "push" / "pop"
+                ) !smt2_sym_char()
+            ) cmd:smt2_symbol() (smt2_() smt2_sexpr())* {
+                Err(PError::new(
+                    format!("unsupported SMT-LIB 2 command `{}`", cmd.inner),
+                    cmd.span,
+                ))
+            }
+        ) smt2_() ")" {
+            res
+        }
+
+        /// Parses an SMT-LIB 2 script.
+        ///
+        /// `push` and `pop` become [snapshots](ast::script::Snapshot) and
+        /// [restores](ast::script::Restore), see [`super::smt2_script`] for the commands
+        /// supported.
+        pub rule smt2_script() -> PRes<ast::script::Block<ast::Expr<'input>, ast::Expr<'input>>>
+        = smt2_() cmds:(cmd:smt2_command() smt2_() { cmd })* {
+            let level = |depth: isize| format!("smt2_push_{}", depth);
+            let mut content = Vec::with_capacity(cmds.len());
+            let mut depth = 0;
+            for cmd in cmds {
+                match cmd? {
+                    None => (),
+                    Some(Either::Left(cmd)) => {
+                        if let ast::script::Command::Reset(_) = cmd {
+                            depth = 0
+                        }
+                        content.push(cmd)
+                    }
+                    Some(Either::Right(n)) if n.inner >= 0 => {
+                        for _ in 0..n.inner {
+                            let name = Spn::new(level(depth), n.span);
+                            content.push(ast::script::Snapshot::new(n.span, name).into());
+                            depth += 1
+                        }
+                    }
+                    Some(Either::Right(n)) => {
+                        if -n.inner > depth {
+                            return Err(PError::new(
+                                format!(
+                                    "cannot pop {} level(s), only {} level(s) pushed",
+                                    -n.inner,
+                                    depth,
+                                ),
+                                n.span,
+                            ));
+                        }
+                        depth += n.inner;
+                        let name = Spn::new(level(depth), n.span);
+                        content.push(ast::script::Restore::new(n.span, name).into())
+                    }
+                }
+            }
+            Ok(ast::script::Block::new(content))
+        }
+    }
+}
+
+/// SMT-LIB 2 operators and the corresponding operator, see [`rules::smt2_expr`].
+pub const SMT2_OPS: [(&str, Op); 23] = [
+    ("ite", Op::Ite),
+    ("=>", Op::Implies),
+    ("+", Op::Add),
+    ("-", Op::Sub),
+    ("*", Op::Mul),
+    ("/", Op::Div),
+    ("div", Op::IDiv),
+    ("mod", Op::Mod),
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+    ("=", Op::Eq),
+    ("not", Op::Not),
+    ("and", Op::And),
+    ("or", Op::Or),
+    ("bvand", Op::BvAnd),
+    ("bvor", Op::BvOr),
+    ("bvxor", Op::BvXor),
+    ("bvshl", Op::Shl),
+    ("bvlshr", Op::Shr),
+    ("select", Op::Select),
+    ("store", Op::Store),
+];
+
+/// Inlines some `let` bindings in an expression.
+///
+/// Bindings are parallel, as in SMT-LIB 2: they are not substituted in each other.
+fn smt2_let<'txt>(
+    bindings: &Map<&'txt str, ast::Expr<'txt>>,
+    expr: ast::Expr<'txt>,
+) -> ast::Expr<'txt> {
+    match expr {
+        ast::Expr::Var { ident, pon: None } => match bindings.get(ident.inner) {
+            Some(expr) => expr.clone(),
+            None => ast::Expr::var(ident),
+        },
+        expr @ ast::Expr::Var { .. } | expr @ ast::Expr::Cst(_) => expr,
+        ast::Expr::App { op, args, closed } => ast::Expr::App {
+            op,
+            args: args
+                .into_iter()
+                .map(|arg| smt2_let(bindings, arg))
+                .collect(),
+            closed,
+        },
+        ast::Expr::Fun { ident, args } => ast::Expr::Fun {
+            ident,
+            args: args
+                .into_iter()
+                .map(|arg| smt2_let(bindings, arg))
+                .collect(),
+        },
+        ast::Expr::Quant {
+            span,
+            forall,
+            vars,
+            body,
+        } => {
+            // Quantified variables shadow the bindings.
+            let mut bindings = bindings.clone();
+            for (var, _) in vars.iter() {
+                let _ = bindings.remove(var.inner);
+            }
+            let body = Box::new(smt2_let(&bindings, *body));
+            ast::Expr::Quant {
+                span,
+                forall,
+                vars,
+                body,
+            }
+        }
+    }
+}
+
+/// A candidate: name, tags, severity, label and expression, see [`rules::candidates`].
+type Candidate<'txt> = (
+    Spn<&'txt str>,
+    Vec<&'txt str>,
+    trans::Severity,
+    Option<crate::meta::Label>,
+    ast::Expr<'txt>,
+);
+
+/// A liveness property: name, keyword and expression, see [`rules::liveness`].
+pub type LivenessProp<'txt> = (Spn<&'txt str>, Spn<&'txt str>, ast::Expr<'txt>);
+
+/// Adds some liveness properties to a system.
+///
+/// Fails if a property is not a boolean expression, or if its name is the name of a candidate or
+/// of another property.
+fn with_liveness(sys: trans::Sys, props: Vec<LivenessProp>) -> PRes<trans::Sys> {
+    use check::liveness::Property;
+    let mut liveness = Map::new();
+    for (name, keyword, expr) in props {
+        let prop = expr
+            .clone()
+            .to_expr(sys.decls())
+            .and_then(|prop| SysDef::check_bool(&prop, &expr).map(|()| prop))
+            .map_err(|e| e.chain_err(|| format!("in liveness property `{}`", name.inner)))?;
+        let prop = Property::of_keyword(keyword.inner, prop)
+            .ok_or_else(|| PError::new("unknown liveness keyword", keyword.span))?;
+        if sys.po_s().contains_key(name.inner) {
+            return Err(PError::new(
+                "a candidate with this name is already defined",
+                name.span,
+            ));
+        }
+        let prev = liveness.insert(name.inner.to_string(), prop);
+        if prev.is_some() {
+            return Err(PError::new(
+                "a liveness property with this name is already defined",
+                name.span,
+            ));
+        }
+    }
+    Ok(sys.with_liveness(liveness))
+}
+
+/// Adds some LTL properties to a system.
+///
+/// Fails if a formula is illegal, or if its name is the name of a candidate or of another
+/// property.
+fn with_ltl(sys: trans::Sys, props: Vec<(Spn<&str>, Spn<&str>)>) -> PRes<trans::Sys> {
+    let mut ltl = Map::new();
+    for (name, formula) in props {
+        let formula = check::ltl::Ltl::parse(formula.inner, sys.decls()).map_err(|e| {
+            PError::new(e, formula.span).chain_err(|| format!("in LTL property `{}`", name.inner))
+        })?;
+        let taken = sys.po_s().contains_key(name.inner) || sys.liveness().contains_key(name.inner);
+        if taken || ltl.insert(name.inner.to_string(), formula).is_some() {
+            return Err(PError::new(
+                "a candidate or property with this name is already defined",
+                name.span,
+            ));
+        }
+    }
+    Ok(sys.with_ltl(ltl))
+}
+
+/// An instance: namespace, subsystem name and port connections.
+type Instance<'txt> = (
+    Spn<&'txt str>,
+    Spn<&'txt str>,
+    Vec<(Spn<&'txt str>, Spn<&'txt str>)>,
+);
+
+/// An optionally labeled expression of a system, see [`trans::Sys::with_conjunct_labels`].
+type Labeled<'txt> = (Option<Spn<&'txt str>>, ast::Expr<'txt>);
+
+/// A parsed system definition, candidates excluded.
+struct SysDef<'txt> {
+    /// Declarations, including function declarations.
+    decls: Decls,
+    /// Parameters and their optional constraint, already declared in `decls`.
+    params: Vec<(Spn<&'txt str>, Option<ast::Expr<'txt>>)>,
+    /// Subsystem instances.
+    instances: Vec<Instance<'txt>>,
+    /// Initial predicate, with the span of the `init` keyword.
+    init: (Span, Vec<Labeled<'txt>>),
+    /// Assumptions, with the span of the `assumptions` keyword.
+    assumptions: Option<(Span, Vec<Labeled<'txt>>)>,
+    /// Transition relation, with the span of the `trans` keyword.
+    trans: (Span, Vec<Either<Labeled<'txt>, ast::Frame<'txt>>>),
+}
+impl<'txt> SysDef<'txt> {
+    /// Checks that a conjunct or candidate is a boolean expression, blames its whole span if not.
+    fn check_bool<V>(expr: &PExpr<V>, ast: &ast::Expr<'txt>) -> PRes<()>
+    where
+        V: HasTyp,
+    {
+        let typ = expr.typ();
+        if typ == Typ::Bool {
+            Ok(())
+        } else {
+            Err(PError::new(
+                format!("expected a boolean expression, got `{}`", typ),
+                ast.full_span(),
+            ))
+        }
+    }
+
+    /// Builds the system, `subsystems` are the subsystems that can be instantiated.
+    fn build(
+        self,
+        subsystems: &Map<&'txt str, trans::Sys>,
+        candidates: Vec<Candidate<'txt>>,
+    ) -> PRes<trans::Sys> {
+        let Self {
+            mut decls,
+            params,
+            instances,
+            init: (init_span, init),
+            assumptions,
+            trans: (trans_span, trans),
+        } = self;
+
+        // Instance variables must be declared before checking the expressions.
+        let mut renamings = Vec::with_capacity(instances.len());
+        for (ns, sub, ports) in instances {
+            let sub_sys = subsystems.get(sub.inner).ok_or_else(|| {
+                PError::new(format!("unknown subsystem `{}`", sub.inner), sub.span)
+            })?;
+            let mut port_map = Map::new();
+            for (port, var) in ports {
+                let prev = port_map.insert(port.inner.to_string(), var.inner.to_string());
+                if prev.is_some() {
+                    return Err(PError::new("this port is already connected", port.span));
+                }
+            }
+            let renaming = decls
+                .instantiate(ns.inner, sub_sys.decls(), &port_map)
+                .map_err(|e| PError::new(e.to_string(), ns.span))?;
+            renamings.push((ns.span, sub_sys, renaming));
+        }
+
+        // Labeled conjuncts, checked separately so that errors mention their label.
+        let (mut init_labels, mut trans_labels) = (vec![], vec![]);
+        let label_err = |kind: &'static str, label: Spn<&'txt str>| {
+            move |e: PError| e.chain_err(|| format!("in {} `{}`", kind, label.inner))
+        };
+        let mut init: Vec<ast::Expr<'txt>> = init
+            .into_iter()
+            .map(|(label, expr)| {
+                let conj = expr.clone().to_expr(&decls);
+                let conj = match label {
+                    Some(label) => conj.map_err(label_err("init conjunct", label))?,
+                    None => conj?,
+                };
+                Self::check_bool(&conj, &expr)?;
+                if let Some(label) = label {
+                    init_labels.push((conj, label.inner.to_string()))
+                }
+                Ok(expr)
+            })
+            .collect::<PRes<_>>()?;
+        let mut assumptions = match assumptions {
+            Some((span, assumptions)) => {
+                let mut exprs = Vec::with_capacity(assumptions.len());
+                for (label, expr) in assumptions {
+                    let conj = expr.clone().to_expr(&decls);
+                    let conj = match label {
+                        Some(label) => conj.map_err(label_err("assumption", label))?,
+                        None => conj?,
+                    };
+                    Self::check_bool(&conj, &expr)?;
+                    if let Some(label) = label {
+                        init_labels.push((conj, label.inner.to_string()));
+                        let next = expr
+                            .primed()
+                            .map_err(|e| PError::new(e, label.span))?
+                            .to_sexpr(&decls)
+                            .map_err(label_err("assumption", label))?;
+                        let curr = expr
+                            .clone()
+                            .to_sexpr(&decls)
+                            .map_err(label_err("assumption", label))?;
+                        trans_labels.push((curr, label.inner.to_string()));
+                        trans_labels.push((next, label.inner.to_string()));
+                    }
+                    exprs.push(expr)
+                }
+                Some((span, exprs))
+            }
+            None => None,
+        };
+
+        let (mut exprs, mut frames) = (vec![], vec![]);
+        // Parameters are rigid, and their constraints are assumptions.
+        for (name, cnd) in params {
+            exprs.push(ast::Expr::binapp(
+                Spn::new(Op::Eq, name.span),
+                ast::Expr::svar(name, Some(name.span)),
+                ast::Expr::svar(name, None),
+            ));
+            if let Some(cnd) = cnd {
+                assumptions
+                    .get_or_insert_with(|| (cnd.span(), vec![]))
+                    .1
+                    .push(cnd);
+            }
+        }
+
+        // Quantifiers are only supported in scripts, `to_sexpr` rejects them in `trans`.
+        let quantified = init
+            .iter()
+            .chain(
+                assumptions
+                    .iter()
+                    .flat_map(|(_, assumptions)| assumptions.iter()),
+            )
+            .chain(candidates.iter().map(|(_, _, _, _, expr)| expr))
+            .find_map(|expr| expr.quantifier_span());
+        if let Some(span) = quantified {
+            return Err(PError::new(
+                "quantifiers are only supported in scripts",
+                span,
+            ));
+        }
+        // Assumptions hold in all states: in the initial states, and in the current and next
+        // states of the transition relation.
+        if let Some((span, assumptions)) = assumptions {
+            let assumptions = ast::Expr::app(Spn::new(Op::And, span), assumptions);
+            // Check the assumptions are stateless before priming them.
+            let _ = assumptions.clone().to_expr(&decls)?;
+            let next = assumptions.primed().map_err(|e| PError::new(e, span))?;
+            init.push(assumptions.clone());
+            exprs.push(assumptions);
+            exprs.push(next);
+        }
+        let init = ast::Expr::app(Spn::new(Op::And, init_span), init).to_expr(&decls)?;
+        for item in trans {
+            match item {
+                Either::Left((label, expr)) => {
+                    let conj = expr.clone().to_sexpr(&decls);
+                    let conj = match label {
+                        Some(label) => conj.map_err(label_err("trans conjunct", label))?,
+                        None => conj?,
+                    };
+                    Self::check_bool(&conj, &expr)?;
+                    if let Some(label) = label {
+                        trans_labels.push((conj, label.inner.to_string()))
+                    }
+                    exprs.push(expr)
+                }
+                Either::Right(frame) => frames.push(frame),
+            }
+        }
+        let trans = if frames.is_empty() {
+            ast::Expr::app(Spn::new(Op::And, trans_span), exprs).to_sexpr(&decls)?
+        } else {
+            let mut conjs = vec![];
+            if !exprs.is_empty() {
+                let trans =
+                    ast::Expr::app(Spn::new(Op::And, trans_span), exprs).to_sexpr(&decls)?;
+                conjs.extend(check::preprocess::conjuncts(&trans));
+            }
+            for frame in frames {
+                conjs.extend(check::preprocess::conjuncts(&frame.to_sexpr(&decls)?));
+            }
+            match conjs.len() {
+                0 => expr::SExpr::from(true),
+                1 => conjs.pop().expect("[unreachable] pop on vec of len `1`"),
+                _ => expr::SExpr::new_op(Op::And, conjs)
+                    .map_err(|e| PError::new(e.to_string(), trans_span))?,
+            }
+        };
+
+        let mut pos = Map::new();
+        let mut tags = Map::new();
+        let mut warnings = Set::new();
+        let mut labels = Map::new();
+
+        for (name, po_tags, severity, label, expr) in candidates {
+            let candidate = expr
+                .clone()
+                .to_expr(&decls)
+                .and_then(|candidate| Self::check_bool(&candidate, &expr).map(|()| candidate))
+                .map_err(|e| e.chain_err(|| format!("in candidate `{}`", name.inner)))?;
+            let prev = pos.insert(name.inner.to_string(), candidate);
+            if prev.is_some() {
+                return Err(PError::new(
+                    "a candidate with this name is already defined",
+                    name.span,
+                ));
+            }
+            let po_tags: Set<String> = po_tags.into_iter().map(String::from).collect();
+            if !po_tags.is_empty() {
+                let _ = tags.insert(name.inner.to_string(), po_tags);
+            }
+            if severity.is_warning() {
+                let _ = warnings.insert(name.inner.to_string());
+            }
+            if let Some(label) = label {
+                let _ = labels.insert(name.inner.to_string(), label);
+            }
+        }
+
+        let mut sys = trans::Sys::new(decls, init, trans, pos)
+            .with_tags(tags)
+            .with_warnings(warnings)
+            .with_labels(labels)
+            .with_conjunct_labels(init_labels, trans_labels);
+        for (span, sub, renaming) in renamings {
+            sys = sys
+                .with_instance(sub, &renaming)
+                .map_err(|e| PError::new(e.to_string(), span))?;
+        }
+        Ok(sys)
     }
 }
 
@@ -1106,15 +2906,49 @@ peg::parser! {
 ///
 /// Comments are one-line rust-style: `// ..\n`.
 ///
-/// A system is composed of four `{ ... }` blocks each starting with a specific keyword:
+/// A system is composed of `{ ... }` blocks each starting with a specific keyword:
 ///
-/// - `svars { ... }`: the [state variables][rules::svars] of the system;
+/// - `subsystems { <name> { ... } ... }`, optional: named systems without candidates, which can
+///   instantiate the subsystems defined before them;
+///
+/// - `svars { ... }`: the [state variables][rules::svars] of the system. Variables can also be
+///   declared with a role in `inputs { ... }`, `outputs { ... }` and `state { ... }` blocks, see
+///   [`trans::Role`]. There is at least one such declaration block, in any order, and an
+///   enumeration declared in one of them can be used in all of them;
+///
+/// - `params { ... }`, optional: the [parameters][rules::params] of the system, *e.g.*
+///   `N: int where N > 0`. Parameters are state variables that keep the same value in all states,
+///   their `where` constraints are assumptions. Candidates are thus checked for all the values of
+///   the parameters verifying the constraints;
+///
+/// - `instances { <ns>: <subsystem>(<port> = <var>, ...), ... }`, optional, after the variables
+///   (and functions): instances of subsystems, see [`trans::Sys::instantiate`]. Ports are
+///   variables of the subsystem connected to variables of the system, the other variables of
+///   instance `<ns>` are declared as `<ns>.<var>` and can be used in the system's expressions;
 ///
 /// - `init { ... }`: the initial predicate, *i.e.* a stateless (no `'` prime) expression;
 ///
-/// - `trans { ... }`: the transition relation, *i.e.* a stateful (`'` primes allowed) expression;
+/// - `assumptions { ... }`, optional: stateless expressions assumed to hold in all states, *e.g.*
+///   constraints over the environment. They are conjoined to the initial predicate, and to the
+///   transition relation over both the current and the next state;
 ///
-/// - `candidates { ... }`: some [candidates][rules::candidates] to prove over the systems.
+/// - `trans { ... }`: the transition relation, *i.e.* a stateful (`'` primes allowed) expression,
+///   possibly with [frame conditions][rules::hsmt_frame] `unchanged { ... }` and
+///   `frame except { ... }`;
+///
+///   the expressions of `init`, `assumptions` and `trans` are comma-separated conjuncts, which can
+///   be labeled as `"<label>": <expr>`, see [`trans::Sys::with_conjunct_labels`];
+///
+/// - `candidates { ... }`: some [candidates][rules::candidates] to prove over the systems;
+///
+/// - `liveness { ... }`, optional: some [liveness properties][rules::liveness] of the system,
+///   see [`trans::Sys::liveness`];
+///
+/// - `ltl { ... }`, optional: some [LTL properties][rules::ltl] of the system, see
+///   [`trans::Sys::ltl`].
+///
+/// State variables that are neither constrained by the transition relation nor framed are reported
+/// by [`trans::Sys::unconstrained`].
 pub fn trans(txt: &str) -> Res<trans::Sys> {
     let res: Res<trans::Sys> = match rules::hsmt_trans(txt) {
         Ok(res) => res.map_err(|e| e.into_error(txt)),
@@ -1129,14 +2963,53 @@ pub fn trans(txt: &str) -> Res<trans::Sys> {
     res.chain_err(|| "run mikino in 'demo' mode for more details about the syntax")
 }
 
+/// Parses a stateless expression over some declarations.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::parse;
+/// let sys = parse::trans(mikino_api::TRANS_DEMO).unwrap();
+/// let expr = parse::expr(" cnt ≥ 7 ∧ ¬reset ", sys.decls()).unwrap();
+/// assert_eq!(expr.to_string(), "(and (>= cnt 7) (not reset))");
+///
+/// let err = parse::expr("'cnt = 0", sys.decls()).unwrap_err();
+/// assert_eq!(err.to_string(), "parse error at 1:1:  | 'cnt = 0<EOI>, illegal *next* modifier");
+/// ```
+pub fn expr(txt: &str, decls: &trans::Decls) -> Res<expr::Expr> {
+    let txt = txt.trim();
+    match rules::hsmt_expr(txt) {
+        Ok(ast) => ast.to_expr(decls).map_err(|e| e.into_error(txt)),
+        Err(e) => {
+            let span = Span::new(e.location.offset, e.location.offset);
+            let (prev, row, col, line, next) = span.pretty_of(txt);
+            let err = Error::parse("", row, col, line, prev, next);
+            Err(err.chain_err(|| format!("expected {}", e.expected)))
+        }
+    }
+}
+
 /// Parses a system, requires the `parser` feature.
 ///
 /// Comments are one-line rust-style: `// ..\n`.
 ///
 /// A system is composed of four `{ ... }` blocks each starting with a specific keyword:
 ///
+/// - `subsystems { <name> { ... } ... }`, optional: named systems without candidates, which can
+///   instantiate the subsystems defined before them;
+///
 /// - `svars { ... }`: the [state variables][rules::svars] of the system;
 ///
+/// - `params { ... }`, optional: the [parameters][rules::params] of the system, *e.g.*
+///   `N: int where N > 0`. Parameters are state variables that keep the same value in all states,
+///   their `where` constraints are assumptions. Candidates are thus checked for all the values of
+///   the parameters verifying the constraints;
+///
+/// - `instances { <ns>: <subsystem>(<port> = <var>, ...), ... }`, optional, after the variables
+///   (and functions): instances of subsystems, see [`trans::Sys::instantiate`]. Ports are
+///   variables of the subsystem connected to variables of the system, the other variables of
+///   instance `<ns>` are declared as `<ns>.<var>` and can be used in the system's expressions;
+///
 /// - `init { ... }`: the initial predicate, *i.e.* a stateless (no `'` prime) expression;
 ///
 /// - `trans { ... }`: the transition relation, *i.e.* a stateful (`'` primes allowed) expression;
@@ -1156,6 +3029,60 @@ pub fn script(txt: &str) -> Res<ast::script::Block<ast::Expr, ast::Expr>> {
     res.chain_err(|| "run mikino in 'demo' mode for more details about the syntax")
 }
 
+/// Parses a plain SMT-LIB 2 script into a script AST.
+///
+/// Comments are SMT-LIB 2 comments: `; ..\n`. Supported commands are
+///
+/// - `declare-const` and `declare-fun`, over sorts `Bool`, `Int`, `Real`, `(_ BitVec n)` and
+///   `(Array s s)`;
+/// - `assert`, terms are parsed by [`rules::smt2_expr`], `(! <term> :named <name>)` names the
+///   assertion;
+/// - `check-sat`, `check-sat-assuming` over literals, `get-model`, `get-value` and
+///   `get-unsat-core`;
+/// - `set-option`, `echo`, `reset` and `exit`;
+/// - `push` and `pop`, which become [snapshots](ast::script::Snapshot) and
+///   [restores](ast::script::Restore).
+///
+/// Commands `set-logic` and `set-info` are ignored, other commands are errors.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::parse;
+/// let block = parse::smt2_script("\
+///     (set-logic QF_LIA) ; ignored
+///     (declare-const n Int)
+///     (assert (> n 0))
+///     (push 1)
+///     (assert (< n 0))
+///     (check-sat)
+///     (pop 1)
+///     (check-sat)
+///     (get-model)
+/// ").unwrap();
+/// assert_eq!(block.content.len(), 8);
+///
+/// let err = parse::smt2_script("(declare-datatypes () ((Color red green)))").unwrap_err();
+/// assert_eq!(
+///     err.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+///     vec![
+///         "parse error at 1:2:  | (declare-datatypes () ((Color red green)))<EOI>",
+///         "unsupported SMT-LIB 2 command `declare-datatypes`",
+///     ],
+/// );
+/// ```
+pub fn smt2_script(txt: &str) -> Res<ast::script::Block<ast::Expr<'_>, ast::Expr<'_>>> {
+    match rules::smt2_script(txt) {
+        Ok(res) => res.map_err(|e| e.into_error(txt)),
+        Err(e) => {
+            let span = Span::new(e.location.offset, e.location.offset);
+            let (prev, row, col, line, next) = span.pretty_of(txt);
+            let err = Error::parse("", row, col, line, prev, next);
+            Err(err.chain_err(|| format!("expected {}", e.expected)))
+        }
+    }
+}
+
 /// Parses its input text.
 pub struct Parser<'txt> {
     /// Text to parse.
@@ -1188,6 +3115,7 @@ impl<'txt> Parser<'txt> {
             prev,
             row,
             col,
+            len: 0,
             line,
             next,
         }
@@ -1548,6 +3476,10 @@ impl<'txt> Parser<'txt> {
         };
 
         let int = self.parse_until(|c| !c.is_numeric(), false);
+        // Decimals are rationals, see `try_decimal`.
+        if self.txt[self.cursor..].starts_with('.') {
+            abort!()
+        }
         if int.len() > 0 {
             let mut int =
                 Int::parse_bytes(int.as_bytes(), 10).expect("parsing an integer cannot fail");
@@ -1579,7 +3511,24 @@ impl<'txt> Parser<'txt> {
     /// let rat = parser.try_rat().unwrap().unwrap();
     /// assert_eq! { &rat.to_string(), "72/11" }
     /// ```
+    ///
+    /// Solvers also print rationals as decimals, possibly in divisions.
+    ///
+    /// ```rust
+    /// # use mikino_api::parse::*;
+    /// let mut parser = Parser::new("(- (/ 1.0 4.0)) (/ (- 3) 2) 2.5 (- 0.75)");
+    /// assert_eq! { &parser.try_rat().unwrap().unwrap().to_string(), "-1/4" }
+    /// parser.ws_cmt();
+    /// assert_eq! { &parser.try_rat().unwrap().unwrap().to_string(), "-3/2" }
+    /// parser.ws_cmt();
+    /// assert_eq! { &parser.try_rat().unwrap().unwrap().to_string(), "5/2" }
+    /// parser.ws_cmt();
+    /// assert_eq! { &parser.try_rat().unwrap().unwrap().to_string(), "-3/4" }
+    /// ```
     pub fn try_rat(&mut self) -> Res<Option<Rat>> {
+        if let Some(rat) = self.try_decimal() {
+            return Ok(Some(rat));
+        }
         let start = self.cursor;
         macro_rules! abort {
             () => {{
@@ -1615,9 +3564,9 @@ impl<'txt> Parser<'txt> {
         abort!(if !self.try_tag("/"));
         self.ws_cmt();
 
-        let num = abort!(if not let Some(_) = self.try_int());
+        let num = abort!(if not let Some(_) = self.try_int_or_decimal());
         self.ws_cmt();
-        let den = abort!(if not let Some(_) = self.try_int());
+        let den = abort!(if not let Some(_) = self.try_int_or_decimal());
         self.ws_cmt();
         self.tag(")")?;
 
@@ -1625,7 +3574,7 @@ impl<'txt> Parser<'txt> {
             bail!(self.fail("division by zero"))
         }
 
-        let mut rat = Rat::new(num, den);
+        let mut rat = num / den;
         if negated {
             self.ws_cmt();
             abort!(if !self.try_tag(")"));
@@ -1634,6 +3583,51 @@ impl<'txt> Parser<'txt> {
         Ok(Some(rat))
     }
 
+    /// Tries to parse a decimal, `<digits>.<digits>` or `(- <digits>.<digits>)`.
+    fn try_decimal(&mut self) -> Option<Rat> {
+        let start = self.cursor;
+        let neg = self.try_tag("(") && {
+            self.ws_cmt();
+            self.try_tag("-")
+        };
+        if neg {
+            self.ws_cmt();
+        } else {
+            self.backtrack(start);
+        }
+        let int = self.parse_until(|c| !c.is_numeric(), false);
+        let dec_start = self.cursor;
+        let dec = if !int.is_empty() && self.try_tag(".") {
+            self.parse_until(|c| !c.is_numeric(), false)
+        } else {
+            ""
+        };
+        if dec.is_empty() {
+            self.backtrack(start);
+            return None;
+        }
+        let digits = format!("{}{}", int, dec);
+        let numer = Int::parse_bytes(digits.as_bytes(), 10).expect("parsing digits cannot fail");
+        let denom = num::pow(Int::from(10), self.cursor - dec_start - 1);
+        let mut rat = Rat::new(numer, denom);
+        if neg {
+            self.ws_cmt();
+            if !self.try_tag(")") {
+                self.backtrack(start);
+                return None;
+            }
+            rat = -rat
+        }
+        Some(rat)
+    }
+
+    /// Tries to parse an integer or a decimal, as a rational.
+    fn try_int_or_decimal(&mut self) -> Option<Rat> {
+        self.try_int()
+            .map(Rat::from_integer)
+            .or_else(|| self.try_decimal())
+    }
+
     /// Tries to parse a boolean.
     ///
     /// # Examples
@@ -1664,6 +3658,198 @@ impl<'txt> Parser<'txt> {
         }
     }
 
+    /// Tries to parse a bitvector, `#x<hexa>`, `#b<binary>` or `(_ bv<value> <width>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{trans::Decls, parse::*};
+    /// # use mikino_api::expr::*;
+    /// let txt = "#x2a #b00101010 (_ bv42 8) #b";
+    /// let mut parser = Parser::new(txt);
+    /// assert_eq!(parser.try_bv().unwrap().unwrap(), Cst::bv(8, 42));
+    /// parser.ws_cmt();
+    /// assert_eq!(parser.try_bv().unwrap().unwrap(), Cst::bv(8, 42));
+    /// parser.ws_cmt();
+    /// assert_eq!(parser.try_bv().unwrap().unwrap(), Cst::bv(8, 42));
+    /// parser.ws_cmt();
+    /// assert_eq!(parser.try_bv().unwrap(), None);
+    /// ```
+    pub fn try_bv(&mut self) -> Res<Option<Cst>> {
+        let start = self.cursor;
+        let (digits, radix, bits) = if self.try_tag("#x") {
+            (self.parse_until(|c| !c.is_ascii_hexdigit(), false), 16, 4)
+        } else if self.try_tag("#b") {
+            (self.parse_until(|c| c != '0' && c != '1', false), 2, 1)
+        } else {
+            return self.try_smt_bv();
+        };
+        if digits.is_empty() {
+            self.backtrack(start);
+            return Ok(None);
+        }
+        let val = Int::parse_bytes(digits.as_bytes(), radix).expect("parsing digits cannot fail");
+        let width = match (digits.len() * bits).to_u32() {
+            Some(width) => width,
+            None => bail!(self.fail("bitvector is too wide")),
+        };
+        Ok(Some(Cst::bv(width, val)))
+    }
+    /// Tries to parse a bitvector of the form `(_ bv<value> <width>)`.
+    fn try_smt_bv(&mut self) -> Res<Option<Cst>> {
+        let start = self.cursor;
+        if !self.try_tag("(") {
+            return Ok(None);
+        }
+        self.ws_cmt();
+        if !self.try_tag("_") {
+            self.backtrack(start);
+            return Ok(None);
+        }
+        self.ws_cmt();
+        if !self.try_tag("bv") {
+            self.backtrack(start);
+            return Ok(None);
+        }
+        let val = match self.try_int() {
+            Some(val) => val,
+            None => bail!(self.fail("expected bitvector value")),
+        };
+        self.ws_cmt();
+        let width = match self.try_int().and_then(|width| width.to_u32()) {
+            Some(width) if width > 0 => width,
+            _ => bail!(self.fail("expected bitvector width")),
+        };
+        self.ws_cmt();
+        self.tag(")")?;
+        Ok(Some(Cst::bv(width, val)))
+    }
+
+    /// Tries to parse an array constant, `((as const <sort>) <cst>)` or `(store <array> <cst> <cst>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{trans::Decls, parse::*};
+    /// # use mikino_api::expr::*;
+    /// let txt = "(store ((as const (Array Int Bool)) false) 3 true) (store a 3 true)";
+    /// let mut parser = Parser::new(txt);
+    /// let arr = parser.try_array().unwrap().unwrap();
+    /// assert_eq!(arr.typ(), Typ::array(Typ::Int, Typ::Bool));
+    /// assert_eq!(arr.select(&Cst::int(3)).unwrap(), Cst::B(true));
+    /// assert_eq!(arr.select(&Cst::int(7)).unwrap(), Cst::B(false));
+    /// parser.ws_cmt();
+    /// assert_eq!(parser.try_array().unwrap(), None);
+    /// ```
+    pub fn try_array(&mut self) -> Res<Option<Cst>> {
+        let start = self.cursor;
+        if !self.try_tag("(") {
+            return Ok(None);
+        }
+        self.ws_cmt();
+        if self.try_tag("store") {
+            let mut csts = Vec::with_capacity(3);
+            for _ in 0..3 {
+                self.ws_cmt();
+                match self.try_cst()? {
+                    Some(cst) => csts.push(cst),
+                    None => {
+                        self.backtrack(start);
+                        return Ok(None);
+                    }
+                }
+            }
+            self.ws_cmt();
+            self.tag(")")?;
+            let (val, idx, arr) = (csts.pop(), csts.pop(), csts.pop());
+            match (arr, idx, val) {
+                (Some(arr), Some(idx), Some(val)) => Ok(Some(
+                    arr.store(idx, val)
+                        .map_err(|e| e.force_source(self.fail("")))?,
+                )),
+                _ => unreachable!("parsed exactly three constants"),
+            }
+        } else if self.try_tag("(") {
+            self.ws_cmt();
+            if !self.try_tag("as") {
+                self.backtrack(start);
+                return Ok(None);
+            }
+            self.ws_cmt();
+            self.tag("const")?;
+            self.ws_cmt();
+            let idx = match self.smt_sort()?.array_typs() {
+                Some((idx, _)) => idx.clone(),
+                None => bail!(self.fail("expected array sort")),
+            };
+            self.ws_cmt();
+            self.tag(")")?;
+            self.ws_cmt();
+            let default = match self.try_cst()? {
+                Some(default) => default,
+                None => bail!(self.fail("expected constant array value")),
+            };
+            self.ws_cmt();
+            self.tag(")")?;
+            Ok(Some(Cst::array(idx, default)))
+        } else {
+            self.backtrack(start);
+            Ok(None)
+        }
+    }
+    /// Tries to parse an enumeration variant, the quoted SMT-LIB symbol `|<typ>::<variant>|`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{trans::Decls, parse::*};
+    /// # use mikino_api::expr::*;
+    /// let txt = "|State::Idle| |State|";
+    /// let mut parser = Parser::new(txt);
+    /// assert_eq!(parser.try_variant().unwrap(), Cst::variant("State", "Idle"));
+    /// parser.ws_cmt();
+    /// assert_eq!(parser.try_variant(), None);
+    /// ```
+    pub fn try_variant(&mut self) -> Option<Cst> {
+        let rest = self.txt[self.cursor..].strip_prefix('|')?;
+        let end = rest.find('|')?;
+        let (typ, variant) = rest[..end].split_once("::")?;
+        if typ.is_empty() || variant.is_empty() {
+            return None;
+        }
+        self.cursor += end + 2;
+        Some(Cst::variant(typ, variant))
+    }
+    /// Parses an SMT-LIB sort, like `Int` or `(Array Int (_ BitVec 8))`.
+    fn smt_sort(&mut self) -> Res<Typ> {
+        let rest = &self.txt[self.cursor..];
+        let mut depth = 0usize;
+        let mut end = rest.len();
+        for (idx, c) in rest.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth <= 1 => {
+                    end = if depth == 0 { idx } else { idx + 1 };
+                    break;
+                }
+                ')' => depth -= 1,
+                c if c.is_whitespace() && depth == 0 => {
+                    end = idx;
+                    break;
+                }
+                _ => (),
+            }
+        }
+        let sort = &rest[..end];
+        match Typ::of_smt_str(sort) {
+            Some(typ) => {
+                self.cursor += end;
+                Ok(typ)
+            }
+            None => bail!(self.fail(format!("unexpected sort `{}`", sort))),
+        }
+    }
+
     /// Tries to parse a constant.
     ///
     /// # Examples
@@ -1671,17 +3857,27 @@ impl<'txt> Parser<'txt> {
     /// ```rust
     /// # use mikino_api::{trans::Decls, parse::*};
     /// # use mikino_api::expr::*;
-    /// let txt = "7405,(/ 7 103),false";
+    /// let txt = "7405,(/ 7 103),false,#x2a,|State::Idle|";
     /// let mut parser = Parser::new(txt);
     /// assert_eq!(parser.try_cst().unwrap().unwrap(), 7405.into());
     /// parser.tag(",").unwrap();
     /// assert_eq!(parser.try_cst().unwrap().unwrap(), (7, 103).into());
     /// parser.tag(",").unwrap();
     /// assert_eq!(parser.try_cst().unwrap().unwrap(), false.into());
+    /// parser.tag(",").unwrap();
+    /// assert_eq!(parser.try_cst().unwrap().unwrap(), Cst::bv(8, 42));
+    /// parser.tag(",").unwrap();
+    /// assert_eq!(parser.try_cst().unwrap().unwrap(), Cst::variant("State", "Idle"));
     /// ```
     pub fn try_cst(&mut self) -> Res<Option<Cst>> {
         if let Some(b) = self.try_bool() {
             Ok(Some(Cst::B(b)))
+        } else if let Some(variant) = self.try_variant() {
+            Ok(Some(variant))
+        } else if let Some(bv) = self.try_bv()? {
+            Ok(Some(bv))
+        } else if let Some(arr) = self.try_array()? {
+            Ok(Some(arr))
         } else if let Some(i) = self.try_int() {
             Ok(Some(Cst::I(i)))
         } else if let Some(r) = self.try_rat()? {
@@ -1699,8 +3895,36 @@ impl<'txt> Parser<'txt> {
             Ok(Typ::Int)
         } else if self.try_tag("rat") {
             Ok(Typ::Rat)
+        } else if self.try_tag("bv") {
+            self.ws_cmt();
+            self.tag("<")?;
+            self.ws_cmt();
+            let width = match self.try_int().and_then(|width| width.to_u32()) {
+                Some(width) if width > 0 => width,
+                _ => bail!(self.fail("expected bitvector width")),
+            };
+            self.ws_cmt();
+            self.tag(">")?;
+            Ok(Typ::BitVec(width))
+        } else if self.try_tag("array") {
+            self.ws_cmt();
+            self.tag("<")?;
+            self.ws_cmt();
+            let idx = self.typ()?;
+            self.ws_cmt();
+            self.tag(",")?;
+            self.ws_cmt();
+            let elm = self.typ()?;
+            self.ws_cmt();
+            self.tag(">")?;
+            Ok(Typ::array(idx, elm))
         } else {
-            bail!(self.fail("expected type"))
+            let start = self.cursor;
+            match self.try_id() {
+                Some(id) if self.decls.enum_variants(id).is_some() => Ok(Typ::enumeration(id)),
+                Some(id) => bail!(self.fail_at(start, format!("unknown type `{}`", id))),
+                None => bail!(self.fail("expected type")),
+            }
         }
     }
 
@@ -1843,11 +4067,9 @@ impl<'a> IdentParser<Var, Typ, &'a str> for Smt2Parser {
         }
     }
     fn parse_type(self, input: &'a str) -> SmtRes<Typ> {
-        match input {
-            "Bool" => Ok(Typ::Bool),
-            "Int" => Ok(Typ::Int),
-            "Real" => Ok(Typ::Rat),
-            _ => bail!("unexpected type string `{}`", input),
+        match Typ::of_smt_str(input) {
+            Some(typ) => Ok(typ),
+            None => bail!("unexpected type string `{}`", input),
         }
     }
 }
@@ -1860,11 +4082,9 @@ impl<'a> IdentParser<SVar, Typ, &'a str> for Smt2Parser {
         }
     }
     fn parse_type(self, input: &'a str) -> SmtRes<Typ> {
-        match input {
-            "Bool" => Ok(Typ::Bool),
-            "Int" => Ok(Typ::Int),
-            "Real" => Ok(Typ::Rat),
-            _ => bail!("unexpected type string `{}`", input),
+        match Typ::of_smt_str(input) {
+            Some(typ) => Ok(typ),
+            None => bail!("unexpected type string `{}`", input),
         }
     }
 }
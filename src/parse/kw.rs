@@ -50,10 +50,24 @@ build_keywords! {
         svars: "svars",
         /// Initial predicate declaration keyword.
         init: "init",
+        /// Assumptions declaration keyword.
+        assumptions: "assumptions",
+        /// Parameter declarations keyword.
+        params: "params",
+        /// Parameter constraint keyword.
+        param_where: "where",
+        /// Subsystem definitions keyword.
+        subsystems: "subsystems",
+        /// Subsystem instances keyword.
+        instances: "instances",
         /// Transition relation declaration keyword.
         trans: "trans",
         /// Candidate map keyword.
         cands: "candidates",
+        /// Enumeration type declaration keyword.
+        enum_decl: "enum",
+        /// Uninterpreted function declaration keyword.
+        funs: "funs",
 
         /// ITE's *if*.
         op_ite_if: "if",
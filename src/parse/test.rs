@@ -333,4 +333,1071 @@ parse error at 4:27
         r#"expected "}""#,
         "run mikino in 'demo' mode for more details about the syntax",
     );
+
+    run!(
+        "svars { v : int }\ninit { v = 0, v + 1 }\ntrans { 'v = v }\ncandidates { \"prop\": v ≥ 0 }" =>
+        "\
+parse error at 2:15
+  | svars { v : int }
+2 | init { v = 0, v + 1 }
+  |               ^~~~~ here
+  | trans { 'v = v }\
+        ",
+        "expected a boolean expression, got `int`",
+        "run mikino in 'demo' mode for more details about the syntax",
+    );
+
+    run!(
+        "svars { v : int, b : bool }\ninit { v = 0 }\ntrans { 'v = if b { v } else { b } }\n\
+        candidates { \"prop\": v ≥ 0 }" =>
+        "\
+parse error at 3:14
+  | init { v = 0 }
+3 | trans { 'v = if b { v } else { b } }
+  |              ^~~~ here
+  | candidates { \"prop\": v ≥ 0 }\
+        ",
+        "`ite`'s second and third arguments should have the same type, got `int` and `bool`",
+        "\
+parse error at 3:14
+  | init { v = 0 }
+3 | trans { 'v = if b { v } else { b } }
+  |              ^~~~~~~~~~~~~~~~~~~ in this application
+  | candidates { \"prop\": v ≥ 0 }\
+        ",
+        "run mikino in 'demo' mode for more details about the syntax",
+    );
+
+    run!(
+        "svars { v : int, b : bool }\ninit { v = 0 }\ntrans { 'v = v }\n\
+        candidates { \"prop\": b ⋁ v + b > 0 }" =>
+        "\
+parse error at 4:30
+  | trans { 'v = v }
+4 | candidates { \"prop\": b ⋁ v + b > 0 }<EOI>
+  |                            ^~~~ here\
+        ",
+        "`+`'s arguments must all have the same type, found `int` and `bool`",
+        "in candidate `prop`",
+        "\
+parse error at 4:28
+  | trans { 'v = v }
+4 | candidates { \"prop\": b ⋁ v + b > 0 }<EOI>
+  |                          ^~~~~ in this application\
+        ",
+        "run mikino in 'demo' mode for more details about the syntax",
+    );
+}
+
+#[test]
+fn display_hints() {
+    let decls = rules::svars(
+        "
+        /// Time counter.
+        /// @unit: ms
+        cnt: int,
+        /// @display: hex
+        flags mask: int,",
+    )
+    .unwrap()
+    .unwrap();
+    let hint = decls.hint("cnt").unwrap();
+    assert_eq!(hint.unit.as_deref(), Some("ms"));
+    assert_eq!(hint.display, None);
+    assert_eq!(decls.hint("flags").unwrap().display.as_deref(), Some("hex"));
+    assert_eq!(decls.hint("mask"), None);
+
+    let err = rules::svars("/// @units: ms\ncnt: int")
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(
+        err.error.to_string(),
+        "unknown display hint `@units`, expected `@display` or `@unit`",
+    );
+}
+
+#[test]
+fn delta_sugar() {
+    let sys = |svars: &str, init: &str, trans: &str| {
+        super::trans(&format!(
+            "svars {{ {} }} init {{ {} }} trans {{ {} }} candidates {{ \"c\": ⊤ }}",
+            svars, init, trans
+        ))
+        .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    let ok = sys("time: rat", "time = 0.0", "delta ≥ 0.0").unwrap();
+    assert_eq!(ok.trans().to_string(), "(>= (- time@1 time@0) (/ 0 1))");
+    // Declared `delta` variables take precedence.
+    let ok = sys("time delta: rat", "time = 0.0", "'delta = delta").unwrap();
+    assert_eq!(ok.trans().to_string(), "(= delta@1 delta@0)");
+
+    let err = sys("time: int", "time = 0", "delta ≥ 0").err().unwrap();
+    assert_eq!(
+        err[1],
+        ("`delta` requires a state variable `time: rat`, the clock")
+    );
+    let err = sys("time: rat", "delta = 0.0", "⊤").err().unwrap();
+    assert_eq!(err[1], "`delta` is only legal in the transition relation");
+    let err = sys("time: rat", "time = 0.0", "'delta = 0.0")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "illegal *next* modifier on `delta`");
+}
+
+#[test]
+fn snapshots() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    let ok = build("vars { n: int } snapshot!(s) vars { m: int } assert { m > n } restore!(s)");
+    assert!(ok.is_ok());
+    // Declarations after the snapshot are not visible after restoring it.
+    let err = build("vars { n: int } snapshot!(s) vars { m: int } restore!(s) assert { m > n }")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown variable `m`");
+    // Restoring can re-declare variables.
+    let ok = build("snapshot!{empty} vars { n: int } restore!{empty} vars { n: bool }");
+    assert!(ok.is_ok());
+
+    let err = build("vars { n: int } restore!(s) snapshot!(s)")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown snapshot `s`");
+}
+
+#[test]
+fn push_pop() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    let script = build(
+        "vars { n: int } push!() vars { m: int } assert { m > n } check_sat!() pop!() \
+        push { 2 } assert { n > 0 } pop { 2 } vars { m: bool }",
+    )
+    .unwrap();
+    assert_eq!(
+        crate::script::to_smt2(&script).unwrap(),
+        "\
+(declare-const n Int)
+(push 1)
+(declare-const m Int)
+(assert (> m n))
+(check-sat)
+(pop 1)
+(push 1)
+(push 1)
+(assert (> n 0))
+(pop 2)
+(declare-const m Bool)
+"
+    );
+    // Declarations after the push are not visible after popping it.
+    let err = build("vars { n: int } push!() vars { m: int } pop!() assert { m > n }")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown variable `m`");
+    // Restoring a snapshot restores its push levels.
+    assert!(build("push!() snapshot!(s) pop!() restore!(s) pop!()").is_ok());
+
+    let err = build("push!(2) pop!(3)").err().unwrap();
+    assert_eq!(err[1], "cannot pop 3 level(s), only 2 level(s) pushed");
+    let err = build("push!() reset!() pop!()").err().unwrap();
+    assert_eq!(err[1], "cannot pop 1 level(s), only 0 level(s) pushed");
+}
+
+#[test]
+fn loops() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    let script = build(
+        "vars { n: int } \
+        for i in -1..=1 { push!() assert { n = 2 * i } repeat 2 { check_sat!() } pop!() }",
+    )
+    .unwrap();
+    let smt2 = crate::script::to_smt2(&script).unwrap();
+    assert_eq!(smt2.matches("(check-sat)").count(), 6);
+    assert!(smt2.contains("(assert (= n (* 2 -1)))"));
+    assert!(smt2.contains("(assert (= n (* 2 0)))"));
+    assert!(smt2.contains("(assert (= n (* 2 1)))"));
+
+    // Inner loops shadow outer ones, evaluations show the values.
+    let script = build("vars { n: int } for i in 0..1 { for i in 7..8 { eval!(n + i) } }").unwrap();
+    assert!(crate::script::to_smt2(&script)
+        .unwrap()
+        .ends_with("(get-value ((+ n 7)))\n"));
+    let mut block = super::rules::repeat("for i in 3..4 { eval!(i) }")
+        .unwrap()
+        .unwrap();
+    match block.content.pop() {
+        Some(ast::script::Command::Query(ast::script::Query::Block(b))) => match &b.content[0] {
+            ast::script::Command::GetValues(gv) => assert_eq!(gv.exprs[0].1, "3"),
+            cmd => panic!("expected an evaluation, got {:?}", cmd),
+        },
+        cmd => panic!("expected a block, got {:?}", cmd),
+    }
+
+    // Loop variables are not visible outside their loop.
+    let err = build("vars { n: int } repeat 0 { } for i in 0..2 { } assert { n > i }")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown variable `i`");
+}
+
+#[test]
+fn definitions() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+    let smt2 = |txt: &str| crate::script::to_smt2(&build(txt).unwrap()).unwrap();
+
+    assert_eq!(
+        smt2(
+            "let bound = 42; let inv = cnt ≥ bound ∧ flag; let flag = true; \
+            vars { cnt: int, flag: bool } assert { inv } check_sat!()"
+        ),
+        "\
+(declare-const cnt Int)
+(declare-const flag Bool)
+(assert (and (>= cnt 42) flag))
+(check-sat)
+"
+    );
+    // Definitions are scoped by blocks and shadowed by declarations.
+    assert!(
+        smt2("vars { n: int } let m = 7; { let n = 2; assert { m > n } } assert { m > n }")
+            .ends_with("(assert (> 7 2))\n(assert (> 7 n))\n")
+    );
+    assert!(smt2("let n = 1; vars { n: int } assert { n > 0 }").ends_with("(assert (> n 0))\n"));
+    let err = build("vars { n: int } { let m = 2; } assert { n > m }")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown variable `m`");
+}
+
+#[test]
+fn optimize() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    let script =
+        build("vars { n: int, b: bool } minimize!(n + 1) maximize { if b { n } else { 0 } }")
+            .unwrap();
+    let err = crate::script::to_smt2(&script).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "cannot export `minimize` to SMT-LIB 2, it depends on the solver's answers"
+    );
+
+    let err = build("vars { b: bool } maximize!(b)").err().unwrap();
+    assert_eq!(err[1], "objective has type bool, expected int or bitvector");
+    let err = build("vars { r: rat } minimize!(r)").err().unwrap();
+    assert_eq!(err[1], "objective has type rat, expected int or bitvector");
+    build("vars { x: bv<8> } minimize!(x) maximize { x & 7bv8 }").unwrap();
+}
+
+#[test]
+fn assert_typ() {
+    let err = crate::script::build::of_str("vars { n: int } assert { n ≥ 0, n + 1 }")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.iter().next().unwrap().pretty(()),
+        "\
+parse error at 1:35
+  |
+1 | vars { n: int } assert { n ≥ 0, n + 1 }<EOI>
+  |                                 ^~~~~ here",
+    );
+    assert_eq!(
+        err.iter().nth(1).unwrap().to_string(),
+        "assertion has type int, expected bool"
+    );
+}
+
+#[test]
+fn get_mus() {
+    use crate::ast::script::{Command, GetMus};
+    let cmd = super::rules::command("get_mus!()").unwrap().unwrap();
+    assert_eq!(cmd, Command::from(GetMus::new((0, 8), "get_mus")));
+    let cmd = super::rules::command("get_mus {}").unwrap().unwrap();
+    assert_eq!(cmd, Command::from(GetMus::new((0, 7), "get_mus")));
+    assert!(super::rules::command("get_mus!(x)").is_err());
+}
+
+#[test]
+fn unsat_core() {
+    use crate::ast::script::{Command, UnsatCore};
+    let cmd = super::rules::command("unsat_core!()").unwrap().unwrap();
+    assert_eq!(cmd, Command::from(UnsatCore::new((0, 11), "unsat_core")));
+    let cmd = super::rules::command("unsat_core {}").unwrap().unwrap();
+    assert_eq!(cmd, Command::from(UnsatCore::new((0, 10), "unsat_core")));
+    assert!(super::rules::command("unsat_core!(x)").is_err());
+
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+    let script =
+        build(r#"vars { n: int } assert { pos: n > 0, n < 7, "n is ok": n ≥ 1 }"#).unwrap();
+    let assert = match &script {
+        Command::Query(crate::ast::script::Query::Block(b)) => &b.content[1],
+        cmd => panic!("expected a block, got {:?}", cmd),
+    };
+    match assert {
+        Command::Assert(a) => {
+            let names: Vec<_> = a
+                .names
+                .iter()
+                .map(|name| name.as_ref().map(|name| name.inner.as_str()))
+                .collect();
+            assert_eq!(names, vec![Some("pos"), None, Some("n is ok")]);
+        }
+        cmd => panic!("expected an assertion, got {:?}", cmd),
+    }
+    assert!(script.uses_unsat_cores());
+    assert!(!build("vars { n: int } assert { n > 0 }")
+        .unwrap()
+        .uses_unsat_cores());
+    assert!(build("if check_sat!() { unsat_core!() }")
+        .unwrap()
+        .uses_unsat_cores());
+
+    // Names cannot be variables, labels cannot contain `|`.
+    let err = build("vars { n: int } assert { n: n > 0 }").err().unwrap();
+    assert_eq!(err[1], "assertion name `n` is already a variable");
+    let err = build(r#"vars { n: int } assert { "n|0": n > 0 }"#)
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "assertion labels cannot contain `|` or `\\`");
+}
+
+#[test]
+fn check_sat_assuming() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+    let script = build("vars { a b: bool } check_sat!(assuming: [a, ¬b,])").unwrap();
+    assert_eq!(
+        crate::script::to_smt2(&script).unwrap(),
+        "\
+(declare-const a Bool)
+(declare-const b Bool)
+(check-sat-assuming (a (not b)))
+"
+    );
+    assert!(build("vars { a: bool } let sat = check_sat { assuming: [not a] };").is_ok());
+    assert!(build("vars { a: bool } if check_sat!(assuming: []) { }").is_ok());
+
+    let err = build("vars { a: bool } check_sat!(assuming: [a, ¬b])")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "check sat mentions 1 unknown literal: b");
+    let err = build("vars { n: int } check_sat!(assuming: [¬n])")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "check sat literal `n` has type int, expected bool");
+
+    // SMT-LIB 2 literals can be negated.
+    let script = crate::script::build::of_smt2_str(
+        "(declare-const a Bool) (declare-const b Bool) (check-sat-assuming ((not a) b))",
+    )
+    .unwrap();
+    assert!(crate::script::to_smt2(&script)
+        .unwrap()
+        .ends_with("(check-sat-assuming ((not a) b))\n"));
+}
+
+#[test]
+fn meta_scoping() {
+    let build = |txt: &str| {
+        crate::script::build::of_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    // Meta-variables can be shadowed.
+    assert!(build("let sat = check_sat!(); let sat = check_sat!(); if sat { }").is_ok());
+    assert!(build("let sat = check_sat!(); { let sat = check_sat!(); } if sat { }").is_ok());
+    // Blocks are scopes.
+    let err = build("{ let sat = check_sat!(); } if sat { }")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown meta-variable `sat`");
+}
+
+#[test]
+fn reserved_idents() {
+    assert!(rules::ident("forall").is_err());
+    assert!(rules::ident("_").is_err());
+    // SMT-LIB commands and builtins are legal, they are mangled at SMT-level.
+    assert_eq!(*rules::ident("reset").unwrap(), "reset");
+    assert_eq!(*rules::ident("div").unwrap(), "div");
+}
+
+#[test]
+fn candidate_attributes() {
+    let sys = crate::parse::trans(
+        r#"
+svars { cnt: int }
+init { cnt = 0 }
+trans { 'cnt = cnt + 1 }
+candidates {
+    #[warn] #[tag(slow)]
+    "cnt is not 3": ¬(cnt = 3),
+    #[tag(critical, slow)]
+    "cnt is positive": cnt ≥ 0,
+}
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sys.severity_of("cnt is not 3"),
+        crate::trans::Severity::Warning
+    );
+    assert_eq!(
+        sys.severity_of("cnt is positive"),
+        crate::trans::Severity::Error
+    );
+    let tags: Vec<_> = sys.tags_of("cnt is positive").collect();
+    assert_eq!(tags, vec!["critical", "slow"]);
+
+    // Annotations survive restriction.
+    let sys = sys.restrict(|name| name != "cnt is positive");
+    assert!(sys.warnings().contains("cnt is not 3"));
+    assert_eq!(sys.tags().len(), 1);
+}
+
+#[test]
+fn labels() {
+    use crate::{
+        ast::script::{Command, MLet, Query},
+        meta::Label,
+    };
+    let sys = crate::parse::trans(
+        r#"
+svars { cnt: int }
+init { cnt = 0 }
+trans { 'cnt = cnt + 1 }
+candidates {
+    /// Counter never reaches 3.
+    #[warn]
+    "cnt is not 3": ¬(cnt = 3),
+    "cnt is positive": cnt ≥ 0,
+}
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sys.label_of("cnt is not 3"),
+        Some(&Label::new("Counter never reaches 3."))
+    );
+    assert!(sys.label_of("cnt is positive").is_none());
+    // Labels survive restriction.
+    let sys = sys.restrict(|name| name != "cnt is positive");
+    assert_eq!(sys.labels().len(), 1);
+
+    let label = |cmd: &str| match super::rules::command(cmd).unwrap().unwrap() {
+        Command::Query(Query::CheckSat(check)) => check.label,
+        Command::MLet(MLet { rhs, .. }) => rhs.label,
+        Command::Query(Query::Ite(ite)) => ite.cnd.right().unwrap().label,
+        cmd => panic!("unexpected command {:?}", cmd),
+    };
+    assert_eq!(
+        label("/// Is it sat?\ncheck_sat!()"),
+        Some(Label::new("Is it sat?"))
+    );
+    assert_eq!(
+        label("/// Bounded.\n///\n/// Needs `n < 10`.\nlet sat = check_sat!();"),
+        Some(Label::new("Bounded.").description("Needs `n < 10`."))
+    );
+    assert_eq!(
+        label("/// Unbounded.\nif check_sat!() { } else { }"),
+        Some(Label::new("Unbounded."))
+    );
+    assert_eq!(label("check_sat!()"), None);
+}
+
+#[test]
+fn frame_conditions() {
+    let sys = crate::parse::trans(
+        r#"
+svars { x y z: int, b: bool }
+init { x = 0, y = 0, z = 0 }
+trans { 'x = x + 1, unchanged { y }, frame except { x, y } }
+candidates { "x": x ≥ 0 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sys.trans().to_string(),
+        "(and (= x@1 (+ x@0 1)) (= y@1 y@0) (= b@1 b@0) (= z@1 z@0))"
+    );
+    assert!(sys.unconstrained().is_empty());
+
+    // Frame conditions only.
+    let sys = crate::parse::trans(
+        r#"
+svars { x: int }
+init { x = 0 }
+trans { unchanged { x } }
+candidates { "x": x = 0 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(sys.trans().to_string(), "(= x@1 x@0)");
+
+    let res = crate::parse::trans(
+        r#"
+svars { x: int }
+init { x = 0 }
+trans { unchanged { y } }
+candidates { "x": x = 0 }
+"#,
+    );
+    match res {
+        Ok(_) => panic!("expected an unknown variable error"),
+        Err(e) => assert!(e.to_string().contains("unknown variable `y`")),
+    }
+}
+
+#[test]
+fn smt2_scripts() {
+    use crate::ast::script::{Command, Restore, Snapshot};
+    let build = |txt: &str| {
+        crate::script::build::of_smt2_str(txt)
+            .map_err(|e| e.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+    };
+
+    // Nested applications of non-associative operators are not flattened.
+    let mut decls = trans::Decls::new();
+    for var in ["a", "b", "c"] {
+        let _ = decls.register(var, Typ::Int);
+    }
+    let decls = &decls;
+    let expr = |txt| {
+        super::rules::smt2_expr(txt)
+            .unwrap()
+            .to_expr(decls)
+            .unwrap()
+    };
+    assert_eq!(
+        expr("(= (- a (- b c)) 0)").to_string(),
+        "(= (- a (- b c)) 0)"
+    );
+    assert_eq!(expr("(+ a (+ b c))").to_string(), "(+ a b c)");
+    let hsmt = parse::expr("a - (b - c) = 0", decls).unwrap();
+    assert_eq!(hsmt.to_string(), "(= (- a (- b c)) 0)");
+
+    // Push and pop are snapshots and restores.
+    let block = parse::smt2_script("(push 2) (pop 1) (push) (pop 2)").unwrap();
+    let snapshot = |name: &str, span| Command::from(Snapshot::new(span, Spn::new(name, span)));
+    let restore = |name: &str, span| Command::from(Restore::new(span, Spn::new(name, span)));
+    assert_eq!(
+        block.content,
+        vec![
+            snapshot("smt2_push_0", (1, 5)),
+            snapshot("smt2_push_1", (1, 5)),
+            restore("smt2_push_1", (10, 13)),
+            snapshot("smt2_push_1", (18, 22)),
+            restore("smt2_push_0", (25, 28)),
+        ]
+    );
+    let err = build("(push 1) (pop 2)").err().unwrap();
+    assert_eq!(err[1], "cannot pop 2 level(s), only 1 level(s) pushed");
+    // Declarations are scoped by push and pop.
+    let err = build("(push 1) (declare-const n Int) (pop 1) (assert (> n 0))")
+        .err()
+        .unwrap();
+    assert_eq!(err[1], "unknown variable `n`");
+    assert!(build("(reset) (push 1) (declare-const n Int) (pop 1) (declare-const n Bool)").is_ok());
+
+    // Sorts must be supported.
+    let err = build("(declare-const l (List Int))").err().unwrap();
+    assert!(err[1].starts_with("expected SMT-LIB 2 sort"), "{:?}", err);
+    let err = build("(define-fun two () Int 2)").err().unwrap();
+    assert_eq!(err[1], "unsupported SMT-LIB 2 command `define-fun`");
+    let ok = build(
+        "\
+        (set-option :produce-models true)
+        (declare-const |so free| (_ BitVec 8))
+        (assert (= (bvand |so free| #x0f) #b00000001))
+        (check-sat-assuming ())
+        (get-value (|so free| (bvor |so free| #xf0)))
+        (get-unsat-core)
+        (echo \"done\")
+        (exit)",
+    );
+    assert!(ok.is_ok(), "{:?}", ok);
+}
+
+#[test]
+fn assumptions() {
+    let sys = parse::trans(
+        r#"
+svars { cnt: int, inc: int }
+init { cnt = 0 }
+assumptions { inc ≥ 0, inc ≤ 3 }
+trans { 'cnt = cnt + inc }
+candidates { "cnt is positive": cnt ≥ 0 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sys.init().to_string(),
+        "(and (= cnt 0) (>= inc 0) (<= inc 3))"
+    );
+    assert_eq!(
+        sys.trans().to_string(),
+        "(and (>= inc@0 0) (<= inc@0 3) (>= inc@1 0) (<= inc@1 3) (= cnt@1 (+ cnt@0 inc@0)))"
+    );
+
+    let err = parse::trans(
+        r#"
+svars { cnt: int }
+init { cnt = 0 }
+assumptions { 'cnt ≥ 0 }
+trans { 'cnt = cnt + 1 }
+candidates { "cnt is positive": cnt ≥ 0 }
+"#,
+    )
+    .err()
+    .unwrap();
+    let err: Vec<_> = err.iter().map(|e| e.to_string()).collect();
+    assert_eq!(err[1], "illegal *next* modifier");
+}
+
+#[test]
+fn liveness() {
+    use crate::check::liveness::Property;
+
+    let sys = parse::trans(
+        r#"
+svars { cnt: int, reset: bool }
+init { cnt = 0 }
+trans { 'cnt = if 'reset { 0 } else { cnt + 1 } }
+candidates { "cnt is positive": cnt ≥ 0 }
+liveness {
+    "cnt grows": eventually cnt > 3,
+    "resets": infinitely_often reset ⋁ cnt = 0,
+}
+"#,
+    )
+    .unwrap();
+    let props: Vec<_> = sys.liveness().iter().collect();
+    assert_eq!(props.len(), 2);
+    assert_eq!(props[0].0, "cnt grows");
+    assert!(matches!(props[0].1, Property::Eventually(_)));
+    assert_eq!(props[0].1.expr().to_string(), "(> cnt 3)");
+    assert_eq!(props[1].0, "resets");
+    assert!(matches!(props[1].1, Property::InfinitelyOften(_)));
+    assert_eq!(props[1].1.expr().to_string(), "(or reset (= cnt 0))");
+
+    let hsmt = sys.to_hsmt().unwrap();
+    assert!(hsmt.ends_with(
+        "liveness {\n    \"cnt grows\": eventually cnt > 3,\n    \
+        \"resets\": infinitely_often reset ⋁ cnt = 0,\n}\n"
+    ));
+    let reparsed = parse::trans(&hsmt).unwrap();
+    assert_eq!(reparsed.liveness(), sys.liveness());
+
+    let run = |liveness: &str| {
+        let txt = format!(
+            "svars {{ cnt: int }} init {{ cnt = 0 }} trans {{ 'cnt = cnt + 1 }} \
+            candidates {{ \"positive\": cnt ≥ 0 }} liveness {{ {} }}",
+            liveness
+        );
+        let err = parse::trans(&txt).err().unwrap();
+        err.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+    };
+    let err = run(r#""grows": eventually cnt + 1"#);
+    assert_eq!(err[1], "expected a boolean expression, got `int`");
+    assert_eq!(err[2], "in liveness property `grows`");
+    let err = run(r#""positive": eventually cnt > 1"#);
+    assert_eq!(err[1], "a candidate with this name is already defined");
+    let err = run(r#""grows": eventually cnt > 1, "grows": eventually cnt > 2"#);
+    assert_eq!(
+        err[1],
+        "a liveness property with this name is already defined"
+    );
+    let err = run(r#""grows": always cnt > 1"#);
+    assert_eq!(
+        err[1],
+        "expected `eventually <expr>` or `infinitely_often <expr>`"
+    );
+}
+
+#[test]
+fn ltl() {
+    let sys = parse::trans(
+        r#"
+svars { req ack: bool, n: int }
+init { ¬ack ⋀ n = 0 }
+trans { 'ack = req, 'n = n + 1 }
+candidates { "positive": n ≥ 0 }
+liveness { "grows": eventually n > 3 }
+ltl {
+    "response": G ({req} ⇒ X F {ack ⋁ if n > 7 { ack } else { in_range(n, 0, 7) }}),
+    "fair": G F {req},
+}
+"#,
+    )
+    .unwrap();
+    let formulas: Vec<_> = sys
+        .ltl()
+        .iter()
+        .map(|(name, ltl)| format!("{}: {}", name, ltl))
+        .collect();
+    assert_eq!(
+        formulas,
+        vec![
+            "fair: G F { req }",
+            "response: G ({ req } ⇒ X F { ack ⋁ if n > 7 { ack } else { 0 ≤ n ⋀ n ≤ 7 } })",
+        ]
+    );
+    let hsmt = sys.to_hsmt().unwrap();
+    let reparsed = parse::trans(&hsmt).unwrap();
+    assert_eq!(reparsed.ltl(), sys.ltl());
+    assert_eq!(reparsed.liveness(), sys.liveness());
+
+    let run = |ltl: &str| {
+        let txt = format!(
+            "svars {{ n: int }} init {{ n = 0 }} trans {{ 'n = n + 1 }} \
+            candidates {{ \"positive\": n ≥ 0 }} liveness {{ \"grows\": eventually n > 3 }} \
+            ltl {{ {} }}",
+            ltl
+        );
+        let err = parse::trans(&txt).err().unwrap();
+        err.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+    };
+    let err = run(r#""int": G {n + 1}"#);
+    assert_eq!(err[1], "LTL atom has type `int`, expected `bool`");
+    assert_eq!(err[2], "in LTL property `int`");
+    let err = run(r#""unclosed": G ({n > 0}"#);
+    assert_eq!(err[1], r#"expected one of ",", "}""#);
+    let err = run(r#""dangling": G {n > 0} U"#);
+    assert_eq!(
+        err[1],
+        "parse error at 1:12: expected an LTL formula | G {n > 0} U<EOI>"
+    );
+    assert_eq!(err[2], "in LTL property `dangling`");
+    for name in ["positive", "grows"] {
+        let err = run(&format!(r#""{}": G {{n > 0}}"#, name));
+        assert_eq!(
+            err[1],
+            "a candidate or property with this name is already defined"
+        );
+    }
+}
+
+#[test]
+fn instances() {
+    let sys = parse::trans(
+        r#"
+subsystems {
+    Counter {
+        svars { reset: bool, cnt: int }
+        init { cnt = 0 }
+        trans { 'cnt = if 'reset { 0 } else { cnt + 1 } }
+    }
+    Pair {
+        svars { reset: bool }
+        instances { fst: Counter(reset = reset), snd: Counter(reset = reset) }
+        init { ⊤ }
+        trans { ⊤ }
+    }
+}
+svars { rst: bool }
+instances { left: Counter(reset = rst), both: Pair(reset = rst) }
+init { ¬rst }
+trans { 'rst = ¬rst }
+candidates { "same counts": left.cnt = both.fst.cnt }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sys.decls().to_string(),
+        "both.fst.cnt both.snd.cnt left.cnt: int,\nrst: bool,"
+    );
+    assert_eq!(
+        sys.init().to_string(),
+        "(and (not rst) (= left.cnt 0) (= both.fst.cnt 0) (= both.snd.cnt 0))"
+    );
+    assert_eq!(
+        sys.trans().to_string(),
+        "(and \
+            (= rst@1 (not rst@0)) \
+            (= left.cnt@1 (ite rst@1 0 (+ left.cnt@0 1))) \
+            (= both.fst.cnt@1 (ite rst@1 0 (+ both.fst.cnt@0 1))) \
+            (= both.snd.cnt@1 (ite rst@1 0 (+ both.snd.cnt@0 1)))\
+        )"
+    );
+    assert_eq!(
+        sys.po_s()["same counts"].to_string(),
+        "(= left.cnt both.fst.cnt)"
+    );
+
+    let err = parse::trans(
+        r#"
+subsystems {
+    Counter {
+        svars { reset: bool, cnt: int }
+        init { cnt = 0 }
+        trans { 'cnt = if 'reset { 0 } else { cnt + 1 } }
+    }
+}
+svars { rst: int }
+instances { left: Counter(reset = rst) }
+init { rst = 0 }
+trans { 'rst = rst }
+candidates { "positive": left.cnt ≥ 0 }
+"#,
+    )
+    .err()
+    .unwrap();
+    let err: Vec<_> = err.iter().map(|e| e.to_string()).collect();
+    assert_eq!(
+        err[1],
+        "port `reset` has type `bool`, cannot connect it to `rst` of type `int`"
+    );
+}
+
+#[test]
+fn params() {
+    let sys = parse::trans(
+        r#"
+svars { cnt: int }
+params { N: int where N > 0, M: int }
+init { cnt = N }
+trans { 'cnt = if cnt ≥ M { N } else { cnt + 1 } }
+candidates { "cnt is positive": cnt > 0 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(sys.decls().to_string(), "M N cnt: int,");
+    assert_eq!(sys.init().to_string(), "(and (= cnt N) (> N 0))");
+    assert_eq!(
+        sys.trans().to_string(),
+        "(and \
+            (= N@1 N@0) \
+            (= M@1 M@0) \
+            (> N@0 0) \
+            (> N@1 0) \
+            (= cnt@1 (ite (>= cnt@0 M@0) N@0 (+ cnt@0 1)))\
+        )"
+    );
+
+    let err = parse::trans(
+        r#"
+svars { N: int }
+params { N: int }
+init { N = 0 }
+trans { 'N = N }
+candidates { "positive": N ≥ 0 }
+"#,
+    )
+    .err()
+    .unwrap();
+    let err: Vec<_> = err.iter().map(|e| e.to_string()).collect();
+    assert_eq!(err[1], "variable `N` is already declared");
+}
+
+#[test]
+fn roles() {
+    use crate::{check::cexs::Cex, expr::display::Formatters, trans::Role};
+
+    let txt = r#"
+inputs { enum Mode { Up, Down }, mode: Mode }
+state { cnt: int }
+outputs { zero: bool }
+svars { other: bool }
+init { cnt = 0, zero, other }
+trans { 'cnt = if 'mode = Mode::Up { cnt + 1 } else { cnt - 1 }, 'zero = ('cnt = 0), 'other }
+candidates { "cnt is positive": cnt ≥ 0 }
+"#;
+    let sys = parse::trans(txt).unwrap();
+    let decls = sys.decls();
+    assert_eq!(decls.role("mode"), Some(Role::Input));
+    assert_eq!(decls.role("cnt"), Some(Role::State));
+    assert_eq!(decls.role("zero"), Some(Role::Output));
+    assert_eq!(decls.role("other"), None);
+    assert!(sys.unconstrained().is_empty());
+
+    let hsmt = sys.to_hsmt().unwrap();
+    assert!(hsmt.starts_with(
+        "svars {\n    enum Mode { Up, Down },\n    other: bool,\n}\n\
+        inputs {\n    mode: Mode,\n}\n\
+        state {\n    cnt: int,\n}\n\
+        outputs {\n    zero: bool,\n}\n"
+    ));
+    assert_eq!(parse::trans(&hsmt).unwrap().decls(), decls);
+
+    let mut cex = Cex::new();
+    for var in decls.all() {
+        let val = match var.id() {
+            "mode" => expr::Cst::variant("Mode", "Up"),
+            "cnt" => expr::Cst::int(1),
+            _ => expr::Cst::bool(false),
+        };
+        cex.insert(0, var, val).unwrap();
+    }
+    assert_eq!(
+        cex.render(decls, &Formatters::new()),
+        "\
+step 0
+    // inputs
+     mode = Mode::Up
+    // state
+      cnt = 1
+    // outputs
+     zero = false
+    // svars
+    other = false
+",
+    );
+
+    let err = parse::trans(
+        r#"
+inputs { x: int }
+state { x: int }
+init { x = 0 }
+trans { 'x = x }
+candidates { "positive": x ≥ 0 }
+"#,
+    )
+    .err()
+    .unwrap();
+    let err: Vec<_> = err.iter().map(|e| e.to_string()).collect();
+    assert_eq!(err[1], "variable `x` is already declared");
+}
+
+#[test]
+fn tokens() {
+    use super::Token::*;
+
+    for txt in [crate::TRANS_DEMO, crate::SCRIPT_DEMO] {
+        let mut end = 0;
+        for tok in super::tokens(txt) {
+            assert_ne!(tok.inner, Unknown, "at {:?}", tok.span);
+            assert!(txt[end..tok.span.start].trim().is_empty());
+            assert!(tok.span.start < tok.span.end);
+            end = tok.span.end;
+        }
+        assert!(txt[end..].trim().is_empty());
+    }
+
+    let run = |txt: &str, expected: &[(&str, Token)]| {
+        let toks: Vec<_> = super::tokens(txt)
+            .into_iter()
+            .map(|tok| (&txt[tok.span.start..tok.span.end], tok.inner))
+            .collect();
+        assert_eq!(toks, expected);
+    };
+    run(
+        "x | y | |so free|",
+        &[
+            ("x", Ident),
+            ("|", Operator),
+            ("y", Ident),
+            ("|", Operator),
+            ("|so free|", Ident),
+        ],
+    );
+    run(
+        "funs { f: (int, bv<8>) -> bool }",
+        &[
+            ("funs", Keyword),
+            ("{", Punctuation),
+            ("f", Ident),
+            (":", Punctuation),
+            ("(", Punctuation),
+            ("int", Type),
+            (",", Punctuation),
+            ("bv", Type),
+            ("<", Operator),
+            ("8", Literal),
+            (">", Operator),
+            (")", Punctuation),
+            ("->", Punctuation),
+            ("bool", Type),
+            ("}", Punctuation),
+        ],
+    );
+    run(
+        r#"#[warn] "a \"b\"": c::d ⋀ 7bv8 >= 1.5 ⇒ ⊤ and not false"#,
+        &[
+            ("#", Punctuation),
+            ("[", Punctuation),
+            ("warn", Keyword),
+            ("]", Punctuation),
+            (r#""a \"b\"""#, Literal),
+            (":", Punctuation),
+            ("c", Ident),
+            ("::", Punctuation),
+            ("d", Ident),
+            ("⋀", Operator),
+            ("7bv8", Literal),
+            (">=", Operator),
+            ("1.5", Literal),
+            ("⇒", Operator),
+            ("⊤", Literal),
+            ("and", Operator),
+            ("not", Operator),
+            ("false", Literal),
+        ],
+    );
+    run(
+        "/// doc\nfor i in 0..=3 { check_sat!() } println! { \"done\" } @",
+        &[
+            ("/// doc", Comment),
+            ("for", Keyword),
+            ("i", Ident),
+            ("in", Keyword),
+            ("0", Literal),
+            ("..", Punctuation),
+            ("=", Operator),
+            ("3", Literal),
+            ("{", Punctuation),
+            ("check_sat!", Keyword),
+            ("(", Punctuation),
+            (")", Punctuation),
+            ("}", Punctuation),
+            ("println!", Keyword),
+            ("{", Punctuation),
+            ("\"done\"", Literal),
+            ("}", Punctuation),
+            ("@", Unknown),
+        ],
+    );
+    run(
+        "reset() if reset { reset!() }",
+        &[
+            ("reset", Keyword),
+            ("(", Punctuation),
+            (")", Punctuation),
+            ("if", Keyword),
+            ("reset", Ident),
+            ("{", Punctuation),
+            ("reset!", Keyword),
+            ("(", Punctuation),
+            (")", Punctuation),
+            ("}", Punctuation),
+        ],
+    );
+    // Unterminated strings run to the end of the text.
+    run(
+        "echo!(\"oops)",
+        &[("echo!", Keyword), ("(", Punctuation), ("\"oops)", Literal)],
+    );
 }
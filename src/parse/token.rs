@@ -0,0 +1,305 @@
+//! Lexer-level tokens of hsmt systems and scripts, for syntax highlighting.
+//!
+//! [`tokens`] splits some text into [`Token`]s without parsing it: it never fails, and text that
+//! no token recognizes yields [`Token::Unknown`]. Whitespaces are skipped.
+//!
+//! Words are classified regardless of their context, *e.g.* a variable named `tag` is a keyword
+//! token. There are two exceptions:
+//!
+//! - script commands are keywords only when followed by `!` or `(`, as in `check_sat!()` or
+//!   `reset()`, so that variables such as `reset` are identifiers;
+//! - `|` is the bitvector disjunction after an identifier, a literal or a closing parenthesis,
+//!   and starts a quoted identifier otherwise.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use mikino_api::parse::{tokens, Token};
+//! let txt = "// counter\ntrans { 'cnt = if reset { 0 } else { cnt + 1 } }";
+//! let toks: Vec<_> = tokens(txt)
+//!     .into_iter()
+//!     .map(|tok| (&txt[tok.span.start..tok.span.end], tok.inner))
+//!     .collect();
+//! assert_eq!(
+//!     toks,
+//!     vec![
+//!         ("// counter", Token::Comment),
+//!         ("trans", Token::Keyword),
+//!         ("{", Token::Punctuation),
+//!         ("'", Token::Operator),
+//!         ("cnt", Token::Ident),
+//!         ("=", Token::Operator),
+//!         ("if", Token::Keyword),
+//!         ("reset", Token::Ident),
+//!         ("{", Token::Punctuation),
+//!         ("0", Token::Literal),
+//!         ("}", Token::Punctuation),
+//!         ("else", Token::Keyword),
+//!         ("{", Token::Punctuation),
+//!         ("cnt", Token::Ident),
+//!         ("+", Token::Operator),
+//!         ("1", Token::Literal),
+//!         ("}", Token::Punctuation),
+//!         ("}", Token::Punctuation),
+//!     ],
+//! );
+//! ```
+
+crate::prelude!();
+
+use super::{Span, Spn};
+
+/// Kinds of tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Token {
+    /// Keywords of systems and scripts, including builtin functions such as `select`, and script
+    /// commands with their `!` if any, as in `check_sat!`.
+    ///
+    /// Script commands are only keywords when followed by `!` or `(`.
+    Keyword,
+    /// Builtin types.
+    Type,
+    /// Operators, including the `and`/`or`/`not` keywords and the `'` next state modifier.
+    Operator,
+    /// Identifiers, quoted or not.
+    Ident,
+    /// Boolean (including `⊤` and `⊥`), integer, decimal and bitvector constants, and double-quoted
+    /// strings.
+    Literal,
+    /// Comments, including outer `///` and inner `//!` documentation.
+    Comment,
+    /// Braces, parentheses, brackets, `,`, `:`, `;`, `::`, `.`, `..`, `->` and `#`.
+    Punctuation,
+    /// Anything else.
+    Unknown,
+}
+impl Token {
+    /// All the kinds of tokens.
+    pub const ALL: [Self; 8] = [
+        Self::Keyword,
+        Self::Type,
+        Self::Operator,
+        Self::Ident,
+        Self::Literal,
+        Self::Comment,
+        Self::Punctuation,
+        Self::Unknown,
+    ];
+
+    /// Name of the kind of token, *e.g.* for CSS classes.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Type => "type",
+            Self::Operator => "operator",
+            Self::Ident => "ident",
+            Self::Literal => "literal",
+            Self::Comment => "comment",
+            Self::Punctuation => "punctuation",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+impl fmt::Display for Token {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(fmt)
+    }
+}
+
+/// Keywords of systems.
+const SYS_KEYWORDS: &[&str] = &[
+    "svars",
+    "inputs",
+    "outputs",
+    "state",
+    "params",
+    "where",
+    "funs",
+    "enum",
+    "subsystems",
+    "instances",
+    "init",
+    "assumptions",
+    "trans",
+    "candidates",
+    "liveness",
+    "eventually",
+    "infinitely_often",
+    "ltl",
+    "unchanged",
+    "frame",
+    "except",
+    "tag",
+    "warn",
+];
+/// Keywords of expressions, including builtin functions.
+const EXPR_KEYWORDS: &[&str] = &[
+    "if",
+    "else",
+    "forall",
+    "exists",
+    "select",
+    "store",
+    "at_most_one",
+    "exactly_one",
+    "in_range",
+    "monotonic",
+];
+/// Keywords of scripts that are not commands.
+const SCRIPT_KEYWORDS: &[&str] = &[
+    "vars",
+    "assert",
+    "let",
+    "otherwise",
+    "repeat",
+    "for",
+    "in",
+    "assuming",
+    "set_option",
+    "set_options",
+];
+/// Script commands, keywords only when followed by `!` or `(`.
+const COMMANDS: &[&str] = &[
+    "check_sat",
+    "get_model",
+    "get_mus",
+    "get_value",
+    "get_values",
+    "unsat_core",
+    "eval",
+    "echo",
+    "println",
+    "panic",
+    "exit",
+    "reset",
+    "push",
+    "pop",
+    "snapshot",
+    "restore",
+    "maximize",
+    "minimize",
+];
+/// Builtin types.
+const TYPES: &[&str] = &["int", "rat", "bool", "bv", "array"];
+/// Keyword operators.
+const WORD_OPERATORS: &[&str] = &["and", "or", "not"];
+
+/// Symbol operators, longest first so that `<=` is not lexed as `<` and `=`.
+const OPERATORS: &[&str] = &[
+    "=>", "<=", ">=", "&&", "||", "<<", ">>", "⇒", "→", "⊃", "∨", "⋁", "∧", "⋀", "≤", "≥", "¬",
+    "+", "-", "*", "/", "%", "<", ">", "=", "&", "|", "^", "!", "'",
+];
+/// Punctuation, longest first.
+const PUNCTUATION: &[&str] = &[
+    "::", "..", "->", "{", "}", "(", ")", "[", "]", ",", ":", ";", ".", "#",
+];
+
+/// Splits some text into tokens, see the [module-level documentation](self).
+pub fn tokens(txt: &str) -> Vec<Spn<Token>> {
+    let mut res: Vec<Spn<Token>> = vec![];
+    let mut pos = 0;
+    while let Some(c) = txt[pos..].chars().next() {
+        let rest = &txt[pos..];
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+        // After an operand, `|` is an operator rather than the start of a quoted identifier.
+        let after_operand = res.last().is_some_and(|last| match last.inner {
+            Token::Ident | Token::Literal => true,
+            Token::Punctuation => &txt[last.span.start..last.span.end] == ")",
+            _ => false,
+        });
+        let (token, len) = if rest.starts_with("//") {
+            (Token::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if c == '"' {
+            (Token::Literal, string_len(rest))
+        } else if let Some(len) = quoted_ident_len(rest).filter(|_| !after_operand) {
+            (Token::Ident, len)
+        } else if c.is_ascii_digit() {
+            (Token::Literal, number_len(rest))
+        } else if c == '⊤' || c == '⊥' {
+            (Token::Literal, c.len_utf8())
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            word(rest)
+        } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(*punct)) {
+            // Before operators so that `->` is not lexed as `-` and `>`.
+            (Token::Punctuation, punct.len())
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            (Token::Operator, op.len())
+        } else {
+            (Token::Unknown, c.len_utf8())
+        };
+        res.push(Spn::new(token, Span::new(pos, pos + len)));
+        pos += len;
+    }
+    res
+}
+
+/// Length of a double-quoted string, up to the end of the text if it is not closed.
+///
+/// As in [`super::rules::dbl_quoted`], `\"` is the only escape sequence.
+fn string_len(rest: &str) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return idx + 1,
+            '\\' if rest[idx + 1..].starts_with('"') => {
+                let _ = chars.next();
+            }
+            _ => (),
+        }
+    }
+    rest.len()
+}
+
+/// Length of a quoted identifier, if any.
+fn quoted_ident_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix('|')?;
+    let end = body.find(&['|', '\\'] as &[char])?;
+    if body[end..].starts_with('|') {
+        Some(end + 2)
+    } else {
+        None
+    }
+}
+
+/// Length of an integer, decimal or bitvector constant.
+fn number_len(rest: &str) -> usize {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let mut len = digits(rest);
+    if rest[len..].starts_with("bv") && rest[len + 2..].starts_with(|c: char| c.is_ascii_digit()) {
+        len += 2;
+        len += digits(&rest[len..]);
+    } else if rest[len..].starts_with('.') && !rest[len..].starts_with("..") {
+        len += 1;
+        len += digits(&rest[len..]);
+    }
+    len
+}
+
+/// Token and length of a word.
+fn word(rest: &str) -> (Token, usize) {
+    let len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let word = &rest[..len];
+    let is = |words: &[&str]| words.contains(&word);
+    if word == "true" || word == "false" {
+        (Token::Literal, len)
+    } else if is(WORD_OPERATORS) {
+        (Token::Operator, len)
+    } else if is(TYPES) {
+        (Token::Type, len)
+    } else if is(COMMANDS) && rest[len..].starts_with('!') {
+        (Token::Keyword, len + 1)
+    } else if is(SYS_KEYWORDS)
+        || is(EXPR_KEYWORDS)
+        || is(SCRIPT_KEYWORDS)
+        || (is(COMMANDS) && rest[len..].trim_start().starts_with('('))
+    {
+        (Token::Keyword, len)
+    } else {
+        (Token::Ident, len)
+    }
+}
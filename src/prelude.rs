@@ -17,7 +17,7 @@ pub use num::{bigint::Sign, BigInt as Int, BigRational as Rat, One, Zero};
 pub use rsmt2::{parse::SmtParser as RSmtParser, SmtConf, SmtRes, Solver as SmtSolver};
 
 pub use crate::{
-    ast, build_decls, build_expr, build_trans, build_typ, check,
+    ast, build_decls, build_expr, build_trans, build_typ, check, decls,
     err::*,
     expr::{self, HasTyp, Typ},
     parse::{self, Span, Spn},
@@ -5,10 +5,16 @@ prelude!(
     expr::{Expr, MExpr},
 );
 
+use crate::{check::trace::json_str, meta::Label};
 use frame::Frame;
 
 pub mod build;
 pub mod frame;
+#[cfg(feature = "jupyter")]
+pub mod jupyter;
+pub mod session;
+
+pub use session::{Cell, Session};
 
 const DEBUG: bool = false;
 
@@ -27,6 +33,9 @@ fn pos_pref(style: impl Style, activate: bool, desc: &str, line: usize) -> Strin
     }
 }
 
+/// Model retrieved value by value, in the format of rsmt2's models.
+type SalvagedModel = Vec<(String, Vec<(String, Typ)>, Typ, expr::Cst)>;
+
 /// Result of running a script.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Outcome {
@@ -71,8 +80,8 @@ impl Outcome {
 /// A step result.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Step {
-    /// Check sat result.
-    CheckRes(parse::Span, CheckSatResEnum),
+    /// Check sat result, with the label of the check sat if any.
+    CheckRes(parse::Span, CheckSatResEnum, Option<Label>),
     /// A model.
     Model {
         /// Command span.
@@ -81,6 +90,13 @@ pub enum Step {
         token: String,
         /// Model.
         model: Map<String, (expr::Cst, Typ)>,
+        /// Rendered values for the variables that have a display hint.
+        display: Map<String, String>,
+        /// Reason why the model is partial, `None` if it is complete.
+        ///
+        /// When `get_model` fails, the values of the declared variables are requested one by one
+        /// until the solver fails again.
+        incomplete: Option<String>,
     },
     /// An evaluation.
     Eval {
@@ -91,6 +107,37 @@ pub enum Step {
         /// Values.
         vals: Vec<(String, expr::Cst)>,
     },
+    /// An optimum.
+    Optimum {
+        /// Command span.
+        span: parse::Span,
+        /// Token used to invoke the command.
+        token: String,
+        /// User-representation of the objective.
+        objective: String,
+        /// True for a maximum, false for a minimum.
+        maximize: bool,
+        /// Optimal value, `None` if the assertions are unsatisfiable.
+        value: Option<expr::Cst>,
+    },
+    /// A minimal unsatisfiable subset of the assertions.
+    Mus {
+        /// Command span.
+        span: parse::Span,
+        /// Token used to invoke the command.
+        token: String,
+        /// Assertions in the subset.
+        exprs: Vec<String>,
+    },
+    /// An unsat core.
+    UnsatCore {
+        /// Command span.
+        span: parse::Span,
+        /// Token used to invoke the command.
+        token: String,
+        /// Names of the assertions in the core, and the assertions.
+        core: Vec<(String, String)>,
+    },
     /// Something to print.
     Echo(Echo),
     /// Nothing observable happened.
@@ -116,7 +163,7 @@ impl Step {
     pub fn pretty(&self, txt: &str, style: impl Style, with_pos: bool) -> Option<String> {
         let pos = |desc, line: usize| pos_pref(&style, with_pos, desc, line);
         let s = match self {
-            Self::CheckRes(span, check_res) => {
+            Self::CheckRes(span, check_res, label) => {
                 let (_, line, _, _, _) = span.pretty_of(txt);
                 let res = match check_res {
                     CheckSatResEnum::True => style.green("sat"),
@@ -124,7 +171,15 @@ impl Step {
                     CheckSatResEnum::Timeout => style.red("timeout"),
                     CheckSatResEnum::Unknown => style.red("unknown"),
                 };
-                format!("{}{}", pos("check_sat", line), res,)
+                match label {
+                    Some(label) => format!(
+                        "{}{} {}",
+                        pos("check_sat", line),
+                        res,
+                        style.gray(&format!("// {}", label.title))
+                    ),
+                    None => format!("{}{}", pos("check_sat", line), res,),
+                }
             }
             Self::Echo(msg) => {
                 if msg.msg.is_empty() {
@@ -136,7 +191,13 @@ impl Step {
                     format!("{}{}", pos(&msg.token, line), cmt)
                 }
             }
-            Self::Model { span, token, model } => {
+            Self::Model {
+                span,
+                token,
+                model,
+                display,
+                incomplete,
+            } => {
                 let (_, line, _, _, _) = span.pretty_of(txt);
                 let mut s = format!("{}model {{", pos(token, line),);
                 let max_id_len = model.keys().fold(0, |max, id| max.max(id.len()));
@@ -147,13 +208,20 @@ impl Step {
                     }
                     s.push_str(&style.bold(id).to_string());
                     s.push_str(": ");
-                    s.push_str(&cst.to_string());
+                    match display.get(id) {
+                        Some(val) => s.push_str(val),
+                        None => s.push_str(&cst.to_string()),
+                    }
                     s.push_str(",");
                 }
                 if model.len() > 0 {
                     s.push_str("\n");
                 }
                 s.push_str("}");
+                if let Some(reason) = incomplete {
+                    let cmt = format!("// incomplete model: {}", reason);
+                    s.push_str(&format!("\n{}", style.gray(&cmt)));
+                }
                 s
             }
             Self::Eval { span, token, vals } => {
@@ -173,6 +241,66 @@ impl Step {
                 s.push_str("}");
                 s
             }
+            Self::Optimum {
+                span,
+                token,
+                objective,
+                maximize,
+                value,
+            } => {
+                let (_, line, _, _, _) = span.pretty_of(txt);
+                let kind = if *maximize { "maximum" } else { "minimum" };
+                let clean = Expr::clean_repr(objective);
+                match value {
+                    Some(value) => format!(
+                        "{}{} {{\n    {}\n        = {},\n}}",
+                        pos(token, line),
+                        kind,
+                        style.bold(&clean),
+                        value
+                    ),
+                    None => format!(
+                        "{}no {} for {}, the assertions are unsatisfiable",
+                        pos(token, line),
+                        kind,
+                        style.bold(&clean),
+                    ),
+                }
+            }
+            Self::Mus { span, token, exprs } => {
+                let (_, line, _, _, _) = span.pretty_of(txt);
+                let mut s = format!("{}mus {{", pos(token, line),);
+                for expr in exprs {
+                    s.push_str("\n    ");
+                    s.push_str(&style.bold(expr).to_string());
+                    s.push(',');
+                }
+                if !exprs.is_empty() {
+                    s.push('\n');
+                }
+                s.push('}');
+                s
+            }
+            Self::UnsatCore { span, token, core } => {
+                let (_, line, _, _, _) = span.pretty_of(txt);
+                let mut s = format!("{}unsat core {{", pos(token, line),);
+                let max_name_len = core.iter().fold(0, |max, (name, _)| max.max(name.len()));
+                for (name, expr) in core {
+                    s.push_str("\n    ");
+                    for _ in 0..(max_name_len - name.len()) {
+                        s.push(' ');
+                    }
+                    s.push_str(&style.bold(name).to_string());
+                    s.push_str(": ");
+                    s.push_str(expr);
+                    s.push(',');
+                }
+                if !core.is_empty() {
+                    s.push('\n');
+                }
+                s.push('}');
+                s
+            }
             Self::Nothing => {
                 return None;
             }
@@ -181,15 +309,121 @@ impl Step {
         Some(s)
     }
 
+    /// Values of a model, `None` if the step is not a model.
+    ///
+    /// Models are parsed from the solver's answer, so models from different runs or solvers can
+    /// be compared directly.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{expr::{Cst, Typ}, parse::Span, script::Step};
+    /// let mut model = std::collections::BTreeMap::new();
+    /// model.insert("n".to_string(), (Cst::int(7), Typ::Int));
+    /// model.insert("b".to_string(), (Cst::bool(true), Typ::Bool));
+    /// let step = Step::Model {
+    ///     span: Span::new(0, 9),
+    ///     token: "get_model".into(),
+    ///     model,
+    ///     display: Default::default(),
+    ///     incomplete: None,
+    /// };
+    /// let values = step.model().unwrap();
+    /// assert_eq!(values["n"], Cst::int(7));
+    /// assert_eq!(values.get("b"), Some(&Cst::bool(true)));
+    /// assert!(Step::Nothing.model().is_none());
+    /// ```
+    pub fn model(&self) -> Option<Map<String, expr::Cst>> {
+        match self {
+            Self::Model { model, .. } => Some(
+                model
+                    .iter()
+                    .map(|(id, (cst, _))| (id.clone(), cst.clone()))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// JSON representation of a model, `None` if the step is not a model.
+    ///
+    /// Values are strings, in the syntax of the [`Cst`](expr::Cst) display.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{expr::{Cst, Typ}, parse::Span, script::Step};
+    /// let mut model = std::collections::BTreeMap::new();
+    /// model.insert("n".to_string(), (Cst::int(7), Typ::Int));
+    /// let step = Step::Model {
+    ///     span: Span::new(0, 9),
+    ///     token: "get_model".into(),
+    ///     model,
+    ///     display: Default::default(),
+    ///     incomplete: None,
+    /// };
+    /// assert_eq!(
+    ///     step.model_to_json().unwrap(),
+    ///     r#"{"model":[{"var":"n","typ":"int","value":"7"}],"incomplete":null}"#,
+    /// );
+    /// assert!(Step::Nothing.model_to_json().is_none());
+    /// ```
+    pub fn model_to_json(&self) -> Option<String> {
+        let (model, incomplete) = match self {
+            Self::Model {
+                model, incomplete, ..
+            } => (model, incomplete),
+            _ => return None,
+        };
+        let mut s = String::from("{\"model\":[");
+        for (idx, (id, (cst, typ))) in model.iter().enumerate() {
+            if idx > 0 {
+                s.push(',');
+            }
+            s.push_str(&format!(
+                "{{\"var\":{},\"typ\":{},\"value\":{}}}",
+                json_str(id),
+                json_str(&typ.to_string()),
+                json_str(&cst.to_string())
+            ));
+        }
+        s.push_str("],\"incomplete\":");
+        match incomplete {
+            Some(reason) => s.push_str(&json_str(reason)),
+            None => s.push_str("null"),
+        }
+        s.push('}');
+        Some(s)
+    }
+
+    /// Span of the command that produced the step, if any.
+    pub fn span(&self) -> Option<parse::Span> {
+        match self {
+            Self::CheckRes(span, _, _)
+            | Self::Model { span, .. }
+            | Self::Eval { span, .. }
+            | Self::Optimum { span, .. }
+            | Self::Mus { span, .. }
+            | Self::UnsatCore { span, .. }
+            | Self::Done(Outcome::Panic(span, _)) => Some(*span),
+            Self::Echo(echo) => Some(echo.span),
+            Self::Done(Outcome::Exit(span, _)) => *span,
+            Self::Nothing => None,
+        }
+    }
+
     /// True if the step result is nothing.
     pub fn is_nothing(&self) -> bool {
         match self {
             Self::Nothing => true,
             Self::Echo(_)
-            | Self::CheckRes(_, _)
+            | Self::CheckRes(_, _, _)
             | Self::Done(_)
             | Self::Model { .. }
-            | Self::Eval { .. } => false,
+            | Self::Eval { .. }
+            | Self::Optimum { .. }
+            | Self::Mus { .. }
+            | Self::UnsatCore { .. } => false,
         }
     }
 }
@@ -200,7 +434,7 @@ impl From<Outcome> for Step {
 }
 impl From<CheckSatRes> for Step {
     fn from(c: CheckSatRes) -> Self {
-        Self::CheckRes(c.span, c.res)
+        Self::CheckRes(c.span, c.res, c.label)
     }
 }
 impl From<QueryRes> for Step {
@@ -243,6 +477,8 @@ pub struct CheckSatRes {
     pub span: parse::Span,
     /// Result.
     pub res: CheckSatResEnum,
+    /// Label of the check sat, if any.
+    pub label: Option<Label>,
 }
 impl CheckSatRes {
     /// Turns itself into a boolean, if possible.
@@ -269,7 +505,11 @@ impl CheckSatRes {
                 }
             }
         };
-        Ok(Self { span, res })
+        Ok(Self {
+            span,
+            res,
+            label: None,
+        })
     }
 }
 
@@ -302,7 +542,11 @@ impl QueryRes {
                 }
             }
         };
-        Ok(Self::CheckSat(CheckSatRes { span, res }))
+        Ok(Self::CheckSat(CheckSatRes {
+            span,
+            res,
+            label: None,
+        }))
     }
 }
 
@@ -341,6 +585,572 @@ impl<'s, E, ME> From<&'s CheckSat> for CurrentCmd<'s, E, ME> {
     }
 }
 
+/// An objective being optimized, see [`Script::optimize`].
+///
+/// Scores are values when minimizing, and opposites of values when maximizing.
+struct Objective<'e> {
+    /// Objective, an integer or a bitvector.
+    expr: &'e Expr,
+    /// True when maximizing.
+    maximize: bool,
+    /// Lowest score, for bitvectors.
+    lowest: Option<Int>,
+}
+impl<'e> Objective<'e> {
+    /// Constructor, fails on objectives that are neither integers nor bitvectors.
+    fn new(expr: &'e Expr, maximize: bool) -> Res<Self> {
+        let lowest = match expr.typ() {
+            Typ::Int => None,
+            Typ::BitVec(width) if maximize => Some(Int::one() - expr::Cst::bv_modulus(width)),
+            Typ::BitVec(_) => Some(Int::zero()),
+            typ => bail!("cannot optimize objectives of type {}", typ),
+        };
+        Ok(Self {
+            expr,
+            maximize,
+            lowest,
+        })
+    }
+
+    /// Constant with some score.
+    fn cst(&self, score: &Int) -> expr::Cst {
+        let val = if self.maximize { -score } else { score.clone() };
+        match self.expr.typ() {
+            Typ::BitVec(width) => expr::Cst::bv(width, val),
+            _ => expr::Cst::I(val),
+        }
+    }
+
+    /// SMT-LIB 2 constraint on the score of the objective being at most `bound`.
+    fn below(&self, bound: &Int) -> Res<String> {
+        let op = match (self.expr.typ(), self.maximize) {
+            (Typ::BitVec(_), false) => "bvule",
+            (Typ::BitVec(_), true) => "bvuge",
+            (_, false) => "<=",
+            (_, true) => ">=",
+        };
+        let bound = match self.cst(bound) {
+            // Strict SMT-LIB 2 solvers reject negative numerals.
+            expr::Cst::I(val) if val.sign() == Sign::Minus => format!("(- {})", -val),
+            cst => crate::solver::smt2::expr(&cst, ())?,
+        };
+        Ok(format!(
+            "({} {} {})",
+            op,
+            crate::solver::smt2::expr(self.expr, ())?,
+            bound,
+        ))
+    }
+}
+
+/// A command modifying the solver's assertion state, recorded for [`Snapshot`]s.
+#[derive(Debug, Clone, Copy)]
+enum Replay<'s> {
+    /// Set-options.
+    SetOptions(&'s SetOptions),
+    /// Constant declarations.
+    Vars(&'s Vars),
+    /// Assertion.
+    Assert(&'s Assert<Expr>),
+    /// Push of one level.
+    Push,
+}
+
+/// SMT-LIB 2 version of a script, the commands it sends to the solver when it runs.
+///
+/// Set-options, declarations, assertions, `check_sat`s (including the ones of meta-lets),
+/// `get_model`s, `get_values`s, `unsat_core`s, resets, pushes and pops are exported as is. Named
+/// assertions become `:named` annotations, and unsat core production is activated if the script
+/// uses them.
+/// Restoring a snapshot is a `reset` followed by the set-options, declarations and assertions of
+/// the snapshot, as in [`Script`]. Echos become comments, and the export stops at the first `exit`
+/// or `panic`.
+///
+/// The commands that follow `if`s branching on check sat results depend on the solver's answers,
+/// so only straight-line scripts can be exported.
+///
+/// # Errors
+///
+/// - on `if`s, `get_mus`, `minimize` and `maximize`, which depend on the solver's answers.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::script::{self, build};
+/// let script = build::of_str(r#"
+/// vars { n m: int }
+/// assert { n > 0, m = n + 1 }
+/// snapshot!(positive)
+/// assert { m < 0 }
+/// check_sat!()
+/// restore!(positive)
+/// echo!("back to positive")
+/// check_sat!()
+/// get_model!()
+/// "#).unwrap();
+/// assert_eq!(
+///     script::to_smt2(&script).unwrap(),
+///     "\
+/// (declare-const m Int)
+/// (declare-const n Int)
+/// (assert (> n 0))
+/// (assert (= m (+ n 1)))
+/// (assert (< m 0))
+/// (check-sat)
+/// (reset)
+/// (declare-const m Int)
+/// (declare-const n Int)
+/// (assert (> n 0))
+/// (assert (= m (+ n 1)))
+/// ; back to positive
+/// (check-sat)
+/// (get-model)
+/// ",
+/// );
+///
+/// let script = build::of_str(r#"
+/// vars { n: int }
+/// assert { pos: n > 0, n < 7, "n is negative": n < 0 }
+/// check_sat!()
+/// unsat_core!()
+/// "#).unwrap();
+/// let smt2 = script::to_smt2(&script).unwrap();
+/// assert_eq!(
+///     smt2,
+///     "\
+/// (set-option :produce-unsat-cores true)
+/// (declare-const n Int)
+/// (assert (! (> n 0) :named pos))
+/// (assert (< n 7))
+/// (assert (! (< n 0) :named |n is negative|))
+/// (check-sat)
+/// (get-unsat-core)
+/// ",
+/// );
+/// assert!(build::of_smt2_str(&smt2).unwrap().uses_unsat_cores());
+///
+/// let script = build::of_str("vars { n: int } if check_sat!() { echo!(\"sat\") }").unwrap();
+/// let err = script::to_smt2(&script).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "cannot export `if` to SMT-LIB 2, the commands it runs depend on the solver's answers",
+/// );
+/// ```
+pub fn to_smt2(script: &Command<Expr, MExpr>) -> Res<String> {
+    let mut export = Export::default();
+    if script.uses_unsat_cores() {
+        export.options = "(set-option :produce-unsat-cores true)\n".into();
+        export.out.push_str(&export.options)
+    }
+    export.command(script)?;
+    Ok(export.out)
+}
+
+/// SMT-LIB 2 export state, see [`to_smt2`].
+#[derive(Default)]
+struct Export {
+    /// Output.
+    out: String,
+    /// Options to set at the start and after each reset.
+    options: String,
+    /// Set-options, declarations and assertions since the last reset, for snapshots.
+    log: Vec<String>,
+    /// Named snapshots, logs to replay on restore.
+    snapshots: Map<String, Vec<String>>,
+    /// True if the script exited.
+    done: bool,
+}
+impl Export {
+    /// Outputs a command modifying the assertion state.
+    fn logged(&mut self, cmd: String) {
+        self.out.push_str(&cmd);
+        self.log.push(cmd);
+    }
+
+    /// Outputs a check sat.
+    fn check_sat(&mut self, check: &CheckSat) {
+        if check.assuming.is_empty() {
+            self.out.push_str("(check-sat)\n")
+        } else {
+            let assuming: Vec<String> = check
+                .assuming
+                .iter()
+                .map(|(lit, pol)| {
+                    if *pol {
+                        lit.inner.clone()
+                    } else {
+                        format!("(not {})", lit.inner)
+                    }
+                })
+                .collect();
+            self.out
+                .push_str(&format!("(check-sat-assuming ({}))\n", assuming.join(" ")))
+        }
+    }
+
+    /// Exports a command.
+    fn command(&mut self, cmd: &Command<Expr, MExpr>) -> Res<()> {
+        use crate::solver::smt2;
+        if self.done {
+            return Ok(());
+        }
+        match cmd {
+            Command::SetOptions(opts) => {
+                for opt in opts.content.iter() {
+                    let val = match opt.val.inner.as_ref() {
+                        Either::Left(cst) => cst.to_string(),
+                        Either::Right(s) => format!("\"{}\"", s),
+                    };
+                    self.logged(format!("(set-option :{} {})\n", opt.key.inner, val))
+                }
+            }
+            Command::Vars(vars) => self.logged(smt2::declare(&vars.decls, None)?),
+            Command::Assert(a) => {
+                for (name, expr) in a.named_exprs() {
+                    match name {
+                        Some(name) => self.logged(format!(
+                            "(assert (! {} :named {}))\n",
+                            smt2::expr(expr, ())?,
+                            smt2::name(&name.inner)
+                        )),
+                        None => self.logged(smt2::assert(expr, ())?),
+                    }
+                }
+            }
+            Command::MLet(mlet) => self.check_sat(&mlet.rhs),
+            Command::Echo(echo) => {
+                for line in echo.msg.lines() {
+                    self.out.push_str(&format!("; {}\n", line))
+                }
+            }
+            Command::GetModel(_) => self.out.push_str("(get-model)\n"),
+            Command::GetValues(gv) => {
+                let exprs = gv
+                    .exprs
+                    .iter()
+                    .map(|(expr, _)| smt2::expr(expr, ()))
+                    .collect::<Res<Vec<_>>>()?;
+                self.out
+                    .push_str(&format!("(get-value ({}))\n", exprs.join(" ")))
+            }
+            Command::GetMus(gm) => bail!(
+                "cannot export `{}` to SMT-LIB 2, it depends on the solver's answers",
+                gm.token
+            ),
+            Command::Optimize(opt) => bail!(
+                "cannot export `{}` to SMT-LIB 2, it depends on the solver's answers",
+                opt.token
+            ),
+            Command::UnsatCore(_) => self.out.push_str("(get-unsat-core)\n"),
+            Command::Reset(_) => {
+                self.out.push_str("(reset)\n");
+                self.out.push_str(&self.options);
+                self.log.clear()
+            }
+            Command::Snapshot(snapshot) => {
+                let _ = self
+                    .snapshots
+                    .insert(snapshot.name.inner.clone(), self.log.clone());
+            }
+            Command::Restore(restore) => {
+                let log = match self.snapshots.get(&restore.name.inner) {
+                    Some(log) => log.clone(),
+                    None => bail!("unknown snapshot `{}`", restore.name.inner),
+                };
+                self.out.push_str("(reset)\n");
+                self.out.push_str(&self.options);
+                self.log.clear();
+                for cmd in log {
+                    self.logged(cmd)
+                }
+            }
+            Command::Push(push) => {
+                for _ in 0..push.count {
+                    self.logged("(push 1)\n".into())
+                }
+            }
+            Command::Pop(pop) => {
+                let pushes: Vec<usize> = self
+                    .log
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, cmd)| if cmd == "(push 1)\n" { Some(idx) } else { None })
+                    .collect();
+                if pop.count > pushes.len() {
+                    bail!(
+                        "cannot pop {} level(s), only {} level(s) pushed",
+                        pop.count,
+                        pushes.len()
+                    )
+                }
+                if pop.count > 0 {
+                    self.out.push_str(&format!("(pop {})\n", pop.count));
+                    self.log.truncate(pushes[pushes.len() - pop.count])
+                }
+            }
+            Command::Query(Query::Block(block)) => {
+                for cmd in block.content.iter() {
+                    self.command(cmd)?
+                }
+            }
+            Command::Query(Query::CheckSat(check)) => self.check_sat(check),
+            Command::Query(Query::Ite(_)) => bail!(
+                "cannot export `if` to SMT-LIB 2, \
+                the commands it runs depend on the solver's answers"
+            ),
+            Command::Query(Query::Panic(_)) | Command::Query(Query::Exit(_)) => {
+                self.out.push_str("(exit)\n");
+                self.done = true
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hsmt version of a script, parsed back by [`build::of_str`].
+///
+/// Definitions (`let <ident> = <expr>;`) and loops are already expanded in `script`, they do not
+/// appear in the output. Blocks are indented by four spaces, the top-level block is not
+/// surrounded by braces.
+///
+/// # Errors
+///
+/// - on check sats with a timeout, and
+/// - when an expression, a name or a message has no hsmt syntax, see [`expr::hsmt`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::script::{self, build};
+/// let script = build::of_str(r#"
+/// vars { n m: int, b: bool }
+/// let pos = n > 0;
+/// assert { pos, "m is next": m = n + 1 }
+/// /// Can `m` be negative?
+/// let res = check_sat!(assuming: [¬b]);
+/// if res {
+///     get_values!(m - n, ¬(pos ⋀ b))
+/// } else {
+///     echo!("unsat")
+///     exit!(1)
+/// }
+/// "#).unwrap();
+/// let hsmt = script::to_hsmt(&script).unwrap();
+/// assert_eq!(
+///     hsmt,
+///     r#"vars {
+///     b: bool,
+///     m: int,
+///     n: int,
+/// }
+/// assert {
+///     n > 0,
+///     "m is next": m = n + 1,
+/// }
+/// /// Can `m` be negative?
+/// let res = check_sat!(assuming: [¬b]);
+/// if res {
+///     get_values!(m - n, ¬(n > 0 ⋀ b))
+/// } else {
+///     echo!("unsat")
+///     exit!(1)
+/// }
+/// "#,
+/// );
+/// assert_eq!(script::to_hsmt(&build::of_str(&hsmt).unwrap()).unwrap(), hsmt);
+/// ```
+pub fn to_hsmt(script: &Command<Expr, MExpr>) -> Res<String> {
+    let mut out = String::new();
+    match script {
+        Command::Query(Query::Block(block)) => {
+            for cmd in block.content.iter() {
+                hsmt_command(cmd, "", &mut out)?
+            }
+        }
+        cmd => hsmt_command(cmd, "", &mut out)?,
+    }
+    Ok(out)
+}
+
+/// Appends the hsmt lines of a block to `out`, `pref` is the indentation of the braces.
+fn hsmt_block(block: &Block<Expr, MExpr>, pref: &str, out: &mut String) -> Res<()> {
+    out.push_str("{\n");
+    let inner = format!("{}    ", pref);
+    for cmd in block.content.iter() {
+        hsmt_command(cmd, &inner, out)?
+    }
+    out.push_str(pref);
+    out.push('}');
+    Ok(())
+}
+
+/// Appends the hsmt lines of a list of lines in braces to `out`, `pref` is the indentation of the
+/// braces.
+fn hsmt_lines(keyword: &str, lines: Vec<String>, pref: &str, out: &mut String) {
+    out.push_str(&format!("{}{} {{\n", pref, keyword));
+    for line in lines {
+        out.push_str(&format!("{}    {}\n", pref, line))
+    }
+    out.push_str(&format!("{}}}\n", pref))
+}
+
+/// Hsmt syntax of a check sat, and the doc comment of its label if any.
+fn hsmt_check_sat(check: &CheckSat, pref: &str) -> Res<(String, String)> {
+    if let Some(timeout) = check.timeout {
+        bail!(
+            "cannot write a check sat with a timeout of {:?} in hsmt",
+            timeout
+        )
+    }
+    let mut doc = String::new();
+    for line in check.label.iter().flat_map(Label::to_doc) {
+        doc.push_str(format!("{}/// {}", pref, line).trim_end());
+        doc.push('\n')
+    }
+    if check.assuming.is_empty() {
+        return Ok((doc, "check_sat!()".into()));
+    }
+    let mut assuming = Vec::with_capacity(check.assuming.len());
+    for (lit, pol) in check.assuming.iter() {
+        let lit = expr::hsmt::ident(&lit.inner)?;
+        assuming.push(if *pol {
+            lit.into()
+        } else {
+            format!("¬{}", lit)
+        })
+    }
+    Ok((
+        doc,
+        format!("check_sat!(assuming: [{}])", assuming.join(", ")),
+    ))
+}
+
+/// Appends the hsmt lines of a command to `out`, `pref` is the indentation of the command.
+fn hsmt_command(cmd: &Command<Expr, MExpr>, pref: &str, out: &mut String) -> Res<()> {
+    use expr::hsmt::{dbl_quoted, ident};
+    match cmd {
+        Command::SetOptions(opts) => {
+            let mut content = Vec::with_capacity(opts.content.len());
+            for opt in opts.content.iter() {
+                let val = match opt.val.inner.as_ref() {
+                    Either::Left(cst) => Expr::Cst(cst.clone()).to_hsmt()?,
+                    Either::Right(s) => dbl_quoted(s)?,
+                };
+                content.push(format!("{}: {}", opt.key.inner, val))
+            }
+            out.push_str(&format!("{}set_options!({})\n", pref, content.join(", ")))
+        }
+        Command::Vars(vars) => {
+            let svars = vars.decls.hsmt_svars_lines(None)?;
+            if !svars.is_empty() {
+                hsmt_lines("vars", svars, pref, out)
+            }
+            let funs = vars.decls.hsmt_funs_lines()?;
+            if !funs.is_empty() {
+                hsmt_lines("funs", funs, pref, out)
+            }
+        }
+        Command::MLet(mlet) => {
+            let (doc, check) = hsmt_check_sat(&mlet.rhs, pref)?;
+            out.push_str(&doc);
+            out.push_str(&format!(
+                "{}let {} = {};\n",
+                pref,
+                ident(&mlet.lhs.inner)?,
+                check
+            ))
+        }
+        Command::Assert(a) => {
+            let mut lines = Vec::with_capacity(a.exprs.len());
+            for (name, expr) in a.named_exprs() {
+                let name = match name {
+                    Some(name) if decls::check_ident(&name.inner).is_ok() => {
+                        format!("{}: ", name.inner)
+                    }
+                    Some(name) => format!("{}: ", dbl_quoted(&name.inner)?),
+                    None => "".into(),
+                };
+                lines.push(format!("{}{},", name, expr.to_hsmt()?))
+            }
+            hsmt_lines("assert", lines, pref, out)
+        }
+        Command::Echo(echo) => out.push_str(&format!(
+            "{}{}!({})\n",
+            pref,
+            echo.token,
+            dbl_quoted(&echo.msg)?
+        )),
+        Command::GetModel(gm) => out.push_str(&format!("{}{}!()\n", pref, gm.token)),
+        Command::GetMus(gm) => out.push_str(&format!("{}{}!()\n", pref, gm.token)),
+        Command::UnsatCore(uc) => out.push_str(&format!("{}{}!()\n", pref, uc.token)),
+        Command::GetValues(gv) => {
+            let exprs = gv
+                .exprs
+                .iter()
+                .map(|(expr, _)| expr.to_hsmt())
+                .collect::<Res<Vec<_>>>()?;
+            out.push_str(&format!("{}{}!({})\n", pref, gv.token, exprs.join(", ")))
+        }
+        Command::Optimize(opt) => out.push_str(&format!(
+            "{}{}!({})\n",
+            pref,
+            opt.token,
+            opt.objective.0.to_hsmt()?
+        )),
+        Command::Reset(_) => out.push_str(&format!("{}reset!()\n", pref)),
+        Command::Snapshot(snapshot) => out.push_str(&format!(
+            "{}snapshot!({})\n",
+            pref,
+            ident(&snapshot.name.inner)?
+        )),
+        Command::Restore(restore) => out.push_str(&format!(
+            "{}restore!({})\n",
+            pref,
+            ident(&restore.name.inner)?
+        )),
+        Command::Push(push) => out.push_str(&format!("{}push!({})\n", pref, push.count)),
+        Command::Pop(pop) => out.push_str(&format!("{}pop!({})\n", pref, pop.count)),
+        Command::Query(Query::Block(block)) => {
+            out.push_str(pref);
+            hsmt_block(block, pref, out)?;
+            out.push('\n')
+        }
+        Command::Query(Query::CheckSat(check)) => {
+            let (doc, check) = hsmt_check_sat(check, pref)?;
+            out.push_str(&doc);
+            out.push_str(&format!("{}{}\n", pref, check))
+        }
+        Command::Query(Query::Ite(ite)) => {
+            let cnd = match &ite.cnd {
+                Either::Left(mvar) => ident(&mvar.inner.ident)?.into_owned(),
+                Either::Right(check) => {
+                    let (doc, check) = hsmt_check_sat(check, pref)?;
+                    out.push_str(&doc);
+                    check
+                }
+            };
+            out.push_str(&format!("{}if {} ", pref, cnd));
+            hsmt_block(&ite.thn, pref, out)?;
+            if !ite.els.content.is_empty() || ite.otw.is_some() {
+                out.push_str(" else ");
+                hsmt_block(&ite.els, pref, out)?
+            }
+            if let Some(otw) = ite.otw.as_ref() {
+                out.push_str(" otherwise ");
+                hsmt_block(otw, pref, out)?
+            }
+            out.push('\n')
+        }
+        Command::Query(Query::Panic(panic)) => {
+            out.push_str(&format!("{}panic!({})\n", pref, dbl_quoted(&panic.msg)?))
+        }
+        Command::Query(Query::Exit(exit)) => {
+            out.push_str(&format!("{}exit!({})\n", pref, exit.code))
+        }
+    }
+    Ok(())
+}
+
 /// A script is a sequence of [`Command`]s and a *meta-environment*.
 ///
 /// The *meta-environment* stores *meta-variables* and the values they have. A *meta-variable*
@@ -368,6 +1178,14 @@ pub struct Script<'s> {
     outcome: Option<Outcome>,
     /// Current command.
     curr: CurrCmd<'s>,
+    /// Display hints of the variables declared so far.
+    hints: Map<String, expr::display::Hint>,
+    /// Formatters used to render values in models.
+    pub formatters: expr::display::Formatters,
+    /// Commands modifying the solver's assertion state since the last reset.
+    log: Vec<Replay<'s>>,
+    /// Named snapshots, logs to replay on restore.
+    snapshots: Map<String, Vec<Replay<'s>>>,
 }
 impl<'s> Script<'s> {
     /// Constructor.
@@ -377,6 +1195,10 @@ impl<'s> Script<'s> {
         script: &'s Command<Expr, MExpr>,
         txt: &'s str,
     ) -> Res<Self> {
+        let mut conf = conf;
+        if script.uses_unsat_cores() {
+            conf.unsat_cores()
+        }
         let solver = SLSolver::new(conf, tee)?;
         let stack = Vec::with_capacity(17);
         let curr = script.into();
@@ -390,9 +1212,22 @@ impl<'s> Script<'s> {
             step_res: Step::Nothing,
             curr,
             outcome: None,
+            hints: Map::new(),
+            formatters: expr::display::Formatters::new(),
+            log: vec![],
+            snapshots: Map::new(),
         })
     }
 
+    /// SMT-LIB 2 version of the script, see [`to_smt2`].
+    pub fn to_smt2(&self) -> Res<String> {
+        to_smt2(self.script)
+    }
+    /// Hsmt version of the script, see [`to_hsmt`].
+    pub fn to_hsmt(&self) -> Res<String> {
+        to_hsmt(self.script)
+    }
+
     /// Sets the internal `res` to `Some(QueryRes::None)`, indicating we must go up.
     pub fn go_up_none(&mut self) -> Res<()> {
         self.go_up_with(QueryRes::None)
@@ -416,10 +1251,12 @@ impl<'s> Script<'s> {
     }
 
     /// Set-options.
-    pub fn set_options(&mut self, opts: &SetOptions) -> Res<()> {
+    pub fn set_options(&mut self, opts: &'s SetOptions) -> Res<()> {
         self.inner_set_options(opts).map_err(|e| {
             e.chain_err(|| PError::new_error(opts.span, self.txt, "while handling these options"))
-        })
+        })?;
+        self.log.push(Replay::SetOptions(opts));
+        self.go_up_none()
     }
     fn inner_set_options(&mut self, opts: &SetOptions) -> Res<()> {
         for opt in opts.content.iter() {
@@ -429,22 +1266,29 @@ impl<'s> Script<'s> {
                 Either::Right(s) => self.solver.set_option(&key, format!("\"{}\"", s))?,
             }
         }
-        self.go_up_none()
+        Ok(())
     }
 
     /// Constant declarations.
-    pub fn decl_vars(&mut self, vars: &Vars) -> Res<()> {
+    pub fn decl_vars(&mut self, vars: &'s Vars) -> Res<()> {
         self.inner_decl_vars(vars).map_err(|e| {
             e.chain_err(|| {
                 PError::new_error(vars.span, self.txt, "while handling these declarations")
             })
-        })
+        })?;
+        self.log.push(Replay::Vars(vars));
+        self.go_up_none()
     }
     fn inner_decl_vars(&mut self, vars: &Vars) -> Res<()> {
+        self.solver.declare_enums(vars.decls.enums())?;
+        self.solver.declare_funs(vars.decls.funs())?;
         for var in vars.decls.all() {
-            self.solver.declare_const(var.id(), var.typ())?;
+            self.solver.declare_const(&var, var.typ())?;
         }
-        self.go_up_none()
+        for (id, hint) in vars.decls.hints() {
+            let _ = self.hints.insert(id.clone(), hint.clone());
+        }
+        Ok(())
     }
 
     /// Check-sat.
@@ -457,10 +1301,26 @@ impl<'s> Script<'s> {
         let res = if check.assuming.is_empty() {
             self.solver.check_sat()
         } else {
-            self.solver
-                .check_sat_assuming(check.assuming.iter().map(|s| &s.inner))
+            let lits = check
+                .assuming
+                .iter()
+                .map(|(lit, pol)| {
+                    let var = Expr::new_var(expr::Var::new(lit.inner.clone(), Typ::Bool));
+                    let lit = if *pol {
+                        var
+                    } else {
+                        Expr::new_op(expr::Op::Not, vec![var])?
+                    };
+                    crate::solver::smt2::expr(&lit, ())
+                })
+                .collect::<Res<Vec<String>>>()?;
+            self.solver.check_sat_assuming(lits)
+        };
+        let res = CheckSatRes {
+            label: check.label.clone(),
+            ..CheckSatRes::new(check.span, res)?
         };
-        QueryRes::from_check_sat(check.span, res)
+        Ok(res.into())
     }
 
     /// If-then-else.
@@ -528,9 +1388,116 @@ impl<'s> Script<'s> {
                 at reset.span,
                 with "while resetting the solver",
         }
+        self.hints.clear();
+        self.log.clear();
         self.go_up_none()
     }
 
+    /// Snapshot.
+    pub fn snapshot(&mut self, snapshot: &'s Snapshot) -> Res<()> {
+        let _prev = self
+            .snapshots
+            .insert(snapshot.name.inner.clone(), self.log.clone());
+        self.go_up_none()
+    }
+    /// Restore.
+    ///
+    /// Resets the solver and replays the declarations, options and assertions of the snapshot.
+    pub fn restore(&mut self, restore: &'s Restore) -> Res<()> {
+        let log = match self.snapshots.get(&restore.name.inner) {
+            Some(log) => log.clone(),
+            None => bail!(PError::new_error(
+                restore.name.span,
+                self.txt,
+                "no snapshot with this name was taken so far",
+            )),
+        };
+        self.replay(&log, true).map_err(|e| {
+            e.chain_err(|| {
+                PError::new_error(restore.span, self.txt, "while restoring this snapshot")
+            })
+        })?;
+        self.go_up_none()
+    }
+
+    /// Push.
+    pub fn push(&mut self, push: &'s Push) -> Res<()> {
+        for _ in 0..push.count {
+            try_to_pres! {
+                self.solver.push(1) =>
+                    in self.txt,
+                    at push.span,
+                    with "while pushing on the solver's assertion stack",
+            }
+            self.log.push(Replay::Push);
+        }
+        self.go_up_none()
+    }
+    /// Pop.
+    ///
+    /// Retracts the declarations, options and assertions since the matching push.
+    pub fn pop(&mut self, pop: &'s Pop) -> Res<()> {
+        let pushes: Vec<usize> = self
+            .log
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, cmd)| match cmd {
+                Replay::Push => Some(idx),
+                Replay::SetOptions(_) | Replay::Vars(_) | Replay::Assert(_) => None,
+            })
+            .collect();
+        if pop.count > pushes.len() {
+            bail!(PError::new_error(
+                pop.span,
+                self.txt,
+                format!(
+                    "cannot pop {} level(s), only {} level(s) pushed",
+                    pop.count,
+                    pushes.len()
+                ),
+            ))
+        }
+        for _ in 0..pop.count {
+            try_to_pres! {
+                self.solver.pop(1) =>
+                    in self.txt,
+                    at pop.span,
+                    with "while popping the solver's assertion stack",
+            }
+        }
+        if pop.count > 0 {
+            self.log.truncate(pushes[pushes.len() - pop.count]);
+        }
+        self.go_up_none()
+    }
+
+    /// Resets the solver and replays a log, skipping assertions if `with_asserts` is false.
+    fn replay(&mut self, log: &[Replay<'s>], with_asserts: bool) -> Res<()> {
+        self.solver
+            .reset()
+            .chain_err(|| "while resetting the solver")?;
+        self.hints.clear();
+        self.log.clear();
+        for cmd in log {
+            match *cmd {
+                Replay::SetOptions(opts) => self.inner_set_options(opts)?,
+                Replay::Vars(vars) => self.inner_decl_vars(vars)?,
+                Replay::Assert(_) if !with_asserts => continue,
+                Replay::Assert(a) => {
+                    for (name, expr) in a.named_exprs() {
+                        self.assert_expr(name, expr)?
+                    }
+                }
+                Replay::Push => self
+                    .solver
+                    .push(1)
+                    .chain_err(|| "while pushing on the solver's assertion stack")?,
+            }
+            self.log.push(*cmd);
+        }
+        Ok(())
+    }
+
     /// Echo.
     pub fn echo(&mut self, echo: &'s Echo) -> Res<()> {
         self.set_step_res(Step::Echo(echo.clone()))?;
@@ -539,24 +1506,53 @@ impl<'s> Script<'s> {
 
     /// Assertion.
     pub fn assert(&mut self, a: &'s Assert<Expr>) -> Res<()> {
-        for (idx, expr) in a.exprs.iter().enumerate() {
-            try_to_pres! {
-                self.solver.assert(expr) =>
-                    in self.txt,
-                    at a.span,
-                    with "while asserting expression #{} of this assertion", idx+1,
+        for (idx, (name, expr)) in a.named_exprs().enumerate() {
+            let res = self.assert_expr(name, expr);
+            match name {
+                Some(name) => try_to_pres! {
+                    res =>
+                        in self.txt,
+                        at name.span,
+                        with "while asserting `{}`", name.inner,
+                },
+                None => try_to_pres! {
+                    res =>
+                        in self.txt,
+                        at a.span,
+                        with "while asserting expression #{} of this assertion", idx+1,
+                },
             }
         }
+        self.log.push(Replay::Assert(a));
         self.go_up_none()
     }
+    /// Asserts an expression, named if `name` is not `None`.
+    fn assert_expr(&mut self, name: Option<&Spn<String>>, expr: &Expr) -> SmtRes<()> {
+        match name {
+            Some(name) => self
+                .solver
+                .named_assert(crate::solver::smt2::name(&name.inner), expr),
+            None => self.solver.assert(expr),
+        }
+    }
 
     /// Get model.
+    ///
+    /// If the solver fails to produce a model, the values of the declared variables are requested
+    /// one by one until it fails again, and the model is incomplete. Values are also requested one
+    /// by one when uninterpreted functions are declared, since the model would define them.
     pub fn get_model(&mut self, gm: &'s GetModel) -> Res<()> {
-        let smt_model = try_to_pres! {
-            self.solver.get_model() =>
-                in self.txt,
-                at gm.span,
-                with "while requesting a model"
+        let has_funs = self.log.iter().any(|cmd| match cmd {
+            Replay::Vars(vars) => !vars.decls.funs().is_empty(),
+            Replay::SetOptions(_) | Replay::Assert(_) | Replay::Push => false,
+        });
+        let (smt_model, incomplete) = if has_funs {
+            self.salvage_model(None)
+        } else {
+            match self.solver.get_model() {
+                Ok(model) => (model, None),
+                Err(e) => self.salvage_model(Some(e.to_string())),
+            }
         };
         let mut model = Map::new();
         for (id, args, typ, val) in smt_model {
@@ -579,10 +1575,313 @@ impl<'s> Script<'s> {
             let _prev = model.insert(id, (val, typ));
             debug_assert_eq!(_prev, None)
         }
+        let display = model
+            .iter()
+            .filter_map(|(id, (cst, _))| {
+                let hint = self.hints.get(id)?;
+                Some((id.clone(), self.formatters.render(Some(hint), cst)))
+            })
+            .collect();
         self.set_step_res(Step::Model {
             span: gm.span,
             token: gm.token.clone(),
             model,
+            display,
+            incomplete,
+        })?;
+        self.go_up_none()
+    }
+
+    /// Retrieves the values of the declared variables one by one, until the solver fails.
+    ///
+    /// Yields the values retrieved and the reason why the model is incomplete, if any. `reason` is
+    /// why `get_model` failed, if it did.
+    fn salvage_model(&mut self, mut reason: Option<String>) -> (SalvagedModel, Option<String>) {
+        let vars: Vec<expr::Var> = self
+            .log
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Replay::Vars(vars) => Some(vars.decls.all()),
+                Replay::SetOptions(_) | Replay::Assert(_) | Replay::Push => None,
+            })
+            .flatten()
+            .collect();
+        let mut model = vec![];
+        for var in vars {
+            let typ = var.typ();
+            let id = var.id().to_string();
+            let expr = Expr::new_var(var);
+            match self.solver.get_values(Some(&expr)) {
+                Ok(vals) => {
+                    if let Some(((), cst)) = vals.into_iter().next() {
+                        model.push((id, vec![], typ, cst))
+                    }
+                }
+                Err(e) => {
+                    reason = Some(match reason {
+                        Some(reason) => format!("{}, then failed on `{}`: {}", reason, id, e),
+                        None => format!("failed on `{}`: {}", id, e),
+                    });
+                    break;
+                }
+            }
+        }
+        (model, reason)
+    }
+
+    /// Get MUS.
+    ///
+    /// Extracts a minimal unsatisfiable subset of the assertions since the last reset (or restore)
+    /// with [`crate::solver::mus`]. The solver is reset and the declarations and options are replayed
+    /// without the assertions for the extraction, then everything is replayed.
+    pub fn get_mus(&mut self, gm: &'s GetMus) -> Res<()> {
+        let log = self.log.clone();
+        let exprs: Vec<&'s Expr> = log
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Replay::Assert(a) => Some(a.exprs.iter()),
+                Replay::SetOptions(_) | Replay::Vars(_) | Replay::Push => None,
+            })
+            .flatten()
+            .collect();
+        let mus = self.replay(&log, false).and_then(|()| {
+            crate::solver::mus(
+                &mut self.solver,
+                exprs.iter().map(|expr| (expr.to_string(), *expr)),
+                (),
+            )
+        });
+        let replayed = self.replay(&log, true);
+        let mus = mus.and_then(|mus| replayed.map(|()| mus)).map_err(|e| {
+            e.chain_err(|| {
+                PError::new_error(
+                    gm.span,
+                    self.txt,
+                    "while extracting a minimal unsatisfiable subset",
+                )
+            })
+        })?;
+        self.set_step_res(Step::Mus {
+            span: gm.span,
+            token: gm.token.clone(),
+            exprs: mus,
+        })?;
+        self.go_up_none()
+    }
+
+    /// Optimize.
+    ///
+    /// Objectives are integers or bitvectors, seen as unsigned. Solvers supporting optimization,
+    /// *i.e.* Z3, optimize the objective natively, followed by a check sat making sure the
+    /// objective is bounded. Other solvers emulate optimization with check sats: integer
+    /// objectives are first improved with bounds doubling their distance to the current value at
+    /// each step, then the optimum is found by binary search; bitvector objectives go straight to
+    /// the binary search. Each of these check sats happens in a dedicated scope, the assertions
+    /// are left as they were.
+    ///
+    /// The last check sat happens at the current level, and its model gives the objective its
+    /// optimal value: a subsequent `get_model!` or `get_values!` reflects the optimum. Solvers
+    /// without `check-sat-assuming` (CVC5) cannot constrain this check sat without modifying the
+    /// assertions, its model is then *some* model of the assertions.
+    ///
+    /// Fails if no bound is found after [`Self::OPTIMIZE_MAX_DOUBLINGS`] steps, which usually
+    /// means the objective is unbounded.
+    pub fn optimize(&mut self, opt: &'s Optimize<Expr>) -> Res<()> {
+        let (expr, repr) = &opt.objective;
+        let value = self.inner_optimize(expr, opt.maximize).map_err(|e| {
+            e.chain_err(|| PError::new_error(opt.span, self.txt, "while optimizing"))
+        })?;
+        self.set_step_res(Step::Optimum {
+            span: opt.span,
+            token: opt.token.clone(),
+            objective: repr.clone(),
+            maximize: opt.maximize,
+            value,
+        })?;
+        self.go_up_none()
+    }
+
+    /// Maximum number of bound doublings when optimizing, see [`Self::optimize`].
+    pub const OPTIMIZE_MAX_DOUBLINGS: usize = 64;
+
+    /// Minimizes or maximizes an objective, `None` if the assertions are unsatisfiable.
+    ///
+    /// The search minimizes the *score* of the objective, its value when minimizing and the
+    /// opposite of its value when maximizing.
+    fn inner_optimize(&mut self, expr: &Expr, maximize: bool) -> Res<Option<expr::Cst>> {
+        let objective = Objective::new(expr, maximize)?;
+        if !self.solver.check_sat()? {
+            return Ok(None);
+        }
+        let best = if self.solver.optimizes() {
+            self.native_optimum(&objective)?
+        } else {
+            self.emulated_optimum(&objective)?
+        };
+        let optimum = objective.cst(&best);
+        // The current model is the one of the last scope, which is gone.
+        let eq = Expr::new_op(expr::Op::Eq, vec![expr.clone(), Expr::Cst(optimum.clone())])?;
+        let sat = if self.solver.conf().get_check_sat_assuming().is_some() {
+            let actlit = self.solver.get_actlit()?;
+            self.solver.assert_act(&actlit, &eq)?;
+            self.solver.check_sat_act(Some(&actlit))?
+        } else {
+            self.solver.check_sat()?
+        };
+        if !sat {
+            bail!(
+                "assertions are unsatisfiable with the optimum `{}`",
+                optimum
+            )
+        }
+        Ok(Some(optimum))
+    }
+
+    /// Optimal score of an objective using the solver's optimization commands.
+    fn native_optimum(&mut self, objective: &Objective) -> Res<Int> {
+        let cmd = format!(
+            "({} {})",
+            if objective.maximize {
+                "maximize"
+            } else {
+                "minimize"
+            },
+            crate::solver::smt2::expr(objective.expr, ())?,
+        );
+        self.solver
+            .push(1)
+            .chain_err(|| "while opening the optimization scope")?;
+        let res = self
+            .solver
+            .command(&cmd)
+            .chain_err(|| "while declaring the objective")
+            .and_then(|()| {
+                if self.solver.check_sat()? {
+                    self.objective_score(objective)
+                } else {
+                    bail!("solver found no model for the objective")
+                }
+            });
+        self.solver
+            .pop(1)
+            .chain_err(|| "while closing the optimization scope")?;
+        let best = res?;
+        // Unbounded objectives still get a value, that a better one disproves.
+        if self.objective_below(objective, &(&best - 1))?.is_some() {
+            bail!("the objective is unbounded")
+        }
+        Ok(best)
+    }
+
+    /// Optimal score of an objective using check sats only.
+    fn emulated_optimum(&mut self, objective: &Objective) -> Res<Int> {
+        let mut best = self.objective_score(objective)?;
+        // Largest score known to be too small, if any.
+        let mut too_small = objective.lowest.as_ref().map(|lowest| lowest - 1);
+        let mut delta = Int::one();
+        for _ in 0..Self::OPTIMIZE_MAX_DOUBLINGS {
+            if too_small.is_some() {
+                break;
+            }
+            let bound = &best - &delta;
+            match self.objective_below(objective, &bound)? {
+                Some(val) => best = val,
+                None => too_small = Some(bound),
+            }
+            delta *= 2;
+        }
+        let mut too_small = match too_small {
+            Some(bound) => bound,
+            None => bail!("could not bound the objective, it is probably unbounded"),
+        };
+        while &too_small + 1 < best {
+            let mid = (&too_small + &best) / 2;
+            match self.objective_below(objective, &mid)? {
+                Some(val) => best = val,
+                None => too_small = mid,
+            }
+        }
+        Ok(best)
+    }
+
+    /// Score of an objective in a model of the assertions lower than or equal to `bound`, if any.
+    fn objective_below(&mut self, objective: &Objective, bound: &Int) -> Res<Option<Int>> {
+        if matches!(&objective.lowest, Some(lowest) if bound < lowest) {
+            return Ok(None);
+        }
+        let below = objective.below(bound)?;
+        self.solver
+            .push(1)
+            .chain_err(|| "while opening the optimization scope")?;
+        let res = self.objective_below_in_scope(objective, &below);
+        self.solver
+            .pop(1)
+            .chain_err(|| "while closing the optimization scope")?;
+        res
+    }
+
+    /// Score of an objective in a model of the assertions and `below`, must be called in a
+    /// dedicated scope.
+    fn objective_below_in_scope(&mut self, objective: &Objective, below: &str) -> Res<Option<Int>> {
+        self.solver.assert(below)?;
+        if self.solver.check_sat()? {
+            self.objective_score(objective).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Score of an objective in the current model.
+    fn objective_score(&mut self, objective: &Objective) -> Res<Int> {
+        let vals = self.solver.get_values(Some(objective.expr))?;
+        let val = match vals.into_iter().next() {
+            Some(((), expr::Cst::I(val))) | Some(((), expr::Cst::BV(_, val))) => val,
+            Some(((), cst)) => bail!(
+                "expected {} value for the objective, got `{}`",
+                objective.expr.typ(),
+                cst
+            ),
+            None => bail!("solver did not produce a value for the objective"),
+        };
+        Ok(if objective.maximize { -val } else { val })
+    }
+
+    /// Unsat core.
+    ///
+    /// Retrieves the names of the assertions the solver used to prove the last check sat unsat,
+    /// which fails if that check sat was not unsat. Unnamed assertions never appear in the core.
+    ///
+    /// Names are compared without the `|` quotes, which solvers add or drop as they see fit.
+    pub fn unsat_core(&mut self, uc: &'s UnsatCore) -> Res<()> {
+        let names: Vec<String> = try_to_pres! {
+            self.solver.get_unsat_core() =>
+                in self.txt,
+                at uc.span,
+                with "while retrieving an unsat core"
+        };
+        let exprs: Map<&str, (&String, &Expr)> = self
+            .log
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Replay::Assert(a) => Some(a.named_exprs()),
+                Replay::SetOptions(_) | Replay::Vars(_) | Replay::Push => None,
+            })
+            .flatten()
+            .filter_map(|(name, expr)| {
+                name.map(|name| (name.inner.trim_matches('|'), (&name.inner, expr)))
+            })
+            .collect();
+        let core = names
+            .into_iter()
+            .map(|name| match exprs.get(name.trim_matches('|')) {
+                Some((name, expr)) => (name.to_string(), expr.to_string()),
+                None => (name, String::new()),
+            })
+            .collect();
+        self.set_step_res(Step::UnsatCore {
+            span: uc.span,
+            token: uc.token.clone(),
+            core,
         })?;
         self.go_up_none()
     }
@@ -693,7 +1992,14 @@ impl<'s> Script<'s> {
             Command::Assert(a) => self.assert(a),
             Command::GetModel(gm) => self.get_model(gm),
             Command::GetValues(gm) => self.get_values(gm),
+            Command::GetMus(gm) => self.get_mus(gm),
+            Command::Optimize(opt) => self.optimize(opt),
+            Command::UnsatCore(uc) => self.unsat_core(uc),
             Command::Reset(reset) => self.reset(reset),
+            Command::Snapshot(snapshot) => self.snapshot(snapshot),
+            Command::Restore(restore) => self.restore(restore),
+            Command::Push(push) => self.push(push),
+            Command::Pop(pop) => self.pop(pop),
             Command::Query(q) => self.go_down_query(q),
         }
     }
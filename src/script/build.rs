@@ -7,21 +7,21 @@ prelude!(
 );
 
 /// Meta-declarations: scoped, unlike normal declarations.
-pub type MDecls = Decls;
+///
+/// Blocks open a new scope, meta-variables can always be shadowed.
+pub type MDecls = decls::SymbolTable;
 
 /// Stack frames for [`doit`](doit()).
 pub enum Frame<'input> {
     /// Meta-let info.
-    MLet(MDecls, Spn<String>),
+    MLet(Spn<String>),
     /// Block info.
     Block(
-        MDecls,
         Vec<Command<Expr, MExpr>>,
         std::vec::IntoIter<Command<ast::Expr<'input>, ast::Expr<'input>>>,
     ),
     /// Ite info, when in the condition.
     IteCnd(
-        MDecls,
         Decls,
         Span,
         Block<ast::Expr<'input>, ast::Expr<'input>>,
@@ -30,7 +30,6 @@ pub enum Frame<'input> {
     ),
     /// Ite info, when in the then branch.
     IteThn(
-        MDecls,
         Decls,
         Span,
         Either<Spn<MetaVar>, CheckSat>,
@@ -39,7 +38,6 @@ pub enum Frame<'input> {
     ),
     /// Ite info, when in the else branch.
     IteEls(
-        MDecls,
         Decls,
         Span,
         Either<Spn<MetaVar>, CheckSat>,
@@ -48,7 +46,6 @@ pub enum Frame<'input> {
     ),
     /// Ite info, when in the otherwise branch.
     IteOtw(
-        MDecls,
         Decls,
         Span,
         Either<Spn<MetaVar>, CheckSat>,
@@ -62,12 +59,73 @@ pub enum Frame<'input> {
 /// Probably should do this using the `log` crate.
 const DEBUG: bool = false;
 
+/// Parses and builds a script.
+///
+/// Same as [`parse::script`] followed by [`doit`](doit()), with located errors.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::script::build;
+/// assert!(build::of_str(mikino_api::SCRIPT_DEMO).is_ok());
+/// let err = build::of_str("vars { n: int } assert { m > 0 }").err().unwrap();
+/// assert_eq!(
+///     err.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+///     vec![
+///         "parse error at 1:26:  | vars { n: int } assert { m > 0 }<EOI>",
+///         "unknown variable `m`",
+///     ],
+/// );
+///
+/// assert!(build::of_str("funs { f: (int) -> int } vars { n: int } assert { f(n) > n }").is_ok());
+/// let err = build::of_str("funs { f: (int) -> int } vars { f: int }").err().unwrap();
+/// assert!(err.to_string().ends_with("variable `f` has the name of a function"));
+/// ```
+pub fn of_str(txt: &str) -> Res<Command<Expr, MExpr>> {
+    let block = parse::script(txt)?;
+    doit(block).map_err(|e| e.into_error(txt))
+}
+
+/// Parses and builds a plain SMT-LIB 2 script.
+///
+/// Same as [`parse::smt2_script`] followed by [`doit`](doit()), with located errors.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{ast::script::CommandExt, script::build};
+/// let script = build::of_smt2_str("\
+///     (declare-fun f (Int) Int)
+///     (declare-const n Int)
+///     (assert (> (f n) (- n)))
+///     (check-sat)
+/// ").unwrap();
+/// assert_eq!(script.desc(), "block(4)");
+///
+/// let err = build::of_smt2_str("(declare-const n Int) (assert (> m 0))").err().unwrap();
+/// assert_eq!(
+///     err.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+///     vec![
+///         "parse error at 1:34:  | (declare-const n Int) (assert (> m 0))<EOI>",
+///         "unknown variable `m`",
+///     ],
+/// );
+/// ```
+pub fn of_smt2_str(txt: &str) -> Res<Command<Expr, MExpr>> {
+    let block = parse::smt2_script(txt)?;
+    doit(block).map_err(|e| e.into_error(txt))
+}
+
 /// Turns a script AST into an actual script.
 pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
     let mut stack: Vec<Frame> = Vec::with_capacity(11);
     let mut curr: Command<ast::Expr, ast::Expr> = block.into();
     let mut decls = Decls::new();
-    let mut meta_decls = MDecls::new();
+    let mut meta_decls = MDecls::new(decls::Shadowing::Allow);
+    // Declarations at each push level, restored by `pop`.
+    let mut pushes: Vec<Decls> = Vec::new();
+    // Declarations and push levels at each snapshot, restored by `restore`.
+    let mut snapshots: Map<String, (Decls, Vec<Decls>)> = Map::new();
 
     macro_rules! show_meta {
         ($blah:tt $cmd:expr) => {
@@ -81,10 +139,11 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
                     $cmd.desc(),
                     $cmd.exits(),
                 );
-                if meta_decls.all().count() > 0 {
+                if meta_decls.visible().count() > 0 {
                     println!("{}- meta declarations:", pref);
-                    for var in meta_decls.all() {
-                        println!("{}  {}: {}", pref, var, var.typ());
+                    for id in meta_decls.visible() {
+                        let sym = &meta_decls[id];
+                        println!("{}  {}: {}", pref, sym.ident, sym.typ);
                     }
                 }
                 if decls.all().count() > 0 {
@@ -104,12 +163,66 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
             Command::Echo(e) => e.into(),
             Command::Reset(r) => {
                 decls.clear();
+                pushes.clear();
                 r.into()
             }
+            Command::Snapshot(snap) => {
+                let _prev =
+                    snapshots.insert(snap.name.inner.clone(), (decls.clone(), pushes.clone()));
+                snap.into()
+            }
+            Command::Restore(r) => {
+                (decls, pushes) = match snapshots.get(&r.name.inner) {
+                    Some(snap) => snap.clone(),
+                    None => {
+                        return Err(PError::new(
+                            format!("unknown snapshot `{}`", r.name.inner),
+                            r.name.span,
+                        ))
+                    }
+                };
+                r.into()
+            }
+            Command::Push(p) => {
+                pushes.extend((0..p.count).map(|_| decls.clone()));
+                p.into()
+            }
+            Command::Pop(p) => {
+                if p.count > pushes.len() {
+                    return Err(PError::new(
+                        format!(
+                            "cannot pop {} level(s), only {} level(s) pushed",
+                            p.count,
+                            pushes.len()
+                        ),
+                        p.span,
+                    ));
+                }
+                if let Some(push_decls) = pushes.drain(pushes.len() - p.count..).next() {
+                    decls = push_decls;
+                }
+                p.into()
+            }
             Command::Query(Query::Panic(p)) => p.into(),
             Command::Query(Query::Exit(e)) => e.into(),
             Command::GetModel(gm) => gm.into(),
+            Command::GetMus(gm) => gm.into(),
+            Command::UnsatCore(uc) => uc.into(),
             Command::Vars(v) => {
+                if let Some(name) = v
+                    .decls
+                    .enums()
+                    .keys()
+                    .find(|name| decls.enum_variants(name).is_some())
+                {
+                    return Err(PError::new(
+                        format!("re-declaring enumeration `{}`", name),
+                        v.span,
+                    ));
+                }
+                decls
+                    .check_funs(&v.decls)
+                    .map_err(|e| PError::new(e.to_string(), v.span))?;
                 let clashes = decls.merge(&v.decls);
                 if let Some(clashes) = clashes {
                     debug_assert!(!clashes.is_empty());
@@ -125,7 +238,7 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
                 Command::Vars(v)
             }
             Command::MLet(ml) => {
-                stack.push(Frame::MLet(meta_decls.clone(), ml.lhs));
+                stack.push(Frame::MLet(ml.lhs));
                 curr = ml.rhs.into();
                 continue 'go_down;
             }
@@ -136,12 +249,55 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
                 }
                 GetValues::new(gv.span, gv.token, vals).into()
             }
+            Command::Optimize(opt) => {
+                let (expr, repr) = opt.objective;
+                let span = expr.full_span();
+                let expr = expr.to_expr(&decls)?;
+                if !matches!(expr.typ(), Typ::Int | Typ::BitVec(_)) {
+                    // Optima of rational objectives are not always reached, *e.g.* under `x < 1`.
+                    return Err(PError::new(
+                        format!(
+                            "objective has type {}, expected int or bitvector",
+                            expr.typ()
+                        ),
+                        span,
+                    ));
+                }
+                Optimize::new(opt.span, opt.token, (expr, repr), opt.maximize).into()
+            }
             Command::Assert(a) => {
                 let mut exprs = Vec::with_capacity(a.exprs.len());
-                for expr in a.exprs.into_iter() {
-                    exprs.push(expr.to_expr(&decls)?);
+                for (name, expr) in a.names.into_iter().zip(a.exprs) {
+                    if let Some(name) = name.as_ref() {
+                        let label = name
+                            .inner
+                            .strip_prefix('|')
+                            .and_then(|name| name.strip_suffix('|'))
+                            .unwrap_or(&name.inner);
+                        if label.contains(&['|', '\\'] as &[char]) {
+                            return Err(PError::new(
+                                "assertion labels cannot contain `|` or `\\`",
+                                name.span,
+                            ));
+                        }
+                        if decls.contains(&name.inner) {
+                            return Err(PError::new(
+                                format!("assertion name `{}` is already a variable", name.inner),
+                                name.span,
+                            ));
+                        }
+                    }
+                    let span = expr.full_span();
+                    let expr = expr.to_expr(&decls)?;
+                    if expr.typ() != Typ::Bool {
+                        return Err(PError::new(
+                            format!("assertion has type {}, expected bool", expr.typ()),
+                            span,
+                        ));
+                    }
+                    exprs.push((name, expr));
                 }
-                Assert::new(a.span, exprs).into()
+                Assert::new_named(a.span, exprs).into()
             }
 
             Command::Query(Query::Block(b)) => {
@@ -150,7 +306,8 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
                 if let Some(first) = todo.next() {
                     let res = Vec::with_capacity(count);
                     curr = first;
-                    stack.push(Frame::Block(meta_decls.clone(), res, todo));
+                    meta_decls.push_scope();
+                    stack.push(Frame::Block(res, todo));
                     continue 'go_down;
                 } else {
                     Block::new(vec![]).into()
@@ -159,10 +316,23 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
             Command::Query(Query::CheckSat(c)) => {
                 // Check for unknown literals.
                 let mut unknown = None;
-                for lit in c.assuming.iter() {
-                    if !decls.contains(&lit.inner) {
-                        let unknown = unknown.get_or_insert_with(Vec::new);
-                        unknown.push(&lit.inner)
+                for (lit, _) in c.assuming.iter() {
+                    match decls.get_var(&lit.inner) {
+                        Some(var) if var.typ() != Typ::Bool => {
+                            return Err(PError::new(
+                                format!(
+                                    "check sat literal `{}` has type {}, expected bool",
+                                    lit.inner,
+                                    var.typ()
+                                ),
+                                lit.span,
+                            ))
+                        }
+                        Some(_) => (),
+                        None => {
+                            let unknown = unknown.get_or_insert_with(Vec::new);
+                            unknown.push(&lit.inner)
+                        }
                     }
                 }
                 if let Some(unknown) = unknown {
@@ -195,7 +365,6 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
 
                     curr = ite.thn.into();
                     stack.push(Frame::IteThn(
-                        meta_decls.clone(),
                         decls.clone(),
                         ite.span,
                         Either::Left(mvar),
@@ -207,7 +376,6 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
                 Either::Right(check_sat) => {
                     curr = check_sat.into();
                     stack.push(Frame::IteCnd(
-                        meta_decls.clone(),
                         decls.clone(),
                         ite.span,
                         ite.thn,
@@ -222,74 +390,54 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
         'go_up: loop {
             show_meta!(up res);
             match stack.pop() {
-                Some(Frame::MLet(mdecls, id)) => match res {
+                Some(Frame::MLet(id)) => match res {
                     Command::Query(Query::CheckSat(c)) => {
-                        meta_decls = mdecls;
                         // Shadowing is fine.
-                        let _prev = meta_decls.register(id.inner.clone(), Typ::Bool);
+                        let _ = meta_decls
+                            .declare(id.inner.clone(), Typ::Bool)
+                            .map_err(|e| PError::new(e.to_string(), id.span))?;
                         res = MLet::new(id, c).into();
                         continue 'go_up;
                     }
                     _ => panic!("[fatal] expected check sat, got {:#?}", res),
                 },
-                Some(Frame::Block(mdecls, mut res_vec, mut todo)) => {
+                Some(Frame::Block(mut res_vec, mut todo)) => {
                     res_vec.push(res);
                     if let Some(next) = todo.next() {
                         curr = next;
-                        stack.push(Frame::Block(mdecls, res_vec, todo));
+                        stack.push(Frame::Block(res_vec, todo));
                         continue 'go_down;
                     } else {
                         res = Block::new(res_vec).into();
-                        meta_decls = mdecls;
+                        meta_decls
+                            .pop_scope()
+                            .expect("[fatal] unbalanced meta-declaration scopes");
                         continue 'go_up;
                     }
                 }
-                Some(Frame::IteCnd(mdecls, vdecls, span, thn, els, otw)) => match res {
+                Some(Frame::IteCnd(vdecls, span, thn, els, otw)) => match res {
                     Command::Query(Query::CheckSat(c)) => {
                         curr = thn.into();
-                        stack.push(Frame::IteThn(
-                            mdecls,
-                            vdecls,
-                            span,
-                            Either::Right(c),
-                            els,
-                            otw,
-                        ));
+                        stack.push(Frame::IteThn(vdecls, span, Either::Right(c), els, otw));
                         continue 'go_down;
                     }
                     res => panic!("[fatal] expected check sat, got {:#?}", res),
                 },
-                Some(Frame::IteThn(mdecls, vdecls, span, cnd, els, otw)) => match res {
+                Some(Frame::IteThn(vdecls, span, cnd, els, otw)) => match res {
                     Command::Query(Query::Block(thn)) => {
                         let thn_decls = mem::replace(&mut decls, vdecls.clone());
-                        meta_decls = mdecls.clone();
                         curr = els.into();
-                        stack.push(Frame::IteEls(
-                            mdecls,
-                            vdecls,
-                            span,
-                            cnd,
-                            (thn, thn_decls),
-                            otw,
-                        ));
+                        stack.push(Frame::IteEls(vdecls, span, cnd, (thn, thn_decls), otw));
                         continue 'go_down;
                     }
                     res => panic!("[fatal] expected block, got {:#?}", res),
                 },
-                Some(Frame::IteEls(mdecls, vdecls, span, cnd, thn, otw)) => match res {
+                Some(Frame::IteEls(vdecls, span, cnd, thn, otw)) => match res {
                     Command::Query(Query::Block(els)) => {
-                        meta_decls = mdecls.clone();
                         if let Some(otw) = otw {
                             let els_decls = mem::replace(&mut decls, vdecls.clone());
                             curr = otw.into();
-                            stack.push(Frame::IteOtw(
-                                mdecls,
-                                vdecls,
-                                span,
-                                cnd,
-                                thn,
-                                (els, els_decls),
-                            ));
+                            stack.push(Frame::IteOtw(vdecls, span, cnd, thn, (els, els_decls)));
                             continue 'go_down;
                         } else {
                             let (thn, thn_decls) = thn;
@@ -314,56 +462,50 @@ pub fn doit(block: Block<ast::Expr, ast::Expr>) -> PRes<Command<Expr, MExpr>> {
                     }
                     res => panic!("[fatal] expected block, got {:#?}", res),
                 },
-                Some(Frame::IteOtw(
-                    mdecls,
-                    _vdecls,
-                    span,
-                    cnd,
-                    (thn, thn_decls),
-                    (els, els_decls),
-                )) => match res {
-                    Command::Query(Query::Block(otw)) => {
-                        meta_decls = mdecls;
-                        match (thn.exits(), els.exits(), otw.exits()) {
-                            // All branches panic.
-                            (true, true, true) => {
-                                let _ = decls.merge(&thn_decls);
-                                let _ = decls.merge(&els_decls);
-                            }
+                Some(Frame::IteOtw(_vdecls, span, cnd, (thn, thn_decls), (els, els_decls))) => {
+                    match res {
+                        Command::Query(Query::Block(otw)) => {
+                            match (thn.exits(), els.exits(), otw.exits()) {
+                                // All branches panic.
+                                (true, true, true) => {
+                                    let _ = decls.merge(&thn_decls);
+                                    let _ = decls.merge(&els_decls);
+                                }
 
-                            // Only one branch does not panic.
-                            (true, true, false) => (),
-                            (false, true, true) => {
-                                decls = thn_decls;
-                            }
-                            (true, false, true) => {
-                                decls = els_decls;
-                            }
+                                // Only one branch does not panic.
+                                (true, true, false) => (),
+                                (false, true, true) => {
+                                    decls = thn_decls;
+                                }
+                                (true, false, true) => {
+                                    decls = els_decls;
+                                }
 
-                            // Two branches do not panic.
-                            (false, false, true) => {
-                                decls = thn_decls;
-                                let _ = decls.inter(&els_decls);
-                            }
-                            (false, true, false) => {
-                                let _ = decls.inter(&thn_decls);
-                            }
-                            (true, false, false) => {
-                                let _ = decls.inter(&els_decls);
-                            }
+                                // Two branches do not panic.
+                                (false, false, true) => {
+                                    decls = thn_decls;
+                                    let _ = decls.inter(&els_decls);
+                                }
+                                (false, true, false) => {
+                                    let _ = decls.inter(&thn_decls);
+                                }
+                                (true, false, false) => {
+                                    let _ = decls.inter(&els_decls);
+                                }
 
-                            // No branch panics.
-                            (false, false, false) => {
-                                let _ = decls.inter(&thn_decls);
-                                let _ = decls.inter(&els_decls);
+                                // No branch panics.
+                                (false, false, false) => {
+                                    let _ = decls.inter(&thn_decls);
+                                    let _ = decls.inter(&els_decls);
+                                }
                             }
-                        }
 
-                        res = Ite::new(span, cnd, thn, els, Some(otw)).into();
-                        continue 'go_up;
+                            res = Ite::new(span, cnd, thn, els, Some(otw)).into();
+                            continue 'go_up;
+                        }
+                        _ => panic!("[fatal] expected block, got {:#?}", res),
                     }
-                    _ => panic!("[fatal] expected block, got {:#?}", res),
-                },
+                }
                 None => match res {
                     Command::Query(Query::Block(b)) => {
                         return Ok(b.into());
@@ -0,0 +1,385 @@
+//! Jupyter kernel support, requires feature `jupyter`.
+//!
+//! A [`Kernel`] executes the hsmt cells of a notebook against a persistent [`Session`], and
+//! produces the `content` of the messages of the [Jupyter messaging protocol][protocol]:
+//!
+//! - [`kernel_info`] for `kernel_info_reply`,
+//! - [`Reply::execute_reply`] for `execute_reply`,
+//! - [`Reply::execute_result`] for `execute_result`, with plain text and HTML representations, and
+//! - [`Reply::error`] for `error`.
+//!
+//! Models and values are rendered as HTML tables, see [`step_html`], and so are
+//! [traces](trace_html).
+//!
+//! This module does not implement the transport layer of the protocol (ZeroMQ sockets, message
+//! framing and signing). Kernel front-ends are expected to route `execute_request`s to
+//! [`Kernel::execute`] and wrap the contents it produces in protocol messages.
+//!
+//! [protocol]: https://jupyter-client.readthedocs.io/en/stable/messaging.html
+//! (Jupyter messaging protocol)
+
+prelude!();
+
+use super::{Cell, Outcome, Session, Step};
+use crate::{
+    check::trace::{json_str, Trace},
+    meta::VERSION,
+};
+
+/// Name of the language of the kernel.
+pub const LANGUAGE: &str = "hsmt";
+/// Version of the messaging protocol the contents follow.
+pub const PROTOCOL_VERSION: &str = "5.3";
+
+/// Content of a `kernel_info_reply`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::script::jupyter::kernel_info;
+/// let info = kernel_info();
+/// assert!(info.starts_with(r#"{"status":"ok","protocol_version":"5.3","implementation":"mikino""#));
+/// assert!(info.contains(r#""language_info":{"name":"hsmt""#));
+/// ```
+pub fn kernel_info() -> String {
+    format!(
+        "{{\"status\":\"ok\",\"protocol_version\":{},\"implementation\":\"mikino\",\
+        \"implementation_version\":{},\"language_info\":{{\"name\":{},\"version\":{},\
+        \"mimetype\":\"text/x-rust\",\"file_extension\":\".rs\",\"codemirror_mode\":\"rust\"}},\
+        \"banner\":{}}}",
+        json_str(PROTOCOL_VERSION),
+        json_str(VERSION),
+        json_str(LANGUAGE),
+        json_str(VERSION),
+        json_str(&format!("mikino {} hsmt kernel", VERSION)),
+    )
+}
+
+/// Escapes a string for HTML.
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// HTML table with a header row.
+fn table(header: &[&str], rows: impl IntoIterator<Item = Vec<String>>) -> String {
+    let mut s = String::from("<table><thead><tr>");
+    for cell in header {
+        s.push_str(&format!("<th>{}</th>", escape(cell)));
+    }
+    s.push_str("</tr></thead><tbody>");
+    for row in rows {
+        s.push_str("<tr>");
+        for cell in row {
+            s.push_str(&format!("<td><code>{}</code></td>", escape(&cell)));
+        }
+        s.push_str("</tr>");
+    }
+    s.push_str("</tbody></table>");
+    s
+}
+
+/// HTML representation of a step, `None` if the step does not produce anything.
+///
+/// Models are rendered as a table of variables and values, values and minimal unsatisfiable
+/// subsets as tables of expressions.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{expr::{Cst, Typ}, parse::Span, script::{jupyter::step_html, Step}};
+/// let mut model = std::collections::BTreeMap::new();
+/// model.insert("x".to_string(), (Cst::from(7usize), Typ::Int));
+/// let step = Step::Model {
+///     span: Span::new(0, 12),
+///     token: "get_model!".into(),
+///     model,
+///     display: std::collections::BTreeMap::new(),
+///     incomplete: None,
+/// };
+/// assert_eq!(
+///     step_html(&step).unwrap(),
+///     "<div class=\"mikino-model\"><table><thead><tr><th>variable</th><th>value</th></tr>\
+///     </thead><tbody><tr><td><code>x</code></td><td><code>7</code></td></tr></tbody></table></div>",
+/// );
+/// ```
+pub fn step_html(step: &Step) -> Option<String> {
+    let s = match step {
+        Step::CheckRes(_, res, label) => format!(
+            "<div class=\"mikino-check-sat\"><code>{}</code>{}</div>",
+            match res {
+                super::CheckSatResEnum::True => "sat",
+                super::CheckSatResEnum::False => "unsat",
+                super::CheckSatResEnum::Timeout => "timeout",
+                super::CheckSatResEnum::Unknown => "unknown",
+            },
+            label
+                .as_ref()
+                .map(|label| format!(
+                    " <span class=\"mikino-label\">{}</span>",
+                    escape(&label.title)
+                ))
+                .unwrap_or_default()
+        ),
+        Step::Echo(echo) => {
+            if echo.msg.is_empty() {
+                return None;
+            }
+            format!("<div class=\"mikino-echo\">{}</div>", escape(&echo.msg))
+        }
+        Step::Model {
+            model,
+            display,
+            incomplete,
+            ..
+        } => {
+            let rows = model.iter().map(|(id, (cst, _))| {
+                let val = display.get(id).cloned().unwrap_or_else(|| cst.to_string());
+                vec![id.clone(), val]
+            });
+            let note = incomplete
+                .as_ref()
+                .map(|reason| {
+                    format!(
+                        "<div class=\"mikino-incomplete\">incomplete model: {}</div>",
+                        escape(reason)
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                "<div class=\"mikino-model\">{}{}</div>",
+                table(&["variable", "value"], rows),
+                note
+            )
+        }
+        Step::Eval { vals, .. } => {
+            let rows = vals
+                .iter()
+                .map(|(repr, val)| vec![expr::Expr::clean_repr(repr), val.to_string()]);
+            format!(
+                "<div class=\"mikino-values\">{}</div>",
+                table(&["expression", "value"], rows)
+            )
+        }
+        Step::Mus { exprs, .. } => {
+            let rows = exprs.iter().map(|expr| vec![expr.clone()]);
+            format!(
+                "<div class=\"mikino-mus\">{}</div>",
+                table(&["assertion"], rows)
+            )
+        }
+        Step::UnsatCore { core, .. } => {
+            let rows = core
+                .iter()
+                .map(|(name, expr)| vec![name.clone(), expr.clone()]);
+            format!(
+                "<div class=\"mikino-unsat-core\">{}</div>",
+                table(&["name", "assertion"], rows)
+            )
+        }
+        Step::Optimum {
+            objective,
+            maximize,
+            value,
+            ..
+        } => {
+            let kind = if *maximize { "maximum" } else { "minimum" };
+            let value = value
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "unsatisfiable".into());
+            let rows = std::iter::once(vec![expr::Expr::clean_repr(objective), value]);
+            format!(
+                "<div class=\"mikino-optimum\">{}</div>",
+                table(&["objective", kind], rows)
+            )
+        }
+        Step::Done(outcome) => outcome_html(outcome),
+        Step::Nothing => return None,
+    };
+    Some(s)
+}
+
+/// HTML representation of an outcome.
+fn outcome_html(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Exit(_, 0) => "<div class=\"mikino-exit\">success</div>".into(),
+        Outcome::Exit(_, code) => format!(
+            "<div class=\"mikino-exit\">done with exit code {}</div>",
+            code
+        ),
+        Outcome::Panic(_, msg) => format!(
+            "<div class=\"mikino-panic\">script panicked with <code>{}</code></div>",
+            escape(msg)
+        ),
+    }
+}
+
+/// HTML representation of a cell, the concatenation of its steps.
+pub fn cell_html(cell: &Cell) -> String {
+    let mut s = String::from("<div class=\"mikino-cell\">");
+    for step in cell.steps.iter() {
+        if let Some(html) = step_html(step) {
+            s.push_str(&html)
+        }
+    }
+    if let Some(outcome) = cell.outcome.as_ref() {
+        s.push_str(&outcome_html(outcome))
+    }
+    s.push_str("</div>");
+    s
+}
+
+/// HTML representation of a trace, one row per variable and one column per step.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}, script::jupyter::trace_html};
+/// let x = Var::new("x", Typ::Int);
+/// let mut trace = Trace::new();
+/// trace.entry(0).or_default().insert(x.clone(), Cst::from(0usize));
+/// trace.entry(1).or_default().insert(x, Cst::from(1usize));
+/// assert_eq!(
+///     trace_html(&trace),
+///     "<div class=\"mikino-trace\"><table><thead><tr><th>variable</th><th>0</th><th>1</th></tr>\
+///     </thead><tbody><tr><td><code>x</code></td><td><code>0</code></td>\
+///     <td><code>1</code></td></tr></tbody></table></div>",
+/// );
+/// ```
+pub fn trace_html(trace: &Trace) -> String {
+    let steps: Vec<String> = trace.keys().map(|step| step.to_string()).collect();
+    let mut header = vec!["variable"];
+    header.extend(steps.iter().map(|step| step as &str));
+    let vars: Set<&expr::Var> = trace.values().flat_map(|vals| vals.keys()).collect();
+    let rows = vars.into_iter().map(|var| {
+        let mut row = vec![var.id().to_string()];
+        for vals in trace.values() {
+            row.push(
+                vals.get(var)
+                    .map(|cst| cst.to_string())
+                    .unwrap_or_else(|| "_".into()),
+            )
+        }
+        row
+    });
+    format!("<div class=\"mikino-trace\">{}</div>", table(&header, rows))
+}
+
+/// Result of executing a cell with a [`Kernel`].
+#[derive(Debug)]
+pub struct Reply {
+    /// Execution count.
+    pub count: usize,
+    /// Executed cell, or error.
+    pub res: Res<Cell>,
+}
+impl Reply {
+    /// True if the cell executed successfully.
+    pub fn is_ok(&self) -> bool {
+        self.res.is_ok()
+    }
+
+    /// Content of the `execute_reply`.
+    pub fn execute_reply(&self) -> String {
+        match &self.res {
+            Ok(_) => format!(
+                "{{\"status\":\"ok\",\"execution_count\":{},\"user_expressions\":{{}}}}",
+                self.count
+            ),
+            Err(e) => format!(
+                "{{\"status\":\"error\",\"execution_count\":{},{}}}",
+                self.count,
+                error_fields(e)
+            ),
+        }
+    }
+
+    /// Content of the `execute_result`, `None` on errors and when the cell produced nothing.
+    pub fn execute_result(&self) -> Option<String> {
+        let cell = self.res.as_ref().ok()?;
+        if cell.steps.is_empty() && cell.outcome.is_none() {
+            return None;
+        }
+        Some(format!(
+            "{{\"execution_count\":{},\"data\":{{\"text/plain\":{},\"text/html\":{}}},\
+            \"metadata\":{{}}}}",
+            self.count,
+            json_str(&cell.pretty(())),
+            json_str(&cell_html(cell)),
+        ))
+    }
+
+    /// Content of the `error`, `None` if the cell executed successfully.
+    pub fn error(&self) -> Option<String> {
+        let e = self.res.as_ref().err()?;
+        Some(format!("{{{}}}", error_fields(e)))
+    }
+}
+
+/// JSON fields `ename`, `evalue` and `traceback` of an error.
+fn error_fields(e: &ErrorChain) -> String {
+    let traceback: Vec<String> = e.pretty(()).lines().map(json_str).collect();
+    format!(
+        "\"ename\":\"mikino\",\"evalue\":{},\"traceback\":[{}]",
+        json_str(&e.to_string()),
+        traceback.join(",")
+    )
+}
+
+/// An hsmt kernel, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{rsmt2::SmtConf, script::jupyter::Kernel};
+/// let mut kernel = Kernel::new(SmtConf::z3("z3"));
+/// let reply = kernel.execute("vars { x: int }\nassert { x > 7 }\ncheck_sat!()\nget_model!()");
+/// assert!(reply.is_ok());
+/// println!("{}", reply.execute_reply());
+/// if let Some(result) = reply.execute_result() {
+///     println!("{}", result)
+/// }
+/// ```
+pub struct Kernel {
+    /// Underlying session.
+    session: Session,
+}
+impl Kernel {
+    /// Constructor.
+    pub fn new(conf: SmtConf) -> Self {
+        Self::of_session(Session::new(conf))
+    }
+    /// Constructor from a session.
+    pub fn of_session(session: Session) -> Self {
+        Self { session }
+    }
+
+    /// Underlying session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+    /// Underlying session, mutable.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Executes a cell, the code of an `execute_request`.
+    pub fn execute(&mut self, code: &str) -> Reply {
+        let res = self.session.execute(code);
+        Reply {
+            count: self.session.count(),
+            res,
+        }
+    }
+}
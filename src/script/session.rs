@@ -0,0 +1,184 @@
+//! Persistent sessions, scripts executed cell by cell.
+//!
+//! A [`Session`] executes hsmt scripts one *cell* at a time, later cells can use the variables
+//! declared and the assertions made by earlier cells. This is what notebooks and REPLs need.
+//!
+//! Scripts are checked statically before running, so a cell only makes sense in the context of the
+//! cells before it. A session hence runs each new cell as the end of the script made of all the
+//! cells it kept so far, and only reports the [`Step`]s of the new cell. Running a cell thus re-runs
+//! the previous ones, which is fine for the small scripts mikino is meant for.
+//!
+//! A cell is kept only if it runs successfully and does not end the script, *i.e.* does not
+//! `exit` or `panic`. Failing cells can be fixed and executed again.
+
+prelude!();
+
+use super::{Outcome, Script, Step};
+use crate::check::engine::Cancel;
+
+/// Result of executing a cell in a [`Session`].
+#[derive(Debug, Clone)]
+pub struct Cell {
+    /// Execution count of the cell in its session, starts at `1`.
+    pub count: usize,
+    /// Text of the session up to and including this cell.
+    txt: String,
+    /// Start of the cell in `txt`.
+    offset: usize,
+    /// Steps of the cell, without the ones that did not produce anything.
+    pub steps: Vec<Step>,
+    /// Outcome, if the cell ended the script.
+    pub outcome: Option<Outcome>,
+}
+impl Cell {
+    /// Code of the cell.
+    pub fn code(&self) -> &str {
+        &self.txt[self.offset..]
+    }
+    /// Text of the session up to and including this cell, the spans of the steps refer to it.
+    pub fn txt(&self) -> &str {
+        &self.txt
+    }
+    /// True if the cell ended the script.
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Pretty, multi-line representation of the steps and outcome of the cell.
+    pub fn pretty(&self, style: impl Style) -> String {
+        let mut lines: Vec<String> = self
+            .steps
+            .iter()
+            .filter_map(|step| step.pretty(&self.txt, &style, false))
+            .filter(|s| !s.is_empty())
+            .collect();
+        if let Some(outcome) = self.outcome.as_ref() {
+            lines.push(outcome.pretty(&self.txt, &style, false))
+        }
+        lines.join("\n")
+    }
+}
+
+/// A persistent session, see the [module-level documentation](self).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{rsmt2::SmtConf, script::Session};
+/// let mut session = Session::new(SmtConf::z3("z3"));
+/// let cell = session.execute("vars { x: int }\nassert { x > 7 }").unwrap();
+/// assert!(cell.steps.is_empty());
+/// let cell = session.execute("check_sat!()\nget_model!()").unwrap();
+/// assert_eq!(cell.count, 2);
+/// println!("{}", cell.pretty(()));
+/// // Failing cells are not kept.
+/// assert!(session.execute("assert { y > 7 }").is_err());
+/// assert_eq!(session.cells().count(), 2);
+/// ```
+pub struct Session {
+    /// Solver configuration.
+    conf: SmtConf,
+    /// Directory where to tee the solver interactions, if any.
+    tee: Option<PathBuf>,
+    /// Cells kept so far.
+    cells: Vec<String>,
+    /// Number of cells executed so far, kept or not.
+    count: usize,
+    /// Cancellation handle, checked between steps.
+    cancel: Cancel,
+}
+impl Session {
+    /// Constructor, no tee.
+    pub fn new(conf: SmtConf) -> Self {
+        Self {
+            conf,
+            tee: None,
+            cells: vec![],
+            count: 0,
+            cancel: Cancel::new(),
+        }
+    }
+    /// Sets the tee directory, overwritten by each cell execution.
+    pub fn tee(mut self, tee: impl Into<PathBuf>) -> Self {
+        self.tee = Some(tee.into());
+        self
+    }
+
+    /// Sets the cancellation handle.
+    ///
+    /// Once cancelled, executing a cell fails before running its next step.
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Number of cells executed so far, including the ones that were not kept.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    /// Cells kept so far, in execution order.
+    pub fn cells(&self) -> impl Iterator<Item = &str> + '_ {
+        self.cells.iter().map(|cell| cell as &str)
+    }
+    /// Forgets all the cells, does not reset the execution count.
+    pub fn reset(&mut self) {
+        self.cells.clear()
+    }
+
+    /// Executes a cell.
+    ///
+    /// # Errors
+    ///
+    /// - when the cell does not parse or is ill-formed in the context of the session, or
+    /// - when running the script fails, or
+    /// - when the session is cancelled.
+    pub fn execute(&mut self, code: &str) -> Res<Cell> {
+        self.count += 1;
+        let mut txt = String::new();
+        for cell in self.cells.iter() {
+            txt.push_str(cell);
+            txt.push('\n');
+        }
+        let offset = txt.len();
+        txt.push_str(code);
+
+        let (steps, outcome) = self.run(&txt, offset)?;
+        if outcome.is_none() {
+            self.cells.push(code.into())
+        }
+        Ok(Cell {
+            count: self.count,
+            txt,
+            offset,
+            steps,
+            outcome,
+        })
+    }
+
+    /// Runs a script, yields the steps and explicit outcome located after `offset`.
+    fn run(&self, txt: &str, offset: usize) -> Res<(Vec<Step>, Option<Outcome>)> {
+        let block = parse::script(txt)?;
+        let script = super::build::doit(block).map_err(|e| e.into_error(txt))?;
+        let mut script = Script::new(self.conf.clone(), self.tee.clone(), &script, txt)?;
+
+        let mut steps = vec![];
+        loop {
+            if self.cancel.is_cancelled() {
+                bail!("cell execution cancelled")
+            }
+            let step = script.step()?;
+            let in_cell = step
+                .span()
+                .map(|span| span.start >= offset)
+                .unwrap_or(false);
+            match step {
+                Step::Done(outcome) => return Ok((steps, Some(outcome).filter(|_| in_cell))),
+                step => {
+                    if in_cell {
+                        steps.push(step)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,35 +1,43 @@
-//! Provides parser-equipped [`rsmt2::Solver`]s.
+//! Provides parser-equipped [`rsmt2::Solver`]s, and the solver [backends].
+//!
+//! Mikino talks to [Z3] by default, [CVC5] and [Yices 2] are also supported.
+//!
+//! Solvers can *tee* their interactions to a file: the commands they send, and the answers to
+//! their check-sat commands as `; response: <answer>` comments. Such logs can be replayed later
+//! with [`dialogue`], for instance to debug failures that depend on the solver version.
+//!
+//! [backends]: Backend (The Backend trait)
+//! [Z3]: https://github.com/Z3Prover/z3 (Z3 on github)
+//! [CVC5]: https://cvc5.github.io (CVC5 official page)
+//! [Yices 2]: https://yices.csl.sri.com (Yices 2 official page)
 
 prelude!(expr::*, parse::Parser);
 
+use rsmt2::print::{Expr2Smt, Sym2Smt};
+
+pub mod dialogue;
+
 /// SMT-LIB parser for *unrolled* expressions, idents, types...
 #[derive(Debug, Clone, Copy)]
 pub struct StatefulParser;
 
 impl<'a> rsmt2::parse::IdentParser<(String, Option<Unroll>), Typ, &'a str> for StatefulParser {
     fn parse_ident(self, input: &'a str) -> SmtRes<(String, Option<Unroll>)> {
-        let input = input.trim();
-        let subs = input.split('@');
-        let mut subs = subs.into_iter();
-        let name = subs
-            .next()
-            .ok_or_else(|| format!("unexpected model variable `{}`", input))?;
-        let step = if let Some(Ok(step)) = subs.next().map(|s| usize::from_str_radix(s, 10)) {
-            Some(step)
-        } else {
-            None
-        };
-        Ok((name.into(), step))
+        decls::demangle(input).map_err(|e| e.to_string().into())
     }
     fn parse_type(self, input: &'a str) -> SmtRes<Typ> {
-        match input {
-            "Bool" => Ok(Typ::Bool),
-            "Int" => Ok(Typ::Int),
-            "Real" => Ok(Typ::Rat),
-            _ => bail!("unexpected type string `{}`", input),
+        match Typ::of_smt_str(input) {
+            Some(typ) => Ok(typ),
+            None => bail!("unexpected type string `{}`", input),
         }
     }
 }
+/// Parses the names of named assertions, used for unsat cores.
+impl<'a> rsmt2::parse::SymParser<String, &'a [u8]> for StatefulParser {
+    fn parse_sym(self, input: &'a [u8]) -> SmtRes<String> {
+        String::from_utf8(input.to_vec()).map_err(|e| format!("illegal symbol: {}", e).into())
+    }
+}
 impl<'a, Br: std::io::BufRead>
     rsmt2::parse::ModelParser<
         (String, Option<Unroll>),
@@ -55,21 +63,54 @@ impl<'a, Br: std::io::BufRead>
     }
 }
 
+impl<'a, Br: std::io::BufRead>
+    rsmt2::parse::ValueParser<Either<Cst, String>, &'a mut RSmtParser<Br>> for StatefulParser
+{
+    fn parse_value(self, input: &'a mut RSmtParser<Br>) -> SmtRes<Either<Cst, String>> {
+        let sexpr = input.get_sexpr()?;
+        let mut parser = Parser::new(sexpr);
+        if let Ok(Some(cst)) = parser.try_cst() {
+            Ok(Either::Left(cst))
+        } else {
+            Ok(Either::Right(sexpr.into()))
+        }
+    }
+}
+/// Expressions are ignored when parsing values, like for [`StatelessParser`].
+impl<'a, Br: std::io::BufRead> rsmt2::parse::ExprParser<(), Unroll, &'a mut RSmtParser<Br>>
+    for StatefulParser
+{
+    fn parse_expr(self, input: &'a mut RSmtParser<Br>, _: Unroll) -> SmtRes<()> {
+        let _sexpr = input.get_sexpr()?;
+        Ok(())
+    }
+}
+
+/// Interpolants are retrieved as strings, see [`check::interpolation`].
+impl<'a, Br: std::io::BufRead> rsmt2::parse::ExprParser<String, (), &'a mut RSmtParser<Br>>
+    for StatefulParser
+{
+    fn parse_expr(self, input: &'a mut RSmtParser<Br>, (): ()) -> SmtRes<String> {
+        Ok(input.get_sexpr()?.into())
+    }
+}
+
 /// SMT-LIB parser for *unrolled* expressions, idents, types...
 #[derive(Debug, Clone, Copy)]
 pub struct StatelessParser;
 
 impl<'a> rsmt2::parse::IdentParser<String, Typ, &'a str> for StatelessParser {
     fn parse_ident(self, input: &'a str) -> SmtRes<String> {
-        let input = input.trim();
-        Ok(input.into())
+        match decls::demangle(input) {
+            Ok((ident, None)) => Ok(ident),
+            Ok((ident, Some(step))) => Ok(format!("{}@{}", ident, step)),
+            Err(e) => Err(e.to_string().into()),
+        }
     }
     fn parse_type(self, input: &'a str) -> SmtRes<Typ> {
-        match input {
-            "Bool" => Ok(Typ::Bool),
-            "Int" => Ok(Typ::Int),
-            "Real" => Ok(Typ::Rat),
-            _ => bail!("unexpected type string `{}`", input),
+        match Typ::of_smt_str(input) {
+            Some(typ) => Ok(typ),
+            None => bail!("unexpected type string `{}`", input),
         }
     }
 }
@@ -106,6 +147,13 @@ impl<'a, Br: std::io::BufRead> rsmt2::parse::ValueParser<Cst, &'a mut RSmtParser
     }
 }
 
+/// Parses the names of named assertions, used for unsat cores.
+impl<'a> rsmt2::parse::SymParser<String, &'a [u8]> for StatelessParser {
+    fn parse_sym(self, input: &'a [u8]) -> SmtRes<String> {
+        String::from_utf8(input.to_vec()).map_err(|e| format!("illegal symbol: {}", e).into())
+    }
+}
+
 /// So yeah, we're completely ignoring expressions when parsing values.
 impl<'a, Br: std::io::BufRead> rsmt2::parse::ExprParser<(), (), &'a mut RSmtParser<Br>>
     for StatelessParser
@@ -116,9 +164,256 @@ impl<'a, Br: std::io::BufRead> rsmt2::parse::ExprParser<(), (), &'a mut RSmtPars
     }
 }
 
+/// An SMT solver backend, see [`BACKENDS`].
+///
+/// A backend knows how to run a solver, and what to tell it before anything else. Solvers are
+/// spawned from an [`SmtConf`], the backend of a configuration is given by [`backend_of`].
+pub trait Backend: fmt::Debug + Sync {
+    /// Name of the backend, as used in configurations.
+    fn name(&self) -> &'static str;
+    /// Default solver command.
+    fn default_cmd(&self) -> &'static str;
+    /// Solver configuration for a command, with model production and incrementality active.
+    fn conf(&self, cmd: &str) -> SmtConf;
+    /// Logic set right after spawning the solver, `None` to let the solver infer it.
+    fn logic(&self) -> Option<&'static str>;
+    /// True if a solver configuration is one of this backend.
+    fn handles(&self, conf: &SmtConf) -> bool;
+    /// Command-line options making the solver give up on queries running longer than `timeout`,
+    /// empty if the backend cannot do it.
+    fn timeout_options(&self, timeout: time::Duration) -> Vec<String>;
+    /// Command-line options making the solver give up on queries when it uses more than `mb`
+    /// megabytes, empty if the backend cannot do it.
+    fn memory_options(&self, mb: u64) -> Vec<String>;
+    /// True if the solver supports Z3's `minimize` and `maximize` optimization commands.
+    fn optimizes(&self) -> bool {
+        false
+    }
+
+    /// Adds the options for some per-query limits to a solver configuration.
+    ///
+    /// Fails if the backend cannot enforce the memory limit. Timeouts are also enforced on
+    /// mikino's side when checking systems, see [`check::Config::query_timeout`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::solver::{Backend, Yices2, Z3};
+    /// let mut conf = Z3.conf("z3");
+    /// Z3.limit(&mut conf, Some(std::time::Duration::from_millis(1500)), Some(512)).unwrap();
+    /// assert!(conf.get_options().iter().any(|opt| opt == "-t:1500"));
+    /// assert!(conf.get_options().iter().any(|opt| opt == "-memory:512"));
+    ///
+    /// // Yices has a timeout in seconds, and no memory limit.
+    /// let mut conf = Yices2.conf("yices-smt2");
+    /// Yices2.limit(&mut conf, Some(std::time::Duration::from_millis(1500)), None).unwrap();
+    /// assert!(conf.get_options().iter().any(|opt| opt == "--timeout=2"));
+    /// let err = Yices2.limit(&mut conf, None, Some(512)).unwrap_err();
+    /// assert_eq!(err.to_string(), "the yices2 backend does not support memory limits");
+    /// ```
+    fn limit(
+        &self,
+        conf: &mut SmtConf,
+        timeout: Option<time::Duration>,
+        mb: Option<u64>,
+    ) -> Res<()> {
+        let timeout = timeout.map(|timeout| self.timeout_options(timeout));
+        let memory = mb.map(|mb| self.memory_options(mb));
+        if memory.as_ref().is_some_and(Vec::is_empty) {
+            bail!("the {} backend does not support memory limits", self.name())
+        }
+        for opt in timeout.into_iter().chain(memory).flatten() {
+            conf.option(opt);
+        }
+        Ok(())
+    }
+
+    /// Solver configuration for a command followed by whitespace-separated arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::solver::{Backend, Cvc5};
+    /// let conf = Cvc5.conf_of_cmd("cvc5 --nl-ext-tplanes").unwrap();
+    /// assert_eq!(conf.get_cmd(), "cvc5");
+    /// assert!(conf.get_options().iter().any(|opt| opt == "--nl-ext-tplanes"));
+    /// assert!(Cvc5.conf_of_cmd("  ").is_err());
+    /// ```
+    fn conf_of_cmd(&self, cmd: &str) -> Res<SmtConf> {
+        let mut args = cmd.split_whitespace();
+        let mut conf = match args.next() {
+            Some(cmd) => self.conf(cmd),
+            None => bail!("illegal {} command `{}`", self.name(), cmd),
+        };
+        for arg in args {
+            conf.option(arg);
+        }
+        Ok(conf)
+    }
+}
+
+/// Z3 backend, the default one.
+#[derive(Debug, Clone, Copy)]
+pub struct Z3;
+impl Backend for Z3 {
+    fn name(&self) -> &'static str {
+        "z3"
+    }
+    fn default_cmd(&self) -> &'static str {
+        "z3"
+    }
+    fn conf(&self, cmd: &str) -> SmtConf {
+        SmtConf::z3(cmd)
+    }
+    fn logic(&self) -> Option<&'static str> {
+        None
+    }
+    fn handles(&self, conf: &SmtConf) -> bool {
+        conf.style() == rsmt2::SmtStyle::Z3
+    }
+    fn timeout_options(&self, timeout: time::Duration) -> Vec<String> {
+        vec![format!("-t:{}", timeout.as_millis())]
+    }
+    fn memory_options(&self, mb: u64) -> Vec<String> {
+        vec![format!("-memory:{}", mb)]
+    }
+    fn optimizes(&self) -> bool {
+        true
+    }
+}
+
+/// CVC5 backend.
+///
+/// Runs CVC5 with rsmt2's CVC4 configuration, which does not use `check-sat-assuming`: [`mus`]
+/// and scripts' `check_sat` with assumptions are not available. Memory limits are not supported,
+/// see [`Backend::limit`].
+#[derive(Debug, Clone, Copy)]
+pub struct Cvc5;
+impl Backend for Cvc5 {
+    fn name(&self) -> &'static str {
+        "cvc5"
+    }
+    fn default_cmd(&self) -> &'static str {
+        "cvc5"
+    }
+    fn conf(&self, cmd: &str) -> SmtConf {
+        let mut conf = SmtConf::cvc4(cmd);
+        conf.models();
+        conf.incremental();
+        conf
+    }
+    fn logic(&self) -> Option<&'static str> {
+        Some("ALL")
+    }
+    fn handles(&self, conf: &SmtConf) -> bool {
+        conf.style() == rsmt2::SmtStyle::CVC4
+    }
+    fn timeout_options(&self, timeout: time::Duration) -> Vec<String> {
+        vec![format!("--tlimit-per={}", timeout.as_millis())]
+    }
+    fn memory_options(&self, _mb: u64) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Yices 2 backend, requires Yices 2.6.2 or later.
+///
+/// Yices does not support datatypes, and hence enumerations, nor quantifiers. Timeouts are in
+/// whole seconds, memory limits are not supported.
+#[derive(Debug, Clone, Copy)]
+pub struct Yices2;
+impl Backend for Yices2 {
+    fn name(&self) -> &'static str {
+        "yices2"
+    }
+    fn default_cmd(&self) -> &'static str {
+        "yices-smt2"
+    }
+    fn conf(&self, cmd: &str) -> SmtConf {
+        let mut conf = SmtConf::yices_2(cmd);
+        conf.models();
+        conf.incremental();
+        conf
+    }
+    fn logic(&self) -> Option<&'static str> {
+        Some("ALL")
+    }
+    fn handles(&self, conf: &SmtConf) -> bool {
+        conf.style() == rsmt2::SmtStyle::Yices2
+    }
+    fn timeout_options(&self, timeout: time::Duration) -> Vec<String> {
+        // Whole seconds only, rounded up so that the limit is never tighter than requested.
+        let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        vec![format!("--timeout={}", std::cmp::max(secs, 1))]
+    }
+    fn memory_options(&self, _mb: u64) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// All the backends.
+pub const BACKENDS: [&dyn Backend; 3] = [&Z3, &Cvc5, &Yices2];
+
+/// Backend from its name, see [`Backend::name`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::solver;
+/// let backend = solver::backend("cvc5").unwrap();
+/// assert_eq!(backend.default_cmd(), "cvc5");
+/// assert_eq!(solver::backend_of(&backend.conf("my_cvc5")).name(), "cvc5");
+/// assert!(solver::backend("mathsat").is_none());
+/// ```
+pub fn backend(name: impl AsRef<str>) -> Option<&'static dyn Backend> {
+    let name = name.as_ref();
+    BACKENDS
+        .iter()
+        .copied()
+        .find(|backend| backend.name() == name)
+}
+
+/// Backend of a solver configuration, [`Z3`] if no backend handles it.
+pub fn backend_of(conf: &SmtConf) -> &'static dyn Backend {
+    BACKENDS
+        .iter()
+        .copied()
+        .find(|backend| backend.handles(conf))
+        .unwrap_or(&Z3)
+}
+
+/// Processes spawned by the current thread, `None` if unknown.
+///
+/// rsmt2 owns the solver process and does not expose its process ID, which is the only way to
+/// kill the solver from another thread. On Linux, the kernel lists the children of a thread in
+/// `/proc/thread-self/children`: the solver is the only new child after rsmt2 spawns it.
+#[cfg(target_os = "linux")]
+fn spawned_by_thread() -> Option<Set<u32>> {
+    let children = std::fs::read_to_string("/proc/thread-self/children").ok()?;
+    children
+        .split_whitespace()
+        .map(|pid| pid.parse().ok())
+        .collect()
+}
+/// Processes spawned by the current thread, unknown outside of Linux.
+#[cfg(not(target_os = "linux"))]
+fn spawned_by_thread() -> Option<Set<u32>> {
+    None
+}
+
 /// Wrapper for rsmt2's solver equipped with one of our parser.
 pub struct Solver<P> {
+    /// Cancellation handle watching the solver, if any, see [`Self::watch`].
+    ///
+    /// Comes first so that it is dropped before the solver is killed.
+    watch: Option<check::engine::Watch>,
     solver: SmtSolver<P>,
+    /// Logic of the solver's backend, if any.
+    logic: Option<&'static str>,
+    /// True if the solver's backend supports optimization commands, see [`Backend::optimizes`].
+    optimizes: bool,
+    /// Process ID of the solver, if known.
+    pid: Option<u32>,
 }
 /// Stateful solver, can parse stateful variables.
 pub type SFSolver = Solver<StatefulParser>;
@@ -130,13 +425,186 @@ impl<P> Solver<P> {
     pub fn new_with(mut conf: SmtConf, parser: P, tee: Option<PathBuf>) -> Res<Self> {
         conf.check_success();
 
-        let mut solver = conf
+        let backend = backend_of(&conf);
+        let before = spawned_by_thread();
+        let solver = conf
             .spawn(parser)
-            .chain_err(|| "while spawning z3 solver")?;
+            .chain_err(|| format!("while spawning {} solver", backend.name()))?;
+        let pid = match (before, spawned_by_thread()) {
+            (Some(before), Some(after)) => {
+                let mut new = after.difference(&before);
+                match (new.next(), new.next()) {
+                    (Some(pid), None) => Some(*pid),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let mut slf = Self {
+            watch: None,
+            solver,
+            logic: backend.logic(),
+            optimizes: backend.optimizes(),
+            pid,
+        };
         if let Some(path) = tee {
-            solver.path_tee(path)?
+            slf.solver.path_tee(path)?
+        }
+        slf.set_backend_logic()?;
+        Ok(slf)
+    }
+
+    /// Sets the logic of the solver's backend, if any.
+    fn set_backend_logic(&mut self) -> Res<()> {
+        if let Some(logic) = self.logic {
+            self.solver
+                .set_custom_logic(logic)
+                .chain_err(|| format!("while setting logic `{}`", logic))?;
+        }
+        Ok(())
+    }
+
+    /// True if the solver supports optimization commands, see [`Backend::optimizes`].
+    pub fn optimizes(&self) -> bool {
+        self.optimizes
+    }
+
+    /// Process ID of the solver, `None` if unknown.
+    ///
+    /// Only known on Linux, and only if the current thread spawned no other process while
+    /// spawning the solver.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Kills the solver process when `cancel` is cancelled, until the solver is [killed].
+    ///
+    /// Also kills it when a check-sat query runs longer than the query timeout of `cancel`, if
+    /// any: the query then fails with a [timeout](rsmt2::errors::ErrorKind::Timeout).
+    ///
+    /// Does nothing if the [process ID](Self::pid) of the solver is unknown, in particular outside
+    /// of Linux: the solver then finishes its current query before the caller notices the
+    /// cancellation, and queries are only limited by the solver's own
+    /// [limits](Backend::limit).
+    ///
+    /// [killed]: Self::kill
+    pub fn watch(&mut self, cancel: &check::engine::Cancel) {
+        self.watch = self.pid.map(|pid| cancel.watch(pid));
+    }
+
+    /// Kills the solver, see [`SmtSolver::kill`].
+    ///
+    /// Stops [watching](Self::watch) it first, since its process ID can be reused afterwards.
+    pub fn kill(&mut self) -> SmtRes<()> {
+        self.watch = None;
+        self.solver.kill()
+    }
+
+    /// Resets the solver, and sets the logic of its backend again.
+    pub fn reset(&mut self) -> SmtRes<()> {
+        self.solver.reset()?;
+        if let Some(logic) = self.logic {
+            self.solver.set_custom_logic(logic)?
+        }
+        Ok(())
+    }
+
+    /// Check-sat command, see [`SmtSolver::check_sat`].
+    ///
+    /// The answer is logged in the tee-ed file, if any.
+    pub fn check_sat(&mut self) -> SmtRes<bool> {
+        let res = self.query(|solver| solver.check_sat());
+        self.log_response(&res)?;
+        res
+    }
+    /// Check-sat command with activation literals, see [`SmtSolver::check_sat_act`].
+    ///
+    /// The answer is logged in the tee-ed file, if any.
+    pub fn check_sat_act<Actlits>(&mut self, actlits: Actlits) -> SmtRes<bool>
+    where
+        Actlits: IntoIterator,
+        Actlits::Item: Sym2Smt,
+    {
+        let res = self.query(|solver| solver.check_sat_act(actlits));
+        self.log_response(&res)?;
+        res
+    }
+    /// Check-sat-assuming command, see [`SmtSolver::check_sat_assuming`].
+    ///
+    /// The answer is logged in the tee-ed file, if any.
+    pub fn check_sat_assuming<Idents>(&mut self, idents: Idents) -> SmtRes<bool>
+    where
+        Idents: IntoIterator,
+        Idents::Item: Sym2Smt,
+    {
+        let res = self.query(|solver| solver.check_sat_assuming(idents));
+        self.log_response(&res)?;
+        res
+    }
+    /// Runs a query, kills the solver if it reaches the query timeout of the handle
+    /// [watching](Self::watch) the solver.
+    fn query<T>(&mut self, query: impl FnOnce(&mut SmtSolver<P>) -> SmtRes<T>) -> SmtRes<T> {
+        let watchdog = self.watch.as_ref().and_then(check::engine::Watch::watchdog);
+        let res = query(&mut self.solver);
+        if watchdog.is_some_and(check::engine::Watchdog::stop) {
+            return Err(rsmt2::errors::ErrorKind::Timeout.into());
+        }
+        res
+    }
+    /// Logs the answer to a check-sat command in the tee-ed file, if any.
+    ///
+    /// See [`dialogue::RESPONSE`] for the format.
+    fn log_response(&mut self, res: &SmtRes<bool>) -> SmtRes<()> {
+        use rsmt2::errors::ErrorKind as EK;
+        if !self.solver.is_teed() {
+            return Ok(());
+        }
+        let response = match res {
+            Ok(true) => "sat".to_string(),
+            Ok(false) => "unsat".to_string(),
+            Err(e) => match e.kind() {
+                EK::Unknown => "unknown".to_string(),
+                EK::Timeout => "timeout".to_string(),
+                _ => format!("error {}", e.to_string().replace('\n', " ")),
+            },
+        };
+        self.solver
+            .comment(&format!("{}{}", dialogue::RESPONSE, response))
+    }
+
+    /// Sends a command rsmt2 has no function for, the solver must answer `success`.
+    ///
+    /// The command follows a `set-info`, closes with its last paren, and we parse the second
+    /// `success` ourselves.
+    pub fn command(&mut self, cmd: &str) -> SmtRes<()> {
+        let cmd = cmd.trim_end();
+        self.solver
+            .set_info(&format!(":source |mikino|)\n{}", &cmd[..cmd.len() - 1]))
+            .and_then(|()| self.solver.check_success())
+    }
+
+    /// Declares enumeration types as SMT-LIB datatypes, see [`trans::Decls::enums`].
+    ///
+    /// Variant `V` of enumeration `E` is the nullary constructor `|E::V|`.
+    pub fn declare_enums(&mut self, enums: &Map<String, Vec<String>>) -> Res<()> {
+        if enums.is_empty() {
+            return Ok(());
+        }
+        // rsmt2's `declare_datatypes` waits for `success` in the middle of the command, which
+        // hangs since we activate print-success.
+        self.command(&smt2::declare_enums(enums))
+            .chain_err(|| "while declaring enumeration types")?;
+        Ok(())
+    }
+
+    /// Declares uninterpreted functions, see [`trans::Decls::funs`].
+    pub fn declare_funs(&mut self, funs: &Map<String, std::sync::Arc<expr::Fun>>) -> Res<()> {
+        for fun in funs.values() {
+            self.solver
+                .declare_fun(&**fun, fun.args(), fun.out())
+                .chain_err(|| format!("while declaring function `{}`", fun.name()))?;
         }
-        Ok(Self { solver })
+        Ok(())
     }
 }
 impl SFSolver {
@@ -162,3 +630,204 @@ impl<P> DerefMut for Solver<P> {
         &mut self.solver
     }
 }
+
+/// Minimal unsatisfiable subset of some named assertions, by deletion-based shrinking.
+///
+/// Each assertion is guarded by an activation literal in a fresh scope, which is popped before
+/// returning: the solver's assertion stack is left as it was. The result is the names of a subset
+/// of the assertions that is unsatisfiable together with the solver's current assertions, and such
+/// that dropping any of its elements makes it satisfiable. Names appear in the same order as in
+/// `assertions`.
+///
+/// Shrinking tries to drop each assertion once, which takes one check-sat per assertion.
+///
+/// Fails if the assertions are satisfiable, or if the solver answers `unknown`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mikino_api::{parse, rsmt2::SmtConf, solver::{self, SLSolver}};
+/// let mut solver = SLSolver::new(SmtConf::z3("z3"), None).unwrap();
+/// solver.declare_const("x", "Int").unwrap();
+/// let assertions = vec![
+///     ("positive", "(> x 0)"),
+///     ("small", "(< x 10)"),
+///     ("negative", "(< x 0)"),
+/// ];
+/// let mus = solver::mus(&mut solver, assertions, ()).unwrap();
+/// assert_eq!(mus, vec!["positive", "negative"]);
+/// ```
+pub fn mus<P, N, E, Info>(
+    solver: &mut Solver<P>,
+    assertions: impl IntoIterator<Item = (N, E)>,
+    info: Info,
+) -> Res<Vec<N>>
+where
+    E: Expr2Smt<Info>,
+    Info: Copy,
+{
+    solver.push(1).chain_err(|| "while opening the MUS scope")?;
+    let res = mus_in_scope(solver, assertions, info);
+    solver.pop(1).chain_err(|| "while closing the MUS scope")?;
+    res
+}
+
+/// Computes the MUS, must be called in a dedicated scope.
+fn mus_in_scope<P, N, E, Info>(
+    solver: &mut Solver<P>,
+    assertions: impl IntoIterator<Item = (N, E)>,
+    info: Info,
+) -> Res<Vec<N>>
+where
+    E: Expr2Smt<Info>,
+    Info: Copy,
+{
+    let mut guarded = vec![];
+    for (idx, (name, expr)) in assertions.into_iter().enumerate() {
+        let actlit = solver.get_actlit()?;
+        solver
+            .assert_act_with(&actlit, expr, info)
+            .chain_err(|| format!("while asserting expression #{}", idx + 1))?;
+        guarded.push((name, actlit));
+    }
+
+    if solver.check_sat_act(guarded.iter().map(|(_, actlit)| actlit))? {
+        bail!("assertions are satisfiable")
+    }
+
+    let mut keep = vec![true; guarded.len()];
+    for idx in 0..guarded.len() {
+        keep[idx] = false;
+        let actlits = guarded
+            .iter()
+            .zip(keep.iter())
+            .filter_map(|((_, actlit), keep)| if *keep { Some(actlit) } else { None });
+        if solver.check_sat_act(actlits)? {
+            keep[idx] = true
+        }
+    }
+
+    Ok(guarded
+        .into_iter()
+        .zip(keep)
+        .filter_map(|((name, _), keep)| if keep { Some(name) } else { None })
+        .collect())
+}
+
+/// SMT-LIB 2 commands as strings, used to export problems without a solver.
+///
+/// The commands are the ones the solvers of this module send, see for instance
+/// [`Sys::to_smt2_bmc`](crate::trans::Sys::to_smt2_bmc) and [`script::to_smt2`].
+pub(crate) mod smt2 {
+    prelude!(expr::*);
+
+    use rsmt2::print::{Expr2Smt, Sort2Smt, Sym2Smt};
+
+    /// Runs a printer on a buffer, yields the result.
+    fn print(f: impl FnOnce(&mut Vec<u8>) -> SmtRes<()>) -> Res<String> {
+        let mut buf: Vec<u8> = vec![];
+        f(&mut buf)?;
+        match String::from_utf8(buf) {
+            Ok(s) => Ok(s),
+            Err(e) => bail!("illegal UTF-8 in SMT-LIB 2 output: {}", e),
+        }
+    }
+
+    /// `declare-datatypes` command for enumeration types, see [`Solver::declare_enums`].
+    ///
+    /// [`Solver::declare_enums`]: super::Solver::declare_enums
+    pub fn declare_enums(enums: &Map<String, Vec<String>>) -> String {
+        let mut cmd = "(declare-datatypes (".to_string();
+        for name in enums.keys() {
+            cmd.push_str(&format!(" ({} 0)", name))
+        }
+        cmd.push_str(" ) (");
+        for (name, variants) in enums.iter() {
+            cmd.push_str(" (");
+            for variant in variants {
+                cmd.push_str(&format!(" |{}::{}|", name, variant))
+            }
+            cmd.push_str(" )")
+        }
+        cmd.push_str(" ) )");
+        cmd
+    }
+
+    /// Declarations of a [`trans::Decls`]: enumerations, functions and variables.
+    ///
+    /// Variables are declared for each step of `steps`, or once without a step if `steps` is
+    /// `None`.
+    pub fn declare(decls: &trans::Decls, steps: Option<&[Unroll]>) -> Res<String> {
+        let mut s = String::new();
+        if !decls.enums().is_empty() {
+            s.push_str(&declare_enums(decls.enums()));
+            s.push('\n');
+        }
+        for fun in decls.funs().values() {
+            s.push_str(&print(|w| {
+                write!(w, "(declare-fun ")?;
+                fun.sym_to_smt2(w, ())?;
+                write!(w, " (")?;
+                for (idx, typ) in fun.args().iter().enumerate() {
+                    if idx > 0 {
+                        write!(w, " ")?;
+                    }
+                    typ.sort_to_smt2(w)?;
+                }
+                write!(w, ") ")?;
+                fun.out().sort_to_smt2(w)?;
+                writeln!(w, ")")?;
+                Ok(())
+            })?);
+        }
+        let declare_const = |var: &Var, step: Option<Unroll>| {
+            print(|w| {
+                write!(w, "(declare-const ")?;
+                match step {
+                    Some(step) => var.sym_to_smt2(w, step)?,
+                    None => var.sym_to_smt2(w, ())?,
+                }
+                write!(w, " ")?;
+                var.typ().sort_to_smt2(w)?;
+                writeln!(w, ")")?;
+                Ok(())
+            })
+        };
+        match steps {
+            Some(steps) => {
+                for step in steps {
+                    for var in decls.all() {
+                        s.push_str(&declare_const(&var, Some(*step))?)
+                    }
+                }
+            }
+            None => {
+                for var in decls.all() {
+                    s.push_str(&declare_const(&var, None)?)
+                }
+            }
+        }
+        Ok(s)
+    }
+
+    /// An expression, with some print information.
+    pub fn expr<Info>(expr: &impl Expr2Smt<Info>, info: Info) -> Res<String> {
+        print(|w| expr.expr_to_smt2(w, info))
+    }
+
+    /// An `assert` command.
+    pub fn assert<Info>(expr: &impl Expr2Smt<Info>, info: Info) -> Res<String> {
+        Ok(format!("(assert {})\n", self::expr(expr, info)?))
+    }
+
+    /// Symbol for the name of an assertion, quoted unless it already is or is a legal identifier.
+    pub fn name(name: &str) -> String {
+        if name.starts_with('|')
+            || (decls::check_ident(name).is_ok() && !decls::SMT_BUILTINS.contains(&name))
+        {
+            name.into()
+        } else {
+            format!("|{}|", name)
+        }
+    }
+}
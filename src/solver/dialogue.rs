@@ -0,0 +1,374 @@
+//! Replay of tee-ed solver interactions.
+//!
+//! [Solvers](super::Solver) tee-ed to a file log the commands they send, and the answer to each
+//! check-sat command as a comment. A [`Dialogue`] is such a log: the commands in order, with the
+//! logged answers of the check-sat commands. [Replaying](Dialogue::replay) it feeds the commands
+//! to a fresh solver, and compares its answers to the logged ones. This helps debugging failures
+//! that depend on the solver, its version, or its options.
+//!
+//! Only the answers to check-sat commands are logged and compared. Models, values and unsat cores
+//! are not, they usually differ between solvers anyway.
+
+crate::prelude!();
+
+/// Prefix of the comments logging the answers to check-sat commands, after the `; `.
+pub const RESPONSE: &str = "response: ";
+
+/// A command of a dialogue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exchange {
+    /// Command, as sent to the solver.
+    pub command: String,
+    /// Logged answer, only for check-sat commands.
+    pub response: Option<String>,
+}
+impl Exchange {
+    /// True if the command is a check-sat or a check-sat-assuming.
+    pub fn is_check_sat(&self) -> bool {
+        is_check_sat(&self.command)
+    }
+}
+
+/// True if a command is a check-sat or a check-sat-assuming.
+fn is_check_sat(command: &str) -> bool {
+    command
+        .strip_prefix('(')
+        .map(|command| command.trim_start().starts_with("check-sat"))
+        .unwrap_or(false)
+}
+
+/// Top-level item of an SMT-LIB 2 text.
+enum Item<'txt> {
+    /// Comment, without the leading `;`.
+    Comment(&'txt str),
+    /// S-expression or atom.
+    Sexpr(&'txt str),
+}
+
+/// Offset of the end of the line containing offset `idx`.
+fn line_end(txt: &str, idx: usize) -> usize {
+    txt[idx..].find('\n').map_or(txt.len(), |end| idx + end)
+}
+
+/// Offset right after the string or quoted symbol starting at offset `idx`.
+///
+/// Strings escape `"` as `""`, quoted symbols cannot contain `|`.
+fn quoted_end(txt: &str, idx: usize) -> Res<usize> {
+    let bytes = txt.as_bytes();
+    let delim = bytes[idx];
+    let mut end = idx + 1;
+    loop {
+        match bytes.get(end) {
+            None => bail!(
+                "unterminated `{}` starting at offset {}",
+                delim as char,
+                idx
+            ),
+            Some(b'"') if delim == b'"' && bytes.get(end + 1) == Some(&b'"') => end += 2,
+            Some(b) if *b == delim => return Ok(end + 1),
+            Some(_) => end += 1,
+        }
+    }
+}
+
+/// Splits an SMT-LIB 2 text in top-level comments and s-expressions.
+///
+/// Works on bytes, which is fine since all delimiters are ASCII.
+fn items(txt: &str) -> Res<Vec<Item<'_>>> {
+    let bytes = txt.as_bytes();
+    let mut res = vec![];
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let start = idx;
+        match bytes[idx] {
+            b if b.is_ascii_whitespace() => idx += 1,
+            b';' => {
+                idx = line_end(txt, idx);
+                res.push(Item::Comment(&txt[start + 1..idx]));
+            }
+            b')' => bail!("unexpected closing parenthesis at offset {}", idx),
+            b'(' => {
+                let mut depth: usize = 0;
+                loop {
+                    match bytes.get(idx) {
+                        None => bail!("unclosed parenthesis starting at offset {}", start),
+                        Some(b'(') => depth += 1,
+                        Some(b')') if depth == 1 => break,
+                        Some(b')') => depth -= 1,
+                        Some(b'"' | b'|') => idx = quoted_end(txt, idx)? - 1,
+                        Some(b';') => idx = line_end(txt, idx) - 1,
+                        Some(_) => (),
+                    }
+                    idx += 1;
+                }
+                idx += 1;
+                res.push(Item::Sexpr(&txt[start..idx]));
+            }
+            b'"' | b'|' => {
+                idx = quoted_end(txt, idx)?;
+                res.push(Item::Sexpr(&txt[start..idx]));
+            }
+            _ => {
+                while idx < bytes.len()
+                    && !bytes[idx].is_ascii_whitespace()
+                    && !matches!(bytes[idx], b'(' | b')' | b';')
+                {
+                    idx += 1
+                }
+                res.push(Item::Sexpr(&txt[start..idx]));
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// A logged solver interaction, see the [module-level documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialogue {
+    /// Commands, in order.
+    exchanges: Vec<Exchange>,
+}
+impl Dialogue {
+    /// Parses a tee-ed interaction.
+    ///
+    /// Comments are ignored, except the ones logging the answers of check-sat commands.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::solver::dialogue::Dialogue;
+    /// let log = "\
+    /// ; Command:
+    /// ; > z3 -in
+    ///
+    /// (declare-const x Int)
+    /// (assert (> x 0)) ; x is positive
+    /// (check-sat)
+    /// ; response: sat
+    /// (assert (< x 0))
+    /// (check-sat)
+    /// ; response: unsat
+    /// ";
+    /// let dialogue = Dialogue::of_str(log).unwrap();
+    /// assert_eq!(dialogue.exchanges().len(), 5);
+    /// assert_eq!(dialogue.exchanges()[1].command, "(assert (> x 0))");
+    /// assert_eq!(dialogue.responses().collect::<Vec<_>>(), vec!["sat", "unsat"]);
+    ///
+    /// let err = Dialogue::of_str("(assert (> x 0)").unwrap_err();
+    /// assert_eq!(err.to_string(), "unclosed parenthesis starting at offset 0");
+    /// ```
+    pub fn of_str(txt: &str) -> Res<Self> {
+        let mut exchanges: Vec<Exchange> = vec![];
+        for item in items(txt)? {
+            match item {
+                Item::Sexpr(command) => exchanges.push(Exchange {
+                    command: command.into(),
+                    response: None,
+                }),
+                Item::Comment(comment) => {
+                    if let Some(response) = comment.trim_start().strip_prefix(RESPONSE) {
+                        match exchanges.last_mut() {
+                            Some(last) if last.is_check_sat() && last.response.is_none() => {
+                                last.response = Some(response.trim().into())
+                            }
+                            _ => bail!("response `{}` does not follow a check-sat", response),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Self { exchanges })
+    }
+    /// Loads a tee-ed interaction from a file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Res<Self> {
+        let path = path.as_ref();
+        let txt = std::fs::read_to_string(path)
+            .chain_err(|| format!("while reading solver log `{}`", path.display()))?;
+        Self::of_str(&txt).chain_err(|| format!("while parsing solver log `{}`", path.display()))
+    }
+
+    /// Commands of the dialogue, in order.
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+    /// Logged answers to the check-sat commands, in order.
+    pub fn responses(&self) -> impl Iterator<Item = &str> + '_ {
+        self.exchanges
+            .iter()
+            .filter_map(|exchange| exchange.response.as_deref())
+    }
+
+    /// Feeds the commands to a fresh solver, compares its answers to the logged ones.
+    ///
+    /// The solver runs until it has processed all the commands, there is no timeout besides the
+    /// ones of `conf`. Check-sat commands without a logged answer, *e.g.* because the original
+    /// solver crashed, are not compared.
+    ///
+    /// # Errors
+    ///
+    /// - when the solver cannot be spawned, or
+    /// - when its output is not legal SMT-LIB 2.
+    ///
+    /// # Examples
+    ///
+    /// Replaying a log with a fake solver that answers `unsat` to all check-sat commands.
+    ///
+    /// ```rust
+    /// # use mikino_api::{solver::dialogue::Dialogue, rsmt2::SmtConf};
+    /// # #[cfg(target_os = "linux")] {
+    /// # let dir = std::env::temp_dir().join(format!("mikino_dialogue_{}", std::process::id()));
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// # let unsat = dir.join("unsat.sh");
+    /// # std::fs::write(&unsat, r#"#!/bin/sh
+    /// # while read -r line; do
+    /// #   case "$line" in *check-sat*) echo unsat ;; esac
+    /// # done
+    /// # "#,
+    /// # ).unwrap();
+    /// # use std::os::unix::fs::PermissionsExt;
+    /// # std::fs::set_permissions(&unsat, std::fs::Permissions::from_mode(0o755)).unwrap();
+    /// # let unsat = unsat.display().to_string();
+    /// let dialogue = Dialogue::of_str("\
+    /// (declare-const x Int)
+    /// (assert (> x 0))
+    /// (check-sat)
+    /// ; response: sat
+    /// (assert (< x 0))
+    /// (check-sat)
+    /// ; response: unsat
+    /// ").unwrap();
+    /// let replay = dialogue.replay(SmtConf::z3(unsat)).unwrap();
+    /// assert_eq!(replay.responses, vec!["unsat", "unsat"]);
+    /// assert!(!replay.is_consistent());
+    /// assert_eq!(
+    ///     replay.mismatches[0].to_string(),
+    ///     "check-sat #1 `(check-sat)` (command #3): logged `sat`, replayed `unsat`",
+    /// );
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// # }
+    /// ```
+    pub fn replay(&self, conf: SmtConf) -> Res<Replay> {
+        use std::{io::Write, process::Stdio};
+        let mut kid = std::process::Command::new(conf.get_cmd())
+            .args(conf.get_options())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .chain_err(|| format!("while spawning solver `{}`", conf.get_cmd()))?;
+        let mut stdin = match kid.stdin.take() {
+            Some(stdin) => stdin,
+            None => bail!("failed to retrieve the solver's standard input"),
+        };
+        let commands: Vec<&str> = self
+            .exchanges
+            .iter()
+            .map(|exchange| &exchange.command as &str)
+            .collect();
+        // Writes from a separate thread so that the solver never blocks on a full output pipe.
+        let output = std::thread::scope(|scope| {
+            let _ = scope.spawn(move || {
+                for command in commands {
+                    // The solver might exit early, it is not an error for the replay.
+                    if writeln!(stdin, "{}", command).is_err() {
+                        break;
+                    }
+                }
+            });
+            kid.wait_with_output()
+        })
+        .chain_err(|| "while waiting for the solver")?;
+        let output = match String::from_utf8(output.stdout) {
+            Ok(output) => output,
+            Err(e) => bail!("illegal UTF-8 in solver output: {}", e),
+        };
+
+        let mut replay = Replay {
+            responses: vec![],
+            errors: vec![],
+            mismatches: vec![],
+        };
+        for item in items(&output).chain_err(|| "while parsing the solver's output")? {
+            match item {
+                Item::Sexpr(sexpr) if sexpr.starts_with("(error") => {
+                    replay.errors.push(sexpr.into())
+                }
+                Item::Sexpr(atom @ ("sat" | "unsat" | "unknown" | "timeout")) => {
+                    replay.responses.push(atom.into())
+                }
+                Item::Sexpr(_) | Item::Comment(_) => (),
+            }
+        }
+
+        let check_sats = self
+            .exchanges
+            .iter()
+            .enumerate()
+            .filter(|(_, exchange)| exchange.is_check_sat());
+        for (query, (idx, exchange)) in check_sats.enumerate() {
+            let logged = match exchange.response.as_ref() {
+                Some(logged) => logged,
+                None => continue,
+            };
+            let replayed = replay.responses.get(query);
+            if replayed != Some(logged) {
+                replay.mismatches.push(Mismatch {
+                    query,
+                    command: idx,
+                    text: exchange.command.clone(),
+                    logged: logged.clone(),
+                    replayed: replayed.cloned(),
+                })
+            }
+        }
+        Ok(replay)
+    }
+}
+
+/// Result of [replaying](Dialogue::replay) a dialogue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    /// Answers of the solver to the check-sat commands, in order.
+    pub responses: Vec<String>,
+    /// Errors reported by the solver.
+    pub errors: Vec<String>,
+    /// Check-sat commands the solver did not answer as logged.
+    pub mismatches: Vec<Mismatch>,
+}
+impl Replay {
+    /// True if the solver answered all check-sat commands as logged, without errors.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// A check-sat command answered differently when replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index of the check-sat among the check-sat commands of the dialogue.
+    pub query: usize,
+    /// Index of the check-sat among all the commands of the dialogue.
+    pub command: usize,
+    /// Check-sat command.
+    pub text: String,
+    /// Logged answer.
+    pub logged: String,
+    /// Answer of the replay, `None` if the solver did not answer.
+    pub replayed: Option<String>,
+}
+impl fmt::Display for Mismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "check-sat #{} `{}` (command #{}): logged `{}`, ",
+            self.query + 1,
+            self.text,
+            self.command + 1,
+            self.logged
+        )?;
+        match self.replayed.as_ref() {
+            Some(replayed) => write!(fmt, "replayed `{}`", replayed),
+            None => write!(fmt, "no answer when replayed"),
+        }
+    }
+}
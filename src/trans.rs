@@ -17,16 +17,42 @@
 
 crate::prelude!();
 
-use expr::{Expr, SExpr, SVar, Typ, Var};
+use std::sync::Arc;
+
+use crate::{
+    check::{liveness::Property, ltl::Ltl},
+    meta::Label,
+};
+use expr::{Expr, Fun, SExpr, SVar, Typ, Var};
+
+pub mod time;
+
+#[cfg(test)]
+mod test;
 
 /// Variable declarations for transition systems.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Decls {
     /// Map from variable identifiers to types.
     id_to_typs: Map<String, Typ>,
+    /// Display hints for some of the variables.
+    hints: Map<String, expr::display::Hint>,
+    /// Roles of the variables declared in an `inputs`, `outputs` or `state` block.
+    roles: Map<String, Role>,
+    /// Map from enumeration type names to their variants.
+    enums: Map<String, Vec<String>>,
+    /// Map from uninterpreted function names to their signatures.
+    funs: Map<String, Arc<Fun>>,
 }
 impl fmt::Display for Decls {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (name, variants) in self.enums.iter() {
+            writeln!(fmt, "enum {} {{ {} }},", name, variants.join(", "))?;
+        }
+        for fun in self.funs.values() {
+            writeln!(fmt, "{},", fun)?;
+        }
+
         // Get the IDs ordered.
         let mut ids: Vec<_> = self.id_to_typs.iter().collect();
         ids.sort_by(|(id_lft, _), (id_rgt, _)| id_lft.cmp(id_rgt));
@@ -42,7 +68,7 @@ impl fmt::Display for Decls {
                             list.push((last_typ, last_vars));
                         }
                     }
-                    (list, Some((*typ, vec![id])))
+                    (list, Some((typ.clone(), vec![id])))
                 });
 
         for (idx, (typ, vars)) in clusters.into_iter().chain(last).enumerate() {
@@ -66,12 +92,20 @@ impl Decls {
     pub fn new() -> Self {
         Self {
             id_to_typs: Map::new(),
+            hints: Map::new(),
+            roles: Map::new(),
+            enums: Map::new(),
+            funs: Map::new(),
         }
     }
 
     /// Empties itself.
     pub fn clear(&mut self) {
-        self.id_to_typs.clear()
+        self.id_to_typs.clear();
+        self.hints.clear();
+        self.roles.clear();
+        self.enums.clear();
+        self.funs.clear()
     }
 
     /// True if `id` is declared.
@@ -90,7 +124,10 @@ impl Decls {
     pub fn to_ml_string(&self) -> String {
         let mut typ_to_ids = Map::new();
         for (id, typ) in &self.id_to_typs {
-            let is_new = typ_to_ids.entry(*typ).or_insert_with(Set::new).insert(id);
+            let is_new = typ_to_ids
+                .entry(typ.clone())
+                .or_insert_with(Set::new)
+                .insert(id);
             assert!(is_new)
         }
 
@@ -111,6 +148,49 @@ impl Decls {
         s
     }
 
+    /// Hsmt lines declaring the variables with some role, the content of an `svars` block if
+    /// `role` is `None`.
+    ///
+    /// One variable per line, preceded by its display hints if any. The enumerations are declared
+    /// in the `svars` block.
+    pub(crate) fn hsmt_svars_lines(&self, role: Option<Role>) -> Res<Vec<String>> {
+        let mut lines = vec![];
+        if role.is_none() {
+            for (name, variants) in self.enums.iter() {
+                lines.push(format!("enum {} {{ {} }},", name, variants.join(", ")))
+            }
+        }
+        for (id, typ) in self.id_to_typs.iter() {
+            if self.role(id) != role {
+                continue;
+            }
+            if let Some(hint) = self.hints.get(id) {
+                if let Some(display) = hint.display.as_ref() {
+                    lines.push(format!("/// @display: {}", display))
+                }
+                if let Some(unit) = hint.unit.as_ref() {
+                    lines.push(format!("/// @unit: {}", unit))
+                }
+            }
+            lines.push(format!("{}: {},", expr::hsmt::ident(id)?, typ))
+        }
+        Ok(lines)
+    }
+    /// Hsmt lines declaring the functions, the content of a `funs` block.
+    pub(crate) fn hsmt_funs_lines(&self) -> Res<Vec<String>> {
+        let mut lines = vec![];
+        for fun in self.funs.values() {
+            let args: Vec<String> = fun.args().iter().map(Typ::to_string).collect();
+            lines.push(format!(
+                "{}: ({}) -> {},",
+                expr::hsmt::ident(fun.name())?,
+                args.join(", "),
+                fun.out()
+            ))
+        }
+        Ok(lines)
+    }
+
     /// Builds a variable corresponding to an identifier.
     ///
     /// None if the identifier of the variable is unknown.
@@ -118,7 +198,7 @@ impl Decls {
         let id = id.as_ref();
         self.id_to_typs
             .get(id)
-            .map(|typ_ref| Var::new(id, *typ_ref))
+            .map(|typ_ref| Var::new(id, typ_ref.clone()))
     }
 
     /// Builds a current state variable corresponding to an identifier.
@@ -142,28 +222,371 @@ impl Decls {
         self.id_to_typs.insert(id.into(), typ)
     }
 
+    /// Declares an enumeration type with some variants.
+    ///
+    /// Returns the previous variants if `name` was already declared, and `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{expr::{Cst, Typ}, trans::Decls};
+    /// let mut decls = Decls::new();
+    /// let _ = decls.declare_enum("State", vec!["Idle".into(), "Busy".into()]);
+    /// let _ = decls.register("s", Typ::enumeration("State"));
+    /// assert!(decls.has_variant("State", "Busy"));
+    /// assert!(!decls.has_variant("State", "Done"));
+    /// assert_eq!(decls.to_string(), "enum State { Idle, Busy },\ns: State,");
+    /// ```
+    pub fn declare_enum(
+        &mut self,
+        name: impl Into<String>,
+        variants: Vec<String>,
+    ) -> Option<Vec<String>> {
+        self.enums.insert(name.into(), variants)
+    }
+
+    /// Enumeration types and their variants.
+    pub fn enums(&self) -> &Map<String, Vec<String>> {
+        &self.enums
+    }
+
+    /// Variants of an enumeration type, `None` if the type is not declared.
+    pub fn enum_variants(&self, name: impl AsRef<str>) -> Option<&[String]> {
+        self.enums
+            .get(name.as_ref())
+            .map(|variants| variants.as_slice())
+    }
+
+    /// True if `typ` is a declared enumeration type with a variant `variant`.
+    pub fn has_variant(&self, typ: impl AsRef<str>, variant: impl AsRef<str>) -> bool {
+        let variant = variant.as_ref();
+        self.enum_variants(typ)
+            .is_some_and(|variants| variants.iter().any(|v| v == variant))
+    }
+
+    /// Fails if `typ` mentions an enumeration type that is not declared.
+    pub fn check_typ(&self, typ: &Typ) -> Res<()> {
+        match typ {
+            Typ::Enum(name) if !self.enums.contains_key(name) => {
+                bail!("unknown enumeration type `{}`", name)
+            }
+            Typ::Array(idx, elm) => {
+                self.check_typ(idx)?;
+                self.check_typ(elm)
+            }
+            Typ::Bool | Typ::Int | Typ::Rat | Typ::BitVec(_) | Typ::Enum(_) => Ok(()),
+        }
+    }
+
+    /// Declares an uninterpreted function.
+    ///
+    /// Returns the previous signature if a function with the same name was already declared, and
+    /// `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{expr::{Fun, Typ}, trans::Decls};
+    /// let mut decls = Decls::new();
+    /// let _ = decls.declare_fun(Fun::new("f", vec![Typ::Int, Typ::Int], Typ::Int));
+    /// let _ = decls.register("x", Typ::Int);
+    /// assert_eq!(decls.get_fun("f").unwrap().out(), &Typ::Int);
+    /// assert!(decls.get_fun("g").is_none());
+    /// assert_eq!(decls.to_string(), "f: (int, int) -> int,\nx: int,");
+    /// ```
+    pub fn declare_fun(&mut self, fun: Arc<Fun>) -> Option<Arc<Fun>> {
+        self.funs.insert(fun.name().into(), fun)
+    }
+
+    /// Uninterpreted functions.
+    pub fn funs(&self) -> &Map<String, Arc<Fun>> {
+        &self.funs
+    }
+
+    /// Signature of an uninterpreted function, `None` if it is not declared.
+    pub fn get_fun(&self, name: impl AsRef<str>) -> Option<Arc<Fun>> {
+        self.funs.get(name.as_ref()).cloned()
+    }
+
+    /// Fails if some declarations clash with the ones of `self`.
+    ///
+    /// Uninterpreted functions cannot have the name of a variable or of another function, and their
+    /// signatures can only mention declared enumeration types.
+    pub fn check_funs(&self, that: &Self) -> Res<()> {
+        for (name, fun) in that.funs.iter() {
+            if self.funs.contains_key(name) {
+                bail!("re-declaring function `{}`", name)
+            } else if self.contains(name) || that.contains(name) {
+                bail!("function `{}` has the name of a variable", name)
+            }
+            for typ in fun.args().iter().chain(Some(fun.out())) {
+                that.check_typ(typ).or_else(|_| self.check_typ(typ))?;
+            }
+        }
+        for id in that.id_to_typs.keys() {
+            if self.funs.contains_key(id) {
+                bail!("variable `{}` has the name of a function", id)
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the display hint of a variable, ignored if the hint is empty.
+    ///
+    /// Returns the previous hint, if any.
+    pub fn set_hint(
+        &mut self,
+        id: impl Into<String>,
+        hint: expr::display::Hint,
+    ) -> Option<expr::display::Hint> {
+        if hint.is_empty() {
+            None
+        } else {
+            self.hints.insert(id.into(), hint)
+        }
+    }
+
+    /// Display hint of a variable, if any.
+    pub fn hint(&self, id: impl AsRef<str>) -> Option<&expr::display::Hint> {
+        self.hints.get(id.as_ref())
+    }
+
+    /// Display hints of all variables that have one.
+    pub fn hints(&self) -> &Map<String, expr::display::Hint> {
+        &self.hints
+    }
+
+    /// Sets the role of a variable.
+    ///
+    /// Returns the previous role, if any.
+    pub fn set_role(&mut self, id: impl Into<String>, role: Role) -> Option<Role> {
+        self.roles.insert(id.into(), role)
+    }
+
+    /// Role of a variable, `None` if it was declared in an `svars` block.
+    pub fn role(&self, id: impl AsRef<str>) -> Option<Role> {
+        self.roles.get(id.as_ref()).cloned()
+    }
+
+    /// True if some variable has a role.
+    pub fn has_roles(&self) -> bool {
+        !self.roles.is_empty()
+    }
+
+    /// An iterator over the variables with some role.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{parse, trans::Role};
+    /// let sys = parse::trans(
+    ///     "inputs { inc: bool } state { cnt: int } outputs { pos: bool } \
+    ///     init { cnt = 0, ¬pos } \
+    ///     trans { 'cnt = (if 'inc { cnt + 1 } else { cnt }), 'pos = ('cnt > 0) } \
+    ///     candidates { \"cnt is positive\": cnt ≥ 0 }",
+    /// )
+    /// .unwrap();
+    /// let ids = |role| -> Vec<String> {
+    ///     sys.decls().with_role(role).map(|var| var.id().to_string()).collect()
+    /// };
+    /// assert_eq!(ids(Role::Input), vec!["inc"]);
+    /// assert_eq!(ids(Role::Output), vec!["pos"]);
+    /// assert_eq!(ids(Role::State), vec!["cnt"]);
+    /// assert_eq!(sys.decls().role("cnt"), Some(Role::State));
+    /// ```
+    pub fn with_role<'a>(&'a self, role: Role) -> impl Iterator<Item = Var> + 'a {
+        self.all()
+            .filter(move |var| self.roles.get(var.id()) == Some(&role))
+    }
+
+    /// Symbol table corresponding to these declarations.
+    ///
+    /// The table forbids shadowing, all the declarations are in the global scope.
+    pub fn symbols(&self) -> decls::SymbolTable {
+        let mut table = decls::SymbolTable::new(decls::Shadowing::Forbid);
+        for (id, typ) in self.id_to_typs.iter() {
+            // Declarations built by hand might not have legal identifiers, so no check.
+            let sym = table
+                .declare_unchecked(id.clone(), typ.clone())
+                .expect("[unreachable] declarations cannot have duplicates");
+            if let Some(hint) = self.hints.get(id) {
+                table.set_hint(sym, hint.clone())
+            }
+        }
+        table
+    }
+
+    /// Imports some declarations under a namespace, used when composing systems.
+    ///
+    /// Variable `v` of `that` is declared as `<ns>.v`, see [`decls::qualify`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{expr::Typ, trans::Decls};
+    /// let mut sub = Decls::new();
+    /// let _ = sub.register("cnt", Typ::Int);
+    /// let mut decls = Decls::new();
+    /// let _ = decls.register("cnt", Typ::Int);
+    /// decls.import("left", &sub).unwrap();
+    /// decls.import("right", &sub).unwrap();
+    /// assert_eq!(decls.to_string(), "cnt left.cnt right.cnt: int,");
+    /// let err = decls.import("left", &sub).unwrap_err();
+    /// assert_eq!(err.to_string(), "identifier `left.cnt` is already declared");
+    /// ```
+    pub fn import(&mut self, ns: &str, that: &Self) -> Res<()> {
+        for (name, variants) in that.enums.iter() {
+            match self.enums.get(name) {
+                Some(mine) if mine != variants => {
+                    bail!("enumeration `{}` is declared with different variants", name)
+                }
+                Some(_) => (),
+                None => {
+                    let _ = self.declare_enum(name.clone(), variants.clone());
+                }
+            }
+        }
+        for (name, fun) in that.funs.iter() {
+            match self.funs.get(name) {
+                Some(mine) if mine != fun => {
+                    bail!("function `{}` is declared with different signatures", name)
+                }
+                Some(_) => (),
+                None => {
+                    let _ = self.declare_fun(fun.clone());
+                }
+            }
+        }
+        let mut table = self.symbols();
+        let imported = table.import(ns, &that.symbols())?;
+        for sym in imported.into_values() {
+            let sym = &table[sym];
+            let _ = self.register(sym.ident.clone(), sym.typ.clone());
+            if let Some(hint) = sym.hint.clone() {
+                let _ = self.set_hint(sym.ident.clone(), hint);
+            }
+        }
+        Ok(())
+    }
+
+    /// Declares the variables of a subsystem instance, see [`Sys::instantiate`].
+    ///
+    /// The `ports` map variables of `sub` to variables of `self` they are connected to. All the
+    /// other variables of `sub` are [imported] under namespace `ns`. Returns the renaming from the
+    /// variables of `sub` to the variables of `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::{expr::Typ, trans::Decls};
+    /// # use std::collections::BTreeMap as Map;
+    /// let mut sub = Decls::new();
+    /// let _ = sub.register("reset", Typ::Bool);
+    /// let _ = sub.register("cnt", Typ::Int);
+    /// let mut decls = Decls::new();
+    /// let _ = decls.register("rst", Typ::Bool);
+    /// let ports: Map<_, _> = vec![("reset".to_string(), "rst".to_string())]
+    ///     .into_iter()
+    ///     .collect();
+    /// let renaming = decls.instantiate("left", &sub, &ports).unwrap();
+    /// assert_eq!(decls.to_string(), "left.cnt: int,\nrst: bool,");
+    /// assert_eq!(renaming["reset"].id(), "rst");
+    /// assert_eq!(renaming["cnt"].id(), "left.cnt");
+    ///
+    /// let _ = decls.register("n", Typ::Int);
+    /// let ports: Map<_, _> = vec![("reset".to_string(), "n".to_string())]
+    ///     .into_iter()
+    ///     .collect();
+    /// let err = decls.instantiate("right", &sub, &ports).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "port `reset` has type `bool`, cannot connect it to `n` of type `int`",
+    /// );
+    /// ```
+    ///
+    /// [imported]: #method.import (The import method)
+    pub fn instantiate(
+        &mut self,
+        ns: &str,
+        sub: &Self,
+        ports: &Map<String, String>,
+    ) -> Res<Map<String, Var>> {
+        let mut inner = sub.clone();
+        for (port, var) in ports.iter() {
+            let port_typ = match inner.id_to_typs.remove(port) {
+                Some(typ) => typ,
+                None => bail!("subsystem has no variable `{}`", port),
+            };
+            let _ = inner.hints.remove(port);
+            let _ = inner.roles.remove(port);
+            match self.id_to_typs.get(var) {
+                Some(typ) if *typ == port_typ => (),
+                Some(typ) => bail!(
+                    "port `{}` has type `{}`, cannot connect it to `{}` of type `{}`",
+                    port,
+                    port_typ,
+                    var,
+                    typ,
+                ),
+                None => bail!("unknown variable `{}`", var),
+            }
+        }
+        self.import(ns, &inner)?;
+        Ok(sub
+            .all()
+            .map(|var| {
+                let id = ports
+                    .get(var.id())
+                    .cloned()
+                    .unwrap_or_else(|| decls::qualify(ns, var.id()));
+                (var.id().to_string(), Var::new(id, var.typ()))
+            })
+            .collect())
+    }
+
     /// An iterator over all the variables declared as [`Var`]s.
     ///
     /// [`Var`]: ../expr/struct.Var.html (The Var struct)
     pub fn all<'a>(&'a self) -> impl Iterator<Item = Var> + 'a {
-        self.id_to_typs.iter().map(|(id, typ)| Var::new(id, *typ))
+        self.id_to_typs
+            .iter()
+            .map(|(id, typ)| Var::new(id, typ.clone()))
     }
 
     /// Merges two sets of variable declarations.
     ///
-    /// Produces common variable declarations, if any.
+    /// Produces common variable declarations, if any. Enumeration types and uninterpreted functions
+    /// of `that` are added, replacing the ones of `self` with the same name.
     pub fn merge(&mut self, that: &Self) -> Option<Map<String, (Typ, Typ)>> {
         let mut clashes = None;
 
+        for (name, variants) in that.enums.iter() {
+            let _ = self.enums.insert(name.clone(), variants.clone());
+        }
+        for (name, fun) in that.funs.iter() {
+            let _ = self.funs.insert(name.clone(), fun.clone());
+        }
+
+        for (id, hint) in that.hints.iter() {
+            if !self.hints.contains_key(id) {
+                let _ = self.hints.insert(id.clone(), hint.clone());
+            }
+        }
+        for (id, role) in that.roles.iter() {
+            if !self.roles.contains_key(id) {
+                let _ = self.roles.insert(id.clone(), *role);
+            }
+        }
+
         for (id, typ) in that.id_to_typs.iter() {
-            let old_typ = self.id_to_typs.insert(id.to_string(), *typ);
+            let old_typ = self.id_to_typs.insert(id.to_string(), typ.clone());
             if let Some(old_typ) = old_typ {
                 if &old_typ != typ {
-                    let _ = self.id_to_typs.insert(id.to_string(), old_typ);
+                    let _ = self.id_to_typs.insert(id.to_string(), old_typ.clone());
                 }
                 let _prev = clashes
                     .get_or_insert_with(Map::new)
-                    .insert(id.into(), (old_typ, *typ));
+                    .insert(id.into(), (old_typ, typ.clone()));
                 debug_assert_eq!(_prev, None);
             }
         }
@@ -183,7 +606,7 @@ impl Decls {
                 } else {
                     let _prev = wrong_types
                         .get_or_insert_with(Map::new)
-                        .insert(key.to_string(), (*typ0, *typ1));
+                        .insert(key.to_string(), (typ0.clone(), typ1.clone()));
                     debug_assert_eq!(_prev, None);
                     false
                 }
@@ -191,10 +614,75 @@ impl Decls {
                 false
             }
         });
+        let id_to_typs = &self.id_to_typs;
+        self.hints.retain(|id, _| id_to_typs.contains_key(id));
+        self.roles.retain(|id, _| id_to_typs.contains_key(id));
         wrong_types
     }
 }
 
+/// Severity of a candidate.
+///
+/// The falsification of a [`Self::Warning`] candidate is reported but is not a failure, see
+/// [`Outcome::is_success`](crate::check::Outcome::is_success).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Severity {
+    /// Falsification is a failure, default.
+    #[default]
+    Error,
+    /// Falsification is only a warning, for candidates marked `#[warn]`.
+    Warning,
+}
+impl Severity {
+    /// True if the severity is [`Self::Warning`].
+    pub fn is_warning(self) -> bool {
+        self == Self::Warning
+    }
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Error => "error".fmt(fmt),
+            Self::Warning => "warning".fmt(fmt),
+        }
+    }
+}
+
+/// Role of a state variable, given by the block declaring it.
+///
+/// Variables declared in an `svars` block have no role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// Chosen freely by the environment at each step, declared in an `inputs` block.
+    Input,
+    /// Computed by the system, declared in an `outputs` block.
+    Output,
+    /// Internal state of the system, declared in a `state` block.
+    State,
+}
+impl Role {
+    /// All the roles, in the order of their blocks in the hsmt syntax.
+    pub const ALL: [Self; 3] = [Self::Input, Self::State, Self::Output];
+
+    /// Keyword of the block declaring the variables with this role.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Self::Input => "inputs",
+            Self::Output => "outputs",
+            Self::State => "state",
+        }
+    }
+}
+impl fmt::Display for Role {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Input => "input".fmt(fmt),
+            Self::Output => "output".fmt(fmt),
+            Self::State => "state".fmt(fmt),
+        }
+    }
+}
+
 /// A transition system with an initial state and transition relation.
 pub struct Sys {
     /// Variable declarations.
@@ -205,18 +693,107 @@ pub struct Sys {
     trans: SExpr,
     /// Proof obligations for this system.
     po_s: Map<String, Expr>,
+    /// Tags of the proof obligations, POs without tags do not appear.
+    tags: Map<String, Set<String>>,
+    /// Proof obligations with severity [`Severity::Warning`].
+    warnings: Set<String>,
+    /// Labels of the proof obligations, POs without a label do not appear.
+    labels: Map<String, Label>,
+    /// Labels of some top-level conjuncts of the initial predicate.
+    init_labels: Vec<(Expr, String)>,
+    /// Labels of some top-level conjuncts of the transition relation.
+    trans_labels: Vec<(SExpr, String)>,
+    /// Liveness properties of the system.
+    liveness: Map<String, Property>,
+    /// LTL properties of the system.
+    ltl: Map<String, Ltl>,
 }
 impl Sys {
-    /// Constructor.
+    /// Constructor, no tags.
     pub fn new(decls: Decls, init: Expr, trans: SExpr, po_s: Map<String, Expr>) -> Self {
         Self {
             decls,
             init,
             trans,
             po_s,
+            tags: Map::new(),
+            warnings: Set::new(),
+            labels: Map::new(),
+            init_labels: vec![],
+            trans_labels: vec![],
+            liveness: Map::new(),
+            ltl: Map::new(),
         }
     }
 
+    /// Sets the tags of the POs, ignores tags for POs that do not exist.
+    pub fn with_tags(mut self, tags: Map<String, Set<String>>) -> Self {
+        let po_s = &self.po_s;
+        self.tags = tags
+            .into_iter()
+            .filter(|(name, tags)| po_s.contains_key(name) && !tags.is_empty())
+            .collect();
+        self
+    }
+    /// Sets the POs with severity [`Severity::Warning`], ignores POs that do not exist.
+    pub fn with_warnings(mut self, warnings: Set<String>) -> Self {
+        let po_s = &self.po_s;
+        self.warnings = warnings
+            .into_iter()
+            .filter(|name| po_s.contains_key(name))
+            .collect();
+        self
+    }
+    /// Sets the labels of the POs, ignores labels for POs that do not exist.
+    pub fn with_labels(mut self, labels: Map<String, Label>) -> Self {
+        let po_s = &self.po_s;
+        self.labels = labels
+            .into_iter()
+            .filter(|(name, _)| po_s.contains_key(name))
+            .collect();
+        self
+    }
+    /// Sets the labels of top-level conjuncts of the initial predicate and transition relation.
+    ///
+    /// Labels come from `"<label>": <expr>` conjuncts in `init`, `assumptions` and `trans`, see
+    /// [`parse::trans`](crate::parse::trans()). They are looked up by conjunct, see
+    /// [`Self::init_label_of`] and [`Self::trans_label_of`].
+    pub fn with_conjunct_labels(
+        mut self,
+        init: Vec<(Expr, String)>,
+        trans: Vec<(SExpr, String)>,
+    ) -> Self {
+        self.init_labels = init;
+        self.trans_labels = trans;
+        self
+    }
+    /// Sets the liveness properties of the system.
+    ///
+    /// Liveness properties come from the `liveness { ... }` block, see
+    /// [`parse::trans`](crate::parse::trans()), and are falsified by lassos, see
+    /// [`check::liveness`].
+    pub fn with_liveness(mut self, liveness: Map<String, Property>) -> Self {
+        self.liveness = liveness;
+        self
+    }
+    /// Sets the LTL properties of the system.
+    ///
+    /// LTL properties come from the `ltl { ... }` block, see
+    /// [`parse::trans`](crate::parse::trans()), and are checked by the engines through a
+    /// [reduction](check::ltl::Safety) to safety.
+    pub fn with_ltl(mut self, ltl: Map<String, Ltl>) -> Self {
+        self.ltl = ltl;
+        self
+    }
+    /// Sets the tags, severities and labels of the POs, and the labels of the conjuncts, to the
+    /// ones of another system.
+    pub fn with_annotations_of(self, that: &Self) -> Self {
+        self.with_tags(that.tags.clone())
+            .with_warnings(that.warnings.clone())
+            .with_labels(that.labels.clone())
+            .with_conjunct_labels(that.init_labels.clone(), that.trans_labels.clone())
+    }
+
     /// Pretty, multi-line string representation of the system.
     pub fn to_ml_string(&self) -> String {
         let mut s = String::new();
@@ -241,6 +818,154 @@ impl Sys {
         s
     }
 
+    /// Hsmt syntax of the system, parsed back by [`parse::trans`](crate::parse::trans()).
+    ///
+    /// Top-level conjuncts of the initial predicate and the transition relation are printed one
+    /// per line, with their label if any. Candidates keep their tags, severities and labels, and
+    /// variables their display hints. Subsystems, parameters and assumptions are not recovered,
+    /// the system is printed as the flat system they produced.
+    ///
+    /// # Errors
+    ///
+    /// - when the system has no variables or no candidates, and
+    /// - when an expression has no hsmt syntax, see [`expr::hsmt`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse;
+    /// let sys = parse::trans(r#"
+    /// svars {
+    ///     /// @unit: ms
+    ///     time: int,
+    ///     reset: bool,
+    /// }
+    /// init { "starts at zero": time = 0 }
+    /// trans { 'time = if 'reset { 0 } else { time + 1 } }
+    /// candidates {
+    ///     /// Time is positive.
+    ///     #[tag(sanity)]
+    ///     "positive": time ≥ 0,
+    /// }
+    /// "#).unwrap();
+    /// let hsmt = sys.to_hsmt().unwrap();
+    /// assert_eq!(
+    ///     hsmt,
+    ///     r#"svars {
+    ///     reset: bool,
+    ///     /// @unit: ms
+    ///     time: int,
+    /// }
+    /// init {
+    ///     "starts at zero": time = 0,
+    /// }
+    /// trans {
+    ///     'time = if 'reset { 0 } else { time + 1 },
+    /// }
+    /// candidates {
+    ///     /// Time is positive.
+    ///     #[tag(sanity)]
+    ///     "positive": time ≥ 0,
+    /// }
+    /// "#,
+    /// );
+    /// assert_eq!(parse::trans(&hsmt).unwrap().to_hsmt().unwrap(), hsmt);
+    /// ```
+    pub fn to_hsmt(&self) -> Res<String> {
+        use expr::hsmt::dbl_quoted;
+        let mut s = String::new();
+        let mut block = |name: &str, lines: Vec<String>| {
+            s.push_str(name);
+            s.push_str(" {\n");
+            for line in lines {
+                s.push_str("    ");
+                s.push_str(&line);
+                s.push('\n')
+            }
+            s.push_str("}\n")
+        };
+
+        if self.decls.id_to_typs.is_empty() {
+            bail!("cannot write a system with no variables in hsmt")
+        }
+        let svars = self.decls.hsmt_svars_lines(None)?;
+        if !svars.is_empty() {
+            block("svars", svars)
+        }
+        for role in Role::ALL {
+            let lines = self.decls.hsmt_svars_lines(Some(role))?;
+            if !lines.is_empty() {
+                block(role.keyword(), lines)
+            }
+        }
+        let funs = self.decls.hsmt_funs_lines()?;
+        if !funs.is_empty() {
+            block("funs", funs)
+        }
+
+        let mut init = vec![];
+        for conj in check::preprocess::conjuncts(&self.init) {
+            let label = match self.init_label_of(&conj) {
+                Some(label) => format!("{}: ", dbl_quoted(label)?),
+                None => "".into(),
+            };
+            init.push(format!("{}{},", label, conj.to_hsmt()?))
+        }
+        block("init", init);
+        let mut trans = vec![];
+        for conj in check::preprocess::conjuncts(&self.trans) {
+            let label = match self.trans_label_of(&conj) {
+                Some(label) => format!("{}: ", dbl_quoted(label)?),
+                None => "".into(),
+            };
+            trans.push(format!("{}{},", label, conj.to_hsmt()?))
+        }
+        block("trans", trans);
+
+        if self.po_s.is_empty() {
+            bail!("cannot write a system with no candidates in hsmt")
+        }
+        let mut candidates = vec![];
+        for (name, po) in self.po_s.iter() {
+            if let Some(label) = self.label_of(name) {
+                for line in label.to_doc() {
+                    candidates.push(format!("/// {}", line).trim_end().to_string())
+                }
+            }
+            let tags: Vec<&str> = self.tags_of(name).map(String::as_str).collect();
+            if !tags.is_empty() {
+                candidates.push(format!("#[tag({})]", tags.join(", ")))
+            }
+            if self.severity_of(name).is_warning() {
+                candidates.push("#[warn]".into())
+            }
+            candidates.push(format!("{}: {},", dbl_quoted(name)?, po.to_hsmt()?))
+        }
+        block("candidates", candidates);
+
+        if !self.liveness.is_empty() {
+            let mut liveness = vec![];
+            for (name, prop) in self.liveness.iter() {
+                let keyword = match prop {
+                    Property::Eventually(_) => "eventually",
+                    Property::InfinitelyOften(_) => "infinitely_often",
+                };
+                let expr = prop.expr().to_hsmt()?;
+                liveness.push(format!("{}: {} {},", dbl_quoted(name)?, keyword, expr))
+            }
+            block("liveness", liveness)
+        }
+        if !self.ltl.is_empty() {
+            let mut ltl = vec![];
+            for (name, formula) in self.ltl.iter() {
+                ltl.push(format!("{}: {},", dbl_quoted(name)?, formula))
+            }
+            block("ltl", ltl)
+        }
+
+        Ok(s)
+    }
+
     /// Variable declaration accessor.
     pub fn decls(&self) -> &Decls {
         &self.decls
@@ -257,59 +982,357 @@ impl Sys {
     pub fn po_s(&self) -> &Map<String, Expr> {
         &self.po_s
     }
+    /// Liveness properties accessor, see [`Self::with_liveness`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse;
+    /// let sys = parse::trans(r#"
+    /// svars { cnt: int, reset: bool }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = if 'reset { 0 } else { cnt + 1 } }
+    /// candidates { "positive": cnt ≥ 0 }
+    /// liveness {
+    ///     "grows": eventually cnt > 3,
+    ///     "resets": infinitely_often reset,
+    /// }
+    /// "#).unwrap();
+    /// let props: Vec<_> = sys
+    ///     .liveness()
+    ///     .iter()
+    ///     .map(|(name, prop)| format!("{}: {}", name, prop))
+    ///     .collect();
+    /// assert_eq!(props, vec!["grows: eventually (> cnt 3)", "resets: infinitely_often reset"]);
+    /// ```
+    pub fn liveness(&self) -> &Map<String, Property> {
+        &self.liveness
+    }
+    /// LTL properties accessor, see [`Self::with_ltl`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse;
+    /// let sys = parse::trans(r#"
+    /// svars { req ack: bool }
+    /// init { ¬ack }
+    /// trans { 'ack = req }
+    /// candidates { "no ack at first": ¬ack ⋁ ack }
+    /// ltl { "response": G ({req} ⇒ X {ack}) }
+    /// "#).unwrap();
+    /// let ltl = &sys.ltl()["response"];
+    /// assert_eq!(ltl.to_string(), "G ({ req } ⇒ X { ack })");
+    /// ```
+    pub fn ltl(&self) -> &Map<String, Ltl> {
+        &self.ltl
+    }
+    /// Tags of all the POs that have some.
+    pub fn tags(&self) -> &Map<String, Set<String>> {
+        &self.tags
+    }
+    /// Tags of a PO.
+    pub fn tags_of(&self, po: &str) -> impl Iterator<Item = &String> + '_ {
+        self.tags.get(po).into_iter().flatten()
+    }
+    /// POs with severity [`Severity::Warning`].
+    pub fn warnings(&self) -> &Set<String> {
+        &self.warnings
+    }
+    /// Labels of all the POs that have one.
+    pub fn labels(&self) -> &Map<String, Label> {
+        &self.labels
+    }
+    /// Label of a PO, if any.
+    pub fn label_of(&self, po: &str) -> Option<&Label> {
+        self.labels.get(po)
+    }
+    /// Label of a top-level conjunct of the initial predicate, if any.
+    pub fn init_label_of(&self, conj: &Expr) -> Option<&str> {
+        self.init_labels
+            .iter()
+            .find(|(labeled, _)| labeled == conj)
+            .map(|(_, label)| label as &str)
+    }
+    /// Label of a top-level conjunct of the transition relation, if any.
+    pub fn trans_label_of(&self, conj: &SExpr) -> Option<&str> {
+        self.trans_labels
+            .iter()
+            .find(|(labeled, _)| labeled == conj)
+            .map(|(_, label)| label as &str)
+    }
+    /// Severity of a PO.
+    pub fn severity_of(&self, po: &str) -> Severity {
+        if self.warnings.contains(po) {
+            Severity::Warning
+        } else {
+            Severity::Error
+        }
+    }
+
+    /// State variables the transition relation does not constrain, *i.e.* whose next version does
+    /// not appear in it.
+    ///
+    /// These variables take arbitrary values at each step, which usually means a frame condition
+    /// is missing, see `unchanged { ... }` in [`parse::trans`](crate::parse::trans()). Variables
+    /// with the [`Role::Input`] role are expected to take arbitrary values and are not reported.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse;
+    /// let sys = parse::trans(r#"
+    /// svars { x y z: int, b: bool }
+    /// init { x = 0, y = 0, z = 0 }
+    /// trans { 'x = x + 1, b ⇒ 'y = y }
+    /// candidates { "x": x ≥ 0 }
+    /// "#).unwrap();
+    /// let unconstrained: Vec<_> = sys.unconstrained().iter().map(|v| v.id().to_string()).collect();
+    /// assert_eq!(unconstrained, vec!["b", "z"]);
+    /// ```
+    pub fn unconstrained(&self) -> Vec<Var> {
+        let mut constrained = Set::new();
+        self.trans.fold(
+            |var| {
+                if var.is_next() {
+                    let _ = constrained.insert(var.id().to_string());
+                }
+            },
+            |_| (),
+            |_, _| (),
+        );
+        self.decls
+            .all()
+            .filter(|var| {
+                !constrained.contains(var.id()) && self.decls.role(var.id()) != Some(Role::Input)
+            })
+            .collect()
+    }
+
+    /// SMT-LIB 2 script of the BMC check at depth `k`.
+    ///
+    /// Declares the variables of steps `0` to `k`, asserts the initial predicate at `0` and the
+    /// transition relation between consecutive steps. Then each candidate is checked in its own
+    /// `push`/`pop` scope, like the BMC engine does: the `check-sat` is `sat` iff the candidate is
+    /// falsified by some trace of length `k + 1`.
+    ///
+    /// The script is standalone and can be fed to any SMT-LIB 2 solver, for debugging or archiving
+    /// purposes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mikino_api::parse;
+    /// let sys = parse::trans(r#"
+    /// svars { cnt: int }
+    /// init { cnt = 0 }
+    /// trans { 'cnt = cnt + 1 }
+    /// candidates { "cnt is not 1": ¬(cnt = 1) }
+    /// "#).unwrap();
+    /// assert_eq!(
+    ///     sys.to_smt2_bmc(1).unwrap(),
+    ///     "\
+    /// (declare-const cnt@0 Int)
+    /// (declare-const cnt@1 Int)
+    /// (assert (= cnt@0 0))
+    /// (assert (= cnt@1 (+ cnt@0 1)))
+    /// ; candidate `cnt is not 1`
+    /// (push 1)
+    /// (assert (not (not (= cnt@1 1))))
+    /// (check-sat)
+    /// (pop 1)
+    /// ",
+    /// );
+    /// ```
+    pub fn to_smt2_bmc(&self, k: Unroll) -> Res<String> {
+        use crate::solver::smt2;
+        let steps: Vec<Unroll> = (0..=k).collect();
+        let mut s = smt2::declare(&self.decls, Some(&steps))?;
+        s.push_str(&smt2::assert(&self.init, 0)?);
+        for step in 0..k {
+            s.push_str(&smt2::assert(&self.trans, step)?);
+        }
+        for (name, po) in self.po_s.iter() {
+            s.push_str(&format!("; candidate `{}`\n(push 1)\n", name));
+            s.push_str(&smt2::assert(&po.negated(), k)?);
+            s.push_str("(check-sat)\n(pop 1)\n");
+        }
+        Ok(s)
+    }
+
+    /// Copy of this system where only the POs verifying `keep` are preserved, with their tags,
+    /// severities and labels.
+    pub fn restrict(&self, mut keep: impl FnMut(&String) -> bool) -> Self {
+        let po_s = self
+            .po_s
+            .iter()
+            .filter(|(name, _)| keep(name))
+            .map(|(name, po)| (name.clone(), po.clone()))
+            .collect();
+        Self::new(
+            self.decls.clone(),
+            self.init.clone(),
+            self.trans.clone(),
+            po_s,
+        )
+        .with_annotations_of(self)
+    }
+
+    /// Adds an instance of a subsystem to this system.
+    ///
+    /// The variables of `sub` appearing in `ports` are connected to the variables of `self` they
+    /// map to, the other ones are declared under namespace `ns` (see [`Decls::instantiate`]). The
+    /// (renamed) initial predicate and transition relation of `sub` are conjoined to the ones of
+    /// `self`. The POs of `sub` are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::collections::BTreeMap as Map;
+    /// let sub = mikino_api::build_trans! {
+    ///     decls { reset: bool, cnt: int }
+    ///     init { (= cnt 0) }
+    ///     trans { (= (cnt @ 1) (ite (reset @ 1) 0 (+ (cnt @ 0) 1))) }
+    ///     candidates { "positive" => (>= cnt 0) }
+    /// }
+    /// .unwrap();
+    /// let sys = mikino_api::build_trans! {
+    ///     decls { rst: bool }
+    ///     init { true }
+    ///     trans { true }
+    ///     candidates {}
+    /// }
+    /// .unwrap();
+    /// let ports: Map<_, _> = vec![("reset".to_string(), "rst".to_string())]
+    ///     .into_iter()
+    ///     .collect();
+    /// let sys = sys.instantiate("left", &sub, &ports).unwrap();
+    /// let sys = sys.instantiate("right", &sub, &ports).unwrap();
+    /// assert_eq!(sys.decls().to_string(), "left.cnt right.cnt: int,\nrst: bool,");
+    /// assert_eq!(sys.init().to_string(), "(and (= left.cnt 0) (= right.cnt 0))");
+    /// assert!(sys.po_s().is_empty());
+    /// ```
+    pub fn instantiate(self, ns: &str, sub: &Self, ports: &Map<String, String>) -> Res<Self> {
+        let mut decls = self.decls.clone();
+        let renaming = decls
+            .instantiate(ns, &sub.decls, ports)
+            .chain_err(|| format!("while instantiating `{}`", ns))?;
+        Self { decls, ..self }.with_instance(sub, &renaming)
+    }
+
+    /// Conjoins the initial predicate and transition relation of a subsystem to the ones of this
+    /// system, with the variables of `sub` renamed by `renaming`.
+    ///
+    /// Does not declare anything, see [`Decls::instantiate`].
+    pub(crate) fn with_instance(mut self, sub: &Self, renaming: &Map<String, Var>) -> Res<Self> {
+        let rename = |var: &Var| {
+            renaming
+                .get(var.id())
+                .cloned()
+                .unwrap_or_else(|| var.clone())
+        };
+        let init = sub.init.fold(
+            |var| Ok(Expr::new_var(rename(var))),
+            |cst| Ok(Expr::new_cst(cst.clone())),
+            |op, args: Vec<Res<Expr>>| Expr::new_op(op, args.into_iter().collect::<Res<_>>()?),
+        )?;
+        let trans = sub.trans.fold(
+            |var| Ok(SExpr::new_var(SVar::new(rename(var), var.is_next()))),
+            |cst| Ok(SExpr::new_cst(cst.clone())),
+            |op, args: Vec<Res<SExpr>>| SExpr::new_op(op, args.into_iter().collect::<Res<_>>()?),
+        )?;
+        let mut inits = check::preprocess::conjuncts(&self.init);
+        inits.extend(check::preprocess::conjuncts(&init));
+        inits.retain(|conj| *conj != Expr::from(true));
+        let mut transs = check::preprocess::conjuncts(&self.trans);
+        transs.extend(check::preprocess::conjuncts(&trans));
+        transs.retain(|conj| *conj != SExpr::from(true));
+        self.init = check::preprocess::conjunction(inits)?;
+        self.trans = check::preprocess::conjunction(transs)?;
+        Ok(self)
+    }
 }
 
-/// Builds an expression.
+/// Builds an expression over some declarations.
+///
+/// The first argument is the kind of expression to build:
 ///
-/// - identifiers must be written as `(var_name: var_typ)`, without any quotes.
+/// - `stateless`: identifiers are variables, as in `(+ cnt 1)`;
+/// - `stateful`: variables are written `(var @ 0)` for the current state and `(var @ 1)` for the
+///   next state, as in `(= (cnt @ 1) (+ (cnt @ 0) 1))`;
+/// - `curr` (`next`): identifiers are current (next) state variables.
+///
+/// Panics on undeclared variables. Fails on ill-typed applications, so it must be used in a
+/// function returning a [`Res`](crate::prelude::Res).
 #[macro_export]
 macro_rules! build_trans_expr {
-    ($state:tt, $decls:expr, true) => ( expr::PExpr::from(true) );
-    ($state:tt, $decls:expr, false) => ( expr::PExpr::from(false) );
+    ($state:tt, $decls:expr, true) => ( $crate::expr::PExpr::from(true) );
+    ($state:tt, $decls:expr, false) => ( $crate::expr::PExpr::from(false) );
 
     (stateless, $decls:expr, $var:ident) => (
         if let Some(var) = $decls.get_var(stringify!($var)) {
-            expr::PExpr::new_var(var)
+            $crate::expr::PExpr::new_var(var)
+        } else {
+            panic!("undeclared variable `{}`", stringify!($var))
+        }
+    );
+    (curr, $decls:expr, $var:ident) => (
+        if let Some(var) = $decls.get_curr_var(stringify!($var)) {
+            $crate::expr::PExpr::new_var(var)
+        } else {
+            panic!("undeclared variable `{}`", stringify!($var))
+        }
+    );
+    (next, $decls:expr, $var:ident) => (
+        if let Some(var) = $decls.get_next_var(stringify!($var)) {
+            $crate::expr::PExpr::new_var(var)
         } else {
             panic!("undeclared variable `{}`", stringify!($var))
         }
     );
     (stateful, $decls:expr, ($var:ident @ 0)) => (
         if let Some(var) = $decls.get_curr_var(stringify!($var)) {
-            expr::PExpr::new_var(var)
+            $crate::expr::PExpr::new_var(var)
         } else {
             panic!("undeclared variable `{}`", stringify!($var))
         }
     );
     (stateful, $decls:expr, ($var:ident @ 1)) => (
         if let Some(var) = $decls.get_next_var(stringify!($var)) {
-            expr::PExpr::new_var(var)
+            $crate::expr::PExpr::new_var(var)
         } else {
             panic!("undeclared variable `{}`", stringify!($var))
         }
     );
 
     ($state:tt, $decls:expr, ($op:tt $($args:tt)*) ) => (
-        expr::PExpr::from((
+        $crate::expr::PExpr::new_app(
             $crate::build_trans_expr!(@op $op),
             vec![ $($crate::build_trans_expr!($state, $decls, $args)),* ],
-        ))
+        )?
     );
 
-    ($state:tt, $decls:expr, $cst:expr) => ( expr::PExpr::from($cst) );
-
-    (@op ite) => ( expr::Op::Ite );
-    (@op +) => ( expr::Op::Add );
-    (@op -) => ( expr::Op::Sub );
-    (@op *) => ( expr::Op::Mul );
-    (@op /) => ( expr::Op::Div );
-    (@op %) => ( expr::Op::Mod );
-    (@op >=) => ( expr::Op::Ge );
-    (@op <=) => ( expr::Op::Le );
-    (@op >) => ( expr::Op::Gt );
-    (@op <) => ( expr::Op::Lt );
-    (@op =) => ( expr::Op::Eq );
-    (@op not) => ( expr::Op::Not );
-    (@op and) => ( expr::Op::And );
-    (@op or) => ( expr::Op::Or );
+    ($state:tt, $decls:expr, $cst:expr) => ( $crate::expr::PExpr::from($cst) );
+
+    (@op ite) => ( $crate::expr::Op::Ite );
+    (@op +) => ( $crate::expr::Op::Add );
+    (@op -) => ( $crate::expr::Op::Sub );
+    (@op *) => ( $crate::expr::Op::Mul );
+    (@op /) => ( $crate::expr::Op::Div );
+    (@op %) => ( $crate::expr::Op::Mod );
+    (@op >=) => ( $crate::expr::Op::Ge );
+    (@op <=) => ( $crate::expr::Op::Le );
+    (@op >) => ( $crate::expr::Op::Gt );
+    (@op <) => ( $crate::expr::Op::Lt );
+    (@op =) => ( $crate::expr::Op::Eq );
+    (@op not) => ( $crate::expr::Op::Not );
+    (@op and) => ( $crate::expr::Op::And );
+    (@op or) => ( $crate::expr::Op::Or );
+    (@op &) => ( $crate::expr::Op::BvAnd );
+    (@op |) => ( $crate::expr::Op::BvOr );
+    (@op ^) => ( $crate::expr::Op::BvXor );
+    (@op <<) => ( $crate::expr::Op::Shl );
+    (@op >>) => ( $crate::expr::Op::Shr );
+    (@op select) => ( $crate::expr::Op::Select );
+    (@op store) => ( $crate::expr::Op::Store );
 }
@@ -0,0 +1,208 @@
+//! Tests over transition systems.
+
+crate::prelude!();
+
+use rsmt2::print::{Expr2Smt, Sort2Smt, Sym2Smt};
+
+/// Renders the SMT-LIB 2 encoding of the demo system unrolled at some step.
+fn demo_smt2(step: Unroll) -> String {
+    let sys = parse::trans(crate::TRANS_DEMO).unwrap();
+    let mut buf: Vec<u8> = vec![];
+    for s in 0..=step + 1 {
+        for var in sys.decls().all() {
+            write!(buf, "(declare-fun ").unwrap();
+            var.sym_to_smt2(&mut buf, s).unwrap();
+            write!(buf, " () ").unwrap();
+            var.typ().sort_to_smt2(&mut buf).unwrap();
+            writeln!(buf, ")").unwrap();
+        }
+    }
+    sys.init().expr_to_smt2(&mut buf, 0).unwrap();
+    writeln!(buf).unwrap();
+    sys.trans().expr_to_smt2(&mut buf, step).unwrap();
+    writeln!(buf).unwrap();
+    for (name, po) in sys.po_s() {
+        write!(buf, "{}: ", name).unwrap();
+        po.expr_to_smt2(&mut buf, step).unwrap();
+        writeln!(buf).unwrap();
+    }
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn deterministic_output() {
+    let reference = demo_smt2(3);
+    for _ in 0..10 {
+        assert_eq!(demo_smt2(3), reference)
+    }
+    assert_eq!(
+        reference,
+        "\
+(declare-fun cnt@0 () Int)
+(declare-fun reset@0 () Bool)
+(declare-fun stop@0 () Bool)
+(declare-fun cnt@1 () Int)
+(declare-fun reset@1 () Bool)
+(declare-fun stop@1 () Bool)
+(declare-fun cnt@2 () Int)
+(declare-fun reset@2 () Bool)
+(declare-fun stop@2 () Bool)
+(declare-fun cnt@3 () Int)
+(declare-fun reset@3 () Bool)
+(declare-fun stop@3 () Bool)
+(declare-fun cnt@4 () Int)
+(declare-fun reset@4 () Bool)
+(declare-fun stop@4 () Bool)
+(and (>= cnt@0 0) (=> reset@0 (= cnt@0 0)))
+(= cnt@4 (ite reset@4 0 (ite stop@4 cnt@3 (+ cnt@3 1))))
+cnt is not -7: (not (= cnt@3 -7))
+cnt is positive: (>= cnt@3 0)
+if reset then cnt is 0: (=> reset@3 (= cnt@3 0))
+"
+    );
+}
+
+#[test]
+fn deterministic_cex() {
+    let sys = parse::trans(crate::TRANS_DEMO).unwrap();
+    let mut cex = crate::check::cexs::Cex::new();
+    // Insert in reverse order, iteration must still be by step and then by variable.
+    let vars: Vec<_> = sys.decls().all().collect();
+    for step in (0..3).rev() {
+        for var in vars.iter().rev() {
+            let val: expr::Cst = match var.typ() {
+                Typ::Bool => true.into(),
+                _ => (step as usize).into(),
+            };
+            cex.insert(step, var.clone(), val).unwrap();
+        }
+    }
+    let flat: Vec<String> = cex
+        .trace
+        .iter()
+        .flat_map(|(step, vals)| vals.keys().map(move |var| format!("{}@{}", var, step)))
+        .collect();
+    assert_eq!(
+        flat,
+        vec![
+            "cnt@0", "reset@0", "stop@0", "cnt@1", "reset@1", "stop@1", "cnt@2", "reset@2",
+            "stop@2",
+        ],
+    );
+}
+
+#[test]
+fn macro_matches_parser() {
+    let parsed = parse::trans(
+        r#"
+svars {
+    /// @unit: ms
+    cnt: int,
+    reset: bool,
+}
+init { cnt = 0, ¬reset }
+trans { 'cnt = (if reset { 0 } else { cnt + 1 }) }
+candidates {
+    #[tag(safety)]
+    "cnt is positive": cnt ≥ 0,
+    #[warn]
+    "cnt is not 3": ¬(cnt = 3),
+}
+"#,
+    )
+    .unwrap();
+    let built = crate::build_trans! {
+        decls {
+            /// @unit: ms
+            cnt: int,
+            reset: bool,
+        }
+        init { (= cnt 0) }
+        init { (not reset) }
+        trans { (= (cnt @ 1) (ite (reset @ 0) 0 (+ (cnt @ 0) 1))) }
+        candidates {
+            #[tag(safety)]
+            "cnt is positive" => (>= cnt 0),
+            #[warn]
+            "cnt is not 3" => (not (= cnt 3)),
+        }
+    }
+    .unwrap();
+    assert_eq!(built.to_ml_string(), parsed.to_ml_string());
+    assert_eq!(built.decls(), parsed.decls());
+    assert_eq!(built.tags(), parsed.tags());
+    assert_eq!(built.warnings(), parsed.warnings());
+
+    let legacy = crate::build_trans! {
+        decls { cnt: int }
+        init: (= cnt 0)
+        trans: (= (cnt @ 1) (+ (cnt @ 0) 1))
+        po_s: "cnt is positive" => (>= cnt 0)
+    }
+    .unwrap();
+    assert_eq!(legacy.po_s().len(), 1);
+
+    let res = crate::build_trans! {
+        decls { cnt: int }
+        init { (= cnt 0) }
+        trans { (= (cnt @ 1) (cnt @ 0)) }
+        candidates { "twice" => (>= cnt 0), "twice" => (<= cnt 0) }
+    };
+    let err = match res {
+        Ok(_) => panic!("expected an error on duplicate candidates"),
+        Err(e) => e,
+    };
+    assert_eq!(err.to_string(), "found two candidates named `twice`");
+}
+
+#[test]
+fn conjunct_labels() {
+    let sys = parse::trans(
+        r#"
+svars { cnt: int, inc: bool }
+init { "starts at zero": cnt = 0, inc }
+assumptions { "never negative": cnt ≥ 0 }
+trans {
+    "counts": 'cnt = (if inc { cnt + 1 } else { cnt }),
+    'inc ∨ cnt < 0,
+}
+candidates { "cnt is positive": cnt ≥ 0 }
+"#,
+    )
+    .unwrap();
+    let init: Vec<_> = check::preprocess::conjuncts(sys.init())
+        .iter()
+        .map(|conj| sys.init_label_of(conj).map(String::from))
+        .collect();
+    assert_eq!(
+        init,
+        vec![
+            Some("starts at zero".into()),
+            None,
+            Some("never negative".into())
+        ],
+    );
+    let trans: Vec<_> = check::preprocess::conjuncts(sys.trans())
+        .iter()
+        .filter_map(|conj| sys.trans_label_of(conj).map(String::from))
+        .collect();
+    assert_eq!(trans, vec!["never negative", "never negative", "counts"]);
+
+    // Errors mention the label.
+    let err = parse::trans(
+        r#"
+svars { cnt: int }
+init { "starts at zero": cnt = true }
+trans { 'cnt = cnt }
+candidates { "cnt is positive": cnt ≥ 0 }
+"#,
+    )
+    .err()
+    .unwrap();
+    let err: Vec<_> = err.iter().map(|e| e.to_string()).collect();
+    assert!(
+        err.iter().any(|e| e == "in init conjunct `starts at zero`"),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,169 @@
+//! Timed systems.
+//!
+//! A system is *timed* if it declares a state variable `time: rat`, its *clock*. In the transition
+//! relation of a timed system, `delta` is sugar for the duration of the step, *i.e.*
+//! `'time - time`, unless a state variable called `delta` is declared.
+//!
+//! Counterexamples of timed systems [render](crate::check::cexs::Cex::render) the value of the
+//! clock and the duration of each step. Step durations can be bounded with the [`StepBounds`]
+//! pre-processing pass.
+//!
+//! ```rust
+//! # use mikino_api::{parse, trans::time};
+//! let sys = parse::trans("\
+//! svars { time: rat, on: bool }
+//! init { time = 0.0, on }
+//! trans { delta > 0.0, delta ≤ 5.0, 'on = ¬on }
+//! candidates { \"time is positive\": time ≥ 0.0 }
+//! ").unwrap();
+//! assert_eq!(time::clock(sys.decls()).unwrap().id(), "time");
+//! assert_eq!(
+//!     sys.trans().to_string(),
+//!     "(and (> (- time@1 time@0) (/ 0 1)) (<= (- time@1 time@0) (/ 5 1)) (= on@1 (not on@0)))",
+//! );
+//!
+//! # use mikino_api::{check::cexs::Cex, expr::{display::Formatters, Cst}, prelude::Rat};
+//! let mut cex = Cex::new();
+//! for (step, time) in vec![0, 3].into_iter().enumerate() {
+//!     for var in sys.decls().all() {
+//!         let val = if var.id() == "time" {
+//!             Cst::rat(Rat::from_integer(time.into()))
+//!         } else {
+//!             Cst::bool(step == 0)
+//!         };
+//!         cex.insert(step, var, val).unwrap();
+//!     }
+//! }
+//! assert_eq!(
+//!     cex.render(sys.decls(), &Formatters::new()),
+//!     "\
+//! step 0 (time = (/ 0 1))
+//!       on = true
+//!     time = (/ 0 1)
+//! step 1 (time = (/ 3 1), +(/ 3 1))
+//!       on = false
+//!     time = (/ 3 1)
+//! ",
+//! );
+//! ```
+
+crate::prelude!();
+
+use check::{preprocess::Pass, trace::Trace};
+use expr::{Cst, Op, PExpr, SExpr, Var};
+use trans::{Decls, Sys};
+
+/// Identifier of the clock variable.
+pub const CLOCK: &str = "time";
+/// Identifier of the step duration sugar.
+pub const DELTA: &str = "delta";
+
+/// Clock variable, if the declarations have one.
+pub fn clock(decls: &Decls) -> Option<Var> {
+    decls.get_var(CLOCK).filter(|var| var.typ() == Typ::Rat)
+}
+
+/// Duration of a step, `'time - time`, if the declarations have a clock.
+pub fn delta(decls: &Decls) -> Option<SExpr> {
+    let clock = clock(decls)?;
+    let (next, curr) = (
+        decls.get_next_var(clock.id())?,
+        decls.get_curr_var(clock.id())?,
+    );
+    SExpr::new_op(Op::Sub, vec![PExpr::new_var(next), PExpr::new_var(curr)]).ok()
+}
+
+/// Value of the clock at some step of a trace.
+pub fn time(trace: &Trace, step: Unroll) -> Option<&Rat> {
+    trace.get_val(step, CLOCK)?.as_rat().ok()
+}
+
+/// Duration of the step leading to `step` in a trace, `None` for step `0`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::trace::Trace, expr::{Cst, Typ, Var}, prelude::Rat, trans::time};
+/// let mut trace = Trace::new();
+/// for (step, (num, den)) in vec![(0, 1), (3, 2), (4, 1)].into_iter().enumerate() {
+///     let val = Cst::rat(Rat::new(num.into(), den.into()));
+///     trace.entry(step).or_default().insert(Var::new("time", Typ::Rat), val);
+/// }
+/// assert_eq!(time::elapsed(&trace, 0), None);
+/// assert_eq!(time::elapsed(&trace, 1), Some(Rat::new(3.into(), 2.into())));
+/// assert_eq!(time::elapsed(&trace, 2), Some(Rat::new(5.into(), 2.into())));
+/// ```
+pub fn elapsed(trace: &Trace, step: Unroll) -> Option<Rat> {
+    let prev = time(trace, step.checked_sub(1)?)?;
+    Some(time(trace, step)? - prev)
+}
+
+/// Bounds the duration of the steps of a timed system, fails on untimed systems.
+///
+/// Conjoins `delta ≥ min` and `delta ≤ max` to the transition relation. Note that this pass
+/// changes the semantics of the system: it only keeps the behaviors where steps respect the
+/// bounds.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mikino_api::{check::preprocess::Pipeline, parse, prelude::Rat, trans::time};
+/// let sys = parse::trans("\
+/// svars { time: rat }
+/// init { time = 0.0 }
+/// trans { 'time ≥ time }
+/// candidates { \"time is positive\": time ≥ 0.0 }
+/// ").unwrap();
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(time::StepBounds {
+///     min: None,
+///     max: Some(Rat::new(1.into(), 2.into())),
+/// });
+/// let sys = pipeline.run(&sys).unwrap();
+/// assert_eq!(
+///     sys.trans().to_string(),
+///     "(and (>= time@1 time@0) (<= (- time@1 time@0) (/ 1 2)))",
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepBounds {
+    /// Minimal step duration, if any.
+    pub min: Option<Rat>,
+    /// Maximal step duration, if any.
+    pub max: Option<Rat>,
+}
+impl Pass for StepBounds {
+    fn name(&self) -> &str {
+        "step-bounds"
+    }
+    fn run(&self, sys: &Sys) -> Res<Sys> {
+        let delta = match delta(sys.decls()) {
+            Some(delta) => delta,
+            None => bail!(
+                "cannot bound step durations, system has no `{}: {}` clock",
+                CLOCK,
+                Typ::Rat,
+            ),
+        };
+        let mut conjs = match sys.trans() {
+            PExpr::App {
+                op: Op::And, args, ..
+            } => args.clone(),
+            trans => vec![trans.clone()],
+        };
+        for (op, bound) in [(Op::Ge, &self.min), (Op::Le, &self.max)] {
+            if let Some(bound) = bound {
+                conjs.push(SExpr::new_op(
+                    op,
+                    vec![delta.clone(), Cst::rat(bound.clone()).into()],
+                )?)
+            }
+        }
+        Ok(Sys::new(
+            sys.decls().clone(),
+            sys.init().clone(),
+            SExpr::new_op(Op::And, conjs)?,
+            sys.po_s().clone(),
+        ))
+    }
+}